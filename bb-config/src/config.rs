@@ -6,6 +6,9 @@ use serde::{Deserialize, Serialize};
 use serde_with::{VecSkipError, serde_as};
 use url::Url;
 
+/// Prefix of variables which can be referenced in [`OsImage::headers`].
+pub const HEADER_VAR_PREFIX: &str = "BB_IMAGER_";
+
 /// [BeagleBoard.org] distros.json abstraction.
 ///
 /// # Merging Behaviour
@@ -72,10 +75,63 @@ pub struct Device {
     #[serde(with = "tuple_vec_map")]
     /// Board Specification. With order preserved
     pub specification: Vec<(String, String)>,
+    /// Serial port settings for flashers talking to the device over serial port. Flasher defaults
+    /// are used for missing values.
+    pub serial: Option<SerialConfig>,
+    /// Time limits for each stage of flashing. Flasher defaults are used for missing values.
+    pub timeouts: Option<Timeouts>,
+    /// USB IDs of the device. Used to filter destinations of flashers using USB.
+    #[serde(default)]
+    pub usb_ids: Vec<UsbId>,
     /// OSHW details for the device.
     pub oshw: Option<String>
 }
 
+/// USB Vendor and Product ID
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UsbId {
+    /// Vendor ID
+    pub vid: u16,
+    /// Product ID
+    pub pid: u16,
+}
+
+/// Serial port settings
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SerialConfig {
+    /// Baud rate
+    pub baud_rate: Option<u32>,
+    /// Flow control
+    pub flow_control: Option<FlowControl>,
+    /// Read and write timeout in milliseconds
+    pub timeout_ms: Option<u64>,
+}
+
+/// Time limits for each stage of flashing, in seconds.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Downloading and extracting the image
+    pub download_secs: Option<u64>,
+    /// Writing to the destination
+    pub flash_secs: Option<u64>,
+    /// Verifying the written image
+    pub verify_secs: Option<u64>,
+    /// Post install customization
+    pub customize_secs: Option<u64>,
+}
+
+/// Serial port flow control
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FlowControl {
+    /// No flow control
+    None,
+    /// XON/XOFF flow control
+    Software,
+    /// RTS/CTS flow control
+    Hardware,
+}
+
 /// Types of customization Initialization formats
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
 #[non_exhaustive]
@@ -108,6 +164,9 @@ pub enum OsListItem {
     ///
     /// [BeagleBoard.org]: https://www.beagleboard.org/
     RemoteSubList(OsRemoteSubList),
+    /// Multiple images which need to be flashed in a fixed order, possibly using different
+    /// flashers.
+    Bundle(OsBundle),
 }
 
 /// [`OsListItem`] which itself can contain a list of [`OsListItem`].
@@ -146,6 +205,35 @@ pub struct OsRemoteSubList {
     pub subitems_url: Url,
 }
 
+/// Images for a product which need to be flashed in a fixed order. Eg: SD Card image along with
+/// firmware for on-board microcontrollers.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct OsBundle {
+    /// Bundle name
+    pub name: String,
+    /// Bundle description
+    pub description: String,
+    /// Bundle icon URL
+    pub icon: Url,
+    /// Devices the bundle can be used with
+    pub devices: HashSet<String>,
+    /// Steps in flashing order
+    pub steps: Vec<BundleStep>,
+}
+
+/// Single step of [`OsBundle`]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BundleStep {
+    /// Flasher to use for the image
+    #[serde(default)]
+    pub flasher: Flasher,
+    /// Instructions to show before starting the step. Eg: Connecting a different port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// Image to flash
+    pub image: OsImage,
+}
+
 /// A singular Os Image for board(s)
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct OsImage {
@@ -176,8 +264,55 @@ pub struct OsImage {
     pub init_format: InitFormat,
     /// Bmap file for the image
     pub bmap: Option<Url>,
+    /// Metalink file listing mirrors for the image. [`OsImage::url`] is used as fallback if none of
+    /// the mirrors can be used.
+    #[serde(default)]
+    pub metalink: Option<Url>,
     /// Special Instructions for flashing board.
     pub info_text: Option<String>,
+    /// Extra HTTP headers to use when downloading the image and bmap. Useful for private image
+    /// repositories. Headers are only sent to the host of [`OsImage::url`].
+    ///
+    /// Values can reference variables using `${NAME}` syntax to avoid storing secrets in the
+    /// config. `NAME` must start with [`HEADER_VAR_PREFIX`]. See [`OsImage::resolved_headers`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(with = "tuple_vec_map")]
+    pub headers: Vec<(String, String)>,
+    /// Binary deltas to reconstruct the image from older images, instead of downloading the
+    /// complete image.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deltas: Vec<ImageDelta>,
+    /// Minimum imager version required to flash the image. Older imagers should hide the image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_imager_version: Option<semver::Version>,
+    /// Image is deprecated and should not be used for new installs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+    /// Date after which the image no longer receives updates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eol_date: Option<chrono::NaiveDate>,
+    /// Changes in this release, in markdown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<String>,
+    /// Url to the complete release notes of the image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_notes_url: Option<Url>,
+}
+
+/// Binary delta (generated using `zstd --patch-from`) against an older [`OsImage`].
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImageDelta {
+    /// [`OsImage::image_download_sha256`] of the older image
+    #[serde(with = "const_hex")]
+    pub base_sha256: [u8; 32],
+    /// Delta download URL. The delta is generated against the extracted older image.
+    pub url: Url,
+    /// Delta sha256
+    #[serde(with = "const_hex")]
+    pub sha256: [u8; 32],
+    /// Sha256 of the extracted image produced by applying the delta
+    #[serde(with = "const_hex")]
+    pub extract_sha256: [u8; 32],
 }
 
 /// Types of flashers Os Image(s) support
@@ -195,6 +330,54 @@ pub enum Flasher {
     Pb2Mspm0,
 }
 
+impl std::fmt::Display for Flasher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Flasher::SdCard => write!(f, "SD Card"),
+            Flasher::BeagleConnectFreedom => write!(f, "BeagleConnect Freedom CC1352P7"),
+            Flasher::Msp430Usb => write!(f, "BeagleConnect Freedom MSP430"),
+            Flasher::Pb2Mspm0 => write!(f, "PocketBeagle 2 MSPM0"),
+        }
+    }
+}
+
+impl Config {
+    /// Newest stable image for a board with `tags`, along with its position in
+    /// [`Config::os_list`]. Only top level images are considered, since sub lists hold testing and
+    /// older images. Deprecated, end of life and unsupported images are skipped.
+    pub fn recommended_image(
+        &self,
+        tags: &HashSet<String>,
+        imager: &semver::Version,
+        today: chrono::NaiveDate,
+    ) -> Option<(usize, &OsImage)> {
+        self.os_list
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| match x {
+                OsListItem::Image(img) => Some((i, img)),
+                _ => None,
+            })
+            .filter(|(_, x)| {
+                !tags.is_disjoint(&x.devices)
+                    && x.supports_imager(imager)
+                    && !x.deprecated
+                    && !x.is_eol(today)
+            })
+            // Prefer the first listed image on ties
+            .min_by_key(|(i, x)| (std::cmp::Reverse(x.release_date), *i))
+    }
+
+    /// Remove [`OsImage::headers`] referencing variables. Should be used for catalogs not trusted
+    /// by the user (i.e. anything other than local catalogs or catalogs added by the user), so
+    /// that they cannot send secrets to arbitrary hosts.
+    pub fn strip_header_variables(&mut self) {
+        for item in &mut self.os_list {
+            item.strip_header_variables();
+        }
+    }
+}
+
 impl Extend<Self> for Config {
     fn extend<T: IntoIterator<Item = Self>>(&mut self, iter: T) {
         for config in iter.into_iter() {
@@ -238,11 +421,28 @@ impl Extend<Self> for Config {
 }
 
 impl OsListItem {
+    /// Same as [`Config::strip_header_variables`], for the item and its children.
+    pub fn strip_header_variables(&mut self) {
+        match self {
+            OsListItem::Image(img) => img.strip_header_variables(),
+            OsListItem::SubList(item) => item
+                .subitems
+                .iter_mut()
+                .for_each(Self::strip_header_variables),
+            OsListItem::RemoteSubList(_) => {}
+            OsListItem::Bundle(item) => item
+                .steps
+                .iter_mut()
+                .for_each(|x| x.image.strip_header_variables()),
+        }
+    }
+
     pub fn icon(&self) -> &url::Url {
         match self {
             OsListItem::Image(img) => &img.icon,
             OsListItem::SubList(img) => &img.icon,
             OsListItem::RemoteSubList(img) => &img.icon,
+            OsListItem::Bundle(img) => &img.icon,
         }
     }
 
@@ -251,6 +451,7 @@ impl OsListItem {
             OsListItem::Image(img) => &img.name,
             OsListItem::SubList(img) => &img.name,
             OsListItem::RemoteSubList(img) => &img.name,
+            OsListItem::Bundle(img) => &img.name,
         }
     }
 
@@ -260,10 +461,80 @@ impl OsListItem {
             OsListItem::Image(item) => !tags.is_disjoint(&item.devices),
             OsListItem::SubList(item) => item.subitems.iter().any(|x| x.has_board_image(tags)),
             OsListItem::RemoteSubList(item) => !tags.is_disjoint(&item.devices),
+            OsListItem::Bundle(item) => !tags.is_disjoint(&item.devices),
         }
     }
 }
 
+impl OsImage {
+    /// [`OsImage::headers`] with variables expanded using `lookup`. Headers referencing unset
+    /// variables, or variables without [`HEADER_VAR_PREFIX`], are skipped.
+    ///
+    /// [`secrets::lookup`](crate::secrets::lookup) looks up variables in the environment and the
+    /// OS keyring.
+    pub fn resolved_headers<F>(&self, lookup: F) -> Vec<(String, String)>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.headers
+            .iter()
+            .filter_map(|(k, v)| Some((k.clone(), expand_vars(v, &lookup)?)))
+            .collect()
+    }
+
+    fn strip_header_variables(&mut self) {
+        self.headers.retain(|(_, v)| !v.contains("${"));
+    }
+
+    /// Check if the image can be handled by imager `version`.
+    pub fn supports_imager(&self, version: &semver::Version) -> bool {
+        self.requires_imager_version
+            .as_ref()
+            .is_none_or(|x| version >= x)
+    }
+
+    /// Check if the image has reached end of life on `today`.
+    pub fn is_eol(&self, today: chrono::NaiveDate) -> bool {
+        self.eol_date.is_some_and(|x| x <= today)
+    }
+
+    /// Warning to show to users before flashing the image, if any.
+    pub fn warning(&self, today: chrono::NaiveDate) -> Option<String> {
+        match (self.is_eol(today), self.deprecated) {
+            (true, _) => Some(format!(
+                "This image reached end of life on {} and no longer receives updates.",
+                self.eol_date.expect("eol_date is set")
+            )),
+            (false, true) => {
+                Some("This image is deprecated. Consider using a newer image.".to_string())
+            }
+            (false, false) => None,
+        }
+    }
+}
+
+/// Expand `${NAME}` with the value of variable `NAME` returned by `lookup`.
+fn expand_vars(val: &str, lookup: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let mut res = String::with_capacity(val.len());
+    let mut rest = val;
+
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..].find('}')? + start;
+        let name = &rest[start + 2..end];
+        if !name.starts_with(HEADER_VAR_PREFIX) {
+            return None;
+        }
+        let var = lookup(name)?;
+
+        res.push_str(&rest[..start]);
+        res.push_str(&var);
+        rest = &rest[end + 1..];
+    }
+
+    res.push_str(rest);
+    Some(res)
+}
+
 impl OsRemoteSubList {
     /// Construct [OsSubList] once subitems have been downloaded.
     pub fn resolve(self, subitems: Vec<OsListItem>) -> OsSubList {
@@ -18,6 +18,13 @@
 //! ```
 
 pub mod config;
+pub mod secrets;
+#[cfg(feature = "sideload")]
+pub mod sideload;
+#[cfg(feature = "signature")]
+pub mod signature;
+#[cfg(feature = "validate")]
+pub mod validate;
 
 /// URL for the BeagleBoard.org `distros.json` file
 pub const DISTROS_URL: &str =
@@ -27,9 +34,488 @@ pub use config::Config;
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "sideload")]
+    #[test]
+    fn sideload() {
+        let dir = std::env::temp_dir().join(format!("bb-config-sideload-{}", std::process::id()));
+        let src = dir.join("my-cape");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("icon.png"), b"icon").unwrap();
+        std::fs::write(src.join("img.img.xz"), b"image").unwrap();
+
+        let catalog = r#"{
+            "imager": {
+                "devices": [{
+                    "name": "My Cape",
+                    "tags": ["my-cape"],
+                    "description": "Cape",
+                    "icon": "icon.png",
+                    "flasher": "SdCard"
+                }]
+            },
+            "os_list": [{
+                "name": "Cape Image",
+                "description": "Image",
+                "icon": "https://example.com/icon.png",
+                "url": "img.img.xz",
+                "image_download_sha256": "f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148",
+                "extract_size": 100,
+                "release_date": "2025-01-01",
+                "devices": ["my-cape"]
+            }]
+        }"#;
+        std::fs::write(src.join(super::sideload::MANIFEST), catalog).unwrap();
+
+        let res = super::sideload::read(&src).unwrap();
+        assert_eq!(res.name, "my-cape");
+        assert_eq!(res.images.len(), 1);
+        assert_eq!(res.files.len(), 1);
+        assert_eq!(res.files[0].0.scheme(), "file");
+        assert_eq!(
+            res.config.imager.devices[0].icon.as_ref(),
+            Some(&res.files[0].0)
+        );
+
+        let installed = dir.join("installed");
+        res.install(&installed).unwrap();
+        let all = super::sideload::installed(&installed);
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].1.as_ref().unwrap(), &res.config);
+
+        std::fs::write(
+            src.join(super::sideload::MANIFEST),
+            catalog.replace("icon.png", "../icon.png"),
+        )
+        .unwrap();
+        assert!(matches!(
+            super::sideload::read(&src),
+            Err(super::sideload::Error::OutsideFolder(_))
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "validate")]
+    #[test]
+    fn validate_config() {
+        use super::validate::{Severity, validate};
+
+        let data = std::str::from_utf8(include_bytes!("../../config.json")).unwrap();
+        assert!(validate(data).iter().all(|x| x.severity != Severity::Error));
+
+        let res = validate("{\n  \"os_list\": [\n");
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].severity, Severity::Error);
+        assert!(res[0].position.is_some());
+
+        let data = r#"{
+  "imager": {
+    "devices": [
+      {
+        "name": "Board",
+        "tags": ["board"],
+        "description": "Board",
+        "flasher": "SdCard"
+      },
+      {
+        "name": "Board",
+        "tags": ["board"],
+        "description": "Board",
+        "icon": "http://example.com/icon.png",
+        "flasher": "Floppy"
+      }
+    ]
+  },
+  "os_list": []
+}"#;
+        let res = validate(data);
+        assert_eq!(res.len(), 2);
+
+        assert_eq!(res[0].severity, Severity::Warning);
+        assert_eq!(res[0].path, "imager.devices[0]");
+        assert_eq!(res[0].position, Some((4, 7)));
+
+        assert_eq!(res[1].severity, Severity::Error);
+        assert_eq!(res[1].path, "imager.devices[1].flasher");
+        assert_eq!(res[1].position, Some((15, 20)));
+        assert_eq!(
+            res[1].to_string().split_once(": error: ").unwrap().0,
+            "15:20"
+        );
+    }
+
+    #[cfg(feature = "signature")]
+    #[test]
+    fn catalog_signature() {
+        use base64::Engine;
+        use ring::signature::{Ed25519KeyPair, KeyPair};
+
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let key: super::signature::PublicKey =
+            engine.encode(pair.public_key().as_ref()).parse().unwrap();
+
+        let data = include_bytes!("../../config.json");
+        let sig = engine.encode(pair.sign(data).as_ref());
+
+        super::signature::verify(data, format!("{sig}\n").as_bytes(), &[key]).unwrap();
+        assert_eq!(
+            super::signature::verify(&data[1..], sig.as_bytes(), &[key]),
+            Err(super::signature::Error::Mismatch)
+        );
+        assert_eq!(
+            super::signature::verify(data, b"not base64!", &[key]),
+            Err(super::signature::Error::InvalidEncoding)
+        );
+        assert_eq!(
+            super::signature::verify(data, sig.as_bytes(), &[]),
+            Err(super::signature::Error::Mismatch)
+        );
+
+        let url = url::Url::parse("https://example.com/os_list.json?x=1").unwrap();
+        assert_eq!(
+            super::signature::signature_url(&url).as_str(),
+            "https://example.com/os_list.json.sig?x=1"
+        );
+    }
+
     #[test]
     fn basic() {
         let data = include_bytes!("../../config.json");
         serde_json::from_slice::<super::Config>(data).unwrap();
     }
+
+    #[test]
+    fn image_headers() {
+        let data = r#"{
+            "name": "Private Image",
+            "description": "Image behind auth",
+            "icon": "https://example.com/icon.png",
+            "url": "https://example.com/img.img.xz",
+            "image_download_sha256": "f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148",
+            "extract_size": 100,
+            "release_date": "2025-01-01",
+            "devices": [],
+            "headers": {
+                "Authorization": "Bearer ${BB_IMAGER_TOKEN}",
+                "X-Missing": "${BB_IMAGER_MISSING}",
+                "X-Unprefixed": "${HOME}",
+                "X-Static": "abc"
+            }
+        }"#;
+
+        let img: super::config::OsImage = serde_json::from_str(data).unwrap();
+        assert_eq!(img.headers.len(), 4);
+        assert!(img.deltas.is_empty());
+        assert!(img.requires_imager_version.is_none());
+        assert!(!img.deprecated);
+
+        let lookup = |x: &str| match x {
+            "BB_IMAGER_TOKEN" => Some("secret".to_string()),
+            "HOME" => Some("/root".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            img.resolved_headers(lookup),
+            [
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+                ("X-Static".to_string(), "abc".to_string())
+            ]
+        );
+
+        let mut item = super::config::OsListItem::Image(img);
+        item.strip_header_variables();
+        let super::config::OsListItem::Image(img) = item else {
+            unreachable!()
+        };
+        assert_eq!(
+            img.resolved_headers(lookup),
+            [("X-Static".to_string(), "abc".to_string())]
+        );
+    }
+
+    #[test]
+    fn image_deltas() {
+        let data = r#"{
+            "name": "Weekly Image",
+            "description": "Weekly image",
+            "icon": "https://example.com/icon.png",
+            "url": "https://example.com/img.img.xz",
+            "image_download_sha256": "f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148",
+            "extract_size": 100,
+            "release_date": "2025-01-08",
+            "devices": [],
+            "deltas": [
+                {
+                    "base_sha256": "0000000000000000000000000000000000000000000000000000000000000001",
+                    "url": "https://example.com/img.img.zst-patch",
+                    "sha256": "0000000000000000000000000000000000000000000000000000000000000002",
+                    "extract_sha256": "0000000000000000000000000000000000000000000000000000000000000003"
+                }
+            ]
+        }"#;
+
+        let img: super::config::OsImage = serde_json::from_str(data).unwrap();
+        assert_eq!(img.deltas.len(), 1);
+        assert_eq!(img.deltas[0].base_sha256[31], 1);
+        assert_eq!(img.deltas[0].extract_sha256[31], 3);
+
+        let json = serde_json::to_string(&img).unwrap();
+        assert_eq!(
+            serde_json::from_str::<super::config::OsImage>(&json).unwrap(),
+            img
+        );
+    }
+
+    #[test]
+    fn image_lifecycle() {
+        let data = r#"{
+            "name": "Old Image",
+            "description": "Old image",
+            "icon": "https://example.com/icon.png",
+            "url": "https://example.com/img.img.xz",
+            "image_download_sha256": "f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148",
+            "extract_size": 100,
+            "release_date": "2025-01-01",
+            "devices": [],
+            "requires_imager_version": "2.1.0",
+            "deprecated": true,
+            "eol_date": "2026-01-01"
+        }"#;
+
+        let img: super::config::OsImage = serde_json::from_str(data).unwrap();
+        assert!(!img.supports_imager(&semver::Version::new(2, 0, 5)));
+        assert!(img.supports_imager(&semver::Version::new(2, 1, 0)));
+
+        let before = chrono::NaiveDate::from_ymd_opt(2025, 12, 31).unwrap();
+        let after = chrono::NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        assert!(!img.is_eol(before));
+        assert!(img.is_eol(after));
+        assert!(img.warning(before).unwrap().contains("deprecated"));
+        assert!(img.warning(after).unwrap().contains("end of life"));
+
+        let json = serde_json::to_string(&img).unwrap();
+        assert_eq!(
+            serde_json::from_str::<super::config::OsImage>(&json).unwrap(),
+            img
+        );
+    }
+
+    #[test]
+    fn image_release_notes() {
+        let data = r#"{
+            "name": "New Image",
+            "description": "New image",
+            "icon": "https://example.com/icon.png",
+            "url": "https://example.com/img.img.xz",
+            "image_download_sha256": "f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148",
+            "extract_size": 100,
+            "release_date": "2025-01-01",
+            "devices": [],
+            "release_notes": "- Kernel 6.12\n- Fixed WiFi",
+            "release_notes_url": "https://example.com/changelog.html"
+        }"#;
+
+        let img: super::config::OsImage = serde_json::from_str(data).unwrap();
+        assert_eq!(
+            img.release_notes.as_deref(),
+            Some("- Kernel 6.12\n- Fixed WiFi")
+        );
+        assert_eq!(
+            img.release_notes_url.as_ref().map(|x| x.as_str()),
+            Some("https://example.com/changelog.html")
+        );
+
+        let json = serde_json::to_string(&img).unwrap();
+        assert_eq!(
+            serde_json::from_str::<super::config::OsImage>(&json).unwrap(),
+            img
+        );
+    }
+
+    #[test]
+    fn device_timeouts() {
+        let data = r#"{
+            "name": "BeagleConnect Freedom",
+            "tags": ["beagleconnect-freedom"],
+            "icon": null,
+            "description": "Wireless board",
+            "flasher": "BeagleConnectFreedom",
+            "documentation": null,
+            "instructions": null,
+            "timeouts": {
+                "flash_secs": 600,
+                "verify_secs": 120
+            },
+            "oshw": null
+        }"#;
+
+        let dev: super::config::Device = serde_json::from_str(data).unwrap();
+        assert_eq!(
+            dev.timeouts,
+            Some(super::config::Timeouts {
+                download_secs: None,
+                flash_secs: Some(600),
+                verify_secs: Some(120),
+                customize_secs: None,
+            })
+        );
+    }
+
+    #[test]
+    fn device_serial() {
+        let data = r#"{
+            "name": "BeagleConnect Freedom",
+            "tags": ["beagleconnect-freedom"],
+            "icon": null,
+            "description": "Wireless board",
+            "flasher": "BeagleConnectFreedom",
+            "documentation": null,
+            "instructions": null,
+            "serial": {
+                "baud_rate": 57600,
+                "flow_control": "hardware"
+            },
+            "usb_ids": [{ "vid": 8263, "pid": 512 }],
+            "oshw": null
+        }"#;
+
+        let dev: super::config::Device = serde_json::from_str(data).unwrap();
+        let serial = dev.serial.unwrap();
+        assert_eq!(serial.baud_rate, Some(57600));
+        assert_eq!(
+            serial.flow_control,
+            Some(super::config::FlowControl::Hardware)
+        );
+        assert!(serial.timeout_ms.is_none());
+        assert_eq!(
+            dev.usb_ids,
+            [super::config::UsbId {
+                vid: 0x2047,
+                pid: 0x0200
+            }]
+        );
+    }
+
+    #[test]
+    fn bundle() {
+        let data = r#"[{
+            "name": "BeagleConnect Freedom Bundle",
+            "description": "Complete firmware",
+            "icon": "https://example.com/icon.png",
+            "devices": ["beagleconnect-freedom"],
+            "steps": [
+                {
+                    "flasher": "BeagleConnectFreedom",
+                    "image": {
+                        "name": "CC1352P7 Firmware",
+                        "description": "Main firmware",
+                        "icon": "https://example.com/icon.png",
+                        "url": "https://example.com/cc1352.bin.xz",
+                        "image_download_sha256": "f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148",
+                        "extract_size": 100,
+                        "release_date": "2025-01-01",
+                        "devices": ["beagleconnect-freedom"]
+                    }
+                },
+                {
+                    "flasher": "Msp430Usb",
+                    "instructions": "Connect the USB port",
+                    "image": {
+                        "name": "MSP430 Firmware",
+                        "description": "USB bridge firmware",
+                        "icon": "https://example.com/icon.png",
+                        "url": "https://example.com/msp430.hex",
+                        "image_download_sha256": "f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148",
+                        "extract_size": 100,
+                        "release_date": "2025-01-01",
+                        "devices": ["beagleconnect-freedom"]
+                    }
+                }
+            ]
+        }]"#;
+
+        let items: Vec<super::config::OsListItem> = serde_json::from_str(data).unwrap();
+        let super::config::OsListItem::Bundle(bundle) = &items[0] else {
+            panic!("Expected bundle");
+        };
+        assert_eq!(bundle.steps.len(), 2);
+        assert_eq!(bundle.steps[1].flasher, super::config::Flasher::Msp430Usb);
+        assert!(
+            items[0].has_board_image(&["beagleconnect-freedom".to_string()].into_iter().collect())
+        );
+
+        let json = serde_json::to_string(&items).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<super::config::OsListItem>>(&json).unwrap(),
+            items
+        );
+    }
+
+    #[test]
+    fn recommended_image() {
+        fn image(name: &str, date: &str, device: &str, extra: &str) -> String {
+            format!(
+                r#"{{
+                    "name": "{name}",
+                    "description": "{name}",
+                    "icon": "https://example.com/icon.png",
+                    "url": "https://example.com/img.img.xz",
+                    "image_download_sha256": "f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148",
+                    "extract_size": 100,
+                    "release_date": "{date}",
+                    "devices": ["{device}"]{extra}
+                }}"#
+            )
+        }
+
+        let data = format!(
+            r#"{{
+                "imager": {{}},
+                "os_list": [
+                    {},
+                    {},
+                    {},
+                    {},
+                    {},
+                    {{
+                        "name": "Testing",
+                        "description": "Testing images",
+                        "icon": "https://example.com/icon.png",
+                        "subitems": [{}]
+                    }}
+                ]
+            }}"#,
+            image("Old", "2025-01-01", "beagley-ai", ""),
+            image("Stable", "2025-06-01", "beagley-ai", ""),
+            image(
+                "Deprecated",
+                "2025-07-01",
+                "beagley-ai",
+                r#", "deprecated": true"#
+            ),
+            image(
+                "Future",
+                "2025-08-01",
+                "beagley-ai",
+                r#", "requires_imager_version": "99.0.0""#
+            ),
+            image("Other", "2025-09-01", "beaglebone-black", ""),
+            image("Nightly", "2025-10-01", "beagley-ai", ""),
+        );
+        let config: super::Config = serde_json::from_str(&data).unwrap();
+
+        let tags = ["beagley-ai".to_string()].into_iter().collect();
+        let version = semver::Version::new(3, 0, 0);
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 12, 1).unwrap();
+        let (idx, img) = config.recommended_image(&tags, &version, today).unwrap();
+        assert_eq!(idx, 1);
+        assert_eq!(img.name, "Stable");
+
+        let tags = ["pocketbeagle2".to_string()].into_iter().collect();
+        assert!(config.recommended_image(&tags, &version, today).is_none());
+    }
 }
@@ -0,0 +1,39 @@
+//! Look up secrets referenced in [`OsImage::headers`](crate::config::OsImage::headers).
+//!
+//! Variables are looked up in the environment first. With the `keyring` feature, the OS keyring
+//! (Keychain, Credential Manager or Secret Service) is used as fallback. Secrets are stored in the
+//! keyring with [`KEYRING_SERVICE`] as service and the variable name as user.
+//!
+//! ```text
+//! # Linux
+//! secret-tool store --label "BeagleBoard Imager" service bb-imager username BB_IMAGER_TOKEN
+//! ```
+
+/// Keyring service used for secrets.
+pub const KEYRING_SERVICE: &str = "bb-imager";
+
+/// Value of variable `name` from the environment or the OS keyring. Only variables starting with
+/// [`HEADER_VAR_PREFIX`](crate::config::HEADER_VAR_PREFIX) are looked up.
+pub fn lookup(name: &str) -> Option<String> {
+    if !name.starts_with(crate::config::HEADER_VAR_PREFIX) {
+        return None;
+    }
+
+    if let Ok(x) = std::env::var(name) {
+        return Some(x);
+    }
+
+    keyring(name)
+}
+
+#[cfg(feature = "keyring")]
+fn keyring(name: &str) -> Option<String> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .and_then(|x| x.get_password())
+        .ok()
+}
+
+#[cfg(not(feature = "keyring"))]
+const fn keyring(_: &str) -> Option<String> {
+    None
+}
@@ -0,0 +1,251 @@
+//! Import boards and images shipped as a local folder, for offline use.
+//!
+//! The folder contains a catalog named [`MANIFEST`], along with the files it references. Fields
+//! referencing files (`icon`, `url`, `bmap` and `metalink`) can use paths relative to the folder
+//! instead of URLs.
+//!
+//! ```text
+//! my-cape/
+//! ├── board.json
+//! ├── icon.webp
+//! └── my-cape-image.img.xz
+//! ```
+//!
+//! Imported catalogs are stored in a directory chosen by the application, and can be loaded again
+//! using [`installed`].
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    Config,
+    config::OsListItem,
+    validate::{Diagnostic, Severity},
+};
+
+/// Catalog file in the folder.
+pub const MANIFEST: &str = "board.json";
+
+/// Fields which can reference files in the folder.
+const FILE_FIELDS: &[&str] = &["icon", "url", "bmap", "metalink"];
+
+/// Errors while importing a folder.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Referenced file does not exist in the folder.
+    MissingFile(PathBuf),
+    /// Referenced path is absolute or points outside the folder.
+    OutsideFolder(String),
+    /// Referenced file has no extension, which is required to cache it.
+    NoExtension(PathBuf),
+    /// Catalog has errors. Contains all diagnostics, including warnings.
+    Invalid(Vec<Diagnostic>),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::MissingFile(p) => write!(f, "Referenced file {} does not exist", p.display()),
+            Self::OutsideFolder(p) => write!(f, "Referenced path {p} is outside the folder"),
+            Self::NoExtension(p) => write!(f, "Referenced file {} has no extension", p.display()),
+            Self::Invalid(d) => {
+                write!(f, "Invalid {MANIFEST}")?;
+                for x in d.iter().filter(|x| x.severity == Severity::Error) {
+                    write!(f, "\n{x}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Folder read using [`read`].
+#[derive(Debug, Clone)]
+pub struct Sideload {
+    /// Name of the folder. Used as the name of the installed catalog.
+    pub name: String,
+    /// Catalog with file references replaced by `file://` URLs.
+    pub config: Config,
+    /// Os image files along with their [`image_download_sha256`](crate::config::OsImage).
+    pub images: Vec<(PathBuf, [u8; 32])>,
+    /// Other referenced files (icons, bmaps, etc) by their `file://` URL.
+    pub files: Vec<(Url, PathBuf)>,
+    /// Warnings found while validating the catalog.
+    pub warnings: Vec<Diagnostic>,
+    catalog: String,
+}
+
+/// Read and validate a folder. Nothing is copied.
+pub fn read(dir: &Path) -> Result<Sideload, Error> {
+    let dir = dir.canonicalize()?;
+    let name = dir
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_else(|| "board".to_string());
+
+    let data = std::fs::read_to_string(dir.join(MANIFEST))?;
+    let mut root: Value = match serde_json::from_str(&data) {
+        Ok(x) => x,
+        Err(_) => return Err(Error::Invalid(crate::validate::validate(&data))),
+    };
+
+    let mut local = HashMap::new();
+    resolve(&dir, &mut root, &mut local)?;
+
+    // Positions refer to the rewritten catalog, which would only confuse users.
+    let catalog = serde_json::to_string_pretty(&root).expect("Failed to serialize catalog");
+    let mut diagnostics = crate::validate::validate(&catalog);
+    diagnostics.iter_mut().for_each(|x| x.position = None);
+    if diagnostics.iter().any(|x| x.severity == Severity::Error) {
+        return Err(Error::Invalid(diagnostics));
+    }
+
+    let config: Config = serde_json::from_str(&catalog).expect("Catalog was validated");
+
+    let mut images = Vec::new();
+    let mut image_urls = Vec::new();
+    collect_images(&config.os_list, &mut |img| {
+        if let Some(p) = local.get(&img.url) {
+            images.push((p.clone(), img.image_download_sha256));
+            image_urls.push(img.url.clone());
+        }
+    });
+
+    let files = local
+        .into_iter()
+        .filter(|(u, _)| !image_urls.contains(u))
+        .collect();
+
+    Ok(Sideload {
+        name,
+        config,
+        images,
+        files,
+        warnings: diagnostics,
+        catalog,
+    })
+}
+
+impl Sideload {
+    /// Store the catalog in `dir`, replacing any previous import of a folder with the same name.
+    /// Returns the path to the stored catalog.
+    ///
+    /// Referenced files are not copied, and should be imported into the cache of the application.
+    pub fn install(&self, dir: &Path) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+
+        let path = dir.join(format!("{}.json", self.name));
+        std::fs::write(&path, &self.catalog)?;
+
+        Ok(path)
+    }
+}
+
+/// Load all catalogs stored in `dir` using [`Sideload::install`].
+pub fn installed(dir: &Path) -> Vec<(PathBuf, io::Result<Config>)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut res: Vec<_> = entries
+        .flatten()
+        .map(|x| x.path())
+        .filter(|x| x.extension().is_some_and(|e| e == "json"))
+        .map(|p| {
+            let config = std::fs::read(&p).and_then(|x| {
+                serde_json::from_slice(&x)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            });
+            (p, config)
+        })
+        .collect();
+    res.sort_by(|a, b| a.0.cmp(&b.0));
+
+    res
+}
+
+/// Replace relative file references in `value` with `file://` URLs.
+fn resolve(dir: &Path, value: &mut Value, local: &mut HashMap<Url, PathBuf>) -> Result<(), Error> {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                match v {
+                    Value::String(s) if FILE_FIELDS.contains(&k.as_str()) => {
+                        if let Some((url, path)) = resolve_file(dir, s)? {
+                            *s = url.to_string();
+                            local.insert(url, path);
+                        }
+                    }
+                    other => resolve(dir, other, local)?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for v in items {
+                resolve(dir, v, local)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Returns [`None`] if `reference` is already a URL.
+fn resolve_file(dir: &Path, reference: &str) -> Result<Option<(Url, PathBuf)>, Error> {
+    if Url::parse(reference) != Err(url::ParseError::RelativeUrlWithoutBase) {
+        return Ok(None);
+    }
+
+    let rel = Path::new(reference);
+    if !rel.components().all(|x| matches!(x, Component::Normal(_))) {
+        return Err(Error::OutsideFolder(reference.to_string()));
+    }
+
+    let path = dir.join(rel);
+    if !path.is_file() {
+        return Err(Error::MissingFile(path));
+    }
+    if path.extension().is_none() {
+        return Err(Error::NoExtension(path));
+    }
+
+    let url =
+        Url::from_file_path(&path).map_err(|_| Error::OutsideFolder(reference.to_string()))?;
+    Ok(Some((url, path)))
+}
+
+fn collect_images(items: &[OsListItem], f: &mut impl FnMut(&crate::config::OsImage)) {
+    for item in items {
+        match item {
+            OsListItem::Image(x) => f(x),
+            OsListItem::SubList(x) => collect_images(&x.subitems, f),
+            OsListItem::Bundle(x) => x.steps.iter().for_each(|s| f(&s.image)),
+            OsListItem::RemoteSubList(_) => {}
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Verify signed catalogs.
+//!
+//! A signed catalog is accompanied by a detached signature at the same URL with
+//! [`SIGNATURE_SUFFIX`] appended. The signature is the base64 encoded Ed25519 signature of the
+//! catalog file exactly as served.
+//!
+//! ```text
+//! https://example.com/os_list.json
+//! https://example.com/os_list.json.sig
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use base64::Engine;
+use ring::signature::{ED25519, UnparsedPublicKey};
+use url::Url;
+
+/// Suffix appended to the catalog URL to get the signature URL.
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+
+/// Base64 encoded Ed25519 public keys trusted for catalogs, separated by commas. Pinned at build
+/// time using the `BB_CONFIG_CATALOG_KEYS` environment variable.
+pub const PINNED_KEYS: Option<&str> = option_env!("BB_CONFIG_CATALOG_KEYS");
+
+const KEY_LEN: usize = 32;
+
+/// Errors while verifying catalogs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Public key is not a base64 encoded Ed25519 key.
+    InvalidKey,
+    /// Signature is not base64 encoded.
+    InvalidEncoding,
+    /// Signature does not match the catalog for any trusted key.
+    Mismatch,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidKey => write!(f, "Invalid catalog public key"),
+            Self::InvalidEncoding => write!(f, "Catalog signature is not valid base64"),
+            Self::Mismatch => write!(f, "Catalog signature does not match any trusted key"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Ed25519 public key trusted to sign catalogs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublicKey([u8; KEY_LEN]);
+
+impl PublicKey {
+    pub const fn new(key: [u8; KEY_LEN]) -> Self {
+        Self(key)
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(s.trim())
+            .map_err(|_| Error::InvalidKey)?;
+
+        key.try_into().map(Self).map_err(|_| Error::InvalidKey)
+    }
+}
+
+/// Keys from [`PINNED_KEYS`]. Empty if no keys were pinned at build time, in which case all
+/// catalogs are treated as unsigned.
+pub fn pinned_keys() -> Vec<PublicKey> {
+    PINNED_KEYS
+        .into_iter()
+        .flat_map(|x| x.split(','))
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| x.parse().expect("Invalid pinned catalog key"))
+        .collect()
+}
+
+/// URL of the detached signature for a catalog.
+pub fn signature_url(catalog: &Url) -> Url {
+    let mut res = catalog.clone();
+    res.set_path(&format!("{}{SIGNATURE_SUFFIX}", catalog.path()));
+    res
+}
+
+/// Check that `signature` is a valid signature of `data` by any of the `keys`.
+pub fn verify(data: &[u8], signature: &[u8], keys: &[PublicKey]) -> Result<(), Error> {
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(signature.trim_ascii())
+        .map_err(|_| Error::InvalidEncoding)?;
+
+    keys.iter()
+        .any(|k| {
+            UnparsedPublicKey::new(&ED25519, k.0)
+                .verify(data, &signature)
+                .is_ok()
+        })
+        .then_some(())
+        .ok_or(Error::Mismatch)
+}
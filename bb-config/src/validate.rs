@@ -0,0 +1,415 @@
+//! Validate catalogs with diagnostics pointing to the offending field.
+//!
+//! [`Config`](crate::Config) skips invalid boards and Os images while parsing, so that a single bad
+//! entry does not break the whole catalog. This makes mistakes in custom catalogs hard to find.
+//! [`validate`] reports every entry which would be skipped, along with other likely mistakes.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use url::Url;
+
+use crate::config::{Device, Flasher, OsBundle, OsImage, OsRemoteSubList, OsSubList};
+
+/// Severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Catalog is usable, but probably not as intended.
+    Warning,
+    /// Entry (or the whole catalog) will be ignored.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Problem found in a catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Path to the field, eg: `imager.devices[2].flasher`. Empty for the whole catalog.
+    pub path: String,
+    /// Line and column (starting from 1) of the field, if known.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((line, col)) = self.position {
+            write!(f, "{line}:{col}: ")?;
+        }
+        write!(f, "{}: ", self.severity)?;
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Check a catalog (config JSON). Diagnostics are returned in document order.
+pub fn validate(src: &str) -> Vec<Diagnostic> {
+    let root: Value = match serde_json::from_str(src) {
+        Ok(x) => x,
+        Err(e) => {
+            let msg = e.to_string();
+            let message = match msg.rsplit_once(" at line ") {
+                Some((x, _)) => x.to_string(),
+                None => msg,
+            };
+
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                path: String::new(),
+                position: Some((e.line(), e.column())),
+                message,
+            }];
+        }
+    };
+
+    let mut v = Validator {
+        src,
+        path: Vec::new(),
+        res: Vec::new(),
+    };
+
+    if let Err(e) = serde_json::from_value::<crate::Config>(root.clone()) {
+        v.push(Severity::Error, format!("Invalid catalog: {e}"));
+        return v.res;
+    }
+
+    let mut tags = HashSet::new();
+    if let Some(devices) = root.pointer("/imager/devices").and_then(Value::as_array) {
+        v.enter(Segment::Key("imager"), |v| {
+            v.enter(Segment::Key("devices"), |v| v.devices(devices, &mut tags))
+        });
+    }
+
+    if let Some(items) = root.get("os_list").and_then(Value::as_array) {
+        v.enter(Segment::Key("os_list"), |v| v.os_list(items, &tags));
+    }
+
+    v.res
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Segment {
+    Key(&'static str),
+    Index(usize),
+}
+
+struct Validator<'a> {
+    src: &'a str,
+    path: Vec<Segment>,
+    res: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        let mut path = String::new();
+        for seg in &self.path {
+            match seg {
+                Segment::Key(k) if path.is_empty() => path.push_str(k),
+                Segment::Key(k) => {
+                    path.push('.');
+                    path.push_str(k);
+                }
+                Segment::Index(i) => path.push_str(&format!("[{i}]")),
+            }
+        }
+
+        self.res.push(Diagnostic {
+            severity,
+            path,
+            position: locate(self.src, &self.path),
+            message: message.into(),
+        });
+    }
+
+    fn enter<T>(&mut self, seg: Segment, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.path.push(seg);
+        let res = f(self);
+        self.path.pop();
+        res
+    }
+
+    fn devices(&mut self, devices: &[Value], tags: &mut HashSet<String>) {
+        let mut names = HashMap::new();
+
+        for (i, dev) in devices.iter().enumerate() {
+            self.enter(Segment::Index(i), |v| {
+                if !v.flasher(dev) {
+                    return;
+                }
+
+                let Some(dev) = v.parse::<Device>(dev, "Board") else {
+                    return;
+                };
+
+                if let Some(prev) = names.insert(dev.name.clone(), i) {
+                    v.push(
+                        Severity::Warning,
+                        format!(
+                            "Duplicate board `{}` overwrites imager.devices[{prev}]",
+                            dev.name
+                        ),
+                    );
+                }
+
+                match &dev.icon {
+                    Some(x) => v.enter(Segment::Key("icon"), |v| v.url(x)),
+                    None => v.push(Severity::Warning, "Board has no icon"),
+                }
+                if let Some(x) = &dev.documentation {
+                    v.enter(Segment::Key("documentation"), |v| v.url(x));
+                }
+                if dev.tags.is_empty() {
+                    v.push(
+                        Severity::Warning,
+                        "Board has no tags, so no images are shown",
+                    );
+                }
+
+                tags.extend(dev.tags);
+            });
+        }
+    }
+
+    fn os_list(&mut self, items: &[Value], tags: &HashSet<String>) {
+        let mut names = HashMap::new();
+
+        for (i, item) in items.iter().enumerate() {
+            self.enter(Segment::Index(i), |v| {
+                if !v.flasher(item) {
+                    return;
+                }
+
+                let (name, devices) = if item.get("subitems_url").is_some() {
+                    let Some(x) = v.parse::<OsRemoteSubList>(item, "Remote sublist") else {
+                        return;
+                    };
+                    v.enter(Segment::Key("icon"), |v| v.url(&x.icon));
+                    v.enter(Segment::Key("subitems_url"), |v| v.url(&x.subitems_url));
+                    (x.name, Some(x.devices))
+                } else if let Some(subitems) = item.get("subitems") {
+                    let Some(x) = v.parse::<OsSubList>(item, "Sublist") else {
+                        return;
+                    };
+                    v.enter(Segment::Key("icon"), |v| v.url(&x.icon));
+                    if let Some(subitems) = subitems.as_array() {
+                        v.enter(Segment::Key("subitems"), |v| v.os_list(subitems, tags));
+                    }
+                    (x.name, None)
+                } else if item.get("steps").is_some() {
+                    let Some(x) = v.parse::<OsBundle>(item, "Bundle") else {
+                        return;
+                    };
+                    v.enter(Segment::Key("icon"), |v| v.url(&x.icon));
+                    v.enter(Segment::Key("steps"), |v| {
+                        for (i, step) in x.steps.iter().enumerate() {
+                            v.enter(Segment::Index(i), |v| {
+                                v.enter(Segment::Key("image"), |v| v.image(&step.image))
+                            });
+                        }
+                    });
+                    (x.name, Some(x.devices))
+                } else {
+                    let Some(x) = v.parse::<OsImage>(item, "Os image") else {
+                        return;
+                    };
+                    v.image(&x);
+                    (x.name, Some(x.devices))
+                };
+
+                if let Some(prev) = names.insert(name.clone(), i) {
+                    v.push(
+                        Severity::Warning,
+                        format!("Duplicate name `{name}`, also used by item {prev} of this list"),
+                    );
+                }
+
+                // Catalogs without boards extend another catalog, so tags cannot be checked.
+                if let Some(devices) = devices
+                    && !tags.is_empty()
+                    && devices.is_disjoint(tags)
+                {
+                    v.enter(Segment::Key("devices"), |v| {
+                        v.push(
+                            Severity::Warning,
+                            "Does not match any board, so it is never shown",
+                        )
+                    });
+                }
+            });
+        }
+    }
+
+    fn image(&mut self, img: &OsImage) {
+        self.enter(Segment::Key("icon"), |v| v.url(&img.icon));
+        self.enter(Segment::Key("url"), |v| v.url(&img.url));
+        if let Some(x) = &img.bmap {
+            self.enter(Segment::Key("bmap"), |v| v.url(x));
+        }
+        if let Some(x) = &img.metalink {
+            self.enter(Segment::Key("metalink"), |v| v.url(x));
+        }
+        if img.extract_size == 0 {
+            self.enter(Segment::Key("extract_size"), |v| {
+                v.push(Severity::Warning, "Extracted size should not be 0")
+            });
+        }
+    }
+
+    /// Check the `flasher` field of an item. Returns `false` if the item will be ignored.
+    fn flasher(&mut self, item: &Value) -> bool {
+        let Some(flasher) = item.get("flasher") else {
+            return true;
+        };
+
+        if serde_json::from_value::<Flasher>(flasher.clone()).is_ok() {
+            return true;
+        }
+
+        self.enter(Segment::Key("flasher"), |v| {
+            v.push(
+                Severity::Error,
+                format!(
+                    "Unknown flasher {flasher}, expected one of \"SdCard\", \"BeagleConnectFreedom\", \"Msp430Usb\", \"Pb2Mspm0\""
+                ),
+            )
+        });
+        false
+    }
+
+    fn parse<T: DeserializeOwned>(&mut self, item: &Value, kind: &str) -> Option<T> {
+        match serde_json::from_value(item.clone()) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                self.push(Severity::Error, format!("{kind} is ignored: {e}"));
+                None
+            }
+        }
+    }
+
+    fn url(&mut self, url: &Url) {
+        match url.scheme() {
+            "https" | "file" => {}
+            "http" => self.push(Severity::Warning, "Insecure URL, use https instead"),
+            x => self.push(Severity::Error, format!("Unsupported URL scheme `{x}`")),
+        }
+    }
+}
+
+/// Find line and column of the value at `path`.
+fn locate(src: &str, path: &[Segment]) -> Option<(usize, usize)> {
+    let b = src.as_bytes();
+    let mut pos = skip_ws(b, 0);
+
+    for seg in path {
+        match (seg, b.get(pos)?) {
+            (Segment::Key(key), b'{') => loop {
+                pos = skip_ws(b, pos + 1);
+                let end = skip_string(b, pos)?;
+                let k: String = serde_json::from_str(&src[pos..end]).ok()?;
+
+                pos = skip_ws(b, end);
+                if b.get(pos)? != &b':' {
+                    return None;
+                }
+                pos = skip_ws(b, pos + 1);
+
+                if k == *key {
+                    break;
+                }
+
+                pos = skip_ws(b, skip_value(b, pos)?);
+                if b.get(pos)? != &b',' {
+                    return None;
+                }
+            },
+            (Segment::Index(i), b'[') => {
+                pos = skip_ws(b, pos + 1);
+                for _ in 0..*i {
+                    pos = skip_ws(b, skip_value(b, pos)?);
+                    if b.get(pos)? != &b',' {
+                        return None;
+                    }
+                    pos = skip_ws(b, pos + 1);
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    let line_start = src[..pos].rfind('\n').map_or(0, |x| x + 1);
+    let line = src[..pos].matches('\n').count() + 1;
+    let col = src[line_start..pos].chars().count() + 1;
+    Some((line, col))
+}
+
+fn skip_ws(b: &[u8], pos: usize) -> usize {
+    pos + b[pos..]
+        .iter()
+        .take_while(|x| x.is_ascii_whitespace())
+        .count()
+}
+
+/// Returns position after the string starting at `pos`.
+fn skip_string(b: &[u8], pos: usize) -> Option<usize> {
+    if b.get(pos)? != &b'"' {
+        return None;
+    }
+
+    let mut i = pos + 1;
+    loop {
+        match b.get(i)? {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+}
+
+/// Returns position after the value starting at `pos`.
+fn skip_value(b: &[u8], pos: usize) -> Option<usize> {
+    match b.get(pos)? {
+        b'"' => skip_string(b, pos),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut i = pos;
+            loop {
+                match b.get(i)? {
+                    b'"' => {
+                        i = skip_string(b, i)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+        }
+        _ => Some(
+            pos + b[pos..]
+                .iter()
+                .take_while(|x| !matches!(x, b',' | b'}' | b']') && !x.is_ascii_whitespace())
+                .count(),
+        ),
+    }
+}
@@ -0,0 +1,132 @@
+//! Reconstruct files from binary deltas generated with `zstd --patch-from`.
+//!
+//! Allows downloading just the difference between an older file already present in cache and a
+//! newer file. The older file needs to be provided in the same form that was used to generate the
+//! delta (e.g. extracted images).
+
+use std::io::{self, Read, Write};
+
+use futures::channel::mpsc;
+use sha2::{Digest as _, Sha256};
+
+use crate::{AsyncTempFile, CacheLock, Downloader};
+
+/// Maximum window size supported by zstd. Deltas of large images use long windows.
+const WINDOW_LOG_MAX: u32 = 31;
+
+impl Downloader {
+    /// Reconstruct a file by applying a delta to `base`. The delta is downloaded (and cached)
+    /// using [`download_with_sha`](Self::download_with_sha). The reconstructed file is cached with
+    /// `sha256`, so it can later be found using [`check_cache_from_sha`](Self::check_cache_from_sha).
+    ///
+    /// Progress is only reported for downloading the delta.
+    pub async fn download_with_delta<U: reqwest::IntoUrl>(
+        &self,
+        base: impl Read + Send + 'static,
+        delta_url: U,
+        delta_sha256: [u8; 32],
+        sha256: [u8; 32],
+        chan: Option<mpsc::Sender<f32>>,
+    ) -> io::Result<std::path::PathBuf> {
+        let file_path = self.path_from_sha(sha256);
+        let _lock = CacheLock::new(&file_path).await?;
+
+        if let Some(p) = self.check_cache_from_sha(sha256).await {
+            return Ok(p);
+        }
+
+        let delta = self
+            .download_with_sha(delta_url, delta_sha256, chan)
+            .await?;
+
+        tracing::info!("Applying delta {:?}", delta);
+        let (file, hash) = tokio::task::spawn_blocking(move || {
+            let delta = io::BufReader::new(std::fs::File::open(delta)?);
+            patch(base, delta)
+        })
+        .await
+        .map_err(io::Error::other)??;
+
+        if hash != sha256 {
+            tracing::error!(
+                "Expected SHA256: {}, got {}",
+                const_hex::encode(sha256),
+                const_hex::encode(hash)
+            );
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid SHA256 after applying delta",
+            ));
+        }
+
+        AsyncTempFile::from(file).persist(&file_path).await?;
+        Ok(file_path)
+    }
+}
+
+/// Apply delta to base. Returns a temporary file with the result along with its SHA256.
+fn patch(mut base: impl Read, delta: impl io::BufRead) -> io::Result<(std::fs::File, [u8; 32])> {
+    // The complete base is needed as reference. Extract it to a file and map it in memory to avoid
+    // keeping multiple GBs in heap.
+    let mut base_file = tempfile::tempfile()?;
+    io::copy(&mut base, &mut base_file)?;
+    // SAFETY: The file is an unnamed temporary file, so nothing else can modify it while mapped.
+    let base_map = unsafe { memmap2::Mmap::map(&base_file)? };
+
+    let mut decoder = zstd::stream::read::Decoder::with_ref_prefix(delta, &base_map)?;
+    decoder.window_log_max(WINDOW_LOG_MAX)?;
+
+    let mut file = tempfile::tempfile()?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+
+    loop {
+        let count = decoder.read(&mut buf)?;
+        if count == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..count]);
+        file.write_all(&buf[..count])?;
+    }
+
+    let hash = hasher
+        .finalize()
+        .as_slice()
+        .try_into()
+        .expect("SHA-256 is 32 bytes");
+
+    Ok((file, hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Seek, Write};
+
+    use sha2::{Digest as _, Sha256};
+
+    use super::patch;
+
+    #[test]
+    fn apply_delta() {
+        let base: Vec<u8> = (0..256 * 1024).map(|x| (x % 251) as u8).collect();
+        let mut target = base.clone();
+        target[1000..1100].fill(0xaa);
+        target.extend_from_slice(b"new release");
+
+        let mut delta = Vec::new();
+        let mut encoder =
+            zstd::stream::write::Encoder::with_ref_prefix(&mut delta, 3, &base).unwrap();
+        encoder.write_all(&target).unwrap();
+        encoder.finish().unwrap();
+
+        let (mut file, hash) = patch(base.as_slice(), delta.as_slice()).unwrap();
+
+        let mut res = Vec::new();
+        file.seek(std::io::SeekFrom::Start(0)).unwrap();
+        file.read_to_end(&mut res).unwrap();
+
+        assert_eq!(res, target);
+        assert_eq!(hash, <[u8; 32]>::from(Sha256::digest(&target)));
+    }
+}
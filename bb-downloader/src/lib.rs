@@ -8,6 +8,13 @@
 //! - Check if a file is available in cache.
 //! - Uses SHA256 for verifying cached files.
 //! - Optional support to download files without caching.
+//! - Cache directory can be shared between multiple processes. Files are locked while being
+//!   downloaded.
+//! - Custom HTTP headers for private repositories.
+//! - Proxies and additional root certificates for networks with TLS inspection.
+//! - Optional support to pick download mirrors from [Metalink](metalink) files.
+//! - Optional support to pull images published as [OCI artifacts](oci).
+//! - Optional support to reconstruct files from [binary deltas](delta) against older cached files.
 //!
 //! # Sample Usage
 //!
@@ -38,6 +45,13 @@
 //! }
 //! ```
 
+#[cfg(feature = "delta")]
+pub mod delta;
+#[cfg(feature = "metalink")]
+pub mod metalink;
+#[cfg(feature = "oci")]
+pub mod oci;
+
 use futures::{Stream, StreamExt, channel::mpsc};
 #[cfg(feature = "json")]
 use serde::de::DeserializeOwned;
@@ -51,6 +65,9 @@ use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 
 pub use reqwest::IntoUrl;
 
+/// Environment variable to override the cache directory. See [`Downloader::new_with_env`].
+pub const CACHE_DIR_ENV: &str = "BB_IMAGER_CACHE_DIR";
+
 /// Simple downloader that caches files in the provided directory. Uses SHA256 to determine if the
 /// file is already downloaded.
 ///
@@ -70,10 +87,22 @@ pub use reqwest::IntoUrl;
 ///
 /// You do not have to wrap the Client in an Rc or Arc to reuse it, because it already uses an Arc
 /// internally.
+///
+/// # TLS
+///
+/// Server certificates are verified using the trust store of the OS, so certificates installed
+/// system wide (e.g. by enterprise proxies) are trusted. Additional root certificates can be
+/// provided using [`with_root_certificates`](Self::with_root_certificates).
 #[derive(Debug, Clone)]
 pub struct Downloader {
     client: reqwest::Client,
     cache_dir: PathBuf,
+    /// Extra headers, along with a URL having the only origin they are sent to.
+    headers: Option<(reqwest::Url, reqwest::header::HeaderMap)>,
+    rate_limit: Option<u64>,
+    proxy: Option<reqwest::Proxy>,
+    root_certs: Vec<reqwest::Certificate>,
+    insecure: bool,
 }
 
 impl Downloader {
@@ -92,14 +121,173 @@ impl Downloader {
             ));
         }
 
-        let client = reqwest::Client::builder()
-            .user_agent(env!("CARGO_PKG_NAME"))
-            .connect_timeout(Duration::from_secs(10))
-            .read_timeout(Duration::from_secs(15))
-            .build()
-            .expect("Unsupported OS");
+        let client = client_builder().build().expect("Unsupported OS");
+
+        Ok(Self {
+            client,
+            cache_dir,
+            headers: None,
+            rate_limit: None,
+            proxy: None,
+            root_certs: Vec::new(),
+            insecure: false,
+        })
+    }
+
+    /// Same as [`new`](Self::new), but [`CACHE_DIR_ENV`] takes precedence over the provided
+    /// directory if set.
+    pub fn new_with_env<P: Into<PathBuf>>(cache_dir: P) -> io::Result<Self> {
+        match std::env::var_os(CACHE_DIR_ENV) {
+            Some(x) if !x.is_empty() => Self::new(x),
+            _ => Self::new(cache_dir),
+        }
+    }
+
+    /// Directory used for storing cached files.
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// Create a downloader which sends the provided HTTP headers with every request to the origin
+    /// (scheme, host and port) of `url`. Useful for private repositories requiring authentication.
+    /// Headers are never sent to other hosts, such as [metalink](metalink) mirrors. The cache is
+    /// shared with the original downloader.
+    pub fn with_headers<K, V>(
+        &self,
+        url: &reqwest::Url,
+        headers: impl IntoIterator<Item = (K, V)>,
+    ) -> io::Result<Self>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut res = self.clone();
+        let mut map = reqwest::header::HeaderMap::new();
+
+        for (k, v) in headers {
+            let k = reqwest::header::HeaderName::from_bytes(k.as_ref().as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let mut v = reqwest::header::HeaderValue::from_str(v.as_ref())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            v.set_sensitive(true);
+
+            map.insert(k, v);
+        }
+
+        res.headers = Some((url.clone(), map));
+        Ok(res)
+    }
+
+    /// Create a downloader which sends all requests through the provided proxy. Supports `http`,
+    /// `https` and `socks5` proxy URLs. The cache is shared with the original downloader.
+    pub fn with_proxy(&self, proxy: &str) -> io::Result<Self> {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        Self {
+            proxy: Some(proxy),
+            ..self.clone()
+        }
+        .rebuild()
+    }
+
+    /// Create a downloader which trusts root certificates from `path` in addition to the OS trust
+    /// store. `path` can be a PEM bundle, a DER encoded certificate, or a directory containing
+    /// `.pem`, `.crt` or `.cer` files. The cache is shared with the original downloader.
+    pub fn with_root_certificates(&self, path: &Path) -> io::Result<Self> {
+        let mut root_certs = self.root_certs.clone();
+
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(path)?
+                .map(|x| x.map(|x| x.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+
+            for p in entries.into_iter().filter(|p| {
+                p.extension()
+                    .is_some_and(|x| x == "pem" || x == "crt" || x == "cer")
+            }) {
+                root_certs.extend(read_certificates(&p)?);
+            }
+        } else {
+            root_certs.extend(read_certificates(path)?);
+        }
+
+        Self {
+            root_certs,
+            ..self.clone()
+        }
+        .rebuild()
+    }
+
+    /// Create a downloader which does not verify TLS certificates. The cache is shared with the
+    /// original downloader.
+    ///
+    /// # Warning
+    ///
+    /// This allows anyone on the network to tamper with downloads. Only use in closed networks,
+    /// and prefer [`with_root_certificates`](Self::with_root_certificates) whenever possible.
+    pub fn with_insecure(&self, insecure: bool) -> io::Result<Self> {
+        if insecure {
+            tracing::warn!("TLS certificate verification is disabled");
+        }
+
+        Self {
+            insecure,
+            ..self.clone()
+        }
+        .rebuild()
+    }
+
+    /// Build the client again after changing network settings.
+    fn rebuild(mut self) -> io::Result<Self> {
+        let mut builder = client_builder()
+            .tls_certs_merge(self.root_certs.iter().cloned())
+            .tls_danger_accept_invalid_certs(self.insecure);
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+
+        self.client = builder.build().map_err(io::Error::other)?;
+        Ok(self)
+    }
+
+    /// Create a downloader which limits download speed to `bytes_per_sec`. The cache is shared
+    /// with the original downloader.
+    pub fn with_rate_limit(&self, bytes_per_sec: u64) -> Self {
+        Self {
+            rate_limit: Some(bytes_per_sec).filter(|x| *x > 0),
+            ..self.clone()
+        }
+    }
+
+    /// Sleep if more than `downloaded` bytes would have been allowed since `start` by the rate
+    /// limit.
+    async fn throttle(&self, start: std::time::Instant, downloaded: usize) {
+        if let Some(limit) = self.rate_limit {
+            let expected = Duration::from_secs_f64(downloaded as f64 / limit as f64);
+            let elapsed = start.elapsed();
+
+            if expected > elapsed {
+                tokio::time::sleep(expected - elapsed).await;
+            }
+        }
+    }
+
+    fn get<U: reqwest::IntoUrl>(&self, url: U) -> io::Result<reqwest::RequestBuilder> {
+        let url = url.into_url().map_err(io::Error::other)?;
+        let headers = self.headers_for(&url);
+        Ok(self.client.get(url).headers(headers))
+    }
 
-        Ok(Self { client, cache_dir })
+    /// Extra headers to send to `url`. Empty unless `url` has the same origin as the one provided
+    /// in [`with_headers`](Self::with_headers).
+    fn headers_for(&self, url: &reqwest::Url) -> reqwest::header::HeaderMap {
+        match &self.headers {
+            Some((origin, headers)) if origin.origin() == url.origin() => headers.clone(),
+            _ => Default::default(),
+        }
     }
 
     /// Check if a downloaded file with a particular SHA256 is already in cache.
@@ -114,12 +302,27 @@ impl Downloader {
             }
 
             // Delete old file
+            tracing::warn!("Cached file {file_path:?} is corrupted. Removing");
             let _ = tokio::fs::remove_file(&file_path).await;
         }
 
         None
     }
 
+    /// Check if a downloaded file with a particular SHA256 is present in cache, without verifying
+    /// its contents. Cheap enough to be used for showing cache status.
+    ///
+    /// [`check_cache_from_sha`](Self::check_cache_from_sha) should be used before actually using the
+    /// file.
+    pub fn check_cache(&self, sha256: [u8; 32]) -> Option<PathBuf> {
+        let file_path = self.path_from_sha(sha256);
+        if file_path.exists() {
+            Some(file_path)
+        } else {
+            None
+        }
+    }
+
     /// Check if a downloaded file is already in cache.
     ///
     /// [`check_cache_from_sha`](Self::check_cache_from_sha) should be prefered in cases when SHA256
@@ -134,6 +337,41 @@ impl Downloader {
         }
     }
 
+    /// Copy a local file into cache, so that it is found by SHA256 without downloading. Useful for
+    /// importing images for offline use.
+    pub async fn import_with_sha(&self, path: &Path, sha256: [u8; 32]) -> io::Result<PathBuf> {
+        let file_path = self.path_from_sha(sha256);
+        let _lock = CacheLock::new(&file_path).await?;
+
+        if let Some(p) = self.check_cache_from_sha(sha256).await {
+            return Ok(p);
+        }
+
+        if sha256_from_path(path).await? != sha256 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid SHA256 for {}", path.display()),
+            ));
+        }
+
+        copy_to_cache(path, &file_path).await?;
+        Ok(file_path)
+    }
+
+    /// Copy a local file into cache, so that it is found by URL without downloading.
+    pub async fn import_with_url<U: reqwest::IntoUrl>(
+        &self,
+        url: U,
+        path: &Path,
+    ) -> io::Result<PathBuf> {
+        let url = url.into_url().map_err(io::Error::other)?;
+        let file_path = self.path_from_url(&url);
+        let _lock = CacheLock::new(&file_path).await?;
+
+        copy_to_cache(path, &file_path).await?;
+        Ok(file_path)
+    }
+
     /// Download a JSON file without caching the contents. Should be used when there is no point in
     /// caching the file.
     #[cfg(feature = "json")]
@@ -142,8 +380,7 @@ impl Downloader {
         T: DeserializeOwned,
         U: reqwest::IntoUrl,
     {
-        self.client
-            .get(url)
+        self.get(url)?
             .send()
             .await
             .map_err(io::Error::other)?
@@ -152,6 +389,126 @@ impl Downloader {
             .map_err(io::Error::other)
     }
 
+    /// Download a small file into memory without caching it. Fails with
+    /// [`NotFound`](io::ErrorKind::NotFound) if the server does not have the file.
+    pub async fn download_bytes_no_cache<U: reqwest::IntoUrl>(
+        &self,
+        url: U,
+    ) -> io::Result<Vec<u8>> {
+        let response = self.get(url)?.send().await.map_err(io::Error::other)?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", response.url()),
+            ));
+        }
+
+        let data = response
+            .error_for_status()
+            .map_err(io::Error::other)?
+            .bytes()
+            .await
+            .map_err(io::Error::other)?;
+
+        Ok(data.to_vec())
+    }
+
+    /// Download a JSON file and cache it. A cached copy younger than `ttl` is used without
+    /// accessing the network. An older copy is returned immediately and refreshed in the
+    /// background, so the next call sees the new contents. This allows working offline once the
+    /// file has been downloaded at least once.
+    ///
+    /// Must be called from within a tokio runtime.
+    #[cfg(feature = "json")]
+    pub async fn download_json_with_ttl<T, U>(&self, url: U, ttl: Duration) -> io::Result<T>
+    where
+        T: DeserializeOwned,
+        U: reqwest::IntoUrl,
+    {
+        let url = url.into_url().map_err(io::Error::other)?;
+
+        if let Some(p) = self.check_cache_from_url(url.clone()) {
+            match read_json(&p).await {
+                Ok(x) => {
+                    let age = tokio::fs::metadata(&p)
+                        .await?
+                        .modified()?
+                        .elapsed()
+                        .unwrap_or_default();
+
+                    if age >= ttl {
+                        let downloader = self.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = downloader.download_no_cache(url.clone(), None).await {
+                                tracing::warn!("Failed to refresh {url}: {e}");
+                            }
+                        });
+                    }
+
+                    return Ok(x);
+                }
+                Err(e) => tracing::warn!("Ignoring invalid cached file {p:?}: {e}"),
+            }
+        }
+
+        let p = self.download_no_cache(url, None).await?;
+        read_json(&p).await
+    }
+
+    /// Pick the preferred reachable mirror from a metalink file. Returns `fallback` if the
+    /// metalink cannot be used or if it does not agree with the expected SHA256.
+    ///
+    /// The downloaded file should still be verified using SHA256 as usual.
+    #[cfg(feature = "metalink")]
+    pub async fn resolve_metalink<U: reqwest::IntoUrl>(
+        &self,
+        url: U,
+        fallback: reqwest::Url,
+        sha256: [u8; 32],
+    ) -> reqwest::Url {
+        let data = async {
+            self.get(url)?
+                .send()
+                .await
+                .map_err(io::Error::other)?
+                .text()
+                .await
+                .map_err(io::Error::other)
+        };
+
+        let links = match data.await.and_then(|x| metalink::Metalink::parse(&x)) {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::warn!("Failed to use metalink: {e}");
+                return fallback;
+            }
+        };
+
+        if links.sha256.is_some_and(|x| x != sha256) {
+            tracing::warn!("Metalink SHA256 does not match");
+            return fallback;
+        }
+
+        for mirror in links.urls {
+            match self
+                .client
+                .head(mirror.clone())
+                .headers(self.headers_for(&mirror))
+                .send()
+                .await
+            {
+                Ok(x) if x.status().is_success() => {
+                    tracing::info!("Using mirror {mirror}");
+                    return mirror;
+                }
+                _ => tracing::debug!("Mirror {mirror} not reachable"),
+            }
+        }
+
+        fallback
+    }
+
     /// Checks if the file is present in cache. If the file is present, returns path to it. Else
     /// downloads the file.
     ///
@@ -167,13 +524,14 @@ impl Downloader {
         chan: Option<mpsc::Sender<f32>>,
     ) -> io::Result<PathBuf> {
         let url = url.into_url().map_err(io::Error::other)?;
+        let _lock = CacheLock::new(&self.path_from_url(&url)).await?;
 
         // Check cache
         if let Some(p) = self.check_cache_from_url(url.clone()) {
             return Ok(p);
         }
 
-        self.download_no_cache(url, chan).await
+        self.download_no_cache_internal(url, chan).await
     }
 
     /// Downloads the file without checking cache.
@@ -192,10 +550,21 @@ impl Downloader {
     pub async fn download_no_cache<U: reqwest::IntoUrl>(
         &self,
         url: U,
-        mut chan: Option<mpsc::Sender<f32>>,
+        chan: Option<mpsc::Sender<f32>>,
     ) -> io::Result<PathBuf> {
         let url = url.into_url().map_err(io::Error::other)?;
+        let _lock = CacheLock::new(&self.path_from_url(&url)).await?;
+
+        self.download_no_cache_internal(url, chan).await
+    }
 
+    /// Cache lock should be held by the caller.
+    #[tracing::instrument(name = "download", skip_all, fields(url = %url))]
+    async fn download_no_cache_internal(
+        &self,
+        url: reqwest::Url,
+        mut chan: Option<mpsc::Sender<f32>>,
+    ) -> io::Result<PathBuf> {
         let file_path = self.path_from_url(&url);
         chan_send(chan.as_mut(), 0.0);
 
@@ -204,12 +573,7 @@ impl Downloader {
         {
             let mut file = tokio::io::BufWriter::new(&mut file.0);
 
-            let response = self
-                .client
-                .get(url)
-                .send()
-                .await
-                .map_err(io::Error::other)?;
+            let response = self.get(url)?.send().await.map_err(io::Error::other)?;
             let response_size = response.content_length();
             let mut response_stream = response.bytes_stream();
 
@@ -218,11 +582,13 @@ impl Downloader {
                 None => response_stream.size_hint().0,
             };
 
+            let start = std::time::Instant::now();
             while let Some(x) = response_stream.next().await {
                 let mut data = x.map_err(io::Error::other)?;
                 cur_pos += data.len();
                 file.write_all_buf(&mut data).await?;
                 chan_send(chan.as_mut(), (cur_pos as f32) / (response_size as f32));
+                self.throttle(start, cur_pos).await;
             }
 
             file.flush().await?
@@ -236,6 +602,11 @@ impl Downloader {
     /// download to finish to use the partial file.
     ///
     /// Uses SHA256 to verify that the file in cache is valid.
+    #[tracing::instrument(
+        name = "download",
+        skip_all,
+        fields(url = tracing::field::Empty, sha256 = %const_hex::encode(sha256))
+    )]
     pub async fn download_to_stream<U: reqwest::IntoUrl>(
         self,
         url: U,
@@ -243,6 +614,7 @@ impl Downloader {
         mut writer: bb_helper::file_stream::WriterFileStream,
     ) -> io::Result<()> {
         let url = url.into_url().map_err(io::Error::other)?;
+        tracing::Span::current().record("url", url.as_str());
         tracing::debug!(
             "Download {:?} with sha256: {:?}",
             url,
@@ -254,22 +626,21 @@ impl Downloader {
         {
             let mut file = tokio::io::BufWriter::new(&mut writer);
 
-            let response = self
-                .client
-                .get(url)
-                .send()
-                .await
-                .map_err(io::Error::other)?;
+            let response = self.get(url)?.send().await.map_err(io::Error::other)?;
 
             let mut response_stream = response.bytes_stream();
 
             let mut hasher = Sha256::new();
+            let mut cur_pos = 0;
+            let start = std::time::Instant::now();
 
             while let Some(x) = response_stream.next().await {
                 tracing::debug!("Got buf");
                 let mut data = x.map_err(io::Error::other)?;
+                cur_pos += data.len();
                 hasher.update(&data);
                 file.write_all_buf(&mut data).await?;
+                self.throttle(start, cur_pos).await;
             }
 
             let hash: [u8; 32] = hasher
@@ -293,6 +664,7 @@ impl Downloader {
         }
 
         tracing::info!("Saving donwloaded file to disk");
+        let _lock = CacheLock::new(&file_path).await?;
         writer.persist(&file_path).await
     }
 
@@ -304,6 +676,11 @@ impl Downloader {
     /// # Progress
     ///
     /// Download progress can be optionally tracked using a [`futures::channel::mpsc`].
+    #[tracing::instrument(
+        name = "download",
+        skip_all,
+        fields(url = tracing::field::Empty, sha256 = %const_hex::encode(sha256))
+    )]
     pub async fn download_with_sha<U: reqwest::IntoUrl>(
         &self,
         url: U,
@@ -311,29 +688,27 @@ impl Downloader {
         mut chan: Option<mpsc::Sender<f32>>,
     ) -> io::Result<PathBuf> {
         let url = url.into_url().map_err(io::Error::other)?;
+        tracing::Span::current().record("url", url.as_str());
         tracing::debug!(
             "Download {:?} with sha256: {:?}",
             url,
             const_hex::encode(sha256)
         );
 
+        let file_path = self.path_from_sha(sha256);
+        let _lock = CacheLock::new(&file_path).await?;
+
         if let Some(p) = self.check_cache_from_sha(sha256).await {
             return Ok(p);
         }
 
-        let file_path = self.path_from_sha(sha256);
         chan_send(chan.as_mut(), 0.0);
 
         let mut file = AsyncTempFile::new()?;
         {
             let mut file = tokio::io::BufWriter::new(&mut file.0);
 
-            let response = self
-                .client
-                .get(url)
-                .send()
-                .await
-                .map_err(io::Error::other)?;
+            let response = self.get(url)?.send().await.map_err(io::Error::other)?;
 
             let mut cur_pos = 0;
             let response_size = response.content_length();
@@ -346,6 +721,7 @@ impl Downloader {
             };
 
             let mut hasher = Sha256::new();
+            let start = std::time::Instant::now();
 
             while let Some(x) = response_stream.next().await {
                 let mut data = x.map_err(io::Error::other)?;
@@ -354,6 +730,7 @@ impl Downloader {
                 file.write_all_buf(&mut data).await?;
 
                 chan_send(chan.as_mut(), (cur_pos as f32) / (response_size as f32));
+                self.throttle(start, cur_pos).await;
             }
 
             let hash: [u8; 32] = hasher
@@ -421,6 +798,45 @@ async fn sha256_from_path(p: &Path) -> io::Result<[u8; 32]> {
     Ok(hash)
 }
 
+fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .user_agent(env!("CARGO_PKG_NAME"))
+        .connect_timeout(Duration::from_secs(10))
+        .read_timeout(Duration::from_secs(15))
+}
+
+/// Read all certificates from a PEM bundle or DER encoded file.
+fn read_certificates(path: &Path) -> io::Result<Vec<reqwest::Certificate>> {
+    let data = std::fs::read(path)?;
+    let invalid = |e: reqwest::Error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid certificate {}: {e}", path.display()),
+        )
+    };
+
+    if data.windows(10).any(|x| x == b"-----BEGIN") {
+        let certs = reqwest::Certificate::from_pem_bundle(&data).map_err(invalid)?;
+        if certs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("No certificates found in {}", path.display()),
+            ));
+        }
+        Ok(certs)
+    } else {
+        reqwest::Certificate::from_der(&data)
+            .map(|x| vec![x])
+            .map_err(invalid)
+    }
+}
+
+#[cfg(feature = "json")]
+async fn read_json<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let data = tokio::fs::read(path).await?;
+    serde_json::from_slice(&data).map_err(io::Error::other)
+}
+
 fn chan_send(chan: Option<&mut mpsc::Sender<f32>>, msg: f32) {
     if let Some(c) = chan {
         let _ = c.try_send(msg);
@@ -435,16 +851,20 @@ impl AsyncTempFile {
         Ok(Self(tokio::fs::File::from_std(f)))
     }
 
+    /// Copy to a partial file next to the destination first, and rename once done. This ensures
+    /// other processes never see a partially written file in cache.
     async fn persist(&mut self, path: &Path) -> io::Result<()> {
-        let mut f = tokio::fs::File::create(path).await?;
+        let part_path = partial_path(path);
+        let mut f = tokio::fs::File::create(&part_path).await?;
         self.0.seek(io::SeekFrom::Start(0)).await?;
 
         tokio::io::copy(&mut self.0, &mut f).await?;
 
         // Causes errors if not present
         f.flush().await?;
+        drop(f);
 
-        Ok(())
+        tokio::fs::rename(part_path, path).await
     }
 }
 
@@ -453,3 +873,113 @@ impl From<std::fs::File> for AsyncTempFile {
         Self(tokio::fs::File::from_std(value))
     }
 }
+
+/// Cache lock should be held by the caller.
+async fn copy_to_cache(src: &Path, file_path: &Path) -> io::Result<()> {
+    let part_path = partial_path(file_path);
+    tokio::fs::copy(src, &part_path).await?;
+    tokio::fs::rename(part_path, file_path).await
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut p = path.as_os_str().to_owned();
+    p.push(".part");
+    PathBuf::from(p)
+}
+
+/// Exclusive lock on a cache entry. Prevents multiple processes (such as GUI and CLI) from
+/// downloading the same file at the same time. The lock is released on drop.
+///
+/// On unix, the lock file is also removed on drop so that the cache directory does not fill up
+/// with lock files. Windows does not allow removing files which other processes have open, so
+/// lock files are kept there.
+struct CacheLock {
+    file: std::fs::File,
+    #[cfg(unix)]
+    path: PathBuf,
+}
+
+impl CacheLock {
+    async fn new(path: &Path) -> io::Result<Self> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+
+        tokio::task::spawn_blocking(move || {
+            loop {
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .truncate(false)
+                    .write(true)
+                    .open(&lock_path)?;
+                file.lock()?;
+
+                // The previous holder might have removed the lock file while we were waiting.
+                // Locking the removed file does not exclude anyone, so try again.
+                if is_current_file(&file, &lock_path) {
+                    return Ok(Self {
+                        file,
+                        #[cfg(unix)]
+                        path: lock_path,
+                    });
+                }
+            }
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        // Removed while still holding the lock, so that waiting processes notice and retry
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Check if `file` is still the file present at `path`.
+#[cfg(unix)]
+fn is_current_file(file: &std::fs::File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), std::fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Lock files are never removed on Windows.
+#[cfg(not(unix))]
+const fn is_current_file(_: &std::fs::File, _: &Path) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CacheLock;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn cache_lock_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let lock_path = dir.path().join("file.lock");
+
+        let lock = CacheLock::new(&path).await.unwrap();
+        assert!(lock_path.exists());
+
+        // Waits for the first lock, and locks the new file once the old one is removed
+        let waiting = tokio::spawn(async move { CacheLock::new(&path).await.unwrap() });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+
+        drop(lock);
+        let lock = waiting.await.unwrap();
+        assert!(lock_path.exists());
+
+        drop(lock);
+        assert!(!lock_path.exists());
+    }
+}
@@ -0,0 +1,176 @@
+//! Minimal [Metalink] parser. Supports both Metalink 3 and Metalink 4 (RFC 5854) files.
+//!
+//! Only http(s) mirror URLs and SHA256 checksum are extracted since the rest is not needed for
+//! downloading images. Torrents and magnet links (`metaurl`) are ignored.
+//!
+//! [Metalink]: https://www.rfc-editor.org/rfc/rfc5854
+
+use std::io;
+
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+
+/// Mirrors for a single file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metalink {
+    /// Mirror URLs ordered by preference. Only http(s) mirrors are included.
+    pub urls: Vec<reqwest::Url>,
+    /// SHA256 of the file, if present.
+    pub sha256: Option<[u8; 32]>,
+}
+
+impl Metalink {
+    /// Parse metalink file. Only the first file entry is considered.
+    pub fn parse(data: &str) -> io::Result<Self> {
+        let mut reader = Reader::from_str(data);
+        let mut urls = Vec::new();
+        let mut sha256 = None;
+
+        loop {
+            match reader.read_event().map_err(io::Error::other)? {
+                Event::Start(e) if e.local_name().as_ref() == b"url" => {
+                    // Metalink 4 uses priority where lower is better. Metalink 3 uses preference
+                    // where higher is better.
+                    let rank = match (attr(&e, "priority")?, attr(&e, "preference")?) {
+                        (Some(x), _) => x.parse::<i64>().unwrap_or(999999),
+                        (None, Some(x)) => -x.parse::<i64>().unwrap_or(0),
+                        (None, None) => 999999,
+                    };
+
+                    let content = text(&mut reader, &e)?;
+                    let Ok(url) = reqwest::Url::parse(content.trim()) else {
+                        continue;
+                    };
+                    if matches!(url.scheme(), "http" | "https") {
+                        urls.push((rank, url));
+                    }
+                }
+                Event::Start(e) if e.local_name().as_ref() == b"hash" => {
+                    let is_sha256 =
+                        attr(&e, "type")?.is_some_and(|x| x.eq_ignore_ascii_case("sha-256"));
+                    let content = text(&mut reader, &e)?;
+                    if is_sha256 && sha256.is_none() {
+                        sha256 = const_hex::decode_to_array(content.trim()).ok();
+                    }
+                }
+                Event::End(e) if e.local_name().as_ref() == b"file" => break,
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        if urls.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "No usable mirrors in metalink",
+            ));
+        }
+
+        // Stable sort to preserve file order for same rank
+        urls.sort_by_key(|x| x.0);
+
+        Ok(Self {
+            urls: urls.into_iter().map(|x| x.1).collect(),
+            sha256,
+        })
+    }
+}
+
+fn attr(e: &BytesStart, name: &str) -> io::Result<Option<String>> {
+    e.try_get_attribute(name)
+        .map_err(io::Error::other)?
+        .map(|x| x.unescape_value().map(|x| x.into_owned()))
+        .transpose()
+        .map_err(io::Error::other)
+}
+
+/// Unescaped text content of element `e`, up to its end tag.
+fn text(reader: &mut Reader<&[u8]>, e: &BytesStart) -> io::Result<String> {
+    let raw = reader.read_text(e.name()).map_err(io::Error::other)?;
+    quick_xml::escape::unescape(&raw)
+        .map(|x| x.into_owned())
+        .map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metalink4() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+  <file name="img.img.xz">
+    <size>14471447</size>
+    <hash type="sha-1">a97fcf6ba9358f8a6f62beee4421863d3e52b080</hash>
+    <hash type="sha-256">f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148</hash>
+    <url location="de" priority="2">https://mirror2.example.com/img.img.xz</url>
+    <url location="us" priority="1">https://mirror1.example.com/img.img.xz</url>
+    <url priority="3">ftp://ftp.example.com/img.img.xz</url>
+    <metaurl mediatype="torrent" priority="1">https://example.com/img.torrent</metaurl>
+  </file>
+</metalink>"#;
+
+        let res = Metalink::parse(data).unwrap();
+        assert_eq!(
+            res.urls,
+            [
+                reqwest::Url::parse("https://mirror1.example.com/img.img.xz").unwrap(),
+                reqwest::Url::parse("https://mirror2.example.com/img.img.xz").unwrap()
+            ]
+        );
+        assert_eq!(
+            res.sha256.map(const_hex::encode).as_deref(),
+            Some("f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148")
+        );
+    }
+
+    #[test]
+    fn metalink3() {
+        let data = r#"<metalink version="3.0" xmlns="http://www.metalinker.org/">
+  <files>
+    <file name="img.img.xz">
+      <resources>
+        <url type="http" preference="10">http://mirror2.example.com/img.img.xz</url>
+        <url type="http" preference="100">http://mirror1.example.com/img.img.xz</url>
+      </resources>
+    </file>
+  </files>
+</metalink>"#;
+
+        let res = Metalink::parse(data).unwrap();
+        assert_eq!(
+            res.urls[0],
+            reqwest::Url::parse("http://mirror1.example.com/img.img.xz").unwrap()
+        );
+        assert_eq!(res.sha256, None);
+    }
+
+    #[test]
+    fn escaped_url() {
+        let data = r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+  <file name="img.img.xz">
+    <url>https://mirror.example.com/get?file=img.img.xz&amp;arch=arm64</url>
+  </file>
+  <file name="other.img.xz">
+    <url>https://mirror.example.com/other.img.xz</url>
+  </file>
+</metalink>"#;
+
+        let res = Metalink::parse(data).unwrap();
+        assert_eq!(
+            res.urls,
+            [
+                reqwest::Url::parse("https://mirror.example.com/get?file=img.img.xz&arch=arm64")
+                    .unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn no_mirrors() {
+        assert!(Metalink::parse("<metalink></metalink>").is_err());
+    }
+}
@@ -0,0 +1,350 @@
+//! Minimal [OCI distribution] client to pull OS images published as OCI artifacts (e.g. on GHCR).
+//!
+//! Only anonymous or token based pull of single-layer artifacts is supported. The first layer of
+//! the manifest is treated as the image.
+//!
+//! [OCI distribution]: https://github.com/opencontainers/distribution-spec/blob/main/spec.md
+
+use std::{io, path::PathBuf, str::FromStr};
+
+use futures::channel::mpsc;
+use serde::Deserialize;
+use sha2::{Digest as _, Sha256};
+
+use crate::Downloader;
+
+const DOCKER_HUB: &str = "registry-1.docker.io";
+const MANIFEST_ACCEPT: &str = "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+
+/// OCI artifact reference. Eg: `ghcr.io/beagleboard/image:latest` or
+/// `ghcr.io/beagleboard/image@sha256:...`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reference {
+    registry: String,
+    repository: String,
+    /// Tag or digest
+    reference: String,
+}
+
+impl Reference {
+    fn manifest_url(&self) -> io::Result<reqwest::Url> {
+        reqwest::Url::parse(&format!(
+            "https://{}/v2/{}/manifests/{}",
+            self.registry, self.repository, self.reference
+        ))
+        .map_err(io::Error::other)
+    }
+
+    fn blob_url(&self, digest: &str) -> io::Result<reqwest::Url> {
+        reqwest::Url::parse(&format!(
+            "https://{}/v2/{}/blobs/{}",
+            self.registry, self.repository, digest
+        ))
+        .map_err(io::Error::other)
+    }
+
+    fn digest(&self) -> Option<[u8; 32]> {
+        parse_digest(&self.reference).ok()
+    }
+}
+
+impl FromStr for Reference {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "Invalid OCI reference");
+        let s = s.strip_prefix("oci://").unwrap_or(s);
+
+        let (name, reference) = match s.split_once('@') {
+            Some((n, d)) => (n, d.to_string()),
+            None => match s.rsplit_once(':') {
+                // Make sure this is not the registry port
+                Some((n, t)) if !t.contains('/') => (n, t.to_string()),
+                _ => (s, "latest".to_string()),
+            },
+        };
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((r, rest)) if r.contains('.') || r.contains(':') || r == "localhost" => {
+                (r.to_string(), rest.to_string())
+            }
+            Some(_) => (DOCKER_HUB.to_string(), name.to_string()),
+            None => (DOCKER_HUB.to_string(), format!("library/{name}")),
+        };
+
+        if repository.is_empty() || reference.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            registry,
+            repository,
+            reference,
+        })
+    }
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sep = if self.reference.contains(':') {
+            '@'
+        } else {
+            ':'
+        };
+        write!(
+            f,
+            "{}/{}{}{}",
+            self.registry, self.repository, sep, self.reference
+        )
+    }
+}
+
+/// Image layer of an OCI artifact.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    /// SHA256 of the layer.
+    pub sha256: [u8; 32],
+    /// Size of the layer.
+    pub size: u64,
+    /// File name from `org.opencontainers.image.title` annotation.
+    pub file_name: Option<String>,
+    url: reqwest::Url,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    layers: Vec<Descriptor>,
+}
+
+#[derive(Deserialize)]
+struct Descriptor {
+    digest: String,
+    size: u64,
+    #[serde(default)]
+    annotations: std::collections::HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+impl Downloader {
+    /// Resolve the image layer of an OCI artifact. Verifies the manifest digest if the reference
+    /// uses one.
+    pub async fn resolve_oci(&self, reference: &Reference) -> io::Result<Artifact> {
+        let manifest_url = reference.manifest_url()?;
+
+        let mut token = None;
+        let mut resp = self
+            .get(manifest_url.clone())?
+            .header(reqwest::header::ACCEPT, MANIFEST_ACCEPT)
+            .send()
+            .await
+            .map_err(io::Error::other)?;
+
+        if resp.status() == reqwest::StatusCode::UNAUTHORIZED {
+            let challenge = resp
+                .headers()
+                .get(reqwest::header::WWW_AUTHENTICATE)
+                .and_then(|x| x.to_str().ok())
+                .ok_or_else(|| io::Error::other("Registry requires unsupported authentication"))?
+                .to_string();
+            let t = self.oci_token(&challenge).await?;
+
+            resp = self
+                .get(manifest_url)?
+                .header(reqwest::header::ACCEPT, MANIFEST_ACCEPT)
+                .bearer_auth(&t)
+                .send()
+                .await
+                .map_err(io::Error::other)?;
+            token = Some(t);
+        }
+
+        let data = resp
+            .error_for_status()
+            .map_err(io::Error::other)?
+            .bytes()
+            .await
+            .map_err(io::Error::other)?;
+
+        if let Some(d) = reference.digest() {
+            let hash: [u8; 32] = Sha256::digest(&data).into();
+            if hash != d {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "OCI manifest digest mismatch",
+                ));
+            }
+        }
+
+        let manifest: Manifest = serde_json::from_slice(&data).map_err(io::Error::other)?;
+        let layer =
+            manifest.layers.into_iter().next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "OCI artifact is empty")
+            })?;
+
+        Ok(Artifact {
+            sha256: parse_digest(&layer.digest)?,
+            size: layer.size,
+            file_name: layer.annotations.get(TITLE_ANNOTATION).cloned(),
+            url: reference.blob_url(&layer.digest)?,
+            token,
+        })
+    }
+
+    /// Download the image layer of an OCI artifact. Uses cache and verifies the digest same as
+    /// [`download_with_sha`](Self::download_with_sha).
+    pub async fn download_oci(
+        &self,
+        artifact: &Artifact,
+        chan: Option<mpsc::Sender<f32>>,
+    ) -> io::Result<PathBuf> {
+        let downloader = match &artifact.token {
+            Some(t) => {
+                self.with_headers(&artifact.url, [("Authorization", format!("Bearer {t}"))])?
+            }
+            None => self.clone(),
+        };
+
+        downloader
+            .download_with_sha(artifact.url.clone(), artifact.sha256, chan)
+            .await
+    }
+
+    /// Stream the image layer of an OCI artifact to pipe while downloading. Verifies the digest
+    /// and saves to cache same as [`download_to_stream`](Self::download_to_stream).
+    pub async fn download_oci_to_stream(
+        &self,
+        artifact: &Artifact,
+        writer: bb_helper::file_stream::WriterFileStream,
+    ) -> io::Result<()> {
+        let downloader = match &artifact.token {
+            Some(t) => {
+                self.with_headers(&artifact.url, [("Authorization", format!("Bearer {t}"))])?
+            }
+            None => self.clone(),
+        };
+
+        downloader
+            .download_to_stream(artifact.url.clone(), artifact.sha256, writer)
+            .await
+    }
+
+    /// Get anonymous pull token using `WWW-Authenticate` challenge.
+    async fn oci_token(&self, challenge: &str) -> io::Result<String> {
+        let params = challenge
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| io::Error::other("Registry requires unsupported authentication"))?;
+
+        let mut realm = None;
+        let mut query = Vec::new();
+        for p in split_params(params) {
+            let Some((k, v)) = p.split_once('=') else {
+                continue;
+            };
+            let v = v.trim_matches('"');
+
+            if k.trim() == "realm" {
+                realm = Some(v);
+            } else {
+                query.push((k.trim(), v));
+            }
+        }
+
+        let realm = realm.ok_or_else(|| io::Error::other("Missing realm in auth challenge"))?;
+        let mut url = reqwest::Url::parse(realm).map_err(io::Error::other)?;
+        url.query_pairs_mut().extend_pairs(query);
+
+        let resp: TokenResponse = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(io::Error::other)?
+            .error_for_status()
+            .map_err(io::Error::other)?
+            .json()
+            .await
+            .map_err(io::Error::other)?;
+
+        resp.token
+            .or(resp.access_token)
+            .ok_or_else(|| io::Error::other("Registry did not return a token"))
+    }
+}
+
+/// Split comma separated params while respecting quotes.
+fn split_params(params: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in params.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                res.push(params[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    res.push(params[start..].trim());
+
+    res
+}
+
+fn parse_digest(digest: &str) -> io::Result<[u8; 32]> {
+    digest
+        .strip_prefix("sha256:")
+        .and_then(|x| const_hex::decode_to_array(x).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Unsupported OCI digest"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference() {
+        let r: Reference = "ghcr.io/beagleboard/image:v1.0".parse().unwrap();
+        assert_eq!(r.registry, "ghcr.io");
+        assert_eq!(r.repository, "beagleboard/image");
+        assert_eq!(r.reference, "v1.0");
+        assert_eq!(r.to_string(), "ghcr.io/beagleboard/image:v1.0");
+
+        let r: Reference = "oci://localhost:5000/image".parse().unwrap();
+        assert_eq!(r.registry, "localhost:5000");
+        assert_eq!(r.repository, "image");
+        assert_eq!(r.reference, "latest");
+
+        let r: Reference = "ubuntu".parse().unwrap();
+        assert_eq!(r.registry, DOCKER_HUB);
+        assert_eq!(r.repository, "library/ubuntu");
+
+        let digest = "sha256:f0ad929cd259957e160ea442eb80986b5f01b4a1b3c9d5c9cb4d0fcb4ba08148";
+        let r: Reference = format!("ghcr.io/org/img@{digest}").parse().unwrap();
+        assert_eq!(r.reference, digest);
+        assert!(r.digest().is_some());
+        assert_eq!(r.to_string(), format!("ghcr.io/org/img@{digest}"));
+    }
+
+    #[test]
+    fn auth_params() {
+        assert_eq!(
+            split_params(
+                r#"realm="https://ghcr.io/token",service="ghcr.io",scope="repository:a/b:pull""#
+            ),
+            [
+                r#"realm="https://ghcr.io/token""#,
+                r#"service="ghcr.io""#,
+                r#"scope="repository:a/b:pull""#
+            ]
+        );
+    }
+}
@@ -1,83 +1,213 @@
-#[derive(Debug, Default, Clone)]
-/// Mountpoints of a drive
-pub struct MountPoint {
-    pub path: String,
-    pub label: Option<String>,
-    pub total_bytes: Option<u64>,
-    pub available_bytes: Option<u64>,
-}
-
-impl MountPoint {
-    pub fn new(path: impl ToString) -> Self {
-        Self {
-            path: path.to_string(),
-            label: None,
-            total_bytes: None,
-            available_bytes: None,
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
-/// Device Description
-pub struct DeviceDescriptor {
-    pub enumerator: String,
-    pub bus_type: Option<String>,
-    pub bus_version: Option<String>,
-    pub device: String,
-    pub device_path: Option<String>,
-    pub raw: String,
-    pub description: String,
-    pub error: Option<String>,
-    pub partition_table_type: Option<String>,
-    pub size: Option<u64>,
-    pub block_size: u32,
-    pub logical_block_size: u32,
-    pub mountpoints: Vec<MountPoint>,
-    pub mountpoint_labels: Vec<String>,
-    /// Device is read-only
-    pub is_readonly: bool,
-    /// Device is a system drive
-    pub is_system: bool,
-    /// Device is an SD-card
-    pub is_card: bool,
-    /// Connected via the Small Computer System Interface (SCSI)
-    pub is_scsi: bool,
-    /// Connected via Universal Serial Bus (USB)
-    pub is_usb: bool,
-    /// Device is a virtual storage device
-    pub is_virtual: bool,
-    /// Device is removable from the running system
-    pub is_removable: bool,
-    /// Connected via the USB Attached SCSI (UAS)
-    pub is_uas: Option<bool>,
-}
-
-impl Default for DeviceDescriptor {
-    fn default() -> Self {
-        Self {
-            block_size: 512,
-            logical_block_size: 512,
-            enumerator: Default::default(),
-            bus_type: Default::default(),
-            bus_version: Default::default(),
-            device: Default::default(),
-            device_path: Default::default(),
-            raw: Default::default(),
-            description: Default::default(),
-            error: Default::default(),
-            partition_table_type: Default::default(),
-            size: Default::default(),
-            mountpoints: Default::default(),
-            mountpoint_labels: Default::default(),
-            is_readonly: Default::default(),
-            is_system: Default::default(),
-            is_card: Default::default(),
-            is_scsi: Default::default(),
-            is_usb: Default::default(),
-            is_virtual: Default::default(),
-            is_removable: Default::default(),
-            is_uas: Default::default(),
-        }
-    }
-}
+#[derive(Debug, Default, Clone)]
+/// Mountpoints of a drive
+pub struct MountPoint {
+    pub path: String,
+    pub label: Option<String>,
+    pub total_bytes: Option<u64>,
+    pub available_bytes: Option<u64>,
+}
+
+impl MountPoint {
+    pub fn new(path: impl ToString) -> Self {
+        Self {
+            path: path.to_string(),
+            label: None,
+            total_bytes: None,
+            available_bytes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Device Description
+pub struct DeviceDescriptor {
+    pub enumerator: String,
+    pub bus_type: Option<String>,
+    pub bus_version: Option<String>,
+    pub device: String,
+    pub device_path: Option<String>,
+    pub raw: String,
+    pub description: String,
+    pub error: Option<String>,
+    pub partition_table_type: Option<String>,
+    pub size: Option<u64>,
+    pub block_size: u32,
+    pub logical_block_size: u32,
+    pub mountpoints: Vec<MountPoint>,
+    pub mountpoint_labels: Vec<String>,
+    /// Device is read-only
+    pub is_readonly: bool,
+    /// Device is a system drive
+    pub is_system: bool,
+    /// Device is an SD-card
+    pub is_card: bool,
+    /// Connected via the Small Computer System Interface (SCSI)
+    pub is_scsi: bool,
+    /// Connected via Universal Serial Bus (USB)
+    pub is_usb: bool,
+    /// Device is a virtual storage device
+    pub is_virtual: bool,
+    /// Device is removable from the running system
+    pub is_removable: bool,
+    /// Connected via the USB Attached SCSI (UAS)
+    pub is_uas: Option<bool>,
+    /// USB port the device is connected to. Only available on Linux.
+    pub usb_location: Option<UsbLocation>,
+    /// Product string of the USB device, such as the card reader model. Only available on Linux.
+    pub usb_product: Option<String>,
+    /// Registers of SD Cards in MMC host controllers. Only available on Linux, since USB card
+    /// readers do not expose them.
+    pub card: Option<CardInfo>,
+}
+
+impl Default for DeviceDescriptor {
+    fn default() -> Self {
+        Self {
+            block_size: 512,
+            logical_block_size: 512,
+            enumerator: Default::default(),
+            bus_type: Default::default(),
+            bus_version: Default::default(),
+            device: Default::default(),
+            device_path: Default::default(),
+            raw: Default::default(),
+            description: Default::default(),
+            error: Default::default(),
+            partition_table_type: Default::default(),
+            size: Default::default(),
+            mountpoints: Default::default(),
+            mountpoint_labels: Default::default(),
+            is_readonly: Default::default(),
+            is_system: Default::default(),
+            is_card: Default::default(),
+            is_scsi: Default::default(),
+            is_usb: Default::default(),
+            is_virtual: Default::default(),
+            is_removable: Default::default(),
+            is_uas: Default::default(),
+            usb_location: Default::default(),
+            usb_product: Default::default(),
+            card: Default::default(),
+        }
+    }
+}
+
+/// Information from the card identification (CID) and card specific data (CSD) registers of an
+/// SD Card. Counterfeit cards often report a manufacturer or OEM id that does not match the
+/// brand on the label.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CardInfo {
+    /// Manufacturer id assigned by the SD Association.
+    pub manufacturer_id: u8,
+    /// OEM or application id. Usually two ASCII characters.
+    pub oem_id: String,
+    /// Product name.
+    pub name: String,
+    /// Product revision as (major, minor).
+    pub revision: (u8, u8),
+    pub serial: u32,
+    /// Year and month of manufacture.
+    pub manufactured: (u16, u8),
+    /// Capacity class from the CSD register, if available.
+    pub class: Option<CardClass>,
+}
+
+impl CardInfo {
+    /// Name of the manufacturer for well known manufacturer ids.
+    pub const fn manufacturer(&self) -> Option<&'static str> {
+        match self.manufacturer_id {
+            0x01 => Some("Panasonic"),
+            0x02 => Some("Toshiba"),
+            0x03 => Some("SanDisk"),
+            0x1b => Some("Samsung"),
+            0x1d => Some("ADATA"),
+            0x27 => Some("Phison"),
+            0x28 => Some("Lexar"),
+            0x31 => Some("Silicon Power"),
+            0x41 => Some("Kingston"),
+            0x74 => Some("Transcend"),
+            0x76 => Some("Patriot"),
+            0x82 => Some("Sony"),
+            _ => None,
+        }
+    }
+}
+
+/// SD Card capacity class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CardClass {
+    /// Standard capacity, up to 2 GB.
+    Sdsc,
+    /// High capacity, up to 32 GB.
+    Sdhc,
+    /// Extended capacity, up to 2 TB.
+    Sdxc,
+    /// Ultra capacity, up to 128 TB.
+    Sduc,
+}
+
+impl std::fmt::Display for CardClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sdsc => write!(f, "SDSC"),
+            Self::Sdhc => write!(f, "SDHC"),
+            Self::Sdxc => write!(f, "SDXC"),
+            Self::Sduc => write!(f, "SDUC"),
+        }
+    }
+}
+
+/// Physical location of a USB device. Identifies the port a device is plugged into, so that
+/// identical devices can be told apart.
+///
+/// Formatted the same as Linux sysfs, i.e. `<bus>-<port>.<port>...` with one port for each hub
+/// between the root hub and the device.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UsbLocation {
+    pub bus: u8,
+    /// Port on each hub, starting from the root hub.
+    pub ports: Vec<u8>,
+}
+
+impl UsbLocation {
+    /// Location of the hub the device is connected to. [`None`] for devices connected directly to
+    /// the root hub.
+    pub fn hub(&self) -> Option<Self> {
+        let (_, ports) = self.ports.split_last()?;
+        if ports.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            bus: self.bus,
+            ports: ports.to_vec(),
+        })
+    }
+}
+
+impl std::fmt::Display for UsbLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-", self.bus)?;
+        for (i, p) in self.ports.iter().enumerate() {
+            if i != 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{p}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for UsbLocation {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Fails with an empty string error if there is no port
+        let (bus, ports) = s.split_once('-').unwrap_or((s, ""));
+
+        Ok(Self {
+            bus: bus.parse()?,
+            ports: ports.split('.').map(str::parse).collect::<Result<_, _>>()?,
+        })
+    }
+}
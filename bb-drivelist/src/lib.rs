@@ -8,7 +8,7 @@ mod device;
 
 mod pal;
 
-pub use device::{DeviceDescriptor, MountPoint};
+pub use device::{CardClass, CardInfo, DeviceDescriptor, MountPoint, UsbLocation};
 
 /// Get a list of all drives
 pub fn drive_list() -> anyhow::Result<Vec<DeviceDescriptor>> {
@@ -1,6 +1,9 @@
-use std::process::Command;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-use crate::device::{DeviceDescriptor, MountPoint};
+use crate::device::{CardClass, CardInfo, DeviceDescriptor, MountPoint, UsbLocation};
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -154,7 +157,118 @@ pub(crate) fn lsblk() -> anyhow::Result<Vec<DeviceDescriptor>> {
 
     let res: Devices = serde_json::from_slice(&output.stdout).unwrap();
 
-    Ok(res.blockdevices.into_iter().map(Into::into).collect())
+    Ok(res
+        .blockdevices
+        .into_iter()
+        .map(Into::into)
+        .map(with_usb_info)
+        .map(with_card_info)
+        .collect())
+}
+
+fn with_usb_info(mut dev: DeviceDescriptor) -> DeviceDescriptor {
+    if dev.is_usb
+        && let Some(usb) = usb_sysfs_path(&dev.raw)
+    {
+        dev.usb_location = usb_location(&usb);
+        dev.usb_product = std::fs::read_to_string(usb.join("product"))
+            .ok()
+            .map(|x| x.trim().to_string());
+    }
+
+    dev
+}
+
+fn with_card_info(mut dev: DeviceDescriptor) -> DeviceDescriptor {
+    let Some(name) = Path::new(&dev.raw).file_name() else {
+        return dev;
+    };
+    let mmc = Path::new("/sys/class/block").join(name).join("device");
+    let read = |x: &str| {
+        std::fs::read_to_string(mmc.join(x))
+            .ok()
+            .map(|x| x.trim().to_string())
+    };
+
+    // eMMC uses a different CID layout
+    if read("type").as_deref() == Some("SD") {
+        dev.card = read("cid").and_then(|cid| card_info(&cid, read("csd").as_deref()));
+    }
+
+    dev
+}
+
+fn hex_register(s: &str) -> Option<[u8; 16]> {
+    if s.len() != 32 || !s.is_ascii() {
+        return None;
+    }
+
+    let mut res = [0u8; 16];
+    for (r, i) in res.iter_mut().zip((0..32).step_by(2)) {
+        *r = u8::from_str_radix(&s[i..i + 2], 16).ok()?;
+    }
+    Some(res)
+}
+
+fn register_str(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_matches(|x: char| x == '\0' || x.is_whitespace())
+        .to_string()
+}
+
+/// Parse the CID and CSD registers of an SD Card, as exposed by sysfs in hex.
+fn card_info(cid: &str, csd: Option<&str>) -> Option<CardInfo> {
+    let cid = hex_register(cid)?;
+
+    let class = csd
+        .and_then(hex_register)
+        .and_then(|csd| match csd[0] >> 6 {
+            0 => Some(CardClass::Sdsc),
+            1 => {
+                let c_size =
+                    (u32::from(csd[7] & 0x3f) << 16) | (u32::from(csd[8]) << 8) | u32::from(csd[9]);
+                // Capacity is (c_size + 1) * 512 KiB
+                if c_size > 0xffff {
+                    Some(CardClass::Sdxc)
+                } else {
+                    Some(CardClass::Sdhc)
+                }
+            }
+            2 => Some(CardClass::Sduc),
+            _ => None,
+        });
+
+    Some(CardInfo {
+        manufacturer_id: cid[0],
+        oem_id: register_str(&cid[1..3]),
+        name: register_str(&cid[3..8]),
+        revision: (cid[8] >> 4, cid[8] & 0x0f),
+        serial: u32::from_be_bytes(cid[9..13].try_into().unwrap()),
+        manufactured: (
+            2000 + u16::from(((cid[13] & 0x0f) << 4) | (cid[14] >> 4)),
+            cid[14] & 0x0f,
+        ),
+        class,
+    })
+}
+
+/// Sysfs directory of the USB device a block device belongs to.
+fn usb_sysfs_path(kname: &str) -> Option<PathBuf> {
+    let name = Path::new(kname).file_name()?;
+    let dev =
+        std::fs::canonicalize(Path::new("/sys/class/block").join(name).join("device")).ok()?;
+
+    usb_device_ancestor(&dev).map(Path::to_path_buf)
+}
+
+/// The block device is nested under the USB device, which is named after its location. Eg:
+/// `/sys/devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1.3/2-1.3:1.0/host6/target6:0:0/6:0:0:0`
+fn usb_device_ancestor(dev: &Path) -> Option<&Path> {
+    dev.ancestors().find(|x| usb_location(x).is_some())
+}
+
+fn usb_location(p: &Path) -> Option<UsbLocation> {
+    p.file_name()?.to_str()?.parse().ok()
 }
 
 #[cfg(test)]
@@ -226,4 +340,65 @@ mod tests {
         let res: super::Devices = serde_json::from_str(data).unwrap();
         let _: Vec<DeviceDescriptor> = res.blockdevices.into_iter().map(Into::into).collect();
     }
+
+    #[test]
+    fn usb_location() {
+        let dev = std::path::Path::new(
+            "/sys/devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1.3/2-1.3:1.0/host6/target6:0:0/6:0:0:0",
+        );
+
+        let usb = super::usb_device_ancestor(dev).unwrap();
+        assert_eq!(
+            usb,
+            std::path::Path::new("/sys/devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1.3")
+        );
+
+        let loc = super::usb_location(usb).unwrap();
+        assert_eq!(loc.bus, 2);
+        assert_eq!(loc.ports, [1, 3]);
+        assert_eq!(loc.to_string(), "2-1.3");
+        assert_eq!(loc.hub().unwrap().to_string(), "2-1");
+        assert!(loc.hub().unwrap().hub().is_none());
+    }
+
+    #[test]
+    fn card_info() {
+        let info = super::card_info(
+            "035344534336344780a4b2c5a10135bd",
+            Some("400e00325b590001dbd37f800a404000"),
+        )
+        .unwrap();
+
+        assert_eq!(info.manufacturer(), Some("SanDisk"));
+        assert_eq!(info.oem_id, "SD");
+        assert_eq!(info.name, "SC64G");
+        assert_eq!(info.revision, (8, 0));
+        assert_eq!(info.serial, 0xa4b2_c5a1);
+        assert_eq!(info.manufactured, (2019, 5));
+        assert_eq!(info.class, Some(crate::CardClass::Sdxc));
+
+        let info = super::card_info(
+            "035344534330384780a4b2c5a10135bd",
+            Some("400e00325b5900003b377f800a404000"),
+        )
+        .unwrap();
+        assert_eq!(info.class, Some(crate::CardClass::Sdhc));
+
+        assert!(super::card_info("0353", None).is_none());
+        assert!(
+            super::card_info("035344534336344780a4b2c5a10135bd", None)
+                .unwrap()
+                .class
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn no_usb_location() {
+        let dev = std::path::Path::new(
+            "/sys/devices/platform/soc/fe340000.mmc/mmc_host/mmc0/mmc0:aaaa/block/mmcblk0",
+        );
+
+        assert!(super::usb_device_ancestor(dev).is_none());
+    }
 }
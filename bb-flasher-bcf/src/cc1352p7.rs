@@ -3,19 +3,46 @@
 //!
 //! BSL command details can be found in [Technical Specification].
 //!
+//! # A/B Slots
+//!
+//! The TI ROM bootloader described in the [Technical Specification] has no notion of firmware
+//! slots. Slots are only used when the device runs the BeagleConnect Freedom slot-aware
+//! bootloader, which is detected by the boot marker page described below. On all other devices the
+//! whole flash is replaced, and requesting a slot explicitly is an error.
+//!
+//! Layout used by the slot-aware bootloader (all values little endian):
+//!
+//! | Region      | Start     | Size      | Contents                                        |
+//! |-------------|-----------|-----------|-------------------------------------------------|
+//! | Slot A      | `0x08000` | `0x52000` | Firmware image                                  |
+//! | Slot B      | `0x5a000` | `0x52000` | Firmware image                                  |
+//! | Boot marker | `0xac000` | 1 page    | `u32` magic `0x534c4f54` ("SLOT"), `u32` slot id |
+//!
+//! Slot id `0` is slot A and `1` is slot B. Firmware images start with a header whose first word
+//! is `0x96f3b83d`, and whose 6th and 7th words hold the version (`major`, `minor`, `u16`
+//! revision) and build number.
+//!
+//! New firmware is written to the inactive slot, and the marker is only switched once the write (and
+//! verification, if enabled) succeeds. This leaves the previous firmware intact for roll-back.
+//!
 //! [BeagleConnect Freedom]: https://www.beagleboard.org/boards/beagleconnect-freedom
 //! [CC1352P7]: https://www.ti.com/product/CC1352P7
 //! [Technical Specification]: https://www.ti.com/lit/ug/swcu192/swcu192.pdf?ts=1741089110661&ref_url=https%253A%252F%252Fwww.ti.com%252Fproduct%252FCC1352P7
 
-use std::{io, time::Duration};
+use std::{
+    fmt::Display,
+    io,
+    time::{Duration, Instant},
+};
 
+pub use serialport::FlowControl;
 use serialport::SerialPort;
 use thiserror::Error;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
 use crate::{
-    Status,
+    DeviceVersion, Status,
     helpers::{chan_send, parse_bin},
 };
 
@@ -26,12 +53,26 @@ const COMMAND_DOWNLOAD: u8 = 0x21;
 const COMMAND_GET_STATUS: u8 = 0x23;
 const COMMAND_SEND_DATA: u8 = 0x24;
 const COMMAND_RESET: u8 = 0x25;
+const COMMAND_SECTOR_ERASE: u8 = 0x26;
 const COMMAND_CRC32: u8 = 0x27;
+const COMMAND_GET_CHIP_ID: u8 = 0x28;
+const COMMAND_MEMORY_READ: u8 = 0x2a;
 const COMMAND_BANK_ERASE: u8 = 0x2c;
 
 const COMMAND_MAX_SIZE: u8 = u8::MAX - 3;
 
 const FIRMWARE_SIZE: u32 = 704 * 1024;
+const PAGE_SIZE: u32 = 8 * 1024;
+
+const SLOT_A_ADDR: u32 = 0x8000;
+const SLOT_B_ADDR: u32 = 0x5a000;
+const SLOT_SIZE: u32 = 0x52000;
+const SLOT_MARKER_ADDR: u32 = 0xac000;
+const SLOT_MARKER_MAGIC: u32 = 0x534c_4f54;
+
+const IMAGE_HEADER_MAGIC: u32 = 0x96f3_b83d;
+
+const BOOTLOADER_TIMEOUT: Duration = Duration::from_secs(20);
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -48,8 +89,13 @@ pub enum Error {
     #[error("Bootloader Responded with Nack.")]
     Nack,
     /// Failed to start Bootloader
-    #[error("Failed to start Bootloader.")]
-    FailedToStartBootloader,
+    #[error(
+        "Failed to start Bootloader. Hold the BOOT button while connecting BeagleConnect Freedom and try again."
+    )]
+    FailedToStartBootloader {
+        #[source]
+        source: Option<serialport::Error>,
+    },
     /// Flashed image is not valid
     #[error("Flashed image is not valid.")]
     InvalidImage,
@@ -59,6 +105,12 @@ pub enum Error {
     /// Aborted before completing
     #[error("Aborted before completing.")]
     Aborted,
+    /// Firmware slot requested on a device without an A/B capable bootloader
+    #[error("Firmware slot {0} requested, but the device bootloader does not support slots.")]
+    SlotsNotSupported(Slot),
+    /// Image is too large for a firmware slot
+    #[error("Image does not fit in firmware slot {0}.")]
+    ImageTooLarge(Slot),
     /// Unknown error occured during IO.
     #[error("Unknown Error during IO. Please check logs for more information.")]
     IoError {
@@ -68,6 +120,76 @@ pub enum Error {
     },
 }
 
+/// Firmware slot on devices with an A/B capable bootloader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    /// The other slot, i.e. the one to write new firmware to when `self` is active.
+    pub const fn other(self) -> Self {
+        match self {
+            Self::A => Self::B,
+            Self::B => Self::A,
+        }
+    }
+
+    const fn addr(self) -> u32 {
+        match self {
+            Self::A => SLOT_A_ADDR,
+            Self::B => SLOT_B_ADDR,
+        }
+    }
+
+    const fn id(self) -> u32 {
+        match self {
+            Self::A => 0,
+            Self::B => 1,
+        }
+    }
+
+    const fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::A),
+            1 => Some(Self::B),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::A => write!(f, "A"),
+            Self::B => write!(f, "B"),
+        }
+    }
+}
+
+/// Default baud rate of the serial port, used for flashing, verifying and probing.
+pub const BAUD_RATE: u32 = 115200;
+
+/// Serial port settings used to talk to the bootloader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortConfig {
+    pub baud_rate: u32,
+    pub flow_control: FlowControl,
+    /// Read and write timeout
+    pub timeout: Duration,
+}
+
+impl Default for PortConfig {
+    fn default() -> Self {
+        Self {
+            baud_rate: BAUD_RATE,
+            flow_control: FlowControl::None,
+            timeout: Duration::from_millis(2000),
+        }
+    }
+}
+
 struct BeagleConnectFreedom<S: SerialPort> {
     port: S,
 }
@@ -80,19 +202,34 @@ where
         let mut bcf = BeagleConnectFreedom { port };
 
         const MAX_RETRIES: usize = 3;
-        let mut last_error = None;
+        let start = Instant::now();
+        let mut last_err = None;
 
         for attempt in 1..=MAX_RETRIES {
             info!("Bootloader invocation attempt {}/{}", attempt, MAX_RETRIES);
 
-            match bcf.invoke_bootloader().and_then(|_| bcf.send_sync()) {
+            // Try break sequence first, then DTR/RTS lines for boards wired for them.
+            let resp = bcf
+                .invoke_bootloader()
+                .and_then(|_| bcf.send_sync())
+                .or_else(|e| {
+                    warn!("Break sequence failed: {:?}", e);
+                    bcf.invoke_bootloader_dtr_rts()
+                        .and_then(|_| bcf.send_sync())
+                });
+
+            match resp {
                 Ok(_) => {
                     info!("Successfully started bootloader on attempt {}", attempt);
                     return Ok(bcf);
                 }
                 Err(e) => {
                     warn!("Bootloader invocation attempt {} failed: {:?}", attempt, e);
-                    last_error = Some(e);
+                    last_err = Some(e);
+                    if start.elapsed() > BOOTLOADER_TIMEOUT {
+                        warn!("Timed out while starting bootloader");
+                        break;
+                    }
                     if attempt < MAX_RETRIES {
                         std::thread::sleep(Duration::from_millis(500));
                     }
@@ -100,7 +237,10 @@ where
             }
         }
 
-        Err(last_error.unwrap_or(Error::FailedToStartBootloader))
+        match last_err {
+            Some(e @ Error::FailedToStartBootloader { .. }) => Err(e),
+            _ => Err(Error::FailedToStartBootloader { source: None }),
+        }
     }
 
     fn wait_for_ack(&mut self) -> Result<()> {
@@ -122,16 +262,36 @@ where
 
         self.port
             .set_break()
-            .map_err(|_| Error::FailedToStartBootloader)?;
+            .map_err(|e| Error::FailedToStartBootloader { source: Some(e) })?;
         std::thread::sleep(Duration::from_secs(2));
         self.port
             .clear_break()
-            .map_err(|_| Error::FailedToStartBootloader)?;
+            .map_err(|e| Error::FailedToStartBootloader { source: Some(e) })?;
 
         std::thread::sleep(Duration::from_millis(500));
         Ok(())
     }
 
+    /// Enter bootloader using DTR (bootloader backdoor pin) and RTS (reset) lines.
+    fn invoke_bootloader_dtr_rts(&mut self) -> Result<()> {
+        info!("Invoke Bootloader using DTR/RTS");
+
+        self.port
+            .write_data_terminal_ready(true)
+            .and_then(|_| self.port.write_request_to_send(true))
+            .map_err(|e| Error::FailedToStartBootloader { source: Some(e) })?;
+        std::thread::sleep(Duration::from_millis(100));
+        self.port
+            .write_request_to_send(false)
+            .map_err(|e| Error::FailedToStartBootloader { source: Some(e) })?;
+        std::thread::sleep(Duration::from_millis(500));
+        self.port
+            .write_data_terminal_ready(false)
+            .map_err(|e| Error::FailedToStartBootloader { source: Some(e) })?;
+
+        Ok(())
+    }
+
     fn send_sync(&mut self) -> Result<()> {
         info!("Send Sync");
         const PKT: &[u8] = &[0x55, 0x55];
@@ -141,15 +301,14 @@ where
         self.wait_for_ack()
     }
 
-    fn crc32(&mut self) -> Result<u32> {
-        let addr = 0u32.to_be_bytes();
-        let size = FIRMWARE_SIZE.to_be_bytes();
+    fn crc32(&mut self, addr: u32, size: u32) -> Result<u32> {
+        let addr = addr.to_be_bytes();
+        let size = size.to_be_bytes();
         let read_repeat = 0u32.to_be_bytes();
-        let mut cmd = [0u8; 2];
-        let mut cmd_data = [0u8; 4];
 
-        let checksum: u8 = size
+        let checksum: u8 = addr
             .iter()
+            .chain(&size)
             .chain(&[COMMAND_CRC32])
             .fold(0u8, |acc, t| acc.wrapping_add(*t));
 
@@ -159,6 +318,21 @@ where
         self.port.write_all(&read_repeat)?;
 
         self.wait_for_ack()?;
+        self.read_u32_response()
+    }
+
+    fn chip_id(&mut self) -> Result<u32> {
+        const CMD: &[u8] = &[3, COMMAND_GET_CHIP_ID, COMMAND_GET_CHIP_ID];
+
+        self.port.write_all(CMD)?;
+
+        self.wait_for_ack()?;
+        self.read_u32_response()
+    }
+
+    fn read_u32_response(&mut self) -> Result<u32> {
+        let mut cmd = [0u8; 2];
+        let mut cmd_data = [0u8; 4];
 
         self.port.read_exact(&mut cmd)?;
         assert_eq!(cmd[0], 6);
@@ -190,6 +364,96 @@ where
         self.get_status()
     }
 
+    fn send_sector_erase(&mut self, addr: u32) -> Result<(), Error> {
+        let addr = addr.to_be_bytes();
+
+        let checksum: u8 = addr
+            .iter()
+            .chain(&[COMMAND_SECTOR_ERASE])
+            .fold(0u8, |acc, t| acc.wrapping_add(*t));
+
+        self.port.write_all(&[7, checksum, COMMAND_SECTOR_ERASE])?;
+        self.port.write_all(&addr)?;
+
+        self.wait_for_ack()?;
+        self.get_status()
+    }
+
+    /// Read `count` 32-bit words starting at `addr`.
+    fn memory_read(&mut self, addr: u32, count: u8) -> Result<Vec<u32>> {
+        const ACCESS_32BIT: u8 = 1;
+
+        let addr = addr.to_be_bytes();
+        let mut cmd = [0u8; 2];
+
+        let checksum: u8 = addr
+            .iter()
+            .chain(&[ACCESS_32BIT, count, COMMAND_MEMORY_READ])
+            .fold(0u8, |acc, t| acc.wrapping_add(*t));
+
+        self.port.write_all(&[9, checksum, COMMAND_MEMORY_READ])?;
+        self.port.write_all(&addr)?;
+        self.port.write_all(&[ACCESS_32BIT, count])?;
+
+        self.wait_for_ack()?;
+
+        self.port.read_exact(&mut cmd)?;
+        if usize::from(cmd[0]) != usize::from(count) * 4 + 2 {
+            return Err(Error::UnknownResponse);
+        }
+
+        let mut data = vec![0u8; usize::from(count) * 4];
+        self.port.read_exact(&mut data)?;
+        if cmd[1] != data.iter().fold(0u8, |acc, x| acc.wrapping_add(*x)) {
+            return Err(Error::UnknownResponse);
+        }
+
+        self.send_ack()?;
+
+        Ok(data
+            .chunks_exact(4)
+            .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+            .collect())
+    }
+
+    /// Returns the active slot. [`None`] if the bootloader is not A/B capable.
+    fn active_slot(&mut self) -> Result<Option<Slot>> {
+        let marker = self.memory_read(SLOT_MARKER_ADDR, 2)?;
+
+        if marker[0] != SLOT_MARKER_MAGIC {
+            return Ok(None);
+        }
+
+        Ok(Slot::from_id(marker[1]))
+    }
+
+    /// Read firmware version from the image header at `addr`. [`None`] if there is no valid
+    /// header.
+    fn firmware_version(&mut self, addr: u32) -> Result<Option<String>> {
+        let header = self.memory_read(addr, 7)?;
+
+        if header[0] != IMAGE_HEADER_MAGIC {
+            return Ok(None);
+        }
+
+        let [major, minor, rev_lo, rev_hi] = header[5].to_le_bytes();
+        let revision = u16::from_le_bytes([rev_lo, rev_hi]);
+
+        Ok(Some(format!("{major}.{minor}.{revision}+{}", header[6])))
+    }
+
+    fn mark_for_boot(&mut self, slot: Slot) -> Result<()> {
+        let mut marker = [0u8; 8];
+        marker[..4].copy_from_slice(&SLOT_MARKER_MAGIC.to_le_bytes());
+        marker[4..].copy_from_slice(&slot.id().to_le_bytes());
+
+        self.send_sector_erase(SLOT_MARKER_ADDR)?;
+        self.send_download(SLOT_MARKER_ADDR, marker.len() as u32)?;
+        self.send_data(&marker)?;
+
+        Ok(())
+    }
+
     fn get_status(&mut self) -> Result<(), Error> {
         const CMD: &[u8] = &[3, COMMAND_GET_STATUS, COMMAND_GET_STATUS];
         let mut resp = [0u8; 1];
@@ -260,8 +524,8 @@ where
         self.wait_for_ack()
     }
 
-    fn verify(&mut self, crc32: u32) -> Result<bool> {
-        let actual_crc32 = self.crc32()?;
+    fn verify(&mut self, addr: u32, size: u32, crc32: u32) -> Result<bool> {
+        let actual_crc32 = self.crc32(addr, size)?;
         info!(
             "CRC32 verification: expected={:08x}, actual={:08x}",
             crc32, actual_crc32
@@ -279,8 +543,8 @@ where
     }
 }
 
-const fn progress(off: usize) -> f32 {
-    (off as f32) / (FIRMWARE_SIZE as f32)
+const fn progress(off: usize, size: u32) -> f32 {
+    (off as f32) / (size as f32)
 }
 
 fn check_token(cancel: Option<&tokio_util::sync::CancellationToken>) -> Result<()> {
@@ -300,6 +564,13 @@ fn check_token(cancel: Option<&tokio_util::sync::CancellationToken>) -> Result<(
 /// - Ti-TXT
 /// - Intel Hex
 ///
+/// # Slots
+///
+/// If the device bootloader is A/B capable and the firmware fits in a slot, it is written to the
+/// inactive slot, which is then marked for boot. `slot` can be used to override the detected
+/// slot, but only on devices with an A/B capable bootloader. Otherwise, the whole flash is
+/// replaced.
+///
 /// # Aborting
 ///
 /// The process can be aborted by dropping all strong references to the [`Arc`] that owns the
@@ -310,7 +581,9 @@ fn check_token(cancel: Option<&tokio_util::sync::CancellationToken>) -> Result<(
 pub fn flash(
     firmware: &[u8],
     port: &str,
+    config: PortConfig,
     verify: bool,
+    slot: Option<Slot>,
     mut chan: Option<mpsc::Sender<Status>>,
     cancel: Option<tokio_util::sync::CancellationToken>,
 ) -> Result<()> {
@@ -318,14 +591,158 @@ pub fn flash(
 
     chan_send(chan.as_mut(), Status::Preparing);
 
-    let port = serialport::new(port, 115200)
-        .timeout(Duration::from_millis(2000))
+    let mut bcf = open(port, config)?;
+
+    check_token(cancel.as_ref())?;
+
+    let fits_in_slot = fits_in_slot(&firmware_bin);
+
+    let Some(active) = bcf.active_slot()? else {
+        return match slot {
+            Some(x) => Err(Error::SlotsNotSupported(x)),
+            None => flash_full(&mut bcf, &firmware_bin, verify, chan, cancel),
+        };
+    };
+    info!("Active slot: {}", active);
+
+    match slot {
+        Some(x) if fits_in_slot => {
+            flash_slot(&mut bcf, &firmware_bin, x, active, verify, chan, cancel)
+        }
+        Some(x) => Err(Error::ImageTooLarge(x)),
+        None if fits_in_slot => flash_slot(
+            &mut bcf,
+            &firmware_bin,
+            active.other(),
+            active,
+            verify,
+            chan,
+            cancel,
+        ),
+        None => {
+            warn!("Image does not fit in a slot. Replacing whole flash");
+            flash_full(&mut bcf, &firmware_bin, verify, chan, cancel)
+        }
+    }
+}
+
+/// Compare firmware on BeagleConnect Freedom with `firmware` without writing anything. Returns
+/// `true` if the firmware matches.
+///
+/// On devices with an A/B capable bootloader, the active slot is compared unless `slot` is
+/// provided. Firmware which does not fit in a slot is compared against the whole flash.
+/// Providing `slot` on devices without an A/B capable bootloader is an error.
+pub fn verify(firmware: &[u8], port: &str, config: PortConfig, slot: Option<Slot>) -> Result<bool> {
+    let firmware_bin = parse_bin(firmware).map_err(|_| Error::InvalidImage)?;
+
+    let mut bcf = open(port, config)?;
+
+    let active = bcf.active_slot()?;
+    let slot = match (slot, fits_in_slot(&firmware_bin)) {
+        (Some(x), _) if active.is_none() => return Err(Error::SlotsNotSupported(x)),
+        (Some(x), true) => Some(x),
+        (Some(x), false) => return Err(Error::ImageTooLarge(x)),
+        (None, true) => active,
+        (None, false) => None,
+    };
+
+    let (addr, size) = match slot {
+        Some(x) => {
+            info!("Compare Slot {}", x);
+            (x.addr(), SLOT_SIZE)
+        }
+        None => (0, FIRMWARE_SIZE),
+    };
+
+    let img_crc32 = crc32fast::hash(
+        &firmware_bin
+            .to_bytes(0..(size as usize), Some(0xff))
+            .expect("Unexpected error"),
+    );
+
+    bcf.verify(addr, size, img_crc32)
+}
+
+/// Query bootloader and firmware version from BeagleConnect Freedom.
+///
+/// Firmware version is read from the image header, which is only present in firmware built for
+/// the A/B capable bootloader.
+pub fn probe(port: &str, config: PortConfig) -> Result<DeviceVersion> {
+    let mut bcf = open(port, config)?;
+
+    let chip_id = bcf.chip_id()?;
+    let firmware = match bcf.active_slot()? {
+        Some(slot) => bcf
+            .firmware_version(slot.addr())?
+            .map(|x| format!("{x} (Slot {slot})")),
+        None => None,
+    };
+
+    Ok(DeviceVersion {
+        bootloader: Some(format!("ROM BSL (Chip ID {chip_id:08x})")),
+        firmware,
+    })
+}
+
+/// Toggle the DTR line of the serial port for `duration`, which blinks the activity LED of most
+/// USB to UART bridges.
+///
+/// RTS (reset) is never asserted, so the firmware keeps running and the device does not enter
+/// the bootloader.
+pub fn identify(port: &str, duration: Duration) -> Result<()> {
+    const INTERVAL: Duration = Duration::from_millis(250);
+
+    info!("Identify {}", port);
+
+    let mut port = serialport::new(port, BAUD_RATE)
         .open_native()
         .map_err(|_| Error::FailedToOpenPort)?;
-    let mut bcf = BeagleConnectFreedom::new(port)?;
+
+    let start = Instant::now();
+    let mut level = true;
+    while start.elapsed() < duration {
+        port.write_data_terminal_ready(level)
+            .map_err(io::Error::other)?;
+        level = !level;
+        std::thread::sleep(INTERVAL);
+    }
+
+    port.write_data_terminal_ready(false)
+        .map_err(io::Error::other)?;
+
+    Ok(())
+}
+
+fn open(port: &str, config: PortConfig) -> Result<BeagleConnectFreedom<impl SerialPort>> {
+    info!("Open {} at {} baud", port, config.baud_rate);
+
+    let port = serialport::new(port, config.baud_rate)
+        .flow_control(config.flow_control)
+        .timeout(config.timeout)
+        .open_native()
+        .map_err(|_| Error::FailedToOpenPort)?;
+    let bcf = BeagleConnectFreedom::new(port)?;
     info!("BeagleConnectFreedom Connected");
 
-    check_token(cancel.as_ref())?;
+    Ok(bcf)
+}
+
+fn fits_in_slot(firmware_bin: &bin_file::BinFile) -> bool {
+    firmware_bin
+        .segments_list()
+        .into_iter()
+        .map(|(addr, data)| addr + data.len())
+        .max()
+        .is_none_or(|x| x <= SLOT_SIZE as usize)
+}
+
+fn flash_full<S: SerialPort>(
+    bcf: &mut BeagleConnectFreedom<S>,
+    firmware_bin: &bin_file::BinFile,
+    verify: bool,
+    mut chan: Option<mpsc::Sender<Status>>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
     chan_send(chan.as_mut(), Status::Flashing(0.0));
 
     let img_crc32 = crc32fast::hash(
@@ -333,7 +750,7 @@ pub fn flash(
             .to_bytes(0..(FIRMWARE_SIZE as usize), Some(0xff))
             .expect("Unexpected error"),
     );
-    if bcf.verify(img_crc32)? {
+    if bcf.verify(0, FIRMWARE_SIZE, img_crc32)? {
         warn!("Skipping flashing same image");
         return Ok(());
     }
@@ -357,7 +774,7 @@ pub fn flash(
 
             chan_send(
                 chan.as_mut(),
-                Status::Flashing(progress(start_address + offset)),
+                Status::Flashing(progress(start_address + offset, FIRMWARE_SIZE)),
             );
             check_token(cancel.as_ref())?;
         }
@@ -367,7 +784,7 @@ pub fn flash(
         chan_send(chan.as_mut(), Status::Verifying);
         // small delay to ensure flash operations are fully complete
         std::thread::sleep(Duration::from_millis(100));
-        if bcf.verify(img_crc32)? {
+        if bcf.verify(0, FIRMWARE_SIZE, img_crc32)? {
             info!("Flashing Successful");
             Ok(())
         } else {
@@ -379,23 +796,111 @@ pub fn flash(
     }
 }
 
+/// Write firmware to `slot` and mark it for boot. Image addresses are relative to the slot start.
+///
+/// Nothing is written if the `active` slot already contains the image. If only `slot` contains
+/// it, it is marked for boot without writing.
+fn flash_slot<S: SerialPort>(
+    bcf: &mut BeagleConnectFreedom<S>,
+    firmware_bin: &bin_file::BinFile,
+    slot: Slot,
+    active: Slot,
+    verify: bool,
+    mut chan: Option<mpsc::Sender<Status>>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    chan_send(chan.as_mut(), Status::Flashing(0.0));
+
+    let img_crc32 = crc32fast::hash(
+        &firmware_bin
+            .to_bytes(0..(SLOT_SIZE as usize), Some(0xff))
+            .expect("Unexpected error"),
+    );
+    if bcf.verify(active.addr(), SLOT_SIZE, img_crc32)? {
+        warn!("Skipping flashing same image");
+        return Ok(());
+    }
+    if slot != active && bcf.verify(slot.addr(), SLOT_SIZE, img_crc32)? {
+        warn!(
+            "Slot {} already contains the image. Marking it for boot",
+            slot
+        );
+        bcf.mark_for_boot(slot)?;
+        return Ok(());
+    }
+
+    check_token(cancel.as_ref())?;
+    info!("Erase Slot {}", slot);
+    for page in (0..SLOT_SIZE).step_by(PAGE_SIZE as usize) {
+        bcf.send_sector_erase(slot.addr() + page)?;
+        check_token(cancel.as_ref())?;
+    }
+
+    info!("Start Flashing Slot {}", slot);
+    for (start_address, data) in firmware_bin.segments_list() {
+        let mut offset = 0;
+        let addr: u32 = start_address.try_into().expect("Unexpected error");
+
+        bcf.send_download(
+            slot.addr() + addr,
+            data.len().try_into().expect("Unexpected error"),
+        )?;
+        while offset < data.len() {
+            offset += bcf.send_data(&data[offset..])?;
+
+            chan_send(
+                chan.as_mut(),
+                Status::Flashing(progress(start_address + offset, SLOT_SIZE)),
+            );
+            check_token(cancel.as_ref())?;
+        }
+    }
+
+    if verify {
+        chan_send(chan.as_mut(), Status::Verifying);
+        // small delay to ensure flash operations are fully complete
+        std::thread::sleep(Duration::from_millis(100));
+        if !bcf.verify(slot.addr(), SLOT_SIZE, img_crc32)? {
+            error!(
+                "Invalid CRC32 in Slot {}. Keeping current slot active",
+                slot
+            );
+            return Err(Error::InvalidImage);
+        }
+    }
+
+    check_token(cancel.as_ref())?;
+    info!("Mark Slot {} for boot", slot);
+    bcf.mark_for_boot(slot)?;
+
+    info!("Flashing Successful");
+    Ok(())
+}
+
 /// Returns all paths to ports having BeagleConnect Freedom.
-pub fn ports(filter: bool) -> std::collections::HashSet<String> {
+///
+/// When `filter` is set, only USB ports are returned. If `usb_ids` (VID, PID) is not empty, the
+/// port must match one of them. Else, on Linux, the USB manufacturer and product strings are used.
+pub fn ports(filter: bool, usb_ids: &[(u16, u16)]) -> std::collections::HashSet<String> {
     serialport::available_ports()
         .expect("Unsupported OS")
         .into_iter()
         .filter(|x| {
-            if filter && cfg!(target_os = "linux") {
-                return match &x.port_type {
-                    serialport::SerialPortType::UsbPort(y) => {
-                        y.manufacturer.as_deref() == Some("BeagleBoard.org")
-                            && y.product.as_deref() == Some("BeagleConnect")
-                    }
-                    _ => false,
-                };
+            if !filter {
+                return true;
             }
 
-            true
+            match &x.port_type {
+                serialport::SerialPortType::UsbPort(y) if !usb_ids.is_empty() => {
+                    usb_ids.contains(&(y.vid, y.pid))
+                }
+                serialport::SerialPortType::UsbPort(y) if cfg!(target_os = "linux") => {
+                    y.manufacturer.as_deref() == Some("BeagleBoard.org")
+                        && y.product.as_deref() == Some("BeagleConnect")
+                }
+                serialport::SerialPortType::UsbPort(_) => true,
+                _ => false,
+            }
         })
         .map(|x| x.port_name)
         .collect()
@@ -17,3 +17,12 @@ pub enum Status {
     Flashing(f32),
     Verifying,
 }
+
+/// Version information read from a device
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeviceVersion {
+    /// Bootloader version. [`None`] if it could not be determined.
+    pub bootloader: Option<String>,
+    /// Firmware version. [`None`] if it could not be determined.
+    pub firmware: Option<String>,
+}
@@ -16,12 +16,14 @@ use thiserror::Error;
 use tokio::sync::mpsc;
 
 use crate::{
-    Status,
+    DeviceVersion, Status,
     helpers::{chan_send, parse_bin},
 };
 
-const VID: u16 = 0x2047;
-const PID: u16 = 0x0200;
+/// USB Vendor ID of MSP430 in BSL mode.
+pub const VID: u16 = 0x2047;
+/// USB Product ID of MSP430 in BSL mode.
+pub const PID: u16 = 0x0200;
 
 const USB_MSG_HEADER: u8 = 0x3f;
 
@@ -33,9 +35,13 @@ const BSL_START_ADDR: [u8; 3] = three_bytes(0x2504);
 
 const CMD_RX_DATA_BLOCK_FAST: u8 = 0x1b;
 const CMD_RX_PASSWORD: u8 = 0x11;
+const CMD_CRC_CHECK: u8 = 0x16;
 const CMD_LOAD_PC: u8 = 0x17;
 const CMD_TX_BSL_VERSION: u8 = 0x19;
 
+const PASSWORD_ADDR: usize = 0xffe0;
+const CRC_CHECK_MAX_SIZE: usize = 4096;
+
 const fn three_bytes(x: usize) -> [u8; 3] {
     let temp = x.to_le_bytes();
     [temp[0], temp[1], temp[2]]
@@ -49,6 +55,12 @@ pub enum Error {
     /// Could not unlock the BSL. Maybe a custom password is being used.
     #[error("Failed to unlock BSL.")]
     UnlockFail,
+    /// Password of the firmware on the device is not known. Unlocking the BSL with a wrong
+    /// password mass erases the flash.
+    #[error(
+        "BSL password of the firmware on the device is not known. Refusing to unlock, since a wrong password erases the device."
+    )]
+    PasswordUnknown,
     /// Failed to erase flash.
     #[error("Failed to perform mass erase.")]
     MassEraseFail,
@@ -58,6 +70,9 @@ pub enum Error {
     /// BSL version request failed.
     #[error("Failed to read BSL Version.")]
     BslVersionFail,
+    /// CRC check request failed.
+    #[error("Failed to read CRC.")]
+    CrcCheckFail,
     // Firmware is not valid.
     #[error("Firmware is not valid")]
     InvalidFirmware,
@@ -67,6 +82,9 @@ pub enum Error {
         #[source]
         source: hidapi::HidError,
     },
+    /// MSP430 is not connected.
+    #[error("MSP430 not found")]
+    DeviceNotFound,
     /// Failed to open MSP430.
     #[error("Failed to open MSP430")]
     FailedToOpenDestination {
@@ -97,12 +115,16 @@ impl MSP430 {
         let req = Self::request(cmd, data);
         self.0.write(&req)?;
 
-        let _ = self.0.read(&mut ans)?;
+        let count = self.0.read(&mut ans)?;
 
-        assert_eq!(ans[0], USB_MSG_HEADER);
-        let length = ans[1];
+        let length = usize::from(ans[1]);
+        if count < 2 || ans[0] != USB_MSG_HEADER || 2 + length > count {
+            return Err(hidapi::HidError::HidApiError {
+                message: format!("Invalid response from BSL: {:x?}", &ans[..count]),
+            });
+        }
 
-        Ok(ans[2..(2 + length as usize)].to_vec())
+        Ok(ans[2..(2 + length)].to_vec())
     }
 
     fn mass_erase(&self) -> Result<()> {
@@ -117,21 +139,33 @@ impl MSP430 {
             )
             .map_err(|_| Error::MassEraseFail)?;
 
-        assert_eq!(ans.len(), 2);
-        assert_ne!(ans[1], 0);
+        // Wrong password is expected, which triggers the mass erase
+        if ans.len() != 2 || ans[1] == 0 {
+            return Err(Error::MassEraseFail);
+        }
 
         Ok(())
     }
 
     fn unlock(&self) -> Result<()> {
+        if self.unlock_with(&[0xffu8; 32])? {
+            Ok(())
+        } else {
+            Err(Error::UnlockFail)
+        }
+    }
+
+    /// Returns `false` if the password was rejected.
+    fn unlock_with(&self, password: &[u8]) -> Result<bool> {
         let ans = self
-            .cmd(CMD_RX_PASSWORD, &[0xffu8; 32])
+            .cmd(CMD_RX_PASSWORD, password)
             .map_err(|_| Error::UnlockFail)?;
 
-        assert_eq!(ans.len(), 2);
-        assert_eq!(ans[1], 0);
+        if ans.len() != 2 {
+            return Err(Error::UnlockFail);
+        }
 
-        Ok(())
+        Ok(ans[1] == 0)
     }
 
     fn load_pc(&self) -> Result<()> {
@@ -139,13 +173,22 @@ impl MSP430 {
             .map_err(|_| Error::BSLJumpFail)
     }
 
-    fn bsl_version(&self) -> Result<()> {
+    fn bsl_version(&self) -> Result<[u8; 4]> {
         let resp = self
             .cmd(CMD_TX_BSL_VERSION, &[])
             .map_err(|_| Error::BslVersionFail)?;
 
-        assert_eq!(resp[0], 0x3a);
-        assert_eq!(resp[1..], BSL_VERSION);
+        match resp.split_first() {
+            Some((0x3a, x)) => x.try_into().map_err(|_| Error::BslVersionFail),
+            _ => Err(Error::BslVersionFail),
+        }
+    }
+
+    /// Check that the full BSL loaded into RAM is running.
+    fn check_full_bsl(&self) -> Result<()> {
+        if self.bsl_version()? != BSL_VERSION {
+            return Err(Error::BslVersionFail);
+        }
 
         Ok(())
     }
@@ -165,6 +208,23 @@ impl MSP430 {
         Ok(bytes_to_write)
     }
 
+    fn crc_check(&self, addr: usize, len: u16) -> Result<u16> {
+        let data: Vec<u8> = three_bytes(addr)
+            .into_iter()
+            .chain(len.to_le_bytes())
+            .collect();
+
+        let resp = self
+            .cmd(CMD_CRC_CHECK, &data)
+            .map_err(|_| Error::CrcCheckFail)?;
+
+        if resp.len() != 3 || resp[0] != 0x3a {
+            return Err(Error::CrcCheckFail);
+        }
+
+        Ok(u16::from_le_bytes([resp[1], resp[2]]))
+    }
+
     fn load_binfile(&self, bin: &bin_file::BinFile) -> Result<()> {
         for (start_address, data) in bin.segments_list() {
             tracing::debug!(
@@ -195,6 +255,11 @@ fn load_bsl(dst: &std::ffi::CStr) -> Result<()> {
     tracing::info!("Unlock");
     msp430.unlock()?;
 
+    start_bsl(&msp430)
+}
+
+/// Load the full BSL into RAM and jump to it. BSL must be unlocked.
+fn start_bsl(msp430: &MSP430) -> Result<()> {
     let bin = BSL.parse().expect("Failed to parse MSP430 BSL");
     tracing::info!("BSL: {}", bin);
 
@@ -237,21 +302,111 @@ pub fn flash(
     let msp430 = MSP430(open_hidraw(dst)?);
 
     tracing::info!("Get BSL Version");
-    msp430.bsl_version()?;
+    msp430.check_full_bsl()?;
     tracing::info!("Flashing");
     msp430.load_binfile(&firmware_bin)?;
 
     Ok(())
 }
 
+/// BSL password of `firmware`, which is its interrupt vector table. Once flashed, the BSL can only
+/// be unlocked using this password.
+pub fn password(firmware: &[u8]) -> Result<[u8; 32]> {
+    let firmware_bin = parse_bin(firmware).map_err(|_| Error::InvalidFirmware)?;
+    password_of(&firmware_bin)
+}
+
+fn password_of(firmware: &bin_file::BinFile) -> Result<[u8; 32]> {
+    firmware
+        .to_bytes(PASSWORD_ADDR..(PASSWORD_ADDR + 32), Some(0xff))
+        .ok()
+        .and_then(|x| x.try_into().ok())
+        .ok_or(Error::InvalidFirmware)
+}
+
+/// Compare firmware on MSP430 in BeagleConnect Freedom with `firmware` without writing anything.
+/// Returns `true` if the firmware matches.
+///
+/// # Password
+///
+/// Unlocking the BSL with a wrong password mass erases the flash. So the BSL is only unlocked
+/// using `password` of the firmware known to be on the device, e.g. the [`password`] of the
+/// firmware used at the last flash. [`Error::PasswordUnknown`] is returned if it is not provided.
+///
+/// If the password of `firmware` differs, it cannot match the device and `false` is returned
+/// without unlocking.
+pub fn verify(firmware: &[u8], dst: &std::ffi::CStr, password: Option<&[u8; 32]>) -> Result<bool> {
+    let firmware_bin = parse_bin(firmware).map_err(|_| Error::InvalidFirmware)?;
+    let password = password.ok_or(Error::PasswordUnknown)?;
+
+    if password_of(&firmware_bin)? != *password {
+        tracing::warn!("Interrupt vector table differs. Firmware does not match");
+        return Ok(false);
+    }
+
+    let msp430 = MSP430(open_hidraw(dst)?);
+
+    tracing::info!("Unlock");
+    if !msp430.unlock_with(password)? {
+        return Err(Error::UnlockFail);
+    }
+
+    start_bsl(&msp430)?;
+    drop(msp430);
+
+    std::thread::sleep(Duration::from_secs(1));
+
+    let msp430 = MSP430(open_hidraw(dst)?);
+
+    tracing::info!("Get BSL Version");
+    msp430.check_full_bsl()?;
+
+    tracing::info!("Compare CRC");
+    for (start_address, data) in firmware_bin.segments_list() {
+        for (i, chunk) in data.chunks(CRC_CHECK_MAX_SIZE).enumerate() {
+            let addr = start_address + i * CRC_CHECK_MAX_SIZE;
+            let actual = msp430.crc_check(addr, chunk.len() as u16)?;
+
+            if actual != crc16_ccitt(chunk) {
+                tracing::warn!("CRC mismatch at {:#x}", addr);
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// CRC-CCITT as calculated by BSL CRC check command.
+fn crc16_ccitt(data: &[u8]) -> u16 {
+    data.iter().fold(0xffff, |crc, x| {
+        (0..8).fold(crc ^ (u16::from(*x) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
 /// Returns all paths to ports having BeagleConnect Freedom.
-pub fn devices(filter: bool) -> std::collections::HashSet<CString> {
+///
+/// When `filter` is set, only devices matching `usb_ids` (VID, PID) are returned. The MSP430 BSL
+/// IDs are used if `usb_ids` is empty.
+pub fn devices(filter: bool, usb_ids: &[(u16, u16)]) -> std::collections::HashSet<CString> {
+    let usb_ids: &[(u16, u16)] = if usb_ids.is_empty() {
+        &[(VID, PID)]
+    } else {
+        usb_ids
+    };
+
     hidapi::HidApi::new()
         .expect("Failed to create hidapi context")
         .device_list()
         .filter(|x| {
             if filter {
-                x.vendor_id() == VID && x.product_id() == PID
+                usb_ids.contains(&(x.vendor_id(), x.product_id()))
             } else {
                 true
             }
@@ -260,6 +415,24 @@ pub fn devices(filter: bool) -> std::collections::HashSet<CString> {
         .collect()
 }
 
+/// Query bootloader version from MSP430 in BeagleConnect Freedom. The device must be in BSL mode.
+///
+/// Firmware version cannot be determined since the firmware is not running in BSL mode.
+pub fn probe(dst: &std::ffi::CStr) -> Result<DeviceVersion> {
+    let msp430 = MSP430(open_hidraw(dst)?);
+
+    let version = msp430.bsl_version()?;
+    let bootloader = format!(
+        "BSL {:02x}.{:02x}.{:02x}.{:02x}",
+        version[0], version[1], version[2], version[3]
+    );
+
+    Ok(DeviceVersion {
+        bootloader: Some(bootloader),
+        firmware: None,
+    })
+}
+
 fn open_hidraw(dst: &std::ffi::CStr) -> Result<hidapi::HidDevice> {
     hidapi::HidApi::new()
         .map_err(|source| Error::FailedToOpenDestination { source })?
@@ -0,0 +1,285 @@
+//! Edit the kernel command line in the boot configuration of the image.
+//!
+//! Both boot configurations used by BeagleBoard.org images are supported:
+//!
+//! - [`EXTLINUX_CONF`]: Every `append` line is edited.
+//! - [`UENV_TXT`]: The `cmdline` variable is edited, and created if missing.
+//!
+//! ```
+//! bb_flasher_sd::cmdline::validate("console=ttyS0,115200n8 isolcpus=1").unwrap();
+//! assert!(bb_flasher_sd::cmdline::validate("video=\"HDMI-A-1").is_err());
+//! ```
+
+use std::fmt::Display;
+
+/// Path of extlinux configuration in the boot partition.
+pub const EXTLINUX_CONF: &str = "extlinux/extlinux.conf";
+/// Path of U-Boot environment in the boot partition.
+pub const UENV_TXT: &str = "uEnv.txt";
+/// Options of the serial console, when enabled.
+pub const SERIAL_CONSOLE_OPTIONS: &str = "115200n8";
+/// Serial console added when the command line does not have one. Existing serial consoles are
+/// kept, since the debug UART differs between boards.
+pub const DEFAULT_SERIAL_CONSOLE: &str = "ttyS0";
+
+/// Errors in kernel command line arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// No arguments given.
+    Empty,
+    /// Arguments contain a newline or other control character.
+    ControlCharacter,
+    /// A double quote is not closed.
+    UnclosedQuote,
+    /// Parameter name is empty or contains invalid characters.
+    InvalidParameter(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Kernel command line arguments are empty"),
+            Self::ControlCharacter => write!(
+                f,
+                "Kernel command line arguments cannot contain control characters"
+            ),
+            Self::UnclosedQuote => write!(f, "Kernel command line has an unclosed quote"),
+            Self::InvalidParameter(x) => write!(f, "Invalid kernel parameter {x}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Split `args` into parameters, keeping quotes. Whitespace in quotes does not separate
+/// parameters, same as the kernel.
+fn params(args: &str) -> Result<Vec<&str>, Error> {
+    let mut res = Vec::new();
+    let mut quoted = false;
+    let mut start = None;
+
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                start.get_or_insert(i);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if let Some(s) = start.take() {
+                    res.push(&args[s..i]);
+                }
+            }
+            _ => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+
+    if quoted {
+        return Err(Error::UnclosedQuote);
+    }
+    if let Some(s) = start {
+        res.push(&args[s..]);
+    }
+
+    Ok(res)
+}
+
+/// Check that `args` can be appended to the kernel command line. Each parameter should be either
+/// `name` or `name=value`.
+pub fn validate(args: &str) -> Result<(), Error> {
+    if args.chars().any(char::is_control) {
+        return Err(Error::ControlCharacter);
+    }
+
+    let params = params(args)?;
+    if params.is_empty() {
+        return Err(Error::Empty);
+    }
+
+    for p in params {
+        let name = p.split_once('=').map_or(p, |(x, _)| x);
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || matches!(x, '_' | '-' | '.'));
+        if !valid {
+            return Err(Error::InvalidParameter(p.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Append `args` to `cmdline`.
+pub(crate) fn append(cmdline: &str, args: &str) -> String {
+    let cmdline = cmdline.trim_end();
+    if cmdline.is_empty() {
+        args.to_string()
+    } else {
+        format!("{cmdline} {args}")
+    }
+}
+
+/// Serial console device in a `console=` parameter. Virtual terminals (`tty0`) are not serial
+/// consoles.
+fn serial_console(param: &str) -> Option<&str> {
+    let dev = param.strip_prefix("console=")?;
+    let dev = dev.split_once(',').map_or(dev, |(x, _)| x);
+    ["ttyS", "ttyO", "ttyAMA"]
+        .iter()
+        .any(|x| dev.starts_with(x))
+        .then_some(dev)
+}
+
+/// Enable or disable the serial console in `cmdline`. When enabled, existing serial consoles are
+/// set to [`SERIAL_CONSOLE_OPTIONS`], and [`DEFAULT_SERIAL_CONSOLE`] is added if there are none.
+pub(crate) fn set_serial_console(cmdline: &str, enable: bool) -> String {
+    let Ok(params) = params(cmdline) else {
+        // Leave malformed command lines alone rather than making them worse
+        return cmdline.to_string();
+    };
+
+    let mut found = false;
+    let mut res: Vec<String> = params
+        .into_iter()
+        .filter_map(|p| match serial_console(p) {
+            Some(dev) if enable => {
+                found = true;
+                Some(format!("console={dev},{SERIAL_CONSOLE_OPTIONS}"))
+            }
+            Some(_) => None,
+            None => Some(p.to_string()),
+        })
+        .collect();
+
+    if enable && !found {
+        res.push(format!(
+            "console={DEFAULT_SERIAL_CONSOLE},{SERIAL_CONSOLE_OPTIONS}"
+        ));
+    }
+
+    res.join(" ")
+}
+
+/// Edit the command line of every `append` line of an extlinux configuration. Returns [`None`]
+/// if there is no `append` line.
+pub(crate) fn edit_extlinux(conf: &str, mut f: impl FnMut(&str) -> String) -> Option<String> {
+    let mut found = false;
+    let res = lines(conf, |line| {
+        let keyword = line.split_whitespace().next()?;
+        if !keyword.eq_ignore_ascii_case("append") {
+            return None;
+        }
+
+        found = true;
+        let start = line.find(keyword).unwrap_or_default() + keyword.len();
+        let cmdline = f(line[start..].trim());
+        Some(
+            format!("{} {cmdline}", &line[..start])
+                .trim_end()
+                .to_string(),
+        )
+    });
+
+    found.then_some(res)
+}
+
+/// Edit the `cmdline` variable of a U-Boot environment. It is created if missing.
+pub(crate) fn edit_uenv(conf: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut found = false;
+    let mut res = lines(conf, |line| {
+        let value = line.strip_prefix("cmdline=")?;
+
+        found = true;
+        Some(format!("cmdline={}", f(value.trim())))
+    });
+
+    if !found {
+        if !res.is_empty() && !res.ends_with('\n') {
+            res.push('\n');
+        }
+        res.push_str(&format!("cmdline={}\n", f("")));
+    }
+
+    res
+}
+
+/// Replace lines for which `f` returns a value. Line endings are preserved.
+fn lines(conf: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    conf.split_inclusive('\n')
+        .map(|x| {
+            let content = x.trim_end_matches(['\r', '\n']);
+            match f(content) {
+                Some(line) => format!("{line}{}", &x[content.len()..]),
+                None => x.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, append, edit_extlinux, edit_uenv, set_serial_console, validate};
+
+    #[test]
+    fn validate_args() {
+        assert_eq!(validate("console=ttyS0,115200n8 isolcpus=1,2"), Ok(()));
+        assert_eq!(validate("rd.break quiet"), Ok(()));
+        assert_eq!(validate("dyndbg=\"file drivers/usb/* +p\""), Ok(()));
+        assert_eq!(validate("  "), Err(Error::Empty));
+        assert_eq!(
+            validate("quiet\nroot=/dev/sda"),
+            Err(Error::ControlCharacter)
+        );
+        assert_eq!(validate("video=\"HDMI-A-1"), Err(Error::UnclosedQuote));
+        assert_eq!(
+            validate("=1"),
+            Err(Error::InvalidParameter("=1".to_string()))
+        );
+        assert_eq!(
+            validate("a$b=1"),
+            Err(Error::InvalidParameter("a$b=1".to_string()))
+        );
+    }
+
+    #[test]
+    fn extlinux() {
+        let conf = "label Linux\r\n    kernel /Image\r\n    append root=/dev/mmcblk0p3 ro\r\n";
+        assert_eq!(
+            edit_extlinux(conf, |x| append(x, "quiet")).unwrap(),
+            "label Linux\r\n    kernel /Image\r\n    append root=/dev/mmcblk0p3 ro quiet\r\n"
+        );
+        assert_eq!(edit_extlinux("label Linux\n", |x| append(x, "quiet")), None);
+    }
+
+    #[test]
+    fn uenv() {
+        let quiet = |x: &str| append(x, "quiet");
+        assert_eq!(
+            edit_uenv("uname_r=6.1\ncmdline=coherent_pool=1M\n", quiet),
+            "uname_r=6.1\ncmdline=coherent_pool=1M quiet\n"
+        );
+        assert_eq!(
+            edit_uenv("uname_r=6.1\n#cmdline=quiet", quiet),
+            "uname_r=6.1\n#cmdline=quiet\ncmdline=quiet\n"
+        );
+        assert_eq!(edit_uenv("", quiet), "cmdline=quiet\n");
+    }
+
+    #[test]
+    fn serial_console() {
+        assert_eq!(
+            set_serial_console("root=/dev/mmcblk0p3 console=ttyS2,9600 console=tty0", true),
+            "root=/dev/mmcblk0p3 console=ttyS2,115200n8 console=tty0"
+        );
+        assert_eq!(
+            set_serial_console("quiet", true),
+            "quiet console=ttyS0,115200n8"
+        );
+        assert_eq!(
+            set_serial_console("console=ttyO0,115200n8 console=tty0 quiet", false),
+            "console=tty0 quiet"
+        );
+    }
+}
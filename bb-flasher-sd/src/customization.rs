@@ -1,6 +1,234 @@
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::{
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
 
-use crate::{Error, Result};
+use tokio::sync::mpsc;
+
+use crate::{
+    Error, Result, Status, cmdline, firstboot, helpers::chan_send, hostname, inspect, label,
+};
+
+/// Steps of post install customization, reported as progress and in errors.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum CustomizationStep {
+    /// Opening the boot partition.
+    Mounting,
+    /// Writing general options to sysconf.txt.
+    Sysconf,
+    /// Setting up the default user and SSH key.
+    User,
+    Wifi,
+    /// Copying user provided files to the boot partition.
+    Files,
+    /// Appending arguments to the kernel command line.
+    Cmdline,
+    /// Writing the unit which installs packages on first boot.
+    FirstBoot,
+    /// Flushing changes to the SD Card.
+    Syncing,
+    /// Setting volume labels of the boot and root partitions.
+    Label,
+}
+
+impl std::fmt::Display for CustomizationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mounting => write!(f, "mounting boot partition"),
+            Self::Sysconf => write!(f, "writing sysconf.txt"),
+            Self::User => write!(f, "configuring user"),
+            Self::Wifi => write!(f, "configuring WiFi"),
+            Self::Files => write!(f, "copying files"),
+            Self::Cmdline => write!(f, "editing kernel command line"),
+            Self::FirstBoot => write!(f, "writing first boot unit"),
+            Self::Syncing => write!(f, "syncing"),
+            Self::Label => write!(f, "setting volume labels"),
+        }
+    }
+}
+
+/// Report the step and tag any error with it.
+fn step<T>(
+    chan: &mut Option<&mut mpsc::Sender<Status>>,
+    step: CustomizationStep,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    tracing::info!("Customization step: {step}");
+    chan_send(chan.as_deref_mut(), Status::Customizing(step));
+
+    f().map_err(|e| Error::CustomizationStepFail {
+        step,
+        source: Box::new(e),
+        unmodified: false,
+    })
+}
+
+/// Record whether the image was left unmodified in a failed step.
+fn set_unmodified(mut e: Error, val: bool) -> Error {
+    if let Error::CustomizationStepFail { unmodified, .. } = &mut e {
+        *unmodified = val;
+    }
+    e
+}
+
+fn staged_path(path: &str) -> String {
+    format!("{path}.tmp")
+}
+
+fn backup_path(path: &str) -> String {
+    format!("{path}.bak")
+}
+
+fn exists<T: fatfs::ReadWriteSeek>(root: &fatfs::Dir<'_, T>, path: &str) -> io::Result<bool> {
+    match root.open_file(path) {
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// A file moved into place during commit.
+struct Committed {
+    path: String,
+    backup: bool,
+    placed: bool,
+}
+
+/// Files written under a temporary name, and only moved into place once every step has
+/// succeeded. Replaced files are kept as backups until the commit completes, so a failure at any
+/// point can be rolled back.
+#[derive(Default)]
+struct Staging {
+    files: Vec<String>,
+    /// Directories created for staged files, in order of creation.
+    dirs: Vec<String>,
+    committed: Vec<Committed>,
+}
+
+impl Staging {
+    fn write<T: fatfs::ReadWriteSeek>(
+        &mut self,
+        root: &fatfs::Dir<'_, T>,
+        path: &str,
+        data: &[u8],
+    ) -> io::Result<()> {
+        // Writing a staged file again replaces it
+        if !self.files.iter().any(|x| x.eq_ignore_ascii_case(path)) {
+            self.create_parents(root, path)?;
+            self.files.push(path.to_string());
+        }
+
+        let mut f = root.create_file(&staged_path(path))?;
+        f.truncate()?;
+        f.write_all(data)?;
+        f.flush()
+    }
+
+    /// Current contents of `path`, including staged changes. [`None`] if it does not exist.
+    fn read<T: fatfs::ReadWriteSeek>(
+        &self,
+        root: &fatfs::Dir<'_, T>,
+        path: &str,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let path = if self.files.iter().any(|x| x.eq_ignore_ascii_case(path)) {
+            staged_path(path)
+        } else if exists(root, path)? {
+            path.to_string()
+        } else {
+            return Ok(None);
+        };
+
+        let mut data = Vec::new();
+        root.open_file(&path)?.read_to_end(&mut data)?;
+        Ok(Some(data))
+    }
+
+    fn create_parents<T: fatfs::ReadWriteSeek>(
+        &mut self,
+        root: &fatfs::Dir<'_, T>,
+        path: &str,
+    ) -> io::Result<()> {
+        let parents = path
+            .match_indices('/')
+            .map(|(i, _)| &path[..i])
+            .filter(|x| !x.is_empty());
+
+        for dir in parents {
+            match root.open_dir(dir) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    root.create_dir(dir)?;
+                    self.dirs.push(dir.to_string());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn commit<T: fatfs::ReadWriteSeek>(&mut self, root: &fatfs::Dir<'_, T>) -> io::Result<()> {
+        for path in &self.files {
+            let backup = exists(root, path)?;
+            if backup {
+                root.rename(path, root, &backup_path(path))?;
+            }
+
+            let res = root.rename(&staged_path(path), root, path);
+            self.committed.push(Committed {
+                path: path.clone(),
+                backup,
+                placed: res.is_ok(),
+            });
+            res?;
+        }
+
+        for c in self.committed.drain(..).filter(|c| c.backup) {
+            if let Err(e) = root.remove(&backup_path(&c.path)) {
+                tracing::warn!("Failed to remove backup of {}: {e}", c.path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Undo all changes. Returns true if the partition is back to its original state.
+    fn rollback<T: fatfs::ReadWriteSeek>(&mut self, root: &fatfs::Dir<'_, T>) -> bool {
+        let mut restored = true;
+        let mut log = |res: io::Result<()>, msg: &str| {
+            if let Err(e) = res {
+                tracing::error!("Rollback failed to {msg}: {e}");
+                restored = false;
+            }
+        };
+
+        for c in self.committed.drain(..).rev() {
+            if c.placed {
+                log(root.remove(&c.path), &format!("remove {}", c.path));
+            }
+            if c.backup {
+                log(
+                    root.rename(&backup_path(&c.path), root, &c.path),
+                    &format!("restore {}", c.path),
+                );
+            }
+        }
+
+        for path in self.files.drain(..) {
+            let res = match root.remove(&staged_path(&path)) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                x => x,
+            };
+            log(res, &format!("remove staged {path}"));
+        }
+
+        for dir in self.dirs.drain(..).rev() {
+            log(root.remove(&dir), &format!("remove directory {dir}"));
+        }
+
+        restored
+    }
+}
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Customization {
@@ -8,9 +236,13 @@ pub enum Customization {
 }
 
 impl Customization {
-    pub(crate) fn customize(&self, dst: impl Write + Seek + Read + std::fmt::Debug) -> Result<()> {
+    pub(crate) fn customize(
+        &self,
+        dst: impl Write + Seek + Read + std::fmt::Debug,
+        chan: Option<&mut mpsc::Sender<Status>>,
+    ) -> Result<()> {
         match self {
-            Self::Sysconf(x) => x.customize(dst),
+            Self::Sysconf(x) => x.customize(dst, chan),
         }
     }
 
@@ -19,6 +251,21 @@ impl Customization {
             Self::Sysconf(x) => x.validate(),
         }
     }
+
+    /// Expand [hostname templates](crate::hostname) for a single destination. `serial` is the
+    /// serial number of the SD Card, if known.
+    pub fn for_destination(&self, serial: Option<&str>) -> Result<Self> {
+        match self {
+            Self::Sysconf(x) => x.for_destination(serial).map(Self::Sysconf),
+        }
+    }
+
+    /// Hostname to be set. Can still be a template before [`Self::for_destination`].
+    pub fn hostname(&self) -> Option<&str> {
+        match self {
+            Self::Sysconf(x) => x.hostname.as_deref(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
@@ -31,78 +278,428 @@ pub struct SysconfCustomization {
     pub wifi: Option<(Box<str>, Box<str>)>,
     pub ssh: Option<Box<str>>,
     pub usb_enable_dhcp: Option<bool>,
+    /// Volume label of the FAT boot partition.
+    pub volume_label: Option<Box<str>>,
+    /// Volume label of the ext root partition.
+    pub rootfs_label: Option<Box<str>>,
+    /// Record of what was flashed, written to the boot partition.
+    pub provenance: Option<Provenance>,
+    /// Files and directories copied to the boot partition.
+    pub files: Vec<BootFile>,
+    /// Arguments appended to the kernel command line. See [`cmdline`].
+    pub cmdline_append: Option<Box<str>>,
+    /// Enable or disable the serial console on the kernel command line. See
+    /// [`cmdline::DEFAULT_SERIAL_CONSOLE`].
+    pub serial_console: Option<bool>,
+    /// apt packages installed on first boot. See [`firstboot`].
+    pub packages: Vec<Box<str>>,
+    /// HTTP proxy for apt and the system environment, configured on first boot.
+    pub http_proxy: Option<Box<str>>,
+    /// HTTPS proxy for apt and the system environment, configured on first boot.
+    pub https_proxy: Option<Box<str>>,
+    /// Replaces the Debian mirror in apt sources on first boot.
+    pub apt_mirror: Option<Box<str>>,
+}
+
+/// File or directory on the host to copy to the boot partition (e.g. a custom `uEnv.txt`).
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct BootFile {
+    /// File or directory on the host. Directories are copied recursively.
+    pub src: PathBuf,
+    /// Path in the boot partition, using `/` as separator. Existing files are replaced.
+    pub dest: Box<str>,
+}
+
+impl BootFile {
+    pub fn new(src: PathBuf, dest: Box<str>) -> Self {
+        Self { src, dest }
+    }
+
+    /// Destination relative to the root of the boot partition. [`None`] if it is empty or
+    /// escapes the boot partition.
+    pub fn normalized_dest(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        for x in self.dest.split(['/', '\\']) {
+            match x {
+                "" | "." => {}
+                ".." => return None,
+                x if x.contains(':') => return None,
+                x => parts.push(x),
+            }
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("/"))
+        }
+    }
+
+    /// Files to copy, along with their path in the boot partition.
+    fn entries(&self) -> io::Result<Vec<(PathBuf, String)>> {
+        let dest = self.normalized_dest().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Invalid destination path")
+        })?;
+
+        let mut res = Vec::new();
+        walk(&self.src, dest, &mut res)?;
+        Ok(res)
+    }
+}
+
+fn walk(src: &Path, dest: String, res: &mut Vec<(PathBuf, String)>) -> io::Result<()> {
+    if !src.is_dir() {
+        res.push((src.to_path_buf(), dest));
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(src)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|x| x.file_name());
+    for x in entries {
+        let name = x.file_name();
+        walk(&x.path(), format!("{dest}/{}", name.to_string_lossy()), res)?;
+    }
+
+    Ok(())
+}
+
+/// Name of the provenance file in the boot partition.
+pub const PROVENANCE_FILE: &str = "bb-imager.txt";
+
+/// What was flashed to the SD Card. Written to the boot partition as [`PROVENANCE_FILE`], using
+/// the same `key=value` format as sysconf.txt, so support can tell what is on a returned device.
+///
+/// A summary of the customization is included. Passwords, Wi-Fi passphrases and SSH keys are never
+/// recorded.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Provenance {
+    /// Name of the OS image.
+    pub image: Box<str>,
+    /// Version or release date of the OS image, if known.
+    pub image_version: Option<Box<str>>,
+    /// SHA256 of the OS image, if known.
+    pub image_sha256: Option<[u8; 32]>,
+    /// Application and version which flashed the SD Card (e.g. `bb-imager-cli 1.0.0`).
+    pub imager: Box<str>,
 }
 
 impl SysconfCustomization {
+    fn for_destination(&self, serial: Option<&str>) -> Result<Self> {
+        let mut res = self.clone();
+
+        if let Some(x) = &self.hostname
+            && hostname::is_template(x)
+        {
+            let expanded = hostname::expand(x, serial)
+                .map_err(|source| Error::InvalidHostnameTemplate { source })?;
+            tracing::info!("Hostname template {x} expanded to {expanded}");
+            res.hostname = Some(expanded.into());
+        }
+
+        Ok(res)
+    }
+
     pub(crate) fn customize(
         &self,
         mut dst: impl Write + Seek + Read + std::fmt::Debug,
+        mut chan: Option<&mut mpsc::Sender<Status>>,
     ) -> Result<()> {
-        if !self.has_customization() {
-            return Ok(());
+        if self.has_boot() {
+            self.customize_boot(&mut dst, &mut chan)?;
+        }
+
+        // Labels are patched in place, so the boot partition must not be mounted
+        if self.volume_label.is_some() || self.rootfs_label.is_some() {
+            step(&mut chan, CustomizationStep::Label, || {
+                self.set_labels(&mut dst)
+            })?;
         }
 
-        let boot_partition = {
+        Ok(())
+    }
+
+    fn customize_boot(
+        &self,
+        mut dst: impl Write + Seek + Read + std::fmt::Debug,
+        chan: &mut Option<&mut mpsc::Sender<Status>>,
+    ) -> Result<()> {
+        // Nothing has been written if the boot partition cannot be opened
+        let boot_partition = step(chan, CustomizationStep::Mounting, || {
             let (start_off, end_off) = customization_partition(&mut dst)?;
             let slice = fscommon::StreamSlice::new(dst, start_off, end_off)
                 .map_err(|_| Error::InvalidPartitionTable)?;
             let boot_stream = fscommon::BufStream::new(slice);
             fatfs::FileSystem::new(boot_stream, fatfs::FsOptions::new())
-                .map_err(|_| Error::InvalidBootPartition)?
-        };
+                .map_err(|_| Error::InvalidBootPartition)
+        })
+        .map_err(|e| set_unmodified(e, true))?;
 
         let boot_root = boot_partition.root_dir();
+        let mut staging = Staging::default();
 
-        let mut conf = boot_root
-            .create_file("sysconf.txt")
-            .map_err(|source| Error::SysconfCreateFail { source })?;
-        conf.seek(SeekFrom::End(0))
-            .expect("Failed to seek to end of sysconf.txt");
+        let res = self.stage(&boot_root, &mut staging, chan).and_then(|conf| {
+            step(chan, CustomizationStep::Syncing, || {
+                if let Some(conf) = conf {
+                    staging
+                        .write(&boot_root, "sysconf.txt", &conf)
+                        .map_err(|source| Error::SysconfCreateFail { source })?;
+                }
+                if let Some(p) = &self.provenance {
+                    staging
+                        .write(
+                            &boot_root,
+                            PROVENANCE_FILE,
+                            self.provenance_file(p).as_bytes(),
+                        )
+                        .map_err(|source| Error::ProvenanceWriteFail { source })?;
+                }
+                staging.commit(&boot_root).map_err(Into::into)
+            })
+        });
+        let restored = res.is_err() && staging.rollback(&boot_root);
 
-        if let Some(h) = &self.hostname {
-            sysconf_w(&mut conf, "hostname", h)?;
+        drop(boot_root);
+        let unmounted = boot_partition.unmount();
+
+        match (res, unmounted) {
+            (Ok(()), Ok(())) => Ok(()),
+            (Ok(()), Err(e)) => Err(Error::CustomizationStepFail {
+                step: CustomizationStep::Syncing,
+                source: Box::new(e.into()),
+                unmodified: false,
+            }),
+            (Err(e), Ok(())) => Err(set_unmodified(e, restored)),
+            (Err(e), Err(err)) => {
+                tracing::error!("Failed to unmount boot partition after rollback: {err}");
+                Err(set_unmodified(e, false))
+            }
         }
+    }
 
-        if let Some(tz) = &self.timezone {
-            sysconf_w(&mut conf, "timezone", tz)?;
+    /// Stage files in the boot partition. Returns the new contents of sysconf.txt, if it is
+    /// customized.
+    fn stage<T: fatfs::ReadWriteSeek>(
+        &self,
+        boot_root: &fatfs::Dir<'_, T>,
+        staging: &mut Staging,
+        chan: &mut Option<&mut mpsc::Sender<Status>>,
+    ) -> Result<Option<Vec<u8>>> {
+        // User files are copied first, so sysconf.txt generated below is not overwritten
+        if !self.files.is_empty() {
+            step(chan, CustomizationStep::Files, || {
+                self.files
+                    .iter()
+                    .try_for_each(|x| copy_file(boot_root, staging, x))
+            })?;
         }
 
-        if let Some(k) = &self.keymap {
-            sysconf_w(&mut conf, "keymap", k)?;
+        // Applied after user files, so a copied uEnv.txt is edited as well
+        if self.has_cmdline() {
+            step(chan, CustomizationStep::Cmdline, || {
+                edit_cmdline(boot_root, staging, |x| self.edit_cmdline(x))
+            })?;
         }
 
-        if let Some((u, p)) = &self.user {
-            sysconf_w(&mut conf, "user_name", u)?;
-            sysconf_w(&mut conf, "user_password", p)?;
+        let script = self.firstboot();
+        if !script.is_empty() {
+            step(chan, CustomizationStep::FirstBoot, || {
+                staging
+                    .write(
+                        boot_root,
+                        firstboot::SCRIPT_FILE,
+                        script.render().as_bytes(),
+                    )
+                    .and_then(|_| {
+                        staging.write(
+                            boot_root,
+                            firstboot::UNIT_FILE,
+                            firstboot::unit().as_bytes(),
+                        )
+                    })
+                    .map_err(|source| Error::FirstBootWriteFail { source })
+            })?;
         }
 
-        if let Some(x) = &self.ssh {
-            sysconf_w(&mut conf, "user_authorized_key", x)?;
+        if !self.has_sysconf() {
+            return Ok(None);
         }
 
-        if Some(true) == self.usb_enable_dhcp {
-            sysconf_w(&mut conf, "usb_enable_dhcp", "yes")?;
+        let mut conf = step(chan, CustomizationStep::Sysconf, || {
+            // Keep the options shipped with the image
+            let mut conf = Vec::new();
+            if exists(boot_root, "sysconf.txt")
+                .map_err(|source| Error::SysconfCreateFail { source })?
+            {
+                boot_root
+                    .open_file("sysconf.txt")
+                    .and_then(|mut f| f.read_to_end(&mut conf))
+                    .map_err(|source| Error::SysconfCreateFail { source })?;
+            }
+
+            if let Some(h) = &self.hostname {
+                sysconf_w(&mut conf, "hostname", h)?;
+            }
+
+            if let Some(tz) = &self.timezone {
+                sysconf_w(&mut conf, "timezone", tz)?;
+            }
+
+            if let Some(k) = &self.keymap {
+                sysconf_w(&mut conf, "keymap", k)?;
+            }
+
+            if Some(true) == self.usb_enable_dhcp {
+                sysconf_w(&mut conf, "usb_enable_dhcp", "yes")?;
+            }
+
+            Ok(conf)
+        })?;
+
+        if self.user.is_some() || self.ssh.is_some() {
+            step(chan, CustomizationStep::User, || {
+                if let Some((u, p)) = &self.user {
+                    sysconf_w(&mut conf, "user_name", u)?;
+                    sysconf_w(&mut conf, "user_password", p)?;
+                }
+
+                if let Some(x) = &self.ssh {
+                    sysconf_w(&mut conf, "user_authorized_key", x)?;
+                }
+
+                Ok(())
+            })?;
         }
 
         if let Some((ssid, psk)) = &self.wifi {
-            let mut wifi_file = boot_root
-                .create_file(format!("services/{ssid}.psk").as_str())
-                .map_err(|e| Error::WifiSetupFail { source: e })?;
+            step(chan, CustomizationStep::Wifi, || {
+                staging
+                    .write(
+                        boot_root,
+                        &format!("services/{ssid}.psk"),
+                        format!("[Security]\nPassphrase={psk}\n\n[Settings]\nAutoConnect=true")
+                            .as_bytes(),
+                    )
+                    .map_err(|e| Error::WifiSetupFail { source: e })?;
+
+                sysconf_w(&mut conf, "iwd_psk_file", &format!("{ssid}.psk"))
+            })?;
+        }
+
+        Ok(Some(conf))
+    }
+
+    /// Contents of [`PROVENANCE_FILE`]. Secrets are left out.
+    fn provenance_file(&self, p: &Provenance) -> String {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.as_secs().to_string())
+            .ok();
+        let sha256 = p
+            .image_sha256
+            .map(|x| x.iter().map(|b| format!("{b:02x}")).collect::<String>());
+        let packages = (!self.packages.is_empty()).then(|| self.packages.join(","));
+        let files = (!self.files.is_empty()).then(|| {
+            self.files
+                .iter()
+                .filter_map(BootFile::normalized_dest)
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+
+        [
+            ("image", Some(p.image.as_ref())),
+            ("image_version", p.image_version.as_deref()),
+            ("image_sha256", sha256.as_deref()),
+            ("imager", Some(p.imager.as_ref())),
+            ("timestamp", timestamp.as_deref()),
+            ("hostname", self.hostname.as_deref()),
+            ("timezone", self.timezone.as_deref()),
+            ("keymap", self.keymap.as_deref()),
+            ("user_name", self.user.as_ref().map(|(x, _)| x.as_ref())),
+            ("wifi_ssid", self.wifi.as_ref().map(|(x, _)| x.as_ref())),
+            ("ssh_key", self.ssh.as_ref().map(|_| "yes")),
+            (
+                "usb_enable_dhcp",
+                (self.usb_enable_dhcp == Some(true)).then_some("yes"),
+            ),
+            ("volume_label", self.volume_label.as_deref()),
+            ("rootfs_label", self.rootfs_label.as_deref()),
+            ("files", files.as_deref()),
+            ("cmdline_append", self.cmdline_append.as_deref()),
+            ("packages", packages.as_deref()),
+            // Proxy URLs can contain credentials
+            (
+                "proxy",
+                (self.http_proxy.is_some() || self.https_proxy.is_some()).then_some("yes"),
+            ),
+            ("apt_mirror", self.apt_mirror.as_deref()),
+            (
+                "serial_console",
+                self.serial_console
+                    .map(|x| if x { "enabled" } else { "disabled" }),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(k, v)| v.map(|v| format!("{k}={v}\n")))
+        .collect()
+    }
 
-            wifi_file
-                .write_all(
-                    format!("[Security]\nPassphrase={psk}\n\n[Settings]\nAutoConnect=true")
-                        .as_bytes(),
-                )
-                .map_err(|e| Error::WifiSetupFail { source: e })?;
+    fn set_labels(&self, mut dst: impl Write + Seek + Read + std::fmt::Debug) -> Result<()> {
+        if let Some(x) = &self.volume_label {
+            let (start_off, end_off) = customization_partition(&mut dst)?;
+            let slice = fscommon::StreamSlice::new(&mut dst, start_off, end_off)
+                .map_err(|_| Error::InvalidPartitionTable)?;
+            label::set_fat_label(slice, x).map_err(|source| Error::LabelSetFail { source })?;
+        }
 
-            sysconf_w(&mut conf, "iwd_psk_file", &format!("{ssid}.psk"))?;
+        if let Some(x) = &self.rootfs_label {
+            let (start_off, end_off) =
+                rootfs_partition(&mut dst)?.ok_or(Error::InvalidRootPartition)?;
+            let slice = fscommon::StreamSlice::new(&mut dst, start_off, end_off)
+                .map_err(|_| Error::InvalidPartitionTable)?;
+            label::set_ext_label(slice, x).map_err(|source| Error::LabelSetFail { source })?;
         }
 
-        Ok(())
+        dst.flush().map_err(Into::into)
     }
 
     pub(crate) fn has_customization(&self) -> bool {
+        self.has_boot() || self.volume_label.is_some() || self.rootfs_label.is_some()
+    }
+
+    /// Boot partition needs to be mounted.
+    fn has_boot(&self) -> bool {
+        self.has_sysconf()
+            || !self.files.is_empty()
+            || self.has_cmdline()
+            || !self.firstboot().is_empty()
+    }
+
+    fn firstboot(&self) -> firstboot::Script<'_> {
+        firstboot::Script {
+            packages: &self.packages,
+            http_proxy: self.http_proxy.as_deref(),
+            https_proxy: self.https_proxy.as_deref(),
+            apt_mirror: self.apt_mirror.as_deref(),
+        }
+    }
+
+    fn has_cmdline(&self) -> bool {
+        self.cmdline_append.is_some() || self.serial_console.is_some()
+    }
+
+    fn edit_cmdline(&self, cmdline: &str) -> String {
+        let mut res = cmdline.to_string();
+        if let Some(x) = self.serial_console {
+            res = cmdline::set_serial_console(&res, x);
+        }
+        if let Some(x) = &self.cmdline_append {
+            res = cmdline::append(&res, x);
+        }
+        res
+    }
+
+    fn has_sysconf(&self) -> bool {
         self.hostname.is_some()
             || self.timezone.is_some()
             || self.keymap.is_some()
@@ -113,12 +710,107 @@ impl SysconfCustomization {
     }
 
     pub(crate) fn validate(&self) -> bool {
-        if let Some((x, _)) = &self.user {
+        let user = if let Some((x, _)) = &self.user {
             x.as_ref() != "root"
         } else {
             true
+        };
+
+        let valid_hostname = self
+            .hostname
+            .as_deref()
+            .is_none_or(|x| hostname::validate(x).is_ok());
+
+        let labels = self
+            .volume_label
+            .as_deref()
+            .is_none_or(label::valid_fat_label)
+            && self
+                .rootfs_label
+                .as_deref()
+                .is_none_or(label::valid_ext_label);
+
+        let files = self.files.iter().all(|x| {
+            x.normalized_dest().is_some_and(|dest| {
+                !(self.has_sysconf() && dest.eq_ignore_ascii_case("sysconf.txt"))
+                    && !(self.provenance.is_some() && dest.eq_ignore_ascii_case(PROVENANCE_FILE))
+            })
+        });
+
+        let valid_cmdline = self
+            .cmdline_append
+            .as_deref()
+            .is_none_or(|x| cmdline::validate(x).is_ok());
+
+        let packages = self
+            .packages
+            .iter()
+            .all(|x| firstboot::valid_package_name(x))
+            && [&self.http_proxy, &self.https_proxy, &self.apt_mirror]
+                .into_iter()
+                .flatten()
+                .all(|x| firstboot::valid_url(x));
+
+        user && valid_hostname && labels && files && valid_cmdline && packages
+    }
+}
+
+/// Edit the kernel command line in every boot configuration present.
+fn edit_cmdline<T: fatfs::ReadWriteSeek>(
+    root: &fatfs::Dir<'_, T>,
+    staging: &mut Staging,
+    f: impl Fn(&str) -> String,
+) -> Result<()> {
+    let fail = |source| Error::CmdlineEditFail { source };
+    let read = |staging: &Staging, path| -> Result<Option<String>> {
+        let data = staging.read(root, path).map_err(fail)?;
+        data.map(|x| String::from_utf8(x).map_err(|e| fail(io::Error::other(e))))
+            .transpose()
+    };
+    let mut found = false;
+
+    if let Some(conf) = read(staging, cmdline::EXTLINUX_CONF)? {
+        // extlinux.conf without append line uses the command line built into the kernel
+        if let Some(conf) = cmdline::edit_extlinux(&conf, &f) {
+            staging
+                .write(root, cmdline::EXTLINUX_CONF, conf.as_bytes())
+                .map_err(fail)?;
+            found = true;
         }
     }
+
+    if let Some(conf) = read(staging, cmdline::UENV_TXT)? {
+        let conf = cmdline::edit_uenv(&conf, &f);
+        staging
+            .write(root, cmdline::UENV_TXT, conf.as_bytes())
+            .map_err(fail)?;
+        found = true;
+    }
+
+    if found {
+        Ok(())
+    } else {
+        Err(Error::BootConfigNotFound)
+    }
+}
+
+fn copy_file<T: fatfs::ReadWriteSeek>(
+    root: &fatfs::Dir<'_, T>,
+    staging: &mut Staging,
+    file: &BootFile,
+) -> Result<()> {
+    let fail = |source| Error::FileCopyFail {
+        path: file.src.clone(),
+        source,
+    };
+
+    for (src, dest) in file.entries().map_err(fail)? {
+        tracing::info!("Copying {} to {dest}", src.display());
+        let data = std::fs::read(&src).map_err(fail)?;
+        staging.write(root, &dest, &data).map_err(fail)?;
+    }
+
+    Ok(())
 }
 
 fn sysconf_w(mut sysconf: impl Write, key: &'static str, value: &str) -> Result<()> {
@@ -130,7 +822,7 @@ fn sysconf_w(mut sysconf: impl Write, key: &'static str, value: &str) -> Result<
         })
 }
 
-fn customization_partition(
+pub(crate) fn customization_partition(
     mut dst: impl Write + Seek + Read + std::fmt::Debug,
 ) -> Result<(u64, u64)> {
     // First try GPT partition table. If that fails, try MBR
@@ -156,3 +848,18 @@ fn customization_partition(
         Ok((start_offset, end_offset))
     }
 }
+
+/// First partition with an ext filesystem.
+fn rootfs_partition(mut dst: impl Seek + Read) -> Result<Option<(u64, u64)>> {
+    let partitions = match inspect::gpt_partitions(&mut dst)? {
+        Some(x) => x,
+        None => inspect::mbr_partitions(&mut dst)?,
+    };
+
+    Ok(partitions
+        .into_iter()
+        .map(|x| (x.start, x.start + x.size))
+        .find(|(start, end)| {
+            fscommon::StreamSlice::new(&mut dst, *start, *end).is_ok_and(inspect::is_ext)
+        }))
+}
@@ -0,0 +1,78 @@
+//! Regular file (or loop device) backed destination. Used to export customized images, and
+//! allows exercising the complete flashing pipeline in automated tests without a physical SD Card.
+
+use std::path::Path;
+#[cfg(feature = "test-util")]
+use std::path::PathBuf;
+
+#[cfg(feature = "test-util")]
+use crate::Device;
+use crate::{Result, helpers::Eject};
+
+impl Eject for std::fs::File {
+    fn eject(self) -> std::io::Result<()> {
+        self.sync_all()
+    }
+}
+
+/// Create a new image file, replacing any existing regular file.
+pub(crate) fn create(dst: &Path) -> Result<std::fs::File> {
+    if dst.exists() && !dst.is_file() {
+        return Err(crate::Error::FailedToOpenDestination {
+            source: anyhow::anyhow!("{} is not a regular file", dst.display()),
+        });
+    }
+
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dst)
+        .map_err(|e| crate::Error::FailedToOpenDestination { source: e.into() })
+}
+
+#[cfg(feature = "test-util")]
+pub(crate) fn open(dst: &Path) -> Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(dst)
+        .map_err(|e| crate::Error::FailedToOpenDestination { source: e.into() })
+}
+
+/// Check if the destination should be treated as a regular file instead of an SD Card. Block
+/// devices are included on unix so that loop devices can be used.
+#[cfg(feature = "test-util")]
+pub(crate) fn is_file(dst: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(dst) else {
+        return false;
+    };
+
+    #[cfg(unix)]
+    if std::os::unix::fs::FileTypeExt::is_block_device(&metadata.file_type()) {
+        return true;
+    }
+
+    metadata.is_file()
+}
+
+#[cfg(feature = "test-util")]
+impl Device {
+    /// Use a regular file or loop device as destination. The file must already exist.
+    pub fn file(path: PathBuf) -> std::io::Result<Self> {
+        use std::io::Seek;
+
+        let metadata = std::fs::metadata(&path)?;
+        // Metadata does not contain the size of block devices
+        let size = std::fs::File::open(&path)?.seek(std::io::SeekFrom::End(0))?;
+        let name = format!("File ({})", path.display());
+
+        Ok(Self::new(
+            name,
+            path,
+            size,
+            metadata.permissions().readonly(),
+        ))
+    }
+}
@@ -1,12 +1,14 @@
-use std::io::{Read, Seek, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::num::NonZeroU64;
 use std::path::Path;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use tokio::sync::mpsc;
 
-use crate::Result;
 use crate::customization::Customization;
-use crate::helpers::{DirectIoBuffer, Eject, chan_send, check_token, progress};
+use crate::helpers::{DirectIoBuffer, Eject, Throttle, chan_send, check_token, progress};
+use crate::verify::{Checks, Recorder};
+use crate::{Result, Status, Verify};
 
 // Stack overflow occurs during debug since box moves data from stack to heap in debug builds
 #[cfg(not(debug_assertions))]
@@ -20,7 +22,15 @@ fn reader_task(
     buf_tx: std::sync::mpsc::SyncSender<(Box<DirectIoBuffer<BUFFER_SIZE>>, usize)>,
     cancel: Option<tokio_util::sync::CancellationToken>,
 ) -> Result<()> {
-    while let Ok(mut buf) = buf_rx.recv() {
+    let mut stalled = Duration::ZERO;
+
+    loop {
+        let start = Instant::now();
+        let Ok(mut buf) = buf_rx.recv() else {
+            break;
+        };
+        stalled += start.elapsed();
+
         let count = read_aligned(&mut img, buf.as_mut_slice())?;
         if count == 0 {
             break;
@@ -32,6 +42,8 @@ fn reader_task(
         check_token(cancel.as_ref())?;
     }
 
+    // Time spent waiting for the writer and hasher to free a buffer
+    tracing::info!("Reader stalled on writer for: {stalled:?}");
     Ok(())
 }
 
@@ -43,15 +55,20 @@ fn reader_task(
 fn writer_task_bmap(
     bmap: bb_bmap_parser::Bmap,
     mut sd: impl Write + Seek,
-    mut chan: Option<&mut mpsc::Sender<f32>>,
+    mut chan: Option<&mut mpsc::Sender<Status>>,
     buf_rx: std::sync::mpsc::Receiver<(Box<DirectIoBuffer<BUFFER_SIZE>>, usize)>,
-    buf_tx: std::sync::mpsc::SyncSender<Box<DirectIoBuffer<BUFFER_SIZE>>>,
+    buf_tx: std::sync::mpsc::SyncSender<(Box<DirectIoBuffer<BUFFER_SIZE>>, u64, usize)>,
+    mut throttle: Option<Throttle>,
     cancel: Option<tokio_util::sync::CancellationToken>,
 ) -> Result<()> {
     let mut pos = 0;
+    let start = Instant::now();
     let (mut buf, mut count) = buf_rx.recv().unwrap();
+    let mut stalled = start.elapsed();
     let img_size = bmap.total_mapped_size();
     let mut bytes_written = 0u64;
+    // Offset and length of the current buffer written to SD Card
+    let mut written = (0, 0);
 
     for b in bmap.block_map() {
         let end_offset = b.offset() + b.length();
@@ -62,6 +79,10 @@ fn writer_task_bmap(
                 sd.seek(std::io::SeekFrom::Start(pos))?;
                 sd.write_all(&buf.as_slice()[..count])?;
                 bytes_written += count as u64;
+                written = (pos, count);
+                if let Some(t) = throttle.as_mut() {
+                    t.consume(count as u64);
+                }
             } else if pos >= end_offset {
                 break;
             }
@@ -71,52 +92,143 @@ fn writer_task_bmap(
             #[allow(clippy::option_map_or_none)]
             chan_send(
                 chan.as_mut().map_or(None, |p| Some(p)),
-                progress(bytes_written, img_size),
+                Status::Flashing(progress(bytes_written, img_size)),
             );
-            check_token(cancel.as_ref())?;
+            check_aborted(&mut sd, pos, cancel.as_ref())?;
 
-            match buf_rx.recv() {
+            let start = Instant::now();
+            let next = buf_rx.recv();
+            stalled += start.elapsed();
+
+            match next {
                 Ok((x, y)) => {
-                    let _ = buf_tx.send(buf);
+                    let _ = buf_tx.send((buf, written.0, written.1));
                     buf = x;
                     count = y;
+                    written = (pos, 0);
                 }
                 Err(_) => break,
             }
         }
     }
 
+    let _ = buf_tx.send((buf, written.0, written.1));
+
+    tracing::info!("Writer stalled on reader for: {stalled:?}");
     sd.flush().map_err(Into::into)
 }
 
 fn writer_task(
     img_size: u64,
     mut sd: impl Write + Seek,
-    mut chan: Option<&mut mpsc::Sender<f32>>,
+    mut chan: Option<&mut mpsc::Sender<Status>>,
     buf_rx: std::sync::mpsc::Receiver<(Box<DirectIoBuffer<BUFFER_SIZE>>, usize)>,
-    buf_tx: std::sync::mpsc::SyncSender<Box<DirectIoBuffer<BUFFER_SIZE>>>,
+    buf_tx: std::sync::mpsc::SyncSender<(Box<DirectIoBuffer<BUFFER_SIZE>>, u64, usize)>,
+    mut throttle: Option<Throttle>,
     cancel: Option<tokio_util::sync::CancellationToken>,
 ) -> Result<()> {
     let mut pos = 0u64;
+    let mut stalled = Duration::ZERO;
+
+    loop {
+        let start = Instant::now();
+        let Ok((buf, count)) = buf_rx.recv() else {
+            break;
+        };
+        stalled += start.elapsed();
 
-    while let Ok((buf, count)) = buf_rx.recv() {
         sd.write_all(&buf.as_slice()[..count])?;
+        if let Some(t) = throttle.as_mut() {
+            t.consume(count as u64);
+        }
+
+        let _ = buf_tx.send((buf, pos, count));
 
         pos += count as u64;
         // Clippy warning is simply wrong here
         #[allow(clippy::option_map_or_none)]
         chan_send(
             chan.as_mut().map_or(None, |p| Some(p)),
-            progress(pos, img_size),
+            Status::Flashing(progress(pos, img_size)),
         );
 
-        let _ = buf_tx.send(buf);
-        check_token(cancel.as_ref())?;
+        check_aborted(&mut sd, pos, cancel.as_ref())?;
     }
 
+    // Time spent waiting for the reader to fill a buffer
+    tracing::info!("Writer stalled on reader for: {stalled:?}");
     sd.flush().map_err(Into::into)
 }
 
+/// Compute checksums of buffers already written to SD Card, and hand them back to the reader.
+/// Buffers with zero length were skipped due to bmap.
+fn hasher_task(
+    mut recorder: Recorder<Discard>,
+    buf_rx: std::sync::mpsc::Receiver<(Box<DirectIoBuffer<BUFFER_SIZE>>, u64, usize)>,
+    buf_tx: std::sync::mpsc::SyncSender<Box<DirectIoBuffer<BUFFER_SIZE>>>,
+) -> Checks {
+    let mut stalled = Duration::ZERO;
+
+    loop {
+        let start = Instant::now();
+        let Ok((buf, offset, count)) = buf_rx.recv() else {
+            break;
+        };
+        stalled += start.elapsed();
+
+        if count != 0 {
+            // Discard cannot fail
+            recorder.seek(SeekFrom::Start(offset)).unwrap();
+            recorder.write_all(&buf.as_slice()[..count]).unwrap();
+        }
+
+        let _ = buf_tx.send(buf);
+    }
+
+    // Time spent waiting for the writer to finish a buffer
+    tracing::info!("Hasher stalled on writer for: {stalled:?}");
+    recorder.finish()
+}
+
+/// Stands in for the SD Card on the hasher thread. Only keeps track of the offset.
+struct Discard(u64);
+
+impl Write for Discard {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for Discard {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match pos {
+            SeekFrom::Start(x) => self.0 = x,
+            _ => return Err(std::io::ErrorKind::Unsupported.into()),
+        }
+        Ok(self.0)
+    }
+}
+
+/// Flush everything written so far before aborting, so that the SD Card is left in a known state.
+fn check_aborted(
+    mut sd: impl Write,
+    offset: u64,
+    cancel: Option<&tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    if check_token(cancel).is_err() {
+        sd.flush()?;
+        tracing::info!("Aborted at offset {offset}");
+        return Err(crate::Error::AbortedAt { offset });
+    }
+
+    Ok(())
+}
+
 /// A lot of reads from compressed files are not aligned. Since reading even from compressed files
 /// is significantly faster than writing to SD Card, better to do multiple reads.
 fn read_aligned(mut img: impl Read, buf: &mut [u8]) -> Result<usize> {
@@ -140,20 +252,33 @@ fn read_aligned(mut img: impl Read, buf: &mut [u8]) -> Result<usize> {
     Ok(pos)
 }
 
+/// Reading (including decompression), writing to SD Card and hashing for [`Verify`] run on
+/// separate threads, passing a fixed set of buffers around: reader -> writer -> hasher -> reader.
+/// This allows decompression and hashing to overlap with device writes.
+///
+/// The time each stage spends waiting on the previous one is logged to help find which one limits
+/// throughput.
+#[allow(clippy::too_many_arguments)]
 fn write_sd(
     img: impl Read + Send,
     img_size: u64,
     bmap: Option<bb_bmap_parser::Bmap>,
     sd: impl Write + Seek,
-    chan: Option<&mut mpsc::Sender<f32>>,
+    chan: Option<&mut mpsc::Sender<Status>>,
+    write_limit: Option<NonZeroU64>,
+    verify: Verify,
     cancel: Option<tokio_util::sync::CancellationToken>,
-) -> Result<()> {
+) -> Result<Checks> {
     const NUM_BUFFERS: usize = 4;
 
     let (tx1, rx1) = std::sync::mpsc::sync_channel(NUM_BUFFERS);
     let (tx2, rx2) = std::sync::mpsc::sync_channel(NUM_BUFFERS);
+    let (tx3, rx3) = std::sync::mpsc::sync_channel(NUM_BUFFERS);
     let global_start = Instant::now();
 
+    let expected = bmap.as_ref().map_or(img_size, |x| x.total_mapped_size());
+    let recorder = Recorder::new(Discard(0), verify, img_size, expected, BUFFER_SIZE);
+
     // Starting buffers
     for _ in 0..NUM_BUFFERS {
         tx1.send(Box::new(DirectIoBuffer::new())).unwrap();
@@ -162,14 +287,18 @@ fn write_sd(
     std::thread::scope(|s| {
         let cancle_clone = cancel.clone();
         let handle = s.spawn(move || reader_task(img, rx1, tx2, cancle_clone));
+        let hasher = s.spawn(move || hasher_task(recorder, rx3, tx1));
 
+        let throttle = write_limit.map(Throttle::new);
         match bmap {
-            Some(x) => writer_task_bmap(x, sd, chan, rx2, tx1, cancel),
-            None => writer_task(img_size, sd, chan, rx2, tx1, cancel),
+            Some(x) => writer_task_bmap(x, sd, chan, rx2, tx3, throttle, cancel),
+            None => writer_task(img_size, sd, chan, rx2, tx3, throttle, cancel),
         }?;
+        let checks = hasher.join().unwrap();
         tracing::info!("Total Time taken: {:?}", global_start.elapsed());
 
-        handle.join().unwrap()
+        handle.join().unwrap()?;
+        Ok(checks)
     })
 }
 
@@ -180,6 +309,10 @@ fn write_sd(
 /// Support post flashing customization. Currently only sysconf is supported, which is used by
 /// [BeagleBoard.org].
 ///
+/// Changes are first written to temporary files in the boot partition and only moved into place
+/// once every step has succeeded. On failure, they are rolled back and the returned error states
+/// whether the freshly flashed image was left unmodified.
+///
 /// # Image
 ///
 /// Using a resolver function for image and image size. This is to allow downloading the image, or
@@ -189,23 +322,120 @@ fn write_sd(
 /// Many users might switch task after starting the flashing process, which would make it
 /// frustrating if the prompt occured after downloading.
 ///
+/// # Write Limit
+///
+/// Some USB card readers and hubs drop out when written to at full speed. `write_limit` (in bytes
+/// per second) caps the average write speed to trade speed for reliability.
+///
+/// # Verification
+///
+/// Written data can be read back and compared before applying customization. See [`Verify`] for
+/// the available modes. Checksums are computed while writing, so the image is not read again.
+///
 /// # Progress
 ///
-/// Progress lies between 0 and 1.
+/// Flashing progress lies between 0 and 1. [`Status::Verifying`] is sent while reading back.
+/// Customization reports the step being applied.
 ///
 /// # Aborting
 ///
-/// The process can be aborted by dropping all strong references to the [`Arc`] that owns the
-/// [`Weak`] passed as `cancel`.
+/// The process can be aborted by cancelling `cancel`, or by dropping the returned future. When
+/// aborted while writing, data written so far is flushed and [`Error::AbortedAt`] reports how much
+/// of the image was written. The SD Card is released before returning.
 ///
-/// [`Arc`]: std::sync::Arc
-/// [`Weak`]: std::sync::Weak
+/// [`Error::AbortedAt`]: crate::Error::AbortedAt
 /// [BeagleBoard.org]: https://www.beagleboard.org/
+#[tracing::instrument(
+    name = "flash",
+    skip_all,
+    fields(
+        dst = %dst.display(),
+        bmap = bmap.is_some(),
+        customization = customization.is_some(),
+        verify = ?verify,
+        img_size = tracing::field::Empty,
+    )
+)]
+#[allow(clippy::too_many_arguments)]
 pub async fn flash<R: Read + Send + 'static>(
     img: impl bb_helper::resolvable::Resolvable<ResolvedType = (R, u64)>,
     bmap: Option<impl bb_helper::resolvable::Resolvable<ResolvedType = Box<str>>>,
     dst: Box<Path>,
-    chan: Option<mpsc::Sender<f32>>,
+    chan: Option<mpsc::Sender<Status>>,
+    customization: Option<Customization>,
+    write_limit: Option<NonZeroU64>,
+    verify: Verify,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    if let Some(x) = &customization
+        && !x.validate()
+    {
+        return Err(crate::Error::InvalidCustomizaton);
+    }
+    let serial = crate::pal::card_serial(&dst);
+    let customization = customization
+        .map(|x| x.for_destination(serial.as_deref()))
+        .transpose()?;
+
+    tracing::info!("Opening Destination");
+    let dst_clone = dst.to_path_buf();
+
+    #[cfg(feature = "test-util")]
+    if crate::file::is_file(&dst_clone) {
+        let sd = crate::file::open(&dst_clone)?;
+        return flash_to(
+            img,
+            bmap,
+            sd,
+            chan,
+            customization,
+            write_limit,
+            verify,
+            cancel,
+        )
+        .await;
+    }
+
+    crate::pal::check_busy(&dst_clone)?;
+    check_write_protected(&dst_clone)?;
+    let sd = crate::pal::open(&dst_clone).await.map_err(|e| match e {
+        crate::Error::IoError { source }
+            if source.kind() == std::io::ErrorKind::ReadOnlyFilesystem =>
+        {
+            crate::Error::WriteProtected
+        }
+        e => e,
+    })?;
+    flash_to(
+        img,
+        bmap,
+        sd,
+        chan,
+        customization,
+        write_limit,
+        verify,
+        cancel,
+    )
+    .await
+}
+
+/// Write OS image along with customization to a new image file instead of an SD Card. Any existing
+/// file at `dst` is replaced, and removed again if exporting fails.
+///
+/// Behaves the same as [`flash`] otherwise.
+#[tracing::instrument(
+    name = "export",
+    skip_all,
+    fields(
+        dst = %dst.display(),
+        customization = customization.is_some(),
+        img_size = tracing::field::Empty,
+    )
+)]
+pub async fn export<R: Read + Send + 'static>(
+    img: impl bb_helper::resolvable::Resolvable<ResolvedType = (R, u64)>,
+    dst: Box<Path>,
+    chan: Option<mpsc::Sender<Status>>,
     customization: Option<Customization>,
     cancel: Option<tokio_util::sync::CancellationToken>,
 ) -> Result<()> {
@@ -214,11 +444,103 @@ pub async fn flash<R: Read + Send + 'static>(
     {
         return Err(crate::Error::InvalidCustomizaton);
     }
+    let customization = customization.map(|x| x.for_destination(None)).transpose()?;
+
+    tracing::info!("Creating image file");
+    let f = crate::file::create(&dst)?;
+
+    // Whole image is written since unmapped blocks would leave the file short
+    let res = flash_to(
+        img,
+        None::<bb_helper::resolvable::LocalStringFile>,
+        f,
+        chan,
+        customization,
+        None,
+        Verify::None,
+        cancel,
+    )
+    .await;
+
+    if res.is_err() {
+        let _ = std::fs::remove_file(&dst);
+    }
+
+    res
+}
+
+/// Apply customization to an already flashed SD Card without writing the OS image again.
+#[tracing::instrument(name = "customize", skip_all, fields(dst = %dst.display()))]
+pub async fn customize(
+    dst: Box<Path>,
+    chan: Option<mpsc::Sender<Status>>,
+    customization: Customization,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    if !customization.validate() {
+        return Err(crate::Error::InvalidCustomizaton);
+    }
+    let customization = customization.for_destination(crate::pal::card_serial(&dst).as_deref())?;
 
     tracing::info!("Opening Destination");
     let dst_clone = dst.to_path_buf();
+
+    #[cfg(feature = "test-util")]
+    if crate::file::is_file(&dst_clone) {
+        let sd = crate::file::open(&dst_clone)?;
+        return customize_existing(sd, chan, customization, cancel).await;
+    }
+
+    crate::pal::check_busy(&dst_clone)?;
+    check_write_protected(&dst_clone)?;
     let sd = crate::pal::open(&dst_clone).await?;
+    customize_existing(sd, chan, customization, cancel).await
+}
 
+async fn customize_existing(
+    mut sd: impl Read + Write + Seek + Eject + std::fmt::Debug + Send + 'static,
+    mut chan: Option<mpsc::Sender<Status>>,
+    customization: Customization,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _guard = span.enter();
+        chan_send(chan.as_mut(), Status::Preparing);
+        check_token(cancel.as_ref())?;
+
+        tracing::info!("Applying customization");
+        let temp = crate::helpers::DeviceWrapper::new(&mut sd)?;
+        customization.customize(temp, chan.as_mut())?;
+
+        tracing::info!("Ejecting SD Card");
+        let _ = sd.eject();
+
+        Ok(())
+    })
+    .await
+    .unwrap()
+}
+
+/// Compare an SD Card with the OS image without writing anything. Fails with
+/// [`Error::ImageMismatch`](crate::Error::ImageMismatch) at the first block that differs.
+///
+/// Useful to check if an interrupted flashing had already written the whole image. Customization
+/// modifies the boot partition, so customized SD Cards never match.
+///
+/// When the image was flashed using `bmap`, the same bmap should be provided since blocks not
+/// mapped in it were never written.
+#[tracing::instrument(
+    name = "compare",
+    skip_all,
+    fields(dst = %dst.display(), bmap = bmap.is_some())
+)]
+pub async fn compare<R: Read + Send + 'static>(
+    img: impl bb_helper::resolvable::Resolvable<ResolvedType = (R, u64)>,
+    bmap: Option<impl bb_helper::resolvable::Resolvable<ResolvedType = Box<str>>>,
+    dst: Box<Path>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
     let mut tasks = tokio::task::JoinSet::new();
 
     tracing::info!("Resolving Image");
@@ -229,18 +551,143 @@ pub async fn flash<R: Read + Send + 'static>(
         ),
         None => None,
     };
-    let (img, img_size) = img.resolve(&mut tasks).await?;
+    let (img, _) = img.resolve(&mut tasks).await?;
 
-    let cancel_child = cancel.as_ref().map(|x| x.child_token());
+    tracing::info!("Opening Destination");
+    #[cfg(feature = "test-util")]
+    let sd = if crate::file::is_file(&dst) {
+        std::fs::File::open(&dst)
+            .map_err(|e| crate::Error::FailedToOpenDestination { source: e.into() })?
+    } else {
+        crate::pal::open_read(&dst).await?
+    };
+    #[cfg(not(feature = "test-util"))]
+    let sd = crate::pal::open_read(&dst).await?;
+
+    let span = tracing::Span::current();
     let res = tokio::task::spawn_blocking(move || {
-        flash_internal(img, img_size, bmap, sd, chan, customization, cancel_child)
+        let _guard = span.enter();
+        compare_internal(img, bmap, sd, cancel)
     })
     .await
     .unwrap();
 
-    // Cancel all tasks on drop
+    while let Some(t) = tasks.join_next().await {
+        if let Err(e) = t.unwrap() {
+            tasks.abort_all();
+            return Err(e.into());
+        }
+    }
+
+    res
+}
+
+/// With bmap, only the buffers written by [`writer_task_bmap`] are compared.
+fn compare_internal(
+    mut img: impl Read,
+    bmap: Option<bb_bmap_parser::Bmap>,
+    mut sd: impl Read + Seek,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    let mut expected = Box::new(DirectIoBuffer::<BUFFER_SIZE>::new());
+    let mut actual = Box::new(DirectIoBuffer::<BUFFER_SIZE>::new());
+    let mut ranges = bmap.as_ref().map(|x| x.block_map().peekable());
+    let mut pos = 0;
+    let mut sd_pos = sd.stream_position()?;
+
+    loop {
+        let count = read_aligned(&mut img, expected.as_mut_slice())?;
+        if count == 0 {
+            return Ok(());
+        }
+        let end = pos + count as u64;
+
+        // Any buffer lying even partially in a bmap range is written
+        let mapped = match ranges.as_mut() {
+            Some(r) => {
+                while r.next_if(|b| b.offset() + b.length() <= pos).is_some() {}
+                r.peek().is_some_and(|b| b.offset() < end)
+            }
+            None => true,
+        };
+
+        if mapped {
+            if sd_pos != pos {
+                sd.seek(SeekFrom::Start(pos))?;
+            }
+            sd.read_exact(&mut actual.as_mut_slice()[..count])?;
+            sd_pos = end;
+
+            if expected.as_slice()[..count] != actual.as_slice()[..count] {
+                return Err(crate::Error::ImageMismatch { offset: pos });
+            }
+        }
+
+        pos = end;
+        check_token(cancel.as_ref())?;
+    }
+}
+
+/// Fail before downloading anything instead of on the first write.
+pub(crate) fn check_write_protected(dst: &Path) -> Result<()> {
+    if crate::devices(false)
+        .into_iter()
+        .any(|x| x.path == dst && x.read_only)
+    {
+        Err(crate::Error::WriteProtected)
+    } else {
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn flash_to<R: Read + Send + 'static>(
+    img: impl bb_helper::resolvable::Resolvable<ResolvedType = (R, u64)>,
+    bmap: Option<impl bb_helper::resolvable::Resolvable<ResolvedType = Box<str>>>,
+    sd: impl Read + Write + Seek + Eject + std::fmt::Debug + Send + 'static,
+    chan: Option<mpsc::Sender<Status>>,
+    customization: Option<Customization>,
+    write_limit: Option<NonZeroU64>,
+    verify: Verify,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    let mut tasks = tokio::task::JoinSet::new();
+
+    tracing::info!("Resolving Image");
+    let bmap = match bmap {
+        Some(x) => Some(
+            bb_bmap_parser::Bmap::from_xml(&x.resolve(&mut tasks).await?)
+                .map_err(|_| crate::Error::InvalidBmap)?,
+        ),
+        None => None,
+    };
+    let (img, img_size) = img.resolve(&mut tasks).await?;
+
+    // Blocking task does not inherit the span by default
+    let span = tracing::Span::current();
+    span.record("img_size", img_size);
+
+    let cancel_child = cancel.as_ref().map(|x| x.child_token());
+    // Cancel the writer and all tasks when dropped, including while waiting for the writer
     let _drop_guard = cancel.map(|x| x.drop_guard());
 
+    let res = tokio::task::spawn_blocking(move || {
+        let _guard = span.enter();
+        flash_internal(
+            img,
+            img_size,
+            bmap,
+            sd,
+            chan,
+            customization,
+            write_limit,
+            verify,
+            cancel_child,
+        )
+    })
+    .await
+    .unwrap();
+
     while let Some(t) = tasks.join_next().await {
         if let Err(e) = t.unwrap() {
             tasks.abort_all();
@@ -251,28 +698,44 @@ pub async fn flash<R: Read + Send + 'static>(
     res
 }
 
+#[allow(clippy::too_many_arguments)]
 fn flash_internal(
     img: impl Read + Send,
     img_size: u64,
     bmap: Option<bb_bmap_parser::Bmap>,
     sd: impl Read + Write + Seek + Eject + std::fmt::Debug,
-    mut chan: Option<mpsc::Sender<f32>>,
+    mut chan: Option<mpsc::Sender<Status>>,
     customization: Option<Customization>,
+    write_limit: Option<NonZeroU64>,
+    verify: Verify,
     cancel: Option<tokio_util::sync::CancellationToken>,
 ) -> Result<()> {
-    chan_send(chan.as_mut(), 0.0);
+    chan_send(chan.as_mut(), Status::Preparing);
 
     let mut sd = crate::helpers::SdCardWrapper::new(sd);
 
     tracing::info!("Writing to SD Card");
-    write_sd(img, img_size, bmap, &mut sd, chan.as_mut(), cancel.clone())?;
+    let checks = write_sd(
+        img,
+        img_size,
+        bmap,
+        &mut sd,
+        chan.as_mut(),
+        write_limit,
+        verify,
+        cancel.clone(),
+    )?;
 
     check_token(cancel.as_ref())?;
 
+    tracing::info!("Verifying SD Card");
+    let mut buf = Box::new(DirectIoBuffer::<BUFFER_SIZE>::new());
+    checks.verify(&mut sd, buf.as_mut_slice(), chan.as_mut(), cancel.as_ref())?;
+
     tracing::info!("Applying customization");
     if let Some(c) = customization {
         let temp = crate::helpers::DeviceWrapper::new(&mut sd).unwrap();
-        c.customize(temp)?;
+        c.customize(temp, chan.as_mut())?;
     }
 
     tracing::info!("Ejecting SD Card");
@@ -283,7 +746,7 @@ fn flash_internal(
 
 #[cfg(test)]
 mod tests {
-    use crate::flashing::{BUFFER_SIZE, read_aligned};
+    use crate::flashing::{BUFFER_SIZE, compare_internal, read_aligned};
 
     use super::write_sd;
 
@@ -295,6 +758,137 @@ mod tests {
         std::io::Cursor::new(data.into())
     }
 
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn flash_file_destination() {
+        use bb_helper::resolvable::{LocalFile, LocalStringFile};
+
+        const FILE_LEN: usize = 16 * 1024;
+
+        let dir = tempfile::tempdir().unwrap();
+        let img_path = dir.path().join("image.img");
+        let dst_path = dir.path().join("sd.img");
+
+        let img = test_file(FILE_LEN);
+        std::fs::write(&img_path, img.get_ref()).unwrap();
+        std::fs::File::create(&dst_path).unwrap();
+
+        crate::flash(
+            LocalFile::new(img_path.into()),
+            None::<LocalStringFile>,
+            dst_path.clone().into(),
+            None,
+            None,
+            None,
+            crate::Verify::Full,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&dst_path).unwrap(), img.get_ref().as_ref());
+
+        crate::compare(
+            LocalFile::new(img_path.clone().into()),
+            None::<LocalStringFile>,
+            dst_path.clone().into(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let mut modified = img.get_ref().to_vec();
+        modified[FILE_LEN - 1] ^= 0xff;
+        std::fs::write(&dst_path, modified).unwrap();
+        assert!(matches!(
+            crate::compare(
+                LocalFile::new(img_path.into()),
+                None::<LocalStringFile>,
+                dst_path.into(),
+                None
+            )
+            .await,
+            Err(crate::Error::ImageMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn customize_file_destination() {
+        use std::io::Read;
+
+        use bb_helper::resolvable::{LocalFile, LocalStringFile};
+
+        use crate::{Customization, SysconfCustomization};
+
+        fn sysconf(path: &std::path::Path) -> String {
+            let mut sd = crate::file::open(path).unwrap();
+            let (start, end) = crate::customization::customization_partition(&mut sd).unwrap();
+            let slice = fscommon::StreamSlice::new(sd, start, end).unwrap();
+            let fs = fatfs::FileSystem::new(slice, fatfs::FsOptions::new()).unwrap();
+
+            let mut conf = String::new();
+            fs.root_dir()
+                .open_file("sysconf.txt")
+                .unwrap()
+                .read_to_string(&mut conf)
+                .unwrap();
+            conf
+        }
+
+        fn hostname(x: &str) -> Customization {
+            Customization::Sysconf(SysconfCustomization {
+                hostname: Some(x.into()),
+                ..Default::default()
+            })
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let img_path = dir.path().join("image.img");
+        let dst_path = dir.path().join("sd.img");
+
+        // Blank FAT32 card used as OS image
+        std::fs::File::create(&img_path)
+            .unwrap()
+            .set_len(64 * 1024 * 1024)
+            .unwrap();
+        crate::format(img_path.clone().into(), Default::default(), None, None)
+            .await
+            .unwrap();
+        std::fs::File::create(&dst_path).unwrap();
+
+        crate::flash(
+            LocalFile::new(img_path.clone().into()),
+            None::<LocalStringFile>,
+            dst_path.clone().into(),
+            None,
+            Some(hostname("beagle-one")),
+            None,
+            crate::Verify::Full,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(sysconf(&dst_path).contains("hostname=beagle-one\n"));
+
+        // Customization modifies the boot partition
+        assert!(matches!(
+            crate::compare(
+                LocalFile::new(img_path.into()),
+                None::<LocalStringFile>,
+                dst_path.clone().into(),
+                None
+            )
+            .await,
+            Err(crate::Error::ImageMismatch { .. })
+        ));
+
+        crate::customize(dst_path.clone().into(), None, hostname("beagle-two"), None)
+            .await
+            .unwrap();
+        assert!(sysconf(&dst_path).contains("hostname=beagle-two\n"));
+    }
+
     #[test]
     fn sd_write() {
         const FILE_LEN: usize = 12 * 1024;
@@ -309,6 +903,8 @@ mod tests {
             &mut sd,
             None,
             None,
+            crate::Verify::None,
+            None,
         )
         .unwrap();
 
@@ -316,23 +912,70 @@ mod tests {
     }
 
     #[test]
-    fn sd_write_bmap() {
-        const FILE_LEN: usize = 32 * 1024;
-        const BLOCK_LEN: u64 = BUFFER_SIZE as u64;
-        const BLOCKS: u64 = (FILE_LEN as u64) / BLOCK_LEN;
-        const MAPPED_BLOCKS: &[u64] = &[0, 2, BLOCKS - 1];
+    fn sd_write_throttled() {
+        const FILE_LEN: usize = 12 * 1024;
 
         let dummy_file = test_file(FILE_LEN);
-        let mut sd = std::io::Cursor::new(vec![0u8; FILE_LEN]);
+        let mut sd = std::io::Cursor::new(Vec::<u8>::new());
+        let start = std::time::Instant::now();
+
+        write_sd(
+            dummy_file.clone(),
+            FILE_LEN as u64,
+            None,
+            &mut sd,
+            None,
+            std::num::NonZeroU64::new(24 * 1024),
+            crate::Verify::None,
+            None,
+        )
+        .unwrap();
+
+        assert!(start.elapsed() >= std::time::Duration::from_millis(400));
+        assert_eq!(sd.get_ref().as_slice(), dummy_file.get_ref().as_ref());
+    }
+
+    #[test]
+    fn sd_write_aborted() {
+        const FILE_LEN: usize = 4 * BUFFER_SIZE;
+
+        let dummy_file = test_file(FILE_LEN);
+        let mut sd = std::io::Cursor::new(Vec::<u8>::new());
+        let cancel = tokio_util::sync::CancellationToken::new();
+        cancel.cancel();
+
+        let res = write_sd(
+            dummy_file.clone(),
+            FILE_LEN as u64,
+            None,
+            &mut sd,
+            None,
+            None,
+            crate::Verify::None,
+            Some(cancel),
+        );
+
+        let Err(crate::Error::AbortedAt { offset }) = res else {
+            panic!("Expected abort, got {:?}", res.err());
+        };
+        assert_eq!(offset, BUFFER_SIZE as u64);
+        assert_eq!(
+            sd.get_ref().as_slice(),
+            &dummy_file.get_ref()[..BUFFER_SIZE]
+        );
+    }
 
+    /// Bmap with blocks of [`BUFFER_SIZE`], mapping `mapped` blocks.
+    fn test_bmap(len: usize, mapped: &[u64]) -> bb_bmap_parser::Bmap {
+        let block_len = BUFFER_SIZE as u64;
         let mut bmap = bb_bmap_parser::Bmap::builder();
-        bmap.image_size(FILE_LEN as u64)
-            .block_size(BLOCK_LEN)
-            .blocks(BLOCKS)
-            .mapped_blocks(MAPPED_BLOCKS.len() as u64)
+        bmap.image_size(len as u64)
+            .block_size(block_len)
+            .blocks(len as u64 / block_len)
+            .mapped_blocks(mapped.len() as u64)
             .checksum_type(bb_bmap_parser::HashType::Sha256);
 
-        for i in MAPPED_BLOCKS {
+        for i in mapped {
             bmap.add_block_range(
                 *i,
                 *i,
@@ -340,15 +983,29 @@ mod tests {
             );
         }
 
-        let bmap = bmap.build().unwrap();
+        bmap.build().unwrap()
+    }
 
-        write_sd(
+    #[test]
+    fn sd_write_bmap() {
+        const FILE_LEN: usize = 32 * 1024;
+        const BLOCK_LEN: u64 = BUFFER_SIZE as u64;
+        const BLOCKS: u64 = (FILE_LEN as u64) / BLOCK_LEN;
+        const MAPPED_BLOCKS: &[u64] = &[0, 2, BLOCKS - 1];
+
+        let dummy_file = test_file(FILE_LEN);
+        let mut sd = std::io::Cursor::new(vec![0u8; FILE_LEN]);
+        let bmap = test_bmap(FILE_LEN, MAPPED_BLOCKS);
+
+        let checks = write_sd(
             dummy_file.clone(),
             FILE_LEN as u64,
             Some(bmap.clone()),
             &mut sd,
             None,
             None,
+            crate::Verify::Full,
+            None,
         )
         .unwrap();
 
@@ -367,6 +1024,50 @@ mod tests {
                 );
             }
         }
+
+        // Only written buffers are hashed
+        let mut buf = vec![0u8; BUFFER_SIZE];
+        sd.get_mut()[BLOCK_LEN as usize] = 0xff;
+        checks.verify(&mut sd, &mut buf, None, None).unwrap();
+
+        sd.get_mut()[2 * BLOCK_LEN as usize] ^= 0xff;
+        assert!(matches!(
+            checks.verify(&mut sd, &mut buf, None, None),
+            Err(crate::Error::VerificationFailed { offset }) if offset == 2 * BLOCK_LEN
+        ));
+    }
+
+    #[test]
+    fn compare_bmap() {
+        const FILE_LEN: usize = 4 * BUFFER_SIZE;
+
+        let dummy_file = test_file(FILE_LEN);
+        let bmap = test_bmap(FILE_LEN, &[1, 3]);
+        let mut sd = std::io::Cursor::new(vec![0u8; FILE_LEN]);
+
+        write_sd(
+            dummy_file.clone(),
+            FILE_LEN as u64,
+            Some(bmap.clone()),
+            &mut sd,
+            None,
+            None,
+            crate::Verify::None,
+            None,
+        )
+        .unwrap();
+
+        compare_internal(dummy_file.clone(), Some(bmap.clone()), &mut sd, None).unwrap();
+        assert!(matches!(
+            compare_internal(dummy_file.clone(), None, &mut sd, None),
+            Err(crate::Error::ImageMismatch { offset: 0 })
+        ));
+
+        sd.get_mut()[3 * BUFFER_SIZE] ^= 0xff;
+        assert!(matches!(
+            compare_internal(dummy_file, Some(bmap), &mut sd, None),
+            Err(crate::Error::ImageMismatch { offset }) if offset == 3 * BUFFER_SIZE as u64
+        ));
     }
 
     struct UnalignedReader(std::io::Cursor<Box<[u8]>>);
@@ -0,0 +1,444 @@
+//! Format SD Cards for reuse as regular storage.
+//!
+//! The partition table and filesystem are written directly to the card, the same way images are
+//! flashed, so cards are formatted the same way on every platform.
+//!
+//! ```no_run
+//! use bb_flasher_sd::format::{FormatOptions, PartitionScheme};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let options = FormatOptions {
+//!         label: Some("BEAGLE".to_string()),
+//!         scheme: PartitionScheme::Gpt,
+//!         ..Default::default()
+//!     };
+//!     let dst = std::path::PathBuf::from("/dev/sdb").into();
+//!
+//!     bb_flasher_sd::format(dst, options, None, None).await.unwrap();
+//! }
+//! ```
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use tokio::sync::mpsc;
+
+use crate::{
+    Error, Result, Status,
+    helpers::{DirectIoBuffer, Eject, chan_send, check_token, progress},
+};
+
+const SECTOR_SIZE: u64 = 512;
+/// Start of the partition. Aligned to the erase block size of most cards.
+const PARTITION_START: u64 = 1024 * 1024;
+/// Zeroed at both ends of the card in a quick format, to clear old partition tables and
+/// filesystem signatures.
+const QUICK_ERASE_LEN: u64 = PARTITION_START;
+const ERASE_BUFFER_SIZE: usize = 64 * 1024;
+
+const MBR_FAT32_LBA: u8 = 0x0c;
+const MBR_GPT_PROTECTIVE: u8 = 0xee;
+/// CHS address used by partitions which can only be addressed by LBA.
+const MBR_CHS_MAX: [u8; 3] = [0xfe, 0xff, 0xff];
+
+const GPT_ENTRIES: u64 = 128;
+const GPT_ENTRY_LEN: u64 = 128;
+const GPT_ENTRIES_SECTORS: u64 = GPT_ENTRIES * GPT_ENTRY_LEN / SECTOR_SIZE;
+const GPT_HEADER_LEN: usize = 92;
+/// Microsoft basic data partition (EBD0A0A2-B9E5-4433-87C0-68B6B72699C7) in on-disk byte order.
+const GPT_BASIC_DATA: [u8; 16] = [
+    0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99, 0xc7,
+];
+
+/// Filesystem created on the card.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Filesystem {
+    /// Supported by all operating systems and most devices.
+    #[default]
+    Fat32,
+}
+
+/// Partition table written to the card.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PartitionScheme {
+    /// Single partition in an MBR partition table. Supported by almost every device.
+    #[default]
+    Mbr,
+    /// Single partition in a GPT partition table.
+    Gpt,
+    /// Filesystem on the whole card, without a partition table.
+    None,
+}
+
+/// Options to format SD Cards with [`crate::format`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FormatOptions {
+    pub filesystem: Filesystem,
+    /// Volume label. See [`crate::label::valid_fat_label`].
+    pub label: Option<String>,
+    /// Overwrite the whole card with zeros before formatting, instead of only the start and end
+    /// of the card. Takes as long as flashing an image of the size of the card.
+    pub full: bool,
+    pub scheme: PartitionScheme,
+}
+
+impl FormatOptions {
+    pub fn validate(&self) -> bool {
+        self.label
+            .as_deref()
+            .is_none_or(crate::label::valid_fat_label)
+    }
+}
+
+/// Format `dst` as described by `options`. Only reports [`Status::Preparing`] and
+/// [`Status::Flashing`], which tracks erasing the card.
+#[tracing::instrument(name = "format", skip_all, fields(dst = %dst.display()))]
+pub(crate) async fn format(
+    dst: Box<Path>,
+    options: FormatOptions,
+    chan: Option<mpsc::Sender<Status>>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    if !options.validate() {
+        return Err(Error::FailedToFormat {
+            source: io::Error::new(io::ErrorKind::InvalidInput, "Invalid volume label"),
+        });
+    }
+
+    tracing::info!("Opening Destination");
+
+    #[cfg(feature = "test-util")]
+    if crate::file::is_file(&dst) {
+        let sd = crate::file::open(&dst)?;
+        return format_to(sd, None, options, chan, cancel).await;
+    }
+
+    crate::pal::check_busy(&dst)?;
+    crate::flashing::check_write_protected(&dst)?;
+    // Seeking to the end does not give the size of physical drives on all platforms
+    let size = crate::devices(false)
+        .into_iter()
+        .find(|x| *x.path == *dst)
+        .map(|x| x.size)
+        .filter(|x| *x != 0);
+    let sd = crate::pal::open(&dst).await?;
+    format_to(sd, size, options, chan, cancel).await
+}
+
+async fn format_to(
+    sd: impl Read + Write + Seek + Eject + std::fmt::Debug + Send + 'static,
+    size: Option<u64>,
+    options: FormatOptions,
+    mut chan: Option<mpsc::Sender<Status>>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _guard = span.enter();
+        chan_send(chan.as_mut(), Status::Preparing);
+
+        let mut sd = crate::helpers::SdCardWrapper::new(sd);
+        let size = match size {
+            Some(x) => x,
+            None => sd.seek(SeekFrom::End(0))?,
+        };
+
+        format_device(&mut sd, size, &options, |x| {
+            check_token(cancel.as_ref())?;
+            chan_send(chan.as_mut(), Status::Flashing(x));
+            Ok(())
+        })?;
+
+        tracing::info!("Ejecting SD Card");
+        let _ = sd.eject();
+
+        Ok(())
+    })
+    .await
+    .unwrap()
+}
+
+/// Erase, partition and format `dst` of `size` bytes. `on_progress` is called while erasing, and
+/// aborts formatting on error.
+fn format_device(
+    mut dst: impl Read + Write + Seek,
+    size: u64,
+    options: &FormatOptions,
+    mut on_progress: impl FnMut(f32) -> Result<()>,
+) -> Result<()> {
+    let size = size - size % SECTOR_SIZE;
+
+    tracing::info!("Erasing SD Card");
+    if options.full {
+        erase(&mut dst, 0..size, &mut on_progress)?;
+    } else {
+        let head = QUICK_ERASE_LEN.min(size);
+        let tail = size.saturating_sub(QUICK_ERASE_LEN).max(head);
+        erase(&mut dst, 0..head, |_| Ok(()))?;
+        erase(&mut dst, tail..size, |_| Ok(()))?;
+    }
+    on_progress(1.0)?;
+
+    tracing::info!("Writing partition table");
+    let mut dst = crate::helpers::DeviceWrapper::new(dst)?;
+    let (start, end) = partition(&mut dst, size, options.scheme)
+        .map_err(|source| Error::FailedToFormat { source })?;
+
+    tracing::info!("Creating filesystem");
+    let mut opts = fatfs::FormatVolumeOptions::new()
+        .fat_type(fatfs::FatType::Fat32)
+        .volume_id(random() as u32);
+    if let Some(x) = options.label.as_deref() {
+        opts = opts.volume_label(crate::label::fat_label_bytes(x));
+    }
+    let slice = fscommon::StreamSlice::new(&mut dst, start, end)?;
+    let mut part = fscommon::BufStream::new(slice);
+    fatfs::format_volume(&mut part, opts)
+        .and_then(|_| part.flush())
+        .map_err(|source| Error::FailedToFormat { source })?;
+    drop(part);
+
+    dst.flush()?;
+    Ok(())
+}
+
+/// Overwrite `range` with zeros.
+fn erase(
+    mut dst: impl Write + Seek,
+    range: std::ops::Range<u64>,
+    mut on_progress: impl FnMut(f32) -> Result<()>,
+) -> Result<()> {
+    let buf = Box::new(DirectIoBuffer::<ERASE_BUFFER_SIZE>::new());
+    let len = range.end - range.start;
+
+    dst.seek(SeekFrom::Start(range.start))?;
+    let mut pos = 0;
+    while pos < len {
+        let count = std::cmp::min(len - pos, ERASE_BUFFER_SIZE as u64);
+        dst.write_all(&buf.as_slice()[..count as usize])?;
+        pos += count;
+        on_progress(progress(pos, len))?;
+    }
+
+    Ok(())
+}
+
+/// Write the partition table for `scheme`. Returns the start and end offset of the partition.
+fn partition(
+    mut dst: impl Write + Seek,
+    size: u64,
+    scheme: PartitionScheme,
+) -> io::Result<(u64, u64)> {
+    let sectors = size / SECTOR_SIZE;
+
+    match scheme {
+        PartitionScheme::None => Ok((0, size)),
+        PartitionScheme::Mbr => {
+            let start = PARTITION_START / SECTOR_SIZE;
+            let len = sectors
+                .checked_sub(start)
+                .filter(|x| *x > 0)
+                .ok_or_else(too_small)?
+                .min(u32::MAX.into());
+            write_mbr(&mut dst, MBR_FAT32_LBA, start, len)?;
+
+            Ok((start * SECTOR_SIZE, (start + len) * SECTOR_SIZE))
+        }
+        PartitionScheme::Gpt => {
+            let start = PARTITION_START / SECTOR_SIZE;
+            // Backup header and entries are at the end of the card
+            let last = sectors
+                .checked_sub(GPT_ENTRIES_SECTORS + 2)
+                .filter(|x| *x >= start)
+                .ok_or_else(too_small)?;
+            write_gpt(&mut dst, sectors, start, last)?;
+
+            Ok((start * SECTOR_SIZE, (last + 1) * SECTOR_SIZE))
+        }
+    }
+}
+
+fn too_small() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "SD Card is too small")
+}
+
+/// Write an MBR with a single partition of type `sys`.
+fn write_mbr(mut dst: impl Write + Seek, sys: u8, start: u64, len: u64) -> io::Result<()> {
+    let mut mbr = [0u8; SECTOR_SIZE as usize];
+
+    mbr[440..444].copy_from_slice(&(random() as u32).to_le_bytes());
+    let entry = &mut mbr[446..462];
+    entry[1..4].copy_from_slice(&MBR_CHS_MAX);
+    entry[4] = sys;
+    entry[5..8].copy_from_slice(&MBR_CHS_MAX);
+    entry[8..12].copy_from_slice(&u32::try_from(start).unwrap_or(u32::MAX).to_le_bytes());
+    entry[12..16].copy_from_slice(&u32::try_from(len).unwrap_or(u32::MAX).to_le_bytes());
+    mbr[510..].copy_from_slice(&[0x55, 0xaa]);
+
+    dst.seek(SeekFrom::Start(0))?;
+    dst.write_all(&mbr)
+}
+
+/// Write a GPT with a single basic data partition from `first` to `last` sector, along with a
+/// protective MBR.
+fn write_gpt(mut dst: impl Write + Seek, sectors: u64, first: u64, last: u64) -> io::Result<()> {
+    let backup_lba = sectors - 1;
+    let backup_entries_lba = backup_lba - GPT_ENTRIES_SECTORS;
+
+    let mut entries = vec![0u8; (GPT_ENTRIES * GPT_ENTRY_LEN) as usize];
+    entries[..16].copy_from_slice(&GPT_BASIC_DATA);
+    entries[16..32].copy_from_slice(&guid());
+    entries[32..40].copy_from_slice(&first.to_le_bytes());
+    entries[40..48].copy_from_slice(&last.to_le_bytes());
+    for (i, x) in "Basic data partition".encode_utf16().enumerate() {
+        entries[56 + i * 2..58 + i * 2].copy_from_slice(&x.to_le_bytes());
+    }
+    let entries_crc = crc32(&entries);
+
+    let disk_guid = guid();
+    let header = |lba: u64, alternate: u64, entries_lba: u64| {
+        let mut h = [0u8; SECTOR_SIZE as usize];
+        h[..8].copy_from_slice(b"EFI PART");
+        h[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        h[12..16].copy_from_slice(&(GPT_HEADER_LEN as u32).to_le_bytes());
+        h[24..32].copy_from_slice(&lba.to_le_bytes());
+        h[32..40].copy_from_slice(&alternate.to_le_bytes());
+        h[40..48].copy_from_slice(&(GPT_ENTRIES_SECTORS + 2).to_le_bytes());
+        h[48..56].copy_from_slice(&(backup_entries_lba - 1).to_le_bytes());
+        h[56..72].copy_from_slice(&disk_guid);
+        h[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+        h[80..84].copy_from_slice(&(GPT_ENTRIES as u32).to_le_bytes());
+        h[84..88].copy_from_slice(&(GPT_ENTRY_LEN as u32).to_le_bytes());
+        h[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+        let crc = crc32(&h[..GPT_HEADER_LEN]);
+        h[16..20].copy_from_slice(&crc.to_le_bytes());
+        h
+    };
+
+    write_mbr(&mut dst, MBR_GPT_PROTECTIVE, 1, sectors - 1)?;
+
+    dst.seek(SeekFrom::Start(SECTOR_SIZE))?;
+    dst.write_all(&header(1, backup_lba, 2))?;
+    dst.write_all(&entries)?;
+
+    dst.seek(SeekFrom::Start(backup_entries_lba * SECTOR_SIZE))?;
+    dst.write_all(&entries)?;
+    dst.write_all(&header(backup_lba, 1, backup_entries_lba))?;
+
+    Ok(())
+}
+
+/// Random value for disk signatures, GUIDs and volume ids. Hash keys are randomly seeded, which
+/// is good enough to avoid collisions between cards.
+fn random() -> u64 {
+    RandomState::new().hash_one(std::time::SystemTime::now())
+}
+
+/// Random (version 4) GUID in on-disk byte order.
+fn guid() -> [u8; 16] {
+    let mut res = [0u8; 16];
+    res[..8].copy_from_slice(&random().to_le_bytes());
+    res[8..].copy_from_slice(&random().to_le_bytes());
+    res[7] = (res[7] & 0x0f) | 0x40;
+    res[8] = (res[8] & 0x3f) | 0x80;
+    res
+}
+
+/// CRC32 (IEEE) used by GPT.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, x| {
+        (0..8).fold(crc ^ u32::from(*x), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const SIZE: u64 = 64 * 1024 * 1024;
+
+    fn card() -> Cursor<Vec<u8>> {
+        Cursor::new(vec![0xffu8; SIZE as usize])
+    }
+
+    #[test]
+    fn crc32_check() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn mbr() {
+        let mut img = card();
+        assert_eq!(
+            partition(&mut img, SIZE, PartitionScheme::Mbr).unwrap(),
+            (PARTITION_START, SIZE)
+        );
+
+        let parts = crate::inspect::mbr_partitions(&mut img).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].start, PARTITION_START);
+        assert_eq!(parts[0].size, SIZE - PARTITION_START);
+        assert_eq!(parts[0].kind, "FAT32 (LBA)");
+    }
+
+    #[test]
+    fn gpt() {
+        let mut img = card();
+        let (start, end) = partition(&mut img, SIZE, PartitionScheme::Gpt).unwrap();
+        assert_eq!(start, PARTITION_START);
+        assert_eq!(end, SIZE - (GPT_ENTRIES_SECTORS + 1) * SECTOR_SIZE);
+
+        let parts = crate::inspect::gpt_partitions(&mut img).unwrap().unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].start, start);
+        assert_eq!(parts[0].size, end - start);
+        assert_eq!(parts[0].kind, "Basic data partition");
+
+        let disk = gpt::GptConfig::new()
+            .writable(false)
+            .open_from_device(&mut img)
+            .unwrap();
+        assert_eq!(disk.partitions().len(), 1);
+    }
+
+    #[test]
+    fn card_too_small() {
+        assert!(partition(card(), SECTOR_SIZE * 40, PartitionScheme::Gpt).is_err());
+        assert!(partition(card(), PARTITION_START, PartitionScheme::Mbr).is_err());
+    }
+
+    #[test]
+    fn format_fat32() {
+        let mut img = card();
+        let options = FormatOptions {
+            label: Some("beagle".to_string()),
+            full: true,
+            ..Default::default()
+        };
+        let mut last = 0.0;
+        format_device(&mut img, SIZE, &options, |x| {
+            last = x;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(last, 1.0);
+
+        let slice = fscommon::StreamSlice::new(&mut img, PARTITION_START, SIZE).unwrap();
+        let fs = fatfs::FileSystem::new(slice, fatfs::FsOptions::new()).unwrap();
+        assert_eq!(fs.fat_type(), fatfs::FatType::Fat32);
+        assert_eq!(fs.volume_label().trim_end(), "BEAGLE");
+    }
+}
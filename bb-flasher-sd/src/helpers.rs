@@ -1,10 +1,14 @@
-use std::io;
+use std::{
+    io,
+    num::NonZeroU64,
+    time::{Duration, Instant},
+};
 
 use tokio::sync::mpsc;
 
-use crate::Result;
+use crate::{Result, Status};
 
-pub(crate) fn chan_send(chan: Option<&mut mpsc::Sender<f32>>, msg: f32) {
+pub(crate) fn chan_send(chan: Option<&mut mpsc::Sender<Status>>, msg: Status) {
     if let Some(c) = chan {
         let _ = c.try_send(msg);
     }
@@ -21,6 +25,33 @@ pub(crate) fn check_token(cancel: Option<&tokio_util::sync::CancellationToken>)
     }
 }
 
+/// Limits the average write speed by sleeping whenever writes get ahead of the allowed rate.
+pub(crate) struct Throttle {
+    rate: NonZeroU64,
+    start: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    /// `rate` is in bytes per second.
+    pub(crate) fn new(rate: NonZeroU64) -> Self {
+        Self {
+            rate,
+            start: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    pub(crate) fn consume(&mut self, count: u64) {
+        self.bytes += count;
+
+        let expected = Duration::from_secs_f64(self.bytes as f64 / self.rate.get() as f64);
+        if let Some(x) = expected.checked_sub(self.start.elapsed()) {
+            std::thread::sleep(x);
+        }
+    }
+}
+
 pub(crate) trait Eject {
     fn eject(self) -> io::Result<()>;
 }
@@ -260,6 +291,40 @@ where
     }
 }
 
+/// Generate disk activity by reading random chunks in short bursts, so that the activity LED of
+/// the card reader blinks. Reads are harmless and data is discarded.
+pub(crate) fn blink<F: io::Read + io::Seek>(
+    mut f: F,
+    size: u64,
+    duration: Duration,
+) -> io::Result<()> {
+    const CHUNK: u64 = 64 * 1024;
+    const BURST: Duration = Duration::from_millis(300);
+
+    let chunks = (size / CHUNK).max(1);
+    let mut buf = vec![0u8; CHUNK as usize];
+    // Random offsets make sure reads are not served from cache
+    let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+    let start = Instant::now();
+
+    while start.elapsed() < duration {
+        let burst = Instant::now();
+        while burst.elapsed() < BURST {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+
+            f.seek(io::SeekFrom::Start((seed % chunks) * CHUNK))?;
+            let len = std::cmp::min(CHUNK, size) as usize;
+            f.read_exact(&mut buf[..len])?;
+        }
+
+        std::thread::sleep(BURST);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::{Read, Seek, SeekFrom, Write};
@@ -352,4 +417,14 @@ mod tests {
         sd.finish().unwrap();
         assert_eq!(test_data.get_ref(), sd.inner.get_ref());
     }
+
+    #[test]
+    fn blink() {
+        let data = test_data();
+        let start = std::time::Instant::now();
+
+        super::blink(data, FILE_LEN as u64, std::time::Duration::from_millis(100)).unwrap();
+
+        assert!(start.elapsed() >= std::time::Duration::from_millis(100));
+    }
 }
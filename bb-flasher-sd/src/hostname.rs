@@ -0,0 +1,176 @@
+//! Hostname templates, so cards flashed with the same customization get unique hostnames and do
+//! not collide on mDNS.
+//!
+//! Templates are expanded separately for each destination. Supported placeholders:
+//!
+//! - `{randN}`: `N` random hex digits.
+//! - `{serialN}`: Last `N` hex digits of the SD Card serial number. Only card readers which expose
+//!   the card identification register (usually built-in readers on Linux) report it. Random digits
+//!   are used if the serial number is not available.
+//!
+//! `N` can be between 1 and [`MAX_DIGITS`].
+//!
+//! ```
+//! let hostname = bb_flasher_sd::hostname::expand("beagle-{rand4}", None).unwrap();
+//! assert_eq!(hostname.len(), "beagle-".len() + 4);
+//! ```
+
+use std::{
+    fmt::Display,
+    hash::{BuildHasher, Hasher},
+    path::Path,
+};
+
+/// Maximum number of digits a placeholder can expand to.
+pub const MAX_DIGITS: usize = 16;
+
+/// Errors in hostname templates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Placeholder is not supported.
+    UnknownPlaceholder(String),
+    /// Placeholder is missing `}`.
+    Unclosed,
+    /// Number of digits is not between 1 and [`MAX_DIGITS`].
+    InvalidDigits(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownPlaceholder(x) => write!(f, "Unknown hostname placeholder {{{x}}}"),
+            Self::Unclosed => write!(f, "Hostname placeholder is missing a closing brace"),
+            Self::InvalidDigits(x) => write!(
+                f,
+                "Hostname placeholder {{{x}}} should have between 1 and {MAX_DIGITS} digits"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+enum Placeholder {
+    Rand(usize),
+    Serial(usize),
+}
+
+impl std::str::FromStr for Placeholder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, digits): (fn(usize) -> Self, _) = if let Some(x) = s.strip_prefix("rand") {
+            (Self::Rand, x)
+        } else if let Some(x) = s.strip_prefix("serial") {
+            (Self::Serial, x)
+        } else {
+            return Err(Error::UnknownPlaceholder(s.to_string()));
+        };
+
+        match digits.parse() {
+            Ok(x) if (1..=MAX_DIGITS).contains(&x) => Ok(kind(x)),
+            _ => Err(Error::InvalidDigits(s.to_string())),
+        }
+    }
+}
+
+/// Check if `hostname` contains placeholders.
+pub fn is_template(hostname: &str) -> bool {
+    hostname.contains('{')
+}
+
+/// Check that all placeholders in `template` are valid.
+pub fn validate(template: &str) -> Result<(), Error> {
+    expand_with(template, |_| String::new()).map(|_| ())
+}
+
+/// Expand all placeholders in `template`. `serial` is the serial number of the SD Card, if known.
+pub fn expand(template: &str, serial: Option<&str>) -> Result<String, Error> {
+    let serial: Option<String> = serial.map(|x| {
+        x.trim()
+            .trim_start_matches("0x")
+            .chars()
+            .filter(char::is_ascii_hexdigit)
+            .map(|x| x.to_ascii_lowercase())
+            .collect()
+    });
+
+    expand_with(template, |p| match p {
+        Placeholder::Rand(n) => random_hex(n),
+        Placeholder::Serial(n) => match serial.as_deref().filter(|x| x.len() >= n) {
+            Some(x) => x[x.len() - n..].to_string(),
+            None => {
+                tracing::warn!("SD Card serial number not available, using random digits");
+                random_hex(n)
+            }
+        },
+    })
+}
+
+/// Serial number of the SD Card at `dst`, if the card reader exposes it.
+pub fn card_serial(dst: &Path) -> Option<String> {
+    crate::pal::card_serial(dst)
+}
+
+fn expand_with(template: &str, mut f: impl FnMut(Placeholder) -> String) -> Result<String, Error> {
+    let mut res = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or(Error::Unclosed)? + start;
+        res.push_str(&rest[..start]);
+        res.push_str(&f(rest[start + 1..end].parse()?));
+        rest = &rest[end + 1..];
+    }
+    res.push_str(rest);
+
+    Ok(res)
+}
+
+fn random_hex(digits: usize) -> String {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    if let Ok(x) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        hasher.write_u128(x.as_nanos());
+    }
+
+    let x = format!("{:016x}", hasher.finish());
+    x[x.len() - digits..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, expand, validate};
+
+    #[test]
+    fn expand_serial() {
+        assert_eq!(
+            expand("beagle-{serial4}", Some("0x1A2B3C4D")).unwrap(),
+            "beagle-3c4d"
+        );
+        assert_eq!(expand("beagle", None).unwrap(), "beagle");
+    }
+
+    #[test]
+    fn expand_rand() {
+        let x = expand("lab-{rand4}-{serial2}", None).unwrap();
+        assert_eq!(x.len(), "lab-".len() + 4 + 1 + 2);
+        assert!(x[4..8].chars().all(|x| x.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn invalid_template() {
+        assert_eq!(
+            validate("beagle-{mac4}"),
+            Err(Error::UnknownPlaceholder("mac4".to_string()))
+        );
+        assert_eq!(validate("beagle-{rand4"), Err(Error::Unclosed));
+        assert_eq!(
+            validate("beagle-{rand0}"),
+            Err(Error::InvalidDigits("rand0".to_string()))
+        );
+        assert_eq!(
+            validate("beagle-{rand17}"),
+            Err(Error::InvalidDigits("rand17".to_string()))
+        );
+    }
+}
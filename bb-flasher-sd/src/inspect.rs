@@ -0,0 +1,393 @@
+//! Inspect partitions and metadata of OS images before flashing.
+//!
+//! Works on (decompressed) image streams. Since compressed images cannot be seeked, the stream is
+//! only read forward. Chunks that have been read are cached to allow seeking back to them.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+use crate::{Error, Result};
+
+const SECTOR_SIZE: u64 = 512;
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+const EXT_SUPERBLOCK_OFFSET: u64 = 1024;
+const EXT_MAGIC: u16 = 0xef53;
+
+/// Information about contents of an OS image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub partition_table: PartitionTable,
+    /// Partitions sorted by their start offset.
+    pub partitions: Vec<Partition>,
+    /// Distro name and version, if it could be detected.
+    pub distro: Option<String>,
+}
+
+/// Type of partition table present in the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionTable {
+    Mbr,
+    Gpt,
+}
+
+impl Display for PartitionTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mbr => write!(f, "MBR"),
+            Self::Gpt => write!(f, "GPT"),
+        }
+    }
+}
+
+/// A single partition in the image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    /// Partition number as present in the partition table. Starts from 1.
+    pub number: u32,
+    /// Offset from the start of image in bytes.
+    pub start: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Partition name for GPT. Partition type for MBR.
+    pub kind: String,
+    /// Filesystem label.
+    pub label: Option<String>,
+    pub filesystem: Option<Filesystem>,
+}
+
+/// Filesystems which can be detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filesystem {
+    Fat,
+    Ext,
+}
+
+impl Display for Filesystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fat => write!(f, "FAT"),
+            Self::Ext => write!(f, "ext2/3/4"),
+        }
+    }
+}
+
+/// Inspect a (decompressed) OS image of `size` bytes.
+pub fn inspect(img: impl Read, size: u64) -> Result<ImageInfo> {
+    let mut img = ForwardStream::new(img, size);
+
+    let (partition_table, mut partitions) = match gpt_partitions(&mut img)? {
+        Some(x) => (PartitionTable::Gpt, x),
+        None => (PartitionTable::Mbr, mbr_partitions(&mut img)?),
+    };
+    partitions.sort_by_key(|x| x.start);
+
+    let mut distro = None;
+    for p in partitions.iter_mut() {
+        let Ok(mut slice) = fscommon::StreamSlice::new(&mut img, p.start, p.start + p.size) else {
+            continue;
+        };
+
+        if is_fat(&mut slice) {
+            p.filesystem = Some(Filesystem::Fat);
+
+            if let Ok(fs) =
+                fatfs::FileSystem::new(fscommon::BufStream::new(slice), fatfs::FsOptions::new())
+            {
+                p.label = Some(fs.volume_label().trim().to_string()).filter(|x| !x.is_empty());
+                if distro.is_none() {
+                    distro = fat_distro(&fs);
+                }
+            }
+        } else if let Some(label) = ext_label(&mut slice) {
+            p.filesystem = Some(Filesystem::Ext);
+            p.label = Some(label).filter(|x| !x.is_empty());
+        }
+    }
+
+    Ok(ImageInfo {
+        partition_table,
+        partitions,
+        distro,
+    })
+}
+
+/// The gpt crate also reads the backup header at the end of the disk, which would require
+/// decompressing the complete image. So parse the primary header by hand.
+pub(crate) fn gpt_partitions(mut img: impl Read + Seek) -> Result<Option<Vec<Partition>>> {
+    let mut header = [0u8; 92];
+    img.seek(SeekFrom::Start(SECTOR_SIZE))?;
+    img.read_exact(&mut header)?;
+
+    if &header[..8] != b"EFI PART" {
+        return Ok(None);
+    }
+
+    let entries_lba = u64::from_le_bytes(header[72..80].try_into().unwrap());
+    let entries_num = u32::from_le_bytes(header[80..84].try_into().unwrap());
+    let entry_size = u32::from_le_bytes(header[84..88].try_into().unwrap());
+
+    if entry_size < 128 {
+        return Err(Error::InvalidPartitionTable);
+    }
+
+    img.seek(SeekFrom::Start(entries_lba * SECTOR_SIZE))?;
+    let mut entry = vec![0u8; entry_size as usize];
+    let mut partitions = Vec::new();
+
+    for number in 1..=entries_num {
+        img.read_exact(&mut entry)?;
+
+        // Unused entries have zero type guid
+        if entry[..16].iter().all(|x| *x == 0) {
+            continue;
+        }
+
+        let first_lba = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+        let last_lba = u64::from_le_bytes(entry[40..48].try_into().unwrap());
+        let name: Vec<u16> = entry[56..128]
+            .chunks_exact(2)
+            .map(|x| u16::from_le_bytes([x[0], x[1]]))
+            .take_while(|x| *x != 0)
+            .collect();
+
+        partitions.push(Partition {
+            number,
+            start: first_lba * SECTOR_SIZE,
+            size: (last_lba + 1).saturating_sub(first_lba) * SECTOR_SIZE,
+            kind: String::from_utf16_lossy(&name),
+            label: None,
+            filesystem: None,
+        });
+    }
+
+    Ok(Some(partitions))
+}
+
+pub(crate) fn mbr_partitions(mut img: impl Read + Seek) -> Result<Vec<Partition>> {
+    img.seek(SeekFrom::Start(0))?;
+    let mbr = mbrman::MBRHeader::read_from(&mut img).map_err(|_| Error::InvalidPartitionTable)?;
+
+    Ok((1..=4)
+        .filter_map(|i| Some((i, mbr.get(i)?)))
+        .filter(|(_, x)| x.sys != 0)
+        .map(|(i, x)| Partition {
+            number: i as u32,
+            start: u64::from(x.starting_lba) * SECTOR_SIZE,
+            size: u64::from(x.sectors) * SECTOR_SIZE,
+            kind: mbr_type(x.sys),
+            label: None,
+            filesystem: None,
+        })
+        .collect())
+}
+
+fn mbr_type(sys: u8) -> String {
+    match sys {
+        0x05 | 0x0f => "Extended".to_string(),
+        0x0b => "FAT32".to_string(),
+        0x0c => "FAT32 (LBA)".to_string(),
+        0x0e => "FAT16 (LBA)".to_string(),
+        0x82 => "Linux swap".to_string(),
+        0x83 => "Linux".to_string(),
+        0xee => "GPT protective".to_string(),
+        _ => format!("0x{sys:02x}"),
+    }
+}
+
+fn is_fat(mut part: impl Read + Seek) -> bool {
+    let mut boot = [0u8; 512];
+    if part.seek(SeekFrom::Start(0)).is_err() || part.read_exact(&mut boot).is_err() {
+        return false;
+    }
+
+    boot[510..] == [0x55, 0xaa] && (&boot[0x36..0x39] == b"FAT" || &boot[0x52..0x57] == b"FAT32")
+}
+
+pub(crate) fn is_ext(part: impl Read + Seek) -> bool {
+    ext_label(part).is_some()
+}
+
+fn ext_label(mut part: impl Read + Seek) -> Option<String> {
+    let mut sb = [0u8; 136];
+    part.seek(SeekFrom::Start(EXT_SUPERBLOCK_OFFSET)).ok()?;
+    part.read_exact(&mut sb).ok()?;
+
+    if u16::from_le_bytes([sb[56], sb[57]]) != EXT_MAGIC {
+        return None;
+    }
+
+    let label = sb[120..136].split(|x| *x == 0).next().unwrap_or_default();
+    Some(String::from_utf8_lossy(label).trim().to_string())
+}
+
+/// BeagleBoard.org images contain `ID.txt` in the boot partition. Fallback to `os-release` for
+/// other images.
+fn fat_distro<T: fatfs::ReadWriteSeek>(fs: &fatfs::FileSystem<T>) -> Option<String> {
+    let root = fs.root_dir();
+
+    if let Some(id) = fat_read_to_string(&root, "ID.txt")
+        && let Some(line) = id.lines().map(str::trim).find(|x| !x.is_empty())
+    {
+        return Some(line.to_string());
+    }
+
+    let os_release = fat_read_to_string(&root, "os-release")?;
+    os_release.lines().find_map(|x| {
+        x.strip_prefix("PRETTY_NAME=")
+            .map(|x| x.trim_matches('"').to_string())
+    })
+}
+
+fn fat_read_to_string<T: fatfs::ReadWriteSeek>(
+    dir: &fatfs::Dir<'_, T>,
+    path: &str,
+) -> Option<String> {
+    let mut f = dir.open_file(path).ok()?;
+    let mut res = String::new();
+    f.read_to_string(&mut res).ok()?;
+    Some(res)
+}
+
+/// Seekable wrapper over a forward only stream. Seeking back is only possible to chunks which have
+/// been read before.
+struct ForwardStream<R> {
+    inner: R,
+    /// Offset of the next byte in inner stream
+    inner_pos: u64,
+    pos: u64,
+    size: u64,
+    chunks: HashMap<u64, Box<[u8]>>,
+}
+
+impl<R: Read> ForwardStream<R> {
+    fn new(inner: R, size: u64) -> Self {
+        Self {
+            inner,
+            inner_pos: 0,
+            pos: 0,
+            size,
+            chunks: HashMap::new(),
+        }
+    }
+
+    fn chunk(&mut self, idx: u64) -> io::Result<&[u8]> {
+        if !self.chunks.contains_key(&idx) {
+            let start = idx * CHUNK_SIZE;
+            if start < self.inner_pos {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Cannot seek back in image stream",
+                ));
+            }
+
+            io::copy(
+                &mut (&mut self.inner).take(start - self.inner_pos),
+                &mut io::sink(),
+            )?;
+
+            let mut buf = Vec::with_capacity(CHUNK_SIZE as usize);
+            (&mut self.inner).take(CHUNK_SIZE).read_to_end(&mut buf)?;
+            self.inner_pos = start + buf.len() as u64;
+            self.chunks.insert(idx, buf.into());
+        }
+
+        Ok(&self.chunks[&idx])
+    }
+}
+
+impl<R: Read> Read for ForwardStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.size {
+            return Ok(0);
+        }
+
+        let offset = (self.pos % CHUNK_SIZE) as usize;
+        let chunk = self.chunk(self.pos / CHUNK_SIZE)?;
+        if chunk.len() <= offset {
+            return Ok(0);
+        }
+
+        let count = buf.len().min(chunk.len() - offset);
+        buf[..count].copy_from_slice(&chunk[offset..offset + count]);
+        self.pos += count as u64;
+
+        Ok(count)
+    }
+}
+
+impl<R> Seek for ForwardStream<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(x) => Some(x),
+            SeekFrom::End(x) => self.size.checked_add_signed(x),
+            SeekFrom::Current(x) => self.pos.checked_add_signed(x),
+        };
+
+        self.pos = new_pos.ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid seek to a negative or overflowing position",
+        ))?;
+        Ok(self.pos)
+    }
+}
+
+/// Filesystem libraries require writable streams, even for read-only access.
+impl<R> Write for ForwardStream<R> {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Image stream is read-only",
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filesystem, PartitionTable, inspect};
+
+    fn mbr_image() -> Vec<u8> {
+        const PART_START: usize = 2048;
+
+        let mut img = vec![0u8; 4 * 1024 * 1024];
+
+        // Single Linux partition
+        let entry = &mut img[446..462];
+        entry[4] = 0x83;
+        entry[8..12].copy_from_slice(&(PART_START as u32).to_le_bytes());
+        entry[12..16].copy_from_slice(&4096u32.to_le_bytes());
+        img[510] = 0x55;
+        img[511] = 0xaa;
+
+        // ext superblock
+        let sb = PART_START * 512 + 1024;
+        img[sb + 56..sb + 58].copy_from_slice(&0xef53u16.to_le_bytes());
+        img[sb + 120..sb + 126].copy_from_slice(b"rootfs");
+
+        img
+    }
+
+    #[test]
+    fn inspect_mbr() {
+        let img = mbr_image();
+        let info = inspect(std::io::Cursor::new(&img), img.len() as u64).unwrap();
+
+        assert_eq!(info.partition_table, PartitionTable::Mbr);
+        assert_eq!(info.partitions.len(), 1);
+
+        let p = &info.partitions[0];
+        assert_eq!(p.number, 1);
+        assert_eq!(p.start, 2048 * 512);
+        assert_eq!(p.size, 4096 * 512);
+        assert_eq!(p.kind, "Linux");
+        assert_eq!(p.filesystem, Some(Filesystem::Ext));
+        assert_eq!(p.label.as_deref(), Some("rootfs"));
+    }
+}
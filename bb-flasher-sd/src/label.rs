@@ -0,0 +1,261 @@
+//! Set filesystem labels, so flashed SD Cards can be identified when mounted on a workstation.
+//!
+//! Neither fatfs nor any other dependency supports relabeling existing filesystems, so the labels
+//! are patched in place.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Maximum length of a FAT volume label.
+pub const FAT_LABEL_LEN: usize = 11;
+/// Maximum length of an ext volume label in bytes.
+pub const EXT_LABEL_LEN: usize = 16;
+
+const FAT_INVALID_CHARS: &[u8] = b"\"*+,./:;<=>?[\\]|";
+const FAT_ATTR_VOLUME_ID: u8 = 0x08;
+const FAT_ATTR_LFN: u8 = 0x0f;
+const FAT_DIR_ENTRY_LEN: usize = 32;
+
+const EXT_SUPERBLOCK_OFFSET: u64 = 1024;
+const EXT_SUPERBLOCK_LEN: usize = 1024;
+const EXT_MAGIC: u16 = 0xef53;
+const EXT_FEATURE_RO_COMPAT_METADATA_CSUM: u32 = 0x400;
+
+/// Check if `label` can be used as a FAT volume label. Lowercase letters are allowed, and
+/// converted to uppercase when written.
+pub fn valid_fat_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= FAT_LABEL_LEN
+        && !label.starts_with(' ')
+        && label
+            .bytes()
+            .all(|x| x.is_ascii() && !x.is_ascii_control() && !FAT_INVALID_CHARS.contains(&x))
+}
+
+/// Check if `label` can be used as an ext volume label.
+pub fn valid_ext_label(label: &str) -> bool {
+    !label.is_empty() && label.len() <= EXT_LABEL_LEN && !label.contains('\0')
+}
+
+pub(crate) fn fat_label_bytes(label: &str) -> [u8; FAT_LABEL_LEN] {
+    let mut res = [b' '; FAT_LABEL_LEN];
+    for (r, x) in res.iter_mut().zip(label.bytes()) {
+        *r = x.to_ascii_uppercase();
+    }
+    res
+}
+
+fn read_at(mut part: impl Read + Seek, pos: u64, buf: &mut [u8]) -> io::Result<()> {
+    part.seek(SeekFrom::Start(pos))?;
+    part.read_exact(buf)
+}
+
+fn write_at(mut part: impl Write + Seek, pos: u64, buf: &[u8]) -> io::Result<()> {
+    part.seek(SeekFrom::Start(pos))?;
+    part.write_all(buf)
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap())
+}
+
+/// Set the label of the FAT filesystem in `part`.
+///
+/// The label is stored both in the boot sector and as an entry in the root directory. Windows only
+/// uses the latter, so it is created if missing.
+pub(crate) fn set_fat_label(mut part: impl Read + Write + Seek, label: &str) -> io::Result<()> {
+    if !valid_fat_label(label) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid FAT volume label",
+        ));
+    }
+    let label = fat_label_bytes(label);
+
+    let mut boot = [0u8; 512];
+    read_at(&mut part, 0, &mut boot)?;
+    if boot[510..] != [0x55, 0xaa] {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not a FAT filesystem",
+        ));
+    }
+
+    let bytes_per_sector = u64::from(u16_at(&boot, 11));
+    let sectors_per_cluster = u64::from(boot[13]);
+    let reserved_sectors = u64::from(u16_at(&boot, 14));
+    let fats = u64::from(boot[16]);
+    let root_entries = u64::from(u16_at(&boot, 17));
+    let fat_size_16 = u64::from(u16_at(&boot, 22));
+
+    // FAT32 does not have a fixed size root directory. The label entry is expected in the first
+    // cluster of the root directory.
+    let (label_offset, root_dir, root_dir_len) = if fat_size_16 == 0 {
+        let fat_size = u64::from(u32_at(&boot, 36));
+        let root_cluster = u64::from(u32_at(&boot, 44));
+        let data = (reserved_sectors + fats * fat_size) * bytes_per_sector;
+        let cluster_len = sectors_per_cluster * bytes_per_sector;
+
+        (
+            71,
+            data + root_cluster.saturating_sub(2) * cluster_len,
+            cluster_len,
+        )
+    } else {
+        (
+            43,
+            (reserved_sectors + fats * fat_size_16) * bytes_per_sector,
+            root_entries * FAT_DIR_ENTRY_LEN as u64,
+        )
+    };
+
+    // The label field is only present with the extended boot signature
+    if boot[label_offset - 5] == 0x29 {
+        boot[label_offset..label_offset + FAT_LABEL_LEN].copy_from_slice(&label);
+        write_at(&mut part, 0, &boot)?;
+    }
+
+    let mut dir = vec![0u8; usize::try_from(root_dir_len).map_err(io::Error::other)?];
+    read_at(&mut part, root_dir, &mut dir)?;
+
+    let mut free = None;
+    for (i, entry) in dir.chunks_exact_mut(FAT_DIR_ENTRY_LEN).enumerate() {
+        let attr = entry[11];
+        match entry[0] {
+            0x00 => {
+                free = free.or(Some(i));
+                break;
+            }
+            0xe5 => free = free.or(Some(i)),
+            _ if attr != FAT_ATTR_LFN && attr & FAT_ATTR_VOLUME_ID != 0 => {
+                entry[..FAT_LABEL_LEN].copy_from_slice(&label);
+                let pos = root_dir + (i * FAT_DIR_ENTRY_LEN) as u64;
+                return write_at(&mut part, pos, entry);
+            }
+            _ => {}
+        }
+    }
+
+    match free {
+        Some(i) => {
+            let mut entry = [0u8; FAT_DIR_ENTRY_LEN];
+            entry[..FAT_LABEL_LEN].copy_from_slice(&label);
+            entry[11] = FAT_ATTR_VOLUME_ID;
+            write_at(&mut part, root_dir + (i * FAT_DIR_ENTRY_LEN) as u64, &entry)
+        }
+        None => {
+            tracing::warn!("No space for volume label in root directory");
+            Ok(())
+        }
+    }
+}
+
+/// Set the label of the ext filesystem in `part`. Only the primary superblock is updated, same as
+/// `e2label`.
+pub(crate) fn set_ext_label(mut part: impl Read + Write + Seek, label: &str) -> io::Result<()> {
+    if !valid_ext_label(label) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid ext volume label",
+        ));
+    }
+
+    let mut sb = [0u8; EXT_SUPERBLOCK_LEN];
+    read_at(&mut part, EXT_SUPERBLOCK_OFFSET, &mut sb)?;
+    if u16_at(&sb, 56) != EXT_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not an ext filesystem",
+        ));
+    }
+
+    sb[120..120 + EXT_LABEL_LEN].fill(0);
+    sb[120..120 + label.len()].copy_from_slice(label.as_bytes());
+
+    if u32_at(&sb, 100) & EXT_FEATURE_RO_COMPAT_METADATA_CSUM != 0 {
+        let csum = crc32c(!0, &sb[..EXT_SUPERBLOCK_LEN - 4]);
+        sb[EXT_SUPERBLOCK_LEN - 4..].copy_from_slice(&csum.to_le_bytes());
+    }
+
+    write_at(&mut part, EXT_SUPERBLOCK_OFFSET, &sb)
+}
+
+/// CRC32C without the final inversion, as used for ext4 metadata checksums.
+fn crc32c(seed: u32, data: &[u8]) -> u32 {
+    data.iter().fold(seed, |crc, x| {
+        (0..8).fold(crc ^ u32::from(*x), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn fat_image() -> Cursor<Vec<u8>> {
+        let mut img = Cursor::new(vec![0u8; 8 * 1024 * 1024]);
+        fatfs::format_volume(
+            &mut img,
+            fatfs::FormatVolumeOptions::new().volume_label(*b"BOOT       "),
+        )
+        .unwrap();
+        img
+    }
+
+    #[test]
+    fn fat_label() {
+        let mut img = fat_image();
+        set_fat_label(&mut img, "my-Beagle").unwrap();
+
+        let fs = fatfs::FileSystem::new(&mut img, fatfs::FsOptions::new()).unwrap();
+        assert_eq!(fs.volume_label().trim_end(), "MY-BEAGLE");
+        assert_eq!(
+            fs.read_volume_label_from_root_dir()
+                .unwrap()
+                .map(|x| x.trim_end().to_string()),
+            Some("MY-BEAGLE".to_string())
+        );
+    }
+
+    #[test]
+    fn fat_label_invalid() {
+        assert!(valid_fat_label("BEAGLE_01"));
+        assert!(!valid_fat_label(""));
+        assert!(!valid_fat_label("BEAGLEBONE01"));
+        assert!(!valid_fat_label("a.b"));
+        assert!(set_fat_label(fat_image(), "a/b").is_err());
+    }
+
+    #[test]
+    fn ext_label() {
+        let mut img = vec![0u8; 4096];
+        let sb = EXT_SUPERBLOCK_OFFSET as usize;
+        img[sb + 56..sb + 58].copy_from_slice(&EXT_MAGIC.to_le_bytes());
+        img[sb + 100..sb + 104].copy_from_slice(&EXT_FEATURE_RO_COMPAT_METADATA_CSUM.to_le_bytes());
+        img[sb + 120..sb + 126].copy_from_slice(b"rootfs");
+
+        let mut img = Cursor::new(img);
+        set_ext_label(&mut img, "beagle").unwrap();
+        let img = img.into_inner();
+
+        assert_eq!(&img[sb + 120..sb + 136], b"beagle\0\0\0\0\0\0\0\0\0\0");
+        let csum = u32_at(&img, sb + EXT_SUPERBLOCK_LEN - 4);
+        assert_eq!(csum, crc32c(!0, &img[sb..sb + EXT_SUPERBLOCK_LEN - 4]));
+    }
+
+    #[test]
+    fn crc32c_check() {
+        // Standard check value, which includes the final inversion
+        assert_eq!(!crc32c(!0, b"123456789"), 0xe306_9283);
+    }
+}
@@ -1,7 +1,8 @@
 //! Library to flash SD cards with OS images. Powers sd card flashing in [BeagleBoard Imager].
 //!
 //! Also allows optional extra [Customization] for BeagleBoard images. Currently only supports
-//! sysconf based post-install configuration.
+//! sysconf based post-install configuration, along with setting volume labels of the boot and root
+//! partitions.
 //!
 //! # Platform Support
 //!
@@ -13,6 +14,9 @@
 //!
 //! - `udev`: Dynamic permissions on Linux. Mostly useful for GUI and flatpaks
 //! - `macos_authopen`: Dynamic permissions on MacOS.
+//! - `io_uring`: Queue writes using io_uring on Linux. Falls back to synchronous writes if not
+//!   supported by the kernel.
+//! - `test-util`: Allow flashing to regular files and loop devices. Useful for testing.
 //!
 //! # Usage
 //!
@@ -26,7 +30,7 @@
 //!     let img = bb_helper::resolvable::LocalFile::new(PathBuf::from("/tmp/image").into());
 //!     let (tx, mut rx) = tokio::sync::mpsc::channel(20);
 //!
-//!     let flash_thread = tokio::spawn(async move { bb_flasher_sd::flash(img, None::<bb_helper::resolvable::LocalStringFile>, dst, Some(tx), None, None).await });
+//!     let flash_thread = tokio::spawn(async move { bb_flasher_sd::flash(img, None::<bb_helper::resolvable::LocalStringFile>, dst, Some(tx), None, None, bb_flasher_sd::Verify::Quick, None).await });
 //!
 //!     while let Some(m) = rx.recv().await {
 //!         println!("{:?}", m);
@@ -42,16 +46,38 @@ use std::{io, path::PathBuf};
 
 use thiserror::Error;
 
+pub mod cmdline;
 pub(crate) mod customization;
+mod file;
+pub mod firstboot;
 mod flashing;
+pub mod format;
 mod helpers;
+pub mod hostname;
+pub mod inspect;
+pub mod label;
 pub(crate) mod pal;
+mod verify;
 
-pub use customization::{Customization, SysconfCustomization};
-pub use flashing::flash;
+pub use bb_drivelist::{CardClass, CardInfo, UsbLocation};
+pub use customization::{
+    BootFile, Customization, CustomizationStep, PROVENANCE_FILE, Provenance, SysconfCustomization,
+};
+pub use flashing::{compare, customize, export, flash};
+pub use verify::Verify;
 
 pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Flashing status
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Preparing,
+    Flashing(f32),
+    /// Reading back the written image. See [`Verify`].
+    Verifying,
+    Customizing(CustomizationStep),
+}
+
 #[derive(Error, Debug)]
 /// Errors for this crate
 pub enum Error {
@@ -63,6 +89,9 @@ pub enum Error {
     InvalidPartitionTable,
     #[error("Only FAT BOOT partitions are supported.")]
     InvalidBootPartition,
+    /// No partition with an ext filesystem was found to label.
+    #[error("No ext root partition found.")]
+    InvalidRootPartition,
     #[error("Failed to create sysconf.txt")]
     SysconfCreateFail {
         #[source]
@@ -79,6 +108,61 @@ pub enum Error {
         #[source]
         source: io::Error,
     },
+    #[error("Invalid hostname template: {source}")]
+    InvalidHostnameTemplate {
+        #[source]
+        source: hostname::Error,
+    },
+    #[error("Failed to set volume label.")]
+    LabelSetFail {
+        #[source]
+        source: io::Error,
+    },
+    #[error("Failed to write {}.", customization::PROVENANCE_FILE)]
+    ProvenanceWriteFail {
+        #[source]
+        source: io::Error,
+    },
+    #[error("Failed to edit kernel command line.")]
+    CmdlineEditFail {
+        #[source]
+        source: io::Error,
+    },
+    /// Neither extlinux.conf with an append line nor uEnv.txt was found to edit the kernel
+    /// command line.
+    #[error(
+        "No boot configuration found to edit. Expected {} or {}.",
+        cmdline::EXTLINUX_CONF,
+        cmdline::UENV_TXT
+    )]
+    BootConfigNotFound,
+    #[error("Failed to write first boot unit.")]
+    FirstBootWriteFail {
+        #[source]
+        source: io::Error,
+    },
+    #[error("Failed to copy {} to boot partition.", path.display())]
+    FileCopyFail {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// A step of post install customization failed.
+    #[error(
+        "Customization failed while {step}: {source}. {}",
+        if *.unmodified {
+            "The flashed image was left unmodified."
+        } else {
+            "The SD Card might be partially customized."
+        }
+    )]
+    CustomizationStepFail {
+        step: CustomizationStep,
+        #[source]
+        source: Box<Error>,
+        /// All staged changes were rolled back, leaving the freshly flashed image intact.
+        unmodified: bool,
+    },
     /// Unknown error occured during IO.
     #[error("Unknown Error during IO. Please check logs for more information.")]
     IoError {
@@ -89,6 +173,10 @@ pub enum Error {
     /// Aborted before completing
     #[error("Aborted before completing.")]
     Aborted,
+    /// Aborted while writing the image. Everything before `offset` in the image has been written
+    /// and flushed to the SD Card.
+    #[error("Aborted after writing the first {offset} bytes of the image.")]
+    AbortedAt { offset: u64 },
     #[error("Failed to format SD Card.")]
     FailedToFormat {
         #[source]
@@ -99,6 +187,24 @@ pub enum Error {
         #[source]
         source: anyhow::Error,
     },
+    /// Destination is mounted or held open by other processes.
+    #[error(
+        "SD Card is in use. Close or unmount the following and try again:\n{}",
+        .holders.join("\n")
+    )]
+    DestinationBusy { holders: Vec<String> },
+    /// Destination is write protected by hardware (e.g. lock switch) or the OS.
+    #[error(
+        "SD Card is write protected. Check the lock switch on the card or adapter and try again."
+    )]
+    WriteProtected,
+    /// Data read back from the SD Card does not match the image. The SD Card is likely failing or
+    /// counterfeit.
+    #[error("Verification failed at offset {offset}. The SD Card might be faulty.")]
+    VerificationFailed { offset: u64 },
+    /// SD Card contents differ from the image. See [`compare`].
+    #[error("SD Card does not match the image at offset {offset}.")]
+    ImageMismatch { offset: u64 },
     #[error("Invalid bmap for the image.")]
     InvalidBmap,
     #[error("Writer thread has been closed.")]
@@ -107,6 +213,14 @@ pub enum Error {
     #[cfg(windows)]
     #[error("Failed to clear SD Card.")]
     WindowsCleanError(std::process::Output),
+    /// Drive letter or volume GUID path does not belong to any drive.
+    #[cfg(windows)]
+    #[error("Volume {0} not found.")]
+    VolumeNotFound(String),
+    /// Volume resides on a fixed or system drive, which is never used as destination.
+    #[cfg(windows)]
+    #[error("Volume {0} is not on a removable drive.")]
+    VolumeNotRemovable(String),
 }
 
 /// Enumerate all SD Cards in system
@@ -121,25 +235,108 @@ pub fn devices(filter: bool) -> std::collections::HashSet<Device> {
                 true
             }
         })
-        .map(|x| Device::new(x.description, x.raw.into(), x.size.unwrap_or_default()))
+        .map(Device::from)
         .collect()
 }
 
+/// Find the SD Card containing a volume given as drive letter (`E:`) or volume GUID path
+/// (`\\?\Volume{...}\`). Volumes on fixed or system drives are rejected.
+#[cfg(windows)]
+pub fn device_from_volume(volume: &str) -> Result<Device> {
+    let mount =
+        pal::volume_mount_point(volume).ok_or_else(|| Error::VolumeNotFound(volume.to_string()))?;
+
+    let drive = bb_drivelist::drive_list()
+        .expect("Unsupported OS for Sd Card")
+        .into_iter()
+        .find(|x| {
+            x.mountpoints
+                .iter()
+                .any(|m| m.path.eq_ignore_ascii_case(&mount))
+        })
+        .ok_or_else(|| Error::VolumeNotFound(volume.to_string()))?;
+
+    if !drive.is_removable || drive.is_system || drive.is_virtual {
+        return Err(Error::VolumeNotRemovable(volume.to_string()));
+    }
+
+    Ok(drive.into())
+}
+
 #[derive(Hash, Debug, PartialEq, Eq, Clone)]
 /// SD Card
 pub struct Device {
     pub name: String,
     pub path: PathBuf,
     pub size: u64,
+    /// Write protected by hardware or the OS.
+    pub read_only: bool,
+    /// Reported as removable media by the OS.
+    pub removable: bool,
+    /// Virtual drive, like a mounted disk image.
+    pub is_virtual: bool,
+    /// USB port of the card reader. Only available on Linux.
+    pub location: Option<UsbLocation>,
+    /// Product string of the card reader. Only available on Linux.
+    pub reader: Option<String>,
+    /// Registers of the SD Card. Only available on Linux for SD Cards in MMC host controllers.
+    pub card: Option<CardInfo>,
+}
+
+impl From<bb_drivelist::DeviceDescriptor> for Device {
+    fn from(x: bb_drivelist::DeviceDescriptor) -> Self {
+        let mut dev = Device::new(
+            x.description,
+            x.raw.into(),
+            x.size.unwrap_or_default(),
+            x.is_readonly,
+        );
+        dev.removable = x.is_removable;
+        dev.is_virtual = x.is_virtual;
+        dev.location = x.usb_location;
+        dev.reader = x.usb_product;
+        dev.card = x.card;
+        dev
+    }
 }
 
 impl Device {
-    const fn new(name: String, path: PathBuf, size: u64) -> Self {
-        Self { name, path, size }
+    const fn new(name: String, path: PathBuf, size: u64, read_only: bool) -> Self {
+        Self {
+            name,
+            path,
+            size,
+            read_only,
+            removable: false,
+            is_virtual: false,
+            location: None,
+            reader: None,
+            card: None,
+        }
     }
 }
 
-/// Format SD card to fat32
-pub async fn format(dst: &std::path::Path) -> Result<()> {
-    crate::pal::format(dst).await
+/// Format SD Card for reuse as regular storage. See [`format::FormatOptions`] for available
+/// options.
+pub async fn format(
+    dst: Box<std::path::Path>,
+    options: format::FormatOptions,
+    chan: Option<tokio::sync::mpsc::Sender<Status>>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> Result<()> {
+    format::format(dst, options, chan, cancel).await
+}
+
+/// Identify the SD card by blinking the activity LED of the card reader for `duration`. Only
+/// performs reads, so it is safe to use on mounted drives.
+pub async fn identify(
+    dst: &std::path::Path,
+    size: u64,
+    duration: std::time::Duration,
+) -> Result<()> {
+    let f = crate::pal::open_read(dst).await?;
+    tokio::task::spawn_blocking(move || helpers::blink(f, size, duration))
+        .await
+        .unwrap()
+        .map_err(|source| Error::IoError { source })
 }
@@ -1,7 +1,7 @@
 use crate::{Error, Result, helpers::Eject};
 
 use std::{
-    io,
+    io::{self, Read, Seek, Write},
     path::{Path, PathBuf},
 };
 
@@ -12,9 +12,9 @@ use std::{
 };
 
 #[cfg(feature = "udev")]
-pub(crate) async fn format(dst: &Path) -> Result<()> {
-    async fn format_inner(dst: &Path) -> io::Result<()> {
-        let dbus_client = udisks2::Client::new().await.map_err(io::Error::other)?;
+pub(crate) async fn open(dst: &Path) -> Result<LinuxDrive> {
+    async fn open_inner(dst: &Path) -> anyhow::Result<LinuxDrive> {
+        let dbus_client = udisks2::Client::new().await?;
 
         let devs = dbus_client
             .manager()
@@ -22,42 +22,37 @@ pub(crate) async fn format(dst: &Path) -> Result<()> {
                 HashMap::from([("path", dst.to_str().unwrap().into())]),
                 HashMap::new(),
             )
-            .await
-            .map_err(io::Error::other)?;
+            .await?;
 
         let block = devs
             .first()
-            .ok_or(io::Error::new(
-                io::ErrorKind::NotFound,
-                "Block device not found",
-            ))?
+            .ok_or(anyhow::anyhow!("Block device not found",))?
             .to_owned();
 
         let obj = dbus_client
             .object(block)
             .expect("Unexpected error")
             .block()
-            .await
-            .map_err(io::Error::other)?;
+            .await?;
 
-        obj.format(
-            "vfat",
-            HashMap::from([("update-partition-type", true.into())]),
-        )
-        .await
-        .map_err(io::Error::other)?;
+        let fd = obj
+            .open_device("rw", HashMap::from([("flags", libc::O_DIRECT.into())]))
+            .await?;
+        let file =
+            unsafe { std::fs::File::from_raw_fd(std::os::fd::OwnedFd::from(fd).into_raw_fd()) };
 
-        Ok(())
+        Ok(LinuxDrive::new(file, dst.to_path_buf()))
     }
 
-    format_inner(dst)
+    open_inner(dst)
         .await
-        .map_err(|source| Error::FailedToFormat { source })
+        .map_err(|e| Error::FailedToOpenDestination { source: e })
 }
 
+/// Open the drive read-only. Does not require the drive to be unmounted.
 #[cfg(feature = "udev")]
-pub(crate) async fn open(dst: &Path) -> Result<LinuxDrive> {
-    async fn open_inner(dst: &Path) -> anyhow::Result<LinuxDrive> {
+pub(crate) async fn open_read(dst: &Path) -> Result<std::fs::File> {
+    async fn open_inner(dst: &Path) -> anyhow::Result<std::fs::File> {
         let dbus_client = udisks2::Client::new().await?;
 
         let devs = dbus_client
@@ -79,16 +74,9 @@ pub(crate) async fn open(dst: &Path) -> Result<LinuxDrive> {
             .block()
             .await?;
 
-        let fd = obj
-            .open_device("rw", HashMap::from([("flags", libc::O_DIRECT.into())]))
-            .await?;
-        let file =
-            unsafe { std::fs::File::from_raw_fd(std::os::fd::OwnedFd::from(fd).into_raw_fd()) };
+        let fd = obj.open_device("r", HashMap::new()).await?;
 
-        Ok(LinuxDrive {
-            file,
-            drive: dst.to_path_buf(),
-        })
+        Ok(unsafe { std::fs::File::from_raw_fd(std::os::fd::OwnedFd::from(fd).into_raw_fd()) })
     }
 
     open_inner(dst)
@@ -96,6 +84,12 @@ pub(crate) async fn open(dst: &Path) -> Result<LinuxDrive> {
         .map_err(|e| Error::FailedToOpenDestination { source: e })
 }
 
+/// Open the drive read-only. Does not require the drive to be unmounted.
+#[cfg(not(feature = "udev"))]
+pub(crate) async fn open_read(dst: &Path) -> Result<std::fs::File> {
+    std::fs::File::open(dst).map_err(|e| Error::FailedToOpenDestination { source: e.into() })
+}
+
 #[cfg(not(feature = "udev"))]
 pub(crate) async fn open(dst: &Path) -> Result<LinuxDrive> {
     let file = tokio::fs::OpenOptions::new()
@@ -108,41 +102,182 @@ pub(crate) async fn open(dst: &Path) -> Result<LinuxDrive> {
         .into_std()
         .await;
 
-    Ok(LinuxDrive {
-        file,
-        drive: dst.to_path_buf(),
-    })
+    Ok(LinuxDrive::new(file, dst.to_path_buf()))
 }
 
-#[cfg(not(feature = "udev"))]
-pub(crate) async fn format(dst: &Path) -> Result<()> {
-    async fn format_inner(dst: &Path) -> io::Result<()> {
-        let output = tokio::process::Command::new("mkfs.vfat")
-            .arg(dst)
-            .output()
-            .await?;
+/// Check that the block device is not mounted or held by anything else.
+///
+/// The kernel refuses `O_EXCL` opens of a block device with `EBUSY` while any of its partitions are
+/// mounted or claimed (eg. by device mapper). Processes that simply have the device open are found
+/// by scanning `/proc`. Other open failures (like missing permissions) are left to [`open`].
+pub(crate) fn check_busy(dst: &Path) -> Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
 
-        if output.status.success() {
-            Ok(())
-        } else {
-            Err(io::Error::other(format!("Status: {}", output.status)))
-        }
+    let excl = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_EXCL)
+        .open(dst);
+    let claimed = matches!(excl, Err(ref e) if e.raw_os_error() == Some(libc::EBUSY));
+    drop(excl);
+
+    let devices = block_devices(dst);
+    let mut holders = mounts(&devices);
+    holders.extend(sysfs_holders(&devices));
+    let processes = processes(&devices);
+
+    if claimed || !processes.is_empty() {
+        holders.extend(processes);
+        return Err(Error::DestinationBusy { holders });
     }
 
-    format_inner(dst)
-        .await
-        .map_err(|source| Error::FailedToFormat { source })
+    Ok(())
+}
+
+/// Serial number from the card identification register. Only exposed by MMC host controllers,
+/// not USB card readers.
+pub(crate) fn card_serial(dst: &Path) -> Option<String> {
+    let dst = dst.canonicalize().ok()?;
+    let name = dst.file_name()?;
+
+    std::fs::read_to_string(
+        Path::new("/sys/class/block")
+            .join(name)
+            .join("device/serial"),
+    )
+    .ok()
+    .map(|x| x.trim().to_string())
+    .filter(|x| !x.is_empty())
+}
+
+/// The block device along with all its partitions.
+fn block_devices(dst: &Path) -> Vec<PathBuf> {
+    let dst = dst.canonicalize().unwrap_or_else(|_| dst.to_path_buf());
+    let Some(name) = dst.file_name().and_then(|x| x.to_str()) else {
+        return vec![dst];
+    };
+
+    let partitions = std::fs::read_dir(Path::new("/sys/class/block").join(name))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|x| x.path().join("partition").exists())
+        .map(|x| Path::new("/dev").join(x.file_name()));
+
+    std::iter::once(dst.clone()).chain(partitions).collect()
+}
+
+fn mounts(devices: &[PathBuf]) -> Vec<String> {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return Vec::new();
+    };
+
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            Some((parts.next()?, parts.next()?))
+        })
+        .filter(|(src, _)| devices.iter().any(|d| d.as_os_str() == *src))
+        .map(|(src, target)| format!("{src} is mounted at {}", target.replace("\\040", " ")))
+        .collect()
+}
+
+fn sysfs_holders(devices: &[PathBuf]) -> Vec<String> {
+    devices
+        .iter()
+        .filter_map(|d| d.file_name())
+        .flat_map(|name| {
+            let holders = Path::new("/sys/class/block").join(name).join("holders");
+            std::fs::read_dir(holders)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(move |h| {
+                    format!(
+                        "{} is held by {}",
+                        name.to_string_lossy(),
+                        h.file_name().to_string_lossy()
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Processes with any of the devices open. Processes of other users can only be seen when
+/// running as root.
+fn processes(devices: &[PathBuf]) -> Vec<String> {
+    let self_pid = std::process::id().to_string();
+
+    std::fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().into_string().ok()?;
+            if pid == self_pid || !pid.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+
+            let path = std::fs::read_dir(entry.path().join("fd"))
+                .ok()?
+                .flatten()
+                .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+                .find(|link| devices.contains(link))?;
+
+            let comm = std::fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+            Some(format!(
+                "{} (pid {pid}) has {} open",
+                comm.trim(),
+                path.display()
+            ))
+        })
+        .collect()
 }
 
 #[derive(Debug)]
 pub(crate) struct LinuxDrive {
+    /// Declared before `file` so that queued writes complete before the file is closed.
+    #[cfg(feature = "io_uring")]
+    uring: Option<super::uring::UringWriter>,
     file: std::fs::File,
     drive: PathBuf,
 }
 
+impl LinuxDrive {
+    fn new(file: std::fs::File, drive: PathBuf) -> Self {
+        Self {
+            #[cfg(feature = "io_uring")]
+            uring: super::uring::UringWriter::new(&file),
+            file,
+            drive,
+        }
+    }
+
+    /// Wait for queued writes and move the file cursor to where they ended.
+    fn sync_pos(&mut self) -> io::Result<()> {
+        #[cfg(feature = "io_uring")]
+        if let Some(u) = self.uring.as_mut() {
+            u.drain()?;
+            self.file.seek(io::SeekFrom::Start(u.pos()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Record the file cursor as the offset for next queued write.
+    fn update_pos(&mut self, pos: u64) {
+        #[cfg(feature = "io_uring")]
+        if let Some(u) = self.uring.as_mut() {
+            u.set_pos(pos);
+        }
+        #[cfg(not(feature = "io_uring"))]
+        let _ = pos;
+    }
+}
+
 #[cfg(feature = "udev")]
 impl Eject for LinuxDrive {
-    fn eject(self) -> io::Result<()> {
+    fn eject(mut self) -> io::Result<()> {
         async fn inner(dst: PathBuf) -> io::Result<()> {
             let dbus_client = udisks2::Client::new().await.map_err(io::Error::other)?;
 
@@ -183,6 +318,7 @@ impl Eject for LinuxDrive {
             Ok(())
         }
 
+        let _ = self.sync_pos();
         let _ = self.file.sync_all();
         let dst = self.drive.clone();
 
@@ -199,7 +335,8 @@ impl Eject for LinuxDrive {
 
 #[cfg(not(feature = "udev"))]
 impl Eject for LinuxDrive {
-    fn eject(self) -> std::io::Result<()> {
+    fn eject(mut self) -> std::io::Result<()> {
+        let _ = self.sync_pos();
         let _ = self.file.sync_all();
         let drive = self.drive.clone();
         std::mem::drop(self);
@@ -218,22 +355,49 @@ impl Eject for LinuxDrive {
 
 impl io::Read for LinuxDrive {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.file.read(buf)
+        self.sync_pos()?;
+        let count = self.file.read(buf)?;
+        self.update_pos(self.file.stream_position()?);
+        Ok(count)
     }
 }
 
 impl io::Seek for LinuxDrive {
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-        self.file.seek(pos)
+        // Queued writes carry their own offset, so no need to wait for them
+        #[cfg(feature = "io_uring")]
+        if let Some(u) = self.uring.as_mut() {
+            let new_pos = match pos {
+                io::SeekFrom::Start(x) => Some(x),
+                io::SeekFrom::Current(x) => u.pos().checked_add_signed(x),
+                io::SeekFrom::End(_) => None,
+            };
+
+            if let Some(x) = new_pos {
+                u.set_pos(x);
+                return Ok(x);
+            }
+        }
+
+        self.sync_pos()?;
+        let pos = self.file.seek(pos)?;
+        self.update_pos(pos);
+        Ok(pos)
     }
 }
 
 impl io::Write for LinuxDrive {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[cfg(feature = "io_uring")]
+        if let Some(u) = self.uring.as_mut() {
+            return u.write(buf);
+        }
+
         self.file.write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        self.sync_pos()?;
         self.file.flush()
     }
 }
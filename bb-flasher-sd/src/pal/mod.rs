@@ -4,10 +4,12 @@ mod linux;
 mod macos;
 #[cfg(windows)]
 mod windows;
+#[cfg(all(target_os = "linux", feature = "io_uring"))]
+mod uring;
 
 #[cfg(target_os = "linux")]
-pub(crate) use linux::{open, format};
+pub(crate) use linux::{open, open_read, check_busy, card_serial};
 #[cfg(target_os = "macos")]
-pub(crate) use macos::{open, format};
+pub(crate) use macos::{open, open_read, check_busy, card_serial};
 #[cfg(windows)]
-pub(crate) use windows::{open, format};
+pub(crate) use windows::{open, open_read, check_busy, card_serial, volume_mount_point};
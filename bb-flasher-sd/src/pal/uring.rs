@@ -0,0 +1,197 @@
+//! Queued writes to SD Card using io_uring.
+//!
+//! Data is copied into owned buffers and submitted without waiting for completion, allowing the
+//! kernel to keep the device busy while the next buffer is being prepared.
+
+use std::{
+    alloc::Layout,
+    io,
+    os::fd::{AsRawFd, RawFd},
+};
+
+use io_uring::{IoUring, opcode, types};
+
+const QUEUE_DEPTH: usize = 8;
+const BUF_SIZE: usize = 1024 * 1024;
+/// Alignment required for O_DIRECT
+const BUF_ALIGN: usize = 4096;
+
+/// Heap allocated buffer aligned for O_DIRECT.
+struct AlignedBuf(*mut u8);
+
+impl AlignedBuf {
+    const LAYOUT: Layout = match Layout::from_size_align(BUF_SIZE, BUF_ALIGN) {
+        Ok(x) => x,
+        Err(_) => panic!("Invalid buffer layout"),
+    };
+
+    fn new() -> Self {
+        let ptr = unsafe { std::alloc::alloc_zeroed(Self::LAYOUT) };
+        if ptr.is_null() {
+            std::alloc::handle_alloc_error(Self::LAYOUT);
+        }
+
+        Self(ptr)
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.0, BUF_SIZE) }
+    }
+}
+
+impl Drop for AlignedBuf {
+    fn drop(&mut self) {
+        unsafe { std::alloc::dealloc(self.0, Self::LAYOUT) }
+    }
+}
+
+// The buffer is uniquely owned
+unsafe impl Send for AlignedBuf {}
+
+pub(crate) struct UringWriter {
+    ring: IoUring,
+    fd: RawFd,
+    free: Vec<AlignedBuf>,
+    /// Buffers owned by the kernel, indexed by `user_data` of the submission.
+    inflight: [Option<(AlignedBuf, usize)>; QUEUE_DEPTH],
+    /// First error reported by a completed write. Returned on next write or flush.
+    error: Option<io::Error>,
+    pos: u64,
+}
+
+impl UringWriter {
+    /// Returns `None` if io_uring is not supported by the running kernel.
+    pub(crate) fn new(file: &std::fs::File) -> Option<Self> {
+        let ring = match IoUring::new(QUEUE_DEPTH as u32) {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::info!("io_uring not available, using synchronous writes: {e}");
+                return None;
+            }
+        };
+
+        Some(Self {
+            ring,
+            fd: file.as_raw_fd(),
+            free: Vec::new(),
+            inflight: Default::default(),
+            error: None,
+            pos: 0,
+        })
+    }
+
+    /// Offset of the next write.
+    pub(crate) const fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    pub(crate) const fn set_pos(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    pub(crate) fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_error()?;
+
+        let slot = match self.inflight.iter().position(Option::is_none) {
+            Some(x) => x,
+            None => {
+                self.wait()?;
+                self.check_error()?;
+                self.inflight
+                    .iter()
+                    .position(Option::is_none)
+                    .expect("No free slot after completion")
+            }
+        };
+
+        let mut data = self.free.pop().unwrap_or_else(AlignedBuf::new);
+        let len = buf.len().min(BUF_SIZE);
+        data.as_mut_slice()[..len].copy_from_slice(&buf[..len]);
+
+        let entry = opcode::Write::new(types::Fd(self.fd), data.0, len as u32)
+            .offset(self.pos)
+            .build()
+            .user_data(slot as u64);
+
+        // SAFETY: The buffer lives in `inflight` until its completion is reaped.
+        unsafe { self.ring.submission().push(&entry) }.map_err(io::Error::other)?;
+        self.inflight[slot] = Some((data, len));
+        self.ring.submit()?;
+
+        self.pos += len as u64;
+        Ok(len)
+    }
+
+    /// Wait for all queued writes to complete.
+    pub(crate) fn drain(&mut self) -> io::Result<()> {
+        while self.inflight.iter().any(Option::is_some) {
+            self.wait()?;
+        }
+
+        self.check_error()
+    }
+
+    fn check_error(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Wait for at least one write to complete.
+    fn wait(&mut self) -> io::Result<()> {
+        match self.ring.submit_and_wait(1) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            x => {
+                x?;
+            }
+        }
+
+        for cqe in self.ring.completion() {
+            let Some((data, len)) = self.inflight[cqe.user_data() as usize].take() else {
+                continue;
+            };
+            self.free.push(data);
+
+            let res = cqe.result();
+            let err = if res < 0 {
+                Some(io::Error::from_raw_os_error(-res))
+            } else if res as usize != len {
+                Some(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    format!("Short write: {res} of {len} bytes"),
+                ))
+            } else {
+                None
+            };
+
+            if self.error.is_none() {
+                self.error = err;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for UringWriter {
+    fn drop(&mut self) {
+        // Buffers cannot be freed while the kernel might still be using them
+        if let Err(e) = self.drain() {
+            tracing::error!("Failed to complete queued writes: {e}");
+            // Leak rather than free memory the kernel might still write from
+            for x in self.inflight.iter_mut().filter_map(Option::take) {
+                std::mem::forget(x);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for UringWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UringWriter")
+            .field("fd", &self.fd)
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
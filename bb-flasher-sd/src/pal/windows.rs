@@ -3,11 +3,10 @@ use std::{
     io::{self, Read, Seek, SeekFrom, Write},
     os::windows::io::AsRawHandle,
     path::Path,
-    process::Stdio,
 };
-use tokio::io::AsyncWriteExt;
 use windows::Win32::{
     Foundation::HANDLE,
+    Storage::FileSystem::GetVolumePathNamesForVolumeNameW,
     System::IO::DeviceIoControl,
     System::Ioctl::{FSCTL_ALLOW_EXTENDED_DASD_IO, FSCTL_LOCK_VOLUME, FSCTL_UNLOCK_VOLUME},
 };
@@ -105,6 +104,48 @@ fn open_and_lock_volume(path: &str) -> anyhow::Result<File> {
     Ok(volume)
 }
 
+/// Mount point (`E:\`) of a volume given as drive letter (`E:`, `E:\`, `\\.\E:`) or volume GUID
+/// path (`\\?\Volume{...}\`).
+pub(crate) fn volume_mount_point(volume: &str) -> Option<String> {
+    if volume.starts_with("\\\\?\\Volume{") {
+        return volume_guid_mount_point(volume);
+    }
+
+    let letter = volume
+        .strip_prefix("\\\\.\\")
+        .unwrap_or(volume)
+        .trim_end_matches('\\');
+
+    match letter.as_bytes() {
+        [c, b':'] if c.is_ascii_alphabetic() => {
+            Some(format!("{}:\\", c.to_ascii_uppercase() as char))
+        }
+        _ => None,
+    }
+}
+
+fn volume_guid_mount_point(volume: &str) -> Option<String> {
+    let mut name = volume.to_string();
+    if !name.ends_with('\\') {
+        name.push('\\');
+    }
+
+    let mut buf = [0u16; 1024];
+    let mut len = 0;
+    unsafe {
+        GetVolumePathNamesForVolumeNameW(
+            &windows::core::HSTRING::from(name),
+            Some(&mut buf),
+            &mut len,
+        )
+    }
+    .ok()?;
+
+    // List of null terminated strings. Only the first mount point is needed.
+    let first = buf.split(|x| *x == 0).next()?;
+    String::from_utf16(first).ok().filter(|x| !x.is_empty())
+}
+
 fn physical_drive_to_volume(drive: &Path) -> anyhow::Result<Option<String>> {
     let desc = bb_drivelist::drive_list()
         .expect("Unexpected error")
@@ -155,39 +196,6 @@ async fn diskpart_clean(path: &Path) -> Result<()> {
     }
 }
 
-async fn diskpart_format(path: &Path) -> io::Result<()> {
-    let disk_num = path
-        .to_str()
-        .unwrap()
-        .strip_prefix("\\\\.\\PhysicalDrive")
-        .ok_or(io::Error::new(io::ErrorKind::NotFound, "Drive not found"))?;
-
-    let mut cmd = tokio::process::Command::new("diskpart")
-        .stderr(Stdio::null())
-        .stdin(Stdio::piped())
-        .stdout(Stdio::null())
-        .spawn()?;
-
-    let mut stdin = cmd.stdin.take().expect("Failed to get stdin");
-    stdin.write_all(b"select disk ").await?;
-    stdin.write_all(disk_num.as_bytes()).await?;
-    stdin.write_all(b"\n").await?;
-    stdin.write_all(b"clean\n").await?;
-    stdin.write_all(b"create partition primary\n").await?;
-    stdin.write_all(b"format quick fs=fat32\n").await?;
-    stdin.write_all(b"assign\n").await?;
-    stdin.write_all(b"exit\n").await?;
-
-    drop(stdin);
-
-    let status = cmd.wait().await?;
-    if status.success() {
-        Ok(())
-    } else {
-        Err(io::Error::other(format!("Status: {status}")))
-    }
-}
-
 impl Read for WinDrive {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.drive.read(buf)
@@ -218,10 +226,14 @@ impl crate::helpers::Eject for WinDrive {
     }
 }
 
-pub(crate) async fn format(dst: &Path) -> Result<()> {
-    diskpart_format(dst)
-        .await
-        .map_err(|source| Error::FailedToFormat { source })
+/// Volumes are locked while opening the drive, which fails if they are in use.
+pub(crate) fn check_busy(_: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Card readers on Windows do not expose the card identification register.
+pub(crate) fn card_serial(_: &Path) -> Option<String> {
+    None
 }
 
 pub(crate) async fn open(dst: &Path) -> Result<WinDrive> {
@@ -229,3 +241,8 @@ pub(crate) async fn open(dst: &Path) -> Result<WinDrive> {
         .await
         .map_err(|e| Error::FailedToOpenDestination { source: e })
 }
+
+/// Open the drive read-only. Volumes are not locked, so this works while mounted.
+pub(crate) async fn open_read(dst: &Path) -> Result<std::fs::File> {
+    std::fs::File::open(dst).map_err(|e| Error::FailedToOpenDestination { source: e.into() })
+}
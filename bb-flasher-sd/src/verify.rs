@@ -0,0 +1,227 @@
+//! Verify the image by reading it back from the SD Card after writing.
+//!
+//! Checksums of written chunks are recorded while writing, so the image does not need to be read
+//! (or downloaded and decompressed) again.
+
+use std::{
+    hash::BuildHasher,
+    io::{self, Read, Seek, Write},
+};
+
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use crate::{
+    Result, Status,
+    helpers::{chan_send, check_token},
+};
+
+/// Size of the start and end of the image always verified in [`Verify::Quick`]. Covers partition
+/// tables (including backup GPT) and the start of the boot partition.
+const QUICK_EDGE: u64 = 16 * 1024 * 1024;
+/// Number of randomly chosen chunks verified in [`Verify::Quick`], in addition to the edges.
+const QUICK_SAMPLES: u64 = 128;
+
+/// Verification of the written image.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Verify {
+    /// Do not read back anything.
+    #[default]
+    None,
+    /// Read back the start and end of the image, along with a random sample of chunks in between.
+    /// Catches most counterfeit and failing SD Cards in a few seconds.
+    Quick,
+    /// Read back everything written. Takes about as long as writing.
+    Full,
+}
+
+#[derive(Debug)]
+struct Chunk {
+    offset: u64,
+    len: usize,
+    sha256: [u8; 32],
+}
+
+/// Records checksums of chunks written to the inner writer, based on [`Verify`] mode.
+pub(crate) struct Recorder<W> {
+    inner: W,
+    pos: u64,
+    checks: Checks,
+}
+
+impl<W> Recorder<W> {
+    /// `size` is the size of the image, and `expected` is the number of bytes which are expected
+    /// to be written (less than `size` when using bmap). `chunk` is the size of each write.
+    pub(crate) fn new(inner: W, mode: Verify, size: u64, expected: u64, chunk: usize) -> Self {
+        let chunks = expected.div_ceil(chunk as u64);
+        let threshold = if chunks <= QUICK_SAMPLES {
+            u64::MAX
+        } else {
+            u64::try_from(u128::from(u64::MAX) * u128::from(QUICK_SAMPLES) / u128::from(chunks))
+                .unwrap()
+        };
+
+        Self {
+            inner,
+            pos: 0,
+            checks: Checks {
+                mode,
+                size,
+                threshold,
+                // Random seed, so that different chunks are checked each time
+                rng: std::collections::hash_map::RandomState::new().hash_one(size) | 1,
+                chunks: Vec::new(),
+                hasher: Sha256::new(),
+            },
+        }
+    }
+
+    pub(crate) fn finish(self) -> Checks {
+        self.checks
+    }
+}
+
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        self.checks.record(self.pos, &buf[..count]);
+        self.pos += count as u64;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Seek> Seek for Recorder<W> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
+/// Checksums recorded while writing.
+pub(crate) struct Checks {
+    mode: Verify,
+    size: u64,
+    /// Chunks are sampled in [`Verify::Quick`] when a random number is below threshold.
+    threshold: u64,
+    rng: u64,
+    chunks: Vec<Chunk>,
+    /// Reused for every chunk, instead of setting up a new hasher for each write.
+    hasher: Sha256,
+}
+
+impl Checks {
+    fn record(&mut self, offset: u64, data: &[u8]) {
+        let keep = match self.mode {
+            Verify::None => false,
+            Verify::Full => true,
+            Verify::Quick => {
+                offset < QUICK_EDGE
+                    || offset + data.len() as u64 > self.size.saturating_sub(QUICK_EDGE)
+                    || self.sample()
+            }
+        };
+
+        if keep && !data.is_empty() {
+            self.hasher.update(data);
+            self.chunks.push(Chunk {
+                offset,
+                len: data.len(),
+                sha256: self.hasher.finalize_reset().into(),
+            });
+        }
+    }
+
+    fn sample(&mut self) -> bool {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+
+        self.rng <= self.threshold
+    }
+
+    /// Read back recorded chunks from `sd` and compare checksums. `buf` is used for reading, and
+    /// needs to be suitably aligned for direct IO.
+    pub(crate) fn verify(
+        &self,
+        mut sd: impl Read + Seek,
+        buf: &mut [u8],
+        chan: Option<&mut mpsc::Sender<Status>>,
+        cancel: Option<&tokio_util::sync::CancellationToken>,
+    ) -> Result<()> {
+        if self.chunks.is_empty() {
+            return Ok(());
+        }
+
+        tracing::info!("Verifying {} chunks", self.chunks.len());
+        chan_send(chan, Status::Verifying);
+
+        let mut hasher = Sha256::new();
+        for c in &self.chunks {
+            check_token(cancel)?;
+
+            sd.seek(io::SeekFrom::Start(c.offset))?;
+            let mut remaining = c.len;
+            while remaining > 0 {
+                let len = remaining.min(buf.len());
+                sd.read_exact(&mut buf[..len])?;
+                hasher.update(&buf[..len]);
+                remaining -= len;
+            }
+
+            if hasher.finalize_reset()[..] != c.sha256[..] {
+                tracing::error!("Verification failed at offset {}", c.offset);
+                return Err(crate::Error::VerificationFailed { offset: c.offset });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Seek, SeekFrom, Write};
+
+    use super::{Recorder, Verify};
+
+    const CHUNK: usize = 4096;
+
+    fn write(mode: Verify, chunks: usize) -> (Cursor<Vec<u8>>, super::Checks) {
+        let size = (chunks * CHUNK) as u64;
+        let mut rec = Recorder::new(Cursor::new(Vec::new()), mode, size, size, CHUNK);
+        for i in 0..chunks {
+            rec.write_all(&[i as u8; CHUNK]).unwrap();
+        }
+
+        (rec.inner.clone(), rec.finish())
+    }
+
+    #[test]
+    fn full() {
+        let (mut sd, checks) = write(Verify::Full, 2);
+        let mut buf = [0u8; CHUNK];
+
+        checks.verify(&mut sd, &mut buf, None, None).unwrap();
+
+        sd.seek(SeekFrom::Start(5000)).unwrap();
+        sd.write_all(&[0xff]).unwrap();
+        assert!(matches!(
+            checks.verify(&mut sd, &mut buf, None, None),
+            Err(crate::Error::VerificationFailed { offset: 4096 })
+        ));
+    }
+
+    #[test]
+    fn quick() {
+        let (_, none) = write(Verify::None, 16);
+        assert!(none.chunks.is_empty());
+
+        // Small images lie completely in the edges
+        let (_, quick) = write(Verify::Quick, 16);
+        assert_eq!(quick.chunks.len(), 16);
+    }
+}
@@ -0,0 +1,216 @@
+//! Verify that a board boots after flashing by watching its serial console.
+//!
+//! Meant for flashing stations where the debug UART of the board is connected to the host. The
+//! board is expected to boot from the flashed media on its own (e.g. eMMC or an SD Card left in
+//! the board). If the USB to UART adapter has DTR/RTS wired to the reset line, it can be used to
+//! reset the board before watching the console.
+
+use std::{
+    io::Read,
+    time::{Duration, Instant},
+};
+
+use futures::channel::mpsc;
+use thiserror::Error;
+
+use crate::{BBFlasher, DownloadFlashingStatus};
+
+/// Serial console output which shows that the board has booted.
+pub const DEFAULT_MARKER: &str = "login:";
+/// Time allowed to boot. First boot of Linux images resizes the root filesystem, which takes a
+/// while on larger cards.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+pub const DEFAULT_BAUD_RATE: u32 = 115200;
+
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+const RESET_PULSE: Duration = Duration::from_millis(100);
+
+/// Errors from boot testing.
+#[derive(Error, Debug)]
+pub enum BootTestError {
+    #[error("Failed to open serial port {port}")]
+    FailedToOpenPort {
+        port: String,
+        #[source]
+        source: serialport::Error,
+    },
+    #[error("Failed to reset the board")]
+    Reset(#[from] serialport::Error),
+    #[error("Failed to read from serial port")]
+    Io(#[from] std::io::Error),
+    #[error("Board did not print \"{marker}\" within {} seconds of booting", timeout.as_secs())]
+    MarkerNotFound { marker: String, timeout: Duration },
+}
+
+/// Wraps a flasher to check that the board boots once flashing is done.
+///
+/// Reports [`DownloadFlashingStatus::BootTesting`] while watching the console, followed by
+/// [`DownloadFlashingStatus::BootVerified`] or [`DownloadFlashingStatus::BootFailed`]. Boot
+/// failures are returned as [`BootTestError`].
+pub struct BootTest<F> {
+    inner: F,
+    port: String,
+    baud_rate: u32,
+    marker: String,
+    timeout: Duration,
+    reset: bool,
+}
+
+impl<F> BootTest<F> {
+    pub fn new(inner: F, port: String) -> Self {
+        Self {
+            inner,
+            port,
+            baud_rate: DEFAULT_BAUD_RATE,
+            marker: DEFAULT_MARKER.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            reset: false,
+        }
+    }
+
+    pub const fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Text printed on the serial console once the board has booted.
+    pub fn marker(mut self, marker: String) -> Self {
+        self.marker = marker;
+        self
+    }
+
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Pulse DTR and RTS to reset the board before watching the console.
+    pub const fn reset(mut self, reset: bool) -> Self {
+        self.reset = reset;
+        self
+    }
+}
+
+impl<F: BBFlasher> BBFlasher for BootTest<F> {
+    async fn flash(
+        self,
+        mut chan: Option<mpsc::Sender<DownloadFlashingStatus>>,
+    ) -> anyhow::Result<()> {
+        self.inner.flash(chan.clone()).await?;
+
+        let mut send = |status| {
+            if let Some(c) = chan.as_mut() {
+                let _ = c.try_send(status);
+            }
+        };
+
+        send(DownloadFlashingStatus::BootTesting);
+
+        let Self {
+            port,
+            baud_rate,
+            marker,
+            timeout,
+            reset,
+            ..
+        } = self;
+        let res = tokio::task::spawn_blocking(move || {
+            wait_for_marker(&port, baud_rate, &marker, timeout, reset)
+        })
+        .await
+        .unwrap();
+
+        match res {
+            Ok(()) => {
+                send(DownloadFlashingStatus::BootVerified);
+                Ok(())
+            }
+            Err(e) => {
+                tracing::error!("Boot test failed: {e}");
+                send(DownloadFlashingStatus::BootFailed);
+                Err(e.into())
+            }
+        }
+    }
+}
+
+fn wait_for_marker(
+    port: &str,
+    baud_rate: u32,
+    marker: &str,
+    timeout: Duration,
+    reset: bool,
+) -> Result<(), BootTestError> {
+    let mut serial = serialport::new(port, baud_rate)
+        .timeout(READ_TIMEOUT)
+        .open()
+        .map_err(|source| BootTestError::FailedToOpenPort {
+            port: port.to_string(),
+            source,
+        })?;
+
+    if reset {
+        for level in [true, false] {
+            serial.write_data_terminal_ready(level)?;
+            serial.write_request_to_send(level)?;
+            std::thread::sleep(RESET_PULSE);
+        }
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut matcher = Matcher::new(marker.as_bytes());
+    let mut buf = [0u8; 512];
+
+    while Instant::now() < deadline {
+        let n = match serial.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        if matcher.feed(&buf[..n]) {
+            tracing::info!("Found boot marker {marker}");
+            return Ok(());
+        }
+    }
+
+    Err(BootTestError::MarkerNotFound {
+        marker: marker.to_string(),
+        timeout,
+    })
+}
+
+/// Find a marker in a stream of chunks, including markers split across chunks.
+struct Matcher<'a> {
+    marker: &'a [u8],
+    tail: Vec<u8>,
+}
+
+impl<'a> Matcher<'a> {
+    const fn new(marker: &'a [u8]) -> Self {
+        Self {
+            marker,
+            tail: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> bool {
+        self.tail.extend_from_slice(chunk);
+
+        if self.marker.is_empty()
+            || self
+                .tail
+                .windows(self.marker.len())
+                .any(|x| x == self.marker)
+        {
+            return true;
+        }
+
+        // Keep just enough to match a marker split across chunks
+        let keep = self.marker.len().saturating_sub(1);
+        let start = self.tail.len().saturating_sub(keep);
+        self.tail.drain(..start);
+
+        false
+    }
+}
@@ -1,10 +1,15 @@
 //! Stuff common to all the flashers
 
-use std::{borrow::Cow, collections::HashSet};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-use futures::channel::mpsc;
+use futures::{StreamExt, channel::mpsc};
 #[cfg(any(feature = "bcf", feature = "bcf_msp430", feature = "pb2_mspm0"))]
 use thiserror::Error;
+use tracing::Instrument;
 
 #[derive(Error, Debug)]
 #[cfg(any(feature = "bcf", feature = "bcf_msp430", feature = "pb2_mspm0"))]
@@ -22,10 +27,134 @@ pub(crate) enum FlasherError {
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum DownloadFlashingStatus {
     Preparing,
+    /// Checking integrity of a cached image before writing to the destination.
+    CheckingImage,
     DownloadingProgress(f32),
+    /// Decompressing an image that needs to be fully extracted before flashing.
+    DecompressingProgress(f32),
     FlashingProgress(f32),
     Verifying,
-    Customizing,
+    Customizing(CustomizationStep),
+    /// Watching the serial console of the board after flashing. See [`BootTest`].
+    ///
+    /// [`BootTest`]: crate::BootTest
+    BootTesting,
+    /// Board booted successfully. Last status of the job.
+    BootVerified,
+    /// Board did not boot. Last status of the job.
+    BootFailed,
+}
+
+impl DownloadFlashingStatus {
+    /// Approximate progress between 0 and 1. Useful for showing an overall progress bar.
+    pub const fn progress(&self) -> f32 {
+        match self {
+            Self::Preparing | Self::CheckingImage => 0.0,
+            Self::DownloadingProgress(x)
+            | Self::DecompressingProgress(x)
+            | Self::FlashingProgress(x) => *x,
+            Self::Verifying | Self::Customizing(_) | Self::BootTesting => 0.99,
+            Self::BootVerified | Self::BootFailed => 1.0,
+        }
+    }
+
+    /// Bytes processed in the current step out of `total`. Only known for steps reporting
+    /// progress.
+    pub fn transferred(&self, total: crate::ByteSize) -> Option<crate::ByteSize> {
+        match self {
+            Self::DownloadingProgress(x)
+            | Self::DecompressingProgress(x)
+            | Self::FlashingProgress(x) => Some(crate::ByteSize::new(
+                (total.bytes() as f64 * f64::from(x.clamp(0.0, 1.0))) as u64,
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Step of post install customization currently being applied.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CustomizationStep {
+    Mounting,
+    Sysconf,
+    User,
+    Wifi,
+    Files,
+    Cmdline,
+    FirstBoot,
+    Syncing,
+    Label,
+}
+
+impl CustomizationStep {
+    pub const ALL: [Self; 9] = [
+        Self::Mounting,
+        Self::Sysconf,
+        Self::User,
+        Self::Wifi,
+        Self::Files,
+        Self::Cmdline,
+        Self::FirstBoot,
+        Self::Syncing,
+        Self::Label,
+    ];
+}
+
+impl std::fmt::Display for CustomizationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mounting => write!(f, "Mounting boot partition"),
+            Self::Sysconf => write!(f, "Writing sysconf.txt"),
+            Self::User => write!(f, "Configuring user"),
+            Self::Wifi => write!(f, "Configuring WiFi"),
+            Self::Files => write!(f, "Copying files"),
+            Self::Cmdline => write!(f, "Editing kernel command line"),
+            Self::FirstBoot => write!(f, "Writing first boot unit"),
+            Self::Syncing => write!(f, "Syncing"),
+            Self::Label => write!(f, "Setting volume labels"),
+        }
+    }
+}
+
+/// Identity of a flashing job. Allows distinguishing progress of concurrent flashes.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct JobId(u64);
+
+impl JobId {
+    /// Create a new unique job id.
+    pub fn new() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for JobId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for JobId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// [DownloadFlashingStatus] along with the job it belongs to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ProgressEvent {
+    pub job: JobId,
+    pub status: DownloadFlashingStatus,
+}
+
+impl From<ProgressEvent> for DownloadFlashingStatus {
+    fn from(value: ProgressEvent) -> Self {
+        value.status
+    }
 }
 
 /// A trait for modeling flashers. Also provides optional live status using channels.
@@ -38,6 +167,34 @@ pub trait BBFlasher {
         self,
         chan: Option<mpsc::Sender<DownloadFlashingStatus>>,
     ) -> impl Future<Output = anyhow::Result<()>>;
+
+    /// Same as [flash](BBFlasher::flash), but progress is tagged with a [JobId]. Useful when
+    /// multiple flashers share a single progress channel.
+    fn flash_job(
+        self,
+        job: JobId,
+        chan: Option<mpsc::Sender<ProgressEvent>>,
+    ) -> impl Future<Output = anyhow::Result<()>>
+    where
+        Self: Sized,
+    {
+        let span = tracing::info_span!("flash_job", %job);
+
+        async move {
+            let Some(chan) = chan else {
+                return self.flash(None).await;
+            };
+
+            let (tx, rx) = mpsc::channel(20);
+            let forward = rx
+                .map(move |status| Ok(ProgressEvent { job, status }))
+                .forward(chan);
+
+            let (res, _) = futures::future::join(self.flash(Some(tx)), forward).await;
+            res
+        }
+        .instrument(span)
+    }
 }
 
 /// A trait for modeling flasher targets.
@@ -0,0 +1,388 @@
+//! Enumerate devices and watch for hotplug.
+//!
+//! Flashers do not query the OS directly. Instead, they select their destinations from the
+//! devices listed by a [`DeviceEnumerator`]:
+//!
+//! - [`System`]: Lists devices connected to the host. Platform differences are handled here, so
+//!   all flashers see the same metadata on every OS.
+//! - [`Fake`]: Devices added and removed manually. Useful to test code depending on devices
+//!   without hardware.
+//! - [`Targets`]: Destinations of any [`BBFlasherTarget`].
+//!
+//! [`watch`] turns any enumerator into a stream of [`DeviceEvent`], which applications can use
+//! instead of polling themselves.
+//!
+//! ```
+//! use futures::StreamExt;
+//! use bb_flasher::devices::{DeviceEvent, Fake, watch};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let fake = Fake::from_iter(["/dev/sda"]);
+//!     let mut events = Box::pin(watch(fake.clone(), std::time::Duration::from_millis(10)));
+//!
+//!     assert_eq!(events.next().await, Some(DeviceEvent::Added("/dev/sda")));
+//!
+//!     fake.insert("/dev/sdb");
+//!     fake.remove(&"/dev/sda");
+//!     assert_eq!(events.next().await, Some(DeviceEvent::Removed("/dev/sda")));
+//!     assert_eq!(events.next().await, Some(DeviceEvent::Added("/dev/sdb")));
+//! }
+//! ```
+
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::Hash,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::Stream;
+
+use crate::BBFlasherTarget;
+
+/// Drive which might be an SD Card.
+#[cfg(feature = "sd")]
+pub use bb_flasher_sd::Device as Drive;
+
+/// Source of devices of type `D`.
+pub trait DeviceEnumerator<D> {
+    /// Devices currently connected.
+    fn enumerate(&self) -> impl Future<Output = HashSet<D>>;
+}
+
+/// USB details of a device.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UsbInfo {
+    pub vid: u16,
+    pub pid: u16,
+    /// Manufacturer string of the USB device. Only available on Linux.
+    pub manufacturer: Option<String>,
+    /// Product string of the USB device. Only available on Linux.
+    pub product: Option<String>,
+}
+
+/// Serial port
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SerialPort {
+    pub path: String,
+    /// Only available for USB serial ports.
+    pub usb: Option<UsbInfo>,
+}
+
+/// HID device
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HidDevice {
+    pub path: std::ffi::CString,
+    pub vid: u16,
+    pub pid: u16,
+}
+
+/// Devices connected to the host.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct System;
+
+impl System {
+    /// All drives, including fixed and virtual drives.
+    #[cfg(feature = "sd")]
+    pub fn drives(&self) -> HashSet<Drive> {
+        bb_flasher_sd::devices(false)
+    }
+
+    /// All serial ports.
+    #[cfg(feature = "bcf")]
+    pub fn serial_ports(&self) -> HashSet<SerialPort> {
+        serialport::available_ports()
+            .expect("Unsupported OS")
+            .into_iter()
+            .map(|x| SerialPort {
+                path: x.port_name,
+                usb: match x.port_type {
+                    serialport::SerialPortType::UsbPort(y) => Some(UsbInfo {
+                        vid: y.vid,
+                        pid: y.pid,
+                        // Windows and MacOS report the driver instead of the USB device here.
+                        manufacturer: y.manufacturer.filter(|_| cfg!(target_os = "linux")),
+                        product: y.product.filter(|_| cfg!(target_os = "linux")),
+                    }),
+                    _ => None,
+                },
+            })
+            .collect()
+    }
+
+    /// All HID devices.
+    #[cfg(feature = "bcf_msp430")]
+    pub fn hid_devices(&self) -> HashSet<HidDevice> {
+        hidapi::HidApi::new()
+            .expect("Failed to create hidapi context")
+            .device_list()
+            .map(|x| HidDevice {
+                path: x.path().to_owned(),
+                vid: x.vendor_id(),
+                pid: x.product_id(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "sd")]
+impl DeviceEnumerator<Drive> for System {
+    fn enumerate(&self) -> impl Future<Output = HashSet<Drive>> {
+        std::future::ready(self.drives())
+    }
+}
+
+#[cfg(feature = "bcf")]
+impl DeviceEnumerator<SerialPort> for System {
+    fn enumerate(&self) -> impl Future<Output = HashSet<SerialPort>> {
+        std::future::ready(self.serial_ports())
+    }
+}
+
+#[cfg(feature = "bcf_msp430")]
+impl DeviceEnumerator<HidDevice> for System {
+    fn enumerate(&self) -> impl Future<Output = HashSet<HidDevice>> {
+        std::future::ready(self.hid_devices())
+    }
+}
+
+/// Destinations of a [`BBFlasherTarget`].
+#[derive(Debug)]
+pub struct Targets<T> {
+    filter: bool,
+    _target: PhantomData<fn() -> T>,
+}
+
+impl<T> Targets<T> {
+    /// Only list destinations which are likely to be correct for the flasher if `filter` is set.
+    /// See [`BBFlasherTarget::destinations`].
+    pub const fn new(filter: bool) -> Self {
+        Self {
+            filter,
+            _target: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Targets<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.filter)
+    }
+}
+
+impl<T: BBFlasherTarget> DeviceEnumerator<T> for Targets<T> {
+    fn enumerate(&self) -> impl Future<Output = HashSet<T>> {
+        T::destinations(self.filter)
+    }
+}
+
+/// Enumerator using a function. Useful for flashers which need more options than `filter`, like
+/// `destinations_with_ids` of BeagleConnect Freedom targets.
+#[derive(Debug, Clone)]
+pub struct FnEnumerator<F>(F);
+
+/// Create an enumerator from a function returning connected devices.
+pub const fn from_fn<F, Fut, D>(f: F) -> FnEnumerator<F>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = HashSet<D>>,
+{
+    FnEnumerator(f)
+}
+
+impl<F, Fut, D> DeviceEnumerator<D> for FnEnumerator<F>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = HashSet<D>>,
+{
+    fn enumerate(&self) -> impl Future<Output = HashSet<D>> {
+        (self.0)()
+    }
+}
+
+/// Enumerator with devices added and removed manually. Clones share the same devices.
+#[derive(Debug)]
+pub struct Fake<D>(Arc<Mutex<HashSet<D>>>);
+
+impl<D> Clone for Fake<D> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<D> Default for Fake<D> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<D: Eq + Hash> FromIterator<D> for Fake<D> {
+    fn from_iter<I: IntoIterator<Item = D>>(iter: I) -> Self {
+        Self(Arc::new(Mutex::new(iter.into_iter().collect())))
+    }
+}
+
+impl<D: Eq + Hash> Fake<D> {
+    /// Connect a device.
+    pub fn insert(&self, device: D) {
+        self.0.lock().unwrap().insert(device);
+    }
+
+    /// Disconnect a device.
+    pub fn remove(&self, device: &D) {
+        self.0.lock().unwrap().remove(device);
+    }
+}
+
+impl<D: Clone + Eq + Hash> DeviceEnumerator<D> for Fake<D> {
+    fn enumerate(&self) -> impl Future<Output = HashSet<D>> {
+        std::future::ready(self.0.lock().unwrap().clone())
+    }
+}
+
+/// Change in connected devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent<D> {
+    Added(D),
+    Removed(D),
+}
+
+struct WatchState<E, D> {
+    enumerator: E,
+    known: HashSet<D>,
+    pending: VecDeque<DeviceEvent<D>>,
+    started: bool,
+}
+
+/// Enumerate devices every `interval`, and emit the changes. Devices connected when the stream is
+/// first polled are emitted as [`DeviceEvent::Added`]. Removals are emitted before additions
+/// found in the same enumeration.
+pub fn watch<E, D>(enumerator: E, interval: Duration) -> impl Stream<Item = DeviceEvent<D>>
+where
+    E: DeviceEnumerator<D>,
+    D: Clone + Eq + Hash,
+{
+    let state = WatchState {
+        enumerator,
+        known: HashSet::new(),
+        pending: VecDeque::new(),
+        started: false,
+    };
+
+    futures::stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(ev) = state.pending.pop_front() {
+                return Some((ev, state));
+            }
+
+            if state.started {
+                tokio::time::sleep(interval).await;
+            }
+            state.started = true;
+
+            let current = state.enumerator.enumerate().await;
+            state.pending.extend(
+                state
+                    .known
+                    .difference(&current)
+                    .cloned()
+                    .map(DeviceEvent::Removed),
+            );
+            state.pending.extend(
+                current
+                    .difference(&state.known)
+                    .cloned()
+                    .map(DeviceEvent::Added),
+            );
+            state.known = current;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    async fn next<D>(events: &mut (impl Stream<Item = DeviceEvent<D>> + Unpin)) -> DeviceEvent<D> {
+        tokio::time::timeout(Duration::from_secs(1), events.next())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn initial() {
+        let fake = Fake::from_iter([1, 2]);
+        let mut events = Box::pin(watch(fake, INTERVAL));
+
+        let mut added = vec![next(&mut events).await, next(&mut events).await];
+        added.sort_by_key(|x| match x {
+            DeviceEvent::Added(x) | DeviceEvent::Removed(x) => *x,
+        });
+        assert_eq!(added, [DeviceEvent::Added(1), DeviceEvent::Added(2)]);
+    }
+
+    #[tokio::test]
+    async fn hotplug() {
+        let fake = Fake::default();
+        let mut events = Box::pin(watch(fake.clone(), INTERVAL));
+
+        fake.insert(1);
+        assert_eq!(next(&mut events).await, DeviceEvent::Added(1));
+
+        fake.remove(&1);
+        assert_eq!(next(&mut events).await, DeviceEvent::Removed(1));
+
+        // Reconnecting emits a new event.
+        fake.insert(1);
+        assert_eq!(next(&mut events).await, DeviceEvent::Added(1));
+    }
+
+    #[tokio::test]
+    async fn removed_first() {
+        let fake = Fake::from_iter([1]);
+        let mut events = Box::pin(watch(fake.clone(), INTERVAL));
+        assert_eq!(next(&mut events).await, DeviceEvent::Added(1));
+
+        // Both changes are seen by the same enumeration.
+        fake.insert(2);
+        fake.remove(&1);
+        assert_eq!(next(&mut events).await, DeviceEvent::Removed(1));
+        assert_eq!(next(&mut events).await, DeviceEvent::Added(2));
+    }
+
+    #[tokio::test]
+    async fn no_change() {
+        let fake = Fake::from_iter([1]);
+        let mut events = Box::pin(watch(fake, INTERVAL));
+        assert_eq!(next(&mut events).await, DeviceEvent::Added(1));
+
+        let res = tokio::time::timeout(INTERVAL * 5, events.next()).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn from_fn_enumerator() {
+        let fake = Fake::from_iter([1, 2, 3]);
+        let odd = from_fn(|| {
+            let fake = fake.clone();
+            async move {
+                fake.enumerate()
+                    .await
+                    .into_iter()
+                    .filter(|x| x % 2 == 1)
+                    .collect()
+            }
+        });
+
+        assert_eq!(odd.enumerate().await, HashSet::from([1, 3]));
+        fake.insert(5);
+        assert_eq!(odd.enumerate().await, HashSet::from([1, 3, 5]));
+    }
+}
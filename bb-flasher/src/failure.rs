@@ -0,0 +1,150 @@
+//! Classify errors returned by flashers, so that applications can suggest how to fix them without
+//! matching on error messages.
+
+use std::{error::Error, io};
+
+/// Broad cause of a failed flashing job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureKind {
+    /// Destination is write protected by hardware (e.g. lock switch) or the OS.
+    WriteProtected,
+    /// Not allowed to open the destination.
+    PermissionDenied,
+    /// Destination is mounted or held open by other processes.
+    DestinationBusy,
+    /// Destination was disconnected or could not be found.
+    DestinationNotFound,
+    /// Not enough free space to store the image.
+    InsufficientSpace,
+    /// Network connection failed while downloading the image.
+    Network,
+    /// Image or firmware is invalid or corrupted.
+    InvalidImage,
+    /// Data read back from the destination does not match the image.
+    VerificationFailed,
+    /// Bootloader of the microcontroller could not be started.
+    Bootloader,
+    /// A stage exceeded its [`Timeouts`](crate::Timeouts).
+    Timeout,
+    /// Board did not boot after flashing.
+    BootFailed,
+    /// Aborted before completing.
+    Aborted,
+    /// Cause could not be determined.
+    Unknown,
+}
+
+impl FailureKind {
+    /// Walk the error chain and return the first recognized cause.
+    pub fn classify(err: &anyhow::Error) -> Self {
+        err.chain()
+            .find_map(Self::from_cause)
+            .unwrap_or(Self::Unknown)
+    }
+
+    fn from_cause(err: &(dyn Error + 'static)) -> Option<Self> {
+        if let Some(e) = err.downcast_ref::<io::Error>() {
+            return Self::from_io(e);
+        }
+
+        if err.is::<crate::TimeoutError>() {
+            return Some(Self::Timeout);
+        }
+
+        if let Some(crate::ImageError::InsufficientSpace { .. }) = err.downcast_ref() {
+            return Some(Self::InsufficientSpace);
+        }
+
+        #[cfg(feature = "boot_test")]
+        if let Some(e) = err.downcast_ref::<crate::BootTestError>() {
+            use crate::BootTestError;
+
+            return match e {
+                BootTestError::FailedToOpenPort { .. } => Some(Self::DestinationBusy),
+                BootTestError::MarkerNotFound { .. } => Some(Self::BootFailed),
+                _ => None,
+            };
+        }
+
+        #[cfg(feature = "sd")]
+        if let Some(e) = err.downcast_ref::<bb_flasher_sd::Error>() {
+            use bb_flasher_sd::Error;
+
+            return match e {
+                Error::WriteProtected => Some(Self::WriteProtected),
+                Error::DestinationBusy { .. } => Some(Self::DestinationBusy),
+                Error::InvalidPartitionTable | Error::InvalidBootPartition | Error::InvalidBmap => {
+                    Some(Self::InvalidImage)
+                }
+                Error::VerificationFailed { .. } => Some(Self::VerificationFailed),
+                Error::Aborted | Error::AbortedAt { .. } => Some(Self::Aborted),
+                _ => None,
+            };
+        }
+
+        #[cfg(feature = "bcf")]
+        if let Some(e) = err.downcast_ref::<bb_flasher_bcf::cc1352p7::Error>() {
+            use bb_flasher_bcf::cc1352p7::Error;
+
+            return match e {
+                Error::FailedToStartBootloader { .. } => Some(Self::Bootloader),
+                Error::FailedToOpenPort => Some(Self::DestinationBusy),
+                Error::InvalidImage | Error::ImageTooLarge(_) => Some(Self::InvalidImage),
+                Error::Aborted => Some(Self::Aborted),
+                _ => None,
+            };
+        }
+
+        #[cfg(feature = "bcf_msp430")]
+        if let Some(e) = err.downcast_ref::<bb_flasher_bcf::msp430::Error>() {
+            use bb_flasher_bcf::msp430::Error;
+
+            return match e {
+                Error::DeviceNotFound => Some(Self::DestinationNotFound),
+                Error::UnlockFail | Error::BSLJumpFail => Some(Self::Bootloader),
+                Error::InvalidFirmware => Some(Self::InvalidImage),
+                _ => None,
+            };
+        }
+
+        #[cfg(feature = "dfu")]
+        if let Some(e) = err.downcast_ref::<bb_flasher_dfu::Error>() {
+            use bb_flasher_dfu::Error;
+
+            return match e {
+                Error::UsbDevNotFound | Error::DfuIntfNotFound => Some(Self::DestinationNotFound),
+                Error::Aborted => Some(Self::Aborted),
+                _ => None,
+            };
+        }
+
+        #[cfg(feature = "pb2_mspm0")]
+        if let Some(bb_flasher_pb2_mspm0::Error::InvalidFirmware) = err.downcast_ref() {
+            return Some(Self::InvalidImage);
+        }
+
+        None
+    }
+
+    fn from_io(err: &io::Error) -> Option<Self> {
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => Some(Self::PermissionDenied),
+            io::ErrorKind::ReadOnlyFilesystem => Some(Self::WriteProtected),
+            io::ErrorKind::ResourceBusy => Some(Self::DestinationBusy),
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => {
+                Some(Self::InsufficientSpace)
+            }
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::NetworkDown => Some(Self::Network),
+            // Errors from other crates are often wrapped in io errors, which hides them from the
+            // error chain.
+            _ => err.get_ref().and_then(|e| Self::from_cause(e)),
+        }
+    }
+}
@@ -3,9 +3,23 @@
 //! [BeagleConnect Freedom]: https://www.beagleboard.org/boards/beagleconnect-freedom
 //! [CC1352P7]: https://www.ti.com/product/CC1352P7
 
-use std::{borrow::Cow, fmt::Display, io::Read};
+use std::{borrow::Cow, fmt::Display};
 
-use crate::{BBFlasher, BBFlasherTarget, Resolvable};
+use crate::{
+    BBFlasher, BBFlasherTarget, Resolvable,
+    devices::{DeviceEnumerator, SerialPort, System, UsbInfo},
+};
+
+pub use bb_flasher_bcf::cc1352p7::{FlowControl, PortConfig, Slot};
+
+/// Default [`Timeouts`](crate::Timeouts). Firmware is small, so flashing should not take more than
+/// a few minutes.
+pub const DEFAULT_TIMEOUTS: crate::Timeouts = crate::Timeouts {
+    download: None,
+    flash: Some(std::time::Duration::from_secs(5 * 60)),
+    verify: Some(std::time::Duration::from_secs(2 * 60)),
+    customize: None,
+};
 
 /// BeagleConnect Freedom target
 #[derive(Hash, PartialEq, Eq, Clone, Debug)]
@@ -15,6 +29,72 @@ impl Target {
     pub fn path(&self) -> &str {
         self.0.as_str()
     }
+
+    /// Same as [`BBFlasherTarget::destinations`], but only USB ports matching `usb_ids` (VID, PID)
+    /// are returned when `filter` is set. Falls back to default filters if `usb_ids` is empty.
+    pub fn destinations_with_ids(
+        filter: bool,
+        usb_ids: &[(u16, u16)],
+    ) -> std::collections::HashSet<Self> {
+        Self::select(System.serial_ports(), filter, usb_ids)
+    }
+
+    /// Same as [`Self::destinations_with_ids`], but select from the ports listed by `enumerator`.
+    pub async fn destinations_from(
+        enumerator: &impl DeviceEnumerator<SerialPort>,
+        filter: bool,
+        usb_ids: &[(u16, u16)],
+    ) -> std::collections::HashSet<Self> {
+        Self::select(enumerator.enumerate().await, filter, usb_ids)
+    }
+
+    /// Without `usb_ids`, the USB manufacturer and product strings are checked when known.
+    fn select(
+        ports: std::collections::HashSet<SerialPort>,
+        filter: bool,
+        usb_ids: &[(u16, u16)],
+    ) -> std::collections::HashSet<Self> {
+        ports
+            .into_iter()
+            .filter(|x| {
+                if !filter {
+                    return true;
+                }
+
+                match &x.usb {
+                    Some(y) if !usb_ids.is_empty() => usb_ids.contains(&(y.vid, y.pid)),
+                    Some(UsbInfo {
+                        manufacturer: Some(manufacturer),
+                        product: Some(product),
+                        ..
+                    }) => manufacturer == "BeagleBoard.org" && product == "BeagleConnect",
+                    Some(_) => true,
+                    None => false,
+                }
+            })
+            .map(|x| Self(x.path))
+            .collect()
+    }
+
+    /// Query bootloader and firmware version. This resets the device into bootloader.
+    pub async fn probe(&self, config: PortConfig) -> anyhow::Result<super::DeviceVersion> {
+        let port = self.0.clone();
+
+        tokio::task::spawn_blocking(move || bb_flasher_bcf::cc1352p7::probe(&port, config))
+            .await
+            .unwrap()
+            .map_err(Into::into)
+    }
+
+    /// Toggle the DTR line for `duration`. Unlike [`Self::probe`], the device is not reset.
+    pub async fn identify(&self, duration: std::time::Duration) -> anyhow::Result<()> {
+        let port = self.0.clone();
+
+        tokio::task::spawn_blocking(move || bb_flasher_bcf::cc1352p7::identify(&port, duration))
+            .await
+            .unwrap()
+            .map_err(Into::into)
+    }
 }
 
 impl From<String> for Target {
@@ -27,12 +107,7 @@ impl BBFlasherTarget for Target {
     const FILE_TYPES: &[&str] = &["bin", "hex", "txt", "xz"];
 
     fn destinations(filter: bool) -> impl Future<Output = std::collections::HashSet<Self>> {
-        let temp = bb_flasher_bcf::cc1352p7::ports(filter)
-            .into_iter()
-            .map(Self)
-            .collect();
-
-        std::future::ready(temp)
+        std::future::ready(Self::destinations_with_ids(filter, &[]))
     }
 
     fn identifier(&self) -> Cow<'_, str> {
@@ -46,6 +121,27 @@ impl Display for Target {
     }
 }
 
+/// BeagleConnect Freedom flashing options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlashingBcfConfig {
+    /// Verify firmware after flashing
+    pub verify: bool,
+    /// Override the firmware slot to write. See [`Flasher`].
+    pub slot: Option<Slot>,
+    /// Serial port settings
+    pub port: PortConfig,
+}
+
+impl Default for FlashingBcfConfig {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            slot: None,
+            port: PortConfig::default(),
+        }
+    }
+}
+
 /// Flasher to flash BeagleConnect Freedom Images
 ///
 /// # Supported Image Formats
@@ -53,11 +149,16 @@ impl Display for Target {
 /// - Ti-TXT
 /// - iHex
 /// - xz: Xz compressed files for any of the above
+///
+/// # Slots
+///
+/// On devices with an A/B capable bootloader, the image is written to the inactive slot unless
+/// [`FlashingBcfConfig::slot`] is provided.
 #[derive(Debug, Clone)]
 pub struct Flasher<I: Resolvable> {
     img: I,
     port: String,
-    verify: bool,
+    config: FlashingBcfConfig,
     cancel: Option<tokio_util::sync::CancellationToken>,
 }
 
@@ -68,58 +169,63 @@ where
     pub fn new(
         img: I,
         port: Target,
-        verify: bool,
+        config: FlashingBcfConfig,
         cancel: Option<tokio_util::sync::CancellationToken>,
     ) -> Self {
         Self {
             img,
             port: port.0,
-            verify,
+            config,
             cancel,
         }
     }
 }
 
+impl<I> Flasher<I>
+where
+    I: Resolvable<ResolvedType = (crate::OsImage, u64)> + Sync,
+{
+    /// Compare firmware on the device with the image without writing anything. Returns `true` if
+    /// the firmware matches.
+    pub async fn verify(self) -> anyhow::Result<bool> {
+        let port = self.port;
+        let config = self.config;
+        let img = super::resolve_firmware(self.img, None).await?;
+
+        tokio::task::spawn_blocking(move || {
+            bb_flasher_bcf::cc1352p7::verify(&img, &port, config.port, config.slot)
+        })
+        .await
+        .unwrap()
+        .map_err(Into::into)
+    }
+}
+
 impl<I> BBFlasher for Flasher<I>
 where
     I: Resolvable<ResolvedType = (crate::OsImage, u64)> + Sync,
 {
+    #[tracing::instrument(name = "bcf_cc1352p7_flash", skip_all, fields(dst = %self.port))]
     async fn flash(
         self,
         chan: Option<futures::channel::mpsc::Sender<crate::DownloadFlashingStatus>>,
     ) -> anyhow::Result<()> {
         let port = self.port;
-        let verify = self.verify;
-        let img = {
-            let mut tasks = tokio::task::JoinSet::new();
-            let (mut img, _) =
-                self.img.resolve(&mut tasks).await.map_err(|source| {
-                    crate::common::FlasherError::ImageResolvingError { source }
-                })?;
-
-            let resp = tokio::task::spawn_blocking(move || {
-                let mut data = Vec::new();
-                img.read_to_end(&mut data)?;
-                Ok::<Vec<u8>, std::io::Error>(data)
-            })
-            .await
-            .unwrap()
-            .map_err(|source| crate::common::FlasherError::ImageResolvingError { source })?;
-
-            while let Some(t) = tasks.join_next().await {
-                if let Err(e) = t.unwrap() {
-                    tasks.abort_all();
-                    return Err(e.into());
-                }
-            }
-
-            resp
-        };
+        let config = self.config;
+        let img = super::resolve_firmware(self.img, chan.clone()).await?;
 
         let flasher_task = if let Some(mut chan) = chan {
             let (tx, mut rx) = tokio::sync::mpsc::channel(20);
             let flasher_task = tokio::task::spawn_blocking(move || {
-                bb_flasher_bcf::cc1352p7::flash(&img, &port, verify, Some(tx), self.cancel)
+                bb_flasher_bcf::cc1352p7::flash(
+                    &img,
+                    &port,
+                    config.port,
+                    config.verify,
+                    config.slot,
+                    Some(tx),
+                    self.cancel,
+                )
             });
 
             // Should run until tx is dropped, i.e. flasher task is done.
@@ -131,10 +237,82 @@ where
             flasher_task
         } else {
             tokio::task::spawn_blocking(move || {
-                bb_flasher_bcf::cc1352p7::flash(&img, &port, verify, None, self.cancel)
+                bb_flasher_bcf::cc1352p7::flash(
+                    &img,
+                    &port,
+                    config.port,
+                    config.verify,
+                    config.slot,
+                    None,
+                    self.cancel,
+                )
             })
         };
 
         flasher_task.await.unwrap().map_err(Into::into)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::devices::Fake;
+
+    use super::*;
+
+    fn port(path: &str, usb: Option<(u16, u16, Option<&str>, Option<&str>)>) -> SerialPort {
+        SerialPort {
+            path: path.to_string(),
+            usb: usb.map(|(vid, pid, manufacturer, product)| UsbInfo {
+                vid,
+                pid,
+                manufacturer: manufacturer.map(String::from),
+                product: product.map(String::from),
+            }),
+        }
+    }
+
+    async fn paths(fake: &Fake<SerialPort>, filter: bool, usb_ids: &[(u16, u16)]) -> Vec<String> {
+        let mut paths: Vec<_> = Target::destinations_from(fake, filter, usb_ids)
+            .await
+            .into_iter()
+            .map(|x| x.0)
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[tokio::test]
+    async fn destinations() {
+        let fake = Fake::from_iter([
+            port("/dev/ttyS0", None),
+            port(
+                "/dev/ttyACM0",
+                Some((
+                    0x2047,
+                    0x0a0c,
+                    Some("BeagleBoard.org"),
+                    Some("BeagleConnect"),
+                )),
+            ),
+            port(
+                "/dev/ttyACM1",
+                Some((0x1234, 0x5678, Some("Other"), Some("Serial"))),
+            ),
+            port("/dev/ttyUSB0", Some((0x1234, 0x5678, None, None))),
+        ]);
+
+        assert_eq!(
+            paths(&fake, false, &[]).await,
+            ["/dev/ttyACM0", "/dev/ttyACM1", "/dev/ttyS0", "/dev/ttyUSB0"]
+        );
+        // Ports without manufacturer and product strings cannot be ruled out.
+        assert_eq!(
+            paths(&fake, true, &[]).await,
+            ["/dev/ttyACM0", "/dev/ttyUSB0"]
+        );
+        assert_eq!(
+            paths(&fake, true, &[(0x1234, 0x5678)]).await,
+            ["/dev/ttyACM1", "/dev/ttyUSB0"]
+        );
+    }
+}
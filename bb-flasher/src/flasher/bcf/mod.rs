@@ -10,6 +10,12 @@ pub mod cc1352p7;
 #[cfg(feature = "bcf_msp430")]
 pub mod msp430;
 
+use std::io::Read;
+
+use crate::Resolvable;
+
+pub use bb_flasher_bcf::DeviceVersion;
+
 impl From<bb_flasher_bcf::Status> for crate::DownloadFlashingStatus {
     fn from(value: bb_flasher_bcf::Status) -> Self {
         match value {
@@ -19,3 +25,37 @@ impl From<bb_flasher_bcf::Status> for crate::DownloadFlashingStatus {
         }
     }
 }
+
+/// Resolve the image and read the whole firmware into memory.
+async fn resolve_firmware<I>(
+    img: I,
+    chan: Option<futures::channel::mpsc::Sender<crate::DownloadFlashingStatus>>,
+) -> anyhow::Result<Vec<u8>>
+where
+    I: Resolvable<ResolvedType = (crate::OsImage, u64)>,
+{
+    let mut tasks = tokio::task::JoinSet::new();
+    let (img, _) = img
+        .resolve(&mut tasks)
+        .await
+        .map_err(|source| crate::common::FlasherError::ImageResolvingError { source })?;
+
+    let mut img = img.with_decompression_progress(chan);
+    let resp = tokio::task::spawn_blocking(move || {
+        let mut data = Vec::new();
+        img.read_to_end(&mut data)?;
+        Ok::<Vec<u8>, std::io::Error>(data)
+    })
+    .await
+    .unwrap()
+    .map_err(|source| crate::common::FlasherError::ImageResolvingError { source })?;
+
+    while let Some(t) = tasks.join_next().await {
+        if let Err(e) = t.unwrap() {
+            tasks.abort_all();
+            return Err(e.into());
+        }
+    }
+
+    Ok(resp)
+}
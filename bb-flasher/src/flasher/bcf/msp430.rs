@@ -4,9 +4,20 @@
 //! [BeagleConnect Freedom]: https://www.beagleboard.org/boards/beagleconnect-freedom
 //! [MSP430]: https://www.ti.com/product/MSP430F5503
 
-use std::{borrow::Cow, ffi::CString, fmt::Display, io::Read};
+use std::{borrow::Cow, ffi::CString, fmt::Display};
 
-use crate::{BBFlasher, BBFlasherTarget, Resolvable};
+use crate::{
+    BBFlasher, BBFlasherTarget, Resolvable,
+    devices::{DeviceEnumerator, HidDevice, System},
+};
+
+/// Default [`Timeouts`](crate::Timeouts).
+pub const DEFAULT_TIMEOUTS: crate::Timeouts = crate::Timeouts {
+    download: None,
+    flash: Some(std::time::Duration::from_secs(2 * 60)),
+    verify: Some(std::time::Duration::from_secs(1 * 60)),
+    customize: None,
+};
 
 /// BeagleConnect Freedom MSP430 target
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -19,6 +30,56 @@ impl Target {
     pub fn path(&self) -> &str {
         self.display_path.as_str()
     }
+
+    /// Same as [`BBFlasherTarget::destinations`], but only devices matching `usb_ids` (VID, PID)
+    /// are returned when `filter` is set. Falls back to MSP430 BSL IDs if `usb_ids` is empty.
+    pub fn destinations_with_ids(
+        filter: bool,
+        usb_ids: &[(u16, u16)],
+    ) -> std::collections::HashSet<Self> {
+        Self::select(System.hid_devices(), filter, usb_ids)
+    }
+
+    /// Same as [`Self::destinations_with_ids`], but select from the devices listed by
+    /// `enumerator`.
+    pub async fn destinations_from(
+        enumerator: &impl DeviceEnumerator<HidDevice>,
+        filter: bool,
+        usb_ids: &[(u16, u16)],
+    ) -> std::collections::HashSet<Self> {
+        Self::select(enumerator.enumerate().await, filter, usb_ids)
+    }
+
+    fn select(
+        devices: std::collections::HashSet<HidDevice>,
+        filter: bool,
+        usb_ids: &[(u16, u16)],
+    ) -> std::collections::HashSet<Self> {
+        let usb_ids: &[(u16, u16)] = if usb_ids.is_empty() {
+            &[(bb_flasher_bcf::msp430::VID, bb_flasher_bcf::msp430::PID)]
+        } else {
+            usb_ids
+        };
+
+        devices
+            .into_iter()
+            .filter(|x| !filter || usb_ids.contains(&(x.vid, x.pid)))
+            .map(|x| Self {
+                display_path: x.path.to_string_lossy().to_string(),
+                raw_path: x.path,
+            })
+            .collect()
+    }
+
+    /// Query bootloader version.
+    pub async fn probe(&self) -> anyhow::Result<super::DeviceVersion> {
+        let dst = self.raw_path.clone();
+
+        tokio::task::spawn_blocking(move || bb_flasher_bcf::msp430::probe(&dst))
+            .await
+            .unwrap()
+            .map_err(Into::into)
+    }
 }
 
 impl Display for Target {
@@ -40,13 +101,7 @@ impl BBFlasherTarget for Target {
     const FILE_TYPES: &[&str] = &["hex", "txt", "xz"];
 
     async fn destinations(filter: bool) -> std::collections::HashSet<Self> {
-        bb_flasher_bcf::msp430::devices(filter)
-            .into_iter()
-            .map(|x| Self {
-                display_path: x.to_string_lossy().to_string(),
-                raw_path: x,
-            })
-            .collect()
+        Self::destinations_with_ids(filter, &[])
     }
 
     fn identifier(&self) -> Cow<'_, str> {
@@ -83,40 +138,49 @@ where
     }
 }
 
+impl<I> Flasher<I>
+where
+    I: Resolvable<ResolvedType = (crate::OsImage, u64)>,
+{
+    /// Compare firmware on the device with the image without writing anything. Returns `true` if
+    /// the firmware matches.
+    ///
+    /// `flashed` is the image last flashed to the device. Its interrupt vector table is the BSL
+    /// password, and unlocking with a wrong password mass erases the device, so verification is
+    /// refused without it.
+    pub async fn verify<F>(self, flashed: Option<F>) -> anyhow::Result<bool>
+    where
+        F: Resolvable<ResolvedType = (crate::OsImage, u64)>,
+    {
+        let dst = self.port;
+        let img = super::resolve_firmware(self.img, None).await?;
+        let password = match flashed {
+            Some(x) => Some(bb_flasher_bcf::msp430::password(
+                &super::resolve_firmware(x, None).await?,
+            )?),
+            None => None,
+        };
+
+        tokio::task::spawn_blocking(move || {
+            bb_flasher_bcf::msp430::verify(&img, &dst, password.as_ref())
+        })
+        .await
+        .unwrap()
+        .map_err(Into::into)
+    }
+}
+
 impl<I> BBFlasher for Flasher<I>
 where
     I: Resolvable<ResolvedType = (crate::OsImage, u64)>,
 {
+    #[tracing::instrument(name = "bcf_msp430_flash", skip_all, fields(dst = ?self.port))]
     async fn flash(
         self,
         chan: Option<futures::channel::mpsc::Sender<crate::DownloadFlashingStatus>>,
     ) -> anyhow::Result<()> {
         let dst = self.port;
-        let img = {
-            let mut tasks = tokio::task::JoinSet::new();
-            let (mut img, _) =
-                self.img.resolve(&mut tasks).await.map_err(|source| {
-                    crate::common::FlasherError::ImageResolvingError { source }
-                })?;
-
-            let resp = tokio::task::spawn_blocking(move || {
-                let mut data = Vec::new();
-                img.read_to_end(&mut data)?;
-                Ok::<Vec<u8>, std::io::Error>(data)
-            })
-            .await
-            .unwrap()
-            .map_err(|source| crate::common::FlasherError::ImageResolvingError { source })?;
-
-            while let Some(t) = tasks.join_next().await {
-                if let Err(e) = t.unwrap() {
-                    tasks.abort_all();
-                    return Err(e.into());
-                }
-            }
-
-            resp
-        };
+        let img = super::resolve_firmware(self.img, chan.clone()).await?;
 
         let flasher_task = if let Some(mut chan) = chan {
             let (tx, mut rx) = tokio::sync::mpsc::channel(20);
@@ -138,3 +202,46 @@ where
         flasher_task.await.unwrap().map_err(Into::into)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::devices::Fake;
+
+    use super::*;
+
+    fn device(path: &str, vid: u16, pid: u16) -> HidDevice {
+        HidDevice {
+            path: CString::new(path).unwrap(),
+            vid,
+            pid,
+        }
+    }
+
+    async fn paths(fake: &Fake<HidDevice>, filter: bool, usb_ids: &[(u16, u16)]) -> Vec<String> {
+        let mut paths: Vec<_> = Target::destinations_from(fake, filter, usb_ids)
+            .await
+            .into_iter()
+            .map(|x| x.display_path)
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[tokio::test]
+    async fn destinations() {
+        let fake = Fake::from_iter([
+            device("/dev/hidraw0", 0x2047, 0x0200),
+            device("/dev/hidraw1", 0x1234, 0x5678),
+        ]);
+
+        assert_eq!(
+            paths(&fake, false, &[]).await,
+            ["/dev/hidraw0", "/dev/hidraw1"]
+        );
+        assert_eq!(paths(&fake, true, &[]).await, ["/dev/hidraw0"]);
+        assert_eq!(
+            paths(&fake, true, &[(0x1234, 0x5678)]).await,
+            ["/dev/hidraw1"]
+        );
+    }
+}
@@ -6,6 +6,14 @@ use std::io;
 
 use futures::channel::mpsc;
 
+/// Default [`Timeouts`](crate::Timeouts).
+pub const DEFAULT_TIMEOUTS: crate::Timeouts = crate::Timeouts {
+    download: None,
+    flash: Some(std::time::Duration::from_secs(10 * 60)),
+    verify: None,
+    customize: None,
+};
+
 #[derive(Hash, Eq, PartialEq)]
 pub struct Target(bb_flasher_dfu::Device);
 
@@ -122,6 +130,15 @@ impl<R> BBFlasher for Flasher<R>
 where
     R: Resolvable<ResolvedType = (crate::OsImage, u64)> + Send + 'static,
 {
+    #[tracing::instrument(
+        name = "dfu_flash",
+        skip_all,
+        fields(
+            device = format_args!("{:04x}:{:04x}", self.vendor_id, self.product_id),
+            bus = self.bus_num,
+            port = self.port_num,
+        )
+    )]
     async fn flash(self, chan: Option<mpsc::Sender<DownloadFlashingStatus>>) -> anyhow::Result<()> {
         let c = if let Some(mut c) = chan {
             let (tx, mut rx) = tokio::sync::mpsc::channel(2);
@@ -0,0 +1,122 @@
+//! Mock flasher which simulates flashing without any hardware. Useful for UI development.
+//!
+//! Progress is deterministic: each progress stage takes the same amount of time and reports
+//! progress in fixed steps.
+
+use std::{fmt::Display, time::Duration};
+
+use futures::channel::mpsc;
+
+use crate::{BBFlasher, CustomizationStep, DownloadFlashingStatus};
+
+const STEPS: u32 = 100;
+
+/// Stages simulated by the mock flasher, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Stage {
+    #[default]
+    Preparing,
+    Downloading,
+    Flashing,
+    Verifying,
+    Customizing,
+}
+
+impl Stage {
+    pub const ALL: [Self; 5] = [
+        Self::Preparing,
+        Self::Downloading,
+        Self::Flashing,
+        Self::Verifying,
+        Self::Customizing,
+    ];
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Preparing => write!(f, "Preparing"),
+            Self::Downloading => write!(f, "Downloading"),
+            Self::Flashing => write!(f, "Flashing"),
+            Self::Verifying => write!(f, "Verifying"),
+            Self::Customizing => write!(f, "Customizing"),
+        }
+    }
+}
+
+/// Flasher simulating progress without touching any hardware.
+#[derive(Debug, Clone)]
+pub struct Flasher {
+    stage_duration: Duration,
+    fail_at: Option<Stage>,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+}
+
+impl Flasher {
+    /// Create a new mock flasher.
+    ///
+    /// Each stage takes `stage_duration`. If `fail_at` is set, flashing fails at the start of
+    /// the given stage.
+    pub const fn new(
+        stage_duration: Duration,
+        fail_at: Option<Stage>,
+        cancel: Option<tokio_util::sync::CancellationToken>,
+    ) -> Self {
+        Self {
+            stage_duration,
+            fail_at,
+            cancel,
+        }
+    }
+
+    async fn stage(
+        &self,
+        stage: Stage,
+        chan: &mut Option<mpsc::Sender<DownloadFlashingStatus>>,
+    ) -> anyhow::Result<()> {
+        if self.fail_at == Some(stage) {
+            anyhow::bail!("Simulated failure during {stage}");
+        }
+
+        let status: fn(f32) -> DownloadFlashingStatus = match stage {
+            Stage::Preparing => |_| DownloadFlashingStatus::Preparing,
+            Stage::Downloading => DownloadFlashingStatus::DownloadingProgress,
+            Stage::Flashing => DownloadFlashingStatus::FlashingProgress,
+            Stage::Verifying => |_| DownloadFlashingStatus::Verifying,
+            Stage::Customizing => |x| {
+                let steps = CustomizationStep::ALL;
+                let i = ((x * steps.len() as f32) as usize).min(steps.len() - 1);
+                DownloadFlashingStatus::Customizing(steps[i])
+            },
+        };
+
+        let step = self.stage_duration / STEPS;
+        for i in 0..STEPS {
+            if self.cancel.as_ref().is_some_and(|x| x.is_cancelled()) {
+                anyhow::bail!("Aborted before completing");
+            }
+
+            if let Some(chan) = chan.as_mut() {
+                let _ = chan.try_send(status(i as f32 / STEPS as f32));
+            }
+
+            tokio::time::sleep(step).await;
+        }
+
+        Ok(())
+    }
+}
+
+impl BBFlasher for Flasher {
+    #[tracing::instrument(name = "mock_flash", skip_all)]
+    async fn flash(
+        self,
+        mut chan: Option<mpsc::Sender<DownloadFlashingStatus>>,
+    ) -> anyhow::Result<()> {
+        for stage in Stage::ALL {
+            self.stage(stage, &mut chan).await?;
+        }
+
+        Ok(())
+    }
+}
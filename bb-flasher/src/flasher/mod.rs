@@ -7,3 +7,5 @@ pub mod sd;
 pub mod pb2;
 #[cfg(feature = "dfu")]
 pub mod dfu;
+#[cfg(any(test, feature = "demo"))]
+pub mod mock;
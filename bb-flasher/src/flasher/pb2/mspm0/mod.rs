@@ -20,6 +20,14 @@ use std::io::Read;
 
 use crate::{BBFlasher, BBFlasherTarget, Resolvable};
 
+/// Default [`Timeouts`](crate::Timeouts).
+pub const DEFAULT_TIMEOUTS: crate::Timeouts = crate::Timeouts {
+    download: None,
+    flash: Some(std::time::Duration::from_secs(2 * 60)),
+    verify: Some(std::time::Duration::from_secs(1 * 60)),
+    customize: None,
+};
+
 /// [PocketBeagle 2] [MSPM0L1105] target
 ///
 /// [PocketBeagle 2]: https://www.beagleboard.org/boards/pocketbeagle-2
@@ -80,17 +88,23 @@ impl<I> BBFlasher for Flasher<I>
 where
     I: Resolvable<ResolvedType = (crate::OsImage, u64)>,
 {
+    #[tracing::instrument(
+        name = "pb2_mspm0_flash",
+        skip_all,
+        fields(persist_eeprom = self.persist_eeprom)
+    )]
     async fn flash(
         self,
         chan: Option<futures::channel::mpsc::Sender<crate::DownloadFlashingStatus>>,
     ) -> anyhow::Result<()> {
         let bin = {
             let mut tasks = tokio::task::JoinSet::new();
-            let (mut img, _) =
+            let (img, _) =
                 self.img.resolve(&mut tasks).await.map_err(|source| {
                     crate::common::FlasherError::ImageResolvingError { source }
                 })?;
 
+            let mut img = img.with_decompression_progress(chan.clone());
             let resp = tokio::task::spawn_blocking(move || {
                 let mut data = String::new();
                 img.read_to_string(&mut data)?;
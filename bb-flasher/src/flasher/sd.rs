@@ -4,30 +4,144 @@
 //!
 //! [BeagleBoard.org]: https://www.beagleboard.org/
 
-use std::{borrow::Cow, fmt::Display, path::PathBuf};
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+};
 
-use crate::{BBFlasher, BBFlasherTarget, DownloadFlashingStatus, Resolvable};
+use crate::{
+    BBFlasher, BBFlasherTarget, CustomizationStep, DownloadFlashingStatus, Resolvable,
+    devices::{DeviceEnumerator, Drive, System},
+};
+
+pub use bb_flasher_sd::cmdline::{Error as CmdlineError, validate as validate_cmdline};
+pub use bb_flasher_sd::firstboot::{valid_package_name, valid_url};
+pub use bb_flasher_sd::format::{Filesystem as FormatFilesystem, FormatOptions, PartitionScheme};
+pub use bb_flasher_sd::hostname::{
+    Error as HostnameTemplateError, is_template as is_hostname_template,
+    validate as validate_hostname_template,
+};
+pub use bb_flasher_sd::inspect::{Filesystem, ImageInfo, Partition, PartitionTable};
+pub use bb_flasher_sd::label::{EXT_LABEL_LEN, FAT_LABEL_LEN, valid_ext_label, valid_fat_label};
+pub use bb_flasher_sd::{
+    BootFile, CardClass, CardInfo, PROVENANCE_FILE, Provenance, UsbLocation, Verify,
+};
+
+/// Default [`Timeouts`](crate::Timeouts). Large images on slow SD Cards can take hours, so only
+/// customization, which should be quick, is limited.
+pub const DEFAULT_TIMEOUTS: crate::Timeouts = crate::Timeouts {
+    download: None,
+    flash: None,
+    verify: None,
+    customize: Some(std::time::Duration::from_secs(10 * 60)),
+};
+
+impl From<bb_flasher_sd::Status> for DownloadFlashingStatus {
+    fn from(value: bb_flasher_sd::Status) -> Self {
+        match value {
+            bb_flasher_sd::Status::Preparing => Self::Preparing,
+            bb_flasher_sd::Status::Flashing(x) => Self::FlashingProgress(x),
+            bb_flasher_sd::Status::Verifying => Self::Verifying,
+            bb_flasher_sd::Status::Customizing(x) => Self::Customizing(x.into()),
+        }
+    }
+}
+
+impl From<bb_flasher_sd::CustomizationStep> for CustomizationStep {
+    fn from(value: bb_flasher_sd::CustomizationStep) -> Self {
+        match value {
+            bb_flasher_sd::CustomizationStep::Mounting => Self::Mounting,
+            bb_flasher_sd::CustomizationStep::Sysconf => Self::Sysconf,
+            bb_flasher_sd::CustomizationStep::User => Self::User,
+            bb_flasher_sd::CustomizationStep::Wifi => Self::Wifi,
+            bb_flasher_sd::CustomizationStep::Files => Self::Files,
+            bb_flasher_sd::CustomizationStep::Cmdline => Self::Cmdline,
+            bb_flasher_sd::CustomizationStep::FirstBoot => Self::FirstBoot,
+            bb_flasher_sd::CustomizationStep::Syncing => Self::Syncing,
+            bb_flasher_sd::CustomizationStep::Label => Self::Label,
+        }
+    }
+}
 
 /// SD Card
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Target(bb_flasher_sd::Device);
 
 impl Target {
-    fn destinations_internal(filter: bool) -> std::collections::HashSet<Self> {
-        bb_flasher_sd::devices(filter)
+    /// Same as [`BBFlasherTarget::destinations`], but select from the drives listed by
+    /// `enumerator`. When `filter` is set, only removable drives which are not virtual are
+    /// returned.
+    pub async fn destinations_from(
+        enumerator: &impl DeviceEnumerator<Drive>,
+        filter: bool,
+    ) -> std::collections::HashSet<Self> {
+        Self::select(enumerator.enumerate().await, filter)
+    }
+
+    fn select(
+        drives: std::collections::HashSet<Drive>,
+        filter: bool,
+    ) -> std::collections::HashSet<Self> {
+        drives
             .into_iter()
+            .filter(|x| !filter || (x.removable && !x.is_virtual))
             .map(Self)
             .collect()
     }
 
-    /// SD Card size in bytes
-    pub const fn size(&self) -> u64 {
-        self.0.size
+    pub const fn size(&self) -> crate::ByteSize {
+        crate::ByteSize::new(self.0.size)
     }
 
     pub fn path(&self) -> &std::path::Path {
         &self.0.path
     }
+
+    /// SD Card is write protected by hardware (e.g. lock switch) or the OS.
+    pub const fn is_read_only(&self) -> bool {
+        self.0.read_only
+    }
+
+    /// SD Card is reported as removable media by the OS.
+    pub const fn is_removable(&self) -> bool {
+        self.0.removable
+    }
+
+    /// USB port of the card reader. Useful to tell apart identical card readers.
+    pub const fn location(&self) -> Option<&UsbLocation> {
+        self.0.location.as_ref()
+    }
+
+    /// Model of the card reader.
+    pub fn reader(&self) -> Option<&str> {
+        self.0.reader.as_deref()
+    }
+
+    /// Manufacturer, model and manufacturing date read from the SD Card.
+    pub const fn card(&self) -> Option<&CardInfo> {
+        self.0.card.as_ref()
+    }
+
+    /// Order by physical port. Destinations without a known port come first.
+    pub fn sort(dsts: &mut [Self]) {
+        dsts.sort_by(|a, b| (a.location(), a.path()).cmp(&(b.location(), b.path())));
+    }
+
+    /// Blink the activity LED of the card reader for `duration` by reading from the SD Card.
+    /// Does not modify the SD Card.
+    pub async fn identify(&self, duration: std::time::Duration) -> anyhow::Result<()> {
+        bb_flasher_sd::identify(&self.0.path, self.0.size, duration)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Use a regular file or loop device as destination. Only meant for testing.
+    #[cfg(feature = "test-util")]
+    pub fn file(path: PathBuf) -> std::io::Result<Self> {
+        bb_flasher_sd::Device::file(path).map(Self)
+    }
 }
 
 impl Display for Target {
@@ -40,21 +154,43 @@ impl TryFrom<PathBuf> for Target {
     type Error = std::io::Error;
 
     fn try_from(value: PathBuf) -> Result<Self, Self::Error> {
-        Self::destinations_internal(false)
-            .into_iter()
-            .find(|x| x.0.path == value)
-            .ok_or(std::io::Error::new(
+        if let Some(x) = System.drives().into_iter().find(|x| x.path == value) {
+            return Ok(Self(x));
+        }
+
+        // Drive letters and volume GUID paths are resolved to the drive containing them.
+        #[cfg(windows)]
+        if let Some(volume) = value
+            .to_str()
+            .filter(|x| !x.starts_with("\\\\.\\PhysicalDrive"))
+        {
+            return bb_flasher_sd::device_from_volume(volume)
+                .map(Self)
+                .map_err(std::io::Error::other);
+        }
+
+        match parent_disk(&value) {
+            Some(disk) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is a partition. Use the whole disk {} instead",
+                    value.display(),
+                    disk.display()
+                ),
+            )),
+            None => Err(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
                 "SD Card target not found",
-            ))
+            )),
+        }
     }
 }
 
 impl BBFlasherTarget for Target {
-    const FILE_TYPES: &[&str] = &["img", "xz"];
+    const FILE_TYPES: &[&str] = &["img", "wic", "xz", "gz", "zst"];
 
     async fn destinations(filter: bool) -> std::collections::HashSet<Self> {
-        Self::destinations_internal(filter)
+        Self::destinations_from(&System, filter).await
     }
 
     fn identifier(&self) -> Cow<'_, str> {
@@ -62,10 +198,133 @@ impl BBFlasherTarget for Target {
     }
 }
 
+/// Find the bmap file next to a local image, as produced by Yocto and `bmaptool create`. For
+/// compressed images like `image.wic.xz`, both `image.wic.bmap` and `image.wic.xz.bmap` are
+/// checked.
+pub fn find_bmap(img: &Path) -> Option<PathBuf> {
+    let mut candidates = Vec::with_capacity(2);
+
+    if img
+        .extension()
+        .is_some_and(|x| x == "xz" || x == "gz" || x == "zst")
+    {
+        candidates.push(img.with_extension("bmap"));
+    }
+
+    let mut p = img.as_os_str().to_owned();
+    p.push(".bmap");
+    candidates.push(PathBuf::from(p));
+
+    candidates.into_iter().find(|x| x.is_file())
+}
+
+/// Whole disk containing a partition, based on the device naming of Linux and MacOS. Returns
+/// [`None`] if the path does not look like a partition.
+///
+/// ```
+/// use std::path::{Path, PathBuf};
+/// use bb_flasher::sd::parent_disk;
+///
+/// assert_eq!(parent_disk(Path::new("/dev/sdb1")), Some(PathBuf::from("/dev/sdb")));
+/// assert_eq!(parent_disk(Path::new("/dev/mmcblk0p2")), Some(PathBuf::from("/dev/mmcblk0")));
+/// assert_eq!(parent_disk(Path::new("/dev/nvme0n1p1")), Some(PathBuf::from("/dev/nvme0n1")));
+/// assert_eq!(parent_disk(Path::new("/dev/rdisk4s1")), Some(PathBuf::from("/dev/rdisk4")));
+/// assert_eq!(parent_disk(Path::new("/dev/sdb")), None);
+/// assert_eq!(parent_disk(Path::new("/dev/mmcblk0")), None);
+/// ```
+pub fn parent_disk(path: &Path) -> Option<PathBuf> {
+    /// Strip a partition number, along with `sep` if it is not empty.
+    fn strip_partition<'a>(name: &'a str, sep: &str) -> Option<&'a str> {
+        let disk = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        if disk.len() == name.len() {
+            return None;
+        }
+
+        let disk = disk.strip_suffix(sep)?;
+        let valid = if sep.is_empty() {
+            disk.ends_with(|c: char| c.is_ascii_alphabetic())
+        } else {
+            disk.ends_with(|c: char| c.is_ascii_digit())
+        };
+
+        valid.then_some(disk)
+    }
+
+    if !path.starts_with("/dev") {
+        return None;
+    }
+
+    let name = path.file_name()?.to_str()?;
+    let disk = if ["mmcblk", "nvme", "loop", "nbd"]
+        .iter()
+        .any(|x| name.starts_with(x))
+    {
+        strip_partition(name, "p")?
+    } else if ["sd", "vd", "hd", "xvd"]
+        .iter()
+        .any(|x| name.starts_with(x))
+    {
+        strip_partition(name, "")?
+    } else if name.starts_with("disk") || name.starts_with("rdisk") {
+        strip_partition(name, "s")?
+    } else {
+        return None;
+    };
+
+    Some(path.with_file_name(disk))
+}
+
+/// Inspect partitions and metadata of a local image. Compressed images are extracted on the fly.
+///
+/// This is a blocking operation.
+pub fn inspect(path: &Path) -> anyhow::Result<ImageInfo> {
+    let img = crate::OsImage::from_path(path)?;
+    let size = img.size();
+
+    bb_flasher_sd::inspect::inspect(img, size).map_err(Into::into)
+}
+
+/// Compare an SD Card with the OS image without writing anything. Used to check if an interrupted
+/// flashing had already written the whole image. Customized SD Cards never match.
+///
+/// Images flashed with a bmap need the same bmap, since unmapped blocks were never written.
+pub async fn compare<I, B>(
+    img: I,
+    bmap: Option<B>,
+    dst: Target,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+) -> anyhow::Result<()>
+where
+    I: Resolvable<ResolvedType = (crate::OsImage, u64)> + Send + 'static,
+    B: Resolvable<ResolvedType = Box<str>>,
+{
+    bb_flasher_sd::compare(img, bmap, dst.0.path.into(), cancel)
+        .await
+        .map_err(Into::into)
+}
+
+/// Serial number of the SD Card at `dst`, if the card reader exposes it.
+pub fn card_serial(dst: &Path) -> Option<String> {
+    bb_flasher_sd::hostname::card_serial(dst)
+}
+
+/// Expand a hostname template like `beagle-{serial4}` or `lab-{rand4}` for a single destination.
+/// `dst` is the SD Card, if any, used to get the card serial number. Templates are expanded
+/// automatically while flashing, so this is only needed to know the generated hostname
+/// beforehand.
+pub fn expand_hostname(
+    template: &str,
+    dst: Option<&Path>,
+) -> Result<String, HostnameTemplateError> {
+    let serial = dst.and_then(bb_flasher_sd::hostname::card_serial);
+    bb_flasher_sd::hostname::expand(template, serial.as_deref())
+}
+
 /// Linux Image post-install customization options. Only work on BeagleBoard.org images.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FlashingSdLinuxConfig {
     customization: Option<bb_flasher_sd::Customization>,
+    verify: Verify,
 }
 
 impl FlashingSdLinuxConfig {
@@ -88,35 +347,307 @@ impl FlashingSdLinuxConfig {
                     wifi,
                     ssh,
                     usb_enable_dhcp,
+                    volume_label: None,
+                    rootfs_label: None,
+                    provenance: None,
+                    files: Vec::new(),
+                    cmdline_append: None,
+                    serial_console: None,
+                    packages: Vec::new(),
+                    http_proxy: None,
+                    https_proxy: None,
+                    apt_mirror: None,
                 },
             )),
+            verify: Verify::None,
         }
     }
 
+    /// Set volume labels of the boot (FAT) and root (ext) partitions, so the SD Card can be
+    /// identified when mounted. See [`valid_fat_label`] and [`valid_ext_label`].
+    pub fn with_labels(mut self, volume: Option<Box<str>>, rootfs: Option<Box<str>>) -> Self {
+        if volume.is_none() && rootfs.is_none() {
+            return self;
+        }
+
+        let bb_flasher_sd::Customization::Sysconf(x) = self
+            .customization
+            .get_or_insert_with(|| bb_flasher_sd::Customization::Sysconf(Default::default()));
+        x.volume_label = volume;
+        x.rootfs_label = rootfs;
+
+        self
+    }
+
+    /// Copy files and directories from the host to the boot partition (e.g. a custom `uEnv.txt`).
+    pub fn with_files(mut self, files: Vec<BootFile>) -> Self {
+        if files.is_empty() {
+            return self;
+        }
+
+        let bb_flasher_sd::Customization::Sysconf(x) = self
+            .customization
+            .get_or_insert_with(|| bb_flasher_sd::Customization::Sysconf(Default::default()));
+        x.files = files;
+
+        self
+    }
+
+    /// Append arguments to the kernel command line in extlinux.conf or uEnv.txt of the boot
+    /// partition (e.g. `isolcpus=1`). See [`validate_cmdline`].
+    pub fn with_cmdline_append(mut self, args: Option<Box<str>>) -> Self {
+        let Some(args) = args else {
+            return self;
+        };
+
+        let bb_flasher_sd::Customization::Sysconf(x) = self
+            .customization
+            .get_or_insert_with(|| bb_flasher_sd::Customization::Sysconf(Default::default()));
+        x.cmdline_append = Some(args);
+
+        self
+    }
+
+    /// Enable or disable the serial console at 115200 baud on the kernel command line. Existing
+    /// serial consoles are kept when enabling, since the debug UART differs between boards.
+    pub fn with_serial_console(mut self, enable: Option<bool>) -> Self {
+        let Some(enable) = enable else {
+            return self;
+        };
+
+        let bb_flasher_sd::Customization::Sysconf(x) = self
+            .customization
+            .get_or_insert_with(|| bb_flasher_sd::Customization::Sysconf(Default::default()));
+        x.serial_console = Some(enable);
+
+        self
+    }
+
+    /// Install apt packages on first boot. See [`valid_package_name`].
+    pub fn with_packages(mut self, packages: Vec<Box<str>>) -> Self {
+        if packages.is_empty() {
+            return self;
+        }
+
+        let bb_flasher_sd::Customization::Sysconf(x) = self
+            .customization
+            .get_or_insert_with(|| bb_flasher_sd::Customization::Sysconf(Default::default()));
+        x.packages = packages;
+
+        self
+    }
+
+    /// Configure HTTP(S) proxy and apt mirror on first boot, for networks behind a caching
+    /// proxy. See [`valid_url`].
+    pub fn with_network(
+        mut self,
+        http_proxy: Option<Box<str>>,
+        https_proxy: Option<Box<str>>,
+        apt_mirror: Option<Box<str>>,
+    ) -> Self {
+        if http_proxy.is_none() && https_proxy.is_none() && apt_mirror.is_none() {
+            return self;
+        }
+
+        let bb_flasher_sd::Customization::Sysconf(x) = self
+            .customization
+            .get_or_insert_with(|| bb_flasher_sd::Customization::Sysconf(Default::default()));
+        x.http_proxy = http_proxy;
+        x.https_proxy = https_proxy;
+        x.apt_mirror = apt_mirror;
+
+        self
+    }
+
+    /// Record what was flashed in [`PROVENANCE_FILE`] on the boot partition. Only written when
+    /// the boot partition is customized.
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        if let Some(bb_flasher_sd::Customization::Sysconf(x)) = &mut self.customization {
+            x.provenance = Some(provenance);
+        }
+        self
+    }
+
+    /// Read back the written image before applying customization. Not used when exporting.
+    pub const fn with_verify(mut self, verify: Verify) -> Self {
+        self.verify = verify;
+        self
+    }
+
     pub const fn none() -> Self {
         Self {
             customization: None,
+            verify: Verify::None,
         }
     }
 }
 
 /// Flasher to format SD Cards
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct FormatFlasher(PathBuf);
+pub struct FormatFlasher {
+    dst: PathBuf,
+    options: FormatOptions,
+}
 
 impl FormatFlasher {
+    /// Format as a single FAT32 partition in an MBR partition table.
     pub fn new(p: Target) -> Self {
-        Self(p.0.path)
+        Self {
+            dst: p.0.path,
+            options: FormatOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: FormatOptions) -> Self {
+        self.options = options;
+        self
     }
 }
 
 impl BBFlasher for FormatFlasher {
+    #[tracing::instrument(name = "sd_format", skip_all, fields(dst = %self.dst.display()))]
     async fn flash(
         self,
-        _: Option<futures::channel::mpsc::Sender<DownloadFlashingStatus>>,
+        chan: Option<futures::channel::mpsc::Sender<DownloadFlashingStatus>>,
     ) -> anyhow::Result<()> {
-        let p = self.0;
-        bb_flasher_sd::format(p.as_path()).await.map_err(Into::into)
+        let dst = self.dst.into_boxed_path();
+
+        if let Some(mut chan) = chan {
+            let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+
+            let t = tokio::spawn(async move {
+                while let Some(x) = rx.recv().await {
+                    let _ = chan.try_send(x.into());
+                }
+            });
+
+            let resp = bb_flasher_sd::format(dst, self.options, Some(tx), None).await;
+
+            t.abort();
+
+            resp
+        } else {
+            bb_flasher_sd::format(dst, self.options, None, None).await
+        }
+        .map_err(Into::into)
+    }
+}
+
+/// Flasher to apply customization to an already flashed SD Card without writing the OS image
+/// again.
+#[derive(Debug, Clone)]
+pub struct CustomizeFlasher {
+    dst: PathBuf,
+    customization: FlashingSdLinuxConfig,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+}
+
+impl CustomizeFlasher {
+    pub fn new(
+        dst: Target,
+        customization: FlashingSdLinuxConfig,
+        cancel: Option<tokio_util::sync::CancellationToken>,
+    ) -> Self {
+        Self {
+            dst: dst.0.path,
+            customization,
+            cancel,
+        }
+    }
+}
+
+impl BBFlasher for CustomizeFlasher {
+    #[tracing::instrument(name = "sd_customize", skip_all, fields(dst = %self.dst.display()))]
+    async fn flash(
+        self,
+        chan: Option<futures::channel::mpsc::Sender<DownloadFlashingStatus>>,
+    ) -> anyhow::Result<()> {
+        let customization = self
+            .customization
+            .customization
+            .ok_or_else(|| anyhow::anyhow!("No customization provided"))?;
+        let dst = self.dst.into_boxed_path();
+
+        if let Some(mut chan) = chan {
+            let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+
+            let t = tokio::spawn(async move {
+                while let Some(x) = rx.recv().await {
+                    let _ = chan.try_send(x.into());
+                }
+            });
+
+            let resp = bb_flasher_sd::customize(dst, Some(tx), customization, self.cancel).await;
+
+            t.abort();
+
+            resp
+        } else {
+            bb_flasher_sd::customize(dst, None, customization, self.cancel).await
+        }
+        .map_err(Into::into)
+    }
+}
+
+/// Flasher to export OS Images along with customization to an image file instead of SD Card.
+#[derive(Debug, Clone)]
+pub struct ExportFlasher<I: Resolvable> {
+    img: I,
+    dst: PathBuf,
+    customization: FlashingSdLinuxConfig,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+}
+
+impl<I> ExportFlasher<I>
+where
+    I: Resolvable,
+{
+    pub const fn new(
+        img: I,
+        dst: PathBuf,
+        customization: FlashingSdLinuxConfig,
+        cancel: Option<tokio_util::sync::CancellationToken>,
+    ) -> Self {
+        Self {
+            img,
+            dst,
+            customization,
+            cancel,
+        }
+    }
+}
+
+impl<I> BBFlasher for ExportFlasher<I>
+where
+    I: Resolvable<ResolvedType = (crate::OsImage, u64)> + Send + 'static,
+{
+    #[tracing::instrument(name = "sd_export", skip_all, fields(dst = %self.dst.display()))]
+    async fn flash(
+        self,
+        chan: Option<futures::channel::mpsc::Sender<DownloadFlashingStatus>>,
+    ) -> anyhow::Result<()> {
+        let customization = self.customization.customization;
+        let dst = self.dst.into_boxed_path();
+
+        if let Some(mut chan) = chan {
+            let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+
+            let t = tokio::spawn(async move {
+                while let Some(x) = rx.recv().await {
+                    let _ = chan.try_send(x.into());
+                }
+            });
+
+            let resp =
+                bb_flasher_sd::export(self.img, dst, Some(tx), customization, self.cancel).await;
+
+            t.abort();
+
+            resp
+        } else {
+            bb_flasher_sd::export(self.img, dst, None, customization, self.cancel).await
+        }
+        .map_err(Into::into)
     }
 }
 
@@ -125,13 +656,20 @@ impl BBFlasher for FormatFlasher {
 /// # Supported Images
 ///
 /// - img: Raw images
+/// - wic: Raw images produced by Yocto
 /// - xz: Xz compressed raw images
+/// - gz: Gzip compressed raw images
+/// - zst: Zstd compressed raw images
+///
+/// Only the blocks mapped in bmap are written when it is provided. See [`find_bmap`] for local
+/// images.
 #[derive(Debug, Clone)]
 pub struct Flasher<I: Resolvable, B: Resolvable> {
     img: I,
     bmap: Option<B>,
     dst: PathBuf,
     customization: FlashingSdLinuxConfig,
+    write_limit: Option<NonZeroU64>,
     cancel: Option<tokio_util::sync::CancellationToken>,
 }
 
@@ -152,9 +690,17 @@ where
             bmap,
             dst: dst.0.path,
             customization,
+            write_limit: None,
             cancel,
         }
     }
+
+    /// Limit average write speed to `limit` bytes per second. Useful for card readers and USB hubs
+    /// that drop out when written to at full speed.
+    pub const fn write_limit(mut self, limit: Option<NonZeroU64>) -> Self {
+        self.write_limit = limit;
+        self
+    }
 }
 
 impl<I, B> BBFlasher for Flasher<I, B>
@@ -162,10 +708,16 @@ where
     I: Resolvable<ResolvedType = (crate::OsImage, u64)> + Send + 'static,
     B: Resolvable<ResolvedType = Box<str>> + Send + 'static,
 {
+    #[tracing::instrument(
+        name = "sd_flash",
+        skip_all,
+        fields(dst = %self.dst.display(), bmap = self.bmap.is_some())
+    )]
     async fn flash(
         self,
         chan: Option<futures::channel::mpsc::Sender<DownloadFlashingStatus>>,
     ) -> anyhow::Result<()> {
+        let verify = self.customization.verify;
         let customization = self.customization.customization;
         let dst = self.dst;
 
@@ -176,11 +728,7 @@ where
                 // Should run until tx is dropped, i.e. flasher task is done.
                 // If it is aborted, then cancel should be dropped, thereby signaling the flasher task to abort
                 while let Some(x) = rx.recv().await {
-                    let _ = chan.try_send(if x == 0.0 {
-                        DownloadFlashingStatus::Preparing
-                    } else {
-                        DownloadFlashingStatus::FlashingProgress(x)
-                    });
+                    let _ = chan.try_send(x.into());
                 }
             });
 
@@ -190,6 +738,8 @@ where
                 dst.into(),
                 Some(tx),
                 customization,
+                self.write_limit,
+                verify,
                 self.cancel,
             )
             .await;
@@ -204,6 +754,8 @@ where
                 dst.into(),
                 None,
                 customization,
+                self.write_limit,
+                verify,
                 self.cancel,
             )
             .await
@@ -211,3 +763,48 @@ where
         .map_err(Into::into)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::devices::Fake;
+
+    use super::*;
+
+    fn drive(path: &str, removable: bool, is_virtual: bool) -> Drive {
+        Drive {
+            name: path.to_string(),
+            path: PathBuf::from(path),
+            size: 1024,
+            read_only: false,
+            removable,
+            is_virtual,
+            location: None,
+            reader: None,
+            card: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn destinations() {
+        let fake = Fake::from_iter([
+            drive("/dev/sda", false, false),
+            drive("/dev/sdb", true, false),
+            drive("/dev/loop0", true, true),
+        ]);
+
+        let paths = |dsts: std::collections::HashSet<Target>| {
+            let mut paths: Vec<_> = dsts.into_iter().map(|x| x.0.path).collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(
+            paths(Target::destinations_from(&fake, true).await),
+            [PathBuf::from("/dev/sdb")]
+        );
+        assert_eq!(
+            paths(Target::destinations_from(&fake, false).await),
+            ["/dev/loop0", "/dev/sda", "/dev/sdb"].map(PathBuf::from)
+        );
+    }
+}
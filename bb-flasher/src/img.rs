@@ -1,21 +1,185 @@
 //! Module to handle extraction of compressed firmware, auto detection of type of extraction, etc
 
-use bb_helper::file_stream::ReaderFileStream;
+use bb_helper::file_stream::{ReaderFileStream, WriterFileStream};
+use futures::channel::mpsc;
 use rc_zip_sync::{ReadZip, ReadZipStreaming};
 use std::{
-    io::{Read, Seek, SeekFrom},
-    path::Path,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 
+use crate::DownloadFlashingStatus;
+
+/// Errors from the image layer
+#[derive(thiserror::Error, Debug)]
+pub enum ImageError {
+    /// Not enough free space in a directory.
+    #[error(
+        "Not enough space in {}: {required} bytes required, {available} bytes available.",
+        dir.display()
+    )]
+    InsufficientSpace {
+        dir: PathBuf,
+        required: u64,
+        available: u64,
+    },
+}
+
+/// Where and how to store large intermediate data, like images being streamed while downloading.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScratchSpace {
+    /// Directory for scratch files. Defaults to the system temporary directory, which might be
+    /// small or backed by RAM.
+    pub dir: Option<PathBuf>,
+    /// Reserve the complete size upfront. Only supported on Linux.
+    pub preallocate: bool,
+}
+
+impl ScratchSpace {
+    pub fn dir(&self) -> PathBuf {
+        self.dir.clone().unwrap_or_else(std::env::temp_dir)
+    }
+
+    /// Check that `size` bytes can be stored in the scratch directory. Always succeeds on
+    /// platforms where free space cannot be queried.
+    pub fn check(&self, size: u64) -> Result<(), ImageError> {
+        check_free_space(&self.dir(), size)
+    }
+
+    /// Create a file stream backed by a file in the scratch directory. If `size` is known, space
+    /// is checked (and reserved) before returning.
+    pub fn file_stream(
+        &self,
+        size: Option<u64>,
+    ) -> std::io::Result<(WriterFileStream, ReaderFileStream)> {
+        if let Some(x) = size {
+            self.check(x)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::StorageFull, e))?;
+        }
+
+        let (writer, reader) = bb_helper::file_stream::file_stream_in(&self.dir())?;
+
+        if let Some(x) = size
+            && self.preallocate
+        {
+            writer.reserve(x)?;
+        }
+
+        Ok((writer, reader))
+    }
+}
+
+/// Check that `size` bytes can be stored in `dir`. Always succeeds on platforms where free space
+/// cannot be queried.
+pub fn check_free_space(dir: &Path, size: u64) -> Result<(), ImageError> {
+    match free_space(dir) {
+        Some(available) if available < size => Err(ImageError::InsufficientSpace {
+            dir: dir.to_path_buf(),
+            required: size,
+            available,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    #[allow(clippy::useless_conversion)]
+    Some(u64::from(stat.f_bavail) * u64::from(stat.f_frsize))
+}
+
+#[cfg(not(unix))]
+fn free_space(_: &Path) -> Option<u64> {
+    None
+}
+
 pub struct OsImage {
     size: u64,
+    /// gzip and some zstd images do not record the exact uncompressed size. `size` is only an
+    /// estimate for such images.
+    exact_size: bool,
     img: OsImageReader,
+    compressed: Option<CompressedProgress>,
+}
+
+/// Tracks how much of a compressed image has been consumed by the decoder.
+struct CompressedProgress {
+    consumed: Arc<AtomicU64>,
+    size: u64,
+    chan: Option<mpsc::Sender<DownloadFlashingStatus>>,
+    last: f32,
+}
+
+impl CompressedProgress {
+    /// Minimum change in progress before sending an update.
+    const STEP: f32 = 0.01;
+
+    fn new(file: std::fs::File) -> std::io::Result<(CountingReader<std::fs::File>, Self)> {
+        let size = file.metadata()?.len();
+        let consumed = Arc::new(AtomicU64::new(0));
+
+        Ok((
+            CountingReader {
+                inner: file,
+                count: consumed.clone(),
+            },
+            Self {
+                consumed,
+                size,
+                chan: None,
+                last: 0.0,
+            },
+        ))
+    }
+
+    fn update(&mut self) {
+        let Some(chan) = self.chan.as_mut() else {
+            return;
+        };
+
+        let p = self.consumed.load(Ordering::Relaxed) as f32 / self.size as f32;
+        if p - self.last >= Self::STEP {
+            self.last = p;
+            let _ = chan.try_send(DownloadFlashingStatus::DecompressingProgress(p.min(1.0)));
+        }
+    }
+}
+
+/// Counts bytes read from the underlying reader.
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
 }
 
 pub(crate) enum OsImageReader {
-    Xz(liblzma::read::XzDecoder<std::fs::File>),
+    Xz(liblzma::read::XzDecoder<CountingReader<std::fs::File>>),
+    Gz(flate2::read::MultiGzDecoder<CountingReader<std::fs::File>>),
+    Zstd(zstd::stream::read::Decoder<'static, std::io::BufReader<CountingReader<std::fs::File>>>),
     Zip(rc_zip_sync::StreamingEntryReader<std::fs::File>),
     XzPiped(liblzma::read::XzDecoder<ReaderFileStream>),
+    GzPiped(flate2::read::MultiGzDecoder<ReaderFileStream>),
+    ZstdPiped(zstd::stream::read::Decoder<'static, std::io::BufReader<ReaderFileStream>>),
     ZipPiped(rc_zip_sync::StreamingEntryReader<ReaderFileStream>),
     Uncompressed(std::io::BufReader<std::fs::File>),
     UncompressedPiped(std::io::BufReader<ReaderFileStream>),
@@ -35,11 +199,35 @@ impl OsImage {
                 let size = liblzma::uncompressed_size(&mut file)?;
 
                 file.seek(std::io::SeekFrom::Start(0))?;
-                let img = liblzma::read::XzDecoder::new_parallel(file);
+                let (reader, compressed) = CompressedProgress::new(file)?;
+
+                Ok(Self {
+                    size,
+                    exact_size: true,
+                    img: OsImageReader::Xz(liblzma::read::XzDecoder::new_parallel(reader)),
+                    compressed: Some(compressed),
+                })
+            }
+            [0x1f, 0x8b, _, _, _, _] => {
+                let size = gz_uncompressed_size(&mut file)?;
+                let (reader, compressed) = CompressedProgress::new(file)?;
 
                 Ok(Self {
                     size,
-                    img: OsImageReader::Xz(img),
+                    exact_size: false,
+                    img: OsImageReader::Gz(flate2::read::MultiGzDecoder::new(reader)),
+                    compressed: Some(compressed),
+                })
+            }
+            [0x28, 0xb5, 0x2f, 0xfd, _, _] => {
+                let size = zstd_uncompressed_size(&mut file)?;
+                let (reader, compressed) = CompressedProgress::new(file)?;
+
+                Ok(Self {
+                    size: size.unwrap_or(compressed.size),
+                    exact_size: size.is_some(),
+                    img: OsImageReader::Zstd(zstd::stream::read::Decoder::new(reader)?),
+                    compressed: Some(compressed),
                 })
             }
             [0x50, 0x4b, 0x03, 0x04, _, _] => {
@@ -54,7 +242,9 @@ impl OsImage {
 
                 Ok(Self {
                     size: img.entry().uncompressed_size,
+                    exact_size: true,
                     img: OsImageReader::Zip(img),
+                    compressed: None,
                 })
             }
             _ => {
@@ -62,7 +252,9 @@ impl OsImage {
 
                 Ok(Self {
                     size,
+                    exact_size: true,
                     img: OsImageReader::Uncompressed(std::io::BufReader::new(file)),
+                    compressed: None,
                 })
             }
         }
@@ -73,42 +265,215 @@ impl OsImage {
         img.read_exact(&mut magic)?;
         img.seek(SeekFrom::Start(0))?;
 
-        match magic {
-            [0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00] => Ok(Self {
-                size,
-                img: OsImageReader::XzPiped(liblzma::read::XzDecoder::new_parallel(img)),
-            }),
-            [0x50, 0x4b, 0x03, 0x04, _, _] => Ok(Self {
-                size,
-                img: OsImageReader::ZipPiped(
-                    img.stream_zip_entries_throwing_caution_to_the_wind()?,
-                ),
-            }),
-            _ => Ok(Self {
-                size,
-                img: OsImageReader::UncompressedPiped(std::io::BufReader::new(img)),
-            }),
-        }
+        let img = match magic {
+            [0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00] => {
+                OsImageReader::XzPiped(liblzma::read::XzDecoder::new_parallel(img))
+            }
+            [0x1f, 0x8b, _, _, _, _] => {
+                OsImageReader::GzPiped(flate2::read::MultiGzDecoder::new(img))
+            }
+            [0x28, 0xb5, 0x2f, 0xfd, _, _] => {
+                OsImageReader::ZstdPiped(zstd::stream::read::Decoder::new(img)?)
+            }
+            [0x50, 0x4b, 0x03, 0x04, _, _] => {
+                OsImageReader::ZipPiped(img.stream_zip_entries_throwing_caution_to_the_wind()?)
+            }
+            _ => OsImageReader::UncompressedPiped(std::io::BufReader::new(img)),
+        };
+
+        Ok(Self {
+            size,
+            exact_size: true,
+            img,
+            compressed: None,
+        })
     }
 
     pub(crate) const fn size(&self) -> u64 {
         self.size
     }
+
+    /// Report [`DownloadFlashingStatus::DecompressingProgress`], based on compressed bytes
+    /// consumed, while reading. Only meant for flashers that decompress the whole image before
+    /// flashing. Does nothing for uncompressed images.
+    pub(crate) fn with_decompression_progress(
+        mut self,
+        chan: Option<mpsc::Sender<DownloadFlashingStatus>>,
+    ) -> Self {
+        if let Some(x) = self.compressed.as_mut() {
+            x.chan = chan;
+        }
+        self
+    }
 }
 
 impl std::io::Read for OsImage {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        match &mut self.img {
+        let n = match &mut self.img {
             OsImageReader::Xz(x) => x.read(buf),
+            OsImageReader::Gz(x) => x.read(buf),
+            OsImageReader::Zstd(x) => x.read(buf),
             OsImageReader::Uncompressed(x) => x.read(buf),
             OsImageReader::XzPiped(x) => x.read(buf),
+            OsImageReader::GzPiped(x) => x.read(buf),
+            OsImageReader::ZstdPiped(x) => x.read(buf),
             OsImageReader::UncompressedPiped(x) => x.read(buf),
             OsImageReader::ZipPiped(x) => x.read(buf),
             OsImageReader::Zip(x) => x.read(buf),
+        }?;
+
+        if let Some(x) = self.compressed.as_mut() {
+            x.update();
         }
+
+        Ok(n)
     }
 }
 
+/// gzip only records the uncompressed size modulo 4 GiB, so this is an estimate for large images.
+fn gz_uncompressed_size(file: &mut std::fs::File) -> std::io::Result<u64> {
+    let mut isize = [0u8; 4];
+
+    file.seek(SeekFrom::End(-4))?;
+    file.read_exact(&mut isize)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(u64::from(u32::from_le_bytes(isize)))
+}
+
+/// Uncompressed size from the zstd frame header. Streaming encoders can omit it.
+fn zstd_uncompressed_size(file: &mut std::fs::File) -> std::io::Result<Option<u64>> {
+    // Maximum size of a zstd frame header
+    let mut header = Vec::with_capacity(18);
+
+    file.by_ref().take(18).read_to_end(&mut header)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(zstd::zstd_safe::get_frame_content_size(&header)
+        .ok()
+        .flatten())
+}
+
+/// Formats an image can be converted to using [`convert`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    #[default]
+    Raw,
+    Xz,
+    Zstd,
+    Gz,
+}
+
+impl ImageFormat {
+    /// Detect format from file extension. Unknown extensions are treated as raw images.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("xz") => Self::Xz,
+            Some("zst") => Self::Zstd,
+            Some("gz") => Self::Gz,
+            _ => Self::Raw,
+        }
+    }
+}
+
+enum ImageWriter<W: Write> {
+    Raw(W),
+    Xz(liblzma::write::XzEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    Gz(flate2::write::GzEncoder<W>),
+}
+
+impl<W: Write> ImageWriter<W> {
+    fn new(w: W, format: ImageFormat, size: Option<u64>) -> std::io::Result<Self> {
+        match format {
+            ImageFormat::Raw => Ok(Self::Raw(w)),
+            ImageFormat::Xz => Ok(Self::Xz(liblzma::write::XzEncoder::new_parallel(w, 6))),
+            ImageFormat::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(w, 0)?;
+                // Allows reading uncompressed size from the frame header
+                encoder.set_pledged_src_size(size)?;
+                Ok(Self::Zstd(encoder))
+            }
+            ImageFormat::Gz => Ok(Self::Gz(flate2::write::GzEncoder::new(
+                w,
+                flate2::Compression::default(),
+            ))),
+        }
+    }
+
+    fn finish(self) -> std::io::Result<W> {
+        match self {
+            Self::Raw(x) => Ok(x),
+            Self::Xz(x) => x.finish(),
+            Self::Zstd(x) => x.finish(),
+            Self::Gz(x) => x.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for ImageWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Raw(x) => x.write(buf),
+            Self::Xz(x) => x.write(buf),
+            Self::Zstd(x) => x.write(buf),
+            Self::Gz(x) => x.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Raw(x) => x.flush(),
+            Self::Xz(x) => x.flush(),
+            Self::Zstd(x) => x.flush(),
+            Self::Gz(x) => x.flush(),
+        }
+    }
+}
+
+/// Convert a local image to `format`. The source can be in any format supported by [`OsImage`].
+/// `progress` is called with the uncompressed bytes processed and the (estimated) uncompressed
+/// size.
+///
+/// This is a blocking operation.
+pub fn convert(
+    src: &Path,
+    dst: &Path,
+    format: ImageFormat,
+    mut progress: impl FnMut(u64, u64),
+) -> std::io::Result<()> {
+    const BUF_SIZE: usize = 1024 * 1024;
+
+    let mut img = OsImage::from_path(src)?;
+    let size = img.size();
+
+    let file = std::fs::File::create(dst)?;
+    let mut writer = ImageWriter::new(
+        std::io::BufWriter::new(file),
+        format,
+        img.exact_size.then_some(size),
+    )?;
+
+    let mut buf = vec![0u8; BUF_SIZE];
+    let mut pos = 0;
+    loop {
+        let n = img.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+
+        writer.write_all(&buf[..n])?;
+        pos += n as u64;
+        progress(pos, size.max(pos));
+    }
+
+    writer
+        .finish()?
+        .into_inner()
+        .map_err(std::io::IntoInnerError::into_error)?
+        .sync_all()
+}
+
 #[cfg(unix)]
 fn size(file: &std::fs::Metadata) -> u64 {
     use std::os::unix::fs::MetadataExt;
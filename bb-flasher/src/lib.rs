@@ -24,11 +24,25 @@
 //! }
 //! ```
 //!
+//! Multiple flashers can be run together using [`JobQueue`], which limits the number of jobs
+//! running at the same time and supports cancelling individual jobs.
+//!
+//! Any flasher can be wrapped in [`Timed`] to fail once a stage exceeds its [`Timeouts`]. Each
+//! flasher module provides sensible `DEFAULT_TIMEOUTS`.
+//!
+//! Destinations are selected from the devices listed by a [`devices::DeviceEnumerator`].
+//! [`devices::watch`] can be used to get notified when destinations are connected or removed.
+//!
+//! With the `boot_test` feature, any flasher can also be wrapped in [`BootTest`] to check that the
+//! board boots by watching its serial console after flashing.
+//!
 //! # Features
 //!
 //! - `sd`: Provide flashing Linux images to SD Cards. Enabled by **default**.
 //! - `sd_linux_udev`: Uses udev to provide GUI prompt to open SD Cards in Linux. Useful for GUI
 //!   applications.
+//! - `sd_linux_io_uring`: Uses io_uring for faster writes to SD Cards in Linux. Falls back to
+//!   synchronous writes on older kernels.
 //! - `sd_macos_authopen`: Uses authopen to provide GUI prompt to open SD Cards in MacOS. Useful
 //!   for GUI applications.
 //! - `bcf`: Provde support for flashing the main processor (CC1352P7) in BeagleConnect Freedom.
@@ -36,17 +50,35 @@
 //!   USB to UART bridge.
 //! - `pb2_mspm0`: Provides support to flash PocketBeagle 2 MSPM0. Needs root permissions.
 //! - `pb2_mspm0_dbus`: Use bb-imager-serivce to flash PocketBeagle 2 as a normal user.
+//! - `test-util`: Provide [`sd::Target::file`] to flash regular files or loop devices. Useful for
+//!   testing the complete flashing pipeline without an SD Card.
+//! - `demo`: Provide [`mock::Flasher`], which simulates flashing without any hardware.
+//! - `boot_test`: Provide [`BootTest`], which verifies that the board boots over serial after
+//!   flashing.
 
+#[cfg(feature = "boot_test")]
+mod boot_test;
 mod common;
+pub mod devices;
+mod failure;
 mod flasher;
 mod img;
+mod queue;
+mod timeout;
+mod units;
 
 use std::path::Path;
 
 pub use bb_helper::resolvable::Resolvable;
+#[cfg(feature = "boot_test")]
+pub use boot_test::{BootTest, BootTestError};
 pub use common::*;
+pub use failure::FailureKind;
 pub use flasher::*;
-pub use img::OsImage;
+pub use img::{ImageError, ImageFormat, OsImage, ScratchSpace, check_free_space, convert};
+pub use queue::{JobHandle, JobQueue, QueueError, QueueEvent};
+pub use timeout::{FlashingStage, Timed, TimeoutError, Timeouts};
+pub use units::{ByteSize, TransferRate, UnitSystem};
 
 /// An Os Image present in the local filesystem
 #[derive(Debug, Clone)]
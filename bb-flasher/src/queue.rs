@@ -0,0 +1,332 @@
+//! Queue to flash multiple images with a concurrency limit.
+
+use std::{collections::HashMap, num::NonZeroUsize};
+
+use futures::{SinkExt, StreamExt, channel::mpsc, future::Either, stream::FuturesUnordered};
+use tokio_util::sync::CancellationToken;
+
+use crate::{BBFlasher, JobId, ProgressEvent};
+
+/// Errors from [`JobQueue`]
+#[derive(thiserror::Error, Debug)]
+pub enum QueueError {
+    /// Job was cancelled using [`JobHandle::cancel`].
+    #[error("Job was cancelled")]
+    Cancelled,
+}
+
+/// Events emitted while running a [`JobQueue`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueEvent {
+    Started(JobId),
+    /// Progress of a running job
+    Progress(ProgressEvent),
+    /// Job has finished. Contains the error message if it failed.
+    Finished {
+        job: JobId,
+        result: Result<(), String>,
+    },
+    /// Progress of the whole queue between 0 and 1. Finished jobs count as complete.
+    Aggregate(f32),
+}
+
+/// Handle to a job in a [`JobQueue`]. Can be used to cancel the job, even while the queue is
+/// running.
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    id: JobId,
+    cancel: CancellationToken,
+}
+
+impl JobHandle {
+    pub const fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Cancel the job. Pending jobs are never started, while running jobs are dropped.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+struct QueuedJob<F> {
+    handle: JobHandle,
+    priority: i32,
+    flasher: F,
+}
+
+/// Flash multiple jobs with a concurrency limit.
+///
+/// Jobs with higher priority are started first. Jobs with equal priority are started in the order
+/// they were added.
+///
+/// ```no_run
+/// # type Flasher = bb_flasher::sd::Flasher<bb_flasher::LocalImage, bb_helper::resolvable::LocalStringFile>;
+/// # async fn run(a: Flasher, b: Flasher) {
+/// let mut queue = bb_flasher::JobQueue::new(std::num::NonZeroUsize::new(2).unwrap());
+/// queue.push(a, 0);
+/// let urgent = queue.push(b, 10);
+/// println!("Queued {} before others", urgent.id());
+///
+/// for (job, res) in queue.run(None).await {
+///     println!("{job}: {res:?}");
+/// }
+/// # }
+/// ```
+pub struct JobQueue<F> {
+    concurrency: NonZeroUsize,
+    jobs: Vec<QueuedJob<F>>,
+}
+
+impl<F: BBFlasher> JobQueue<F> {
+    /// Create a queue running at most `concurrency` jobs at the same time.
+    pub const fn new(concurrency: NonZeroUsize) -> Self {
+        Self {
+            concurrency,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Add a job to the queue.
+    pub fn push(&mut self, flasher: F, priority: i32) -> JobHandle {
+        let handle = JobHandle {
+            id: JobId::new(),
+            cancel: CancellationToken::new(),
+        };
+
+        self.jobs.push(QueuedJob {
+            handle: handle.clone(),
+            priority,
+            flasher,
+        });
+
+        handle
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Run all jobs. Failure of a job does not affect other jobs. Returns the result of each job
+    /// in the order they finished.
+    pub async fn run(
+        mut self,
+        mut chan: Option<mpsc::Sender<QueueEvent>>,
+    ) -> Vec<(JobId, anyhow::Result<()>)> {
+        // Stable sort keeps the submission order for equal priority
+        self.jobs.sort_by_key(|x| std::cmp::Reverse(x.priority));
+
+        let total = self.jobs.len();
+        let mut pending = self.jobs.into_iter();
+        let mut running = FuturesUnordered::new();
+        let mut progress: HashMap<JobId, f32> = HashMap::with_capacity(total);
+        let mut results = Vec::with_capacity(total);
+
+        let (tx, mut rx) = mpsc::channel(20);
+
+        loop {
+            while running.len() < self.concurrency.get()
+                && let Some(job) = pending.next()
+            {
+                let id = job.handle.id;
+
+                if job.handle.cancel.is_cancelled() {
+                    progress.insert(id, 1.0);
+                    send(&mut chan, finished(id, &Err(QueueError::Cancelled.into()))).await;
+                    results.push((id, Err(QueueError::Cancelled.into())));
+                    continue;
+                }
+
+                send(&mut chan, QueueEvent::Started(id)).await;
+                running.push(run_job(job, tx.clone()));
+            }
+
+            if running.is_empty() {
+                break;
+            }
+
+            match futures::future::select(rx.next(), running.next()).await {
+                Either::Left((Some(event), _)) => {
+                    progress.insert(event.job, event.status.progress());
+                    send(&mut chan, QueueEvent::Progress(event)).await;
+                }
+                Either::Right((Some((id, res)), _)) => {
+                    progress.insert(id, 1.0);
+                    send(&mut chan, finished(id, &res)).await;
+                    results.push((id, res));
+                }
+                // Sender is held by the queue and running is not empty
+                _ => unreachable!(),
+            }
+
+            let done: f32 = progress.values().sum();
+            send(&mut chan, QueueEvent::Aggregate(done / total as f32)).await;
+        }
+
+        results
+    }
+}
+
+async fn run_job<F: BBFlasher>(
+    job: QueuedJob<F>,
+    chan: mpsc::Sender<ProgressEvent>,
+) -> (JobId, anyhow::Result<()>) {
+    let id = job.handle.id;
+    let flash = std::pin::pin!(job.flasher.flash_job(id, Some(chan)));
+    let cancelled = std::pin::pin!(job.handle.cancel.cancelled());
+
+    match futures::future::select(flash, cancelled).await {
+        Either::Left((res, _)) => (id, res),
+        Either::Right(_) => (id, Err(QueueError::Cancelled.into())),
+    }
+}
+
+fn finished(job: JobId, res: &anyhow::Result<()>) -> QueueEvent {
+    QueueEvent::Finished {
+        job,
+        result: res.as_ref().map(|_| ()).map_err(|e| format!("{e:#}")),
+    }
+}
+
+async fn send(chan: &mut Option<mpsc::Sender<QueueEvent>>, event: QueueEvent) {
+    if let Some(chan) = chan {
+        let _ = chan.send(event).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroUsize, time::Duration};
+
+    use futures::{StreamExt, channel::mpsc};
+
+    use super::{JobQueue, QueueError, QueueEvent};
+    use crate::{JobId, mock};
+
+    fn flasher(stage_duration: Duration) -> mock::Flasher {
+        mock::Flasher::new(stage_duration, None, None)
+    }
+
+    fn queue(concurrency: usize) -> JobQueue<mock::Flasher> {
+        JobQueue::new(NonZeroUsize::new(concurrency).unwrap())
+    }
+
+    async fn run(
+        queue: JobQueue<mock::Flasher>,
+    ) -> (Vec<(JobId, anyhow::Result<()>)>, Vec<QueueEvent>) {
+        let (tx, rx) = mpsc::channel(20);
+        futures::join!(queue.run(Some(tx)), rx.collect::<Vec<_>>())
+    }
+
+    fn started(events: &[QueueEvent]) -> Vec<JobId> {
+        events
+            .iter()
+            .filter_map(|x| match x {
+                QueueEvent::Started(id) => Some(*id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit() {
+        let mut q = queue(2);
+        for _ in 0..4 {
+            q.push(flasher(Duration::ZERO), 0);
+        }
+
+        let (results, events) = run(q).await;
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|(_, x)| x.is_ok()));
+
+        let mut running = 0;
+        let mut max = 0;
+        for e in &events {
+            match e {
+                QueueEvent::Started(_) => running += 1,
+                QueueEvent::Finished { .. } => running -= 1,
+                _ => {}
+            }
+            max = max.max(running);
+        }
+        assert_eq!(max, 2);
+        assert_eq!(running, 0);
+    }
+
+    #[tokio::test]
+    async fn priority() {
+        let mut q = queue(1);
+        let low = q.push(flasher(Duration::ZERO), 0);
+        let high = q.push(flasher(Duration::ZERO), 10);
+        let low2 = q.push(flasher(Duration::ZERO), 0);
+
+        let (results, events) = run(q).await;
+        let order = vec![high.id(), low.id(), low2.id()];
+        assert_eq!(started(&events), order);
+        assert_eq!(results.iter().map(|(x, _)| *x).collect::<Vec<_>>(), order);
+    }
+
+    #[tokio::test]
+    async fn cancel_job() {
+        let mut q = queue(1);
+        let running = q.push(flasher(Duration::from_secs(60)), 10);
+        let pending = q.push(flasher(Duration::from_secs(60)), 0);
+        let other = q.push(flasher(Duration::ZERO), 0);
+
+        pending.cancel();
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            running.cancel();
+        };
+        let ((results, events), _) = futures::join!(run(q), cancel);
+
+        let cancelled = |id| {
+            results.iter().any(|(x, res)| {
+                *x == id
+                    && res
+                        .as_ref()
+                        .is_err_and(|e| matches!(e.downcast_ref(), Some(QueueError::Cancelled)))
+            })
+        };
+        assert!(cancelled(running.id()));
+        assert!(cancelled(pending.id()));
+        assert!(
+            results
+                .iter()
+                .any(|(x, res)| *x == other.id() && res.is_ok())
+        );
+
+        // Cancelled jobs are never started
+        assert_eq!(started(&events), vec![running.id(), other.id()]);
+    }
+
+    #[tokio::test]
+    async fn aggregate_progress() {
+        let mut q = queue(1);
+        q.push(flasher(Duration::ZERO), 0);
+        q.push(flasher(Duration::ZERO), 0);
+
+        let (_, events) = run(q).await;
+        let aggregate: Vec<f32> = events
+            .iter()
+            .filter_map(|x| match x {
+                QueueEvent::Aggregate(x) => Some(*x),
+                _ => None,
+            })
+            .collect();
+
+        assert!(aggregate.iter().all(|x| (0.0..=1.0).contains(x)));
+        assert_eq!(aggregate.last(), Some(&1.0));
+
+        // First job counts as complete once finished
+        let first = events
+            .iter()
+            .position(|x| matches!(x, QueueEvent::Finished { .. }))
+            .unwrap();
+        assert!(matches!(events[first + 1], QueueEvent::Aggregate(x) if x >= 0.5));
+    }
+}
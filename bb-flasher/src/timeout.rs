@@ -0,0 +1,350 @@
+//! Limit the time spent in each stage of flashing. Slow SD Cards or large firmware can take a long
+//! time, but a stage running far longer than expected usually means the device is hanging.
+
+use std::{fmt::Display, time::Duration};
+
+use futures::{StreamExt, channel::mpsc, future::Either};
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::{BBFlasher, DownloadFlashingStatus};
+
+/// Stage of flashing with a separate time budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlashingStage {
+    /// Downloading and extracting the image.
+    Download,
+    /// Writing to the destination.
+    Flash,
+    /// Verifying the written image.
+    Verify,
+    /// Post install customization.
+    Customize,
+}
+
+impl FlashingStage {
+    const fn from_status(status: &DownloadFlashingStatus) -> Option<Self> {
+        match status {
+            // Boot testing has a separate timeout
+            DownloadFlashingStatus::Preparing
+            | DownloadFlashingStatus::CheckingImage
+            | DownloadFlashingStatus::BootTesting
+            | DownloadFlashingStatus::BootVerified
+            | DownloadFlashingStatus::BootFailed => None,
+            DownloadFlashingStatus::DownloadingProgress(_)
+            | DownloadFlashingStatus::DecompressingProgress(_) => Some(Self::Download),
+            DownloadFlashingStatus::FlashingProgress(_) => Some(Self::Flash),
+            DownloadFlashingStatus::Verifying => Some(Self::Verify),
+            DownloadFlashingStatus::Customizing(_) => Some(Self::Customize),
+        }
+    }
+}
+
+impl Display for FlashingStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Download => write!(f, "Downloading"),
+            Self::Flash => write!(f, "Flashing"),
+            Self::Verify => write!(f, "Verifying"),
+            Self::Customize => write!(f, "Customizing"),
+        }
+    }
+}
+
+/// Maximum time allowed for each [`FlashingStage`]. [`None`] disables the timeout for a stage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Timeouts {
+    pub download: Option<Duration>,
+    pub flash: Option<Duration>,
+    pub verify: Option<Duration>,
+    pub customize: Option<Duration>,
+}
+
+impl Timeouts {
+    /// No timeouts for any stage.
+    pub const NONE: Self = Self {
+        download: None,
+        flash: None,
+        verify: None,
+        customize: None,
+    };
+
+    pub const fn get(&self, stage: FlashingStage) -> Option<Duration> {
+        match stage {
+            FlashingStage::Download => self.download,
+            FlashingStage::Flash => self.flash,
+            FlashingStage::Verify => self.verify,
+            FlashingStage::Customize => self.customize,
+        }
+    }
+
+    /// Use timeouts from `defaults` for stages without one.
+    pub const fn or(self, defaults: Self) -> Self {
+        const fn or(x: Option<Duration>, y: Option<Duration>) -> Option<Duration> {
+            match x {
+                Some(_) => x,
+                None => y,
+            }
+        }
+
+        Self {
+            download: or(self.download, defaults.download),
+            flash: or(self.flash, defaults.flash),
+            verify: or(self.verify, defaults.verify),
+            customize: or(self.customize, defaults.customize),
+        }
+    }
+}
+
+/// A stage did not finish in time.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("{stage} did not finish within {}. The device might not be responding.", pretty(*.limit))]
+pub struct TimeoutError {
+    pub stage: FlashingStage,
+    pub limit: Duration,
+}
+
+fn pretty(d: Duration) -> String {
+    let secs = d.as_secs();
+    match (secs / 60, secs % 60) {
+        (0, s) => format!("{s} seconds"),
+        (m, 0) => format!("{m} minutes"),
+        (m, s) => format!("{m} minutes {s} seconds"),
+    }
+}
+
+/// Wraps a flasher to fail with [`TimeoutError`] once a stage exceeds its [`Timeouts`].
+///
+/// Stages are tracked using the [`DownloadFlashingStatus`] reported by the inner flasher.
+pub struct Timed<F> {
+    inner: F,
+    timeouts: Timeouts,
+    cancel: Option<tokio_util::sync::CancellationToken>,
+}
+
+impl<F> Timed<F> {
+    pub const fn new(inner: F, timeouts: Timeouts) -> Self {
+        Self {
+            inner,
+            timeouts,
+            cancel: None,
+        }
+    }
+
+    /// Token to cancel on timeout. Should be the token given to the inner flasher, so that any
+    /// blocking work is stopped as well.
+    pub fn cancel(mut self, cancel: tokio_util::sync::CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+}
+
+impl<F: BBFlasher> BBFlasher for Timed<F> {
+    async fn flash(
+        self,
+        mut chan: Option<mpsc::Sender<DownloadFlashingStatus>>,
+    ) -> anyhow::Result<()> {
+        let (tx, mut rx) = mpsc::channel(20);
+
+        let res = {
+            let flash = std::pin::pin!(self.inner.flash(Some(tx)));
+            let watchdog = std::pin::pin!(watch(&mut rx, chan.as_mut(), self.timeouts));
+
+            match futures::future::select(flash, watchdog).await {
+                Either::Left((res, _)) => Ok(res),
+                Either::Right((Ok(()), flash)) => Ok(flash.await),
+                Either::Right((Err(e), _)) => Err(e),
+            }
+        };
+
+        match res {
+            Ok(res) => {
+                // Statuses sent just before the flasher finished are still queued
+                while let Ok(Some(status)) = rx.try_next() {
+                    if let Some(c) = chan.as_mut() {
+                        let _ = c.try_send(status);
+                    }
+                }
+
+                res
+            }
+            Err(e) => {
+                tracing::error!("{e}");
+                if let Some(cancel) = self.cancel {
+                    cancel.cancel();
+                }
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// Forward statuses to `chan` while tracking the current stage. Returns once the flasher has
+/// finished, or a stage exceeds its timeout.
+async fn watch(
+    rx: &mut mpsc::Receiver<DownloadFlashingStatus>,
+    mut chan: Option<&mut mpsc::Sender<DownloadFlashingStatus>>,
+    timeouts: Timeouts,
+) -> Result<(), TimeoutError> {
+    let mut current: Option<(FlashingStage, Instant)> = None;
+
+    loop {
+        let deadline =
+            current.and_then(|(stage, start)| timeouts.get(stage).map(|x| (stage, start, x)));
+
+        let status = match deadline {
+            Some((stage, start, limit)) => {
+                match tokio::time::timeout_at(start + limit, rx.next()).await {
+                    Ok(x) => x,
+                    Err(_) => return Err(TimeoutError { stage, limit }),
+                }
+            }
+            None => rx.next().await,
+        };
+
+        // Flasher has finished
+        let Some(status) = status else {
+            return Ok(());
+        };
+
+        let stage = FlashingStage::from_status(&status);
+        if stage != current.map(|(x, _)| x) {
+            current = stage.map(|x| (x, Instant::now()));
+        }
+
+        if let Some(c) = chan.as_mut() {
+            let _ = c.try_send(status);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{StreamExt, channel::mpsc};
+
+    use super::{FlashingStage, Timed, TimeoutError, Timeouts, pretty};
+    use crate::{BBFlasher, CustomizationStep, DownloadFlashingStatus};
+
+    /// Reports `statuses` and then either finishes or hangs forever.
+    struct Flasher {
+        statuses: Vec<DownloadFlashingStatus>,
+        stall: bool,
+    }
+
+    impl BBFlasher for Flasher {
+        async fn flash(
+            self,
+            mut chan: Option<mpsc::Sender<DownloadFlashingStatus>>,
+        ) -> anyhow::Result<()> {
+            for s in self.statuses {
+                if let Some(c) = chan.as_mut() {
+                    c.try_send(s).unwrap();
+                }
+            }
+
+            if self.stall {
+                std::future::pending::<()>().await;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn or() {
+        let timeouts = Timeouts {
+            flash: Some(Duration::from_secs(1)),
+            ..Timeouts::NONE
+        };
+        let defaults = Timeouts {
+            flash: Some(Duration::from_secs(5)),
+            verify: Some(Duration::from_secs(2)),
+            ..Timeouts::NONE
+        };
+
+        assert_eq!(
+            timeouts.or(defaults),
+            Timeouts {
+                flash: Some(Duration::from_secs(1)),
+                verify: Some(Duration::from_secs(2)),
+                ..Timeouts::NONE
+            }
+        );
+        assert_eq!(Timeouts::NONE.or(defaults), defaults);
+        assert_eq!(defaults.or(Timeouts::NONE), defaults);
+    }
+
+    #[test]
+    fn pretty_duration() {
+        assert_eq!(pretty(Duration::ZERO), "0 seconds");
+        assert_eq!(pretty(Duration::from_secs(59)), "59 seconds");
+        assert_eq!(pretty(Duration::from_secs(60)), "1 minutes");
+        assert_eq!(pretty(Duration::from_secs(150)), "2 minutes 30 seconds");
+
+        let e = TimeoutError {
+            stage: FlashingStage::Verify,
+            limit: Duration::from_secs(90),
+        };
+        assert_eq!(
+            e.to_string(),
+            "Verifying did not finish within 1 minutes 30 seconds. The device might not be responding."
+        );
+    }
+
+    #[tokio::test]
+    async fn stalled_stage() {
+        let limit = Duration::from_millis(50);
+        let cancel = tokio_util::sync::CancellationToken::new();
+        let flasher = Flasher {
+            statuses: vec![
+                DownloadFlashingStatus::DownloadingProgress(1.0),
+                DownloadFlashingStatus::FlashingProgress(0.5),
+            ],
+            stall: true,
+        };
+        let timeouts = Timeouts {
+            download: Some(limit),
+            flash: Some(limit),
+            ..Timeouts::NONE
+        };
+
+        let err = Timed::new(flasher, timeouts)
+            .cancel(cancel.clone())
+            .flash(None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<TimeoutError>(),
+            Some(&TimeoutError {
+                stage: FlashingStage::Flash,
+                limit
+            })
+        );
+        assert!(cancel.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn trailing_statuses() {
+        let statuses = vec![
+            DownloadFlashingStatus::FlashingProgress(1.0),
+            DownloadFlashingStatus::Customizing(CustomizationStep::Sysconf),
+            DownloadFlashingStatus::BootVerified,
+        ];
+        let flasher = Flasher {
+            statuses: statuses.clone(),
+            stall: false,
+        };
+        let timeouts = Timeouts {
+            flash: Some(Duration::from_secs(1)),
+            ..Timeouts::NONE
+        };
+        let (tx, rx) = mpsc::channel(20);
+
+        Timed::new(flasher, timeouts).flash(Some(tx)).await.unwrap();
+
+        assert_eq!(rx.collect::<Vec<_>>().await, statuses);
+    }
+}
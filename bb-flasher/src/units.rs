@@ -0,0 +1,245 @@
+//! Byte sizes and transfer rates with human readable formatting.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+static DECIMAL: AtomicBool = AtomicBool::new(false);
+
+/// Prefixes used to format [`ByteSize`] and [`TransferRate`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnitSystem {
+    /// Powers of 1024 (KiB, MiB, GiB). Matches the size reported by most operating systems.
+    #[default]
+    Binary,
+    /// Powers of 1000 (KB, MB, GB). Matches the size printed on SD Cards.
+    Decimal,
+}
+
+impl UnitSystem {
+    /// Unit system used by [`Display`](fmt::Display) implementations. Defaults to
+    /// [`UnitSystem::Binary`].
+    pub fn preferred() -> Self {
+        if DECIMAL.load(Ordering::Relaxed) {
+            Self::Decimal
+        } else {
+            Self::Binary
+        }
+    }
+
+    /// Set unit system used by [`Display`](fmt::Display) implementations for the whole
+    /// application.
+    pub fn set_preferred(self) {
+        DECIMAL.store(self == Self::Decimal, Ordering::Relaxed);
+    }
+
+    const fn base(self) -> f64 {
+        match self {
+            Self::Binary => 1024.0,
+            Self::Decimal => 1000.0,
+        }
+    }
+
+    const fn units(self) -> [&'static str; 7] {
+        match self {
+            Self::Binary => ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+            Self::Decimal => ["B", "KB", "MB", "GB", "TB", "PB", "EB"],
+        }
+    }
+
+    /// Precision defaults to 2 decimal places and can be overridden using the format string.
+    fn fmt(self, bytes: f64, suffix: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = self.units();
+        let mut size = bytes;
+        let mut unit = 0;
+
+        while size >= self.base() && unit < units.len() - 1 {
+            size /= self.base();
+            unit += 1;
+        }
+
+        if unit == 0 {
+            write!(f, "{size:.0} {}{suffix}", units[unit])
+        } else {
+            let precision = f.precision().unwrap_or(2);
+            write!(f, "{size:.precision$} {}{suffix}", units[unit])
+        }
+    }
+}
+
+/// Size in bytes. [`Display`](fmt::Display) uses the [preferred](UnitSystem::preferred) unit
+/// system.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    pub const fn new(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    pub const fn bytes(self) -> u64 {
+        self.0
+    }
+
+    /// Format using a specific unit system instead of the preferred one.
+    pub fn display(self, units: UnitSystem) -> impl fmt::Display {
+        Formatted {
+            value: self.0 as f64,
+            units,
+            suffix: "",
+        }
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(value: ByteSize) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        UnitSystem::preferred().fmt(self.0 as f64, "", f)
+    }
+}
+
+/// Transfer rate in bytes per second. [`Display`](fmt::Display) uses the
+/// [preferred](UnitSystem::preferred) unit system.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct TransferRate(f64);
+
+impl TransferRate {
+    /// Average rate of transferring `bytes` in `elapsed` time.
+    pub fn new(bytes: ByteSize, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        if secs == 0.0 {
+            Self(0.0)
+        } else {
+            Self(bytes.0 as f64 / secs)
+        }
+    }
+
+    pub const fn bytes_per_sec(self) -> f64 {
+        self.0
+    }
+
+    /// Format using a specific unit system instead of the preferred one.
+    pub fn display(self, units: UnitSystem) -> impl fmt::Display {
+        Formatted {
+            value: self.0,
+            units,
+            suffix: "/s",
+        }
+    }
+}
+
+impl fmt::Display for TransferRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        UnitSystem::preferred().fmt(self.0, "/s", f)
+    }
+}
+
+struct Formatted {
+    value: f64,
+    units: UnitSystem,
+    suffix: &'static str,
+}
+
+impl fmt::Display for Formatted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.units.fmt(self.value, self.suffix, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ByteSize, TransferRate, UnitSystem};
+
+    fn binary(x: u64) -> String {
+        ByteSize::new(x).display(UnitSystem::Binary).to_string()
+    }
+
+    fn decimal(x: u64) -> String {
+        ByteSize::new(x).display(UnitSystem::Decimal).to_string()
+    }
+
+    #[test]
+    fn byte_size_binary() {
+        assert_eq!(binary(0), "0 B");
+        assert_eq!(binary(1000), "1000 B");
+        assert_eq!(binary(1023), "1023 B");
+        assert_eq!(binary(1024), "1.00 KiB");
+        assert_eq!(binary(1536), "1.50 KiB");
+        assert_eq!(binary(1024 * 1024), "1.00 MiB");
+        assert_eq!(binary(1 << 30), "1.00 GiB");
+        assert_eq!(binary(16_000_000_000), "14.90 GiB");
+        assert_eq!(binary(u64::MAX), "16.00 EiB");
+    }
+
+    #[test]
+    fn byte_size_decimal() {
+        assert_eq!(decimal(0), "0 B");
+        assert_eq!(decimal(999), "999 B");
+        assert_eq!(decimal(1000), "1.00 KB");
+        assert_eq!(decimal(1024), "1.02 KB");
+        assert_eq!(decimal(1_000_000), "1.00 MB");
+        assert_eq!(decimal(1 << 30), "1.07 GB");
+        assert_eq!(decimal(16_000_000_000), "16.00 GB");
+    }
+
+    #[test]
+    fn precision() {
+        let size = ByteSize::new(1536);
+        assert_eq!(
+            format!("{:.1}", size.display(UnitSystem::Binary)),
+            "1.5 KiB"
+        );
+        assert_eq!(format!("{:.0}", size.display(UnitSystem::Decimal)), "2 KB");
+        // Bytes are always whole numbers
+        assert_eq!(
+            format!("{:.3}", ByteSize::new(512).display(UnitSystem::Binary)),
+            "512 B"
+        );
+    }
+
+    #[test]
+    fn transfer_rate() {
+        let rate = TransferRate::new(ByteSize::new(20 * 1024 * 1024), Duration::from_secs(2));
+        assert_eq!(rate.bytes_per_sec(), 10.0 * 1024.0 * 1024.0);
+        assert_eq!(rate.display(UnitSystem::Binary).to_string(), "10.00 MiB/s");
+        assert_eq!(rate.display(UnitSystem::Decimal).to_string(), "10.49 MB/s");
+
+        let rate = TransferRate::new(ByteSize::new(999), Duration::from_secs(1));
+        assert_eq!(rate.display(UnitSystem::Decimal).to_string(), "999 B/s");
+
+        let rate = TransferRate::new(ByteSize::new(1024), Duration::ZERO);
+        assert_eq!(rate.display(UnitSystem::Binary).to_string(), "0 B/s");
+    }
+
+    /// Preferred unit system is global, so no other test changes it.
+    #[test]
+    fn preferred() {
+        assert_eq!(UnitSystem::preferred(), UnitSystem::Binary);
+        assert_eq!(ByteSize::new(1024).to_string(), "1.00 KiB");
+
+        UnitSystem::Decimal.set_preferred();
+        assert_eq!(ByteSize::new(1000).to_string(), "1.00 KB");
+        assert_eq!(
+            TransferRate::new(ByteSize::new(1000), Duration::from_secs(1)).to_string(),
+            "1.00 KB/s"
+        );
+
+        UnitSystem::Binary.set_preferred();
+        assert_eq!(ByteSize::new(1000).to_string(), "1000 B");
+    }
+}
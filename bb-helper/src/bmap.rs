@@ -0,0 +1,206 @@
+//! Generate block maps (bmap) for raw images, compatible with [bmaptool].
+//!
+//! A block map lists the blocks of an image which contain data, along with their SHA256. Writers
+//! only need to write mapped blocks, which is much faster for images with a lot of free space.
+//! Like bmaptool, unmapped blocks are found using holes in sparse files. Files without holes, or
+//! on platforms and filesystems without support for finding holes, are mapped completely.
+//!
+//! ```
+//! use std::io::Cursor;
+//!
+//! let img = Cursor::new(vec![1u8; 3 * 4096]);
+//! let bmap = bb_helper::bmap::generate(img, 3 * 4096, &[0..4096, 8192..12288], 4096).unwrap();
+//!
+//! assert!(bmap.contains("<MappedBlocksCount> 2 </MappedBlocksCount>"));
+//! ```
+//!
+//! [bmaptool]: https://github.com/yoctoproject/bmaptool
+
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Seek, SeekFrom},
+    ops::{Range, RangeInclusive},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+/// Block size used by bmaptool.
+pub const DEFAULT_BLOCK_SIZE: u64 = 4096;
+
+const CHECKSUM_PLACEHOLDER: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Generate bmap for the raw image at `path`, mapping blocks which are not holes.
+pub fn generate_file(path: &Path) -> io::Result<String> {
+    let file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let ranges = data_ranges(&file)?;
+
+    generate(file, size, &ranges, DEFAULT_BLOCK_SIZE)
+}
+
+/// Generate bmap (format 2.0) for a raw image of `size` bytes. `mapped` are byte ranges containing
+/// data, in increasing order. Blocks partially covered by a range are mapped.
+pub fn generate(
+    mut img: impl Read + Seek,
+    size: u64,
+    mapped: &[Range<u64>],
+    block_size: u64,
+) -> io::Result<String> {
+    let blocks = size.div_ceil(block_size);
+    let ranges = block_ranges(mapped, block_size, blocks);
+
+    let mut block_map = String::new();
+    let mut mapped_blocks = 0;
+    let mut buf = vec![0u8; block_size as usize];
+
+    for r in ranges {
+        let start = r.start() * block_size;
+        let end = ((r.end() + 1) * block_size).min(size);
+        mapped_blocks += r.end() - r.start() + 1;
+
+        img.seek(SeekFrom::Start(start))?;
+        let mut hasher = Sha256::new();
+        let mut pos = start;
+        while pos < end {
+            let len = (end - pos).min(block_size) as usize;
+            img.read_exact(&mut buf[..len])?;
+            hasher.update(&buf[..len]);
+            pos += len as u64;
+        }
+        let chksum = const_hex::encode(hasher.finalize());
+
+        let range = if r.start() == r.end() {
+            r.start().to_string()
+        } else {
+            format!("{}-{}", r.start(), r.end())
+        };
+        let _ = writeln!(
+            block_map,
+            "        <Range chksum=\"{chksum}\"> {range} </Range>"
+        );
+    }
+
+    let bmap = format!(
+        "<?xml version=\"1.0\" ?>\n\
+         <bmap version=\"2.0\">\n    \
+             <ImageSize> {size} </ImageSize>\n    \
+             <BlockSize> {block_size} </BlockSize>\n    \
+             <BlocksCount> {blocks} </BlocksCount>\n    \
+             <MappedBlocksCount> {mapped_blocks} </MappedBlocksCount>\n    \
+             <ChecksumType> sha256 </ChecksumType>\n    \
+             <BmapFileChecksum> {CHECKSUM_PLACEHOLDER} </BmapFileChecksum>\n    \
+             <BlockMap>\n\
+         {block_map}    \
+             </BlockMap>\n\
+         </bmap>\n"
+    );
+
+    // Checksum of the file is calculated with the checksum itself set to zeros
+    let checksum = const_hex::encode(Sha256::digest(bmap.as_bytes()));
+    Ok(bmap.replacen(CHECKSUM_PLACEHOLDER, &checksum, 1))
+}
+
+/// Convert byte ranges to merged inclusive block ranges, limited to `blocks`.
+fn block_ranges(mapped: &[Range<u64>], block_size: u64, blocks: u64) -> Vec<RangeInclusive<u64>> {
+    let mut ans: Vec<RangeInclusive<u64>> = Vec::new();
+
+    for r in mapped.iter().filter(|x| !x.is_empty()) {
+        let start = r.start / block_size;
+        let end = r.end.div_ceil(block_size).min(blocks);
+        if start >= end {
+            continue;
+        }
+        let end = end - 1;
+
+        match ans.last_mut() {
+            Some(last) if start <= last.end() + 1 => {
+                *last = *last.start()..=end.max(*last.end());
+            }
+            _ => ans.push(start..=end),
+        }
+    }
+
+    ans
+}
+
+/// Byte ranges of `file` containing data, i.e. everything except holes.
+#[cfg(target_os = "linux")]
+pub fn data_ranges(file: &std::fs::File) -> io::Result<Vec<Range<u64>>> {
+    use std::os::fd::AsRawFd;
+
+    let size = file.metadata()?.len();
+    let fd = file.as_raw_fd();
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+
+    while pos < size {
+        let offset = libc::off_t::try_from(pos).map_err(io::Error::other)?;
+        let start = unsafe { libc::lseek(fd, offset, libc::SEEK_DATA) };
+        if start < 0 {
+            let e = io::Error::last_os_error();
+            return match e.raw_os_error() {
+                // No more data till the end of file
+                Some(libc::ENXIO) => Ok(ranges),
+                // Filesystem does not support finding holes
+                Some(libc::EINVAL | libc::EOPNOTSUPP) => Ok(std::iter::once(0..size).collect()),
+                _ => Err(e),
+            };
+        }
+
+        let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+        if end < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        ranges.push(start as u64..end as u64);
+        pos = end as u64;
+    }
+
+    Ok(ranges)
+}
+
+/// Byte ranges of `file` containing data. Finding holes is not supported on this platform, so
+/// the whole file is returned.
+#[cfg(not(target_os = "linux"))]
+pub fn data_ranges(file: &std::fs::File) -> io::Result<Vec<Range<u64>>> {
+    Ok(vec![0..file.metadata()?.len()])
+}
+
+#[cfg(test)]
+// Single data ranges are intended
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_ranges() {
+        assert_eq!(block_ranges(&[], 4096, 10), vec![]);
+        assert_eq!(
+            block_ranges(&[0..100, 4000..5000, 12288..16384], 4096, 10),
+            vec![0..=1, 3..=3]
+        );
+        assert_eq!(block_ranges(&[0..u64::MAX], 4096, 10), vec![0..=9]);
+    }
+
+    #[test]
+    fn bmap_checksum() {
+        let img: Vec<u8> = (0..10000u32).map(|x| (x % 251) as u8).collect();
+        let bmap = generate(io::Cursor::new(&img), img.len() as u64, &[0..10000], 4096).unwrap();
+
+        assert!(bmap.contains("<BlocksCount> 3 </BlocksCount>"));
+        assert!(bmap.contains(&format!(
+            "<Range chksum=\"{}\"> 0-2 </Range>",
+            const_hex::encode(Sha256::digest(&img))
+        )));
+
+        let start = bmap.find("<BmapFileChecksum> ").unwrap() + "<BmapFileChecksum> ".len();
+        let checksum = &bmap[start..start + CHECKSUM_PLACEHOLDER.len()];
+        let zeroed = bmap.replacen(checksum, CHECKSUM_PLACEHOLDER, 1);
+        assert_eq!(
+            checksum,
+            const_hex::encode(Sha256::digest(zeroed.as_bytes()))
+        );
+    }
+}
@@ -23,14 +23,43 @@ impl WriterFileStream {
         Self { file, writing }
     }
 
+    /// Copy the file to `path`. Data is first written to `{path}.part` and renamed once done,
+    /// so a partially written file is never seen at `path`.
     pub async fn persist(&mut self, path: &Path) -> io::Result<()> {
-        let mut f = tokio::fs::File::create(path).await?;
+        let mut part_path = path.as_os_str().to_owned();
+        part_path.push(".part");
+
+        let mut f = tokio::fs::File::create(&part_path).await?;
         self.file.seek(io::SeekFrom::Start(0)).await?;
 
         tokio::io::copy(&mut self.file, &mut f).await?;
 
         // Causes errors if not present
         f.flush().await?;
+        drop(f);
+
+        tokio::fs::rename(part_path, path).await
+    }
+
+    /// Reserve disk space for `size` bytes upfront without changing the visible file size, so
+    /// running out of space fails now rather than midway. Only supported on Linux, no-op
+    /// elsewhere.
+    pub fn reserve(&self, size: u64) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+
+            let len = libc::off_t::try_from(size).map_err(io::Error::other)?;
+            let ret = unsafe {
+                libc::fallocate(self.file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, len)
+            };
+            if ret != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        let _ = size;
 
         Ok(())
     }
@@ -102,7 +131,12 @@ impl std::io::Seek for ReaderFileStream {
 }
 
 pub fn file_stream() -> io::Result<(WriterFileStream, ReaderFileStream)> {
-    let file = tempfile::NamedTempFile::new()?;
+    file_stream_in(&std::env::temp_dir())
+}
+
+/// Same as [`file_stream`], but the backing file is created in `dir`.
+pub fn file_stream_in(dir: &Path) -> io::Result<(WriterFileStream, ReaderFileStream)> {
+    let file = tempfile::NamedTempFile::new_in(dir)?;
     let flag = Arc::new(AtomicBool::new(true));
 
     let reader = ReaderFileStream::new(file.reopen()?, flag.clone());
@@ -0,0 +1,359 @@
+//! A registry of successful flashes.
+//!
+//! Each entry is stored as a single JSON line, which allows appending new records without
+//! rewriting the whole file and keeps the file readable by other tools.
+
+use std::{
+    io::{self, BufRead, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A single successful flash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since UNIX epoch.
+    pub timestamp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board: Option<String>,
+    pub image: String,
+    /// Hex encoded SHA256 of the image file before extraction. For catalog images, this is the
+    /// download checksum which the downloaded file was verified against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_sha256: Option<String>,
+    pub destination: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_name: Option<String>,
+    /// Serial number of the SD Card, if the card reader exposes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_serial: Option<String>,
+    /// Hex encoded SHA256 of the customization applied after flashing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customization_sha256: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Create a new entry with the current time as timestamp.
+    pub fn new(image: String, destination: String) -> Self {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            timestamp,
+            board: None,
+            image,
+            image_sha256: None,
+            destination,
+            destination_name: None,
+            destination_serial: None,
+            customization_sha256: None,
+        }
+    }
+
+    pub fn board(mut self, board: String) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    pub fn image_sha256(mut self, sha256: [u8; 32]) -> Self {
+        self.image_sha256 = Some(const_hex::encode(sha256));
+        self
+    }
+
+    pub fn destination_name(mut self, name: String) -> Self {
+        self.destination_name = Some(name);
+        self
+    }
+
+    pub fn destination_serial(mut self, serial: String) -> Self {
+        self.destination_serial = Some(serial);
+        self
+    }
+
+    /// Store hash of the customization. Secrets such as passwords should be left out of `data`,
+    /// since weak passwords could be recovered from an unsalted hash.
+    pub fn customization(mut self, data: &[u8]) -> Self {
+        self.customization_sha256 = Some(const_hex::encode(Sha256::digest(data)));
+        self
+    }
+
+    /// Timestamp formatted as `YYYY-MM-DD HH:MM:SS UTC`.
+    pub fn timestamp_utc(&self) -> String {
+        i64::try_from(self.timestamp)
+            .ok()
+            .and_then(|x| chrono::DateTime::from_timestamp(x, 0))
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M:%S UTC")
+            .to_string()
+    }
+}
+
+/// Registry of successful flashes backed by a file.
+#[derive(Debug, Clone)]
+pub struct History(PathBuf);
+
+impl History {
+    pub const fn new(path: PathBuf) -> Self {
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Append a new entry to the registry. Creates the file if not present.
+    pub fn append(&self, entry: &HistoryEntry) -> io::Result<()> {
+        if let Some(p) = self.0.parent() {
+            std::fs::create_dir_all(p)?;
+        }
+
+        let mut line = serde_json::to_vec(entry).map_err(io::Error::other)?;
+        line.push(b'\n');
+
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.0)?
+            .write_all(&line)
+    }
+
+    /// All entries in the registry, oldest first. Malformed lines are skipped.
+    pub fn entries(&self) -> io::Result<Vec<HistoryEntry>> {
+        let f = match std::fs::File::open(&self.0) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut res = Vec::new();
+        for line in io::BufReader::new(f).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Ok(x) = serde_json::from_str(&line) {
+                res.push(x);
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// Compute SHA256 of a file. Blocking.
+pub fn file_sha256(path: &Path) -> io::Result<[u8; 32]> {
+    file_sha256_with_progress(path, |_, _| {})
+}
+
+/// Compute SHA256 of a file, calling `progress` with bytes hashed so far and the file size after
+/// each chunk. Blocking.
+pub fn file_sha256_with_progress(
+    path: &Path,
+    mut progress: impl FnMut(u64, u64),
+) -> io::Result<[u8; 32]> {
+    let mut f = std::fs::File::open(path)?;
+    let size = f.metadata()?.len();
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut pos = 0;
+
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+
+        pos += n as u64;
+        progress(pos, size);
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+/// Cached checksum of a local file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ChecksumEntry {
+    path: PathBuf,
+    size: u64,
+    /// Modification time as nanoseconds since UNIX epoch.
+    mtime: u128,
+    sha256: String,
+}
+
+/// Cache of SHA256 of local files keyed by path, size and modification time. Avoids re-hashing
+/// large images which are flashed repeatedly.
+#[derive(Debug, Clone)]
+pub struct ChecksumCache(PathBuf);
+
+impl ChecksumCache {
+    pub const fn new(path: PathBuf) -> Self {
+        Self(path)
+    }
+
+    /// SHA256 of a file. Uses the cached value if the file has not changed since it was computed.
+    /// Blocking.
+    pub fn file_sha256(&self, path: &Path) -> io::Result<[u8; 32]> {
+        let path = std::fs::canonicalize(path)?;
+        let (size, mtime) = file_key(&path)?;
+
+        let mut entries = self.entries()?;
+        if let Some(x) = entries
+            .iter()
+            .find(|x| x.path == path && x.size == size && x.mtime == mtime)
+            && let Ok(sha256) = const_hex::decode_to_array(&x.sha256)
+        {
+            return Ok(sha256);
+        }
+
+        let sha256 = file_sha256(&path)?;
+
+        entries.retain(|x| x.path != path);
+        entries.push(ChecksumEntry {
+            path,
+            size,
+            mtime,
+            sha256: const_hex::encode(sha256),
+        });
+        self.save(&entries)?;
+
+        Ok(sha256)
+    }
+
+    /// Remove cached checksum of a file.
+    pub fn invalidate(&self, path: &Path) -> io::Result<()> {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let mut entries = self.entries()?;
+        let len = entries.len();
+
+        entries.retain(|x| x.path != path);
+        if entries.len() == len {
+            return Ok(());
+        }
+
+        self.save(&entries)
+    }
+
+    /// Remove all cached checksums.
+    pub fn clear(&self) -> io::Result<()> {
+        match std::fs::remove_file(&self.0) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn entries(&self) -> io::Result<Vec<ChecksumEntry>> {
+        match std::fs::read(&self.0) {
+            // A corrupt cache is simply rebuilt
+            Ok(x) => Ok(serde_json::from_slice(&x).unwrap_or_default()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save(&self, entries: &[ChecksumEntry]) -> io::Result<()> {
+        let dir = self.0.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+
+        // Write to temporary file first so that concurrent readers never see a partial cache
+        let mut f = tempfile::NamedTempFile::new_in(dir)?;
+        serde_json::to_writer(&mut f, entries).map_err(io::Error::other)?;
+        f.persist(&self.0).map_err(|e| e.error)?;
+
+        Ok(())
+    }
+}
+
+fn file_key(path: &Path) -> io::Result<(u64, u128)> {
+    let meta = std::fs::metadata(path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_nanos())
+        .unwrap_or_default();
+
+    Ok((meta.len(), mtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp() {
+        let mut entry = HistoryEntry::new(String::new(), String::new());
+
+        entry.timestamp = 0;
+        assert_eq!(entry.timestamp_utc(), "1970-01-01 00:00:00 UTC");
+
+        entry.timestamp = 1709210096;
+        assert_eq!(entry.timestamp_utc(), "2024-02-29 12:34:56 UTC");
+    }
+
+    #[test]
+    fn append_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::new(dir.path().join("history.jsonl"));
+
+        assert!(history.entries().unwrap().is_empty());
+
+        let entry = HistoryEntry::new("image.img.xz".to_string(), "/dev/sdb".to_string())
+            .board("BeagleY-AI".to_string())
+            .customization(b"hostname=beagle");
+        history.append(&entry).unwrap();
+        history.append(&entry).unwrap();
+
+        assert_eq!(history.entries().unwrap(), vec![entry.clone(), entry]);
+    }
+
+    #[test]
+    fn sha256_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let img = dir.path().join("image.img");
+        std::fs::write(&img, vec![0u8; 3 * 1024 * 1024 + 5]).unwrap();
+
+        let mut updates = Vec::new();
+        let sha256 =
+            file_sha256_with_progress(&img, |pos, size| updates.push((pos, size))).unwrap();
+
+        assert_eq!(sha256, file_sha256(&img).unwrap());
+        assert_eq!(
+            updates.last(),
+            Some(&(3 * 1024 * 1024 + 5, 3 * 1024 * 1024 + 5))
+        );
+        assert!(updates.windows(2).all(|x| x[0].0 < x[1].0));
+    }
+
+    #[test]
+    fn checksum_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ChecksumCache::new(dir.path().join("checksums.json"));
+        let img = dir.path().join("image.img");
+
+        std::fs::write(&img, b"image").unwrap();
+        let sha256 = file_sha256(&img).unwrap();
+        assert_eq!(cache.file_sha256(&img).unwrap(), sha256);
+
+        // Cached value is used while the file is unchanged
+        let mut entries = cache.entries().unwrap();
+        entries[0].sha256 = const_hex::encode([0u8; 32]);
+        cache.save(&entries).unwrap();
+        assert_eq!(cache.file_sha256(&img).unwrap(), [0u8; 32]);
+
+        cache.invalidate(&img).unwrap();
+        assert_eq!(cache.file_sha256(&img).unwrap(), sha256);
+
+        // Changing the file invalidates the cached value
+        std::fs::write(&img, b"new image").unwrap();
+        let sha256 = file_sha256(&img).unwrap();
+        assert_eq!(cache.file_sha256(&img).unwrap(), sha256);
+
+        cache.clear().unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+    }
+}
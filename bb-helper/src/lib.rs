@@ -1,4 +1,14 @@
+#[cfg(feature = "bmap")]
+pub mod bmap;
 #[cfg(feature = "file_stream")]
 pub mod file_stream;
+#[cfg(feature = "history")]
+pub mod history;
+#[cfg(feature = "mdns")]
+pub mod mdns;
+#[cfg(feature = "qr")]
+pub mod qr;
 #[cfg(feature = "resolvable")]
 pub mod resolvable;
+#[cfg(feature = "usb_network")]
+pub mod usb_network;
@@ -0,0 +1,264 @@
+//! Minimal mDNS resolver, used to find boards on the local network once they boot after flashing.
+//!
+//! Queries are sent as one-shot (legacy unicast) queries from an ephemeral port, so responders
+//! such as Avahi reply directly and there is no need to bind the mDNS port.
+//!
+//! ```
+//! assert_eq!(bb_helper::mdns::fqdn("beaglebone"), "beaglebone.local");
+//! assert_eq!(
+//!     bb_helper::mdns::ssh_command("beaglebone", Some("debian")),
+//!     "ssh debian@beaglebone.local"
+//! );
+//! ```
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+/// mDNS multicast group and port.
+pub const MDNS_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+/// Time between queries while waiting for the board to come online.
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const HEADER_LEN: usize = 12;
+/// Maximum compression pointers followed while reading a name. Guards against loops.
+const MAX_POINTERS: usize = 16;
+
+/// Name of `hostname` in the `.local` domain.
+pub fn fqdn(hostname: &str) -> String {
+    let hostname = hostname.trim_end_matches('.');
+    if hostname.to_ascii_lowercase().ends_with(".local") {
+        hostname.to_string()
+    } else {
+        format!("{hostname}.local")
+    }
+}
+
+/// Command to log in to the board over SSH.
+pub fn ssh_command(hostname: &str, user: Option<&str>) -> String {
+    match user {
+        Some(u) => format!("ssh {u}@{}", fqdn(hostname)),
+        None => format!("ssh {}", fqdn(hostname)),
+    }
+}
+
+/// Query for IPv4 and IPv6 addresses of `name`. Returns [`None`] if `name` is not a valid DNS
+/// name.
+pub fn query(name: &str) -> Option<Vec<u8>> {
+    let mut res = vec![0u8; HEADER_LEN];
+    // Two questions, no answers
+    res[5] = 2;
+
+    let encoded = encode_name(name)?;
+    for ty in [TYPE_A, TYPE_AAAA] {
+        res.extend_from_slice(&encoded);
+        res.extend_from_slice(&ty.to_be_bytes());
+        res.extend_from_slice(&CLASS_IN.to_be_bytes());
+    }
+
+    Some(res)
+}
+
+fn encode_name(name: &str) -> Option<Vec<u8>> {
+    let mut res = Vec::with_capacity(name.len() + 2);
+
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return None;
+        }
+        res.push(label.len() as u8);
+        res.extend_from_slice(label.as_bytes());
+    }
+    res.push(0);
+
+    (res.len() <= 255).then_some(res)
+}
+
+/// Addresses of `name` in an mDNS response. Malformed packets and other records are ignored.
+pub fn parse_response(packet: &[u8], name: &str) -> Vec<IpAddr> {
+    parse_records(packet, name).unwrap_or_default()
+}
+
+fn parse_records(packet: &[u8], name: &str) -> Option<Vec<IpAddr>> {
+    let header = packet.get(..HEADER_LEN)?;
+    // Not a response
+    if header[2] & 0x80 == 0 {
+        return None;
+    }
+
+    let count = |i: usize| usize::from(u16::from_be_bytes([header[i], header[i + 1]]));
+    let questions = count(4);
+    let records = count(6) + count(8) + count(10);
+    let name = name.trim_end_matches('.');
+
+    let mut pos = HEADER_LEN;
+    for _ in 0..questions {
+        let (_, next) = read_name(packet, pos)?;
+        pos = next + 4;
+    }
+
+    let mut res = Vec::new();
+    for _ in 0..records {
+        let (record_name, next) = read_name(packet, pos)?;
+        let fixed = packet.get(next..next + 10)?;
+        let ty = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let len = usize::from(u16::from_be_bytes([fixed[8], fixed[9]]));
+        let data = packet.get(next + 10..next + 10 + len)?;
+        pos = next + 10 + len;
+
+        if !record_name.eq_ignore_ascii_case(name) {
+            continue;
+        }
+
+        let addr = match (ty, data.len()) {
+            (TYPE_A, 4) => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(data).ok()?)),
+            (TYPE_AAAA, 16) => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(data).ok()?)),
+            _ => continue,
+        };
+        if !res.contains(&addr) {
+            res.push(addr);
+        }
+    }
+
+    Some(res)
+}
+
+/// Read a possibly compressed name at `pos`. Also returns the position after the name.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    let mut pointers = 0;
+
+    loop {
+        let len = *packet.get(pos)?;
+        match len {
+            0 => {
+                end.get_or_insert(pos + 1);
+                break;
+            }
+            x if x & 0xc0 == 0xc0 => {
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return None;
+                }
+                end.get_or_insert(pos + 2);
+                pos = usize::from(u16::from_be_bytes([x & 0x3f, *packet.get(pos + 1)?]));
+            }
+            x if x & 0xc0 == 0 => {
+                let label = packet.get(pos + 1..pos + 1 + usize::from(x))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + usize::from(x);
+            }
+            _ => return None,
+        }
+    }
+
+    Some((labels.join("."), end?))
+}
+
+/// Resolve `hostname` in the `.local` domain. Queries are repeated until the host responds or
+/// `timeout` expires, so this can be used to wait for a board to come online. Returns an empty
+/// list on timeout.
+pub async fn resolve(hostname: &str, timeout: Duration) -> std::io::Result<Vec<IpAddr>> {
+    let name = fqdn(hostname);
+    let query = query(&name).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Invalid hostname {hostname}"),
+        )
+    })?;
+
+    let socket = tokio::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut buf = vec![0u8; 9000];
+
+    while tokio::time::Instant::now() < deadline {
+        socket.send_to(&query, MDNS_ADDR).await?;
+
+        let retry = (tokio::time::Instant::now() + RETRY_INTERVAL).min(deadline);
+        while let Ok(res) = tokio::time::timeout_at(retry, socket.recv_from(&mut buf)).await {
+            let (n, _) = res?;
+            let addrs = parse_response(&buf[..n], &name);
+            if !addrs.is_empty() {
+                return Ok(addrs);
+            }
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response with a compressed A record, an unrelated record and an AAAA record.
+    fn response() -> Vec<u8> {
+        let mut res = vec![0, 0, 0x84, 0, 0, 0, 0, 3, 0, 0, 0, 0];
+        // beaglebone.local A 192.168.7.2
+        res.extend_from_slice(&encode_name("beaglebone.local").unwrap());
+        res.extend_from_slice(&[0, 1, 0x80, 1, 0, 0, 0, 120, 0, 4, 192, 168, 7, 2]);
+        // other.local A 10.0.0.1, with `local` compressed
+        res.extend_from_slice(&[5, b'o', b't', b'h', b'e', b'r', 0xc0, 23]);
+        res.extend_from_slice(&[0, 1, 0x80, 1, 0, 0, 0, 120, 0, 4, 10, 0, 0, 1]);
+        // beaglebone.local AAAA fe80::1, fully compressed
+        res.extend_from_slice(&[0xc0, 12, 0, 28, 0x80, 1, 0, 0, 0, 120, 0, 16]);
+        res.extend_from_slice(&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        res
+    }
+
+    #[test]
+    fn encode_query() {
+        let q = query("bb.local").unwrap();
+        assert_eq!(&q[..HEADER_LEN], &[0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            &q[HEADER_LEN..],
+            b"\x02bb\x05local\x00\x00\x01\x00\x01\x02bb\x05local\x00\x00\x1c\x00\x01"
+        );
+        assert_eq!(query("bad..local"), None);
+        assert_eq!(query(&format!("{}.local", "a".repeat(64))), None);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_response(&response(), "BeagleBone.local"),
+            vec![
+                IpAddr::V4(Ipv4Addr::new(192, 168, 7, 2)),
+                IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1))
+            ]
+        );
+        assert_eq!(
+            parse_response(&response(), "other.local"),
+            vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]
+        );
+        assert!(parse_response(&response(), "missing.local").is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        let r = response();
+        assert!(parse_response(&r[..r.len() - 1], "beaglebone.local").is_empty());
+
+        // Queries are not responses
+        let mut q = r.clone();
+        q[2] = 0;
+        assert!(parse_response(&q, "beaglebone.local").is_empty());
+
+        // Compression loop
+        let mut l = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0xc0, 12];
+        l.extend_from_slice(&[0, 1, 0, 1, 0, 0, 0, 0, 0, 4, 1, 2, 3, 4]);
+        assert!(parse_response(&l, "beaglebone.local").is_empty());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(fqdn("beagle.local."), "beagle.local");
+        assert_eq!(fqdn("Beagle.LOCAL"), "Beagle.LOCAL");
+        assert_eq!(ssh_command("beagle", None), "ssh beagle.local");
+    }
+}
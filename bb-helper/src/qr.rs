@@ -0,0 +1,538 @@
+//! Minimal QR Code encoder, used to hand over device credentials after flashing.
+//!
+//! Only byte mode with medium error correction and versions 1 to 10 are supported, which is
+//! plenty for a few lines of text (up to [`MAX_LEN`] bytes).
+//!
+//! ```
+//! let qr = bb_helper::qr::QrCode::encode(b"beagle.local").unwrap();
+//! assert_eq!(qr.size(), 21);
+//! assert!(qr.to_svg(4).starts_with("<svg"));
+//! ```
+
+use std::fmt::Write;
+
+/// Maximum number of bytes which can be encoded.
+pub const MAX_LEN: usize = 213;
+
+const MAX_VERSION: usize = 10;
+/// Error correction codewords per block for medium error correction, indexed by version.
+const ECC_CODEWORDS_PER_BLOCK: [usize; MAX_VERSION + 1] =
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+/// Error correction blocks for medium error correction, indexed by version.
+const NUM_ECC_BLOCKS: [usize; MAX_VERSION + 1] = [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+/// Format bits of medium error correction level.
+const ECC_FORMAT_BITS: u32 = 0;
+
+/// Data is longer than [`MAX_LEN`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataTooLong;
+
+impl std::fmt::Display for DataTooLong {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Data too long for QR Code (max {MAX_LEN} bytes)")
+    }
+}
+
+impl std::error::Error for DataTooLong {}
+
+/// QR Code symbol. Modules are dark when `true`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+    is_function: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode `data` using the smallest version which fits.
+    pub fn encode(data: &[u8]) -> Result<Self, DataTooLong> {
+        let version = (1..=MAX_VERSION)
+            .find(|&v| data_bits(data.len(), v) <= num_data_codewords(v) * 8)
+            .ok_or(DataTooLong)?;
+
+        let codewords = add_ecc_and_interleave(&data_codewords(data, version), version);
+
+        let mut qr = Self::new(version);
+        qr.draw_function_patterns(version);
+        qr.draw_codewords(&codewords);
+
+        // Keep the mask with the lowest penalty, as required by the specification
+        let mut best = None;
+        for mask in 0..8 {
+            qr.apply_mask(mask);
+            qr.draw_format_bits(mask);
+            let penalty = qr.penalty();
+            if best.is_none_or(|(_, x)| penalty < x) {
+                best = Some((mask, penalty));
+            }
+            // Masks are XOR, so applying again undoes it
+            qr.apply_mask(mask);
+        }
+
+        let (mask, _) = best.expect("At least one mask");
+        qr.apply_mask(mask);
+        qr.draw_format_bits(mask);
+
+        Ok(qr)
+    }
+
+    /// Width and height in modules, without the quiet zone.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Check if module at `x`, `y` is dark. Modules outside the symbol are light.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+
+    /// Render as SVG with a light quiet zone of `border` modules on each side.
+    pub fn to_svg(&self, border: usize) -> String {
+        let dim = self.size + 2 * border;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in (0..self.size).filter(|&x| self.get(x, y)) {
+                let _ = write!(path, "M{},{}h1v1h-1z", x + border, y + border);
+            }
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {dim} {dim}\" shape-rendering=\"crispEdges\">\
+             <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\
+             <path d=\"{path}\" fill=\"#000000\"/></svg>"
+        )
+    }
+
+    fn new(version: usize) -> Self {
+        let size = version * 4 + 17;
+        Self {
+            size,
+            modules: vec![false; size * size],
+            is_function: vec![false; size * size],
+        }
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.is_function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder(3, 3);
+        self.draw_finder(size - 4, 3);
+        self.draw_finder(3, size - 4);
+
+        let align = alignment_positions(version);
+        let last = align.len().saturating_sub(1);
+        for (i, &x) in align.iter().enumerate() {
+            for (j, &y) in align.iter().enumerate() {
+                // Overlaps with finder patterns
+                if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                    continue;
+                }
+                self.draw_alignment(x, y);
+            }
+        }
+
+        // Reserve format bits area. Actual bits are drawn after masking.
+        self.draw_format_bits(0);
+
+        if version >= 7 {
+            let mut rem = version as u32;
+            for _ in 0..12 {
+                rem = (rem << 1) ^ ((rem >> 11) * 0x1f25);
+            }
+            let bits = ((version as u32) << 12) | rem;
+
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 == 1;
+                let a = size - 11 + i % 3;
+                let b = i / 3;
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4..=4_isize {
+            for dx in -4..=4_isize {
+                let (Some(xx), Some(yy)) = (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                else {
+                    continue;
+                };
+                if xx < self.size && yy < self.size {
+                    let dist = dx.abs().max(dy.abs());
+                    self.set_function(xx, yy, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2..=2_isize {
+            for dx in -2..=2_isize {
+                self.set_function(
+                    x.wrapping_add_signed(dx),
+                    y.wrapping_add_signed(dy),
+                    dx.abs().max(dy.abs()) != 1,
+                );
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u32) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| (bits >> i) & 1 == 1;
+        let size = self.size;
+
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // Always dark
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Place data in the zigzag pattern, skipping function modules.
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+
+        loop {
+            // Skip vertical timing pattern
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vert } else { vert };
+                    if !self.is_function[y * size + x] && i < data.len() * 8 {
+                        self.modules[y * size + x] = (data[i >> 3] >> (7 - (i & 7))) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    7 => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                    _ => unreachable!(),
+                };
+                let idx = y * self.size + x;
+                if invert && !self.is_function[idx] {
+                    self.modules[idx] = !self.modules[idx];
+                }
+            }
+        }
+    }
+
+    /// Penalty score used to pick the mask (ISO/IEC 18004, section 7.8.3).
+    fn penalty(&self) -> usize {
+        const FINDER_LIKE: [bool; 11] = [
+            true, false, true, true, true, false, true, false, false, false, false,
+        ];
+
+        let size = self.size;
+        let mut res = 0;
+
+        let lines = (0..size).flat_map(|i| {
+            [
+                (0..size).map(|j| self.get(j, i)).collect::<Vec<_>>(),
+                (0..size).map(|j| self.get(i, j)).collect::<Vec<_>>(),
+            ]
+        });
+        for line in lines {
+            for run in line.chunk_by(|a, b| a == b).map(<[bool]>::len) {
+                if run >= 5 {
+                    res += run - 2;
+                }
+            }
+
+            for w in line.windows(FINDER_LIKE.len()) {
+                if w == FINDER_LIKE || w.iter().rev().eq(FINDER_LIKE.iter()) {
+                    res += 40;
+                }
+            }
+        }
+
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let c = self.get(x, y);
+                if c == self.get(x + 1, y) && c == self.get(x, y + 1) && c == self.get(x + 1, y + 1)
+                {
+                    res += 3;
+                }
+            }
+        }
+
+        let total = size * size;
+        let dark = self.modules.iter().filter(|x| **x).count();
+        let k = (dark * 20)
+            .abs_diff(total * 10)
+            .div_ceil(total)
+            .saturating_sub(1);
+        res + k * 10
+    }
+}
+
+/// Text listing credentials of a freshly flashed device. Empty values are skipped.
+pub fn device_credentials(
+    hostname: Option<&str>,
+    user: Option<&str>,
+    wifi_ssid: Option<&str>,
+    wifi_password: Option<&str>,
+) -> String {
+    [
+        ("Hostname", hostname),
+        ("User", user),
+        ("Wi-Fi", wifi_ssid),
+        ("Wi-Fi Password", wifi_password),
+    ]
+    .into_iter()
+    .filter_map(|(k, v)| v.filter(|x| !x.is_empty()).map(|v| format!("{k}: {v}\n")))
+    .collect()
+}
+
+/// Number of data bits needed to encode `len` bytes in byte mode.
+const fn data_bits(len: usize, version: usize) -> usize {
+    let count_bits = if version < 10 { 8 } else { 16 };
+    4 + count_bits + len * 8
+}
+
+const fn num_raw_data_modules(version: usize) -> usize {
+    let mut res = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let num_align = version / 7 + 2;
+        res -= (25 * num_align - 10) * num_align - 55;
+        if version >= 7 {
+            res -= 36;
+        }
+    }
+    res
+}
+
+const fn num_data_codewords(version: usize) -> usize {
+    num_raw_data_modules(version) / 8 - ECC_CODEWORDS_PER_BLOCK[version] * NUM_ECC_BLOCKS[version]
+}
+
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+
+    let num_align = version / 7 + 2;
+    let step = (version * 4 + num_align * 2 + 1) / (num_align * 2 - 2) * 2;
+    let mut res: Vec<usize> = (0..num_align - 1)
+        .map(|i| version * 4 + 10 - i * step)
+        .collect();
+    res.push(6);
+    res.reverse();
+    res
+}
+
+/// Byte mode segment, terminator and padding.
+fn data_codewords(data: &[u8], version: usize) -> Vec<u8> {
+    let capacity = num_data_codewords(version) * 8;
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity);
+    let mut push = |val: usize, len: usize| {
+        bits.extend((0..len).rev().map(|i| (val >> i) & 1 == 1));
+    };
+
+    push(0b0100, 4);
+    push(data.len(), if version < 10 { 8 } else { 16 });
+    for x in data {
+        push(usize::from(*x), 8);
+    }
+
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    bits.resize(bits.len().next_multiple_of(8), false);
+
+    let mut res: Vec<u8> = bits
+        .chunks(8)
+        .map(|x| x.iter().fold(0, |acc, b| (acc << 1) | u8::from(*b)))
+        .collect();
+    for pad in [0xec, 0x11].into_iter().cycle() {
+        if res.len() >= capacity / 8 {
+            break;
+        }
+        res.push(pad);
+    }
+
+    res
+}
+
+fn add_ecc_and_interleave(data: &[u8], version: usize) -> Vec<u8> {
+    let num_blocks = NUM_ECC_BLOCKS[version];
+    let block_ecc_len = ECC_CODEWORDS_PER_BLOCK[version];
+    let raw_codewords = num_raw_data_modules(version) / 8;
+    let num_short_blocks = num_blocks - raw_codewords % num_blocks;
+    let short_block_len = raw_codewords / num_blocks;
+
+    let divisor = reed_solomon_divisor(block_ecc_len);
+    let mut blocks = Vec::with_capacity(num_blocks);
+    let mut k = 0;
+    for i in 0..num_blocks {
+        let len = short_block_len - block_ecc_len + usize::from(i >= num_short_blocks);
+        let dat = &data[k..k + len];
+        k += len;
+
+        let mut block = dat.to_vec();
+        // Padding so that all blocks have the same length. Skipped when interleaving.
+        if i < num_short_blocks {
+            block.push(0);
+        }
+        block.extend(reed_solomon_remainder(dat, &divisor));
+        blocks.push(block);
+    }
+
+    let mut res = Vec::with_capacity(raw_codewords);
+    for i in 0..blocks[0].len() {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_block_len - block_ecc_len || j >= num_short_blocks {
+                res.push(block[i]);
+            }
+        }
+    }
+
+    res
+}
+
+fn format_bits(mask: u32) -> u32 {
+    let data = (ECC_FORMAT_BITS << 3) | mask;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    ((data << 10) | rem) ^ 0x5412
+}
+
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut res = vec![0; degree];
+    res[degree - 1] = 1;
+
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            res[j] = gf_mul(res[j], root);
+            if j + 1 < degree {
+                res[j] ^= res[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+
+    res
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut res = vec![0; divisor.len()];
+    for b in data {
+        let factor = b ^ res.remove(0);
+        res.push(0);
+        for (x, y) in res.iter_mut().zip(divisor) {
+            *x ^= gf_mul(*y, factor);
+        }
+    }
+    res
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= u32::from((y >> i) & 1) * u32::from(x);
+    }
+    z as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reed_solomon() {
+        // "HELLO WORLD" 1-M example from ISO/IEC 18004 Annex I
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            reed_solomon_remainder(&data, &reed_solomon_divisor(10)),
+            [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn capacity() {
+        let expected = [0, 16, 28, 44, 64, 86, 108, 124, 154, 182, 216];
+        for (v, x) in expected.into_iter().enumerate().skip(1) {
+            assert_eq!(num_data_codewords(v), x);
+        }
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+
+        assert!(QrCode::encode(&[b'a'; MAX_LEN]).is_ok());
+        assert_eq!(QrCode::encode(&[b'a'; MAX_LEN + 1]), Err(DataTooLong));
+    }
+
+    #[test]
+    fn format_and_version() {
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(format_bits(5), 0b100000011001110);
+
+        let qr = QrCode::encode(&[b'a'; 120]).unwrap();
+        assert_eq!(qr.size(), 7 * 4 + 17);
+        // Version information 000111110010010100 in the lower left block
+        let bits: u32 = (0..18)
+            .map(|i| u32::from(qr.get(i / 3, qr.size() - 11 + i % 3)) << i)
+            .sum();
+        assert_eq!(bits, 0x07c94);
+    }
+
+    #[test]
+    fn device_credentials_text() {
+        assert_eq!(
+            device_credentials(Some("beagle-1a2b"), Some("debian"), Some(""), None),
+            "Hostname: beagle-1a2b\nUser: debian\n"
+        );
+    }
+}
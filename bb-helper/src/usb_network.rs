@@ -0,0 +1,107 @@
+//! Probe the network connection to boards connected over USB (Ethernet over USB gadget).
+//!
+//! BeagleBoard.org images provide a USB network gadget with a DHCP server, which gives the host
+//! an address in a fixed subnet. The gadget is detected by checking if the host has that address,
+//! which works the same way on all platforms.
+//!
+//! ```
+//! let link = bb_helper::usb_network::LINKS[0];
+//! assert_eq!(link.board.to_string(), "192.168.7.2");
+//! assert_eq!(link.url(3000), "http://192.168.7.2:3000/");
+//! ```
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+pub const SSH_PORT: u16 = 22;
+pub const HTTP_PORT: u16 = 80;
+/// Web IDE (Cloud9 or VS Code) on BeagleBoard.org images.
+pub const IDE_PORT: u16 = 3000;
+
+/// Subnet of a USB network gadget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Link {
+    /// Address assigned to the host.
+    pub host: Ipv4Addr,
+    /// Address of the board.
+    pub board: Ipv4Addr,
+}
+
+/// Known gadget subnets. RNDIS/ECM used by Windows and Linux is first, followed by ECM/NCM
+/// used by macOS.
+pub const LINKS: [Link; 2] = [
+    Link {
+        host: Ipv4Addr::new(192, 168, 7, 1),
+        board: Ipv4Addr::new(192, 168, 7, 2),
+    },
+    Link {
+        host: Ipv4Addr::new(192, 168, 6, 1),
+        board: Ipv4Addr::new(192, 168, 6, 2),
+    },
+];
+
+impl Link {
+    /// Check if the host has an address in the subnet, meaning the gadget is connected and the
+    /// board has assigned an address.
+    pub fn is_up(&self) -> bool {
+        std::net::UdpSocket::bind((self.host, 0)).is_ok()
+    }
+
+    /// URL of a web service on the board.
+    pub fn url(&self, port: u16) -> String {
+        match port {
+            HTTP_PORT => format!("http://{}/", self.board),
+            _ => format!("http://{}:{port}/", self.board),
+        }
+    }
+}
+
+/// First connected USB network gadget.
+pub fn detect() -> Option<Link> {
+    LINKS.into_iter().find(Link::is_up)
+}
+
+/// Services accepting connections on the board.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Services {
+    pub ssh: bool,
+    pub http: bool,
+    pub ide: bool,
+}
+
+impl Services {
+    pub const fn any(&self) -> bool {
+        self.ssh || self.http || self.ide
+    }
+}
+
+/// Check which services on the board accept connections. Each port is given `timeout` to
+/// connect.
+pub async fn probe(link: Link, timeout: Duration) -> Services {
+    let check = async |port: u16| {
+        let addr = SocketAddr::from((link.board, port));
+        matches!(
+            tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await,
+            Ok(Ok(_))
+        )
+    };
+
+    Services {
+        ssh: check(SSH_PORT).await,
+        http: check(HTTP_PORT).await,
+        ide: check(IDE_PORT).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        assert_eq!(LINKS[1].url(HTTP_PORT), "http://192.168.6.2/");
+        assert_eq!(LINKS[1].url(IDE_PORT), "http://192.168.6.2:3000/");
+    }
+}
@@ -0,0 +1,14 @@
+fn main() {
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+
+    let commit = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|x| x.status.success())
+        .and_then(|x| String::from_utf8(x.stdout).ok())
+        .map(|x| x.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=GIT_COMMIT={commit}");
+}
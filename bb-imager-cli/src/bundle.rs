@@ -0,0 +1,226 @@
+//! Flash all images of a bundle in order. Images are downloaded before flashing the first step, so
+//! that a network failure does not leave the board partially flashed.
+
+use anyhow::Context;
+use bb_config::config::{Flasher, OsBundle, OsImage, OsListItem, SerialConfig};
+use std::path::PathBuf;
+
+use crate::cli::TargetCommands;
+
+pub(crate) async fn flash_bundle(
+    args: crate::cli::DownloaderArgs,
+    board: String,
+    name: String,
+    dsts: Vec<String>,
+    yes: bool,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let term = console::Term::stdout();
+    let downloader = crate::downloader(args)?;
+
+    let config = crate::prefetch::fetch_config(&downloader).await?;
+    let device = config
+        .imager
+        .devices
+        .iter()
+        .find(|x| x.name.eq_ignore_ascii_case(&board))
+        .with_context(|| format!("Unknown board: {board}"))?;
+
+    let bundle = find_bundle(&downloader, config.os_list, &device.tags, &name)
+        .await
+        .with_context(|| format!("No bundle {name} found for {}", device.name))?;
+
+    let dst_count = bundle
+        .steps
+        .iter()
+        .filter(|x| needs_destination(x.flasher))
+        .count();
+    if dsts.len() != dst_count {
+        anyhow::bail!(
+            "{} needs {dst_count} destinations, got {}",
+            bundle.name,
+            dsts.len()
+        );
+    }
+
+    let mut imgs = Vec::with_capacity(bundle.steps.len());
+    for step in &bundle.steps {
+        if !quiet {
+            term.write_line(&format!("Downloading {}", step.image.name))?;
+        }
+
+        let img = download_image(&downloader, &step.image)
+            .await
+            .with_context(|| format!("Failed to download {}", step.image.name))?;
+        imgs.push(img);
+    }
+
+    let total = bundle.steps.len();
+    let mut dsts = dsts.into_iter();
+    for (i, (step, (img, bmap))) in bundle.steps.into_iter().zip(imgs).enumerate() {
+        term.write_line(&format!(
+            "Step {}/{total}: {} ({})",
+            i + 1,
+            step.image.name,
+            step.flasher
+        ))?;
+        if let Some(x) = &step.instructions {
+            term.write_line(x)?;
+        }
+
+        if !yes {
+            term.write_str("Press Enter to continue ")?;
+            term.read_line()?;
+        }
+
+        let dst = if needs_destination(step.flasher) {
+            dsts.next()
+        } else {
+            None
+        };
+        let target = target(step.flasher, img, bmap, dst, device.serial.as_ref())?;
+        crate::flash(target, quiet, false, None, yes).await;
+    }
+
+    if !quiet {
+        term.write_line(&format!("Flashed {} successfully", bundle.name))?;
+    }
+
+    Ok(())
+}
+
+/// Search for a bundle by name, including in sub lists.
+async fn find_bundle(
+    downloader: &bb_downloader::Downloader,
+    os_list: Vec<OsListItem>,
+    tags: &std::collections::HashSet<String>,
+    name: &str,
+) -> Option<OsBundle> {
+    let mut stack = vec![os_list];
+
+    while let Some(items) = stack.pop() {
+        for item in items {
+            if !item.has_board_image(tags) {
+                continue;
+            }
+
+            match item {
+                OsListItem::Bundle(x) if x.name.eq_ignore_ascii_case(name) => return Some(x),
+                OsListItem::SubList(x) => stack.push(x.subitems),
+                OsListItem::RemoteSubList(x) => {
+                    match downloader
+                        .download_json_no_cache::<Vec<OsListItem>, _>(x.subitems_url.clone())
+                        .await
+                    {
+                        Ok(mut y) => {
+                            y.iter_mut().for_each(OsListItem::strip_header_variables);
+                            stack.push(y)
+                        }
+                        Err(e) => eprintln!("Failed to fetch {}: {e}", x.subitems_url),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    None
+}
+
+/// Download image (and bmap, if present) into cache.
+pub(crate) async fn download_image(
+    downloader: &bb_downloader::Downloader,
+    img: &OsImage,
+) -> anyhow::Result<(PathBuf, Option<PathBuf>)> {
+    let downloader =
+        downloader.with_headers(&img.url, img.resolved_headers(bb_config::secrets::lookup))?;
+
+    let p = if img.url.scheme() == "oci" {
+        let reference: bb_downloader::oci::Reference = img.url.as_str().parse()?;
+        let artifact = downloader.resolve_oci(&reference).await?;
+        downloader.download_oci(&artifact, None).await?
+    } else {
+        downloader
+            .download_with_sha(img.url.clone(), img.image_download_sha256, None)
+            .await?
+    };
+
+    let bmap = match &img.bmap {
+        Some(x) => Some(downloader.download(x.clone(), None).await?),
+        None => None,
+    };
+
+    Ok((p, bmap))
+}
+
+/// Flashers which do not have a fixed destination.
+const fn needs_destination(flasher: Flasher) -> bool {
+    !matches!(flasher, Flasher::Pb2Mspm0)
+}
+
+#[cfg_attr(not(feature = "bcf_cc1352p7"), allow(unused_variables))]
+fn target(
+    flasher: Flasher,
+    img: PathBuf,
+    bmap: Option<PathBuf>,
+    dst: Option<String>,
+    serial: Option<&SerialConfig>,
+) -> anyhow::Result<TargetCommands> {
+    let img = img.into_boxed_path();
+
+    let res = match flasher {
+        Flasher::SdCard => TargetCommands::Sd {
+            img,
+            dst: dst.context("Missing destination")?.into(),
+            sysconf: Default::default(),
+            bmap: bmap.map(PathBuf::into_boxed_path),
+            write_limit: None,
+            verify: Default::default(),
+            export: false,
+            qr_code: None,
+            #[cfg(feature = "boot_test")]
+            boot_test: Default::default(),
+            #[cfg(feature = "test-util")]
+            dst_file: false,
+        },
+        #[cfg(feature = "bcf_cc1352p7")]
+        Flasher::BeagleConnectFreedom => TargetCommands::Bcf {
+            img,
+            dst: dst.context("Missing destination")?,
+            no_verify: false,
+            slot: None,
+            port: port_args(serial),
+        },
+        #[cfg(feature = "bcf_msp430")]
+        Flasher::Msp430Usb => TargetCommands::Msp430 {
+            img,
+            dst: dst.context("Missing destination")?,
+        },
+        #[cfg(feature = "pb2_mspm0")]
+        Flasher::Pb2Mspm0 => TargetCommands::Pb2Mspm0 {
+            img,
+            no_eeprom: false,
+        },
+        _ => anyhow::bail!("{flasher} is not supported by this build"),
+    };
+
+    Ok(res)
+}
+
+/// Serial port settings from the catalog. Used when the board needs non default settings.
+#[cfg(feature = "bcf_cc1352p7")]
+fn port_args(serial: Option<&SerialConfig>) -> crate::cli::BcfPortArgs {
+    let Some(serial) = serial else {
+        return Default::default();
+    };
+
+    crate::cli::BcfPortArgs {
+        baud_rate: serial.baud_rate,
+        flow_control: serial.flow_control.map(|x| match x {
+            bb_config::config::FlowControl::None => crate::cli::BcfFlowControl::None,
+            bb_config::config::FlowControl::Software => crate::cli::BcfFlowControl::Software,
+            bb_config::config::FlowControl::Hardware => crate::cli::BcfFlowControl::Hardware,
+        }),
+        timeout_ms: serial.timeout_ms,
+    }
+}
@@ -1,13 +1,69 @@
-use std::path::{Path, PathBuf};
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
+};
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    disable_version_flag = true,
+    arg_required_else_help = true
+)]
 pub struct Opt {
     #[command(subcommand)]
     /// Specifies the subcommand to execute.
-    pub command: Commands,
+    pub command: Option<Commands>,
+
+    #[arg(short = 'V', long)]
+    /// Print version.
+    pub version: bool,
+
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    /// Show more log messages. Can be repeated: `-v` for info, `-vv` for debug and `-vvv` for trace
+    /// messages. Also includes git commit, enabled features and flasher backends in version output.
+    pub verbose: u8,
+
+    #[arg(long, global = true, value_name = "PATH")]
+    /// Write debug log messages to a file, regardless of `--verbose`. Useful for bug reports.
+    pub log_file: Option<PathBuf>,
+
+    #[arg(long, value_name = "DIR")]
+    /// Import a board folder containing `board.json` and the files it references. The board is
+    /// added to the catalog of both CLI and GUI, and its images are copied to the cache.
+    pub import_board: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub downloader: DownloaderArgs,
+
+    #[arg(long, global = true)]
+    /// Show sizes using powers of 1000 (GB) instead of powers of 1024 (GiB).
+    pub si: bool,
+
+    #[arg(short, long, global = true)]
+    /// Do not ask for confirmation before writing to destinations. Required when standard input
+    /// is not a terminal.
+    pub yes: bool,
+}
+
+/// Options for downloading images and catalogs.
+#[derive(Args, Debug, Clone)]
+pub struct DownloaderArgs {
+    #[arg(long, global = true)]
+    /// Directory used to cache downloaded images. Shared with the GUI by default. Can also be set
+    /// using `BB_IMAGER_CACHE_DIR` environment variable.
+    pub cache_dir: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    /// PEM bundle, DER certificate or directory of certificates to trust in addition to the OS
+    /// trust store. Needed behind proxies which inspect HTTPS traffic.
+    pub ca_certs: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    /// Do not verify TLS certificates. DANGEROUS: only use in closed lab networks.
+    pub insecure: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -21,6 +77,80 @@ pub enum Commands {
         #[arg(long)]
         /// Suppress standard output messages for a quieter experience.
         quiet: bool,
+
+        #[arg(long)]
+        /// Do not record this flash in the history registry.
+        no_history: bool,
+
+        #[arg(long)]
+        /// Treat image path as an OCI artifact reference (e.g. `ghcr.io/org/image:tag`). The
+        /// artifact is pulled into cache before flashing.
+        image_oci: bool,
+
+        #[arg(long, conflicts_with = "image_oci")]
+        /// Treat image path as a board name (e.g. "BeagleY-AI") and flash the newest stable image
+        /// for it from the catalog.
+        recommended: bool,
+    },
+
+    /// Command to flash all images of a bundle from the catalog in order.
+    ///
+    /// Bundles are used by products which need multiple images (e.g. SD Card image and
+    /// microcontroller firmware) flashed in a fixed order. All images are downloaded before
+    /// flashing the first step.
+    FlashBundle {
+        #[arg(long)]
+        /// Name of the board (e.g., "BeagleConnect Freedom").
+        board: String,
+
+        /// Name of the bundle in the catalog.
+        bundle: String,
+
+        #[arg(long)]
+        /// Destination for each step, in order. Can be specified multiple times. Steps with a
+        /// fixed destination (e.g. PocketBeagle 2 MSPM0) do not need one.
+        dst: Vec<String>,
+
+        #[arg(long)]
+        /// Suppress standard output messages for a quieter experience.
+        quiet: bool,
+    },
+
+    /// Command to flash an image to multiple SD Cards, with customization for each card read from
+    /// a CSV manifest.
+    ///
+    /// The first row of the manifest names the columns. Each row selects the SD Card using
+    /// `destination` (e.g., `/dev/sdb`) or `usb_port` of the card reader (e.g., `1-2.1`), and can
+    /// set `hostname`, `timezone`, `keymap`, `user_name`, `user_password`, `wifi_ssid`,
+    /// `wifi_password`, `ssh_key`, `volume_label` and `rootfs_label`. Empty values fall back to the
+    /// customization given as arguments.
+    Provision {
+        /// Local path to image file. Can be compressed (xz) or extracted file
+        img: Box<Path>,
+
+        #[arg(long)]
+        /// CSV manifest with one row for each SD Card.
+        manifest: PathBuf,
+
+        #[arg(long)]
+        /// Path of the CSV file to write status and checksums of each SD Card to. Defaults to the
+        /// manifest path with `.results.csv` extension.
+        results: Option<PathBuf>,
+
+        #[arg(long, default_value_t = NonZeroUsize::MIN)]
+        /// Number of SD Cards to flash in parallel.
+        parallel: NonZeroUsize,
+
+        #[command(flatten)]
+        sysconf: SysconfArgs,
+
+        #[arg(long)]
+        /// Do not record flashes in the history registry.
+        no_history: bool,
+
+        #[arg(long)]
+        /// Suppress standard output messages for a quieter experience.
+        quiet: bool,
     },
 
     /// Command to list available destinations for flashing based on the selected target.
@@ -32,6 +162,10 @@ pub enum Commands {
         /// Only print paths seperated by newline
         no_frills: bool,
 
+        #[arg(long, value_enum, default_value_t, conflicts_with = "no_frills")]
+        /// Output format.
+        output: OutputFormat,
+
         #[arg(long)]
         /// Show all possible destinations without any sanity filters. Can be used when a device is
         /// not visible due to incorrect reporting by OS.
@@ -40,9 +174,179 @@ pub enum Commands {
 
     /// Command to format SD Card
     Format {
+        /// The destination device (e.g., `/dev/sdX`, `\\.\PhysicalDriveN` or drive letter `E:` on
+        /// Windows).
+        dst: PathBuf,
+
+        #[arg(long, value_parser = parse_volume_label)]
+        /// Volume label of the new FAT32 filesystem.
+        label: Option<Box<str>>,
+
+        #[arg(long, value_enum, default_value_t = PartitionScheme::Mbr)]
+        /// Partition table to create.
+        scheme: PartitionScheme,
+
+        #[arg(long)]
+        /// Overwrite the whole SD Card with zeros before formatting. Takes as long as flashing an
+        /// image of the size of the SD Card.
+        full: bool,
+
+        #[arg(long)]
+        /// Suppress standard output messages for a quieter experience.
+        quiet: bool,
+    },
+
+    /// Command to blink the activity LED of a destination so that it can be physically located.
+    /// Only reads from SD Cards, and does not reset serial devices.
+    Identify {
+        /// Specifies the target type of the destination.
+        target: IdentifyTarget,
+
         /// The destination device (e.g., `/dev/sdX` or specific device identifiers).
+        dst: String,
+
+        #[arg(long, default_value_t = 5)]
+        /// Time to blink in seconds.
+        duration: u64,
+    },
+
+    /// Command to wait for a board to come online after flashing and print its addresses. Boards
+    /// are found using mDNS, so use the hostname configured while flashing.
+    Discover {
+        /// Hostname of the board (e.g., "beaglebone"). `.local` is appended if missing.
+        hostname: String,
+
+        #[arg(long, default_value_t = 300)]
+        /// Time to wait for the board in seconds.
+        timeout: u64,
+
+        #[arg(long)]
+        /// User name to show in the SSH command.
+        user: Option<String>,
+
+        #[arg(long)]
+        /// Only print addresses, one per line. Useful for scripts.
+        no_frills: bool,
+    },
+
+    /// Command to download images for a board into the cache shared with the GUI.
+    Prefetch {
+        #[arg(long)]
+        /// Name of the board (e.g., "BeagleY-AI").
+        board: String,
+
+        #[arg(long, conflicts_with = "image")]
+        /// Download all images for the board, including the ones in sub lists.
+        all: bool,
+
+        #[arg(long)]
+        /// Only download images with the given name. Can be specified multiple times.
+        image: Vec<String>,
+
+        #[arg(long)]
+        /// Suppress standard output messages for a quieter experience.
+        quiet: bool,
+    },
+
+    /// Command to show partitions and metadata of an SD Card image.
+    Inspect {
+        /// Local path to image file. Can be compressed (xz, gz, zst) or extracted file
+        img: Box<Path>,
+
+        #[arg(long, value_enum, default_value_t)]
+        /// Output format. CSV only contains the partitions.
+        output: OutputFormat,
+    },
+
+    /// Command to convert a local image between raw, xz, zstd and gzip formats. Prints the SHA256
+    /// of the converted image.
+    Convert {
+        /// Local path to image file. Can be compressed (xz, gz, zst, zip) or extracted file.
+        input: Box<Path>,
+
+        /// Path of converted image. The format is chosen from the extension (.xz, .zst, .gz).
+        /// Any other extension produces a raw image.
+        output: Box<Path>,
+
+        #[arg(long)]
+        /// Only print the checksum, without progress.
+        quiet: bool,
+    },
+
+    /// Command to compute SHA256 of a local image. Useful to compare against published checksums.
+    Checksum {
+        /// Local path to image file. The checksum is computed for the file as is, without
+        /// extracting it.
+        img: Box<Path>,
+
+        #[arg(long)]
+        /// Only print the checksum, without progress.
+        quiet: bool,
+    },
+
+    /// Command to generate a bmap file for a raw image, compatible with bmaptool. Only blocks
+    /// containing data are mapped, which are found using holes in sparse files. Extract compressed
+    /// images with a tool that keeps holes (e.g. `xz -d`) first.
+    Bmap {
+        /// Local path to raw image file.
+        img: Box<Path>,
+
+        #[arg(long)]
+        /// Path of the bmap file. Defaults to the image path with `.bmap` appended.
+        output: Option<PathBuf>,
+    },
+
+    /// Command to check a catalog (config JSON) for mistakes. Exits with an error if any entry
+    /// would be ignored.
+    ValidateConfig {
+        /// Local path to the catalog.
+        file: Box<Path>,
+    },
+
+    /// Command to compare firmware on a microcontroller with an image without writing it.
+    #[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+    Verify {
+        #[command(subcommand)]
+        /// Type of microcontroller to verify
+        target: VerifyCommands,
+    },
+
+    /// Command to query bootloader and firmware version of connected microcontrollers.
+    #[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+    Probe {
+        /// Specifies the target type to probe.
+        target: ProbeTarget,
+
+        #[arg(long)]
+        /// Probe all possible destinations without any sanity filters.
+        no_filter: bool,
+
+        #[command(flatten)]
+        port: BcfPortArgs,
+    },
+
+    /// Command to inspect previously flashed destinations.
+    History {
+        #[command(subcommand)]
+        /// Specifies the history subcommand to execute.
+        command: HistoryCommands,
+    },
+
+    /// Command to change customization of an already flashed SD Card without flashing the image
+    /// again.
+    Customize {
+        #[arg(long)]
+        /// The destination device (e.g., `/dev/sdX`, `\\.\PhysicalDriveN` or drive letter `E:` on
+        /// Windows).
         dst: PathBuf,
 
+        #[command(flatten)]
+        sysconf: SysconfArgs,
+
+        #[arg(long, value_name = "PATH")]
+        /// Write a QR Code (SVG) with hostname, user and Wi-Fi settings to the given path.
+        qr_code: Option<PathBuf>,
+
         #[arg(long)]
         /// Suppress standard output messages for a quieter experience.
         quiet: bool,
@@ -69,55 +373,53 @@ pub enum TargetCommands {
         #[arg(long)]
         /// Disable checksum verification after flashing to speed up the process.
         no_verify: bool,
+
+        #[arg(long, value_enum)]
+        /// Override the firmware slot to write. Only supported on devices with an A/B capable
+        /// bootloader, fails otherwise. By default, the inactive slot is detected and used.
+        slot: Option<BcfSlot>,
+
+        #[command(flatten)]
+        port: BcfPortArgs,
     },
     /// Flash an SD card with customizable settings for BeagleBoard devices.
     Sd {
         /// Local path to image file. Can be compressed (xz) or extracted file
         img: Box<Path>,
 
-        /// The destination device (e.g., `/dev/sdX` or specific device identifiers).
+        /// The destination device (e.g., `/dev/sdX`, `\\.\PhysicalDriveN` or drive letter `E:` on
+        /// Windows).
         dst: PathBuf,
 
-        #[arg(long)]
-        /// Set a custom hostname for the device (e.g., "beaglebone").
-        hostname: Option<Box<str>>,
-
-        #[arg(long)]
-        /// Set the timezone for the device (e.g., "America/New_York").
-        timezone: Option<Box<str>>,
-
-        #[arg(long)]
-        /// Set the keyboard layout/keymap (e.g., "us" for the US layout).
-        keymap: Option<Box<str>>,
-
-        #[arg(long, requires = "user_password", verbatim_doc_comment)]
-        /// Set a username for the default user. Cannot be `root`. Requires `user_password`.
-        /// Required to enter GUI session due to regulatory requirements.
-        user_name: Option<Box<str>>,
-
-        #[arg(long, requires = "user_name", verbatim_doc_comment)]
-        /// Set a password for the default user. Requires `user_name`.
-        /// Required to enter GUI session due to regulatory requirements.
-        user_password: Option<Box<str>>,
-
-        #[arg(long, requires = "wifi_password")]
-        /// Configure a Wi-Fi SSID for network access. Requires `wifi_password`.
-        wifi_ssid: Option<Box<str>>,
-
-        #[arg(long, requires = "wifi_ssid")]
-        /// Set the password for the specified Wi-Fi SSID. Requires `wifi_ssid`.
-        wifi_password: Option<Box<str>>,
+        #[command(flatten)]
+        sysconf: SysconfArgs,
 
-        #[arg(long)]
-        /// Set SSH public key for authentication
-        ssh_key: Option<Box<str>>,
-
-        #[arg(long)]
-        /// Enable USB DHCP
-        usb_enable_dhcp: bool,
-        /// Provide the bmap file for the image
+        /// Provide the bmap file for the image. If not provided, a bmap file next to the image
+        /// (e.g. `image.wic.bmap` for `image.wic.xz`) is used when present.
         #[arg(long)]
         bmap: Option<Box<Path>>,
+        /// Limit write speed to the SD Card in MiB/s. Useful for card readers and USB hubs that
+        /// drop out when written to at full speed.
+        #[arg(long, value_name = "MIB_PER_SEC")]
+        write_limit: Option<NonZeroU64>,
+        /// Read back the written image to detect failing or counterfeit SD Cards.
+        #[arg(long, value_enum, default_value_t)]
+        verify: VerifyMode,
+        /// Export a customized image file to `dst` instead of writing to an SD Card. Any existing
+        /// file is replaced.
+        #[arg(long, conflicts_with_all = ["bmap", "write_limit", "verify"])]
+        export: bool,
+        /// Write a QR Code (SVG) with hostname, user and Wi-Fi settings to the given path after
+        /// flashing, so the board can be handed over without a separate credentials list.
+        #[arg(long, value_name = "PATH")]
+        qr_code: Option<PathBuf>,
+        #[cfg(feature = "boot_test")]
+        #[command(flatten)]
+        boot_test: BootTestArgs,
+        /// Treat the destination as a regular file or loop device. Only meant for development.
+        #[cfg(feature = "test-util")]
+        #[arg(long, hide = true)]
+        dst_file: bool,
     },
     /// Flash MSP430 on BeagleConnectFreedom.
     #[cfg(feature = "bcf_msp430")]
@@ -149,6 +451,329 @@ pub enum TargetCommands {
     },
 }
 
+#[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+#[derive(Subcommand, Debug)]
+pub enum VerifyCommands {
+    /// Verify BeagleConnect Freedom.
+    #[cfg(feature = "bcf_cc1352p7")]
+    Bcf {
+        /// Local path to image file. Can be compressed (xz) or extracted file
+        img: Box<Path>,
+
+        /// The destination device (e.g., `/dev/ttyACM0` or specific device identifiers).
+        dst: String,
+
+        #[arg(long, value_enum)]
+        /// Compare the given firmware slot. Only supported on devices with an A/B capable
+        /// bootloader, fails otherwise. By default, the active slot is used.
+        slot: Option<BcfSlot>,
+
+        #[command(flatten)]
+        port: BcfPortArgs,
+    },
+    /// Verify MSP430 in BeagleConnect Freedom. The device must be in BSL mode.
+    #[cfg(feature = "bcf_msp430")]
+    Msp430 {
+        /// Local path to image file. Can be compressed (xz) or extracted file
+        img: Box<Path>,
+
+        /// The destination device (e.g., `/dev/hidraw0` or specific device identifiers).
+        dst: String,
+
+        /// Image last flashed to the device. It is used as the BSL password, since unlocking
+        /// with a wrong password erases the device. Verification is refused without it.
+        #[arg(long, value_name = "PATH")]
+        flashed: Option<Box<Path>>,
+    },
+}
+
+/// Post install customization for BeagleBoard.org SD Card images.
+#[derive(Args, Clone, Debug, Default)]
+pub struct SysconfArgs {
+    #[arg(long, value_parser = parse_hostname, verbatim_doc_comment)]
+    /// Set a custom hostname for the device (e.g., "beaglebone").
+    /// Use `{randN}` or `{serialN}` for N random or SD Card serial digits (e.g., "beagle-{rand4}").
+    pub hostname: Option<Box<str>>,
+
+    #[arg(long)]
+    /// Set the timezone for the device (e.g., "America/New_York"). Use "auto" for the timezone of
+    /// this machine.
+    pub timezone: Option<Box<str>>,
+
+    #[arg(long)]
+    /// Set the keyboard layout/keymap (e.g., "us" for the US layout).
+    pub keymap: Option<Box<str>>,
+
+    #[arg(long, requires = "user_password", verbatim_doc_comment)]
+    /// Set a username for the default user. Cannot be `root`. Requires `user_password`.
+    /// Required to enter GUI session due to regulatory requirements.
+    pub user_name: Option<Box<str>>,
+
+    #[arg(long, requires = "user_name", verbatim_doc_comment)]
+    /// Set a password for the default user. Requires `user_name`.
+    /// Required to enter GUI session due to regulatory requirements.
+    pub user_password: Option<Box<str>>,
+
+    #[arg(long, requires = "wifi_password")]
+    /// Configure a Wi-Fi SSID for network access. Requires `wifi_password`.
+    pub wifi_ssid: Option<Box<str>>,
+
+    #[arg(long, requires = "wifi_ssid")]
+    /// Set the password for the specified Wi-Fi SSID. Requires `wifi_ssid`.
+    pub wifi_password: Option<Box<str>>,
+
+    #[arg(long)]
+    /// Set SSH public key for authentication
+    pub ssh_key: Option<Box<str>>,
+
+    #[arg(long)]
+    /// Enable USB DHCP
+    pub usb_enable_dhcp: bool,
+
+    #[arg(long, value_parser = parse_volume_label)]
+    /// Set the volume label of the boot partition, shown when the SD Card is mounted (e.g.,
+    /// "BEAGLE01"). Up to 11 characters.
+    pub volume_label: Option<Box<str>>,
+
+    #[arg(long, value_parser = parse_rootfs_label)]
+    /// Set the volume label of the root partition. Up to 16 bytes.
+    pub rootfs_label: Option<Box<str>>,
+
+    #[arg(long, value_parser = parse_cmdline)]
+    /// Append arguments to the kernel command line (e.g., "console=ttyS0,115200n8 isolcpus=1").
+    /// Edits extlinux.conf or uEnv.txt in the boot partition.
+    pub cmdline_append: Option<Box<str>>,
+
+    #[arg(long)]
+    /// Enable (true) or disable (false) the serial console at 115200 baud on the debug UART.
+    pub serial_console: Option<bool>,
+
+    #[arg(long, value_name = "PACKAGES", value_delimiter = ',', value_parser = parse_package)]
+    /// Install apt packages on first boot (e.g., "git,python3-numpy"). Requires network access on
+    /// the device.
+    pub preinstall: Vec<Box<str>>,
+
+    #[arg(long, value_parser = parse_url)]
+    /// Configure an HTTP proxy for apt and the system on first boot (e.g., "http://10.0.0.1:3142").
+    pub http_proxy: Option<Box<str>>,
+
+    #[arg(long, value_parser = parse_url)]
+    /// Configure an HTTPS proxy for apt and the system on first boot.
+    pub https_proxy: Option<Box<str>>,
+
+    #[arg(long, value_parser = parse_url)]
+    /// Replace the Debian apt mirror on first boot (e.g., "http://mirror.example.com/debian").
+    pub apt_mirror: Option<Box<str>>,
+
+    #[arg(long = "copy", value_name = "SRC:DEST", value_parser = parse_copy)]
+    /// Copy a file or directory to the boot partition (e.g., "uEnv.txt:uEnv.txt"). Can be used
+    /// multiple times.
+    pub copy: Vec<bb_flasher::sd::BootFile>,
+}
+
+pub(crate) fn parse_hostname(s: &str) -> Result<Box<str>, String> {
+    bb_flasher::sd::validate_hostname_template(s)
+        .map(|_| s.into())
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) fn parse_volume_label(s: &str) -> Result<Box<str>, String> {
+    if bb_flasher::sd::valid_fat_label(s) {
+        Ok(s.into())
+    } else {
+        Err(format!(
+            "must be 1 to {} ASCII characters, excluding \"*+,./:;<=>?[\\]|",
+            bb_flasher::sd::FAT_LABEL_LEN
+        ))
+    }
+}
+
+pub(crate) fn parse_rootfs_label(s: &str) -> Result<Box<str>, String> {
+    if bb_flasher::sd::valid_ext_label(s) {
+        Ok(s.into())
+    } else {
+        Err(format!(
+            "must be 1 to {} bytes",
+            bb_flasher::sd::EXT_LABEL_LEN
+        ))
+    }
+}
+
+fn parse_cmdline(s: &str) -> Result<Box<str>, String> {
+    bb_flasher::sd::validate_cmdline(s)
+        .map(|_| s.into())
+        .map_err(|e| e.to_string())
+}
+
+fn parse_package(s: &str) -> Result<Box<str>, String> {
+    if bb_flasher::sd::valid_package_name(s) {
+        Ok(s.into())
+    } else {
+        Err(format!(
+            "{s} is not a valid package name. Use lowercase letters, digits, +, - and ."
+        ))
+    }
+}
+
+fn parse_url(s: &str) -> Result<Box<str>, String> {
+    if bb_flasher::sd::valid_url(s) {
+        Ok(s.into())
+    } else {
+        Err("must be an http:// or https:// URL without spaces or quotes".to_string())
+    }
+}
+
+fn parse_copy(s: &str) -> Result<bb_flasher::sd::BootFile, String> {
+    // Paths in the FAT boot partition cannot contain `:`, unlike Windows paths on the host
+    let (src, dest) = s
+        .rsplit_once(':')
+        .ok_or_else(|| "must be in the form SRC:DEST".to_string())?;
+    let src = PathBuf::from(src);
+    if !src.exists() {
+        return Err(format!("{} does not exist", src.display()));
+    }
+
+    let file = bb_flasher::sd::BootFile::new(src, dest.into());
+    match file.normalized_dest() {
+        Some(_) => Ok(file),
+        None => Err(format!("invalid destination {dest} in boot partition")),
+    }
+}
+
+/// Check that the board boots after flashing by watching its serial console. Defaults are used
+/// for missing values.
+#[cfg(feature = "boot_test")]
+#[derive(Args, Clone, Debug, Default)]
+pub struct BootTestArgs {
+    #[arg(long, value_name = "PORT", conflicts_with = "export")]
+    /// Serial port connected to the debug UART of the board (e.g. `/dev/ttyUSB0`). When given,
+    /// flashing only succeeds once the board prints the boot marker.
+    pub boot_test_port: Option<String>,
+
+    #[arg(long, requires = "boot_test_port")]
+    /// Serial console output which shows that the board has booted [default: "login:"].
+    pub boot_marker: Option<String>,
+
+    #[arg(long, value_name = "SECONDS", requires = "boot_test_port")]
+    /// Time allowed for the board to boot [default: 300].
+    pub boot_timeout: Option<u64>,
+
+    #[arg(long, requires = "boot_test_port")]
+    /// Serial console baud rate [default: 115200].
+    pub boot_baud_rate: Option<u32>,
+
+    #[arg(long, requires = "boot_test_port")]
+    /// Reset the board by pulsing DTR and RTS before watching the console. Needs an adapter with
+    /// DTR/RTS wired to the reset line.
+    pub boot_reset: bool,
+}
+
+/// Serial port settings for BeagleConnect Freedom. Defaults are used for missing values.
+#[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+#[derive(Args, Clone, Debug, Default)]
+pub struct BcfPortArgs {
+    #[arg(long)]
+    /// Serial port baud rate. Use a lower value if the adapter can't sustain the default.
+    pub baud_rate: Option<u32>,
+
+    #[arg(long, value_enum)]
+    /// Serial port flow control.
+    pub flow_control: Option<BcfFlowControl>,
+
+    #[arg(long)]
+    /// Serial port read and write timeout in milliseconds.
+    pub timeout_ms: Option<u64>,
+}
+
+#[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BcfFlowControl {
+    /// No flow control
+    None,
+    /// XON/XOFF flow control
+    Software,
+    /// RTS/CTS flow control
+    Hardware,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum OutputFormat {
+    /// Human readable table
+    #[default]
+    Table,
+    /// Array of JSON objects
+    Json,
+    /// CSV with a header row
+    Csv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum VerifyMode {
+    /// Do not read back the image
+    None,
+    /// Read back the start and end of the image, along with a random sample of blocks
+    #[default]
+    Quick,
+    /// Read back the whole image. Takes about as long as writing
+    Full,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PartitionScheme {
+    /// Single partition in an MBR partition table. Supported by almost every device.
+    Mbr,
+    /// Single partition in a GPT partition table.
+    Gpt,
+    /// Filesystem on the whole SD Card, without a partition table.
+    None,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum IdentifyTarget {
+    /// SD card targets. Reads from the SD card to blink the card reader.
+    Sd,
+    /// BeagleConnect Freedom targets. Toggles the DTR line.
+    #[cfg(feature = "bcf_cc1352p7")]
+    Bcf,
+}
+
+#[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ProbeTarget {
+    /// BeagleConnect Freedom targets. Devices are reset into bootloader while probing.
+    #[cfg(feature = "bcf_cc1352p7")]
+    Bcf,
+    /// MSP430 targets in BSL mode
+    #[cfg(feature = "bcf_msp430")]
+    Msp430,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommands {
+    /// List all recorded flashes, oldest first.
+    List {
+        #[arg(long, value_enum, default_value_t)]
+        /// Output format.
+        output: OutputFormat,
+    },
+    /// Show details of a recorded flash.
+    Show {
+        /// Index of the entry as shown by `history list`.
+        index: usize,
+    },
+    /// Forget cached checksums of local images, forcing them to be hashed again.
+    ClearChecksums,
+}
+
+#[cfg(feature = "bcf_cc1352p7")]
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BcfSlot {
+    /// Firmware slot A
+    A,
+    /// Firmware slot B
+    B,
+}
+
 #[derive(ValueEnum, Clone, Copy, Debug)]
 pub enum DestinationsTarget {
     /// BeagleConnect Freedom targets.
@@ -1,33 +1,216 @@
+mod bundle;
 mod cli;
+mod output;
+mod prefetch;
+mod provision;
 
+use anyhow::Context;
 use bb_flasher::{BBFlasher, BBFlasherTarget, DownloadFlashingStatus, LocalImage};
+use bb_helper::history::{ChecksumCache, History, HistoryEntry};
 use bb_helper::resolvable::LocalStringFile;
 use clap::{CommandFactory, Parser};
-use cli::{Commands, DestinationsTarget, Opt, TargetCommands};
+use cli::{
+    Commands, DestinationsTarget, HistoryCommands, Opt, OutputFormat, SysconfArgs, TargetCommands,
+};
 use futures::StreamExt;
 use std::path::PathBuf;
+use tokio_util::sync::CancellationToken;
+
+/// Exit code when flashing is cancelled using Ctrl+C. Same as used by shells for SIGINT.
+const EXIT_CANCELLED: i32 = 130;
 
 #[tokio::main]
 async fn main() {
     let opt = Opt::parse();
 
-    match opt.command {
-        Commands::Flash { target, quiet } => flash(*target, quiet).await,
-        Commands::Format { dst, quiet } => format(dst, quiet).await,
+    init_logging(opt.verbose, opt.log_file.as_deref());
+
+    if opt.si {
+        bb_flasher::UnitSystem::Decimal.set_preferred();
+    }
+
+    if opt.version {
+        print_version(opt.verbose > 0);
+        return;
+    }
+
+    if let Some(dir) = opt.import_board {
+        import_board(dir, opt.downloader)
+            .await
+            .expect("Failed to import board");
+        return;
+    }
+
+    let Some(command) = opt.command else {
+        Opt::command()
+            .error(
+                clap::error::ErrorKind::MissingSubcommand,
+                "a subcommand is required",
+            )
+            .exit()
+    };
+
+    match command {
+        Commands::Flash {
+            mut target,
+            quiet,
+            no_history,
+            image_oci,
+            recommended,
+        } => {
+            if recommended {
+                pull_recommended(&mut target, opt.downloader.clone(), quiet)
+                    .await
+                    .expect("Failed to download recommended image");
+            }
+
+            let oci = if image_oci {
+                Some(
+                    pull_oci(&mut target, opt.downloader, quiet)
+                        .await
+                        .expect("Failed to pull OCI image"),
+                )
+            } else {
+                None
+            };
+            flash(*target, quiet, no_history, oci, opt.yes).await
+        }
+        Commands::FlashBundle {
+            board,
+            bundle,
+            dst,
+            quiet,
+        } => bundle::flash_bundle(opt.downloader, board, bundle, dst, opt.yes, quiet)
+            .await
+            .expect("Failed to flash bundle"),
+        Commands::Provision {
+            img,
+            manifest,
+            results,
+            parallel,
+            sysconf,
+            no_history,
+            quiet,
+        } => provision::provision(
+            img, manifest, results, parallel, sysconf, no_history, opt.yes, quiet,
+        )
+        .await
+        .expect("Failed to provision"),
+        Commands::Format {
+            dst,
+            label,
+            scheme,
+            full,
+            quiet,
+        } => format(dst, label, scheme, full, opt.yes, quiet).await,
+        Commands::Customize {
+            dst,
+            sysconf,
+            qr_code,
+            quiet,
+        } => customize(dst, sysconf, qr_code, opt.yes, quiet).await,
         Commands::ListDestinations {
             target,
             no_frills,
+            output,
             no_filter,
         } => {
-            list_destinations(target, no_frills, no_filter).await;
+            list_destinations(target, no_frills, output, no_filter).await;
         }
+        Commands::Identify {
+            target,
+            dst,
+            duration,
+        } => identify(target, dst, duration).await,
+        Commands::Discover {
+            hostname,
+            timeout,
+            user,
+            no_frills,
+        } => discover(hostname, timeout, user, no_frills).await,
+        Commands::Prefetch {
+            board,
+            all,
+            image,
+            quiet,
+        } => prefetch::prefetch(opt.downloader, board, all, image, quiet)
+            .await
+            .expect("Failed to prefetch images"),
+        Commands::Inspect { img, output } => inspect(img, output).await,
+        Commands::Convert {
+            input,
+            output,
+            quiet,
+        } => convert(input, output, quiet).await,
+        Commands::Checksum { img, quiet } => checksum(img, quiet).await,
+        Commands::Bmap { img, output } => bmap(img, output).await,
+        Commands::ValidateConfig { file } => validate_config(file).await,
+        #[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+        Commands::Verify { target } => verify(target).await,
+        #[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+        Commands::Probe {
+            target,
+            no_filter,
+            port,
+        } => probe(target, no_filter, port).await,
+        Commands::History { command } => history(command),
         Commands::GenerateCompletion { shell } => generate_completion(shell),
     }
 }
 
-async fn flash(target: TargetCommands, quite: bool) {
-    if quite {
-        flash_internal(target, None).await
+async fn flash(
+    mut target: TargetCommands,
+    quite: bool,
+    no_history: bool,
+    oci: Option<String>,
+    yes: bool,
+) {
+    let (hostname, qr_code) = match &mut target {
+        TargetCommands::Sd {
+            dst,
+            sysconf,
+            export,
+            qr_code,
+            #[cfg(feature = "test-util")]
+            dst_file,
+            ..
+        } => {
+            #[cfg(feature = "test-util")]
+            let is_file = *dst_file;
+            #[cfg(not(feature = "test-util"))]
+            let is_file = false;
+
+            // Resolve the destination before progress output starts, so that prompts are not
+            // mixed with it.
+            if !*export && !is_file {
+                *dst = check_macos_device_path(check_partition_path(std::mem::take(dst)));
+            }
+            if !*export {
+                confirm_overwrite(&[dst.as_path()], yes);
+            }
+
+            let hostname = expand_hostname(sysconf, (!*export).then_some(dst.as_path()));
+            let qr_code = qr_code.take().map(|x| (x, device_credentials(sysconf)));
+            (hostname, qr_code)
+        }
+        #[allow(unreachable_patterns)]
+        _ => (None, None),
+    };
+
+    let entry = if no_history {
+        None
+    } else {
+        let (mut entry, img) = history_entry(&target);
+        if let Some(x) = oci {
+            entry.image = x;
+        }
+        Some((entry, img))
+    };
+
+    let cancel = cancel_on_ctrl_c();
+
+    let res = if quite {
+        flash_internal(target, None, Some(cancel)).await
     } else {
         let (tx, mut rx) = futures::channel::mpsc::channel(20);
         tokio::task::spawn(async move {
@@ -45,7 +228,10 @@ async fn flash(target: TargetCommands, quite: bool) {
             term.write_line(&stage_msg(DownloadFlashingStatus::Preparing, stage))
                 .unwrap();
 
-            while let Some(progress) = rx.next().await {
+            while let Some(bb_flasher::ProgressEvent {
+                status: progress, ..
+            }) = rx.next().await
+            {
                 // Skip if no change in stage
                 if progress == last_state {
                     continue;
@@ -57,6 +243,10 @@ async fn flash(target: TargetCommands, quite: bool) {
                         DownloadFlashingStatus::DownloadingProgress(p),
                         DownloadFlashingStatus::DownloadingProgress(_),
                     )
+                    | (
+                        DownloadFlashingStatus::DecompressingProgress(p),
+                        DownloadFlashingStatus::DecompressingProgress(_),
+                    )
                     | (
                         DownloadFlashingStatus::FlashingProgress(p),
                         DownloadFlashingStatus::FlashingProgress(_),
@@ -65,6 +255,7 @@ async fn flash(target: TargetCommands, quite: bool) {
                     }
                     // Create new bar when stage has changed
                     (DownloadFlashingStatus::DownloadingProgress(p), _)
+                    | (DownloadFlashingStatus::DecompressingProgress(p), _)
                     | (DownloadFlashingStatus::FlashingProgress(p), _) => {
                         if let Some(b) = last_bar.take() {
                             b.finish();
@@ -78,9 +269,20 @@ async fn flash(target: TargetCommands, quite: bool) {
                         temp_bar.set_position((p * 100.0) as u64);
                         last_bar = Some(temp_bar);
                     }
+                    // Only print the step when moving between customization steps
+                    (
+                        DownloadFlashingStatus::Customizing(s),
+                        DownloadFlashingStatus::Customizing(_),
+                    ) => {
+                        term.write_line(&format!("    {s}")).unwrap();
+                    }
                     // Print stage when entering a new stage without progress
                     (DownloadFlashingStatus::Verifying, _)
-                    | (DownloadFlashingStatus::Customizing, _)
+                    | (DownloadFlashingStatus::CheckingImage, _)
+                    | (DownloadFlashingStatus::Customizing(_), _)
+                    | (DownloadFlashingStatus::BootTesting, _)
+                    | (DownloadFlashingStatus::BootVerified, _)
+                    | (DownloadFlashingStatus::BootFailed, _)
                     | (DownloadFlashingStatus::Preparing, _) => {
                         if let Some(b) = last_bar.take() {
                             b.finish();
@@ -88,6 +290,9 @@ async fn flash(target: TargetCommands, quite: bool) {
 
                         stage += 1;
                         term.write_line(&stage_msg(progress, stage)).unwrap();
+                        if let DownloadFlashingStatus::Customizing(s) = progress {
+                            term.write_line(&format!("    {s}")).unwrap();
+                        }
                     }
                 }
 
@@ -99,80 +304,859 @@ async fn flash(target: TargetCommands, quite: bool) {
             }
         });
 
-        flash_internal(target, Some(tx)).await
+        flash_internal(target, Some(tx), Some(cancel)).await
+    };
+
+    if let Err(e) = &res
+        && bb_flasher::FailureKind::classify(e) == bb_flasher::FailureKind::Aborted
+    {
+        eprintln!("{} {e:#}", console::style("Cancelled:").yellow().bold());
+        std::process::exit(EXIT_CANCELLED);
+    }
+    res.expect("Filed to flash");
+
+    if !quite && let Some(x) = hostname {
+        console::Term::stdout()
+            .write_line(&format!("Hostname: {x}"))
+            .unwrap();
+    }
+
+    if let Some((path, credentials)) = qr_code {
+        write_qr_code(&path, &credentials);
+    }
+
+    if let Some((entry, img)) = entry {
+        record_history(entry, img).await;
+    }
+}
+
+/// Cancel the returned token on Ctrl+C, so that flashers can stop cleanly and release the
+/// destination. A second Ctrl+C exits immediately.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("\nCancelling. Press Ctrl+C again to exit immediately.");
+        token.cancel();
+
+        let _ = tokio::signal::ctrl_c().await;
+        std::process::exit(EXIT_CANCELLED);
+    });
+
+    cancel
+}
+
+/// Expand hostname template for the destination beforehand, so the generated hostname can be
+/// shown and recorded in history.
+fn expand_hostname(sysconf: &mut SysconfArgs, dst: Option<&std::path::Path>) -> Option<Box<str>> {
+    let template = sysconf
+        .hostname
+        .as_deref()
+        .filter(|x| bb_flasher::sd::is_hostname_template(x))?;
+    let hostname: Box<str> = bb_flasher::sd::expand_hostname(template, dst)
+        .expect("Invalid hostname template")
+        .into();
+
+    sysconf.hostname = Some(hostname.clone());
+    Some(hostname)
+}
+
+/// Text encoded in the QR Code handed over with the board. Passwords of the default user are
+/// left out.
+fn device_credentials(sysconf: &SysconfArgs) -> String {
+    bb_helper::qr::device_credentials(
+        sysconf.hostname.as_deref(),
+        sysconf.user_name.as_deref(),
+        sysconf.wifi_ssid.as_deref(),
+        sysconf.wifi_password.as_deref(),
+    )
+}
+
+fn write_qr_code(path: &std::path::Path, credentials: &str) {
+    if credentials.is_empty() {
+        eprintln!("No hostname, user or Wi-Fi configured. Skipping QR Code");
+        return;
+    }
+
+    let res = bb_helper::qr::QrCode::encode(credentials.as_bytes())
+        .map_err(std::io::Error::other)
+        .and_then(|x| std::fs::write(path, x.to_svg(4)));
+    if let Err(e) = res {
+        eprintln!("Failed to write QR Code: {e}");
+    }
+}
+
+/// Prepare history entry before flashing. Image checksum is computed only after flashing is
+/// successful.
+fn history_entry(target: &TargetCommands) -> (HistoryEntry, Option<PathBuf>) {
+    match target {
+        TargetCommands::Sd {
+            img, dst, sysconf, ..
+        } => {
+            // Passwords are left out, since they could be recovered from the hash
+            let customization: String = [
+                ("hostname", sysconf.hostname.as_deref()),
+                ("timezone", sysconf.timezone.as_deref()),
+                ("keymap", sysconf.keymap.as_deref()),
+                ("user_name", sysconf.user_name.as_deref()),
+                ("wifi_ssid", sysconf.wifi_ssid.as_deref()),
+                ("ssh_key", sysconf.ssh_key.as_deref()),
+                ("usb_enable_dhcp", sysconf.usb_enable_dhcp.then_some("true")),
+            ]
+            .into_iter()
+            .filter_map(|(k, v)| v.map(|v| format!("{k}={v}\n")))
+            .collect();
+
+            let mut entry = HistoryEntry::new(
+                img.to_string_lossy().to_string(),
+                dst.to_string_lossy().to_string(),
+            );
+            if let Some(x) = bb_flasher::sd::card_serial(dst) {
+                entry = entry.destination_serial(x);
+            }
+            if !customization.is_empty() {
+                entry = entry.customization(customization.as_bytes());
+            }
+
+            (entry, Some(img.to_path_buf()))
+        }
+        #[cfg(feature = "bcf_cc1352p7")]
+        TargetCommands::Bcf { img, dst, .. } => (
+            HistoryEntry::new(img.to_string_lossy().to_string(), dst.clone()),
+            Some(img.to_path_buf()),
+        ),
+        #[cfg(feature = "bcf_msp430")]
+        TargetCommands::Msp430 { img, dst } => (
+            HistoryEntry::new(img.to_string_lossy().to_string(), dst.clone()),
+            Some(img.to_path_buf()),
+        ),
+        #[cfg(feature = "pb2_mspm0")]
+        TargetCommands::Pb2Mspm0 { img, .. } => (
+            HistoryEntry::new(img.to_string_lossy().to_string(), "MSPM0".to_string()),
+            Some(img.to_path_buf()),
+        ),
+        #[cfg(feature = "dfu")]
+        TargetCommands::Dfu { identifier, imgs } => {
+            (HistoryEntry::new(imgs.join(" "), identifier.clone()), None)
+        }
+    }
+}
+
+async fn record_history(mut entry: HistoryEntry, img: Option<PathBuf>) {
+    let Some(history) = history_registry() else {
+        return;
+    };
+
+    if let Some(img) = img {
+        match image_sha256(img).await {
+            Ok(x) => entry = entry.image_sha256(x),
+            Err(e) => eprintln!("Failed to compute image checksum: {e}"),
+        }
+    }
+
+    if let Err(e) = history.append(&entry) {
+        eprintln!("Failed to record flash in history: {e}");
+    }
+}
+
+/// SHA256 of a local image. Uses the checksum cache shared with the GUI when available.
+async fn image_sha256(img: PathBuf) -> std::io::Result<[u8; 32]> {
+    let cache = checksum_cache();
+    tokio::task::spawn_blocking(move || match cache {
+        Some(c) => c.file_sha256(&img),
+        None => bb_helper::history::file_sha256(&img),
+    })
+    .await
+    .unwrap()
+}
+
+/// Record of the image written to the boot partition when customizing.
+async fn provenance(img: &std::path::Path) -> bb_flasher::sd::Provenance {
+    let image_sha256 = match image_sha256(img.to_path_buf()).await {
+        Ok(x) => Some(x),
+        Err(e) => {
+            eprintln!("Failed to compute image checksum: {e}");
+            None
+        }
+    };
+
+    bb_flasher::sd::Provenance {
+        image: img
+            .file_name()
+            .unwrap_or(img.as_os_str())
+            .to_string_lossy()
+            .into(),
+        image_version: None,
+        image_sha256,
+        imager: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")).into(),
+    }
+}
+
+/// Pull OCI artifact into cache and replace the image path in target with the cached file.
+/// Returns the artifact reference.
+async fn pull_oci(
+    target: &mut TargetCommands,
+    args: cli::DownloaderArgs,
+    quiet: bool,
+) -> anyhow::Result<String> {
+    let img = match target {
+        TargetCommands::Sd { img, .. } => img,
+        #[cfg(feature = "bcf_cc1352p7")]
+        TargetCommands::Bcf { img, .. } => img,
+        #[cfg(feature = "bcf_msp430")]
+        TargetCommands::Msp430 { img, .. } => img,
+        #[cfg(feature = "pb2_mspm0")]
+        TargetCommands::Pb2Mspm0 { img, .. } => img,
+        #[cfg(feature = "dfu")]
+        TargetCommands::Dfu { .. } => anyhow::bail!("OCI images are not supported for DFU"),
+    };
+
+    let reference: bb_downloader::oci::Reference = img.to_string_lossy().parse()?;
+    let downloader = downloader(args)?;
+
+    if !quiet {
+        console::Term::stdout().write_line(&format!("Pulling {reference}"))?;
+    }
+
+    let artifact = downloader.resolve_oci(&reference).await?;
+    let p = downloader.download_oci(&artifact, None).await?;
+    *img = p.into();
+
+    Ok(reference.to_string())
+}
+
+/// Download the newest stable image for the board named by the image path, and replace the path
+/// with the downloaded image.
+async fn pull_recommended(
+    target: &mut TargetCommands,
+    args: cli::DownloaderArgs,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let (img, bmap) = match target {
+        TargetCommands::Sd { img, bmap, .. } => (img, Some(bmap)),
+        #[cfg(feature = "bcf_cc1352p7")]
+        TargetCommands::Bcf { img, .. } => (img, None),
+        #[cfg(feature = "bcf_msp430")]
+        TargetCommands::Msp430 { img, .. } => (img, None),
+        #[cfg(feature = "pb2_mspm0")]
+        TargetCommands::Pb2Mspm0 { img, .. } => (img, None),
+        #[cfg(feature = "dfu")]
+        TargetCommands::Dfu { .. } => {
+            anyhow::bail!("Recommended images are not supported for DFU")
+        }
+    };
+
+    let board = img.to_string_lossy().into_owned();
+    let downloader = downloader(args)?;
+    let config = prefetch::fetch_config(&downloader).await?;
+    let device = config
+        .imager
+        .devices
+        .iter()
+        .find(|x| x.name.eq_ignore_ascii_case(&board))
+        .with_context(|| format!("Unknown board: {board}"))?;
+
+    let today = chrono::Local::now().date_naive();
+    let (_, os) = config
+        .recommended_image(&device.tags, &prefetch::imager_version(), today)
+        .with_context(|| format!("No stable image found for {}", device.name))?;
+
+    if !quiet {
+        console::Term::stdout().write_line(&format!("Downloading {}", os.name))?;
+    }
+
+    let (p, b) = bundle::download_image(&downloader, os)
+        .await
+        .with_context(|| format!("Failed to download {}", os.name))?;
+    *img = p.into();
+    if let Some(bmap) = bmap
+        && bmap.is_none()
+    {
+        *bmap = b.map(PathBuf::into_boxed_path);
+    }
+
+    Ok(())
+}
+
+/// Downloader using the cache shared with the GUI, unless overridden.
+fn downloader(args: cli::DownloaderArgs) -> anyhow::Result<bb_downloader::Downloader> {
+    let mut res = match args.cache_dir {
+        Some(x) => bb_downloader::Downloader::new(x)?,
+        None => bb_downloader::Downloader::new_with_env(
+            project_dirs()
+                .context("Failed to find cache directory")?
+                .cache_dir(),
+        )?,
+    };
+
+    if let Some(p) = args.ca_certs {
+        res = res
+            .with_root_certificates(&p)
+            .with_context(|| format!("Failed to load certificates from {}", p.display()))?;
+    }
+
+    if args.insecure {
+        res = res.with_insecure(true)?;
+    }
+
+    Ok(res)
+}
+
+/// Import a board folder into the catalog and cache shared with the GUI.
+async fn import_board(dir: PathBuf, args: cli::DownloaderArgs) -> anyhow::Result<()> {
+    let downloader = downloader(args)?;
+    let term = console::Term::stdout();
+
+    let board = tokio::task::spawn_blocking(move || bb_config::sideload::read(&dir)).await??;
+    for x in &board.warnings {
+        term.write_line(&format!(
+            "{} {x}",
+            console::style("Warning:").yellow().bold()
+        ))?;
+    }
+
+    for (path, sha256) in &board.images {
+        term.write_line(&format!("Copying {}", path.display()))?;
+        downloader.import_with_sha(path, *sha256).await?;
+    }
+    for (url, path) in &board.files {
+        downloader.import_with_url(url.clone(), path).await?;
+    }
+
+    let boards_dir = sideload_dir().context("Failed to find data directory")?;
+    board.install(&boards_dir)?;
+
+    term.write_line(&format!("Imported {}", board.name))?;
+    Ok(())
+}
+
+/// Directory of imported board folders. Shared with the GUI.
+pub(crate) fn sideload_dir() -> Option<PathBuf> {
+    project_dirs().map(|x| x.data_dir().join("boards"))
+}
+
+/// Directories shared with the GUI.
+fn project_dirs() -> Option<directories::ProjectDirs> {
+    directories::ProjectDirs::from("org", "beagleboard", "imagingutility")
+}
+
+fn history_registry() -> Option<History> {
+    let dirs = project_dirs()?;
+    Some(History::new(dirs.data_local_dir().join("history.jsonl")))
+}
+
+/// Checksums of local images, reused across flashes of the same unchanged file.
+fn checksum_cache() -> Option<ChecksumCache> {
+    let dirs = project_dirs()?;
+    let dir = dirs.state_dir().unwrap_or(dirs.data_local_dir());
+    Some(ChecksumCache::new(dir.join("checksums.json")))
+}
+
+fn history(command: HistoryCommands) {
+    let term = console::Term::stdout();
+
+    let entries = history_registry()
+        .map(|x| x.entries().expect("Failed to read history"))
+        .unwrap_or_default();
+
+    match command {
+        HistoryCommands::List { output } if !matches!(output, OutputFormat::Table) => {
+            const COLUMNS: &[&str] = &[
+                "index",
+                "timestamp",
+                "board",
+                "image",
+                "image_sha256",
+                "destination",
+                "destination_name",
+                "destination_serial",
+                "customization_sha256",
+            ];
+
+            let rows = entries
+                .into_iter()
+                .enumerate()
+                .map(|(i, e)| {
+                    vec![
+                        i.into(),
+                        e.timestamp.into(),
+                        e.board.into(),
+                        e.image.into(),
+                        e.image_sha256.into(),
+                        e.destination.into(),
+                        e.destination_name.into(),
+                        e.destination_serial.into(),
+                        e.customization_sha256.into(),
+                    ]
+                })
+                .collect();
+            output::print(output, COLUMNS, rows);
+        }
+        HistoryCommands::List { .. } => {
+            for (i, e) in entries.iter().enumerate() {
+                term.write_line(&format!(
+                    "{i:>4}  {}  {}  {}",
+                    e.timestamp_utc(),
+                    e.destination,
+                    e.image
+                ))
+                .unwrap();
+            }
+        }
+        HistoryCommands::Show { index } => {
+            let e = entries.get(index).expect("Invalid history entry");
+            let na = "N/A";
+
+            term.write_line(&format!("Time:               {}", e.timestamp_utc()))
+                .unwrap();
+            term.write_line(&format!(
+                "Board:              {}",
+                e.board.as_deref().unwrap_or(na)
+            ))
+            .unwrap();
+            term.write_line(&format!("Image:              {}", e.image))
+                .unwrap();
+            term.write_line(&format!(
+                "Image SHA256:       {}",
+                e.image_sha256.as_deref().unwrap_or(na)
+            ))
+            .unwrap();
+            term.write_line(&format!("Destination:        {}", e.destination))
+                .unwrap();
+            term.write_line(&format!(
+                "Destination Name:   {}",
+                e.destination_name.as_deref().unwrap_or(na)
+            ))
+            .unwrap();
+            term.write_line(&format!(
+                "Destination Serial: {}",
+                e.destination_serial.as_deref().unwrap_or(na)
+            ))
+            .unwrap();
+            term.write_line(&format!(
+                "Customization Hash: {}",
+                e.customization_sha256.as_deref().unwrap_or(na)
+            ))
+            .unwrap();
+        }
+        HistoryCommands::ClearChecksums => {
+            if let Some(x) = checksum_cache() {
+                x.clear().expect("Failed to clear checksum cache");
+            }
+        }
+    }
+}
+
+async fn inspect(img: Box<std::path::Path>, output: OutputFormat) {
+    const COLUMNS: &[&str] = &["number", "start", "size", "type", "filesystem", "label"];
+
+    let info = tokio::task::spawn_blocking(move || bb_flasher::sd::inspect(&img))
+        .await
+        .unwrap()
+        .expect("Failed to inspect image");
+
+    if !matches!(output, OutputFormat::Table) {
+        let rows = info
+            .partitions
+            .into_iter()
+            .map(|p| {
+                vec![
+                    p.number.into(),
+                    p.start.into(),
+                    p.size.into(),
+                    p.kind.into(),
+                    p.filesystem.map(|x| x.to_string()).into(),
+                    p.label.into(),
+                ]
+            })
+            .collect();
+
+        match output {
+            OutputFormat::Json => {
+                let res = serde_json::json!({
+                    "partition_table": info.partition_table.to_string(),
+                    "distro": info.distro,
+                    "partitions": output::json(COLUMNS, rows),
+                });
+                println!("{res:#}");
+            }
+            _ => output::print(output, COLUMNS, rows),
+        }
+        return;
+    }
+
+    let term = console::Term::stdout();
+
+    term.write_line(&format!("Partition Table: {}", info.partition_table))
+        .unwrap();
+    term.write_line(&format!(
+        "Distro:          {}",
+        info.distro.as_deref().unwrap_or("N/A")
+    ))
+    .unwrap();
+    term.write_line("").unwrap();
+
+    term.write_line(&format!(
+        "{:>3}  {:>12}  {:>12}  {:<20}  {:<10}  Label",
+        "#", "Start", "Size", "Type", "Filesystem"
+    ))
+    .unwrap();
+    for p in info.partitions {
+        term.write_line(&format!(
+            "{:>3}  {:>12}  {:>12}  {:<20}  {:<10}  {}",
+            p.number,
+            p.start,
+            bb_flasher::ByteSize::new(p.size).to_string(),
+            p.kind,
+            p.filesystem.map(|x| x.to_string()).unwrap_or_default(),
+            p.label.unwrap_or_default()
+        ))
+        .unwrap();
+    }
+}
+
+async fn convert(input: Box<std::path::Path>, output: Box<std::path::Path>, quiet: bool) {
+    let bar = if quiet {
+        indicatif::ProgressBar::hidden()
+    } else {
+        indicatif::ProgressBar::new(0).with_style(
+            indicatif::ProgressStyle::with_template(
+                "{msg:15}  [{wide_bar}] [{bytes}/{total_bytes}]",
+            )
+            .expect("Failed to create progress bar"),
+        )
+    };
+    bar.set_message("Converting");
+
+    let bar_clone = bar.clone();
+    let output_clone = output.clone();
+    tokio::task::spawn_blocking(move || {
+        let format = bb_flasher::ImageFormat::from_path(&output_clone);
+        bb_flasher::convert(&input, &output_clone, format, |pos, size| {
+            bar_clone.set_length(size);
+            bar_clone.set_position(pos);
+        })
+    })
+    .await
+    .unwrap()
+    .expect("Failed to convert image");
+    bar.finish_and_clear();
+
+    checksum(output, quiet).await
+}
+
+async fn bmap(img: Box<std::path::Path>, output: Option<PathBuf>) {
+    if bb_flasher::ImageFormat::from_path(&img) != bb_flasher::ImageFormat::Raw {
+        eprintln!("bmap can only be generated for raw images. Extract the image first.");
+        std::process::exit(1);
+    }
+
+    let output = output.unwrap_or_else(|| {
+        let mut p = img.as_os_str().to_owned();
+        p.push(".bmap");
+        PathBuf::from(p)
+    });
+
+    let bmap = tokio::task::spawn_blocking(move || bb_helper::bmap::generate_file(&img))
+        .await
+        .unwrap()
+        .expect("Failed to generate bmap");
+    std::fs::write(&output, bmap).expect("Failed to write bmap");
+
+    console::Term::stdout()
+        .write_line(&format!("bmap written to {}", output.display()))
+        .unwrap();
+}
+
+async fn checksum(img: Box<std::path::Path>, quiet: bool) {
+    let bar = if quiet {
+        indicatif::ProgressBar::hidden()
+    } else {
+        indicatif::ProgressBar::new(0).with_style(
+            indicatif::ProgressStyle::with_template(
+                "{msg:15}  [{wide_bar}] [{bytes}/{total_bytes}]",
+            )
+            .expect("Failed to create progress bar"),
+        )
+    };
+    bar.set_message("Computing");
+
+    let bar_clone = bar.clone();
+    let sha256 = tokio::task::spawn_blocking(move || {
+        bb_helper::history::file_sha256_with_progress(&img, |pos, size| {
+            bar_clone.set_length(size);
+            bar_clone.set_position(pos);
+        })
+    })
+    .await
+    .unwrap()
+    .expect("Failed to compute checksum");
+    bar.finish_and_clear();
+
+    console::Term::stdout()
+        .write_line(&const_hex::encode(sha256))
+        .unwrap();
+}
+
+async fn validate_config(file: Box<std::path::Path>) {
+    let data = tokio::fs::read_to_string(&file)
+        .await
+        .expect("Failed to read catalog");
+    let res = bb_config::validate::validate(&data);
+    let term = console::Term::stdout();
+
+    for d in &res {
+        let line = format!("{}:{d}", file.display());
+        let line = match d.severity {
+            bb_config::validate::Severity::Error => console::style(line).red(),
+            bb_config::validate::Severity::Warning => console::style(line).yellow(),
+        };
+        term.write_line(&line.to_string()).unwrap();
+    }
+
+    let errors = res
+        .iter()
+        .filter(|x| x.severity == bb_config::validate::Severity::Error)
+        .count();
+    term.write_line(&format!("{errors} errors, {} warnings", res.len() - errors))
+        .unwrap();
+
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "bcf_cc1352p7")]
+const fn bcf_slot(slot: cli::BcfSlot) -> bb_flasher::bcf::cc1352p7::Slot {
+    match slot {
+        cli::BcfSlot::A => bb_flasher::bcf::cc1352p7::Slot::A,
+        cli::BcfSlot::B => bb_flasher::bcf::cc1352p7::Slot::B,
+    }
+}
+
+#[cfg(feature = "bcf_cc1352p7")]
+fn bcf_port_config(args: cli::BcfPortArgs) -> bb_flasher::bcf::cc1352p7::PortConfig {
+    let default = bb_flasher::bcf::cc1352p7::PortConfig::default();
+
+    bb_flasher::bcf::cc1352p7::PortConfig {
+        baud_rate: args.baud_rate.unwrap_or(default.baud_rate),
+        flow_control: match args.flow_control {
+            Some(cli::BcfFlowControl::None) => bb_flasher::bcf::cc1352p7::FlowControl::None,
+            Some(cli::BcfFlowControl::Software) => bb_flasher::bcf::cc1352p7::FlowControl::Software,
+            Some(cli::BcfFlowControl::Hardware) => bb_flasher::bcf::cc1352p7::FlowControl::Hardware,
+            None => default.flow_control,
+        },
+        timeout: args
+            .timeout_ms
+            .map(std::time::Duration::from_millis)
+            .unwrap_or(default.timeout),
+    }
+}
+
+#[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+async fn verify(target: cli::VerifyCommands) {
+    let resp = match target {
+        #[cfg(feature = "bcf_cc1352p7")]
+        cli::VerifyCommands::Bcf {
+            img,
+            dst,
+            slot,
+            port,
+        } => {
+            let config = bb_flasher::bcf::cc1352p7::FlashingBcfConfig {
+                verify: true,
+                slot: slot.map(bcf_slot),
+                port: bcf_port_config(port),
+            };
+
+            bb_flasher::bcf::cc1352p7::Flasher::new(LocalImage::new(img), dst.into(), config, None)
+                .verify()
+                .await
+        }
+        #[cfg(feature = "bcf_msp430")]
+        cli::VerifyCommands::Msp430 { img, dst, flashed } => {
+            bb_flasher::bcf::msp430::Flasher::new(LocalImage::new(img), dst.into())
+                .verify(flashed.map(LocalImage::new))
+                .await
+        }
+    };
+    let term = console::Term::stdout();
+
+    if resp.expect("Failed to verify firmware") {
+        term.write_line("Firmware matches").unwrap();
+    } else {
+        term.write_line("Firmware does not match").unwrap();
+        std::process::exit(1);
+    }
+}
+
+#[cfg(any(feature = "bcf_cc1352p7", feature = "bcf_msp430"))]
+#[cfg_attr(not(feature = "bcf_cc1352p7"), allow(unused_variables))]
+async fn probe(target: cli::ProbeTarget, no_filter: bool, port: cli::BcfPortArgs) {
+    let resp: Vec<(String, anyhow::Result<bb_flasher::bcf::DeviceVersion>)> = match target {
+        #[cfg(feature = "bcf_cc1352p7")]
+        cli::ProbeTarget::Bcf => {
+            let config = bcf_port_config(port);
+            let mut ans = Vec::new();
+            for d in bb_flasher::bcf::cc1352p7::Target::destinations(!no_filter).await {
+                ans.push((d.to_string(), d.probe(config).await));
+            }
+            ans
+        }
+        #[cfg(feature = "bcf_msp430")]
+        cli::ProbeTarget::Msp430 => {
+            let mut ans = Vec::new();
+            for d in bb_flasher::bcf::msp430::Target::destinations(!no_filter).await {
+                ans.push((d.to_string(), d.probe().await));
+            }
+            ans
+        }
+    };
+    let term = console::Term::stdout();
+
+    if resp.is_empty() {
+        term.write_line("No devices found").unwrap();
+        return;
+    }
+
+    for (path, version) in resp {
+        term.write_line(&path).unwrap();
+        match version {
+            Ok(v) => {
+                term.write_line(&format!(
+                    "  Bootloader: {}",
+                    v.bootloader.as_deref().unwrap_or("Unknown")
+                ))
+                .unwrap();
+                term.write_line(&format!(
+                    "  Firmware:   {}",
+                    v.firmware.as_deref().unwrap_or("Unknown")
+                ))
+                .unwrap();
+            }
+            Err(e) => term.write_line(&format!("  Error: {e:?}")).unwrap(),
+        }
     }
-    .expect("Filed to flash")
 }
 
 async fn flash_internal(
     target: TargetCommands,
-    chan: Option<futures::channel::mpsc::Sender<DownloadFlashingStatus>>,
+    chan: Option<futures::channel::mpsc::Sender<bb_flasher::ProgressEvent>>,
+    cancel: Option<CancellationToken>,
 ) -> anyhow::Result<()> {
+    let job = bb_flasher::JobId::new();
+
     match target {
         TargetCommands::Sd {
             dst,
-            hostname,
-            timezone,
-            keymap,
-            user_name,
-            user_password,
-            wifi_ssid,
-            wifi_password,
+            sysconf,
             img,
-            ssh_key,
-            usb_enable_dhcp,
             bmap,
+            write_limit,
+            verify,
+            export,
+            #[cfg(feature = "boot_test")]
+            boot_test,
+            #[cfg(feature = "test-util")]
+            dst_file,
+            ..
         } => {
-            let user = user_name.map(|x| (x, user_password.unwrap()));
-            let wifi = wifi_ssid.map(|x| (x, wifi_password.unwrap()));
-
-            let dst = check_macos_device_path(dst);
-
-            let customization = bb_flasher::sd::FlashingSdLinuxConfig::sysconfig(
-                hostname,
-                timezone,
-                keymap,
-                user,
-                wifi,
-                ssh_key,
-                Some(usb_enable_dhcp),
-            );
+            const MIB: std::num::NonZeroU64 = std::num::NonZeroU64::new(1024 * 1024).unwrap();
+
+            let customization = sysconf_config(sysconf).with_provenance(provenance(&img).await);
+
+            if export {
+                return bb_flasher::sd::ExportFlasher::new(
+                    LocalImage::new(img),
+                    dst,
+                    customization,
+                    cancel,
+                )
+                .flash_job(job, chan)
+                .await;
+            }
+
+            #[cfg(feature = "test-util")]
+            let dst = if dst_file {
+                bb_flasher::sd::Target::file(dst)?
+            } else {
+                bb_flasher::sd::Target::try_from(dst)?
+            };
+            #[cfg(not(feature = "test-util"))]
+            let dst = bb_flasher::sd::Target::try_from(dst)?;
+
+            let bmap = bmap.or_else(|| bb_flasher::sd::find_bmap(&img).map(Into::into));
+            let verify = match verify {
+                cli::VerifyMode::None => bb_flasher::sd::Verify::None,
+                cli::VerifyMode::Quick => bb_flasher::sd::Verify::Quick,
+                cli::VerifyMode::Full => bb_flasher::sd::Verify::Full,
+            };
 
-            bb_flasher::sd::Flasher::new(
+            let flasher = bb_flasher::sd::Flasher::new(
                 LocalImage::new(img),
                 bmap.map(LocalStringFile::new),
-                dst.try_into().unwrap(),
-                customization,
-                None,
+                dst,
+                customization.with_verify(verify),
+                cancel,
             )
-            .flash(chan)
-            .await
+            .write_limit(write_limit.map(|x| x.saturating_mul(MIB)));
+
+            #[cfg(feature = "boot_test")]
+            if let Some(port) = boot_test.boot_test_port {
+                let mut flasher =
+                    bb_flasher::BootTest::new(flasher, port).reset(boot_test.boot_reset);
+                if let Some(x) = boot_test.boot_marker {
+                    flasher = flasher.marker(x);
+                }
+                if let Some(x) = boot_test.boot_timeout {
+                    flasher = flasher.timeout(std::time::Duration::from_secs(x));
+                }
+                if let Some(x) = boot_test.boot_baud_rate {
+                    flasher = flasher.baud_rate(x);
+                }
+
+                return flasher.flash_job(job, chan).await;
+            }
+
+            flasher.flash_job(job, chan).await
         }
         #[cfg(feature = "bcf_cc1352p7")]
         TargetCommands::Bcf {
             img,
             dst,
             no_verify,
+            slot,
+            port,
         } => {
+            let config = bb_flasher::bcf::cc1352p7::FlashingBcfConfig {
+                verify: !no_verify,
+                slot: slot.map(bcf_slot),
+                port: bcf_port_config(port),
+            };
+
             bb_flasher::bcf::cc1352p7::Flasher::new(
                 LocalImage::new(img),
                 dst.into(),
-                !no_verify,
-                None,
+                config,
+                cancel,
             )
-            .flash(chan)
+            .flash_job(job, chan)
             .await
         }
         #[cfg(feature = "bcf_msp430")]
         TargetCommands::Msp430 { img, dst } => {
             bb_flasher::bcf::msp430::Flasher::new(LocalImage::new(img), dst.into())
-                .flash(chan)
+                .flash_job(job, chan)
                 .await
         }
         #[cfg(feature = "pb2_mspm0")]
         TargetCommands::Pb2Mspm0 { no_eeprom, img } => {
             bb_flasher::pb2::mspm0::Flasher::new(LocalImage::new(img), !no_eeprom)
-                .flash(chan)
+                .flash_job(job, chan)
                 .await
         }
         #[cfg(feature = "dfu")]
@@ -191,9 +1175,9 @@ async fn flash_internal(
                 })
                 .collect();
 
-            bb_flasher::dfu::Flasher::from_identifier(img_list, &identifier, None)
+            bb_flasher::dfu::Flasher::from_identifier(img_list, &identifier, cancel)
                 .unwrap()
-                .flash(chan)
+                .flash_job(job, chan)
                 .await
         }
     }
@@ -243,11 +1227,208 @@ fn check_macos_device_path(dst: PathBuf) -> PathBuf {
     dst
 }
 
-async fn format(dst: PathBuf, quite: bool) {
+/// Offer to use the whole disk if `dst` is a partition. Defaults to no, since the whole disk will
+/// be overwritten.
+fn check_partition_path(dst: PathBuf) -> PathBuf {
+    let Some(disk) = bb_flasher::sd::parent_disk(&dst).filter(|x| x.exists()) else {
+        return dst;
+    };
+
+    let term = console::Term::stderr();
+    let _ = term.write_line(&format!(
+        "{} {} is a partition. SD Cards can only be flashed as a whole disk: {}\n",
+        console::style("Warning:").yellow().bold(),
+        dst.display(),
+        disk.display()
+    ));
+
+    let _ = term.write_str(&format!(
+        "Do you want to use {} instead? All data on it will be lost. [y/N] ",
+        console::style(disk.display()).bold()
+    ));
+
+    let mut input = String::new();
+    std::io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+
+    let input = input.trim().to_lowercase();
+    if input == "y" || input == "yes" {
+        let _ = term.write_line(&format!("Switching to {}\n", disk.display()));
+        return disk;
+    }
+
+    dst
+}
+
+/// Name, path and size of an SD Card for confirmation prompts. Falls back to the path if the SD
+/// Card is not found.
+fn describe_destination(dst: &std::path::Path) -> String {
+    match bb_flasher::sd::Target::try_from(dst.to_path_buf()) {
+        Ok(x) => format!("{} ({}, {})", x.to_string().trim(), dst.display(), x.size()),
+        Err(_) => dst.display().to_string(),
+    }
+}
+
+/// Ask before erasing all data on `dsts`.
+fn confirm_overwrite(dsts: &[&std::path::Path], yes: bool) {
+    let dsts: Vec<_> = dsts.iter().map(|x| describe_destination(x)).collect();
+    confirm(
+        &format!("All data on {} will be lost.", dsts.join(", ")),
+        yes,
+    );
+}
+
+/// Ask for confirmation before writing to a destination. Exits if the user declines, or if there
+/// is no terminal to ask on and `--yes` was not given.
+fn confirm(msg: &str, yes: bool) {
+    use std::io::IsTerminal;
+
+    if yes {
+        return;
+    }
+
+    let term = console::Term::stderr();
+    if !std::io::stdin().is_terminal() {
+        let _ = term.write_line(&format!(
+            "{} {msg} Pass --yes to continue without confirmation.",
+            console::style("Error:").red().bold(),
+        ));
+        std::process::exit(1);
+    }
+
+    let _ = term.write_str(&format!(
+        "{} {msg} Continue? [y/N] ",
+        console::style("Warning:").yellow().bold(),
+    ));
+
+    let mut input = String::new();
+    std::io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+
+    let input = input.trim().to_lowercase();
+    if input != "y" && input != "yes" {
+        let _ = term.write_line("Aborted");
+        std::process::exit(1);
+    }
+}
+
+fn sysconf_config(args: SysconfArgs) -> bb_flasher::sd::FlashingSdLinuxConfig {
+    let user = args.user_name.map(|x| (x, args.user_password.unwrap()));
+    let wifi = args.wifi_ssid.map(|x| (x, args.wifi_password.unwrap()));
+
+    let timezone = match args.timezone {
+        Some(x) if x.eq_ignore_ascii_case("auto") => {
+            let tz = host_timezone();
+            if tz.is_none() {
+                eprintln!("Failed to detect timezone of this machine. Leaving it unchanged.");
+            }
+            tz.map(Into::into)
+        }
+        x => x,
+    };
+
+    bb_flasher::sd::FlashingSdLinuxConfig::sysconfig(
+        args.hostname,
+        timezone,
+        args.keymap,
+        user,
+        wifi,
+        args.ssh_key,
+        Some(args.usb_enable_dhcp),
+    )
+    .with_labels(args.volume_label, args.rootfs_label)
+    .with_files(args.copy)
+    .with_cmdline_append(args.cmdline_append)
+    .with_serial_console(args.serial_console)
+    .with_packages(args.preinstall)
+    .with_network(args.http_proxy, args.https_proxy, args.apt_mirror)
+}
+
+/// IANA timezone of this machine. Checks `TZ`, `/etc/timezone` and the `/etc/localtime` link.
+fn host_timezone() -> Option<String> {
+    if let Ok(x) = std::env::var("TZ") {
+        let x = x.trim_start_matches(':');
+        if x.contains('/') && !x.starts_with('/') {
+            return Some(x.to_string());
+        }
+    }
+
+    if let Ok(x) = std::fs::read_to_string("/etc/timezone")
+        && !x.trim().is_empty()
+    {
+        return Some(x.trim().to_string());
+    }
+
+    let target = std::fs::read_link("/etc/localtime").ok()?;
+    let (_, tz) = target.to_str()?.split_once("zoneinfo/")?;
+    Some(tz.to_string())
+}
+
+async fn customize(
+    dst: PathBuf,
+    mut sysconf: SysconfArgs,
+    qr_code: Option<PathBuf>,
+    yes: bool,
+    quite: bool,
+) {
+    let (tx, _) = futures::channel::mpsc::channel(20);
+    let term = console::Term::stdout();
+
+    let dst = check_macos_device_path(check_partition_path(dst));
+    confirm(
+        &format!(
+            "Customization on {} will be replaced.",
+            describe_destination(&dst)
+        ),
+        yes,
+    );
+    let hostname = expand_hostname(&mut sysconf, Some(&dst));
+    let credentials = device_credentials(&sysconf);
+
+    bb_flasher::sd::CustomizeFlasher::new(dst.try_into().unwrap(), sysconf_config(sysconf), None)
+        .flash(Some(tx))
+        .await
+        .unwrap();
+
+    if !quite {
+        term.write_line("Customization successful").unwrap();
+        if let Some(x) = hostname {
+            term.write_line(&format!("Hostname: {x}")).unwrap();
+        }
+    }
+
+    if let Some(path) = qr_code {
+        write_qr_code(&path, &credentials);
+    }
+}
+
+async fn format(
+    dst: PathBuf,
+    label: Option<Box<str>>,
+    scheme: cli::PartitionScheme,
+    full: bool,
+    yes: bool,
+    quite: bool,
+) {
     let (tx, _) = futures::channel::mpsc::channel(20);
     let term = console::Term::stdout();
 
-    let config = bb_flasher::sd::FormatFlasher::new(dst.try_into().unwrap());
+    let options = bb_flasher::sd::FormatOptions {
+        label: label.map(String::from),
+        full,
+        scheme: match scheme {
+            cli::PartitionScheme::Mbr => bb_flasher::sd::PartitionScheme::Mbr,
+            cli::PartitionScheme::Gpt => bb_flasher::sd::PartitionScheme::Gpt,
+            cli::PartitionScheme::None => bb_flasher::sd::PartitionScheme::None,
+        },
+        ..Default::default()
+    };
+    let dst = check_partition_path(dst);
+    confirm_overwrite(&[dst.as_path()], yes);
+    let dst = dst.try_into().unwrap();
+    let config = bb_flasher::sd::FormatFlasher::new(dst).with_options(options);
     config.flash(Some(tx)).await.unwrap();
 
     if !quite {
@@ -255,6 +1436,56 @@ async fn format(dst: PathBuf, quite: bool) {
     }
 }
 
+async fn identify(target: cli::IdentifyTarget, dst: String, duration: u64) {
+    let duration = std::time::Duration::from_secs(duration);
+
+    let resp = match target {
+        cli::IdentifyTarget::Sd => {
+            let dst = bb_flasher::sd::Target::try_from(check_partition_path(PathBuf::from(dst)))
+                .expect("Failed to find destination");
+            dst.identify(duration).await
+        }
+        #[cfg(feature = "bcf_cc1352p7")]
+        cli::IdentifyTarget::Bcf => {
+            bb_flasher::bcf::cc1352p7::Target::from(dst)
+                .identify(duration)
+                .await
+        }
+    };
+
+    resp.expect("Failed to identify destination");
+}
+
+async fn discover(hostname: String, timeout: u64, user: Option<String>, no_frills: bool) {
+    let term = console::Term::stdout();
+    let name = bb_helper::mdns::fqdn(&hostname);
+
+    if !no_frills {
+        term.write_line(&format!("Waiting for {name}")).unwrap();
+    }
+
+    let addrs = bb_helper::mdns::resolve(&hostname, std::time::Duration::from_secs(timeout))
+        .await
+        .expect("Failed to query mDNS");
+
+    if addrs.is_empty() {
+        eprintln!("{name} did not respond within {timeout} seconds");
+        std::process::exit(1);
+    }
+
+    for x in addrs {
+        term.write_line(&x.to_string()).unwrap();
+    }
+
+    if !no_frills {
+        term.write_line(&format!(
+            "Log in using: {}",
+            bb_helper::mdns::ssh_command(&hostname, user.as_deref())
+        ))
+        .unwrap();
+    }
+}
+
 async fn no_frills_list_destinations<T: BBFlasherTarget>(no_filter: bool) {
     let term = console::Term::stdout();
     let dsts = T::destinations(!no_filter).await;
@@ -264,7 +1495,121 @@ async fn no_frills_list_destinations<T: BBFlasherTarget>(no_filter: bool) {
     }
 }
 
-async fn list_destinations(target: DestinationsTarget, no_frills: bool, no_filter: bool) {
+/// Destinations of flashers which only have a name and a path.
+async fn destination_rows<T: BBFlasherTarget>(no_filter: bool) -> Vec<Vec<serde_json::Value>> {
+    T::destinations(!no_filter)
+        .await
+        .into_iter()
+        .map(|x| vec![x.to_string().trim().into(), x.identifier().into()])
+        .collect()
+}
+
+/// Print destinations as JSON or CSV. Includes everything known about the destinations, unlike
+/// the table which only shows what fits on a terminal.
+async fn print_destinations(target: DestinationsTarget, output: OutputFormat, no_filter: bool) {
+    let (columns, rows): (&[&str], _) = match target {
+        DestinationsTarget::Sd => {
+            let mut dsts: Vec<_> = bb_flasher::sd::Target::destinations(!no_filter)
+                .await
+                .into_iter()
+                .collect();
+            bb_flasher::sd::Target::sort(&mut dsts);
+
+            let rows = dsts
+                .into_iter()
+                .map(|x| {
+                    let card = x.card();
+                    vec![
+                        x.to_string().trim().into(),
+                        x.identifier().into(),
+                        x.size().bytes().into(),
+                        x.is_read_only().into(),
+                        x.is_removable().into(),
+                        x.location().map(|l| l.to_string()).into(),
+                        x.reader().into(),
+                        card.and_then(|c| c.manufacturer()).into(),
+                        card.map(|c| c.name.clone()).into(),
+                        card.map(|c| c.serial).into(),
+                    ]
+                })
+                .collect();
+
+            (
+                &[
+                    "name",
+                    "path",
+                    "size",
+                    "read_only",
+                    "removable",
+                    "usb_port",
+                    "reader",
+                    "card_manufacturer",
+                    "card_name",
+                    "card_serial",
+                ],
+                rows,
+            )
+        }
+        #[cfg(feature = "dfu")]
+        DestinationsTarget::Dfu => {
+            let rows = bb_flasher::dfu::Target::destinations(!no_filter)
+                .await
+                .into_iter()
+                .map(|x| {
+                    vec![
+                        x.to_string().trim().into(),
+                        x.identifier().into(),
+                        x.bus_number().into(),
+                        x.port_num().into(),
+                        x.vendor_id().into(),
+                        x.product_id().into(),
+                    ]
+                })
+                .collect();
+
+            (
+                &[
+                    "name",
+                    "path",
+                    "bus_number",
+                    "port_num",
+                    "vendor_id",
+                    "product_id",
+                ],
+                rows,
+            )
+        }
+        #[cfg(feature = "bcf_cc1352p7")]
+        DestinationsTarget::Bcf => (
+            &["name", "path"],
+            destination_rows::<bb_flasher::bcf::cc1352p7::Target>(no_filter).await,
+        ),
+        #[cfg(feature = "bcf_msp430")]
+        DestinationsTarget::Msp430 => (
+            &["name", "path"],
+            destination_rows::<bb_flasher::bcf::msp430::Target>(no_filter).await,
+        ),
+        #[cfg(feature = "pb2_mspm0")]
+        DestinationsTarget::Pb2Mspm0 => (
+            &["name", "path"],
+            destination_rows::<bb_flasher::pb2::mspm0::Target>(no_filter).await,
+        ),
+    };
+
+    output::print(output, columns, rows);
+}
+
+async fn list_destinations(
+    target: DestinationsTarget,
+    no_frills: bool,
+    output: OutputFormat,
+    no_filter: bool,
+) {
+    if !matches!(output, OutputFormat::Table) {
+        print_destinations(target, output, no_filter).await;
+        return;
+    }
+
     if no_frills {
         match target {
             DestinationsTarget::Sd => {
@@ -296,17 +1641,26 @@ async fn list_destinations(target: DestinationsTarget, no_frills: bool, no_filte
         DestinationsTarget::Sd => {
             const NAME_HEADER: &str = "SD Card";
             const PATH_HEADER: &str = "Path";
-            const SIZE_HEADER: &str = "Size (in G)";
-            const BYTES_IN_GB: u64 = 1024 * 1024 * 1024;
+            const SIZE_HEADER: &str = "Size";
+            const PORT_HEADER: &str = "USB Port";
+            const READER_HEADER: &str = "Card Reader";
 
-            let dsts_str: Vec<_> = bb_flasher::sd::Target::destinations(!no_filter)
+            // Sort by port so that identical card readers can be told apart
+            let mut dsts: Vec<_> = bb_flasher::sd::Target::destinations(!no_filter)
                 .await
+                .into_iter()
+                .collect();
+            bb_flasher::sd::Target::sort(&mut dsts);
+
+            let dsts_str: Vec<_> = dsts
                 .into_iter()
                 .map(|x| {
                     (
                         x.to_string().trim().to_string(),
                         x.identifier().to_string(),
-                        (x.size() / BYTES_IN_GB).to_string(),
+                        x.size().to_string(),
+                        x.location().map(|l| l.to_string()).unwrap_or_default(),
+                        x.reader().unwrap_or_default().to_string(),
                     )
                 })
                 .collect();
@@ -329,21 +1683,42 @@ async fn list_destinations(target: DestinationsTarget, no_frills: bool, no_filte
                 .chain([SIZE_HEADER.len()])
                 .max()
                 .unwrap();
+            let max_port_len = dsts_str
+                .iter()
+                .map(|x| x.3.len())
+                .chain([PORT_HEADER.len()])
+                .max()
+                .unwrap();
+            let max_reader_len = dsts_str
+                .iter()
+                .map(|x| x.4.len())
+                .chain([READER_HEADER.len()])
+                .max()
+                .unwrap();
 
             let table_border = format!(
-                "+-{}-+-{}-+-{}-+",
+                "+-{}-+-{}-+-{}-+-{}-+-{}-+",
                 std::iter::repeat_n('-', max_name_len).collect::<String>(),
                 std::iter::repeat_n('-', max_path_len).collect::<String>(),
-                std::iter::repeat_n('-', SIZE_HEADER.len()).collect::<String>(),
+                std::iter::repeat_n('-', max_size_len).collect::<String>(),
+                std::iter::repeat_n('-', max_port_len).collect::<String>(),
+                std::iter::repeat_n('-', max_reader_len).collect::<String>(),
             );
 
             term.write_line(&table_border).unwrap();
 
             term.write_line(&format!(
-                "| {} | {} | {: <6} |",
+                "| {} | {} | {} | {} | {} |",
                 console::pad_str(NAME_HEADER, max_name_len, console::Alignment::Left, None),
                 console::pad_str(PATH_HEADER, max_path_len, console::Alignment::Left, None),
                 console::pad_str(SIZE_HEADER, max_size_len, console::Alignment::Left, None),
+                console::pad_str(PORT_HEADER, max_port_len, console::Alignment::Left, None),
+                console::pad_str(
+                    READER_HEADER,
+                    max_reader_len,
+                    console::Alignment::Left,
+                    None
+                ),
             ))
             .unwrap();
 
@@ -351,10 +1726,12 @@ async fn list_destinations(target: DestinationsTarget, no_frills: bool, no_filte
 
             for d in dsts_str {
                 term.write_line(&format!(
-                    "| {} | {} | {} |",
+                    "| {} | {} | {} | {} | {} |",
                     console::pad_str(&d.0, max_name_len, console::Alignment::Left, None),
                     console::pad_str(&d.1, max_path_len, console::Alignment::Left, None),
-                    console::pad_str(&d.2, max_size_len, console::Alignment::Right, None)
+                    console::pad_str(&d.2, max_size_len, console::Alignment::Right, None),
+                    console::pad_str(&d.3, max_port_len, console::Alignment::Left, None),
+                    console::pad_str(&d.4, max_reader_len, console::Alignment::Left, None),
                 ))
                 .unwrap();
             }
@@ -460,10 +1837,15 @@ async fn list_destinations(target: DestinationsTarget, no_frills: bool, no_filte
 const fn progress_msg(status: DownloadFlashingStatus) -> &'static str {
     match status {
         DownloadFlashingStatus::Preparing => "Preparing  ",
+        DownloadFlashingStatus::CheckingImage => "Checking Image",
         DownloadFlashingStatus::DownloadingProgress(_) => "Downloading",
+        DownloadFlashingStatus::DecompressingProgress(_) => "Decompressing",
         DownloadFlashingStatus::FlashingProgress(_) => "Flashing",
         DownloadFlashingStatus::Verifying => "Verifying",
-        DownloadFlashingStatus::Customizing => "Customizing",
+        DownloadFlashingStatus::Customizing(_) => "Customizing",
+        DownloadFlashingStatus::BootTesting => "Waiting for Boot",
+        DownloadFlashingStatus::BootVerified => "Boot Verified",
+        DownloadFlashingStatus::BootFailed => "Boot Failed",
     }
 }
 
@@ -471,6 +1853,102 @@ fn stage_msg(status: DownloadFlashingStatus, stage: usize) -> String {
     format!("[{stage}] {}", progress_msg(status))
 }
 
+/// Log to stderr at the level selected by `--verbose`, keeping stdout clean for output meant for
+/// scripts. `RUST_LOG` overrides the level. Debug logs are additionally written to `log_file`.
+fn init_logging(verbose: u8, log_file: Option<&std::path::Path>) {
+    use tracing_subscriber::{
+        Layer, filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt,
+    };
+
+    let level = match verbose {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+
+    let file_layer = log_file.map(|path| {
+        let file = std::fs::File::create(path).expect("Failed to create log file");
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(file)
+            .with_filter(LevelFilter::DEBUG)
+    });
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .without_time()
+                .with_target(verbose > 1)
+                .with_filter(
+                    tracing_subscriber::EnvFilter::builder()
+                        .with_default_directive(level.into())
+                        .from_env_lossy(),
+                ),
+        )
+        .with(file_layer)
+        .try_init()
+        .expect("Failed to register tracing_subscriber");
+}
+
+fn print_version(verbose: bool) {
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+
+    if !verbose {
+        return;
+    }
+
+    const FEATURES: &[(&str, &str, bool)] = &[
+        (
+            "bcf_cc1352p7",
+            "BeagleConnect Freedom",
+            cfg!(feature = "bcf_cc1352p7"),
+        ),
+        (
+            "bcf_msp430",
+            "BeagleConnect Freedom MSP430",
+            cfg!(feature = "bcf_msp430"),
+        ),
+        (
+            "pb2_mspm0",
+            "PocketBeagle 2 MSPM0",
+            cfg!(feature = "pb2_mspm0"),
+        ),
+        ("dfu", "USB DFU", cfg!(feature = "dfu")),
+    ];
+
+    let features: Vec<&str> = FEATURES
+        .iter()
+        .filter(|(_, _, enabled)| *enabled)
+        .map(|(name, _, _)| *name)
+        .collect();
+    let flashers: Vec<&str> = std::iter::once("SD Card")
+        .chain(
+            FEATURES
+                .iter()
+                .filter(|(_, _, enabled)| *enabled)
+                .map(|(_, flasher, _)| *flasher),
+        )
+        .collect();
+
+    println!("commit: {}", env!("GIT_COMMIT"));
+    println!(
+        "target: {}-{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS
+    );
+    println!(
+        "features: {}",
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        }
+    );
+    println!("flashers: {}", flashers.join(", "));
+}
+
 fn generate_completion(target: clap_complete::Shell) {
     let mut cmd = Opt::command();
     const BIN_NAME: &str = env!("CARGO_PKG_NAME");
@@ -0,0 +1,59 @@
+//! Machine readable output of read-only commands. Each command describes its output as named
+//! columns and rows of values, which are printed as an array of JSON objects or as CSV with a
+//! header row. Tables are printed by the commands themselves.
+
+use serde_json::Value;
+
+use crate::cli::OutputFormat;
+
+/// Print rows as JSON or CSV. Tables are not handled here.
+pub(crate) fn print(format: OutputFormat, columns: &[&str], rows: Vec<Vec<Value>>) {
+    match format {
+        OutputFormat::Json => println!("{:#}", json(columns, rows)),
+        OutputFormat::Csv => print!("{}", csv(columns, rows)),
+        OutputFormat::Table => unreachable!("Tables are printed by each command"),
+    }
+}
+
+/// Array of objects with `columns` as keys.
+pub(crate) fn json(columns: &[&str], rows: Vec<Vec<Value>>) -> Value {
+    rows.into_iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|x| x.to_string())
+                .zip(row)
+                .collect::<serde_json::Map<_, _>>()
+        })
+        .map(Value::Object)
+        .collect()
+}
+
+fn csv(columns: &[&str], rows: Vec<Vec<Value>>) -> String {
+    let mut data = columns.join(",");
+    data.push('\n');
+
+    for row in rows {
+        data.push_str(
+            &row.iter()
+                .map(|x| match x {
+                    Value::Null => String::new(),
+                    Value::String(s) => csv_field(s),
+                    _ => csv_field(&x.to_string()),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        data.push('\n');
+    }
+
+    data
+}
+
+pub(crate) fn csv_field(x: &str) -> String {
+    if x.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", x.replace('"', "\"\""))
+    } else {
+        x.to_string()
+    }
+}
@@ -0,0 +1,205 @@
+//! Download images for a board ahead of time. Images are stored in the cache shared with the GUI,
+//! which allows flashing without network access later.
+
+use anyhow::Context;
+use bb_config::config::{Config, OsImage, OsListItem};
+use futures::StreamExt;
+
+const DEFAULT_CONFIG: &[u8] = include_bytes!("../../config.json");
+
+pub(crate) async fn prefetch(
+    args: crate::cli::DownloaderArgs,
+    board: String,
+    all: bool,
+    images: Vec<String>,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let term = console::Term::stdout();
+    let downloader = crate::downloader(args)?;
+
+    let config = fetch_config(&downloader).await?;
+
+    let device = config
+        .imager
+        .devices
+        .iter()
+        .find(|x| x.name.eq_ignore_ascii_case(&board))
+        .with_context(|| format!("Unknown board: {board}"))?;
+
+    // Images in sub lists are only needed when requested explicitly.
+    let os_images = board_images(
+        &downloader,
+        config.os_list,
+        &device.tags,
+        all || !images.is_empty(),
+    )
+    .await;
+    let os_images: Vec<_> = if images.is_empty() {
+        os_images
+    } else {
+        os_images
+            .into_iter()
+            .filter(|x| images.iter().any(|y| y.eq_ignore_ascii_case(&x.name)))
+            .collect()
+    };
+
+    if os_images.is_empty() {
+        anyhow::bail!("No images found for {}", device.name);
+    }
+
+    let today = chrono::Local::now().date_naive();
+    for img in os_images {
+        if let Some(x) = img.warning(today) {
+            eprintln!("Warning: {}: {x}", img.name);
+        }
+
+        if !quiet {
+            term.write_line(&format!("Downloading {}", img.name))?;
+        }
+
+        download_image(&downloader, &img, quiet)
+            .await
+            .with_context(|| format!("Failed to download {}", img.name))?;
+    }
+
+    if !quiet {
+        term.write_line("Prefetch successful")?;
+    }
+
+    Ok(())
+}
+
+/// Fetch the same catalog as GUI.
+pub(crate) async fn fetch_config(downloader: &bb_downloader::Downloader) -> anyhow::Result<Config> {
+    let mut config: Config = serde_json::from_slice(DEFAULT_CONFIG)?;
+
+    let remote_configs: Vec<_> = config.imager.remote_configs.iter().cloned().collect();
+    for url in remote_configs {
+        match downloader
+            .download_json_no_cache::<Config, _>(url.clone())
+            .await
+        {
+            Ok(mut x) => {
+                x.strip_header_variables();
+                config.extend([x])
+            }
+            Err(e) => eprintln!("Failed to fetch config {url}: {e}"),
+        }
+    }
+
+    if let Some(dir) = crate::sideload_dir() {
+        for (path, x) in bb_config::sideload::installed(&dir) {
+            match x {
+                Ok(x) => config.extend([x]),
+                Err(e) => eprintln!("Failed to load imported board {}: {e}", path.display()),
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+pub(crate) fn imager_version() -> semver::Version {
+    semver::Version::parse(env!("CARGO_PKG_VERSION")).unwrap()
+}
+
+/// Collect all images for a board. Sub lists (including remote ones) are only traversed if
+/// `recursive` is true.
+async fn board_images(
+    downloader: &bb_downloader::Downloader,
+    os_list: Vec<OsListItem>,
+    tags: &std::collections::HashSet<String>,
+    recursive: bool,
+) -> Vec<OsImage> {
+    let mut res = Vec::new();
+    let mut stack = vec![os_list];
+
+    while let Some(items) = stack.pop() {
+        for item in items {
+            if !item.has_board_image(tags) {
+                continue;
+            }
+
+            match item {
+                OsListItem::Image(x) if !x.supports_imager(&imager_version()) => {
+                    eprintln!(
+                        "Skipping {}: requires imager {} or newer",
+                        x.name,
+                        x.requires_imager_version.as_ref().unwrap()
+                    );
+                }
+                OsListItem::Image(x) => res.push(x),
+                OsListItem::SubList(x) if recursive => stack.push(x.subitems),
+                OsListItem::RemoteSubList(x) if recursive => {
+                    match downloader
+                        .download_json_no_cache::<Vec<OsListItem>, _>(x.subitems_url.clone())
+                        .await
+                    {
+                        Ok(mut y) => {
+                            y.iter_mut().for_each(OsListItem::strip_header_variables);
+                            stack.push(y)
+                        }
+                        Err(e) => eprintln!("Failed to fetch {}: {e}", x.subitems_url),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    res
+}
+
+async fn download_image(
+    downloader: &bb_downloader::Downloader,
+    img: &OsImage,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let downloader =
+        &downloader.with_headers(&img.url, img.resolved_headers(bb_config::secrets::lookup))?;
+
+    let progress_task = if quiet {
+        None
+    } else {
+        let (tx, mut rx) = futures::channel::mpsc::channel(20);
+        let bar = indicatif::ProgressBar::new(100);
+        bar.set_style(
+            indicatif::ProgressStyle::with_template("[{wide_bar}] [{percent:3} %]")
+                .expect("Failed to create progress bar"),
+        );
+
+        let task = tokio::spawn(async move {
+            while let Some(p) = rx.next().await {
+                bar.set_position((p * 100.0) as u64);
+            }
+            bar.finish();
+        });
+
+        Some((tx, task))
+    };
+
+    let url = match &img.metalink {
+        Some(x) => {
+            downloader
+                .resolve_metalink(x.clone(), img.url.clone(), img.image_download_sha256)
+                .await
+        }
+        None => img.url.clone(),
+    };
+
+    let (chan, task) = progress_task.unzip();
+    let res = downloader
+        .download_with_sha(url, img.image_download_sha256, chan)
+        .await;
+
+    if let Some(t) = task {
+        let _ = t.await;
+    }
+    res?;
+
+    if let Some(bmap) = &img.bmap {
+        downloader.download(bmap.clone(), None).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,394 @@
+//! Flash an image to multiple SD Cards, with customization for each card read from a CSV manifest.
+//! The manifest is validated completely before the first card is flashed, so a typo in the last
+//! row does not leave a classroom half provisioned.
+
+use anyhow::Context;
+use futures::StreamExt;
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::{SysconfArgs, TargetCommands};
+use crate::output::csv_field;
+
+const DESTINATION_COLUMNS: &[&str] = &["destination", "usb_port"];
+const CUSTOMIZATION_COLUMNS: &[&str] = &[
+    "hostname",
+    "timezone",
+    "keymap",
+    "user_name",
+    "user_password",
+    "wifi_ssid",
+    "wifi_password",
+    "ssh_key",
+    "volume_label",
+    "rootfs_label",
+];
+const RESULTS_HEADER: &[&str] = &[
+    "line",
+    "destination",
+    "hostname",
+    "status",
+    "image_sha256",
+    "error",
+];
+
+/// Single destination in the manifest.
+struct Row {
+    /// Line in the manifest. Used in diagnostics and results.
+    line: usize,
+    dst: PathBuf,
+    sysconf: SysconfArgs,
+}
+
+/// Outcome of flashing a single destination.
+struct Outcome {
+    line: usize,
+    dst: PathBuf,
+    hostname: Option<Box<str>>,
+    res: anyhow::Result<()>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn provision(
+    img: Box<Path>,
+    manifest: PathBuf,
+    results: Option<PathBuf>,
+    parallel: NonZeroUsize,
+    sysconf: SysconfArgs,
+    no_history: bool,
+    yes: bool,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let term = console::Term::stdout();
+
+    let data = std::fs::read_to_string(&manifest)
+        .with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let rows = parse_manifest(&data, &sysconf).await?;
+    let total = rows.len();
+
+    let dsts: Vec<_> = rows.iter().map(|x| x.dst.as_path()).collect();
+    crate::confirm_overwrite(&dsts, yes);
+
+    if !quiet {
+        term.write_line(&format!(
+            "Provisioning {total} destinations, {parallel} at a time"
+        ))?;
+    }
+
+    let img_sha256 = crate::image_sha256(img.to_path_buf())
+        .await
+        .context("Failed to compute image checksum")?;
+
+    let cancel = crate::cancel_on_ctrl_c();
+    let mut outcomes: Vec<Outcome> = futures::stream::iter(rows)
+        .map(|row| flash_row(img.clone(), row, no_history, cancel.clone()))
+        .buffer_unordered(parallel.get())
+        .inspect(|x| {
+            if quiet {
+                return;
+            }
+            let msg = match &x.res {
+                Ok(()) => format!("Line {}: {} flashed", x.line, x.dst.display()),
+                Err(e) => format!("Line {}: {} failed: {e:#}", x.line, x.dst.display()),
+            };
+            let _ = term.write_line(&msg);
+        })
+        .collect()
+        .await;
+    outcomes.sort_by_key(|x| x.line);
+
+    let results = results.unwrap_or_else(|| manifest.with_extension("results.csv"));
+    write_results(&results, &outcomes, &img_sha256)
+        .with_context(|| format!("Failed to write {}", results.display()))?;
+
+    let failed = outcomes.iter().filter(|x| x.res.is_err()).count();
+    if !quiet {
+        term.write_line(&format!("Results written to {}", results.display()))?;
+    }
+    if cancel.is_cancelled() {
+        std::process::exit(crate::EXIT_CANCELLED);
+    }
+    if failed != 0 {
+        anyhow::bail!("{failed} of {total} destinations failed");
+    }
+
+    Ok(())
+}
+
+async fn flash_row(
+    img: Box<Path>,
+    row: Row,
+    no_history: bool,
+    cancel: CancellationToken,
+) -> Outcome {
+    let Row {
+        line,
+        dst,
+        mut sysconf,
+    } = row;
+
+    // Do not touch SD Cards which were not started before cancelling
+    if cancel.is_cancelled() {
+        return Outcome {
+            line,
+            dst,
+            hostname: None,
+            res: Err(anyhow::anyhow!("Cancelled before starting")),
+        };
+    }
+
+    // Templates are expanded in place, so history records the generated hostname
+    crate::expand_hostname(&mut sysconf, Some(&dst));
+    let hostname = sysconf.hostname.clone();
+    let target = TargetCommands::Sd {
+        img,
+        dst: dst.clone(),
+        sysconf,
+        bmap: None,
+        write_limit: None,
+        verify: Default::default(),
+        export: false,
+        qr_code: None,
+        #[cfg(feature = "boot_test")]
+        boot_test: Default::default(),
+        #[cfg(feature = "test-util")]
+        dst_file: false,
+    };
+
+    let entry = (!no_history).then(|| crate::history_entry(&target));
+    let res = crate::flash_internal(target, None, Some(cancel)).await;
+
+    if res.is_ok()
+        && let Some((entry, img)) = entry
+    {
+        crate::record_history(entry, img).await;
+    }
+
+    Outcome {
+        line,
+        dst,
+        hostname,
+        res,
+    }
+}
+
+/// Parse and validate the manifest. All problems are reported together.
+async fn parse_manifest(data: &str, base: &SysconfArgs) -> anyhow::Result<Vec<Row>> {
+    let mut records = parse_csv(data)?.into_iter();
+    let (_, header) = records.next().context("Manifest is empty")?;
+    let header: Vec<&str> = header.iter().map(|x| x.trim()).collect();
+
+    if let Some(x) = header
+        .iter()
+        .find(|x| !DESTINATION_COLUMNS.contains(*x) && !CUSTOMIZATION_COLUMNS.contains(*x))
+    {
+        anyhow::bail!(
+            "Unsupported column {x}. Supported columns: {}, {}",
+            DESTINATION_COLUMNS.join(", "),
+            CUSTOMIZATION_COLUMNS.join(", ")
+        );
+    }
+    if !header.iter().any(|x| DESTINATION_COLUMNS.contains(x)) {
+        anyhow::bail!("Manifest requires a destination or usb_port column");
+    }
+
+    // Destinations are resolved up front, since cards cannot be swapped once flashing starts
+    let dsts: Vec<_> = bb_flasher::sd::Target::destinations(false)
+        .await
+        .into_iter()
+        .collect();
+    let ports: HashMap<String, PathBuf> = dsts
+        .iter()
+        .filter_map(|x| Some((x.location()?.to_string(), x.path().to_path_buf())))
+        .collect();
+
+    let mut rows: Vec<Row> = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, record) in records {
+        if record.len() != header.len() {
+            errors.push(format!(
+                "line {line}: expected {} values, found {}",
+                header.len(),
+                record.len()
+            ));
+            continue;
+        }
+
+        let values: HashMap<&str, &str> = header
+            .iter()
+            .copied()
+            .zip(record.iter().map(String::as_str))
+            .filter(|(_, v)| !v.is_empty())
+            .collect();
+
+        let dst = match (values.get("destination"), values.get("usb_port")) {
+            (Some(x), _) if !dsts.iter().any(|d| d.path() == Path::new(x)) => {
+                Err(format!("SD Card {x} not found"))
+            }
+            (Some(x), _) => Ok(PathBuf::from(x)),
+            (None, Some(x)) => ports
+                .get(*x)
+                .cloned()
+                .ok_or_else(|| format!("No SD Card found in USB port {x}")),
+            (None, None) => Err("destination or usb_port is required".to_string()),
+        };
+        let dst = match dst {
+            Ok(x) if rows.iter().any(|r| r.dst == x) => {
+                errors.push(format!("line {line}: {} is used twice", x.display()));
+                continue;
+            }
+            Ok(x) => x,
+            Err(e) => {
+                errors.push(format!("line {line}: {e}"));
+                continue;
+            }
+        };
+
+        let sysconf = row_sysconf(base.clone(), &values);
+        if let Err(e) = validate_sysconf(&sysconf) {
+            errors.push(format!("line {line}: {e}"));
+            continue;
+        }
+
+        rows.push(Row { line, dst, sysconf });
+    }
+
+    if !errors.is_empty() {
+        anyhow::bail!("Invalid manifest:\n{}", errors.join("\n"));
+    }
+    if rows.is_empty() {
+        anyhow::bail!("Manifest does not contain any destinations");
+    }
+
+    Ok(rows)
+}
+
+/// Merge values of a row over the customization given as arguments.
+fn row_sysconf(mut sysconf: SysconfArgs, values: &HashMap<&str, &str>) -> SysconfArgs {
+    let value = |k: &str| values.get(k).map(|x| Box::<str>::from(*x));
+
+    sysconf.hostname = value("hostname").or(sysconf.hostname);
+    sysconf.timezone = value("timezone").or(sysconf.timezone);
+    sysconf.keymap = value("keymap").or(sysconf.keymap);
+    sysconf.user_name = value("user_name").or(sysconf.user_name);
+    sysconf.user_password = value("user_password").or(sysconf.user_password);
+    sysconf.wifi_ssid = value("wifi_ssid").or(sysconf.wifi_ssid);
+    sysconf.wifi_password = value("wifi_password").or(sysconf.wifi_password);
+    sysconf.ssh_key = value("ssh_key").or(sysconf.ssh_key);
+    sysconf.volume_label = value("volume_label").or(sysconf.volume_label);
+    sysconf.rootfs_label = value("rootfs_label").or(sysconf.rootfs_label);
+
+    sysconf
+}
+
+/// Same checks as the command line arguments, since manifest values bypass clap.
+fn validate_sysconf(sysconf: &SysconfArgs) -> Result<(), String> {
+    if let Some(x) = &sysconf.hostname {
+        crate::cli::parse_hostname(x)?;
+    }
+    if let Some(x) = &sysconf.volume_label {
+        crate::cli::parse_volume_label(x).map_err(|e| format!("volume_label {e}"))?;
+    }
+    if let Some(x) = &sysconf.rootfs_label {
+        crate::cli::parse_rootfs_label(x).map_err(|e| format!("rootfs_label {e}"))?;
+    }
+    if sysconf.user_name.as_deref() == Some("root") {
+        return Err("user_name cannot be root".to_string());
+    }
+    if sysconf.user_name.is_some() != sysconf.user_password.is_some() {
+        return Err("user_name and user_password must be set together".to_string());
+    }
+    if sysconf.wifi_ssid.is_some() != sysconf.wifi_password.is_some() {
+        return Err("wifi_ssid and wifi_password must be set together".to_string());
+    }
+
+    Ok(())
+}
+
+fn write_results(path: &Path, outcomes: &[Outcome], img_sha256: &[u8; 32]) -> std::io::Result<()> {
+    let img_sha256 = const_hex::encode(img_sha256);
+    let mut data = RESULTS_HEADER.join(",");
+    data.push('\n');
+
+    for x in outcomes {
+        let (status, error) = match &x.res {
+            Ok(()) => ("success", String::new()),
+            Err(e) => ("failed", format!("{e:#}")),
+        };
+        let record = [
+            x.line.to_string(),
+            x.dst.to_string_lossy().to_string(),
+            x.hostname.as_deref().unwrap_or_default().to_string(),
+            status.to_string(),
+            img_sha256.clone(),
+            error,
+        ];
+
+        data.push_str(
+            &record
+                .iter()
+                .map(|x| csv_field(x))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        data.push('\n');
+    }
+
+    std::fs::write(path, data)
+}
+
+/// Parse CSV (RFC 4180). Returns each record along with the line it starts on. Empty lines are
+/// skipped.
+fn parse_csv(data: &str) -> anyhow::Result<Vec<(usize, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+
+    let mut chars = data.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            }
+            _ => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+
+    if quoted {
+        anyhow::bail!("line {start}: missing closing quote");
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+
+    records.retain(|(_, x)| x.len() != 1 || !x[0].trim().is_empty());
+    Ok(records)
+}
@@ -1,3 +1,5 @@
+use std::io::Write;
+
 fn main() {
     embed_resource::compile(
         "assets/packages/windows/gui-manifest.rc",
@@ -5,4 +7,42 @@ fn main() {
     )
     .manifest_required()
     .unwrap();
+
+    println!("cargo:rustc-env=GIT_COMMIT={}", git_commit());
+    third_party_attributions();
+}
+
+fn git_commit() -> String {
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+
+    std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|x| x.status.success())
+        .and_then(|x| String::from_utf8(x.stdout).ok())
+        .map(|x| x.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Generate a list of all third-party crates bundled in the application from Cargo.lock.
+fn third_party_attributions() {
+    println!("cargo:rerun-if-changed=../Cargo.lock");
+
+    let lock = std::fs::read_to_string("../Cargo.lock").unwrap_or_default();
+    let out_path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("third_party.txt");
+    let mut out = std::fs::File::create(out_path).unwrap();
+
+    let mut name = None;
+    for line in lock.lines() {
+        if let Some(x) = line.strip_prefix("name = ") {
+            name = Some(x.trim_matches('"').to_string());
+        } else if let Some(x) = line.strip_prefix("version = ")
+            && let Some(n) = name.take()
+            && !n.starts_with("bb-")
+            && n != "xtask"
+        {
+            writeln!(out, "{} {}", n, x.trim_matches('"')).unwrap();
+        }
+    }
 }
@@ -6,9 +6,29 @@ pub(crate) const LATEST_RELEASE_URL: &str =
     "https://api.github.com/repos/beagleboard/bb-imager-rs/releases/latest";
 
 pub(crate) const PACKAGE_QUALIFIER: (&str, &str, &str) = ("org", "beagleboard", "imagingutility");
+/// Url scheme for deep links, like `bb-imager://flash?board=<name>&image=<name>`.
+pub(crate) const DEEP_LINK_SCHEME: &str = "bb-imager";
+/// Desktop entry installed by Linux packages. Used to identify the launcher icon.
+#[cfg(target_os = "linux")]
+pub(crate) const DESKTOP_ENTRY_URI: &str = "application://bb-imager-gui.desktop";
 
 pub(crate) const DEFAULT_CONFIG: &[u8] = include_bytes!("../../config.json");
+/// Time after which cached remote OS sub lists are refreshed in the background.
+pub(crate) const REMOTE_SUBITEMS_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// How long a destination blinks when identifying it.
+pub(crate) const IDENTIFY_DURATION: std::time::Duration = std::time::Duration::from_secs(5);
+/// How long to wait for a flashed board to come online. First boot resizes the root filesystem,
+/// which can take a few minutes.
+pub(crate) const DISCOVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// How long messages shown inside the application stay visible.
+pub(crate) const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(5);
+/// Delay before retrying a remote catalog which could not be fetched. Doubled after every failed
+/// attempt, up to 64 times.
+pub(crate) const CATALOG_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
 pub(crate) const WINDOW_SIZE: iced::Size = iced::Size::new(680.0, 450.0);
+pub(crate) const MIN_WINDOW_SIZE: iced::Size = iced::Size::new(480.0, 450.0);
+/// Window width below which layouts switch to a compact arrangement.
+pub(crate) const NARROW_WIDTH: f32 = 600.0;
 pub(crate) const APP_NAME: &str = "BeagleBoard Imager";
 pub(crate) const APP_RELEASE: &str = if option_env!("PRE_RELEASE").is_some() {
     "pre-release"
@@ -17,6 +37,49 @@ pub(crate) const APP_RELEASE: &str = if option_env!("PRE_RELEASE").is_some() {
 };
 pub(crate) const APP_DESC: &str = env!("CARGO_PKG_DESCRIPTION");
 pub(crate) const APP_LINCESE: &str = include_str!("../../LICENSE");
+pub(crate) const APP_COMMIT: &str = env!("GIT_COMMIT");
+/// Crates bundled in the application. Generated from Cargo.lock at build time.
+pub(crate) const THIRD_PARTY_ATTRIBUTIONS: &str =
+    include_str!(concat!(env!("OUT_DIR"), "/third_party.txt"));
+pub(crate) const BEAGLE_BOARD_ABOUT: &str = "BeagleBoard.org Foundation is a Michigan, USA-based \
+501(c)(3) non-profit corporation existing to provide education in and collaboration around the \
+design and use of open-source software and hardware in embedded computing.";
+
+/// Steps shown in the first-run onboarding wizard as (title, description).
+pub(crate) const ONBOARDING_STEPS: &[(&str, &str)] = &[
+    (
+        "Welcome",
+        "BeagleBoard Imager writes operating system images for your Beagle. Flashing takes three \
+         steps: choose a board, choose an image, and choose where to write it.",
+    ),
+    (
+        "Choose a Board",
+        "Start by selecting your board from the list on the home screen. Only images compatible \
+         with the selected board will be shown in the next step.",
+    ),
+    (
+        "Choose an Image",
+        "Pick one of the recommended images, browse the sub lists for testing images, or use a \
+         custom image file from your computer.",
+    ),
+    (
+        "Choose a Destination",
+        "Select the SD card or device to write the image to. All existing data on the \
+         destination will be erased.",
+    ),
+    (
+        "Customize",
+        "Supported images can be customized before flashing. Hostname, timezone, keyboard \
+         layout, user account, Wi-Fi and SSH keys are applied on the first boot of your board. \
+         Customization is saved and reused for future flashes.",
+    ),
+    (
+        "Learn More",
+        "Board documentation is linked from the board details on the home screen. The \
+         BeagleBoard.org documentation has guides for getting started with every board.",
+    ),
+];
+pub(crate) const DOCS_URL: &str = "https://docs.beagleboard.org/";
 
 // Icons
 pub(crate) const WINDOW_ICON: &[u8] = include_bytes!("../assets/icons/icon.png");
@@ -30,7 +93,11 @@ pub(crate) const ARROW_FORWARD_IOS_ICON: &[u8] =
     include_bytes!("../assets/icons/arrow-forward-ios.svg");
 pub(crate) const FILE_SAVE_ICON: &[u8] = include_bytes!("../assets/icons/file-save.svg");
 pub(crate) const INFO_ICON: &[u8] = include_bytes!("../assets/icons/info.svg");
+pub(crate) const SETTINGS_ICON: &[u8] = include_bytes!("../assets/icons/settings.svg");
 pub(crate) const COPY_ICON: &[u8] = include_bytes!("../assets/icons/content-copy.svg");
+pub(crate) const STAR_ICON: &[u8] = include_bytes!("../assets/icons/star.svg");
+pub(crate) const EXPAND_MORE_ICON: &[u8] = include_bytes!("../assets/icons/expand-more.svg");
+pub(crate) const STAR_BORDER_ICON: &[u8] = include_bytes!("../assets/icons/star-border.svg");
 
 // Font
 pub(crate) const FONT_REGULAR: iced::Font = iced::Font::with_name("Nunito");
@@ -80,14 +147,106 @@ pub(crate) const BACKGROUND: iced::Color = color!(30, 30, 30);
 pub(crate) const CARD: iced::Color = color!(45, 45, 45);
 pub(crate) const DANGER: iced::Color = color!(255, 0, 0);
 
-pub(crate) const KEYMAP_LAYOUTS: &[&str] = &[
-    "af", "al", "am", "ara", "at", "au", "az", "ba", "bd", "be", "bg", "br", "brai", "bt", "bw",
-    "by", "ca", "cd", "ch", "cm", "cn", "cz", "de", "dk", "dz", "ee", "epo", "es", "et", "fi",
-    "fo", "fr", "gb", "ge", "gh", "gn", "gr", "hr", "hu", "id", "ie", "il", "in", "iq", "ir", "is",
-    "it", "jp", "jv", "ke", "kg", "kh", "kr", "kz", "la", "latam", "lk", "lt", "lv", "ma", "mao",
-    "md", "me", "mk", "ml", "mm", "mn", "mt", "mv", "my", "ng", "nl", "no", "np", "ph", "pk", "pl",
-    "pt", "ro", "rs", "ru", "se", "si", "sk", "sn", "sy", "tg", "th", "tj", "tm", "tr", "tw", "tz",
-    "ua", "us", "uz", "vn", "za",
+/// Keyboard layouts supported by sysconf.txt: (code, language, region).
+pub(crate) const KEYMAPS: &[(&str, &str, &str)] = &[
+    ("af", "Afghani", "Asia"),
+    ("al", "Albanian", "Europe"),
+    ("am", "Armenian", "Asia"),
+    ("ara", "Arabic", "Middle East"),
+    ("at", "German (Austria)", "Europe"),
+    ("au", "English (Australia)", "Oceania"),
+    ("az", "Azerbaijani", "Asia"),
+    ("ba", "Bosnian", "Europe"),
+    ("bd", "Bangla", "Asia"),
+    ("be", "Belgian", "Europe"),
+    ("bg", "Bulgarian", "Europe"),
+    ("br", "Portuguese (Brazil)", "Americas"),
+    ("brai", "Braille", "Other"),
+    ("bt", "Dzongkha", "Asia"),
+    ("bw", "Tswana", "Africa"),
+    ("by", "Belarusian", "Europe"),
+    ("ca", "French (Canada)", "Americas"),
+    ("cd", "French (DR Congo)", "Africa"),
+    ("ch", "German (Switzerland)", "Europe"),
+    ("cm", "English (Cameroon)", "Africa"),
+    ("cn", "Chinese", "Asia"),
+    ("cz", "Czech", "Europe"),
+    ("de", "German", "Europe"),
+    ("dk", "Danish", "Europe"),
+    ("dz", "Berber (Algeria)", "Africa"),
+    ("ee", "Estonian", "Europe"),
+    ("epo", "Esperanto", "Other"),
+    ("es", "Spanish", "Europe"),
+    ("et", "Amharic", "Africa"),
+    ("fi", "Finnish", "Europe"),
+    ("fo", "Faroese", "Europe"),
+    ("fr", "French", "Europe"),
+    ("gb", "English (UK)", "Europe"),
+    ("ge", "Georgian", "Asia"),
+    ("gh", "English (Ghana)", "Africa"),
+    ("gn", "N'Ko (Guinea)", "Africa"),
+    ("gr", "Greek", "Europe"),
+    ("hr", "Croatian", "Europe"),
+    ("hu", "Hungarian", "Europe"),
+    ("id", "Indonesian", "Asia"),
+    ("ie", "Irish", "Europe"),
+    ("il", "Hebrew", "Middle East"),
+    ("in", "Indian", "Asia"),
+    ("iq", "Iraqi", "Middle East"),
+    ("ir", "Persian", "Middle East"),
+    ("is", "Icelandic", "Europe"),
+    ("it", "Italian", "Europe"),
+    ("jp", "Japanese", "Asia"),
+    ("jv", "Javanese", "Asia"),
+    ("ke", "Swahili (Kenya)", "Africa"),
+    ("kg", "Kyrgyz", "Asia"),
+    ("kh", "Khmer", "Asia"),
+    ("kr", "Korean", "Asia"),
+    ("kz", "Kazakh", "Asia"),
+    ("la", "Lao", "Asia"),
+    ("latam", "Spanish (Latin American)", "Americas"),
+    ("lk", "Sinhala", "Asia"),
+    ("lt", "Lithuanian", "Europe"),
+    ("lv", "Latvian", "Europe"),
+    ("ma", "Arabic (Morocco)", "Africa"),
+    ("mao", "Maori", "Oceania"),
+    ("md", "Moldavian", "Europe"),
+    ("me", "Montenegrin", "Europe"),
+    ("mk", "Macedonian", "Europe"),
+    ("ml", "Bambara", "Africa"),
+    ("mm", "Burmese", "Asia"),
+    ("mn", "Mongolian", "Asia"),
+    ("mt", "Maltese", "Europe"),
+    ("mv", "Dhivehi", "Asia"),
+    ("my", "Malay", "Asia"),
+    ("ng", "English (Nigeria)", "Africa"),
+    ("nl", "Dutch", "Europe"),
+    ("no", "Norwegian", "Europe"),
+    ("np", "Nepali", "Asia"),
+    ("ph", "Filipino", "Asia"),
+    ("pk", "Urdu (Pakistan)", "Asia"),
+    ("pl", "Polish", "Europe"),
+    ("pt", "Portuguese", "Europe"),
+    ("ro", "Romanian", "Europe"),
+    ("rs", "Serbian", "Europe"),
+    ("ru", "Russian", "Europe"),
+    ("se", "Swedish", "Europe"),
+    ("si", "Slovenian", "Europe"),
+    ("sk", "Slovak", "Europe"),
+    ("sn", "Wolof", "Africa"),
+    ("sy", "Arabic (Syria)", "Middle East"),
+    ("tg", "French (Togo)", "Africa"),
+    ("th", "Thai", "Asia"),
+    ("tj", "Tajik", "Asia"),
+    ("tm", "Turkmen", "Asia"),
+    ("tr", "Turkish", "Middle East"),
+    ("tw", "Taiwanese", "Asia"),
+    ("tz", "Swahili (Tanzania)", "Africa"),
+    ("ua", "Ukrainian", "Europe"),
+    ("us", "English (US)", "Americas"),
+    ("uz", "Uzbek", "Asia"),
+    ("vn", "Vietnamese", "Asia"),
+    ("za", "English (South Africa)", "Africa"),
 ];
 
 pub(crate) const TIMEZONES: &[&str] = &[
@@ -0,0 +1,131 @@
+//! Present flashing failures as dialogs with steps to fix them, instead of just the error message.
+
+use bb_flasher::FailureKind;
+
+use crate::{constants, state::FlashJob};
+
+/// Lines of logs included in diagnostics.
+const DIAGNOSTICS_LOG_LINES: usize = 200;
+
+#[cfg(target_os = "linux")]
+const PERMISSION_STEPS: &[&str] = &[
+    "Click Retry and approve the authentication prompt.",
+    "If no prompt appears, make sure a polkit authentication agent is running.",
+];
+#[cfg(target_os = "macos")]
+const PERMISSION_STEPS: &[&str] =
+    &["Click Retry and enter your password when asked to allow access to the disk."];
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+const PERMISSION_STEPS: &[&str] = &["Run BeagleBoard Imager as Administrator and try again."];
+
+/// Failed flashing job.
+#[derive(Debug, Clone)]
+pub(crate) struct Failure {
+    pub(crate) kind: FailureKind,
+    /// Top level error message.
+    pub(crate) message: String,
+    /// Complete error chain.
+    pub(crate) details: String,
+}
+
+impl Failure {
+    pub(crate) fn new(err: &anyhow::Error) -> Self {
+        Self {
+            kind: FailureKind::classify(err),
+            message: err.to_string(),
+            details: format!("{err:?}"),
+        }
+    }
+
+    pub(crate) const fn title(&self) -> &'static str {
+        match self.kind {
+            FailureKind::WriteProtected => "SD Card is write protected",
+            FailureKind::PermissionDenied => "Insufficient permissions",
+            FailureKind::DestinationBusy => "Destination is in use",
+            FailureKind::DestinationNotFound => "Destination not found",
+            FailureKind::InsufficientSpace => "Not enough free space",
+            FailureKind::Network => "Network error",
+            FailureKind::InvalidImage => "Image is not valid",
+            FailureKind::VerificationFailed => "Verification failed",
+            FailureKind::Bootloader => "Failed to start bootloader",
+            FailureKind::Timeout => "Flashing timed out",
+            FailureKind::BootFailed => "Board did not boot",
+            FailureKind::Aborted => "Flashing aborted",
+            FailureKind::Unknown => "Flashing failed",
+        }
+    }
+
+    /// Steps the user can take to fix the failure.
+    pub(crate) const fn remediation(&self) -> &'static [&'static str] {
+        match self.kind {
+            FailureKind::WriteProtected => &[
+                "Slide the lock switch on the SD Card or adapter away from the Lock position.",
+                "Remove and re-insert the SD Card, then retry.",
+            ],
+            FailureKind::PermissionDenied => PERMISSION_STEPS,
+            FailureKind::DestinationBusy => &[
+                "Close applications using the destination, such as file managers or serial monitors.",
+                "Eject any mounted partitions, then retry.",
+            ],
+            FailureKind::DestinationNotFound => &[
+                "Check that the device is connected, then retry.",
+                "Try a different USB port or cable.",
+            ],
+            FailureKind::InsufficientSpace => &[
+                "Free up disk space, or remove old images from the cache directory.",
+                "Change the cache or scratch directory in Settings.",
+            ],
+            FailureKind::Network => &[
+                "Check your internet connection, then retry.",
+                "If your network requires a proxy, configure it in Settings.",
+                "If your network inspects HTTPS traffic, add its CA certificate in Settings.",
+            ],
+            FailureKind::InvalidImage => &[
+                "Make sure the image is meant for the selected board.",
+                "Download the image again, or check the checksum of local images.",
+            ],
+            FailureKind::VerificationFailed => &[
+                "The SD Card might be failing or counterfeit. Retry with a different SD Card.",
+                "Try a different card reader or USB port.",
+            ],
+            FailureKind::Bootloader => {
+                &["Hold the BOOT button while connecting the device, then retry."]
+            }
+            FailureKind::Timeout => &[
+                "Reconnect the device, then retry.",
+                "Try a different card reader, USB port or cable.",
+            ],
+            FailureKind::BootFailed => &[
+                "Check the serial console connection and baud rate.",
+                "Make sure the board boots from the flashed media, then retry.",
+            ],
+            FailureKind::Aborted => &["Retry flashing."],
+            FailureKind::Unknown => &[
+                "Check the logs for more information.",
+                "Copy diagnostics and include them when reporting an issue.",
+            ],
+        }
+    }
+
+    /// Report to attach to issues. Includes application and system information, the job and the
+    /// end of the logs.
+    pub(crate) fn diagnostics(&self, job: &FlashJob, logs: &str) -> String {
+        let lines: Vec<&str> = logs.lines().collect();
+        let logs = lines[lines.len().saturating_sub(DIAGNOSTICS_LOG_LINES)..].join("\n");
+
+        format!(
+            "{} {} ({})\nOS: {} {}\n\nBoard: {}\nImage: {}\nDestination: {}\n\nFailure: {:?}\n{}\n\nLogs:\n{}",
+            constants::APP_NAME,
+            constants::APP_RELEASE,
+            constants::APP_COMMIT,
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            job.board,
+            job.image,
+            job.destination,
+            self.kind,
+            self.details,
+            logs
+        )
+    }
+}
@@ -1,19 +1,36 @@
-use std::{collections::HashMap, fmt::Display, path::PathBuf, sync::LazyLock, time::Duration};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    path::PathBuf,
+    sync::LazyLock,
+    time::Duration,
+};
 
 use crate::{BBImagerMessage, PACKAGE_QUALIFIER, constants};
 use bb_config::config::{self, OsListItem};
-use bb_flasher::{BBFlasher, BBFlasherTarget, DownloadFlashingStatus, sd::FlashingSdLinuxConfig};
+use bb_flasher::{
+    BBFlasher, BBFlasherTarget, ByteSize, DownloadFlashingStatus, sd::FlashingSdLinuxConfig,
+};
 use iced::{futures, widget};
 use url::Url;
 
 #[derive(Debug, Clone)]
 pub(crate) struct Boards {
     config: config::Config,
+    /// Remote catalogs merged without a valid signature.
+    unsigned: BTreeSet<Url>,
 }
 
 impl Boards {
-    pub(crate) fn merge(&mut self, config: bb_config::Config) {
-        self.config.extend([config])
+    pub(crate) fn merge(&mut self, catalog: Catalog) {
+        if !catalog.signed {
+            self.unsigned.insert(catalog.url);
+        }
+        self.config.extend([catalog.config])
+    }
+
+    pub(crate) fn unsigned_catalogs(&self) -> impl Iterator<Item = &Url> {
+        self.unsigned.iter()
     }
 
     pub(crate) fn unrsolved_configs(&self) -> impl Iterator<Item = &Url> {
@@ -24,6 +41,25 @@ impl Boards {
         self.config.imager.devices.iter().enumerate()
     }
 
+    /// Release date of the newest image in the catalog. Used to identify the catalog version.
+    pub(crate) fn catalog_date(&self) -> Option<chrono::NaiveDate> {
+        fn newest(items: &[OsListItem]) -> Option<chrono::NaiveDate> {
+            items
+                .iter()
+                .filter_map(|x| match x {
+                    OsListItem::Image(img) => Some(img.release_date),
+                    OsListItem::SubList(item) => newest(&item.subitems),
+                    OsListItem::RemoteSubList(_) => None,
+                    OsListItem::Bundle(item) => {
+                        item.steps.iter().map(|x| x.image.release_date).max()
+                    }
+                })
+                .max()
+        }
+
+        newest(&self.config.os_list)
+    }
+
     pub(crate) fn image(&self, target: &[usize]) -> &OsListItem {
         let mut res = &self.config.os_list;
         let (last, rest) = target.split_last().unwrap();
@@ -31,7 +67,7 @@ impl Boards {
         for i in rest {
             let item = res.get(*i).expect("No Subitem");
             res = match item {
-                OsListItem::Image(_) => panic!("No subitem"),
+                OsListItem::Image(_) | OsListItem::Bundle(_) => panic!("No subitem"),
                 OsListItem::SubList(item) => &item.subitems,
                 OsListItem::RemoteSubList { .. } => panic!("No subitem"),
             }
@@ -50,7 +86,7 @@ impl Boards {
         for i in subitems {
             let item = res.get(*i).expect("No Subitem");
             res = match item {
-                OsListItem::Image(_) => panic!("No subitem"),
+                OsListItem::Image(_) | OsListItem::Bundle(_) => panic!("No subitem"),
                 OsListItem::SubList(item) => &item.subitems,
                 OsListItem::RemoteSubList { .. } => return None,
             }
@@ -66,11 +102,140 @@ impl Boards {
                 .filter(|(_, x)| match x {
                     OsListItem::RemoteSubList(item) => flasher_supported(item.flasher),
                     OsListItem::SubList(item) => flasher_supported(item.flasher),
-                    _ => true,
+                    OsListItem::Image(item) => {
+                        item.supports_imager(&crate::updater::current_version())
+                    }
+                    OsListItem::Bundle(item) => item.steps.iter().all(|x| {
+                        flasher_supported(x.flasher)
+                            && x.image.supports_imager(&crate::updater::current_version())
+                    }),
                 }),
         )
     }
 
+    /// Os images and bundles of all boards with name or tags containing `query`. Remote sub lists
+    /// which have not been resolved yet are not searched.
+    pub(crate) fn search(&self, query: &str) -> Vec<SearchResult<'_>> {
+        fn search_board<'a>(
+            boards: &'a Boards,
+            board: usize,
+            pos: &mut Vec<usize>,
+            query: &str,
+            res: &mut Vec<SearchResult<'a>>,
+        ) {
+            let Some(items) = boards.images(board, pos) else {
+                return;
+            };
+            let items: Vec<_> = items.collect();
+
+            for (idx, item) in items {
+                pos.push(idx);
+                match item {
+                    OsListItem::SubList(_) => search_board(boards, board, pos, query, res),
+                    OsListItem::RemoteSubList(_) => {}
+                    OsListItem::Image(img) => {
+                        if img.name.to_lowercase().contains(query)
+                            || img.tags.iter().any(|t| t.to_lowercase().contains(query))
+                        {
+                            res.push(SearchResult {
+                                board,
+                                target: pos.clone(),
+                                item,
+                            });
+                        }
+                    }
+                    OsListItem::Bundle(bundle) => {
+                        if bundle.name.to_lowercase().contains(query) {
+                            res.push(SearchResult {
+                                board,
+                                target: pos.clone(),
+                                item,
+                            });
+                        }
+                    }
+                }
+                pos.pop();
+            }
+        }
+
+        let query = query.trim().to_lowercase();
+        let mut res = Vec::new();
+
+        if !query.is_empty() {
+            for (board, _) in self.devices() {
+                search_board(self, board, &mut Vec::new(), &query, &mut res);
+            }
+        }
+
+        res
+    }
+
+    /// Position and name of the newest stable Os image for a board.
+    pub(crate) fn recommended(&self, board_idx: usize) -> Option<(Vec<usize>, &str)> {
+        let (idx, img) = self.config.recommended_image(
+            &self.device(board_idx).tags,
+            &crate::updater::current_version(),
+            chrono::Local::now().date_naive(),
+        )?;
+
+        // Skip images of boards with flashers not supported by this build
+        self.images(board_idx, &[])?
+            .any(|(i, _)| i == idx)
+            .then(|| (vec![idx], img.name.as_str()))
+    }
+
+    /// Find a board, and optionally one of its Os images, by name. Names are matched
+    /// case-insensitively. Images in unresolved remote sub lists are not found.
+    pub(crate) fn find(
+        &self,
+        board: &str,
+        image: Option<&str>,
+    ) -> Option<(usize, Option<Vec<usize>>)> {
+        let (idx, _) = self
+            .devices()
+            .find(|(_, x)| x.name.eq_ignore_ascii_case(board))?;
+
+        let target = image.and_then(|name| {
+            self.search(name)
+                .into_iter()
+                .find(|x| x.board == idx && x.item.name().eq_ignore_ascii_case(name.trim()))
+                .map(|x| x.target)
+        });
+
+        Some((idx, target))
+    }
+
+    /// Flasher used for an item, as set by the sub list containing it.
+    pub(crate) fn flasher(&self, board_idx: usize, target: &[usize]) -> config::Flasher {
+        match target.split_last() {
+            Some((_, parent)) if !parent.is_empty() => match self.image(parent) {
+                OsListItem::SubList(x) => x.flasher,
+                _ => panic!("Expected list"),
+            },
+            _ => self.device(board_idx).flasher,
+        }
+    }
+
+    /// Number of Os images for a board in a sub list, including nested sub lists. Images in
+    /// unresolved remote sub lists are not counted.
+    pub(crate) fn image_count(&self, board_idx: usize, target: &[usize]) -> usize {
+        let Some(items) = self.images(board_idx, target) else {
+            return 0;
+        };
+
+        items
+            .map(|(idx, x)| match x {
+                OsListItem::SubList(_) => {
+                    let mut t = target.to_vec();
+                    t.push(idx);
+                    self.image_count(board_idx, &t)
+                }
+                OsListItem::RemoteSubList(_) => 0,
+                OsListItem::Image(_) | OsListItem::Bundle(_) => 1,
+            })
+            .sum()
+    }
+
     pub(crate) fn device(&self, board_idx: usize) -> &config::Device {
         self.config
             .imager
@@ -79,9 +244,18 @@ impl Boards {
             .expect("Board does not exist")
     }
 
-    pub(crate) fn resolve_remote_subitem(&mut self, subitems: Vec<OsListItem>, target: &[usize]) {
+    pub(crate) fn resolve_remote_subitem(
+        &mut self,
+        mut subitems: Vec<OsListItem>,
+        target: &[usize],
+    ) {
         assert!(!target.is_empty());
 
+        // Remote sub lists are never trusted
+        subitems
+            .iter_mut()
+            .for_each(OsListItem::strip_header_variables);
+
         let mut res = &mut self.config.os_list;
 
         let (last, rest) = target.split_last().unwrap();
@@ -89,7 +263,7 @@ impl Boards {
         for i in rest {
             let item = res.get_mut(*i).expect("No Subitem");
             res = match item {
-                OsListItem::Image(_) => panic!("No subitem"),
+                OsListItem::Image(_) | OsListItem::Bundle(_) => panic!("No subitem"),
                 OsListItem::SubList(item) => &mut item.subitems,
                 OsListItem::RemoteSubList { .. } => panic!("No subitem"),
             }
@@ -116,7 +290,10 @@ impl Boards {
             os_list: value.os_list,
         };
 
-        Self { config: filtered }
+        Self {
+            config: filtered,
+            unsigned: BTreeSet::new(),
+        }
     }
 
     pub(crate) fn new() -> Self {
@@ -132,6 +309,7 @@ pub(crate) enum BoardImageIcon {
     Remote(url::Url),
     Local,
     Format,
+    Customize,
 }
 
 #[derive(Debug, Clone)]
@@ -140,6 +318,10 @@ pub(crate) enum BoardImage {
     SdFormat {
         details: Vec<(&'static str, String)>,
     },
+    /// Apply customization to an already flashed SD Card
+    SdCustomize {
+        details: Vec<(&'static str, String)>,
+    },
     Image {
         flasher: config::Flasher,
         init_format: config::InitFormat,
@@ -149,20 +331,45 @@ pub(crate) enum BoardImage {
         description: Option<String>,
         icon: BoardImageIcon,
         details: Vec<(&'static str, String)>,
+        warning: Option<String>,
+        release_notes: Option<String>,
+        release_notes_url: Option<url::Url>,
+    },
+    Bundle {
+        name: String,
+        description: String,
+        icon: BoardImageIcon,
+        details: Vec<(&'static str, String)>,
+        steps: Vec<BundleStep>,
     },
 }
 
+/// Single step of [`BoardImage::Bundle`]
+#[derive(Debug, Clone)]
+pub(crate) struct BundleStep {
+    pub(crate) img: BoardImage,
+    pub(crate) instructions: Option<String>,
+}
+
 impl BoardImage {
     pub(crate) fn local(path: PathBuf, flasher: config::Flasher) -> Self {
         let metadata = std::fs::metadata(&path).expect("File does not exist");
-        let details = vec![
+        let mut details = vec![
             ("Path", path.to_string_lossy().to_string()),
             ("Size", metadata.len().to_string()),
         ];
 
+        let bmap = match flasher {
+            config::Flasher::SdCard => bb_flasher::sd::find_bmap(&path),
+            _ => None,
+        };
+        if let Some(x) = &bmap {
+            details.push(("Bmap", x.to_string_lossy().to_string()));
+        }
+
         Self::Image {
             img: bb_flasher::LocalImage::new(path.into()).into(),
-            bmap: None,
+            bmap: bmap.map(Bmap::Local),
             flasher,
             // Do not try to apply customization for local images
             init_format: config::InitFormat::None,
@@ -170,6 +377,9 @@ impl BoardImage {
             description: None,
             icon: BoardImageIcon::Local,
             details,
+            warning: None,
+            release_notes: None,
+            release_notes_url: None,
         }
     }
 
@@ -180,23 +390,71 @@ impl BoardImage {
     ) -> Self {
         let mut details = vec![
             ("Release Date", image.release_date.to_string()),
-            ("Image Size", pretty_bytes(image.extract_size)),
+            ("Image Size", ByteSize::new(image.extract_size).to_string()),
         ];
 
         if let Some(x) = image.image_download_size {
-            details.push(("Download Size", pretty_bytes(x)))
+            details.push(("Download Size", ByteSize::new(x).to_string()))
         }
 
-        Self::Image {
-            img: RemoteImage::new(
+        if let Some(x) = image.eol_date {
+            details.push(("End of Life", x.to_string()))
+        }
+
+        let warning = image.warning(chrono::Local::now().date_naive());
+
+        let headers = image.resolved_headers(bb_config::secrets::lookup);
+        let downloader = match downloader.with_headers(&image.url, headers) {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::warn!("Ignoring invalid headers for {}: {e}", image.name);
+                downloader
+            }
+        };
+
+        let img = if image.url.scheme() == "oci" {
+            match image.url.as_str().parse() {
+                Ok(reference) => OciImage {
+                    name: image.name.into(),
+                    reference,
+                    sha256: image.image_download_sha256,
+                    extract_size: image.extract_size,
+                    downloader: downloader.clone(),
+                    scratch: Default::default(),
+                }
+                .into(),
+                Err(e) => {
+                    tracing::warn!("Invalid OCI reference {}: {e}", image.url);
+                    RemoteImage::new(
+                        image.name.into(),
+                        Box::new(image.url),
+                        image.metalink.map(Box::new),
+                        image.image_download_sha256,
+                        image.extract_size,
+                        image.deltas,
+                        downloader.clone(),
+                    )
+                    .with_download_size(image.image_download_size)
+                    .into()
+                }
+            }
+        } else {
+            RemoteImage::new(
                 image.name.into(),
                 Box::new(image.url),
+                image.metalink.map(Box::new),
                 image.image_download_sha256,
                 image.extract_size,
+                image.deltas,
                 downloader.clone(),
             )
-            .into(),
-            bmap: image.bmap.map(|url| Bmap {
+            .with_download_size(image.image_download_size)
+            .into()
+        };
+
+        Self::Image {
+            img,
+            bmap: image.bmap.map(|url| Bmap::Remote {
                 url: Box::new(url),
                 downloader,
             }),
@@ -206,6 +464,40 @@ impl BoardImage {
             description: Some(image.description),
             icon: BoardImageIcon::Remote(image.icon),
             details,
+            warning,
+            release_notes: image.release_notes,
+            release_notes_url: image.release_notes_url,
+        }
+    }
+
+    pub(crate) fn bundle(bundle: config::OsBundle, downloader: bb_downloader::Downloader) -> Self {
+        let details = bundle
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                (
+                    "Step",
+                    format!("{}. {} ({})", i + 1, x.image.name, x.flasher),
+                )
+            })
+            .collect();
+
+        let steps = bundle
+            .steps
+            .into_iter()
+            .map(|x| BundleStep {
+                img: Self::remote(x.image, x.flasher, downloader.clone()),
+                instructions: x.instructions,
+            })
+            .collect();
+
+        Self::Bundle {
+            name: bundle.name,
+            description: bundle.description,
+            icon: BoardImageIcon::Remote(bundle.icon),
+            details,
+            steps,
         }
     }
 
@@ -215,44 +507,88 @@ impl BoardImage {
         }
     }
 
+    pub(crate) fn customize() -> Self {
+        Self::SdCustomize {
+            details: vec![("Customization", "Linux Sysconf".to_string())],
+        }
+    }
+
     pub(crate) fn description(&self) -> Option<&str> {
         match self {
             BoardImage::SdFormat { .. } => Some("Format a SD Card to FAT32 for reuse."),
+            BoardImage::SdCustomize { .. } => {
+                Some("Change settings of an already flashed SD Card without reflashing.")
+            }
             BoardImage::Image { description, .. } => description.as_ref().map(|x| x.as_str()),
+            BoardImage::Bundle { description, .. } => Some(description),
         }
     }
 
     pub(crate) fn icon(&self) -> &BoardImageIcon {
         match self {
             BoardImage::SdFormat { .. } => &BoardImageIcon::Format,
+            BoardImage::SdCustomize { .. } => &BoardImageIcon::Customize,
             BoardImage::Image { icon, .. } => icon,
+            BoardImage::Bundle { icon, .. } => icon,
         }
     }
 
-    pub(crate) const fn flasher(&self) -> config::Flasher {
+    /// Flasher to use. For bundles, the flasher of the first step.
+    pub(crate) fn flasher(&self) -> config::Flasher {
         match self {
-            BoardImage::SdFormat { .. } => config::Flasher::SdCard,
+            BoardImage::SdFormat { .. } | BoardImage::SdCustomize { .. } => config::Flasher::SdCard,
             BoardImage::Image { flasher, .. } => *flasher,
+            BoardImage::Bundle { steps, .. } => {
+                steps.first().map(|x| x.img.flasher()).unwrap_or_default()
+            }
         }
     }
 
     pub(crate) const fn init_format(&self) -> config::InitFormat {
         match self {
             BoardImage::Image { init_format, .. } => *init_format,
-            BoardImage::SdFormat { .. } => config::InitFormat::None,
+            BoardImage::SdCustomize { .. } => config::InitFormat::Sysconf,
+            BoardImage::SdFormat { .. } | BoardImage::Bundle { .. } => config::InitFormat::None,
         }
     }
 
     pub(crate) fn info_text(&self) -> Option<&str> {
         match self {
             BoardImage::Image { info_text, .. } => info_text.as_ref().map(|x| x.as_str()),
-            BoardImage::SdFormat { .. } => None,
+            BoardImage::SdFormat { .. }
+            | BoardImage::SdCustomize { .. }
+            | BoardImage::Bundle { .. } => None,
+        }
+    }
+
+    /// Deprecation or end of life warning for the image.
+    pub(crate) fn warning(&self) -> Option<&str> {
+        match self {
+            BoardImage::Image { warning, .. } => warning.as_deref(),
+            BoardImage::SdFormat { .. } | BoardImage::SdCustomize { .. } => None,
+            BoardImage::Bundle { steps, .. } => steps.iter().find_map(|x| x.img.warning()),
+        }
+    }
+
+    pub(crate) fn release_notes(&self) -> Option<&str> {
+        match self {
+            Self::Image { release_notes, .. } => release_notes.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn release_notes_url(&self) -> Option<&url::Url> {
+        match self {
+            Self::Image {
+                release_notes_url, ..
+            } => release_notes_url.as_ref(),
+            _ => None,
         }
     }
 
     pub(crate) fn file_name(&self) -> Option<String> {
         match self {
-            Self::SdFormat { .. } => None,
+            Self::SdFormat { .. } | Self::SdCustomize { .. } | Self::Bundle { .. } => None,
             Self::Image { img, .. } => Some(img.file_name()),
         }
     }
@@ -260,7 +596,44 @@ impl BoardImage {
     pub(crate) fn details(&self) -> &[(&'static str, String)] {
         match self {
             BoardImage::SdFormat { details } => details,
+            BoardImage::SdCustomize { details } => details,
             BoardImage::Image { details, .. } => details,
+            BoardImage::Bundle { details, .. } => details,
+        }
+    }
+
+    /// SHA256 of the image before extraction. Only known for remote images.
+    pub(crate) fn sha256(&self) -> Option<[u8; 32]> {
+        match self {
+            Self::SdFormat { .. } | Self::SdCustomize { .. } | Self::Bundle { .. } => None,
+            Self::Image { img, .. } => img.sha256(),
+        }
+    }
+
+    /// Size of the image after extraction. Only known for remote images.
+    pub(crate) fn image_size(&self) -> Option<u64> {
+        match self {
+            Self::SdFormat { .. } | Self::SdCustomize { .. } | Self::Bundle { .. } => None,
+            Self::Image { img, .. } => img.image_size(),
+        }
+    }
+
+    /// Bmap used when writing to SD Cards.
+    pub(crate) const fn bmap(&self) -> Option<&Bmap> {
+        match self {
+            Self::Image { bmap, .. } => bmap.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Path of the image in local filesystem. Only known for local images.
+    pub(crate) fn local_path(&self) -> Option<PathBuf> {
+        match self {
+            Self::Image {
+                img: SelectedImage::LocalImage(x),
+                ..
+            } => Some(x.path().to_path_buf()),
+            _ => None,
         }
     }
 }
@@ -269,14 +642,24 @@ impl std::fmt::Display for BoardImage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             BoardImage::SdFormat { .. } => write!(f, "Format SD Card"),
+            BoardImage::SdCustomize { .. } => write!(f, "Customize SD Card"),
+            BoardImage::Bundle { name, .. } => write!(f, "{name}"),
             BoardImage::Image { img: image, .. } => image.fmt(f),
         }
     }
 }
 
-pub(crate) fn system_timezone() -> Option<&'static String> {
-    static SYSTEM_TIMEZONE: LazyLock<Option<String>> = LazyLock::new(localzone::get_local_zone);
-    (*SYSTEM_TIMEZONE).as_ref()
+/// Timezone of the host, if it is in [`TIMEZONES`](crate::constants::TIMEZONES). Falls back to
+/// UTC, so enabling the timezone option always has a valid selection.
+pub(crate) fn system_timezone() -> &'static str {
+    static SYSTEM_TIMEZONE: LazyLock<Option<&'static str>> = LazyLock::new(|| {
+        let tz = localzone::get_local_zone()?;
+        crate::constants::TIMEZONES
+            .iter()
+            .find(|x| x.eq_ignore_ascii_case(&tz))
+            .copied()
+    });
+    (*SYSTEM_TIMEZONE).unwrap_or("Etc/UTC")
 }
 
 pub(crate) fn system_keymap() -> String {
@@ -291,8 +674,9 @@ pub(crate) fn system_keymap() -> String {
         if let Some(region) = parts.next() {
             let region = region.split('@').next().unwrap_or(region).trim();
             if !region.is_empty()
-                && let Some(&canon) = crate::constants::KEYMAP_LAYOUTS
+                && let Some(canon) = crate::constants::KEYMAPS
                     .iter()
+                    .map(|(k, _, _)| *k)
                     .find(|k| k.eq_ignore_ascii_case(region))
             {
                 return Some(canon.to_string());
@@ -311,32 +695,122 @@ pub(crate) fn system_keymap() -> String {
 pub(crate) struct RemoteImage {
     name: Box<str>,
     url: Box<url::Url>,
+    metalink: Option<Box<url::Url>>,
     extract_sha256: [u8; 32],
     extract_size: u64,
+    deltas: Vec<config::ImageDelta>,
     downloader: bb_downloader::Downloader,
+    download_size: Option<u64>,
+    scratch: bb_flasher::ScratchSpace,
 }
 
 impl RemoteImage {
     pub(crate) fn new(
         name: Box<str>,
         url: Box<url::Url>,
+        metalink: Option<Box<url::Url>>,
         extract_sha256: [u8; 32],
         extract_size: u64,
+        deltas: Vec<config::ImageDelta>,
         downloader: bb_downloader::Downloader,
     ) -> Self {
         Self {
             name,
             url,
+            metalink,
             extract_sha256,
             extract_size,
+            deltas,
             downloader,
+            download_size: None,
+            scratch: Default::default(),
+        }
+    }
+
+    /// Size of the file being downloaded, if known. Used to check for scratch space before
+    /// streaming the download.
+    pub(crate) const fn with_download_size(mut self, size: Option<u64>) -> Self {
+        self.download_size = size;
+        self
+    }
+
+    /// Find the image in cache. If not present, try reconstructing it from an older image in cache
+    /// using deltas.
+    async fn cached(&self) -> Option<PathBuf> {
+        if let Some(p) = self
+            .downloader
+            .check_cache_from_sha(self.extract_sha256)
+            .await
+        {
+            return Some(p);
+        }
+
+        for d in &self.deltas {
+            if let Some(p) = self.downloader.check_cache_from_sha(d.extract_sha256).await {
+                return Some(p);
+            }
+        }
+
+        for d in &self.deltas {
+            let Some(base) = self.downloader.check_cache_from_sha(d.base_sha256).await else {
+                continue;
+            };
+
+            tracing::info!("Reconstructing image from older image in cache");
+            let res = match bb_flasher::OsImage::from_path(&base) {
+                Ok(base) => {
+                    self.downloader
+                        .download_with_delta(base, d.url.clone(), d.sha256, d.extract_sha256, None)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
+            match res {
+                Ok(p) => return Some(p),
+                Err(e) => tracing::warn!("Failed to apply delta: {e}"),
+            }
         }
+
+        None
     }
 
     fn file_name(&self) -> &str {
         self.url.path_segments().unwrap().next_back().unwrap()
     }
 
+    /// Url to download the image from. Prefers mirrors from metalink if present.
+    async fn download_url(&self) -> url::Url {
+        match &self.metalink {
+            Some(x) => {
+                self.downloader
+                    .resolve_metalink(*x.clone(), *self.url.clone(), self.extract_sha256)
+                    .await
+            }
+            None => *self.url.clone(),
+        }
+    }
+
+    /// Download the image to cache, unless already present.
+    async fn download(
+        &self,
+        chan: Option<futures::channel::mpsc::Sender<f32>>,
+    ) -> std::io::Result<PathBuf> {
+        match self
+            .downloader
+            .check_cache_from_sha(self.extract_sha256)
+            .await
+        {
+            Some(p) => Ok(p),
+            None => {
+                let url = self.download_url().await;
+                self.downloader
+                    .download_with_sha(url, self.extract_sha256, chan)
+                    .await
+            }
+        }
+    }
+
     async fn save(
         &self,
         path: &std::path::Path,
@@ -350,10 +824,7 @@ impl RemoteImage {
             }
         });
 
-        let p = self
-            .downloader
-            .download_with_sha(*self.url.clone(), self.extract_sha256, Some(tx))
-            .await?;
+        let p = self.download(Some(tx)).await?;
         tokio::fs::copy(p, path).await?;
         handle.abort();
 
@@ -368,22 +839,18 @@ impl bb_flasher::Resolvable for RemoteImage {
         &self,
         rt: &mut tokio::task::JoinSet<std::io::Result<()>>,
     ) -> std::io::Result<Self::ResolvedType> {
-        if let Some(path) = self
-            .downloader
-            .check_cache_from_sha(self.extract_sha256)
-            .await
-        {
+        if let Some(path) = self.cached().await {
             tracing::info!("Found the remote image in cache");
             Ok((bb_flasher::OsImage::from_path(&path)?, self.extract_size))
         } else {
             tracing::info!("Remote image not found in cache. Downloading");
-            let (tx, rx) = bb_helper::file_stream::file_stream()?;
+            let (tx, rx) = self.scratch.file_stream(self.download_size)?;
             let downloader = self.downloader.clone();
-            let url = self.url.clone();
+            let url = self.download_url().await;
             let sha = self.extract_sha256;
             rt.spawn(async move {
                 downloader
-                    .download_to_stream(*url, sha, tx)
+                    .download_to_stream(url, sha, tx)
                     .await
                     .map_err(|e| {
                         let msg = format!("Error while downloading Os Image: {e}");
@@ -411,10 +878,127 @@ impl std::fmt::Display for RemoteImage {
     }
 }
 
+/// Image published as an OCI artifact. The catalog SHA256 is the digest of the image layer.
 #[derive(Debug, Clone)]
-pub(crate) struct Bmap {
-    url: Box<Url>,
+pub(crate) struct OciImage {
+    name: Box<str>,
+    reference: bb_downloader::oci::Reference,
+    sha256: [u8; 32],
+    extract_size: u64,
     downloader: bb_downloader::Downloader,
+    scratch: bb_flasher::ScratchSpace,
+}
+
+impl OciImage {
+    fn file_name(&self) -> String {
+        let r = self.reference.to_string();
+        r.rsplit('/').next().unwrap_or(&r).replace([':', '@'], "-")
+    }
+
+    /// Resolve the artifact and make sure it is the one listed in the catalog.
+    async fn artifact(&self) -> std::io::Result<bb_downloader::oci::Artifact> {
+        let artifact = self.downloader.resolve_oci(&self.reference).await?;
+        if artifact.sha256 != self.sha256 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "OCI artifact digest does not match the catalog",
+            ));
+        }
+
+        Ok(artifact)
+    }
+
+    async fn pull(
+        &self,
+        chan: Option<futures::channel::mpsc::Sender<f32>>,
+    ) -> std::io::Result<PathBuf> {
+        if let Some(p) = self.downloader.check_cache_from_sha(self.sha256).await {
+            return Ok(p);
+        }
+
+        let artifact = self.artifact().await?;
+        self.downloader.download_oci(&artifact, chan).await
+    }
+
+    async fn save(
+        &self,
+        path: &std::path::Path,
+        mut chan: futures::channel::mpsc::Sender<DownloadFlashingStatus>,
+    ) -> std::io::Result<()> {
+        let (tx, mut rx) = futures::channel::mpsc::channel(5);
+
+        let handle = tokio::spawn(async move {
+            while let Some(x) = futures::StreamExt::next(&mut rx).await {
+                let _ = chan.try_send(DownloadFlashingStatus::DownloadingProgress(x));
+            }
+        });
+
+        // Stop forwarding progress on errors too
+        let res = self.pull(Some(tx)).await;
+        handle.abort();
+        tokio::fs::copy(res?, path).await?;
+
+        Ok(())
+    }
+}
+
+impl bb_flasher::Resolvable for OciImage {
+    type ResolvedType = (bb_flasher::OsImage, u64);
+
+    async fn resolve(
+        &self,
+        rt: &mut tokio::task::JoinSet<std::io::Result<()>>,
+    ) -> std::io::Result<Self::ResolvedType> {
+        if let Some(path) = self.downloader.check_cache_from_sha(self.sha256).await {
+            tracing::info!("Found the OCI image in cache");
+            let img = tokio::task::spawn_blocking(move || bb_flasher::OsImage::from_path(&path))
+                .await
+                .unwrap()?;
+
+            return Ok((img, self.extract_size));
+        }
+
+        tracing::info!("OCI image not found in cache. Downloading");
+        let artifact = self.artifact().await?;
+        let (tx, rx) = self.scratch.file_stream(Some(artifact.size))?;
+        let downloader = self.downloader.clone();
+        rt.spawn(async move {
+            downloader
+                .download_oci_to_stream(&artifact, tx)
+                .await
+                .map_err(|e| {
+                    let msg = format!("Error while downloading OCI image: {e}");
+                    tracing::error!("{}", &msg);
+                    std::io::Error::other(msg)
+                })?;
+            tracing::info!("Image download finished");
+            Ok(())
+        });
+
+        let extract_size = self.extract_size;
+        let img =
+            tokio::task::spawn_blocking(move || bb_flasher::OsImage::from_piped(rx, extract_size))
+                .await
+                .unwrap()?;
+
+        Ok((img, self.extract_size))
+    }
+}
+
+impl std::fmt::Display for OciImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Bmap {
+    Remote {
+        url: Box<Url>,
+        downloader: bb_downloader::Downloader,
+    },
+    /// Bmap next to a local image
+    Local(PathBuf),
 }
 
 impl bb_flasher::Resolvable for Bmap {
@@ -424,7 +1008,10 @@ impl bb_flasher::Resolvable for Bmap {
         &self,
         _: &mut tokio::task::JoinSet<std::io::Result<()>>,
     ) -> std::io::Result<Self::ResolvedType> {
-        let p = self.downloader.download(*self.url.clone(), None).await?;
+        let p = match self {
+            Self::Remote { url, downloader } => downloader.download(*url.clone(), None).await?,
+            Self::Local(p) => p.clone(),
+        };
 
         tokio::fs::read_to_string(p).await.map(Into::into)
     }
@@ -434,6 +1021,7 @@ impl bb_flasher::Resolvable for Bmap {
 pub(crate) enum SelectedImage {
     LocalImage(bb_flasher::LocalImage),
     RemoteImage(RemoteImage),
+    Oci(OciImage),
 }
 
 impl SelectedImage {
@@ -441,53 +1029,267 @@ impl SelectedImage {
         match self {
             Self::LocalImage(x) => x.file_name().to_string_lossy().to_string(),
             Self::RemoteImage(x) => x.file_name().to_string(),
+            Self::Oci(x) => x.file_name(),
         }
     }
 
-    async fn save(
-        &self,
-        path: &std::path::Path,
-        chan: futures::channel::mpsc::Sender<DownloadFlashingStatus>,
-    ) -> std::io::Result<()> {
-        match self {
-            Self::LocalImage(x) => tokio::fs::copy(x.path(), path).await.map(|_| ()),
-            Self::RemoteImage(x) => x.save(path, chan).await,
+    /// Check integrity of cached remote images. Corrupted images are removed from cache and thus
+    /// downloaded again. Images present in cache are used as local images to avoid checking again.
+    async fn check_cache(
+        &mut self,
+        chan: &mut futures::channel::mpsc::Sender<DownloadFlashingStatus>,
+    ) {
+        if let Self::LocalImage(_) = self {
+            return;
         }
-    }
-}
 
-impl bb_flasher::Resolvable for SelectedImage {
-    type ResolvedType = (bb_flasher::OsImage, u64);
+        let _ = chan.try_send(DownloadFlashingStatus::CheckingImage);
+        let cached = match self {
+            Self::LocalImage(_) => None,
+            Self::RemoteImage(x) => x.cached().await,
+            Self::Oci(x) => x.downloader.check_cache_from_sha(x.sha256).await,
+        };
 
-    async fn resolve(
-        &self,
-        rt: &mut tokio::task::JoinSet<std::io::Result<()>>,
-    ) -> std::io::Result<Self::ResolvedType> {
-        match self {
-            SelectedImage::LocalImage(x) => x.resolve(rt).await,
-            SelectedImage::RemoteImage(x) => x.resolve(rt).await,
+        match cached {
+            Some(p) => {
+                tracing::info!("Cached image is valid");
+                *self = Self::LocalImage(bb_flasher::LocalImage::new(p.into()));
+            }
+            None => tracing::info!("Image not present in cache"),
         }
     }
-}
 
-impl std::fmt::Display for SelectedImage {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            SelectedImage::LocalImage(x) => x.fmt(f),
-            SelectedImage::RemoteImage(x) => x.fmt(f),
-        }
-    }
-}
+    /// Download remote images to cache before flashing instead of streaming them to the
+    /// destination while downloading.
+    async fn download(
+        &mut self,
+        chan: &mut futures::channel::mpsc::Sender<DownloadFlashingStatus>,
+    ) -> std::io::Result<()> {
+        let (tx, mut rx) = futures::channel::mpsc::channel(5);
+        let mut chan = chan.clone();
 
-impl From<RemoteImage> for SelectedImage {
-    fn from(value: RemoteImage) -> Self {
-        Self::RemoteImage(value)
-    }
-}
+        let handle = tokio::spawn(async move {
+            while let Some(x) = futures::StreamExt::next(&mut rx).await {
+                let _ = chan.try_send(DownloadFlashingStatus::DownloadingProgress(x));
+            }
+        });
 
-impl From<bb_flasher::LocalImage> for SelectedImage {
-    fn from(value: bb_flasher::LocalImage) -> Self {
-        Self::LocalImage(value)
+        let p = match self {
+            Self::LocalImage(_) => return Ok(()),
+            Self::RemoteImage(x) => x.download(Some(tx)).await?,
+            Self::Oci(x) => x.pull(Some(tx)).await?,
+        };
+        handle.abort();
+
+        *self = Self::LocalImage(bb_flasher::LocalImage::new(p.into()));
+        Ok(())
+    }
+
+    /// Check that there is enough free space to download and extract the image, so flashing fails
+    /// before starting instead of midway.
+    fn preflight(&self, dst: &Destination, options: &FlashOptions) -> anyhow::Result<()> {
+        if let Destination::Image(p) = dst
+            && let Some(size) = self.image_size()
+        {
+            let dir = p.parent().unwrap_or(std::path::Path::new("."));
+            bb_flasher::check_free_space(dir, size)?;
+        }
+
+        // Size of OCI artifacts is only known after resolving
+        let (downloader, sha256, size) = match self {
+            Self::LocalImage(_) | Self::Oci(_) => return Ok(()),
+            Self::RemoteImage(x) => match x.download_size {
+                Some(size) => (&x.downloader, x.extract_sha256, size),
+                None => return Ok(()),
+            },
+        };
+
+        if downloader.check_cache(sha256).is_some() {
+            return Ok(());
+        }
+
+        if let Destination::LocalFile(p) = dst {
+            let dir = p.parent().unwrap_or(std::path::Path::new("."));
+            bb_flasher::check_free_space(dir, size)?;
+        } else if options.stream_download {
+            options.scratch.check(size)?;
+            return Ok(());
+        }
+
+        bb_flasher::check_free_space(downloader.cache_dir(), size).map_err(|e| {
+            let msg =
+                format!("{e} Remove old images from the cache directory or change it in Settings.");
+            anyhow::Error::from(e).context(msg)
+        })
+    }
+
+    /// Scratch space to use for streaming remote images while downloading.
+    fn set_scratch(&mut self, scratch: bb_flasher::ScratchSpace) {
+        match self {
+            Self::LocalImage(_) => {}
+            Self::RemoteImage(x) => x.scratch = scratch,
+            Self::Oci(x) => x.scratch = scratch,
+        }
+    }
+
+    const fn sha256(&self) -> Option<[u8; 32]> {
+        match self {
+            Self::LocalImage(_) => None,
+            Self::RemoteImage(x) => Some(x.extract_sha256),
+            Self::Oci(x) => Some(x.sha256),
+        }
+    }
+
+    const fn image_size(&self) -> Option<u64> {
+        match self {
+            Self::LocalImage(_) => None,
+            Self::RemoteImage(x) => Some(x.extract_size),
+            Self::Oci(x) => Some(x.extract_size),
+        }
+    }
+
+    async fn save(
+        &self,
+        path: &std::path::Path,
+        chan: futures::channel::mpsc::Sender<DownloadFlashingStatus>,
+    ) -> std::io::Result<()> {
+        match self {
+            Self::LocalImage(x) => tokio::fs::copy(x.path(), path).await.map(|_| ()),
+            Self::RemoteImage(x) => x.save(path, chan).await,
+            Self::Oci(x) => x.save(path, chan).await,
+        }
+    }
+}
+
+impl bb_flasher::Resolvable for SelectedImage {
+    type ResolvedType = (bb_flasher::OsImage, u64);
+
+    async fn resolve(
+        &self,
+        rt: &mut tokio::task::JoinSet<std::io::Result<()>>,
+    ) -> std::io::Result<Self::ResolvedType> {
+        match self {
+            SelectedImage::LocalImage(x) => x.resolve(rt).await,
+            SelectedImage::RemoteImage(x) => x.resolve(rt).await,
+            SelectedImage::Oci(x) => x.resolve(rt).await,
+        }
+    }
+}
+
+impl std::fmt::Display for SelectedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectedImage::LocalImage(x) => x.fmt(f),
+            SelectedImage::RemoteImage(x) => x.fmt(f),
+            SelectedImage::Oci(x) => x.fmt(f),
+        }
+    }
+}
+
+impl From<RemoteImage> for SelectedImage {
+    fn from(value: RemoteImage) -> Self {
+        Self::RemoteImage(value)
+    }
+}
+
+impl From<OciImage> for SelectedImage {
+    fn from(value: OciImage) -> Self {
+        Self::Oci(value)
+    }
+}
+
+impl From<bb_flasher::LocalImage> for SelectedImage {
+    fn from(value: bb_flasher::LocalImage) -> Self {
+        Self::LocalImage(value)
+    }
+}
+
+/// Application settings that affect flashing.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FlashOptions {
+    /// SD Card write speed limit in bytes per second.
+    pub(crate) write_limit: Option<std::num::NonZeroU64>,
+    /// Read back verification of SD Cards.
+    pub(crate) verify: bb_flasher::sd::Verify,
+    /// Stream remote images to the destination while downloading.
+    pub(crate) stream_download: bool,
+    pub(crate) scratch: bb_flasher::ScratchSpace,
+    /// Time limits for each stage of flashing.
+    pub(crate) timeouts: bb_flasher::Timeouts,
+}
+
+impl FlashOptions {
+    pub(crate) fn new(app_config: &crate::persistance::GuiConfiguration) -> Self {
+        Self {
+            write_limit: app_config
+                .write_limit()
+                .and_then(|x| std::num::NonZeroU64::new(x.saturating_mul(1024 * 1024))),
+            verify: app_config.sd_verify().into(),
+            stream_download: !app_config.cache_before_flash(),
+            scratch: bb_flasher::ScratchSpace {
+                dir: app_config.scratch_dir().map(Into::into),
+                preallocate: true,
+            },
+            timeouts: bb_flasher::Timeouts::NONE,
+        }
+    }
+}
+
+/// Stage timeouts for flashing `dst`. Values from the catalog take precedence over flasher
+/// defaults.
+pub(crate) fn flash_timeouts(
+    dst: &Destination,
+    catalog: Option<&config::Timeouts>,
+) -> bb_flasher::Timeouts {
+    let defaults = match dst {
+        Destination::LocalFile(_) | Destination::Image(_) | Destination::SdCard(_) => {
+            bb_flasher::sd::DEFAULT_TIMEOUTS
+        }
+        #[cfg(feature = "bcf_cc1352p7")]
+        Destination::BeagleConnectFreedom(_) => bb_flasher::bcf::cc1352p7::DEFAULT_TIMEOUTS,
+        #[cfg(feature = "bcf_msp430")]
+        Destination::Msp430(_) => bb_flasher::bcf::msp430::DEFAULT_TIMEOUTS,
+        #[cfg(feature = "pb2_mspm0")]
+        Destination::Pb2Mspm0 => bb_flasher::pb2::mspm0::DEFAULT_TIMEOUTS,
+    };
+
+    match catalog {
+        Some(x) => bb_flasher::Timeouts {
+            download: x.download_secs.map(Duration::from_secs),
+            flash: x.flash_secs.map(Duration::from_secs),
+            verify: x.verify_secs.map(Duration::from_secs),
+            customize: x.customize_secs.map(Duration::from_secs),
+        }
+        .or(defaults),
+        None => defaults,
+    }
+}
+
+/// Complete flashing job, including downloading the image. Allows enforcing timeouts on all
+/// stages using [`bb_flasher::Timed`].
+struct FlashingTask {
+    img: BoardImage,
+    customization: FlashingCustomization,
+    dst: Destination,
+    options: FlashOptions,
+    cancel: tokio_util::sync::CancellationToken,
+}
+
+impl BBFlasher for FlashingTask {
+    async fn flash(
+        self,
+        chan: Option<futures::channel::mpsc::Sender<DownloadFlashingStatus>>,
+    ) -> anyhow::Result<()> {
+        let chan = chan.expect("Progress channel is always provided by Timed");
+        flash_inner(
+            self.img,
+            self.customization,
+            self.dst,
+            self.options,
+            chan,
+            self.cancel,
+        )
+        .await
     }
 }
 
@@ -495,24 +1297,100 @@ pub(crate) async fn flash(
     img: BoardImage,
     customization: FlashingCustomization,
     dst: Destination,
-    chan: futures::channel::mpsc::Sender<DownloadFlashingStatus>,
+    options: FlashOptions,
+    job: bb_flasher::JobId,
+    chan: futures::channel::mpsc::Sender<bb_flasher::ProgressEvent>,
     cancel: tokio_util::sync::CancellationToken,
 ) -> anyhow::Result<()> {
+    let timeouts = options.timeouts;
+    let task = FlashingTask {
+        img,
+        customization,
+        dst,
+        options,
+        cancel: cancel.clone(),
+    };
+
+    bb_flasher::Timed::new(task, timeouts)
+        .cancel(cancel)
+        .flash_job(job, Some(chan))
+        .await
+}
+
+async fn flash_inner(
+    mut img: BoardImage,
+    customization: FlashingCustomization,
+    dst: Destination,
+    options: FlashOptions,
+    mut chan: futures::channel::mpsc::Sender<DownloadFlashingStatus>,
+    cancel: tokio_util::sync::CancellationToken,
+) -> anyhow::Result<()> {
+    let write_limit = options.write_limit;
+    let verify = options.verify;
+
+    if let BoardImage::Image { img, .. } = &img {
+        img.preflight(&dst, &options)?;
+    }
+
+    // Catch corrupted cache before anything is written to the destination
+    if let BoardImage::Image { img, .. } = &mut img
+        && !matches!(dst, Destination::LocalFile(_))
+    {
+        img.check_cache(&mut chan).await;
+
+        if options.stream_download {
+            img.set_scratch(options.scratch);
+        } else {
+            img.download(&mut chan).await?;
+        }
+    }
+
+    let provenance = provenance(&img);
+
     match (img, customization, dst) {
         (BoardImage::Image { img, .. }, _, Destination::LocalFile(f)) => {
             img.save(&f, chan).await.map_err(Into::into)
         }
+        (
+            BoardImage::Image { img, .. },
+            FlashingCustomization::LinuxSdSysconfig(customization),
+            Destination::Image(p),
+        ) => {
+            let customization =
+                FlashingSdLinuxConfig::from(customization).with_provenance(provenance);
+            bb_flasher::sd::ExportFlasher::new(img, p, customization, Some(cancel))
+                .flash(Some(chan))
+                .await
+        }
+        (BoardImage::Image { img, .. }, FlashingCustomization::NoneSd, Destination::Image(p)) => {
+            bb_flasher::sd::ExportFlasher::new(img, p, FlashingSdLinuxConfig::none(), Some(cancel))
+                .flash(Some(chan))
+                .await
+        }
         (BoardImage::SdFormat { .. }, _, Destination::SdCard(t)) => {
             bb_flasher::sd::FormatFlasher::new(t)
                 .flash(Some(chan))
                 .await
         }
+        (
+            BoardImage::SdCustomize { .. },
+            FlashingCustomization::LinuxSdSysconfig(customization),
+            Destination::SdCard(t),
+        ) => {
+            bb_flasher::sd::CustomizeFlasher::new(t, customization.into(), Some(cancel))
+                .flash(Some(chan))
+                .await
+        }
         (
             BoardImage::Image { img, bmap, .. },
             FlashingCustomization::LinuxSdSysconfig(customization),
             Destination::SdCard(t),
         ) => {
-            bb_flasher::sd::Flasher::new(img, bmap, t, customization.into(), Some(cancel))
+            let customization = FlashingSdLinuxConfig::from(customization)
+                .with_provenance(provenance)
+                .with_verify(verify);
+            bb_flasher::sd::Flasher::new(img, bmap, t, customization, Some(cancel))
+                .write_limit(write_limit)
                 .flash(Some(chan))
                 .await
         }
@@ -521,7 +1399,9 @@ pub(crate) async fn flash(
             FlashingCustomization::NoneSd,
             Destination::SdCard(t),
         ) => {
-            bb_flasher::sd::Flasher::new(img, bmap, t, FlashingSdLinuxConfig::none(), Some(cancel))
+            let customization = FlashingSdLinuxConfig::none().with_verify(verify);
+            bb_flasher::sd::Flasher::new(img, bmap, t, customization, Some(cancel))
+                .write_limit(write_limit)
                 .flash(Some(chan))
                 .await
         }
@@ -531,7 +1411,13 @@ pub(crate) async fn flash(
             FlashingCustomization::Bcf(customization),
             Destination::BeagleConnectFreedom(t),
         ) => {
-            bb_flasher::bcf::cc1352p7::Flasher::new(img, t, customization.verify, Some(cancel))
+            let config = bb_flasher::bcf::cc1352p7::FlashingBcfConfig {
+                verify: customization.verify,
+                slot: None,
+                port: bcf_port_config(customization.serial.as_ref()),
+            };
+
+            bb_flasher::bcf::cc1352p7::Flasher::new(img, t, config, Some(cancel))
                 .flash(Some(chan))
                 .await
         }
@@ -555,9 +1441,88 @@ pub(crate) async fn flash(
     }
 }
 
+/// Record of the image written to the boot partition when customizing.
+fn provenance(img: &BoardImage) -> bb_flasher::sd::Provenance {
+    bb_flasher::sd::Provenance {
+        image: img.to_string().into(),
+        image_version: img
+            .details()
+            .iter()
+            .find(|(k, _)| *k == "Release Date")
+            .map(|(_, v)| v.as_str().into()),
+        image_sha256: img.sha256(),
+        imager: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")).into(),
+    }
+}
+
+/// Inspect partitions and metadata of an image. Remote images can only be inspected once they are
+/// present in cache.
+pub(crate) async fn inspect_image(
+    img: BoardImage,
+    downloader: bb_downloader::Downloader,
+) -> Result<bb_flasher::sd::ImageInfo, String> {
+    let path = match (img.local_path(), img.sha256()) {
+        (Some(p), _) => p,
+        (None, Some(sha)) => downloader
+            .check_cache_from_sha(sha)
+            .await
+            .ok_or("Image needs to be downloaded before it can be inspected")?,
+        (None, None) => return Err("Image cannot be inspected".to_string()),
+    };
+
+    tokio::task::spawn_blocking(move || bb_flasher::sd::inspect(&path))
+        .await
+        .expect("Tokio runtime failed to spawn task")
+        .map_err(|e| {
+            tracing::error!("Failed to inspect image: {e:?}");
+            e.to_string()
+        })
+}
+
+/// Developer settings to simulate flashing using [`bb_flasher::mock::Flasher`].
+#[cfg(feature = "demo")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MockFlasherConfig {
+    pub(crate) enabled: bool,
+    /// Duration of each flashing stage in seconds.
+    pub(crate) stage_secs: u8,
+    pub(crate) fail_at: Option<bb_flasher::mock::Stage>,
+}
+
+#[cfg(feature = "demo")]
+impl Default for MockFlasherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stage_secs: 5,
+            fail_at: None,
+        }
+    }
+}
+
+#[cfg(feature = "demo")]
+impl MockFlasherConfig {
+    pub(crate) async fn flash(
+        self,
+        job: bb_flasher::JobId,
+        chan: futures::channel::mpsc::Sender<bb_flasher::ProgressEvent>,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> anyhow::Result<()> {
+        bb_flasher::mock::Flasher::new(
+            Duration::from_secs(self.stage_secs.into()),
+            self.fail_at,
+            Some(cancel),
+        )
+        .flash_job(job, Some(chan))
+        .await
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub(crate) enum Destination {
     LocalFile(PathBuf),
+    /// Image file with customization applied, ready to be flashed later.
+    Image(PathBuf),
     SdCard(bb_flasher::sd::Target),
     #[cfg(feature = "bcf_cc1352p7")]
     BeagleConnectFreedom(bb_flasher::bcf::cc1352p7::Target),
@@ -571,6 +1536,7 @@ impl Display for Destination {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Destination::LocalFile(_) => write!(f, "Save To File"),
+            Destination::Image(_) => write!(f, "Export Customized Image"),
             Destination::SdCard(target) => target.fmt(f),
             #[cfg(feature = "bcf_cc1352p7")]
             Destination::BeagleConnectFreedom(target) => target.fmt(f),
@@ -584,7 +1550,7 @@ impl Display for Destination {
 
 impl Destination {
     #[allow(irrefutable_let_patterns)]
-    pub(crate) fn size(&self) -> Option<u64> {
+    pub(crate) fn size(&self) -> Option<ByteSize> {
         if let Destination::SdCard(item) = self {
             Some(item.size())
         } else {
@@ -592,18 +1558,125 @@ impl Destination {
         }
     }
 
+    /// USB port of the destination. Only known for SD Cards.
+    pub(crate) fn location(&self) -> Option<&bb_flasher::sd::UsbLocation> {
+        match self {
+            Self::SdCard(t) => t.location(),
+            _ => None,
+        }
+    }
+
+    /// Path or identifier of the destination.
+    pub(crate) fn identifier(&self) -> String {
+        self.details()
+            .into_iter()
+            .find(|(k, _)| *k == "Path")
+            .map(|(_, v)| v)
+            .unwrap_or_else(|| self.to_string())
+    }
+
+    /// Reason the destination cannot be selected.
+    pub(crate) const fn disabled_reason(&self) -> Option<&'static str> {
+        match self {
+            Self::SdCard(t) if t.is_read_only() => {
+                Some("SD Card is write protected. Check the lock switch on the card or adapter.")
+            }
+            _ => None,
+        }
+    }
+
     /// Download instead of flashing
     pub(crate) fn is_download_action(&self) -> bool {
         matches!(self, Self::LocalFile(_))
     }
 
+    /// Destination can be physically identified, e.g. by blinking an activity LED.
+    pub(crate) const fn supports_identify(&self) -> bool {
+        match self {
+            Self::SdCard(_) => true,
+            #[cfg(feature = "bcf_cc1352p7")]
+            Self::BeagleConnectFreedom(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Blink the card reader activity LED, or toggle DTR on serial devices, so that the user can
+    /// confirm the selected destination. Does not modify the destination.
+    pub(crate) async fn identify(self) -> Result<(), String> {
+        let resp = match self {
+            Self::SdCard(t) => t.identify(constants::IDENTIFY_DURATION).await,
+            #[cfg(feature = "bcf_cc1352p7")]
+            Self::BeagleConnectFreedom(t) => t.identify(constants::IDENTIFY_DURATION).await,
+            _ => return Err("Identifying not supported".to_string()),
+        };
+
+        resp.map_err(|e| {
+            tracing::error!("Failed to identify destination: {e:?}");
+            e.to_string()
+        })
+    }
+
+    /// Bootloader and firmware version can be queried.
+    #[cfg(feature = "bcf_cc1352p7")]
+    pub(crate) const fn supports_probe(&self) -> bool {
+        match self {
+            Self::BeagleConnectFreedom(_) => true,
+            #[cfg(feature = "bcf_msp430")]
+            Self::Msp430(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Query bootloader and firmware version.
+    #[cfg(feature = "bcf_cc1352p7")]
+    pub(crate) async fn probe(
+        self,
+        config: bb_flasher::bcf::cc1352p7::PortConfig,
+    ) -> Result<bb_flasher::bcf::DeviceVersion, String> {
+        let resp = match self {
+            Self::BeagleConnectFreedom(t) => t.probe(config).await,
+            #[cfg(feature = "bcf_msp430")]
+            Self::Msp430(t) => t.probe().await,
+            _ => return Err("Probing not supported".to_string()),
+        };
+
+        resp.map_err(|e| e.to_string())
+    }
+
     pub(crate) fn details(&self) -> Vec<(&'static str, String)> {
         match self {
-            Self::LocalFile(p) => vec![("Path", p.to_string_lossy().to_string())],
-            Self::SdCard(t) => vec![
-                ("Path", t.path().to_string_lossy().to_string()),
-                ("Size", pretty_bytes(t.size())),
-            ],
+            Self::LocalFile(p) | Self::Image(p) => vec![("Path", p.to_string_lossy().to_string())],
+            Self::SdCard(t) => {
+                let mut details = vec![
+                    ("Path", t.path().to_string_lossy().to_string()),
+                    ("Size", t.size().to_string()),
+                ];
+                if let Some(x) = t.location() {
+                    details.push(("USB Port", x.to_string()));
+                }
+                if let Some(x) = t.reader() {
+                    details.push(("Card Reader", x.to_string()));
+                }
+                if let Some(x) = t.card() {
+                    let manufacturer = match x.manufacturer() {
+                        Some(m) => format!("{m} (0x{:02x}, OEM {})", x.manufacturer_id, x.oem_id),
+                        None => format!("0x{:02x} (OEM {})", x.manufacturer_id, x.oem_id),
+                    };
+                    details.push(("Card Manufacturer", manufacturer));
+                    details.push((
+                        "Card Model",
+                        format!("{} rev {}.{}", x.name, x.revision.0, x.revision.1),
+                    ));
+                    details.push((
+                        "Manufactured",
+                        format!("{:02}/{}", x.manufactured.1, x.manufactured.0),
+                    ));
+                    if let Some(c) = x.class {
+                        details.push(("Card Type", c.to_string()));
+                    }
+                }
+                details
+            }
             #[cfg(feature = "bcf_cc1352p7")]
             Self::BeagleConnectFreedom(t) => vec![("Path", t.path().to_string())],
             #[cfg(feature = "bcf_msp430")]
@@ -614,33 +1687,207 @@ impl Destination {
     }
 }
 
-pub(crate) async fn destinations(flasher: config::Flasher, filter: bool) -> Vec<Destination> {
+/// Destinations for a flasher. USB destinations are filtered by `usb_ids` (VID, PID) of the board
+/// when `filter` is set.
+#[cfg_attr(not(feature = "bcf_cc1352p7"), allow(unused_variables))]
+pub(crate) async fn destinations(
+    flasher: config::Flasher,
+    filter: bool,
+    usb_ids: &[(u16, u16)],
+) -> Vec<Destination> {
     match flasher {
-        config::Flasher::SdCard => bb_flasher::sd::Target::destinations(filter)
-            .await
-            .into_iter()
-            .map(Destination::SdCard)
-            .collect(),
+        config::Flasher::SdCard => {
+            let mut dsts: Vec<_> = bb_flasher::sd::Target::destinations(filter)
+                .await
+                .into_iter()
+                .collect();
+            bb_flasher::sd::Target::sort(&mut dsts);
+            dsts.into_iter().map(Destination::SdCard).collect()
+        }
         #[cfg(feature = "bcf_cc1352p7")]
         config::Flasher::BeagleConnectFreedom => {
-            bb_flasher::bcf::cc1352p7::Target::destinations(filter)
-                .await
+            bb_flasher::bcf::cc1352p7::Target::destinations_with_ids(filter, usb_ids)
                 .into_iter()
                 .map(Destination::BeagleConnectFreedom)
                 .collect()
         }
         #[cfg(feature = "bcf_msp430")]
-        config::Flasher::Msp430Usb => bb_flasher::bcf::msp430::Target::destinations(filter)
-            .await
-            .into_iter()
-            .map(Destination::Msp430)
-            .collect(),
+        config::Flasher::Msp430Usb => {
+            bb_flasher::bcf::msp430::Target::destinations_with_ids(filter, usb_ids)
+                .into_iter()
+                .map(Destination::Msp430)
+                .collect()
+        }
         #[cfg(feature = "pb2_mspm0")]
         config::Flasher::Pb2Mspm0 => vec![Destination::Pb2Mspm0],
         _ => unimplemented!(),
     }
 }
 
+/// Check if a local file can be flashed by `flasher`, based on its extension.
+pub(crate) fn is_supported_file(path: &std::path::Path, flasher: config::Flasher) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| file_filter(flasher).contains(&x))
+}
+
+/// Request passed as command line argument when launched from a file association or deep link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LaunchRequest {
+    /// Local image to preselect once a board is chosen.
+    Image(PathBuf),
+    /// `bb-imager://flash?board=<name>&image=<name>`
+    Flash {
+        board: String,
+        image: Option<String>,
+    },
+    /// `--kiosk --job <job.toml>`
+    Kiosk(PathBuf),
+}
+
+impl LaunchRequest {
+    /// Parse the command line arguments. Unsupported arguments are ignored.
+    pub(crate) fn from_args(args: impl Iterator<Item = std::ffi::OsString>) -> Option<Self> {
+        let args: Vec<_> = args.skip(1).collect();
+
+        if args.iter().any(|x| x == "--kiosk") {
+            let job = args.iter().skip_while(|x| *x != "--job").nth(1);
+            if job.is_none() {
+                tracing::error!("Kiosk mode requires a job file: --kiosk --job <job.toml>");
+            }
+            return job.map(|x| Self::Kiosk(PathBuf::from(x)));
+        }
+
+        let arg = args.into_iter().next()?;
+
+        if let Some(url) = arg.to_str().and_then(|x| Url::parse(x).ok())
+            && url.scheme() == constants::DEEP_LINK_SCHEME
+        {
+            return Self::from_url(&url);
+        }
+
+        let path = PathBuf::from(arg);
+        if path.is_file() {
+            Some(Self::Image(path))
+        } else {
+            tracing::warn!("Ignoring unsupported argument: {}", path.display());
+            None
+        }
+    }
+
+    fn from_url(url: &Url) -> Option<Self> {
+        if url.host_str() != Some("flash") {
+            tracing::warn!("Unsupported deep link: {url}");
+            return None;
+        }
+
+        let mut board = None;
+        let mut image = None;
+        for (k, v) in url.query_pairs() {
+            match k.as_ref() {
+                "board" => board = Some(v.into_owned()),
+                "image" => image = Some(v.into_owned()),
+                _ => {}
+            }
+        }
+
+        Some(Self::Flash {
+            board: board?,
+            image,
+        })
+    }
+}
+
+/// Flashing job for kiosk mode, loaded from a TOML file.
+///
+/// ```toml
+/// board = "BeagleY-AI"
+/// image = "BeagleY-AI Debian 12.x (Bookworm) XFCE"
+/// # Local image. Used instead of `image` when present.
+/// # image_path = "/srv/images/custom.img.xz"
+///
+/// # Customization for a single destination, matched by path or USB port
+/// [[destinations]]
+/// usb_port = "1-2.1"
+/// hostname = "lab-01"
+/// ```
+///
+/// Customization is taken from the saved application settings. Destination overrides are merged
+/// over it.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub(crate) struct KioskJob {
+    /// Board name in the catalog
+    pub(crate) board: String,
+    /// Os image name in the catalog
+    #[serde(default)]
+    pub(crate) image: Option<String>,
+    /// Local image
+    #[serde(default)]
+    pub(crate) image_path: Option<PathBuf>,
+    /// Per destination customization overrides
+    #[serde(default)]
+    pub(crate) destinations: Vec<DestinationOverride>,
+}
+
+impl KioskJob {
+    pub(crate) fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        let job: Self = toml::from_str(&data)?;
+
+        for x in &job.destinations {
+            if x.path.is_none() && x.usb_port.is_none() {
+                anyhow::bail!("Destination override requires path or usb_port");
+            }
+            if !x.customization.validate_hostname() {
+                anyhow::bail!("Invalid hostname in destination override");
+            }
+        }
+
+        match &job.image_path {
+            Some(x) if !x.is_file() => anyhow::bail!("Image {} not found", x.display()),
+            None if job.image.is_none() => anyhow::bail!("Either image or image_path is required"),
+            _ => Ok(job),
+        }
+    }
+
+    /// Customization override for `dst`. The first matching override is used.
+    pub(crate) fn destination_override(
+        &self,
+        dst: &Destination,
+    ) -> Option<&crate::persistance::SdSysconfCustomization> {
+        self.destinations
+            .iter()
+            .find(|x| x.matches(dst))
+            .map(|x| &x.customization)
+    }
+}
+
+/// Customization for a single destination in a [`KioskJob`].
+#[derive(Debug, Clone, serde::Deserialize)]
+pub(crate) struct DestinationOverride {
+    /// Destination path, e.g. `/dev/sdb`
+    #[serde(default)]
+    pub(crate) path: Option<String>,
+    /// USB port of the card reader. Stable across cards, unlike the path.
+    #[serde(default)]
+    pub(crate) usb_port: Option<String>,
+    #[serde(flatten)]
+    pub(crate) customization: crate::persistance::SdSysconfCustomization,
+}
+
+impl DestinationOverride {
+    fn matches(&self, dst: &Destination) -> bool {
+        let details = dst.details();
+        let detail = |key: &str| details.iter().find(|(k, _)| *k == key).map(|(_, v)| v);
+
+        self.path.as_ref().is_none_or(|x| detail("Path") == Some(x))
+            && self
+                .usb_port
+                .as_ref()
+                .is_none_or(|x| detail("USB Port") == Some(x))
+    }
+}
+
 pub(crate) fn file_filter(flasher: config::Flasher) -> &'static [&'static str] {
     match flasher {
         config::Flasher::SdCard => bb_flasher::sd::Target::FILE_TYPES,
@@ -654,6 +1901,33 @@ pub(crate) fn file_filter(flasher: config::Flasher) -> &'static [&'static str] {
     }
 }
 
+/// Serial port settings for BeagleConnect Freedom. Defaults are used for values missing in the
+/// catalog.
+#[cfg(feature = "bcf_cc1352p7")]
+pub(crate) fn bcf_port_config(
+    serial: Option<&config::SerialConfig>,
+) -> bb_flasher::bcf::cc1352p7::PortConfig {
+    let mut ans = bb_flasher::bcf::cc1352p7::PortConfig::default();
+
+    if let Some(serial) = serial {
+        if let Some(x) = serial.baud_rate {
+            ans.baud_rate = x;
+        }
+        if let Some(x) = serial.flow_control {
+            ans.flow_control = match x {
+                config::FlowControl::None => bb_flasher::bcf::cc1352p7::FlowControl::None,
+                config::FlowControl::Software => bb_flasher::bcf::cc1352p7::FlowControl::Software,
+                config::FlowControl::Hardware => bb_flasher::bcf::cc1352p7::FlowControl::Hardware,
+            };
+        }
+        if let Some(x) = serial.timeout_ms {
+            ans.timeout = Duration::from_millis(x);
+        }
+    }
+
+    ans
+}
+
 const fn flasher_supported(flasher: config::Flasher) -> bool {
     match flasher {
         config::Flasher::SdCard => true,
@@ -708,6 +1982,78 @@ impl FlashingCustomization {
         }
     }
 
+    /// Use serial port settings of the board from the catalog.
+    pub(crate) fn with_serial(mut self, serial: Option<&config::SerialConfig>) -> Self {
+        if let Self::Bcf(x) = &mut self {
+            x.serial = serial.cloned();
+        }
+        self
+    }
+
+    /// Hostname, user and Wi-Fi settings to hand over with the board. Shown as QR Code once
+    /// flashing finishes.
+    pub(crate) fn credentials(&self) -> Option<String> {
+        let Self::LinuxSdSysconfig(x) = self else {
+            return None;
+        };
+
+        let res = bb_helper::qr::device_credentials(
+            x.hostname.as_deref(),
+            x.user.as_ref().map(|u| u.username.as_str()),
+            x.wifi.as_ref().map(|w| w.ssid.as_str()),
+            x.wifi.as_ref().map(|w| w.password.as_str()),
+        );
+        (!res.is_empty()).then_some(res)
+    }
+
+    /// Hostname and user configured for the board. Used to find the board on the network once it
+    /// boots.
+    pub(crate) fn login(&self) -> Option<(String, Option<String>)> {
+        let Self::LinuxSdSysconfig(x) = self else {
+            return None;
+        };
+
+        let hostname = x.hostname.clone().filter(|x| !x.is_empty())?;
+        Some((hostname, x.user.as_ref().map(|u| u.username.clone())))
+    }
+
+    /// Merge per destination customization override.
+    pub(crate) fn with_override(
+        mut self,
+        customization: Option<&crate::persistance::SdSysconfCustomization>,
+    ) -> Self {
+        if let Self::LinuxSdSysconfig(x) = &mut self
+            && let Some(c) = customization
+        {
+            *x = std::mem::take(x).merge(c.clone());
+        }
+        self
+    }
+
+    /// Expand hostname template for a single destination. Also returns the generated hostname.
+    pub(crate) fn for_destination(mut self, dst: &Destination) -> (Self, Option<String>) {
+        let Self::LinuxSdSysconfig(x) = &mut self else {
+            return (self, None);
+        };
+        let Some(template) = x
+            .hostname
+            .as_deref()
+            .filter(|x| bb_flasher::sd::is_hostname_template(x))
+        else {
+            return (self, None);
+        };
+
+        let card = match dst {
+            Destination::SdCard(t) => Some(t.path()),
+            _ => None,
+        };
+        let hostname =
+            bb_flasher::sd::expand_hostname(template, card).expect("Hostname was validated");
+        x.hostname = Some(hostname.clone());
+
+        (self, Some(hostname))
+    }
+
     pub(crate) fn reset(&mut self) {
         match self {
             Self::LinuxSdSysconfig(_) => {
@@ -728,69 +2074,235 @@ impl FlashingCustomization {
         match self {
             FlashingCustomization::LinuxSdSysconfig(sd_customization) => {
                 sd_customization.validate_user()
+                    && sd_customization.validate_hostname()
+                    && sd_customization.validate_volume_label()
+                    && sd_customization.validate_rootfs_label()
+                    && sd_customization.validate_files()
+                    && sd_customization.validate_cmdline_append()
+                    && sd_customization.validate_preinstall()
+                    && sd_customization.validate_network()
             }
             _ => true,
         }
     }
+
+    /// Serialized customization. Used to record customization hash in flash history.
+    ///
+    /// Passwords are left out so that the hash cannot be used to guess them.
+    pub(crate) fn serialized(&self) -> Option<Vec<u8>> {
+        match self {
+            Self::LinuxSdSysconfig(x) => {
+                let mut x = x.clone();
+                if let Some(user) = x.user.as_mut() {
+                    user.password.clear();
+                }
+                if let Some(wifi) = x.wifi.as_mut() {
+                    wifi.password.clear();
+                }
+                serde_json::to_vec(&x).ok()
+            }
+            Self::Bcf(x) => serde_json::to_vec(x).ok(),
+            #[cfg(feature = "pb2_mspm0")]
+            Self::Pb2Mspm0(x) => serde_json::to_vec(x).ok(),
+            Self::NoneSd | Self::Msp430 => None,
+        }
+    }
+}
+
+/// Catalog fetched from a remote URL.
+#[derive(Debug, Clone)]
+pub(crate) struct Catalog {
+    url: Url,
+    config: config::Config,
+    /// Signature was verified against the pinned keys.
+    signed: bool,
+}
+
+impl Catalog {
+    pub(crate) const fn url(&self) -> &Url {
+        &self.url
+    }
 }
 
 /// Fetches the main remote os_list file from `bb_config::DISTROS_URL` and merges it with the base
 /// config.
+///
+/// Variables in image headers are only allowed for `trusted` catalogs, i.e. catalogs added by the
+/// user.
 async fn fetch_remote_os_list(
     client: bb_downloader::Downloader,
     url: Url,
-) -> std::io::Result<config::Config> {
-    client.download_json_no_cache(url).await
+    trusted: bool,
+) -> std::io::Result<Catalog> {
+    let data = client.download_bytes_no_cache(url.clone()).await?;
+    let signed = verify_catalog(&client, &url, &data).await?;
+    let mut config: config::Config = serde_json::from_slice(&data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    if !trusted {
+        config.strip_header_variables();
+    }
+
+    Ok(Catalog {
+        url,
+        config,
+        signed,
+    })
+}
+
+/// Check the detached signature of a catalog. Returns `false` for catalogs without a signature,
+/// or if no keys are pinned in this build. Catalogs with an invalid signature are rejected.
+async fn verify_catalog(
+    client: &bb_downloader::Downloader,
+    url: &Url,
+    data: &[u8],
+) -> std::io::Result<bool> {
+    let keys = bb_config::signature::pinned_keys();
+    if keys.is_empty() {
+        return Ok(false);
+    }
+
+    match client
+        .download_bytes_no_cache(bb_config::signature::signature_url(url))
+        .await
+    {
+        Ok(sig) => bb_config::signature::verify(data, &sig, &keys)
+            .map(|_| true)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            tracing::warn!("Catalog {url} is not signed");
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Validate a local catalog for import. The catalog is only returned if none of its entries would
+/// be ignored.
+pub(crate) async fn import_catalog(
+    path: PathBuf,
+) -> Result<(Option<Catalog>, Vec<bb_config::validate::Diagnostic>), String> {
+    let data = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+    let diagnostics = bb_config::validate::validate(&data);
+    if diagnostics
+        .iter()
+        .any(|x| x.severity == bb_config::validate::Severity::Error)
+    {
+        return Ok((None, diagnostics));
+    }
+
+    let config = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+    let url = Url::from_file_path(&path).map_err(|_| "Invalid catalog path".to_string())?;
+
+    Ok((
+        Some(Catalog {
+            url,
+            config,
+            signed: false,
+        }),
+        diagnostics,
+    ))
+}
+
+/// Directory of imported board folders. Shared with the CLI.
+pub(crate) fn sideload_dir() -> PathBuf {
+    project_dirs().unwrap().data_dir().join("boards")
+}
+
+/// Import a board folder. Images and other files are copied to the cache, and the catalog is
+/// stored so that the board is loaded on every start.
+pub(crate) async fn import_board(
+    downloader: bb_downloader::Downloader,
+    dir: PathBuf,
+) -> Result<Catalog, String> {
+    let board = tokio::task::spawn_blocking(move || bb_config::sideload::read(&dir))
+        .await
+        .unwrap()
+        .map_err(|e| e.to_string())?;
+    for x in &board.warnings {
+        tracing::warn!("{x}");
+    }
+
+    for (path, sha256) in &board.images {
+        downloader
+            .import_with_sha(path, *sha256)
+            .await
+            .map_err(|e| format!("Failed to copy {}: {e}", path.display()))?;
+    }
+    for (url, path) in &board.files {
+        downloader
+            .import_with_url(url.clone(), path)
+            .await
+            .map_err(|e| format!("Failed to copy {}: {e}", path.display()))?;
+    }
+
+    let path = board
+        .install(&sideload_dir())
+        .map_err(|e| format!("Failed to save board: {e}"))?;
+
+    Ok(Catalog {
+        url: Url::from_file_path(path).expect("Absolute path"),
+        config: board.config,
+        signed: false,
+    })
+}
+
+/// Merge boards imported using [`import_board`] in previous sessions.
+pub(crate) fn sideloaded_catalogs_task() -> iced::Task<BBImagerMessage> {
+    let tasks = bb_config::sideload::installed(&sideload_dir())
+        .into_iter()
+        .filter_map(|(path, x)| match x {
+            Ok(config) => Some(Catalog {
+                url: Url::from_file_path(&path).ok()?,
+                config,
+                signed: false,
+            }),
+            Err(e) => {
+                tracing::error!("Failed to load imported board {}: {e}", path.display());
+                None
+            }
+        })
+        .map(|x| iced::Task::done(BBImagerMessage::ExtendConfig(x)));
+
+    iced::Task::batch(tasks)
 }
 
 pub(crate) fn refresh_config_task(
     client: bb_downloader::Downloader,
     config: &Boards,
 ) -> iced::Task<BBImagerMessage> {
-    let tasks = config.unrsolved_configs().map(|x| {
-        iced::Task::perform(
-            fetch_remote_os_list(client.clone(), x.clone()),
-            |x: std::io::Result<config::Config>| match x {
-                Ok(y) => BBImagerMessage::ExtendConfig(y),
-                Err(e) => {
-                    tracing::error!("Failed to fetch config: {e}");
-                    BBImagerMessage::Null
-                }
-            },
-        )
-    });
+    let tasks = config
+        .unrsolved_configs()
+        .map(|x| fetch_config_task(client.clone(), x.clone(), false));
     iced::Task::batch(tasks)
 }
 
-#[cfg(target_os = "linux")]
-async fn show_notification_xdg_portal(body: &str) -> ashpd::Result<()> {
-    let proxy = ashpd::desktop::notification::NotificationProxy::new().await?;
-
-    let app_id = "org.beagleboard.imagingutility";
-    proxy
-        .add_notification(
-            app_id,
-            ashpd::desktop::notification::Notification::new("BeagleBoard Imager").body(body),
-        )
-        .await
+/// Run a download in background. Running background downloads are counted in the activity
+/// indicator.
+pub(crate) fn background_task(task: iced::Task<BBImagerMessage>) -> iced::Task<BBImagerMessage> {
+    iced::Task::done(BBImagerMessage::BackgroundStarted)
+        .chain(task.map(|x| BBImagerMessage::BackgroundFinished(Box::new(x))))
 }
 
-pub(crate) async fn show_notification(body: String) -> notify_rust::error::Result<()> {
-    #[cfg(target_os = "linux")]
-    if show_notification_xdg_portal(&body).await.is_ok() {
-        return Ok(());
-    }
-
-    tokio::task::spawn_blocking(move || {
-        notify_rust::Notification::new()
-            .appname("BeagleBoard Imager")
-            .body(&body)
-            .finalize()
-            .show()
-    })
-    .await
-    .unwrap()
-    .map(|_| ())
+/// Fetch a config from url and merge it with the existing config. See [`fetch_remote_os_list`] for
+/// `trusted`.
+pub(crate) fn fetch_config_task(
+    client: bb_downloader::Downloader,
+    url: Url,
+    trusted: bool,
+) -> iced::Task<BBImagerMessage> {
+    background_task(iced::Task::perform(
+        fetch_remote_os_list(client, url.clone(), trusted),
+        move |x: std::io::Result<Catalog>| match x {
+            Ok(y) => BBImagerMessage::ExtendConfig(y),
+            Err(e) => {
+                tracing::error!("Failed to fetch config {url}: {e}");
+                BBImagerMessage::CatalogFailed(url.clone(), e.to_string())
+            }
+        },
+    ))
 }
 
 pub(crate) fn project_dirs() -> Option<directories::ProjectDirs> {
@@ -801,6 +2313,38 @@ pub(crate) fn project_dirs() -> Option<directories::ProjectDirs> {
     )
 }
 
+/// Apply network settings (proxy, TLS, download limit) from app configuration to a downloader.
+pub(crate) fn apply_network_settings(
+    mut downloader: bb_downloader::Downloader,
+    app_config: &crate::persistance::GuiConfiguration,
+) -> bb_downloader::Downloader {
+    if let Some(proxy) = app_config.proxy() {
+        match downloader.with_proxy(proxy) {
+            Ok(x) => downloader = x,
+            Err(e) => tracing::error!("Failed to use proxy {proxy}: {e}"),
+        }
+    }
+
+    if let Some(p) = app_config.ca_certs() {
+        match downloader.with_root_certificates(p) {
+            Ok(x) => downloader = x,
+            Err(e) => tracing::error!("Failed to load certificates from {}: {e}", p.display()),
+        }
+    }
+
+    if app_config.insecure_tls() {
+        match downloader.with_insecure(true) {
+            Ok(x) => downloader = x,
+            Err(e) => tracing::error!("Failed to disable certificate verification: {e}"),
+        }
+    }
+
+    match app_config.download_limit() {
+        Some(limit) => downloader.with_rate_limit(limit * 1024),
+        None => downloader,
+    }
+}
+
 pub(crate) fn log_file_path() -> PathBuf {
     let dirs = project_dirs().unwrap();
     dirs.cache_dir().with_file_name(format!(
@@ -822,7 +2366,8 @@ impl From<PathBuf> for ImageHandleCacheValue {
     fn from(value: PathBuf) -> Self {
         let img = std::fs::read(&value).expect("Failed to open image");
         match image::guess_format(&img) {
-            Ok(_) => Self::Img(widget::image::Handle::from_path(value)),
+            // Handles from path are identified by the path, so refreshed icons would not be shown.
+            Ok(_) => Self::Img(widget::image::Handle::from_bytes(img)),
             Err(_) => Self::Svg(widget::svg::Handle::from_memory(img)),
         }
     }
@@ -854,6 +2399,10 @@ impl ImageHandleCache {
     pub(crate) fn insert(&mut self, u: url::Url, path: PathBuf) {
         self.0.insert(u, path.into());
     }
+
+    pub(crate) fn urls(&self) -> impl Iterator<Item = &url::Url> {
+        self.0.keys()
+    }
 }
 
 impl Extend<(url::Url, PathBuf)> for ImageHandleCache {
@@ -870,28 +2419,6 @@ impl FromIterator<(url::Url, PathBuf)> for ImageHandleCache {
     }
 }
 
-pub(crate) fn pretty_bytes(bytes: u64) -> String {
-    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
-
-    if bytes == 0 {
-        return "0 B".to_string();
-    }
-
-    let mut size = bytes as f64;
-    let mut unit = 0;
-
-    while size >= 1024.0 && unit < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit += 1;
-    }
-
-    if unit == 0 {
-        format!("{} {}", bytes, UNITS[unit])
-    } else {
-        format!("{:.2} {}", size, UNITS[unit])
-    }
-}
-
 pub(crate) const fn static_destination(flasher: config::Flasher) -> Option<Destination> {
     match flasher {
         #[cfg(feature = "pb2_mspm0")]
@@ -900,6 +2427,20 @@ pub(crate) const fn static_destination(flasher: config::Flasher) -> Option<Desti
     }
 }
 
+/// Check if the customization page should be skipped in favour of the saved customization. The
+/// page can still be reached by going back from review to change customization for a single flash.
+pub(crate) fn skip_customization(
+    customization: &FlashingCustomization,
+    app_config: &crate::persistance::GuiConfiguration,
+) -> bool {
+    matches!(customization, FlashingCustomization::LinuxSdSysconfig(_))
+        && app_config.apply_saved_customization()
+        && app_config
+            .sd_customization()
+            .is_some_and(|x| x.sysconf_customization().is_some())
+        && customization.validate()
+}
+
 /// Return customization enum variant for cases where no customization is present
 pub(crate) fn no_customization(
     flasher: config::Flasher,
@@ -941,16 +2482,40 @@ pub(crate) enum OsImageId {
     // Vec points to parent
     Format(Vec<usize>),
     // Vec points to parent
+    Customize(Vec<usize>),
+    // Vec points to parent
     Local(Vec<usize>),
     // Vec points to OsImage
     Remote(Vec<usize>),
 }
 
+/// Os image found by [`Boards::search`].
+pub(crate) struct SearchResult<'a> {
+    pub(crate) board: usize,
+    /// Position of the image in Os list
+    pub(crate) target: Vec<usize>,
+    pub(crate) item: &'a OsListItem,
+}
+
 pub(crate) struct OsImageItem<'a> {
     pub(crate) id: OsImageId,
     pub(crate) icon: Option<&'a url::Url>,
     pub(crate) label: &'a str,
     pub(crate) is_sublist: bool,
+    /// Nesting level inside collapsible groups
+    pub(crate) depth: usize,
+    /// Set for sub lists shown as collapsible groups
+    pub(crate) group: Option<ImageGroup>,
+    /// Download size or cache status of the image
+    pub(crate) badge: Option<String>,
+}
+
+/// Sub list which is expanded in place instead of navigating into it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ImageGroup {
+    /// Number of images for the board in the group
+    pub(crate) count: usize,
+    pub(crate) expanded: bool,
 }
 
 impl<'a> OsImageItem<'a> {
@@ -960,6 +2525,21 @@ impl<'a> OsImageItem<'a> {
             icon: None,
             label,
             is_sublist: false,
+            depth: 0,
+            group: None,
+            badge: None,
+        }
+    }
+
+    pub(crate) fn customize(parent: Vec<usize>) -> Self {
+        Self {
+            id: OsImageId::Customize(parent),
+            icon: None,
+            label: "Customize Existing SD Card",
+            is_sublist: false,
+            depth: 0,
+            group: None,
+            badge: None,
         }
     }
 
@@ -969,6 +2549,9 @@ impl<'a> OsImageItem<'a> {
             icon: None,
             label: "Select Local Image",
             is_sublist: false,
+            depth: 0,
+            group: None,
+            badge: None,
         }
     }
 
@@ -977,12 +2560,39 @@ impl<'a> OsImageItem<'a> {
         url: &'a url::Url,
         label: &'a str,
         is_sublist: bool,
+        depth: usize,
     ) -> Self {
         Self {
             id: OsImageId::Remote(id),
             icon: Some(url),
             label,
             is_sublist,
+            depth,
+            group: None,
+            badge: None,
+        }
+    }
+
+    pub(crate) fn with_badge(mut self, badge: Option<String>) -> Self {
+        self.badge = badge;
+        self
+    }
+
+    pub(crate) fn group(
+        id: Vec<usize>,
+        url: &'a url::Url,
+        label: &'a str,
+        depth: usize,
+        group: ImageGroup,
+    ) -> Self {
+        Self {
+            id: OsImageId::Remote(id),
+            icon: Some(url),
+            label,
+            is_sublist: true,
+            depth,
+            group: Some(group),
+            badge: None,
         }
     }
 }
@@ -990,6 +2600,7 @@ impl<'a> OsImageItem<'a> {
 #[derive(Debug)]
 pub(crate) enum DestinationItem<'a> {
     SaveToFile(String),
+    ExportImage(String),
     Destination(&'a Destination),
 }
 
@@ -997,6 +2608,7 @@ impl<'a> std::fmt::Display for DestinationItem<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             DestinationItem::SaveToFile(_) => write!(f, "Save To File"),
+            DestinationItem::ExportImage(_) => write!(f, "Export Customized Image"),
             DestinationItem::Destination(d) => d.fmt(f),
         }
     }
@@ -1006,13 +2618,37 @@ impl<'a> DestinationItem<'a> {
     pub(crate) fn msg(&'a self) -> BBImagerMessage {
         match self {
             DestinationItem::SaveToFile(x) => BBImagerMessage::SelectFileDest(x.clone()),
+            DestinationItem::ExportImage(x) => BBImagerMessage::SelectImageDest(x.clone()),
             DestinationItem::Destination(d) => BBImagerMessage::SelectDest((*d).clone()),
         }
     }
 
+    pub(crate) const fn disabled_reason(&self) -> Option<&'static str> {
+        match self {
+            DestinationItem::SaveToFile(_) | DestinationItem::ExportImage(_) => None,
+            DestinationItem::Destination(d) => d.disabled_reason(),
+        }
+    }
+
+    pub(crate) fn location(&self) -> Option<&'a bb_flasher::sd::UsbLocation> {
+        match *self {
+            DestinationItem::Destination(d) => d.location(),
+            DestinationItem::SaveToFile(_) | DestinationItem::ExportImage(_) => None,
+        }
+    }
+
+    /// Model of the card reader. Only known for SD Cards.
+    pub(crate) fn reader(&self) -> Option<&'a str> {
+        match *self {
+            DestinationItem::Destination(Destination::SdCard(t)) => t.reader(),
+            _ => None,
+        }
+    }
+
     pub(crate) fn is_selected(&'a self, dst: &'a Destination) -> bool {
         match self {
             DestinationItem::SaveToFile(_) => false,
+            DestinationItem::ExportImage(_) => matches!(dst, Destination::Image(_)),
             DestinationItem::Destination(d) => dst.eq(d),
         }
     }
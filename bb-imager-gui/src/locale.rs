@@ -0,0 +1,116 @@
+//! Timezones and keymaps grouped by region, with fuzzy search for the customization pickers.
+
+use std::sync::LazyLock;
+
+use crate::constants;
+
+/// A single selectable timezone or keymap.
+#[derive(Debug)]
+pub(crate) struct Entry {
+    /// Value written to sysconf.txt.
+    pub(crate) id: &'static str,
+    /// Human readable name.
+    pub(crate) label: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct Group {
+    pub(crate) name: &'static str,
+    pub(crate) entries: Vec<Entry>,
+}
+
+/// Timezones grouped by area (e.g. `Europe`). Zones without an area are grouped as `Other`.
+pub(crate) fn timezones() -> &'static [Group] {
+    static TIMEZONES: LazyLock<Vec<Group>> = LazyLock::new(|| {
+        group(constants::TIMEZONES.iter().map(|id| {
+            let (region, city) = id.split_once('/').unwrap_or(("Other", id));
+            let label = city.replace('_', " ").replace('/', " / ");
+            (region, Entry { id, label })
+        }))
+    });
+    &TIMEZONES
+}
+
+/// Keymaps grouped by region.
+pub(crate) fn keymaps() -> &'static [Group] {
+    static KEYMAPS: LazyLock<Vec<Group>> = LazyLock::new(|| {
+        group(constants::KEYMAPS.iter().map(|(id, name, region)| {
+            let label = format!("{name} ({id})");
+            (*region, Entry { id, label })
+        }))
+    });
+    &KEYMAPS
+}
+
+/// Group entries, keeping groups and entries sorted.
+fn group(entries: impl Iterator<Item = (&'static str, Entry)>) -> Vec<Group> {
+    let mut res: Vec<Group> = Vec::new();
+
+    for (name, entry) in entries {
+        match res.iter_mut().find(|x| x.name == name) {
+            Some(g) => g.entries.push(entry),
+            None => res.push(Group {
+                name,
+                entries: vec![entry],
+            }),
+        }
+    }
+
+    res.sort_by_key(|x| x.name);
+    for g in &mut res {
+        g.entries.sort_by(|a, b| a.label.cmp(&b.label));
+    }
+
+    res
+}
+
+/// Entries matching `query`, best match first. Characters of the query need to appear in order,
+/// but not next to each other, so `nyork` finds `America/New_York`.
+pub(crate) fn search<'a>(groups: &'a [Group], query: &str) -> Vec<(&'a Group, &'a Entry)> {
+    let query: Vec<char> = query
+        .to_lowercase()
+        .chars()
+        .filter(|x| !x.is_whitespace())
+        .collect();
+
+    let mut res: Vec<_> = groups
+        .iter()
+        .flat_map(|g| g.entries.iter().map(move |e| (g, e)))
+        .filter_map(|(g, e)| {
+            let score = [e.label.as_str(), e.id, g.name]
+                .into_iter()
+                .filter_map(|x| fuzzy_score(&query, x))
+                .min()?;
+            Some((score, g, e))
+        })
+        .collect();
+
+    res.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.2.label.cmp(&b.2.label)));
+    res.into_iter().map(|(_, g, e)| (g, e)).collect()
+}
+
+/// Number of skipped characters between matched characters. Lower is better.
+pub(crate) fn fuzzy_score(query: &[char], text: &str) -> Option<usize> {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    let mut score = 0;
+    let mut started = false;
+
+    for q in query {
+        let mut skipped = 0;
+        loop {
+            let c = text.next()?;
+            if c == *q {
+                break;
+            }
+            skipped += 1;
+        }
+
+        // Leading characters do not count, so matches in the middle of a name are found
+        if started {
+            score += skipped;
+        }
+        started = true;
+    }
+
+    Some(score)
+}
@@ -3,7 +3,7 @@
 use std::time::Duration;
 
 use constants::PACKAGE_QUALIFIER;
-use iced::{Subscription, Task, futures::SinkExt, widget};
+use iced::{Subscription, Task, widget};
 use message::BBImagerMessage;
 use tokio_stream::StreamExt as _;
 use tracing::level_filters::LevelFilter;
@@ -12,10 +12,16 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use crate::state::BBImagerCommon;
 
 mod constants;
+mod failure;
 mod helpers;
+mod locale;
 mod message;
+mod notifier;
+mod palette;
 mod persistance;
 mod state;
+mod taskbar;
+mod telemetry;
 mod ui;
 mod updater;
 
@@ -26,7 +32,10 @@ fn main() -> iced::Result {
         std::fs::create_dir_all(log_file_dir).unwrap();
     }
 
+    let (_telemetry, otel_layer) = telemetry::init();
+
     tracing_subscriber::registry()
+        .with(otel_layer)
         .with(
             tracing_subscriber::EnvFilter::builder()
                 .with_default_directive(LevelFilter::INFO.into())
@@ -51,13 +60,20 @@ fn main() -> iced::Result {
             .ok();
     assert!(icon.is_some());
 
-    #[cfg(target_os = "macos")]
-    // HACK: mac_notification_sys set application name (not an option in notify-rust)
-    let _ = notify_rust::set_application("org.beagleboard.imagingutility");
+    notifier::init();
 
+    let geometry = persistance::GuiConfiguration::load()
+        .ok()
+        .and_then(|x| x.window().copied());
     let settings = iced::window::Settings {
-        min_size: Some(constants::WINDOW_SIZE),
-        size: constants::WINDOW_SIZE,
+        min_size: Some(constants::MIN_WINDOW_SIZE),
+        size: geometry.map(|x| x.size()).unwrap_or(constants::WINDOW_SIZE),
+        position: geometry
+            .and_then(|x| x.position())
+            .map(iced::window::Position::Specific)
+            .unwrap_or_default(),
+        // Save window geometry before exiting
+        exit_on_close_request: false,
         ..Default::default()
     };
 
@@ -101,6 +117,14 @@ enum BBImager {
     FlashingFail(state::FlashingFailState),
     FlashingSuccess(state::FlashingFinishState),
     AppInfo(state::OverlayState),
+    Settings(state::SettingsState),
+    Jobs(state::JobsState),
+    History(state::HistoryState),
+    UsbNetwork(state::UsbNetworkState),
+    Onboarding(state::OnboardingState),
+    Recovery(state::RecoveryState),
+    Bundle(state::BundleState),
+    Kiosk(state::KioskState),
 }
 
 impl BBImager {
@@ -108,6 +132,7 @@ impl BBImager {
         Self::ChooseBoard(state::ChooseBoardState {
             common,
             selected_board: None,
+            search: String::new(),
         })
     }
 }
@@ -115,22 +140,34 @@ impl BBImager {
 impl BBImager {
     fn new() -> (Self, Task<BBImagerMessage>) {
         let app_config = persistance::GuiConfiguration::load().unwrap_or_default();
+        app_config.unit_system().set_preferred();
 
-        let downloader = bb_downloader::Downloader::new(
-            directories::ProjectDirs::from(
-                PACKAGE_QUALIFIER.0,
-                PACKAGE_QUALIFIER.1,
-                PACKAGE_QUALIFIER.2,
-            )
-            .unwrap()
+        let cache_dir = app_config
             .cache_dir()
-            .to_path_buf(),
-        )
-        .unwrap();
+            .map(|x| x.to_path_buf())
+            .unwrap_or_else(|| {
+                directories::ProjectDirs::from(
+                    PACKAGE_QUALIFIER.0,
+                    PACKAGE_QUALIFIER.1,
+                    PACKAGE_QUALIFIER.2,
+                )
+                .unwrap()
+                .cache_dir()
+                .to_path_buf()
+            });
+        let downloader = helpers::apply_network_settings(
+            bb_downloader::Downloader::new_with_env(cache_dir).unwrap(),
+            &app_config,
+        );
 
         // Fetch old config
         let client = downloader.clone();
         let config_task = helpers::refresh_config_task(client, &helpers::Boards::new());
+        let catalog_task = match app_config.catalog_url() {
+            Some(x) => helpers::fetch_config_task(downloader.clone(), x.clone(), true),
+            None => Task::none(),
+        };
+        let sideload_task = helpers::sideloaded_catalogs_task();
         let boards = helpers::Boards::new();
 
         let img_handle_cache = helpers::ImageHandleCache::from_iter(
@@ -143,18 +180,17 @@ impl BBImager {
                 }),
         );
 
+        let window_size = app_config
+            .window()
+            .map(|x| x.size())
+            .unwrap_or(constants::WINDOW_SIZE);
+
         let common = BBImagerCommon {
+            window_size,
             app_config,
             downloader: downloader.clone(),
-            timezones: widget::combo_box::State::new(
-                constants::TIMEZONES.iter().map(|x| x.to_string()).collect(),
-            ),
-            keymaps: widget::combo_box::State::new(
-                constants::KEYMAP_LAYOUTS
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect(),
-            ),
+            timezone_search: String::new(),
+            keymap_search: String::new(),
             boards,
             board_svg_handle: widget::svg::Handle::from_memory(constants::BOARD_ICON),
             downloading_svg_handle: widget::svg::Handle::from_memory(constants::DOWNLOADING_ICON),
@@ -167,44 +203,148 @@ impl BBImager {
             usb_svg_handle: widget::svg::Handle::from_memory(constants::USB_ICON),
             file_save_icon: widget::svg::Handle::from_memory(constants::FILE_SAVE_ICON),
             info_svg_handle: widget::svg::Handle::from_memory(constants::INFO_ICON),
+            settings_svg_handle: widget::svg::Handle::from_memory(constants::SETTINGS_ICON),
             window_icon_handle: widget::image::Handle::from_bytes(crate::constants::WINDOW_ICON),
             copy_svg_handle: widget::svg::Handle::from_memory(constants::COPY_ICON),
+            star_svg_handle: widget::svg::Handle::from_memory(constants::STAR_ICON),
+            expand_more_svg_handle: widget::svg::Handle::from_memory(constants::EXPAND_MORE_ICON),
+            star_border_svg_handle: widget::svg::Handle::from_memory(constants::STAR_BORDER_ICON),
 
             img_handle_cache,
 
             scroll_id: widget::Id::unique(),
+
+            jobs: Default::default(),
+            remote_subitem_errors: Default::default(),
+            expanded_groups: Default::default(),
+            taskbar_progress: None,
+            unlocked: false,
+            launch_image: None,
+
+            palette: None,
+            palette_id: widget::Id::unique(),
+
+            catalog_failures: Default::default(),
+            background_tasks: 0,
+            toasts: Default::default(),
+            next_toast_id: 0,
+
+            #[cfg(feature = "bcf_cc1352p7")]
+            device_versions: Default::default(),
+
+            #[cfg(feature = "demo")]
+            mock_flasher: Default::default(),
         };
 
         // Fetch all board images
         let board_image_task = common.fetch_board_images();
 
         let updater_task = common.updater_task();
+
+        let launch = helpers::LaunchRequest::from_args(std::env::args_os()).or_else(|| {
+            common
+                .app_config
+                .kiosk_job()
+                .map(|x| helpers::LaunchRequest::Kiosk(x.to_path_buf()))
+        });
+        let kiosk_job = match &launch {
+            Some(helpers::LaunchRequest::Kiosk(path)) => helpers::KioskJob::load(path)
+                .inspect_err(|e| {
+                    tracing::error!("Failed to load kiosk job {}: {e}", path.display())
+                })
+                .ok(),
+            _ => None,
+        };
+
+        // Check if the last flashing was interrupted
+        let mut markers = persistance::FlashingMarker::load();
+        let mut page = match kiosk_job {
+            Some(job) => Self::Kiosk(state::KioskState::new(common, job)),
+            None if !markers.is_empty() => {
+                tracing::warn!("Found interrupted flashing: {:#?}", markers);
+                let marker = markers.remove(0);
+                Self::Recovery(state::RecoveryState::new(common, marker, markers))
+            }
+            // Skip onboarding when launched for a specific image
+            None if launch.is_none() && !common.app_config.hide_onboarding() => {
+                Self::Onboarding(state::OnboardingState::new(common))
+            }
+            None => Self::choose_board(common),
+        };
+
+        let launch_task = match (&mut page, launch) {
+            (Self::Kiosk(inner), _) => {
+                let fullscreen = iced::window::latest()
+                    .and_then(|id| iced::window::set_mode(id, iced::window::Mode::Fullscreen));
+                // The image might be in a remote sub list
+                let subitems = match inner.common.boards.find(&inner.job.board, None) {
+                    Some((board, _)) => inner.common.fetch_remote_subitems(board, &[]),
+                    None => Task::none(),
+                };
+
+                Task::batch([fullscreen, subitems])
+            }
+            (Self::ChooseBoard(inner), Some(helpers::LaunchRequest::Image(x))) => {
+                inner.common.launch_image = Some(x);
+                Task::none()
+            }
+            (Self::ChooseBoard(inner), Some(helpers::LaunchRequest::Flash { board, image })) => {
+                match inner.common.boards.find(&board, image.as_deref()) {
+                    Some((board, Some(target))) => {
+                        Task::done(BBImagerMessage::SelectSearchResult { board, target })
+                    }
+                    Some((board, None)) => Task::done(BBImagerMessage::SelectBoard(board))
+                        .chain(Task::done(BBImagerMessage::Next)),
+                    None => {
+                        tracing::warn!("Board {board} from deep link not found");
+                        Task::none()
+                    }
+                }
+            }
+            _ => Task::none(),
+        };
+
         (
-            Self::choose_board(common),
-            Task::batch([config_task, board_image_task, updater_task]),
+            page,
+            Task::batch([
+                config_task,
+                catalog_task,
+                sideload_task,
+                board_image_task,
+                updater_task,
+                launch_task,
+            ]),
         )
     }
 
     fn theme(&self) -> iced::Theme {
-        iced::Theme::custom(
-            "Beagle",
-            iced::theme::Palette {
-                background: constants::BACKGROUND,
-                text: iced::Color::WHITE,
-                primary: constants::TONGUE_ORANGE,
-                success: constants::CHECK_MARK_GREEN,
-                warning: constants::HAIR_LIGHT_BROWN,
-                danger: constants::DANGER,
-            },
-        )
+        match self.common().app_config.theme() {
+            persistance::AppTheme::Beagle => iced::Theme::custom(
+                "Beagle",
+                iced::theme::Palette {
+                    background: constants::BACKGROUND,
+                    text: iced::Color::WHITE,
+                    primary: constants::TONGUE_ORANGE,
+                    success: constants::CHECK_MARK_GREEN,
+                    warning: constants::HAIR_LIGHT_BROWN,
+                    danger: constants::DANGER,
+                },
+            ),
+            persistance::AppTheme::Dark => iced::Theme::Dark,
+            persistance::AppTheme::Light => iced::Theme::Light,
+        }
     }
 
     fn fetch_board_images(&self) -> Task<BBImagerMessage> {
         self.common().fetch_board_images()
     }
 
-    fn boards_merge(&mut self, c: bb_config::Config) {
-        self.common_mut().boards.merge(c)
+    fn boards_merge(&mut self, c: helpers::Catalog) {
+        self.common_mut().boards.merge(c);
+
+        if let Self::Kiosk(inner) = self {
+            inner.resolve();
+        }
     }
 
     fn common_mut(&mut self) -> &mut BBImagerCommon {
@@ -219,6 +359,14 @@ impl BBImager {
             BBImager::FlashingFail(x) => &mut x.common,
             BBImager::FlashingSuccess(x) => &mut x.common,
             BBImager::AppInfo(x) => x.common_mut(),
+            BBImager::Settings(x) => x.common_mut(),
+            BBImager::Jobs(x) => x.page.common_mut(),
+            BBImager::History(x) => x.page.common_mut(),
+            BBImager::UsbNetwork(x) => x.page.common_mut(),
+            BBImager::Onboarding(x) => &mut x.common,
+            BBImager::Recovery(x) => &mut x.common,
+            BBImager::Bundle(x) => &mut x.common,
+            BBImager::Kiosk(x) => &mut x.common,
             BBImager::Dummy => panic!("Invalid State"),
         }
     }
@@ -235,6 +383,14 @@ impl BBImager {
             BBImager::FlashingFail(x) => &x.common,
             BBImager::FlashingSuccess(x) => &x.common,
             BBImager::AppInfo(x) => x.common(),
+            BBImager::Settings(x) => x.common(),
+            BBImager::Jobs(x) => x.page.common(),
+            BBImager::History(x) => x.page.common(),
+            BBImager::UsbNetwork(x) => x.page.common(),
+            BBImager::Onboarding(x) => &x.common,
+            BBImager::Recovery(x) => &x.common,
+            BBImager::Bundle(x) => &x.common,
+            BBImager::Kiosk(x) => &x.common,
             BBImager::Dummy => panic!("Invalid state"),
         }
     }
@@ -247,10 +403,25 @@ impl BBImager {
         &mut self,
         item: Vec<bb_config::config::OsListItem>,
         target: &[usize],
-    ) {
-        self.common_mut()
-            .boards
-            .resolve_remote_subitem(item, target);
+    ) -> Task<BBImagerMessage> {
+        let common = self.common_mut();
+        common.remote_subitem_errors.remove(target);
+        common.boards.resolve_remote_subitem(item, target);
+
+        if let Self::Kiosk(inner) = self {
+            inner.resolve();
+        }
+
+        // Lazily load the next level if the list is currently open
+        match self {
+            Self::ChooseOs(inner) if inner.pos == target => Task::batch([
+                inner
+                    .common
+                    .fetch_remote_subitems(inner.selected_board, target),
+                inner.common.fetch_os_images(inner.selected_board, target),
+            ]),
+            _ => Task::none(),
+        }
     }
 
     fn restart(&mut self) {
@@ -263,33 +434,91 @@ impl BBImager {
                 BBImager::choose_board(x.common)
             }
             BBImager::FlashingFail(x) => BBImager::choose_board(x.common),
-            BBImager::Dummy | BBImager::AppInfo(_) | BBImager::ChooseBoard(_) => {
+            BBImager::Recovery(x) => BBImager::choose_board(x.common),
+            BBImager::Bundle(x) => BBImager::choose_board(x.common),
+            BBImager::Onboarding(x) => BBImager::choose_board(x.common),
+            BBImager::Dummy
+            | BBImager::AppInfo(_)
+            | BBImager::Settings(_)
+            | BBImager::Jobs(_)
+            | BBImager::History(_)
+            | BBImager::UsbNetwork(_)
+            | BBImager::ChooseBoard(_)
+            | BBImager::Kiosk(_) => {
                 panic!("Unexpected screen")
             }
         };
     }
 
     fn subscription(&self) -> Subscription<BBImagerMessage> {
-        match self {
-            Self::ChooseDest(x) => Subscription::run_with(
-                (x.selected_image.1.flasher(), x.filter_destination),
-                |(flasher, filter)| {
-                    iced::futures::stream::unfold(
-                        (*flasher, *filter),
-                        async move |(flasher, filter)| {
-                            let mut dest = helpers::destinations(flasher, filter).await;
-
-                            dest.sort_by_key(|x| x.to_string());
-
-                            let msg = BBImagerMessage::Destinations(dest);
-                            Some((msg, (flasher, filter)))
-                        },
-                    )
-                    .throttle(Duration::from_secs(1))
-                },
+        let window_events = iced::event::listen_with(|evt, _, id| match evt {
+            iced::Event::Window(iced::window::Event::Resized(x)) => {
+                Some(BBImagerMessage::WindowResized(x))
+            }
+            iced::Event::Window(iced::window::Event::Moved(x)) => {
+                Some(BBImagerMessage::WindowMoved(x))
+            }
+            iced::Event::Window(iced::window::Event::CloseRequested) => {
+                Some(BBImagerMessage::WindowCloseRequested(id))
+            }
+            iced::Event::Keyboard(iced::keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                match key.as_ref() {
+                    iced::keyboard::Key::Character("k") if modifiers.command() => {
+                        Some(BBImagerMessage::TogglePalette)
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape) => {
+                        Some(BBImagerMessage::ClosePalette)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        });
+
+        let dest_events = |flasher: bb_config::config::Flasher,
+                           filter: bool,
+                           board: &bb_config::config::Device| {
+            let usb_ids: Vec<(u16, u16)> = board.usb_ids.iter().map(|x| (x.vid, x.pid)).collect();
+
+            Subscription::run_with((flasher, filter, usb_ids), |(flasher, filter, usb_ids)| {
+                iced::futures::stream::unfold(
+                    (*flasher, *filter, usb_ids.clone()),
+                    async move |(flasher, filter, usb_ids)| {
+                        let mut dest = helpers::destinations(flasher, filter, &usb_ids).await;
+
+                        dest.sort_by_key(|x| x.to_string());
+
+                        let msg = BBImagerMessage::Destinations(dest);
+                        Some((msg, (flasher, filter, usb_ids)))
+                    },
+                )
+                .throttle(Duration::from_secs(1))
+            })
+        };
+
+        let page_events = match self {
+            Self::ChooseDest(x) => dest_events(
+                x.selected_image.1.flasher(),
+                x.filter_destination,
+                x.selected_board(),
             ),
+            Self::Bundle(x) if !x.is_flashing() => match x.current_step() {
+                Some(step) if helpers::static_destination(step.img.flasher()).is_none() => {
+                    dest_events(step.img.flasher(), true, x.selected_board())
+                }
+                _ => Subscription::none(),
+            },
+            Self::UsbNetwork(_) => Subscription::run(usb_network_events),
+            Self::Kiosk(x) if !x.is_flashing() => match &x.selected {
+                Some((board, img)) if helpers::static_destination(img.flasher()).is_none() => {
+                    dest_events(img.flasher(), true, x.common.boards.device(*board))
+                }
+                _ => Subscription::none(),
+            },
             _ => Subscription::none(),
-        }
+        };
+
+        Subscription::batch([window_events, page_events])
     }
 
     fn start_flashing(&mut self) -> Task<BBImagerMessage> {
@@ -298,65 +527,177 @@ impl BBImager {
             _ => panic!("Unexpected page"),
         };
 
-        let board = state.common.boards.device(state.selected_board);
+        let board = state.common.boards.device(state.selected_board).clone();
 
         let is_download = state.is_download();
-        let customization = state.customization;
-        let img = state.selected_image.1.clone();
-        let dst = state.selected_dest;
+        let marker = if is_download {
+            None
+        } else {
+            Some(persistance::FlashingMarker::new(
+                board.name.clone(),
+                &state.selected_image.1,
+                &state.selected_dest,
+            ))
+        };
+        let history = if is_download {
+            None
+        } else {
+            let mut entry = bb_helper::history::HistoryEntry::new(
+                state.selected_image.1.to_string(),
+                state.selected_dest.identifier(),
+            )
+            .board(board.name.clone())
+            .destination_name(state.selected_dest.to_string());
+
+            if let Some(x) = state.selected_image.1.sha256() {
+                entry = entry.image_sha256(x);
+            }
+            if let Some(x) = state.customization.serialized() {
+                entry = entry.customization(&x);
+            }
 
+            Some((entry, state.selected_image.1.local_path()))
+        };
+        let mut common = state.common;
+
+        let job_info = (
+            board.name.clone(),
+            state.selected_image.1.to_string(),
+            state.selected_dest.to_string(),
+        );
         tracing::info!("Starting Flashing Process");
         tracing::info!("Selected Board: {:#?}", board);
-        tracing::info!("Selected Image: {:#?}", img);
-        tracing::info!("Selected Destination: {:#?}", dst);
-        tracing::info!("Selected Customization: {:#?}", customization);
-
-        let cancel = tokio_util::sync::CancellationToken::new();
 
-        let s = iced::stream::channel(20, async move |mut chan| {
-            let (tx, mut rx) = iced::futures::channel::mpsc::channel(19);
+        let selection = state::JobSelection {
+            selected_image: state.selected_image.clone(),
+            selected_dest: state.selected_dest.clone(),
+            customization: state.customization.clone(),
+        };
+        let (job_id, t) = common.spawn_flash_job(
+            job_info,
+            state.selected_image.1,
+            state.customization.with_serial(board.serial.as_ref()),
+            state.selected_dest,
+            marker.clone(),
+            history,
+        );
 
-            let cancel_child = cancel.child_token();
-            let flash_task = tokio::spawn(async move {
-                helpers::flash(img, customization, dst, tx, cancel_child).await
-            });
-            let mut chan_clone = chan.clone();
-            let progress_task = tokio::spawn(async move {
-                while let Some(progress) = rx.next().await {
-                    let _ = chan_clone.try_send(BBImagerMessage::FlashProgress(progress));
+        let marker_task = match marker {
+            Some(m) => Task::future(async move {
+                if let Err(e) = m.save().await {
+                    tracing::warn!("Failed to save flashing marker: {e}");
                 }
-            });
-            let _guard = cancel.drop_guard();
+                BBImagerMessage::Null
+            }),
+            None => Task::none(),
+        };
 
-            let res = flash_task
-                .await
-                .expect("Tokio runtime failed to spawn task");
+        *self = Self::Flashing(state::FlashingState {
+            common,
+            selected_board: state.selected_board,
+            job: job_id,
+            selection,
+        });
 
-            let res = match res {
-                Ok(_) => {
-                    tracing::info!("Flashing Successfull");
-                    BBImagerMessage::FlashSuccess
-                }
-                Err(e) => {
-                    tracing::error!("Flashing failed with error: {:#?}", e);
-                    BBImagerMessage::FlashFail(e.to_string())
-                }
-            };
+        Task::batch([marker_task, t])
+    }
 
-            let _ = chan.send(res).await;
-            progress_task.abort();
-        });
+    /// Flash the current step of a bundle. The bundle page stays open and tracks the job.
+    fn start_bundle_step(&mut self) -> Task<BBImagerMessage> {
+        let Self::Bundle(inner) = self else {
+            panic!("Unexpected page");
+        };
 
-        let (t, h) = Task::stream(s).abortable();
+        let step = inner
+            .current_step()
+            .expect("Bundle already finished")
+            .clone();
+        let dst = inner
+            .destination()
+            .expect("Destination should already be selected");
+        let board = inner.selected_board().name.clone();
+        let customization = helpers::FlashingCustomization::new(
+            step.img.flasher(),
+            &step.img,
+            &inner.common.app_config,
+        )
+        .with_serial(inner.selected_board().serial.as_ref());
+
+        let mut entry =
+            bb_helper::history::HistoryEntry::new(step.img.to_string(), dst.identifier())
+                .board(board.clone())
+                .destination_name(dst.to_string());
+        if let Some(x) = step.img.sha256() {
+            entry = entry.image_sha256(x);
+        }
+        if let Some(x) = customization.serialized() {
+            entry = entry.customization(&x);
+        }
 
-        *self = Self::Flashing(state::FlashingState {
-            is_download,
-            common: state.common,
-            selected_board: state.selected_board,
-            cancel_flashing: h,
-            progress: bb_flasher::DownloadFlashingStatus::Preparing,
-            start_timestamp: None,
-        });
+        tracing::info!(
+            "Starting bundle step {} of {}",
+            inner.step + 1,
+            inner.selected_image.1
+        );
+
+        let job_info = (board, step.img.to_string(), dst.to_string());
+        let (job_id, t) = inner.common.spawn_flash_job(
+            job_info,
+            step.img,
+            customization,
+            dst,
+            None,
+            Some((entry, None)),
+        );
+
+        inner.job = Some(job_id);
+        inner.error = None;
+
+        t
+    }
+
+    /// Flash the kiosk image to the selected destination. The kiosk page stays open and tracks
+    /// the job.
+    fn start_kiosk_flash(&mut self) -> Task<BBImagerMessage> {
+        let Self::Kiosk(inner) = self else {
+            panic!("Unexpected page");
+        };
+
+        let (board_idx, img) = inner.selected.clone().expect("Image not found");
+        let board = inner.common.boards.device(board_idx).clone();
+        let dst = inner
+            .destination()
+            .expect("Destination should already be selected");
+        let customization =
+            helpers::FlashingCustomization::new(img.flasher(), &img, &inner.common.app_config)
+                .with_serial(board.serial.as_ref())
+                .with_override(inner.job.destination_override(&dst));
+
+        let mut entry = bb_helper::history::HistoryEntry::new(img.to_string(), dst.identifier())
+            .board(board.name.clone())
+            .destination_name(dst.to_string());
+        if let Some(x) = img.sha256() {
+            entry = entry.image_sha256(x);
+        }
+        if let Some(x) = customization.serialized() {
+            entry = entry.customization(&x);
+        }
+        let local_path = img.local_path();
+
+        tracing::info!("Starting kiosk flashing of {img} to {dst}");
+
+        let job_info = (board.name, img.to_string(), dst.to_string());
+        let (job_id, t) = inner.common.spawn_flash_job(
+            job_info,
+            img,
+            customization,
+            dst,
+            None,
+            Some((entry, local_path)),
+        );
+
+        inner.flash_job = Some(job_id);
+        inner.last_result = None;
 
         t
     }
@@ -395,12 +736,23 @@ impl BBImager {
                 }
             }
             Self::AppInfo(inner) => inner.page.into(),
+            Self::Settings(inner) => inner.page.into(),
+            Self::Jobs(inner) => inner.page.into(),
+            Self::History(inner) => inner.page.into(),
+            Self::UsbNetwork(inner) => inner.page.into(),
+            Self::Recovery(inner) => Self::choose_board(inner.common),
+            Self::Bundle(inner) => Self::ChooseOs(inner.into()),
+            Self::Onboarding(mut inner) => {
+                inner.step = inner.step.saturating_sub(1);
+                Self::Onboarding(inner)
+            }
             Self::Dummy
             | Self::FlashingSuccess(_)
             | Self::FlashingFail(_)
             | Self::FlashingCancel(_)
             | Self::Flashing(_)
-            | Self::ChooseBoard(_) => panic!("Unexpected message"),
+            | Self::ChooseBoard(_)
+            | Self::Kiosk(_) => panic!("Unexpected message"),
         };
 
         self.scroll_reset()
@@ -408,15 +760,30 @@ impl BBImager {
 
     fn next(&mut self) -> Task<BBImagerMessage> {
         *self = match std::mem::take(self) {
-            Self::ChooseBoard(inner) => {
+            Self::ChooseBoard(mut inner) => {
                 let selected_board = inner
                     .selected_board
                     .expect("Board should alread have been selected");
+
+                // Preselect image opened through a file association
+                let flasher = inner.common.boards.device(selected_board).flasher;
+                let selected_image = inner
+                    .common
+                    .launch_image
+                    .take_if(|x| helpers::is_supported_file(x, flasher))
+                    .map(|x| {
+                        (
+                            helpers::OsImageId::Local(Vec::new()),
+                            helpers::BoardImage::local(x, flasher),
+                        )
+                    });
+
                 Self::ChooseOs(state::ChooseOsState {
                     common: inner.common,
                     selected_board,
                     pos: Vec::with_capacity(5),
-                    selected_image: None,
+                    selected_image,
+                    checksum: None,
                 })
             }
             Self::ChooseOs(inner) => {
@@ -424,7 +791,13 @@ impl BBImager {
                     .selected_image
                     .expect("Image should already be selected");
 
-                if let Some(dest) = helpers::static_destination(selected_image.1.flasher()) {
+                if let helpers::BoardImage::Bundle { .. } = selected_image.1 {
+                    Self::Bundle(state::BundleState::new(
+                        inner.common,
+                        inner.selected_board,
+                        selected_image,
+                    ))
+                } else if let Some(dest) = helpers::static_destination(selected_image.1.flasher()) {
                     if let Some(customization) = helpers::no_customization(
                         selected_image.1.flasher(),
                         &selected_image.1,
@@ -436,6 +809,7 @@ impl BBImager {
                             selected_image,
                             selected_dest: dest,
                             customization,
+                            image_info: None,
                         })
                     } else {
                         let temp = helpers::FlashingCustomization::new(
@@ -443,14 +817,22 @@ impl BBImager {
                             &selected_image.1,
                             &inner.common.app_config,
                         );
+                        let skip = helpers::skip_customization(&temp, &inner.common.app_config);
 
-                        Self::Customize(state::CustomizeState {
+                        let state = state::CustomizeState {
                             common: inner.common,
                             selected_board: inner.selected_board,
                             selected_image,
                             selected_dest: dest,
                             customization: temp,
-                        })
+                            image_info: None,
+                        };
+
+                        if skip {
+                            Self::Review(state)
+                        } else {
+                            Self::Customize(state)
+                        }
                     }
                 } else {
                     Self::ChooseDest(state::ChooseDestState {
@@ -479,6 +861,7 @@ impl BBImager {
                         selected_image: inner.selected_image,
                         selected_dest,
                         customization,
+                        image_info: None,
                     })
                 } else {
                     let temp = helpers::FlashingCustomization::new(
@@ -486,24 +869,45 @@ impl BBImager {
                         &inner.selected_image.1,
                         &inner.common.app_config,
                     );
+                    let skip = helpers::skip_customization(&temp, &inner.common.app_config);
 
-                    Self::Customize(state::CustomizeState {
+                    let state = state::CustomizeState {
                         common: inner.common,
                         selected_board: inner.selected_board,
                         selected_image: inner.selected_image,
                         selected_dest,
                         customization: temp,
-                    })
+                        image_info: None,
+                    };
+
+                    if skip {
+                        Self::Review(state)
+                    } else {
+                        Self::Customize(state)
+                    }
                 }
             }
             Self::Customize(inner) => Self::Review(inner),
+            Self::Onboarding(mut inner) => {
+                if !inner.is_last_step() {
+                    inner.step += 1;
+                }
+                Self::Onboarding(inner)
+            }
             Self::Dummy
             | Self::Review(_)
             | Self::Flashing(_)
             | Self::FlashingFail(_)
             | Self::FlashingCancel(_)
             | Self::FlashingSuccess(_)
-            | Self::AppInfo(_) => {
+            | Self::AppInfo(_)
+            | Self::Settings(_)
+            | Self::Jobs(_)
+            | Self::History(_)
+            | Self::UsbNetwork(_)
+            | Self::Recovery(_)
+            | Self::Bundle(_)
+            | Self::Kiosk(_) => {
                 panic!("Unexpected message")
             }
         };
@@ -540,3 +944,19 @@ impl BBImager {
         }
     }
 }
+
+/// Poll the USB network gadget of a connected board.
+fn usb_network_events() -> impl iced::futures::Stream<Item = BBImagerMessage> {
+    iced::futures::stream::unfold((), async |()| {
+        let status = match bb_helper::usb_network::detect() {
+            Some(link) => Some((
+                link,
+                bb_helper::usb_network::probe(link, Duration::from_secs(1)).await,
+            )),
+            None => None,
+        };
+
+        Some((BBImagerMessage::UsbNetworkStatus(status), ()))
+    })
+    .throttle(Duration::from_secs(2))
+}
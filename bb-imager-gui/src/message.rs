@@ -3,7 +3,7 @@
 use iced::{Task, widget::operation};
 
 use crate::{
-    BBImager, helpers,
+    BBImager, constants, helpers,
     state::{OverlayData, OverlayState},
 };
 
@@ -12,44 +12,121 @@ pub(crate) enum BBImagerMessage {
     /// Messages to ignore
     Null,
 
-    ExtendConfig(bb_config::Config),
+    ExtendConfig(crate::helpers::Catalog),
+    /// Remote catalog could not be fetched.
+    CatalogFailed(url::Url, String),
+    /// Fetch a failed remote catalog again.
+    RetryCatalog(url::Url),
+    /// Fetch all failed remote catalogs again.
+    RefreshCatalogs,
+    /// Background download started. See [`helpers::background_task`].
+    BackgroundStarted,
+    /// Background download finished with the message.
+    BackgroundFinished(Box<BBImagerMessage>),
     ResolveRemoteSubitemItem {
         item: Vec<bb_config::config::OsListItem>,
         target: Vec<usize>,
     },
+    RemoteSubitemFailed {
+        target: Vec<usize>,
+        error: String,
+    },
+    /// Retry resolving a remote OS sub list that failed to load
+    RetryRemoteSubitem(Vec<usize>),
 
     /// A new version of application is available
     UpdateAvailable(semver::Version),
 
     /// Select a board by index. Can only be used in Board selection page.
     SelectBoard(usize),
+    /// Search boards and Os images of all boards. Can only be used in Board selection page.
+    BoardSearch(String),
+    /// Pin or unpin a board by name.
+    TogglePinBoard(String),
+    /// Pin or unpin an Os image by name.
+    TogglePinImage(String),
+    /// Select a board along with its newest stable Os image, and continue to destination
+    /// selection. Can only be used in Board selection page.
+    SelectRecommended(usize),
+    /// Select both board and Os image from search results.
+    SelectSearchResult {
+        board: usize,
+        target: Vec<usize>,
+    },
 
     /// ChooseOs Page
     SelectOs(helpers::OsImageId),
     SelectLocalOs((Vec<usize>, helpers::BoardImage)),
     GotoOsListParent,
+    /// Compute SHA256 of the selected local image
+    ComputeChecksum,
+    ChecksumProgress(f32),
+    ChecksumResult(std::path::PathBuf, Result<String, String>),
 
     /// Choose Destination page
     SelectDest(helpers::Destination),
     SelectFileDest(String),
+    /// Pick path to export customized image to.
+    SelectImageDest(String),
     DestinationFilter(bool),
 
     // Customization Page
     UpdateFlashConfig(crate::helpers::FlashingCustomization),
     ResetFlashingConfig,
+    /// Search in the timezone picker
+    TimezoneSearch(String),
+    /// Search in the keymap picker
+    KeymapSearch(String),
+    /// Pick a file, or a directory if true, to copy to the boot partition
+    AddBootFile(bool),
+    BootFileSelected(std::path::PathBuf),
 
     // Review Page
     FlashStart,
+    /// Show partitions and metadata of the selected image
+    InspectImage,
+    InspectImageResult(Result<bb_flasher::sd::ImageInfo, String>),
+
+    // Bundle Page
+    /// Flash the current step of the bundle
+    BundleFlashStep,
+
+    // Kiosk Page
+    /// Flash the kiosk image to the selected destination
+    KioskFlash,
 
     // Flashing Page
-    FlashProgress(bb_flasher::DownloadFlashingStatus),
-    FlashSuccess,
-    FlashCancel,
-    FlashFail(String),
+    // Flashing jobs
+    FlashProgress(bb_flasher::ProgressEvent),
+    FlashSuccess(bb_flasher::JobId),
+    FlashCancel(bb_flasher::JobId),
+    FlashFail(bb_flasher::JobId, crate::failure::Failure),
+    /// Retry failed job with the same selections
+    FlashRetry,
+
+    /// Show all flashing jobs
+    Jobs,
+    /// Remove finished jobs from the list
+    JobsClear,
+    /// Show previously recorded flashes
+    History,
 
     // Reset to start from beginning.
     Restart,
 
+    // Recovery Page
+    RecoveryDismiss,
+    /// Flash the board, image and destination of the interrupted flashing again
+    RecoveryRestart,
+    /// Compare the destination with the image of the interrupted flashing
+    RecoveryVerify,
+    RecoveryVerified(Result<(), String>),
+
+    // Onboarding Page
+    OnboardingDontShow(bool),
+    /// Leave the onboarding wizard. Also used to skip it.
+    OnboardingFinish,
+
     /// Open URL in browser
     OpenUrl(url::Url),
 
@@ -63,6 +140,30 @@ pub(crate) enum BBImagerMessage {
 
     /// Update destinations
     Destinations(Vec<helpers::Destination>),
+    /// Query microcontroller version. Resets the device into bootloader.
+    #[cfg(feature = "bcf_cc1352p7")]
+    ProbeDest(helpers::Destination),
+    /// Result of probing microcontroller version, by destination identifier
+    #[cfg(feature = "bcf_cc1352p7")]
+    DestinationProbed(String, Result<bb_flasher::bcf::DeviceVersion, String>),
+
+    /// Blink the destination so that it can be physically located
+    IdentifyDest(helpers::Destination),
+    /// Identifying destination finished
+    IdentifyDestFinished(Result<(), String>),
+
+    /// Show help to connect to the board over USB network.
+    UsbNetwork,
+    UsbNetworkStatus(
+        Option<(
+            bb_helper::usb_network::Link,
+            bb_helper::usb_network::Services,
+        )>,
+    ),
+
+    /// Wait for the flashed board to come online using mDNS.
+    FindBoard,
+    BoardFound(Result<Vec<std::net::IpAddr>, String>),
 
     /// Read-only editor
     EditorEvent(iced::widget::text_editor::Action),
@@ -72,6 +173,83 @@ pub(crate) enum BBImagerMessage {
 
     /// Copy text to clipboard.
     CopyToClipboard(String),
+
+    /// Show a message using system notification, or inside the application if system
+    /// notifications are disabled or fail.
+    Notify(String),
+    /// Queue a message to show inside the application.
+    Toast(String),
+    /// Remove a message shown inside the application by id.
+    DismissToast(u64),
+
+    /// Show application settings
+    Settings,
+
+    /// Open or close the command palette.
+    TogglePalette,
+    ClosePalette,
+    PaletteSearch(String),
+    /// Close the command palette and run the action.
+    PaletteRun(crate::palette::Action),
+
+    /// Scan for destinations without waiting for the next periodic refresh.
+    RefreshDestinations,
+
+    // Main window events
+    WindowResized(iced::Size),
+    WindowMoved(iced::Point),
+    WindowCloseRequested(iced::window::Id),
+
+    // Settings Page
+    /// Pick a new cache directory.
+    SelectCacheDir,
+    UpdateCacheDir(std::path::PathBuf),
+    SelectScratchDir,
+    /// Use the given directory for streaming images. `None` for system temporary directory.
+    UpdateScratchDir(Option<std::path::PathBuf>),
+    /// Forget checksums of local images so they are hashed again on next flash.
+    ClearChecksumCache,
+    /// Download board and image icons again.
+    RefreshIcons,
+    SettingsTheme(crate::persistance::AppTheme),
+    SettingsTelemetry(bool),
+    SettingsNotifications(bool),
+    SettingsHistory(bool),
+    SettingsCacheBeforeFlash(bool),
+    SettingsSdVerify(crate::persistance::SdVerify),
+    /// Always apply saved SD Card customization without showing the customization page.
+    SettingsApplySavedCustomization(bool),
+    SettingsDecimalUnits(bool),
+    SettingsProxy(String),
+    SettingsCaCerts(String),
+    /// Skip TLS certificate verification. Applied on save.
+    SettingsInsecureTls(bool),
+    SettingsCatalogUrl(String),
+    /// Pick a local catalog file to validate and merge for this session
+    ImportCatalog,
+    /// Pick a board folder to import permanently
+    ImportBoard,
+    BoardImported(Result<crate::helpers::Catalog, String>),
+    CatalogImported(
+        Result<
+            (
+                Option<crate::helpers::Catalog>,
+                Vec<bb_config::validate::Diagnostic>,
+            ),
+            String,
+        >,
+    ),
+    SettingsDownloadLimit(String),
+    SettingsWriteLimit(String),
+    /// Validate and persist network settings
+    SettingsSave,
+    /// Admin PIN input for locked settings
+    SettingsPin(String),
+    /// Unlock settings and customization for this session
+    SettingsUnlock,
+    /// Update developer settings for simulated flashing
+    #[cfg(feature = "demo")]
+    MockFlasher(crate::helpers::MockFlasherConfig),
 }
 
 pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBImagerMessage> {
@@ -82,11 +260,115 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
             }
             _ => panic!("Unexpected message"),
         },
+        BBImagerMessage::BoardSearch(x) => match state {
+            BBImager::ChooseBoard(inner) => {
+                inner.search = x;
+
+                let icons: std::collections::HashSet<url::Url> = inner
+                    .search_results()
+                    .into_iter()
+                    .map(|x| x.item.icon())
+                    .filter(|x| inner.image_handle_cache().get(x).is_none())
+                    .cloned()
+                    .collect();
+                return inner.common.fetch_images(icons);
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::TogglePinBoard(x) => {
+            let common = state.common_mut();
+            common.app_config.toggle_pinned_board(x);
+            return common.save_app_config();
+        }
+        BBImagerMessage::TogglePinImage(x) => {
+            let common = state.common_mut();
+            common.app_config.toggle_pinned_image(x);
+            return common.save_app_config();
+        }
+        BBImagerMessage::SelectRecommended(board) => {
+            let target = match state {
+                BBImager::ChooseBoard(inner) => {
+                    inner.selected_board = Some(board);
+                    inner.common.boards.recommended(board).map(|(x, _)| x)
+                }
+                _ => panic!("Unexpected message"),
+            };
+            let Some(target) = target else {
+                return Task::none();
+            };
+
+            let os_task = state.next();
+            let select_task = update(
+                state,
+                BBImagerMessage::SelectOs(helpers::OsImageId::Remote(target)),
+            );
+            let BBImager::ChooseOs(inner) = state else {
+                panic!("Unexpected state")
+            };
+            if inner.selected_image.is_none() {
+                return Task::batch([os_task, select_task]);
+            }
+
+            let dest_task = state.next();
+            return Task::batch([os_task, select_task, dest_task]);
+        }
+        BBImagerMessage::SelectSearchResult { board, target } => {
+            match state {
+                BBImager::ChooseBoard(inner) => inner.selected_board = Some(board),
+                _ => panic!("Unexpected message"),
+            }
+
+            let next_task = state.next();
+            let BBImager::ChooseOs(inner) = state else {
+                panic!("Unexpected state")
+            };
+
+            // Expand all groups containing the image
+            let parent = &target[..target.len() - 1];
+            let groups = inner.common.expanded_groups.entry(board).or_default();
+            for i in 1..=parent.len() {
+                groups.insert(parent[..i].to_vec());
+            }
+            let icons_task = inner.common.fetch_os_images(board, parent);
+
+            let select_task = update(
+                state,
+                BBImagerMessage::SelectOs(helpers::OsImageId::Remote(target)),
+            );
+            return Task::batch([next_task, icons_task, select_task]);
+        }
+        BBImagerMessage::ComputeChecksum => match state {
+            BBImager::ChooseOs(inner) => return inner.compute_checksum(),
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::ChecksumProgress(x) => {
+            // The user might have moved away from the page while computing
+            if let BBImager::ChooseOs(inner) = state
+                && let Some(crate::state::LocalChecksum::Computing(p)) = &mut inner.checksum
+            {
+                *p = x;
+            }
+        }
+        BBImagerMessage::ChecksumResult(path, x) => {
+            // Ignore results for images which are no longer selected
+            if let BBImager::ChooseOs(inner) = state
+                && inner
+                    .selected_image
+                    .as_ref()
+                    .and_then(|(_, img)| img.local_path())
+                    .is_some_and(|p| p == path)
+            {
+                inner.checksum = Some(crate::state::LocalChecksum::Done(x));
+            }
+        }
         BBImagerMessage::SelectOs(id) => match state {
             BBImager::ChooseOs(inner) => match id {
                 helpers::OsImageId::Format(_) => {
                     inner.selected_image = Some((id, helpers::BoardImage::format()))
                 }
+                helpers::OsImageId::Customize(_) => {
+                    inner.selected_image = Some((id, helpers::BoardImage::customize()))
+                }
                 helpers::OsImageId::Local(parent) => {
                     let flasher = inner.flasher();
                     let extensions = helpers::file_filter(flasher);
@@ -118,8 +400,30 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
                                 inner.downloader().clone(),
                             ),
                         ))
+                    } else if let bb_config::config::OsListItem::Bundle(x) = inner.image(&target) {
+                        inner.selected_image = Some((
+                            helpers::OsImageId::Remote(target),
+                            helpers::BoardImage::bundle(x.clone(), inner.downloader().clone()),
+                        ))
+                    } else if let bb_config::config::OsListItem::SubList(_) = inner.image(&target) {
+                        if inner.toggle_group(target.clone()) {
+                            return Task::batch([
+                                inner
+                                    .common
+                                    .fetch_remote_subitems(inner.selected_board, &target),
+                                inner.common.fetch_os_images(inner.selected_board, &target),
+                            ]);
+                        }
                     } else {
-                        inner.pos = target
+                        inner.common.remote_subitem_errors.remove(&target);
+                        let tasks = Task::batch([
+                            inner
+                                .common
+                                .fetch_remote_subitems(inner.selected_board, &target),
+                            inner.common.fetch_os_images(inner.selected_board, &target),
+                        ]);
+                        inner.pos = target;
+                        return tasks;
                     }
                 }
             },
@@ -127,28 +431,59 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
         },
         BBImagerMessage::SelectLocalOs((parent, image)) => match state {
             BBImager::ChooseOs(inner) => {
+                inner.checksum = None;
                 inner.selected_image = Some((helpers::OsImageId::Local(parent), image))
             }
             _ => panic!("Unexpected message"),
         },
         BBImagerMessage::OpenUrl(x) => {
             return Task::future(async move {
-                let res = webbrowser::open(x.as_str());
-                tracing::debug!("Open Url Resp {res:?}");
-                BBImagerMessage::Null
+                match webbrowser::open(x.as_str()) {
+                    Ok(()) => BBImagerMessage::Null,
+                    Err(e) => {
+                        tracing::warn!("Failed to open {x}: {e}");
+                        BBImagerMessage::Toast(format!("Failed to open {x} in browser"))
+                    }
+                }
             });
         }
         BBImagerMessage::Next => return state.next(),
         BBImagerMessage::Back => return state.back(),
         BBImagerMessage::ResolveImage(k, v) => state.image_cache_insert(k, v),
+        BBImagerMessage::RefreshIcons => return state.common().refresh_images(),
         BBImagerMessage::ExtendConfig(c) => {
             tracing::debug!("Update Config: {:#?}", c);
+            state.common_mut().catalog_failures.remove(c.url());
             state.boards_merge(c);
             return state.fetch_board_images();
         }
+        BBImagerMessage::CatalogFailed(url, e) => return state.common_mut().catalog_failed(url, e),
+        BBImagerMessage::RetryCatalog(url) => return state.common_mut().retry_catalog(url),
+        BBImagerMessage::RefreshCatalogs => return state.common_mut().refresh_catalogs(),
+        BBImagerMessage::BackgroundStarted => state.common_mut().background_tasks += 1,
+        BBImagerMessage::BackgroundFinished(x) => {
+            let common = state.common_mut();
+            common.background_tasks = common.background_tasks.saturating_sub(1);
+            return update(state, *x);
+        }
         BBImagerMessage::ResolveRemoteSubitemItem { item, target } => {
-            state.resolve_remote_subitem(item, &target)
+            return state.resolve_remote_subitem(item, &target);
+        }
+        BBImagerMessage::RemoteSubitemFailed { target, error } => {
+            state
+                .common_mut()
+                .remote_subitem_errors
+                .insert(target, error);
         }
+        BBImagerMessage::RetryRemoteSubitem(target) => match state {
+            BBImager::ChooseOs(inner) => {
+                inner.common.remote_subitem_errors.remove(&target);
+                return inner
+                    .common
+                    .fetch_remote_subitems(inner.selected_board, &target);
+            }
+            _ => panic!("Unexpected message"),
+        },
         BBImagerMessage::UpdateAvailable(x) => {
             return show_notification(format!("A new version of application is available {}", x));
         }
@@ -158,17 +493,98 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
             }
             _ => panic!("Unexpected message"),
         },
-        BBImagerMessage::Destinations(x) => {
-            if let BBImager::ChooseDest(inner) = state
-                && x != inner.destinations
-            {
+        BBImagerMessage::Destinations(x) => match state {
+            BBImager::ChooseDest(inner) if x != inner.destinations => {
+                inner.destinations = x;
+
+                #[cfg(feature = "bcf_cc1352p7")]
+                inner
+                    .common
+                    .device_versions
+                    .retain(|k, _| inner.destinations.iter().any(|d| d.identifier() == *k));
+            }
+            BBImager::Bundle(inner) if x != inner.destinations => {
+                inner.destinations = x;
+            }
+            BBImager::Kiosk(inner) if x != inner.destinations => {
+                // Reset once the destination is removed, and pick the only one by default
+                if inner.selected_dest.as_ref().is_some_and(|d| !x.contains(d)) {
+                    inner.selected_dest = None;
+                }
+                if inner.selected_dest.is_none() && x.len() == 1 {
+                    inner.selected_dest = x.first().cloned();
+                }
                 inner.destinations = x;
             }
+            _ => {}
+        },
+        #[cfg(feature = "bcf_cc1352p7")]
+        BBImagerMessage::ProbeDest(x) => match state {
+            BBImager::ChooseDest(inner) => {
+                let serial = inner.selected_board().serial.clone();
+                return inner.common.probe_destination(x, serial.as_ref());
+            }
+            _ => panic!("Unexpected message"),
+        },
+        #[cfg(feature = "bcf_cc1352p7")]
+        BBImagerMessage::DestinationProbed(id, x) => {
+            let versions = &mut state.common_mut().device_versions;
+            // Ignore results for destinations which have been disconnected
+            if let Some(v) = versions.get_mut(&id) {
+                *v = Some(x);
+            }
+        }
+        BBImagerMessage::IdentifyDest(x) => {
+            return Task::perform(x.identify(), BBImagerMessage::IdentifyDestFinished);
+        }
+        BBImagerMessage::IdentifyDestFinished(Err(e)) => {
+            return show_notification(format!("Failed to identify destination: {e}"));
+        }
+        BBImagerMessage::IdentifyDestFinished(Ok(())) => {}
+        BBImagerMessage::FindBoard => match state {
+            BBImager::FlashingSuccess(inner) => {
+                let (hostname, _) = inner.login.clone().expect("Missing hostname");
+                inner.discovery = crate::state::Discovery::Searching;
+
+                return Task::perform(
+                    async move {
+                        let addrs =
+                            bb_helper::mdns::resolve(&hostname, constants::DISCOVERY_TIMEOUT)
+                                .await
+                                .map_err(|e| e.to_string())?;
+                        if addrs.is_empty() {
+                            Err(format!(
+                                "{} did not come online",
+                                bb_helper::mdns::fqdn(&hostname)
+                            ))
+                        } else {
+                            Ok(addrs)
+                        }
+                    },
+                    BBImagerMessage::BoardFound,
+                );
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::BoardFound(x) => {
+            // Ignore results once the page has been left
+            if let BBImager::FlashingSuccess(inner) = state {
+                inner.discovery = match x {
+                    Ok(x) => crate::state::Discovery::Found(x),
+                    Err(e) => crate::state::Discovery::Failed(e),
+                };
+            }
         }
         BBImagerMessage::SelectDest(x) => match state {
             BBImager::ChooseDest(inner) => {
                 inner.selected_dest = Some(x);
             }
+            BBImager::Bundle(inner) => {
+                inner.selected_dest = Some(x);
+            }
+            BBImager::Kiosk(inner) => {
+                inner.selected_dest = Some(x);
+            }
             _ => panic!("Unexpected message"),
         },
         BBImagerMessage::SelectFileDest(x) => {
@@ -186,6 +602,43 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
                 },
             );
         }
+        BBImagerMessage::SelectImageDest(x) => {
+            return Task::perform(
+                async move {
+                    rfd::AsyncFileDialog::new()
+                        .set_file_name(x)
+                        .add_filter("Disk Image", &["img"])
+                        .save_file()
+                        .await
+                        .map(|x| x.inner().to_path_buf())
+                },
+                move |x| match x {
+                    Some(y) => BBImagerMessage::SelectDest(helpers::Destination::Image(y)),
+                    None => BBImagerMessage::Null,
+                },
+            );
+        }
+        BBImagerMessage::RefreshDestinations => match state {
+            BBImager::ChooseDest(inner) => {
+                let flasher = inner.selected_image.1.flasher();
+                let filter = inner.filter_destination;
+                let usb_ids: Vec<(u16, u16)> = inner
+                    .selected_board()
+                    .usb_ids
+                    .iter()
+                    .map(|x| (x.vid, x.pid))
+                    .collect();
+
+                return Task::perform(
+                    async move { helpers::destinations(flasher, filter, &usb_ids).await },
+                    |mut x| {
+                        x.sort_by_key(|x| x.to_string());
+                        BBImagerMessage::Destinations(x)
+                    },
+                );
+            }
+            _ => panic!("Unexpected message"),
+        },
         BBImagerMessage::DestinationFilter(x) => match state {
             BBImager::ChooseDest(inner) => {
                 inner.filter_destination = x;
@@ -204,122 +657,306 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
             }
             _ => panic!("Unexpected message"),
         },
-        BBImagerMessage::FlashCancel => {
-            let mut msg = "Flashing cancelled by user";
-
-            *state = match std::mem::take(state) {
-                BBImager::Flashing(inner) => {
-                    inner.cancel_flashing.abort();
-
-                    if inner.is_download {
-                        msg = "Download cancelled by user";
-                    }
-                    BBImager::FlashingCancel(inner.into())
-                }
-                BBImager::AppInfo(inner) => match inner.page {
-                    OverlayData::Flashing(flashing_state) => {
-                        flashing_state.cancel_flashing.abort();
-
-                        if flashing_state.is_download {
-                            msg = "Download cancelled by user";
-                        }
-
-                        BBImager::AppInfo(OverlayState {
-                            page: OverlayData::FlashingCancel(flashing_state.into()),
-                            ..inner
-                        })
+        BBImagerMessage::TimezoneSearch(x) => {
+            state.common_mut().timezone_search = x;
+        }
+        BBImagerMessage::KeymapSearch(x) => {
+            state.common_mut().keymap_search = x;
+        }
+        BBImagerMessage::AddBootFile(folder) => {
+            return Task::perform(
+                async move {
+                    let dialog = rfd::AsyncFileDialog::new();
+                    if folder {
+                        dialog.pick_folder().await
+                    } else {
+                        dialog.pick_file().await
                     }
-                    _ => panic!("Unexpected message"),
+                    .map(|x| x.inner().to_path_buf())
                 },
-                _ => panic!("Unexpected message"),
+                |x| match x {
+                    Some(y) => BBImagerMessage::BootFileSelected(y),
+                    None => BBImagerMessage::Null,
+                },
+            );
+        }
+        BBImagerMessage::BootFileSelected(p) => match state {
+            BBImager::Customize(inner) => {
+                if let helpers::FlashingCustomization::LinuxSdSysconfig(x) =
+                    &mut inner.customization
+                {
+                    x.files.push(crate::persistance::SdBootFile::new(p));
+                }
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::FlashCancel(id) => {
+            let Some(job) = state.common_mut().job_mut(id) else {
+                tracing::warn!("Ignoring cancel of unknown job {id:?}");
+                return Task::none();
             };
+            let msg = job.finish(crate::state::JobStatus::Cancelled);
+            leave_flashing_page(state, id, |x| OverlayData::FlashingCancel(x.into()));
+            if let Some(inner) = bundle_page(state, id) {
+                inner.job = None;
+                inner.error = Some("Cancelled".to_string());
+            }
+            if let Some(inner) = kiosk_page(state, id) {
+                inner.finish(Err("Cancelled".to_string()));
+            }
 
-            return show_notification(msg.to_string());
+            return Task::batch([
+                show_notification(msg.to_string()),
+                state.common().clear_flashing_marker(id),
+                state.common_mut().update_taskbar(),
+            ]);
         }
         BBImagerMessage::Restart => {
             state.restart();
         }
-        BBImagerMessage::FlashFail(err) => {
-            let mut msg = "Flashing failed";
+        BBImagerMessage::RecoveryDismiss => {
+            let BBImager::Recovery(mut inner) = std::mem::take(state) else {
+                panic!("Unexpected message");
+            };
+            let job_id = inner.marker.job_id;
+
+            // Show the next interrupted flashing, if any
+            *state = if inner.pending.is_empty() {
+                BBImager::choose_board(inner.common)
+            } else {
+                let marker = inner.pending.remove(0);
+                BBImager::Recovery(crate::state::RecoveryState::new(
+                    inner.common,
+                    marker,
+                    inner.pending,
+                ))
+            };
 
-            let logs =
-                std::fs::read_to_string(helpers::log_file_path()).expect("Failed to read logs");
-            let logs = iced::widget::text_editor::Content::with_text(&logs);
+            return clear_flashing_marker(job_id);
+        }
+        BBImagerMessage::OnboardingDontShow(x) => match state {
+            BBImager::Onboarding(inner) => inner.dont_show = x,
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::OnboardingFinish => match std::mem::take(state) {
+            BBImager::Onboarding(mut inner) => {
+                let dont_show = inner.dont_show;
+                inner.common.app_config.update_hide_onboarding(dont_show);
 
-            *state = match std::mem::take(state) {
-                BBImager::Flashing(inner) => {
-                    if inner.is_download {
-                        msg = "Download failed";
-                    }
+                *state = BBImager::choose_board(inner.common);
 
-                    BBImager::FlashingFail(crate::state::FlashingFailState {
-                        common: inner.common,
-                        err,
-                        logs,
-                    })
+                if dont_show {
+                    return state.common().save_app_config();
                 }
-                BBImager::AppInfo(inner) => match inner.page {
-                    OverlayData::Flashing(flashing_state) => {
-                        if flashing_state.is_download {
-                            msg = "Download failed";
-                        }
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::RecoveryRestart => {
+            let BBImager::Recovery(inner) = std::mem::take(state) else {
+                panic!("Unexpected message");
+            };
+            let job_id = inner.marker.job_id;
 
-                        BBImager::AppInfo(OverlayState {
-                            page: OverlayData::FlashingFail(crate::state::FlashingFailState {
-                                common: flashing_state.common,
-                                err,
-                                logs,
-                            }),
-                            ..inner
-                        })
-                    }
-                    _ => panic!("Unexpected message"),
-                },
-                _ => panic!("Unexpected message"),
+            let Some((selected_board, selected_image)) = inner.selection() else {
+                // Image is no longer available, so only preselect the board
+                let selected_board = inner.board();
+                *state = BBImager::ChooseBoard(crate::state::ChooseBoardState {
+                    common: inner.common,
+                    selected_board,
+                    search: String::new(),
+                });
+                return clear_flashing_marker(job_id);
             };
+            let selected_dest = inner.destination();
 
-            return show_notification(msg.to_string());
-        }
-        BBImagerMessage::FlashProgress(x) => match state {
-            BBImager::Flashing(inner) => {
-                inner.progress_update(x);
+            *state = BBImager::ChooseOs(crate::state::ChooseOsState {
+                common: inner.common,
+                selected_board,
+                pos: Vec::with_capacity(5),
+                selected_image: Some(selected_image),
+                checksum: None,
+            });
+            let mut tasks = vec![clear_flashing_marker(job_id), state.next()];
+
+            if let BBImager::ChooseDest(inner) = state
+                && let Some(dest) = selected_dest
+            {
+                inner.selected_dest = Some(dest);
+                tasks.push(state.next());
             }
-            BBImager::AppInfo(inner) => match &mut inner.page {
-                OverlayData::Flashing(flashing_state) => flashing_state.progress_update(x),
-                _ => panic!("Unexpected message"),
-            },
+
+            return Task::batch(tasks);
+        }
+        BBImagerMessage::RecoveryVerify => match state {
+            BBImager::Recovery(inner) => return inner.verify(),
             _ => panic!("Unexpected message"),
         },
+        BBImagerMessage::RecoveryVerified(x) => {
+            // The user might have moved away from the recovery page while comparing
+            if let BBImager::Recovery(inner) = state {
+                inner.verifying = false;
+                inner.verified = Some(x);
+            }
+        }
+        BBImagerMessage::FlashFail(id, failure) => {
+            let Some(job) = state.common_mut().job_mut(id) else {
+                tracing::warn!("Ignoring failure of unknown job {id:?}");
+                return Task::none();
+            };
+            let err = failure.message.clone();
+            let msg = job.finish(crate::state::JobStatus::Failed(err.clone()));
+
+            leave_flashing_page(state, id, |x| {
+                let logs =
+                    std::fs::read_to_string(helpers::log_file_path()).expect("Failed to read logs");
+                let diagnostics = x
+                    .job()
+                    .map(|job| failure.diagnostics(job, &logs))
+                    .unwrap_or_default();
+                let logs = iced::widget::text_editor::Content::with_text(&logs);
+
+                OverlayData::FlashingFail(crate::state::FlashingFailState {
+                    common: x.common,
+                    selected_board: x.selected_board,
+                    selection: x.selection,
+                    failure,
+                    diagnostics,
+                    logs,
+                })
+            });
+            if let Some(inner) = bundle_page(state, id) {
+                inner.job = None;
+                inner.error = Some(err.clone());
+            }
+            if let Some(inner) = kiosk_page(state, id) {
+                inner.finish(Err(err));
+            }
+
+            return Task::batch([
+                show_notification(msg.to_string()),
+                state.common().clear_flashing_marker(id),
+                state.common_mut().update_taskbar(),
+            ]);
+        }
+        BBImagerMessage::FlashProgress(x) => {
+            let Some(job) = state.common_mut().job_mut(x.job) else {
+                tracing::warn!("Ignoring progress of unknown job {:?}", x.job);
+                return Task::none();
+            };
+            job.progress_update(x.status);
+            let marker = job.save_marker();
+
+            return Task::batch([marker, state.common_mut().update_taskbar()]);
+        }
         BBImagerMessage::FlashStart => {
+            if let BBImager::Review(inner) = state
+                && !inner.is_download()
+                && inner
+                    .common
+                    .destination_busy(&inner.selected_dest.to_string())
+            {
+                return show_notification(format!(
+                    "{} is already being flashed",
+                    inner.selected_dest
+                ));
+            }
+
             return state.start_flashing();
         }
-        BBImagerMessage::FlashSuccess => {
-            let mut msg = "Flashing finished successfully";
-
+        BBImagerMessage::FlashRetry => {
             *state = match std::mem::take(state) {
-                BBImager::Flashing(inner) => {
-                    if inner.is_download {
-                        msg = "Download finished successfully";
-                    }
-                    BBImager::FlashingSuccess(inner.into())
-                }
-                BBImager::AppInfo(inner) => match inner.page {
-                    OverlayData::Flashing(flashing_state) => {
-                        if flashing_state.is_download {
-                            msg = "Download finished successfully";
-                        }
-
-                        BBImager::AppInfo(OverlayState {
-                            page: OverlayData::FlashingSuccess(flashing_state.into()),
-                            ..inner
-                        })
-                    }
-                    _ => panic!("Unexpected message"),
-                },
+                BBImager::FlashingFail(inner) => BBImager::Review(inner.into()),
                 _ => panic!("Unexpected message"),
             };
 
-            return show_notification(msg.to_string());
+            return state.start_flashing();
+        }
+        BBImagerMessage::BundleFlashStep => {
+            if let BBImager::Bundle(inner) = state
+                && let Some(dst) = inner.destination()
+                && inner.common.destination_busy(&dst.to_string())
+            {
+                return show_notification(format!("{dst} is already being flashed"));
+            }
+
+            return state.start_bundle_step();
+        }
+        BBImagerMessage::KioskFlash => {
+            if let BBImager::Kiosk(inner) = state
+                && let Some(dst) = inner.destination()
+                && inner.common.destination_busy(&dst.to_string())
+            {
+                return show_notification(format!("{dst} is already being flashed"));
+            }
+
+            return state.start_kiosk_flash();
+        }
+        BBImagerMessage::InspectImage => match state {
+            BBImager::Review(inner) => {
+                return Task::perform(
+                    helpers::inspect_image(
+                        inner.selected_image.1.clone(),
+                        inner.common.downloader.clone(),
+                    ),
+                    BBImagerMessage::InspectImageResult,
+                );
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::InspectImageResult(x) => {
+            // The user might have moved away from the review page while inspecting
+            if let BBImager::Review(inner) = state {
+                inner.image_info = Some(x);
+            }
+        }
+        BBImagerMessage::FlashSuccess(id) => {
+            let Some(job) = state.common_mut().job_mut(id) else {
+                tracing::warn!("Ignoring success of unknown job {id:?}");
+                return Task::none();
+            };
+            let msg = job.finish(crate::state::JobStatus::Success);
+            leave_flashing_page(state, id, |x| OverlayData::FlashingSuccess(x.into()));
+            if let Some(inner) = bundle_page(state, id) {
+                inner.advance();
+            }
+            if let Some(inner) = kiosk_page(state, id)
+                && let Some(dst) = inner.common.job(id).map(|x| x.destination.clone())
+            {
+                inner.finish(Ok(dst));
+            }
+
+            return Task::batch([
+                show_notification(msg.to_string()),
+                state.common().clear_flashing_marker(id),
+                state.common_mut().update_taskbar(),
+            ]);
+        }
+        BBImagerMessage::Jobs => {
+            *state = BBImager::Jobs(crate::state::JobsState {
+                page: std::mem::take(state).try_into().expect("Unexpected page"),
+            });
+        }
+        BBImagerMessage::History => {
+            // Opened from the about page. Go back to the page below it.
+            let page = match std::mem::take(state) {
+                BBImager::AppInfo(inner) => inner.page,
+                x => x.try_into().expect("Unexpected page"),
+            };
+            *state = BBImager::History(crate::state::HistoryState::new(page));
+        }
+        BBImagerMessage::UsbNetwork => {
+            *state = BBImager::UsbNetwork(crate::state::UsbNetworkState {
+                page: std::mem::take(state).try_into().expect("Unexpected page"),
+                status: None,
+            });
+        }
+        BBImagerMessage::UsbNetworkStatus(x) => {
+            if let BBImager::UsbNetwork(inner) = state {
+                inner.status = x;
+            }
+        }
+        BBImagerMessage::JobsClear => {
+            state.common_mut().jobs.retain(|_, x| x.is_running());
         }
         BBImagerMessage::EditorEvent(evt) => match evt {
             iced::widget::text_editor::Action::Edit(_) => {}
@@ -342,16 +979,499 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
         BBImagerMessage::CopyToClipboard(data) => {
             return iced::clipboard::write(data);
         }
+        BBImagerMessage::SelectCacheDir => {
+            return Task::perform(
+                async move {
+                    rfd::AsyncFileDialog::new()
+                        .pick_folder()
+                        .await
+                        .map(|x| x.inner().to_path_buf())
+                },
+                |x| match x {
+                    Some(y) => BBImagerMessage::UpdateCacheDir(y),
+                    None => BBImagerMessage::Null,
+                },
+            );
+        }
+        BBImagerMessage::UpdateCacheDir(p) => match state {
+            BBImager::Settings(inner) => match bb_downloader::Downloader::new(p.clone()) {
+                Ok(downloader) => {
+                    inner.cache_dir = p.to_string_lossy().to_string();
+
+                    let common = inner.common_mut();
+                    common.downloader =
+                        helpers::apply_network_settings(downloader, &common.app_config);
+                    common.app_config.update_cache_dir(p);
+
+                    return inner.save_app_config();
+                }
+                Err(e) => {
+                    return show_notification(format!("Invalid cache directory: {e}"));
+                }
+            },
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SelectScratchDir => {
+            return Task::perform(
+                async move {
+                    rfd::AsyncFileDialog::new()
+                        .pick_folder()
+                        .await
+                        .map(|x| x.inner().to_path_buf())
+                },
+                |x| match x {
+                    Some(y) => BBImagerMessage::UpdateScratchDir(Some(y)),
+                    None => BBImagerMessage::Null,
+                },
+            );
+        }
+        BBImagerMessage::UpdateScratchDir(p) => match state {
+            BBImager::Settings(inner) => {
+                inner.common_mut().app_config.update_scratch_dir(p);
+                inner.scratch_dir =
+                    crate::state::SettingsState::scratch_dir_label(inner.app_config());
+
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::ClearChecksumCache => {
+            return Task::future(async move {
+                let Some(cache) = crate::persistance::checksum_cache() else {
+                    return Ok(());
+                };
+                tokio::task::spawn_blocking(move || cache.clear())
+                    .await
+                    .unwrap()
+            })
+            .then(|res| match res {
+                Ok(()) => show_notification("Cleared image checksum cache".to_string()),
+                Err(e) => show_notification(format!("Failed to clear checksum cache: {e}")),
+            });
+        }
+        BBImagerMessage::WindowResized(x) => {
+            let common = state.common_mut();
+            common.window_size = x;
+
+            // Ignore minimized window
+            if x.width >= constants::MIN_WINDOW_SIZE.width
+                && x.height >= constants::MIN_WINDOW_SIZE.height
+            {
+                common.app_config.update_window_size(x);
+            }
+        }
+        BBImagerMessage::WindowMoved(x) => {
+            state.common_mut().app_config.update_window_position(x);
+        }
+        BBImagerMessage::WindowCloseRequested(id) => {
+            let config = state.common().app_config.clone();
+            return Task::future(async move {
+                if let Err(e) = config.save().await {
+                    tracing::error!("Failed to save config: {e}");
+                }
+            })
+            .then(move |_| iced::window::close(id));
+        }
+        BBImagerMessage::Settings => {
+            *state = BBImager::Settings(crate::state::SettingsState::new(
+                std::mem::take(state).try_into().expect("Unexpected page"),
+            ));
+
+            return operation::snap_to(
+                state.common().scroll_id.clone(),
+                operation::RelativeOffset::START,
+            );
+        }
+        BBImagerMessage::TogglePalette => {
+            // Kiosk mode is locked to flashing
+            if matches!(state, BBImager::Kiosk(_)) {
+                return Task::none();
+            }
+
+            let common = state.common_mut();
+            if common.palette.take().is_none() {
+                common.palette = Some(String::new());
+                return operation::focus(common.palette_id.clone());
+            }
+        }
+        BBImagerMessage::ClosePalette => state.common_mut().palette = None,
+        BBImagerMessage::Notify(msg) => {
+            if !state.common().app_config.notifications() {
+                return Task::done(BBImagerMessage::Toast(msg));
+            }
+
+            return Task::future(async move {
+                match crate::notifier::notify(msg.clone()).await {
+                    Ok(()) => BBImagerMessage::Null,
+                    Err(e) => {
+                        tracing::warn!("Failed to show notification: {e}");
+                        BBImagerMessage::Toast(msg)
+                    }
+                }
+            });
+        }
+        BBImagerMessage::Toast(msg) => return state.common_mut().push_toast(msg),
+        BBImagerMessage::DismissToast(id) => return state.common_mut().dismiss_toast(id),
+        BBImagerMessage::PaletteSearch(x) => state.common_mut().palette = Some(x),
+        BBImagerMessage::PaletteRun(x) => {
+            state.common_mut().palette = None;
+            return Task::done(x.message());
+        }
+        BBImagerMessage::SettingsTheme(x) => match state {
+            BBImager::Settings(inner) => {
+                inner.common_mut().app_config.update_theme(x);
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsTelemetry(x) => match state {
+            BBImager::Settings(inner) => {
+                inner.common_mut().app_config.update_telemetry(x);
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsNotifications(x) => match state {
+            BBImager::Settings(inner) => {
+                inner.common_mut().app_config.update_notifications(x);
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsHistory(x) => match state {
+            BBImager::Settings(inner) => {
+                inner.common_mut().app_config.update_history(x);
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsCacheBeforeFlash(x) => match state {
+            BBImager::Settings(inner) => {
+                inner.common_mut().app_config.update_cache_before_flash(x);
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsSdVerify(x) => match state {
+            BBImager::Settings(inner) => {
+                inner.common_mut().app_config.update_sd_verify(x);
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsApplySavedCustomization(x) => match state {
+            BBImager::Settings(inner) => {
+                inner
+                    .common_mut()
+                    .app_config
+                    .update_apply_saved_customization(x);
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsDecimalUnits(x) => match state {
+            BBImager::Settings(inner) => {
+                inner.common_mut().app_config.update_decimal_units(x);
+                return inner.save_app_config();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        #[cfg(feature = "demo")]
+        BBImagerMessage::MockFlasher(x) => match state {
+            BBImager::Settings(inner) => inner.common_mut().mock_flasher = x,
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsProxy(x) => match state {
+            BBImager::Settings(inner) => inner.proxy = x,
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsCaCerts(x) => match state {
+            BBImager::Settings(inner) => inner.ca_certs = x,
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsInsecureTls(x) => match state {
+            BBImager::Settings(inner) => inner.insecure_tls = x,
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsCatalogUrl(x) => match state {
+            BBImager::Settings(inner) => inner.catalog_url = x,
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::ImportCatalog => {
+            return Task::perform(
+                async move {
+                    let path = rfd::AsyncFileDialog::new()
+                        .add_filter("Catalog", &["json"])
+                        .pick_file()
+                        .await?
+                        .inner()
+                        .to_path_buf();
+
+                    Some(helpers::import_catalog(path).await)
+                },
+                |x| match x {
+                    Some(y) => BBImagerMessage::CatalogImported(y),
+                    None => BBImagerMessage::Null,
+                },
+            );
+        }
+        BBImagerMessage::ImportBoard => {
+            let downloader = state.common().downloader.clone();
+            return Task::perform(
+                async move {
+                    let dir = rfd::AsyncFileDialog::new()
+                        .pick_folder()
+                        .await?
+                        .inner()
+                        .to_path_buf();
+
+                    Some(helpers::import_board(downloader, dir).await)
+                },
+                |x| match x {
+                    Some(y) => BBImagerMessage::BoardImported(y),
+                    None => BBImagerMessage::Null,
+                },
+            );
+        }
+        BBImagerMessage::BoardImported(x) => match x {
+            Ok(c) => {
+                state.boards_merge(c);
+                return Task::batch([
+                    state.fetch_board_images(),
+                    show_notification("Board imported".to_string()),
+                ]);
+            }
+            Err(e) => return show_notification(format!("Failed to import board: {e}")),
+        },
+        BBImagerMessage::CatalogImported(x) => {
+            let (catalog, diagnostics) = match x {
+                Ok(y) => y,
+                Err(e) => return show_notification(e),
+            };
+
+            match state {
+                BBImager::Settings(inner) => inner.catalog_diagnostics = diagnostics,
+                _ => panic!("Unexpected message"),
+            }
+
+            return match catalog {
+                Some(c) => {
+                    state.boards_merge(c);
+                    Task::batch([
+                        state.fetch_board_images(),
+                        show_notification("Catalog imported".to_string()),
+                    ])
+                }
+                None => show_notification("Catalog has errors and was not imported".to_string()),
+            };
+        }
+        BBImagerMessage::SettingsDownloadLimit(x) => match state {
+            BBImager::Settings(inner) => {
+                if x.is_empty() || x.parse::<u64>().is_ok() {
+                    inner.download_limit = x;
+                }
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsWriteLimit(x) => match state {
+            BBImager::Settings(inner) => {
+                if x.is_empty() || x.parse::<u64>().is_ok() {
+                    inner.write_limit = x;
+                }
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsPin(x) => match state {
+            BBImager::Settings(inner) => {
+                inner.pin = x;
+                inner.pin_error = false;
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsUnlock => match state {
+            BBImager::Settings(inner) => {
+                if inner.app_config().check_admin_pin(&inner.pin) {
+                    tracing::info!("Settings unlocked");
+                    inner.common_mut().unlocked = true;
+                } else {
+                    inner.pin_error = true;
+                }
+                inner.pin.clear();
+            }
+            _ => panic!("Unexpected message"),
+        },
+        BBImagerMessage::SettingsSave => match state {
+            BBImager::Settings(inner) => {
+                let proxy = Some(inner.proxy.trim())
+                    .filter(|x| !x.is_empty())
+                    .map(str::to_string);
+                if let Some(p) = &proxy
+                    && let Err(e) = inner.common().downloader.with_proxy(p)
+                {
+                    return show_notification(format!("Invalid proxy: {e}"));
+                }
+
+                let ca_certs = Some(inner.ca_certs.trim())
+                    .filter(|x| !x.is_empty())
+                    .map(std::path::PathBuf::from);
+                if let Some(p) = &ca_certs
+                    && let Err(e) = inner.common().downloader.with_root_certificates(p)
+                {
+                    return show_notification(format!("Invalid CA certificates: {e}"));
+                }
+
+                let catalog_url = match inner.catalog_url.trim() {
+                    "" => None,
+                    x => match url::Url::parse(x) {
+                        Ok(y) => Some(y),
+                        Err(e) => return show_notification(format!("Invalid catalog URL: {e}")),
+                    },
+                };
+
+                let download_limit = inner.download_limit.parse::<u64>().ok().filter(|x| *x > 0);
+                let write_limit = inner.write_limit.parse::<u64>().ok().filter(|x| *x > 0);
+
+                let catalog_task = match &catalog_url {
+                    Some(x) if Some(x) != inner.app_config().catalog_url() => {
+                        helpers::fetch_config_task(
+                            inner.common().downloader.clone(),
+                            x.clone(),
+                            true,
+                        )
+                    }
+                    _ => Task::none(),
+                };
+
+                let insecure_tls = inner.insecure_tls;
+                let common = inner.common_mut();
+                if let Some(x) = common.app_config.catalog_url()
+                    && Some(x) != catalog_url.as_ref()
+                {
+                    common.catalog_failures.remove(x);
+                }
+                common.app_config.update_proxy(proxy);
+                common.app_config.update_ca_certs(ca_certs);
+                common.app_config.update_insecure_tls(insecure_tls);
+                common.app_config.update_catalog_url(catalog_url);
+                common.app_config.update_download_limit(download_limit);
+                common.app_config.update_write_limit(write_limit);
+
+                match bb_downloader::Downloader::new(common.downloader.cache_dir()) {
+                    Ok(x) => {
+                        common.downloader = helpers::apply_network_settings(x, &common.app_config)
+                    }
+                    Err(e) => tracing::error!("Failed to recreate downloader: {e}"),
+                }
+
+                return Task::batch([
+                    inner.save_app_config(),
+                    catalog_task,
+                    show_notification("Settings saved".to_string()),
+                ]);
+            }
+            _ => panic!("Unexpected message"),
+        },
         BBImagerMessage::Null => {}
     }
 
     Task::none()
 }
 
+/// Move away from flashing page if it is showing the job. Overlays are preserved.
+fn leave_flashing_page(
+    state: &mut BBImager,
+    id: bb_flasher::JobId,
+    f: impl FnOnce(crate::state::FlashingState) -> OverlayData,
+) {
+    *state = match std::mem::take(state) {
+        BBImager::Flashing(inner) if inner.job == id => f(inner).into(),
+        BBImager::AppInfo(inner) => match inner.page {
+            OverlayData::Flashing(x) if x.job == id => BBImager::AppInfo(OverlayState {
+                page: f(x),
+                ..inner
+            }),
+            page => BBImager::AppInfo(OverlayState { page, ..inner }),
+        },
+        BBImager::Jobs(inner) => match inner.page {
+            OverlayData::Flashing(x) if x.job == id => {
+                BBImager::Jobs(crate::state::JobsState { page: f(x) })
+            }
+            page => BBImager::Jobs(crate::state::JobsState { page }),
+        },
+        BBImager::History(inner) => match inner.page {
+            OverlayData::Flashing(x) if x.job == id => {
+                BBImager::History(crate::state::HistoryState {
+                    page: f(x),
+                    ..inner
+                })
+            }
+            page => BBImager::History(crate::state::HistoryState { page, ..inner }),
+        },
+        BBImager::UsbNetwork(inner) => match inner.page {
+            OverlayData::Flashing(x) if x.job == id => {
+                BBImager::UsbNetwork(crate::state::UsbNetworkState {
+                    page: f(x),
+                    ..inner
+                })
+            }
+            page => BBImager::UsbNetwork(crate::state::UsbNetworkState { page, ..inner }),
+        },
+        x => x,
+    };
+}
+
+/// Kiosk page tracking the job.
+fn kiosk_page(
+    state: &mut BBImager,
+    id: bb_flasher::JobId,
+) -> Option<&mut crate::state::KioskState> {
+    match state {
+        BBImager::Kiosk(inner) if inner.flash_job == Some(id) => Some(inner),
+        _ => None,
+    }
+}
+
+/// Bundle page tracking the job. The page can be hidden behind an overlay.
+fn bundle_page(
+    state: &mut BBImager,
+    id: bb_flasher::JobId,
+) -> Option<&mut crate::state::BundleState> {
+    let page = match state {
+        BBImager::Bundle(inner) => inner,
+        BBImager::AppInfo(OverlayState {
+            page: OverlayData::Bundle(inner),
+            ..
+        })
+        | BBImager::Jobs(crate::state::JobsState {
+            page: OverlayData::Bundle(inner),
+        })
+        | BBImager::History(crate::state::HistoryState {
+            page: OverlayData::Bundle(inner),
+            ..
+        })
+        | BBImager::Settings(crate::state::SettingsState {
+            page: OverlayData::Bundle(inner),
+            ..
+        })
+        | BBImager::UsbNetwork(crate::state::UsbNetworkState {
+            page: OverlayData::Bundle(inner),
+            ..
+        }) => inner,
+        _ => return None,
+    };
+
+    (page.job == Some(id)).then_some(page)
+}
+
 fn show_notification(msg: String) -> Task<BBImagerMessage> {
+    Task::done(BBImagerMessage::Notify(msg))
+}
+
+fn clear_flashing_marker(job_id: u64) -> Task<BBImagerMessage> {
     Task::future(async move {
-        let res = helpers::show_notification(msg).await;
-        tracing::debug!("Notification response {res:?}");
+        if let Err(e) = crate::persistance::FlashingMarker::clear(job_id).await {
+            tracing::warn!("Failed to clear flashing marker: {e}");
+        }
         BBImagerMessage::Null
     })
 }
@@ -0,0 +1,67 @@
+//! System notifications. Failures are reported to the caller instead of panicking, so that the
+//! message can be shown inside the application instead.
+
+use std::fmt;
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const APP_ID: &str = "org.beagleboard.imagingutility";
+
+#[derive(Debug)]
+pub(crate) enum Error {
+    Notify(notify_rust::error::Error),
+    /// Notification backend panicked. This happens on some platforms with no notification
+    /// service.
+    Panic,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Notify(e) => write!(f, "{e}"),
+            Self::Panic => write!(f, "Notification service not available"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Set application identity used by the notification service. Should be called once on startup.
+pub(crate) fn init() {
+    #[cfg(target_os = "macos")]
+    // HACK: mac_notification_sys set application name (not an option in notify-rust)
+    let _ = notify_rust::set_application(APP_ID);
+}
+
+/// Show a system notification. On Linux, XDG desktop portal is tried first so that it works in
+/// sandboxes (Flatpak), falling back to D-Bus notification service.
+pub(crate) async fn notify(body: String) -> Result<(), Error> {
+    #[cfg(target_os = "linux")]
+    match xdg_portal(&body).await {
+        Ok(()) => return Ok(()),
+        Err(e) => tracing::debug!("XDG portal notification failed: {e}"),
+    }
+
+    tokio::task::spawn_blocking(move || {
+        notify_rust::Notification::new()
+            .appname("BeagleBoard Imager")
+            .body(&body)
+            .finalize()
+            .show()
+    })
+    .await
+    .map_err(|_| Error::Panic)?
+    .map(|_| ())
+    .map_err(Error::Notify)
+}
+
+#[cfg(target_os = "linux")]
+async fn xdg_portal(body: &str) -> ashpd::Result<()> {
+    let proxy = ashpd::desktop::notification::NotificationProxy::new().await?;
+
+    proxy
+        .add_notification(
+            APP_ID,
+            ashpd::desktop::notification::Notification::new("BeagleBoard Imager").body(body),
+        )
+        .await
+}
@@ -0,0 +1,126 @@
+//! Command palette (Ctrl+K) listing actions available on the current page.
+
+use crate::{BBImager, constants, locale, message::BBImagerMessage};
+
+/// Action which can be run from the command palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    SelectBoard,
+    RefreshDestinations,
+    RefreshCatalogs,
+    RefreshIcons,
+    StartFlash,
+    Jobs,
+    History,
+    UsbNetwork,
+    Settings,
+    AppInfo,
+    OpenDocs,
+}
+
+impl Action {
+    const ALL: [Self; 11] = [
+        Self::SelectBoard,
+        Self::RefreshDestinations,
+        Self::RefreshCatalogs,
+        Self::RefreshIcons,
+        Self::StartFlash,
+        Self::Jobs,
+        Self::History,
+        Self::UsbNetwork,
+        Self::Settings,
+        Self::AppInfo,
+        Self::OpenDocs,
+    ];
+
+    pub(crate) const fn label(self) -> &'static str {
+        match self {
+            Self::SelectBoard => "Select Board",
+            Self::RefreshDestinations => "Refresh Destinations",
+            Self::RefreshCatalogs => "Retry Catalog Download",
+            Self::RefreshIcons => "Refresh Icons",
+            Self::StartFlash => "Start Flashing",
+            Self::Jobs => "Show Jobs",
+            Self::History => "Show Flash History",
+            Self::UsbNetwork => "Connect over USB",
+            Self::Settings => "Open Settings",
+            Self::AppInfo => "About BeagleBoard Imager",
+            Self::OpenDocs => "Open Documentation",
+        }
+    }
+
+    pub(crate) fn message(self) -> BBImagerMessage {
+        match self {
+            Self::SelectBoard => BBImagerMessage::Restart,
+            Self::RefreshDestinations => BBImagerMessage::RefreshDestinations,
+            Self::RefreshCatalogs => BBImagerMessage::RefreshCatalogs,
+            Self::RefreshIcons => BBImagerMessage::RefreshIcons,
+            Self::StartFlash => BBImagerMessage::FlashStart,
+            Self::Jobs => BBImagerMessage::Jobs,
+            Self::History => BBImagerMessage::History,
+            Self::UsbNetwork => BBImagerMessage::UsbNetwork,
+            Self::Settings => BBImagerMessage::Settings,
+            Self::AppInfo => BBImagerMessage::AppInfo,
+            Self::OpenDocs => {
+                BBImagerMessage::OpenUrl(url::Url::parse(constants::DOCS_URL).unwrap())
+            }
+        }
+    }
+
+    /// Check if the action can be run on the current page.
+    fn is_available(self, state: &BBImager) -> bool {
+        // Pages which can be left for an overlay (settings, jobs, etc)
+        let overlay = matches!(
+            state,
+            BBImager::ChooseBoard(_)
+                | BBImager::ChooseOs(_)
+                | BBImager::ChooseDest(_)
+                | BBImager::Customize(_)
+                | BBImager::Review(_)
+                | BBImager::Flashing(_)
+                | BBImager::FlashingCancel(_)
+                | BBImager::FlashingFail(_)
+                | BBImager::FlashingSuccess(_)
+                | BBImager::Bundle(_)
+        );
+
+        match self {
+            Self::SelectBoard => matches!(
+                state,
+                BBImager::ChooseOs(_)
+                    | BBImager::ChooseDest(_)
+                    | BBImager::Customize(_)
+                    | BBImager::Review(_)
+                    | BBImager::FlashingCancel(_)
+                    | BBImager::FlashingFail(_)
+                    | BBImager::FlashingSuccess(_)
+            ),
+            Self::RefreshDestinations => matches!(state, BBImager::ChooseDest(_)),
+            Self::RefreshCatalogs => !state.common().catalog_failures.is_empty(),
+            Self::StartFlash => matches!(state, BBImager::Review(_)),
+            Self::Jobs | Self::History | Self::UsbNetwork | Self::Settings | Self::AppInfo => {
+                overlay
+            }
+            Self::RefreshIcons | Self::OpenDocs => true,
+        }
+    }
+}
+
+/// Actions available on the current page matching `query`, best match first.
+pub(crate) fn search(state: &BBImager, query: &str) -> Vec<Action> {
+    let query: Vec<char> = query
+        .to_lowercase()
+        .chars()
+        .filter(|x| !x.is_whitespace())
+        .collect();
+
+    let mut res: Vec<_> = Action::ALL
+        .into_iter()
+        .filter(|x| x.is_available(state))
+        .filter_map(|x| Some((locale::fuzzy_score(&query, x.label())?, x)))
+        .collect();
+
+    // Stable sort keeps the default order for equal scores
+    res.sort_by_key(|(score, _)| *score);
+    res.into_iter().map(|(_, x)| x).collect()
+}
@@ -1,6 +1,14 @@
 //! This module contains persistance for configuration
 
-use std::{io::Read, path::PathBuf};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Read,
+    path::PathBuf,
+    sync::{
+        LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
 
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
@@ -15,6 +23,155 @@ pub(crate) struct GuiConfiguration {
     #[cfg(feature = "pb2_mspm0")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pb2_mspm0_customization: Option<Pb2Mspm0Customization>,
+    /// Apply saved SD Card customization to every flash without showing the customization page.
+    #[serde(default)]
+    apply_saved_customization: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_dir: Option<PathBuf>,
+    #[serde(default)]
+    theme: AppTheme,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proxy: Option<String>,
+    /// PEM bundle, DER certificate or directory of certificates trusted in addition to the OS
+    /// trust store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ca_certs: Option<PathBuf>,
+    /// Skip TLS certificate verification. Only meant for closed lab networks.
+    #[serde(default)]
+    insecure_tls: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    catalog_url: Option<url::Url>,
+    #[serde(default)]
+    telemetry: bool,
+    /// Show messages inside the application instead of using system notifications.
+    #[serde(default)]
+    disable_notifications: bool,
+    /// Do not record successful flashes in the flash history.
+    #[serde(default)]
+    disable_history: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    download_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    write_limit: Option<u64>,
+    #[serde(default)]
+    sd_verify: SdVerify,
+    #[serde(default)]
+    cache_before_flash: bool,
+    /// Show sizes using powers of 1000 (GB) instead of 1024 (GiB)
+    #[serde(default)]
+    decimal_units: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scratch_dir: Option<PathBuf>,
+    #[serde(default)]
+    hide_onboarding: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    window: Option<WindowGeometry>,
+    /// Names of boards shown at the top of board selection
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pinned_boards: BTreeSet<String>,
+    /// Names of Os images shown at the top of image selection
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pinned_images: BTreeSet<String>,
+    /// Prevent changing settings and customization, for shared machines. Only set by editing the
+    /// configuration file.
+    #[serde(default)]
+    locked: bool,
+    /// PIN to unlock a locked configuration for the current session. This only deters casual
+    /// changes; the configuration file itself should be protected by file permissions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    admin_pin: Option<String>,
+    /// Kiosk job started when launched without arguments. Used with `locked` to fix the board
+    /// and image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kiosk_job: Option<PathBuf>,
+}
+
+/// Last known size and position of the main window
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct WindowGeometry {
+    width: f32,
+    height: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    x: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    y: Option<f32>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            width: crate::constants::WINDOW_SIZE.width,
+            height: crate::constants::WINDOW_SIZE.height,
+            x: None,
+            y: None,
+        }
+    }
+}
+
+impl WindowGeometry {
+    pub(crate) const fn size(&self) -> iced::Size {
+        iced::Size::new(self.width, self.height)
+    }
+
+    pub(crate) fn position(&self) -> Option<iced::Point> {
+        Some(iced::Point::new(self.x?, self.y?))
+    }
+}
+
+/// Themes supported by the GUI
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum AppTheme {
+    #[default]
+    Beagle,
+    Dark,
+    Light,
+}
+
+impl AppTheme {
+    pub(crate) const ALL: [Self; 3] = [Self::Beagle, Self::Dark, Self::Light];
+}
+
+impl std::fmt::Display for AppTheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Beagle => write!(f, "Beagle"),
+            Self::Dark => write!(f, "Dark"),
+            Self::Light => write!(f, "Light"),
+        }
+    }
+}
+
+/// Read back verification after writing SD Cards
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum SdVerify {
+    None,
+    #[default]
+    Quick,
+    Full,
+}
+
+impl SdVerify {
+    pub(crate) const ALL: [Self; 3] = [Self::None, Self::Quick, Self::Full];
+}
+
+impl std::fmt::Display for SdVerify {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::Quick => write!(f, "Quick"),
+            Self::Full => write!(f, "Full"),
+        }
+    }
+}
+
+impl From<SdVerify> for bb_flasher::sd::Verify {
+    fn from(value: SdVerify) -> Self {
+        match value {
+            SdVerify::None => Self::None,
+            SdVerify::Quick => Self::Quick,
+            SdVerify::Full => Self::Full,
+        }
+    }
 }
 
 impl GuiConfiguration {
@@ -65,6 +222,219 @@ impl GuiConfiguration {
         self.pb2_mspm0_customization.as_ref()
     }
 
+    /// Skip the customization page for SD Cards, and apply the saved customization as is. Only
+    /// takes effect once customization has been saved at least once.
+    pub(crate) const fn apply_saved_customization(&self) -> bool {
+        self.apply_saved_customization
+    }
+
+    pub(crate) fn update_apply_saved_customization(&mut self, t: bool) {
+        self.apply_saved_customization = t;
+    }
+
+    /// Custom cache directory. The default cache directory is used if not set.
+    pub(crate) fn cache_dir(&self) -> Option<&std::path::Path> {
+        self.cache_dir.as_deref()
+    }
+
+    pub(crate) fn update_cache_dir(&mut self, t: PathBuf) {
+        self.cache_dir = Some(t);
+    }
+
+    pub(crate) const fn theme(&self) -> AppTheme {
+        self.theme
+    }
+
+    pub(crate) fn update_theme(&mut self, t: AppTheme) {
+        self.theme = t;
+    }
+
+    /// Proxy used for all network requests. System proxy is used if not set.
+    pub(crate) fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    pub(crate) fn update_proxy(&mut self, t: Option<String>) {
+        self.proxy = t;
+    }
+
+    /// Root certificates trusted in addition to the OS trust store.
+    pub(crate) fn ca_certs(&self) -> Option<&std::path::Path> {
+        self.ca_certs.as_deref()
+    }
+
+    pub(crate) fn update_ca_certs(&mut self, t: Option<PathBuf>) {
+        self.ca_certs = t;
+    }
+
+    /// Whether TLS certificate verification is disabled.
+    pub(crate) const fn insecure_tls(&self) -> bool {
+        self.insecure_tls
+    }
+
+    pub(crate) fn update_insecure_tls(&mut self, t: bool) {
+        self.insecure_tls = t;
+    }
+
+    /// Additional catalog (config.json) to load along with the default one.
+    pub(crate) const fn catalog_url(&self) -> Option<&url::Url> {
+        self.catalog_url.as_ref()
+    }
+
+    pub(crate) fn update_catalog_url(&mut self, t: Option<url::Url>) {
+        self.catalog_url = t;
+    }
+
+    /// Whether the user has opted in to sending anonymous usage data.
+    pub(crate) const fn telemetry(&self) -> bool {
+        self.telemetry
+    }
+
+    pub(crate) fn update_telemetry(&mut self, t: bool) {
+        self.telemetry = t;
+    }
+
+    /// Whether to use system notifications. Messages are shown inside the application otherwise.
+    pub(crate) const fn notifications(&self) -> bool {
+        !self.disable_notifications
+    }
+
+    pub(crate) fn update_notifications(&mut self, t: bool) {
+        self.disable_notifications = !t;
+    }
+
+    /// Whether to record successful flashes in the flash history.
+    pub(crate) const fn history(&self) -> bool {
+        !self.disable_history
+    }
+
+    pub(crate) fn update_history(&mut self, t: bool) {
+        self.disable_history = !t;
+    }
+
+    /// Download speed limit in KiB/s.
+    pub(crate) const fn download_limit(&self) -> Option<u64> {
+        self.download_limit
+    }
+
+    pub(crate) fn update_download_limit(&mut self, t: Option<u64>) {
+        self.download_limit = t;
+    }
+
+    /// SD Card write speed limit in MiB/s.
+    pub(crate) const fn write_limit(&self) -> Option<u64> {
+        self.write_limit
+    }
+
+    pub(crate) fn update_write_limit(&mut self, t: Option<u64>) {
+        self.write_limit = t;
+    }
+
+    /// Verification of SD Cards after writing. Quick verification reads back the start and end of
+    /// the image along with a random sample.
+    pub(crate) const fn sd_verify(&self) -> SdVerify {
+        self.sd_verify
+    }
+
+    pub(crate) fn update_sd_verify(&mut self, t: SdVerify) {
+        self.sd_verify = t;
+    }
+
+    /// Download remote images completely before flashing instead of streaming them to the
+    /// destination while downloading.
+    pub(crate) const fn cache_before_flash(&self) -> bool {
+        self.cache_before_flash
+    }
+
+    pub(crate) fn update_cache_before_flash(&mut self, t: bool) {
+        self.cache_before_flash = t;
+    }
+
+    pub(crate) const fn unit_system(&self) -> bb_flasher::UnitSystem {
+        if self.decimal_units {
+            bb_flasher::UnitSystem::Decimal
+        } else {
+            bb_flasher::UnitSystem::Binary
+        }
+    }
+
+    pub(crate) fn update_decimal_units(&mut self, t: bool) {
+        self.decimal_units = t;
+        self.unit_system().set_preferred();
+    }
+
+    /// Directory for images being streamed while downloading. Defaults to system temp directory.
+    pub(crate) fn scratch_dir(&self) -> Option<&std::path::Path> {
+        self.scratch_dir.as_deref()
+    }
+
+    pub(crate) fn update_scratch_dir(&mut self, t: Option<PathBuf>) {
+        self.scratch_dir = t;
+    }
+
+    /// Whether the first-run onboarding wizard should be skipped.
+    pub(crate) const fn hide_onboarding(&self) -> bool {
+        self.hide_onboarding
+    }
+
+    pub(crate) fn update_hide_onboarding(&mut self, t: bool) {
+        self.hide_onboarding = t;
+    }
+
+    pub(crate) fn is_board_pinned(&self, name: &str) -> bool {
+        self.pinned_boards.contains(name)
+    }
+
+    pub(crate) fn toggle_pinned_board(&mut self, name: String) {
+        if !self.pinned_boards.remove(&name) {
+            self.pinned_boards.insert(name);
+        }
+    }
+
+    /// Whether settings and customization are locked by the administrator.
+    pub(crate) const fn locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether a locked configuration can be unlocked from the GUI.
+    pub(crate) const fn has_admin_pin(&self) -> bool {
+        self.admin_pin.is_some()
+    }
+
+    pub(crate) fn check_admin_pin(&self, pin: &str) -> bool {
+        self.admin_pin.as_deref().is_some_and(|x| x == pin)
+    }
+
+    pub(crate) fn kiosk_job(&self) -> Option<&std::path::Path> {
+        self.kiosk_job.as_deref()
+    }
+
+    pub(crate) fn is_image_pinned(&self, name: &str) -> bool {
+        self.pinned_images.contains(name)
+    }
+
+    pub(crate) fn toggle_pinned_image(&mut self, name: String) {
+        if !self.pinned_images.remove(&name) {
+            self.pinned_images.insert(name);
+        }
+    }
+
+    pub(crate) const fn window(&self) -> Option<&WindowGeometry> {
+        self.window.as_ref()
+    }
+
+    pub(crate) fn update_window_size(&mut self, t: iced::Size) {
+        let x = self.window.get_or_insert_default();
+        x.width = t.width;
+        x.height = t.height;
+    }
+
+    pub(crate) fn update_window_position(&mut self, t: iced::Point) {
+        let x = self.window.get_or_insert_default();
+        x.x = Some(t.x);
+        x.y = Some(t.y);
+    }
+
     pub(crate) fn update_sd_customization(&mut self, t: SdCustomization) {
         self.sd_customization = Some(t);
     }
@@ -106,6 +476,28 @@ pub(crate) struct SdSysconfCustomization {
     pub(crate) ssh: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) usb_enable_dhcp: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) volume_label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rootfs_label: Option<String>,
+    /// Files copied to the boot partition.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) files: Vec<SdBootFile>,
+    /// Arguments appended to the kernel command line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) cmdline_append: Option<String>,
+    /// Enable or disable the serial console. Left as is in the image if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) serial_console: Option<bool>,
+    /// apt packages installed on first boot, separated by commas or spaces.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) preinstall: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) http_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) https_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) apt_mirror: Option<String>,
 }
 
 impl Default for SdSysconfCustomization {
@@ -122,6 +514,15 @@ impl Default for SdSysconfCustomization {
             } else {
                 None
             },
+            volume_label: None,
+            rootfs_label: None,
+            files: Vec::new(),
+            cmdline_append: None,
+            serial_console: None,
+            preinstall: None,
+            http_proxy: None,
+            https_proxy: None,
+            apt_mirror: None,
         }
     }
 }
@@ -162,16 +563,140 @@ impl SdSysconfCustomization {
         self
     }
 
+    pub(crate) fn update_volume_label(mut self, t: Option<String>) -> Self {
+        self.volume_label = t;
+        self
+    }
+
+    pub(crate) fn update_rootfs_label(mut self, t: Option<String>) -> Self {
+        self.rootfs_label = t;
+        self
+    }
+
+    pub(crate) fn update_files(mut self, t: Vec<SdBootFile>) -> Self {
+        self.files = t;
+        self
+    }
+
+    pub(crate) fn update_cmdline_append(mut self, t: Option<String>) -> Self {
+        self.cmdline_append = t;
+        self
+    }
+
+    pub(crate) fn update_serial_console(mut self, t: Option<bool>) -> Self {
+        self.serial_console = t;
+        self
+    }
+
+    pub(crate) fn update_preinstall(mut self, t: Option<String>) -> Self {
+        self.preinstall = t;
+        self
+    }
+
+    /// Packages to install on first boot.
+    pub(crate) fn packages(&self) -> Vec<&str> {
+        self.preinstall
+            .as_deref()
+            .unwrap_or_default()
+            .split([',', ' '])
+            .filter(|x| !x.is_empty())
+            .collect()
+    }
+
+    pub(crate) fn validate_preinstall(&self) -> bool {
+        self.packages()
+            .into_iter()
+            .all(bb_flasher::sd::valid_package_name)
+    }
+
+    pub(crate) fn update_http_proxy(mut self, t: Option<String>) -> Self {
+        self.http_proxy = t;
+        self
+    }
+
+    pub(crate) fn update_https_proxy(mut self, t: Option<String>) -> Self {
+        self.https_proxy = t;
+        self
+    }
+
+    pub(crate) fn update_apt_mirror(mut self, t: Option<String>) -> Self {
+        self.apt_mirror = t;
+        self
+    }
+
+    pub(crate) fn validate_network(&self) -> bool {
+        [&self.http_proxy, &self.https_proxy, &self.apt_mirror]
+            .into_iter()
+            .flatten()
+            .all(|x| bb_flasher::sd::valid_url(x))
+    }
+
+    pub(crate) fn validate_cmdline_append(&self) -> bool {
+        self.cmdline_append
+            .as_deref()
+            .is_none_or(|x| bb_flasher::sd::validate_cmdline(x).is_ok())
+    }
+
+    pub(crate) fn validate_files(&self) -> bool {
+        self.files.iter().all(SdBootFile::validate)
+    }
+
+    pub(crate) fn validate_hostname(&self) -> bool {
+        self.hostname
+            .as_deref()
+            .is_none_or(|x| bb_flasher::sd::validate_hostname_template(x).is_ok())
+    }
+
+    pub(crate) fn validate_volume_label(&self) -> bool {
+        self.volume_label
+            .as_deref()
+            .is_none_or(bb_flasher::sd::valid_fat_label)
+    }
+
+    pub(crate) fn validate_rootfs_label(&self) -> bool {
+        self.rootfs_label
+            .as_deref()
+            .is_none_or(bb_flasher::sd::valid_ext_label)
+    }
+
     pub(crate) fn validate_user(&self) -> bool {
         match &self.user {
             Some(x) => x.validate_username(),
             None => true,
         }
     }
+
+    /// Merge `other` over this customization. Options set in `other` take precedence.
+    pub(crate) fn merge(self, other: Self) -> Self {
+        Self {
+            hostname: other.hostname.or(self.hostname),
+            timezone: other.timezone.or(self.timezone),
+            keymap: other.keymap.or(self.keymap),
+            user: other.user.or(self.user),
+            wifi: other.wifi.or(self.wifi),
+            ssh: other.ssh.or(self.ssh),
+            usb_enable_dhcp: other.usb_enable_dhcp.or(self.usb_enable_dhcp),
+            volume_label: other.volume_label.or(self.volume_label),
+            rootfs_label: other.rootfs_label.or(self.rootfs_label),
+            files: if other.files.is_empty() {
+                self.files
+            } else {
+                other.files
+            },
+            cmdline_append: other.cmdline_append.or(self.cmdline_append),
+            serial_console: other.serial_console.or(self.serial_console),
+            preinstall: other.preinstall.or(self.preinstall),
+            http_proxy: other.http_proxy.or(self.http_proxy),
+            https_proxy: other.https_proxy.or(self.https_proxy),
+            apt_mirror: other.apt_mirror.or(self.apt_mirror),
+        }
+    }
 }
 
 impl From<SdSysconfCustomization> for bb_flasher::sd::FlashingSdLinuxConfig {
     fn from(value: SdSysconfCustomization) -> Self {
+        let packages = value.packages().into_iter().map(Into::into).collect();
+
         Self::sysconfig(
             value.hostname.map(Into::into),
             value.timezone.map(Into::into),
@@ -181,6 +706,55 @@ impl From<SdSysconfCustomization> for bb_flasher::sd::FlashingSdLinuxConfig {
             value.ssh.map(Into::into),
             value.usb_enable_dhcp,
         )
+        .with_labels(
+            value.volume_label.map(Into::into),
+            value.rootfs_label.map(Into::into),
+        )
+        .with_files(value.files.into_iter().map(Into::into).collect())
+        .with_cmdline_append(value.cmdline_append.map(Into::into))
+        .with_serial_console(value.serial_console)
+        .with_packages(packages)
+        .with_network(
+            value.http_proxy.map(Into::into),
+            value.https_proxy.map(Into::into),
+            value.apt_mirror.map(Into::into),
+        )
+    }
+}
+
+/// File or directory on the host copied to the boot partition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SdBootFile {
+    pub(crate) src: PathBuf,
+    /// Path in the boot partition.
+    pub(crate) dest: String,
+}
+
+impl SdBootFile {
+    /// Copy to the root of the boot partition, keeping the name.
+    pub(crate) fn new(src: PathBuf) -> Self {
+        let dest = src
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self { src, dest }
+    }
+
+    pub(crate) fn update_dest(mut self, t: String) -> Self {
+        self.dest = t;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> bool {
+        bb_flasher::sd::BootFile::from(self.clone())
+            .normalized_dest()
+            .is_some()
+    }
+}
+
+impl From<SdBootFile> for bb_flasher::sd::BootFile {
+    fn from(value: SdBootFile) -> Self {
+        Self::new(value.src, value.dest.into())
     }
 }
 
@@ -237,6 +811,9 @@ impl SdCustomizationWifi {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct BcfCustomization {
     pub(crate) verify: bool,
+    /// Serial port settings of the board from the catalog. Not persisted.
+    #[serde(skip)]
+    pub(crate) serial: Option<bb_config::config::SerialConfig>,
 }
 
 impl BcfCustomization {
@@ -248,7 +825,10 @@ impl BcfCustomization {
 
 impl Default for BcfCustomization {
     fn default() -> Self {
-        Self { verify: true }
+        Self {
+            verify: true,
+            serial: None,
+        }
     }
 }
 
@@ -274,3 +854,221 @@ impl Default for Pb2Mspm0Customization {
         }
     }
 }
+
+/// Marker recorded while a destination is being written. If the application exits before the
+/// flashing finishes, the marker is left behind and can be picked up on the next launch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct FlashingMarker {
+    pub(crate) job_id: u64,
+    pub(crate) board: String,
+    pub(crate) image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) image_sha256: Option<String>,
+    /// Path of local images.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) image_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) image_size: Option<u64>,
+    pub(crate) destination: String,
+    /// Path of SD Card destinations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) destination_path: Option<PathBuf>,
+    pub(crate) progress: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last_offset: Option<u64>,
+    /// Bmap used while writing. Blocks not mapped in it were never written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) bmap: Option<MarkerBmap>,
+}
+
+/// Location of the bmap used in a [`FlashingMarker`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MarkerBmap {
+    Local(PathBuf),
+    Remote(String),
+}
+
+/// Latest progress written for each marker, or `None` once cleared. Markers are written from
+/// separate tasks, which can finish out of order.
+static MARKER_WRITES: LazyLock<tokio::sync::Mutex<HashMap<u64, Option<f32>>>> =
+    LazyLock::new(Default::default);
+
+impl FlashingMarker {
+    pub(crate) fn new(
+        board: String,
+        image: &crate::helpers::BoardImage,
+        destination: &crate::helpers::Destination,
+    ) -> Self {
+        // Timestamp in milliseconds, unique even if multiple jobs start at the same time
+        static LAST_JOB_ID: AtomicU64 = AtomicU64::new(0);
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.as_millis() as u64)
+            .unwrap_or_default();
+        let job_id = LAST_JOB_ID
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+                Some(now.max(x + 1))
+            })
+            .map_or(now, |x| now.max(x + 1));
+        let destination_path = match destination {
+            crate::helpers::Destination::SdCard(t) => Some(t.path().to_path_buf()),
+            _ => None,
+        };
+        // Bmap is only used when writing to SD Cards
+        let bmap = destination_path
+            .as_ref()
+            .and(image.bmap())
+            .map(|x| match x {
+                crate::helpers::Bmap::Local(p) => MarkerBmap::Local(p.clone()),
+                crate::helpers::Bmap::Remote { url, .. } => MarkerBmap::Remote(url.to_string()),
+            });
+
+        Self {
+            job_id,
+            board,
+            image: image.to_string(),
+            image_sha256: image.sha256().map(const_hex::encode),
+            image_path: image.local_path(),
+            image_size: image.image_size(),
+            destination: destination.to_string(),
+            destination_path,
+            progress: 0.0,
+            last_offset: None,
+            bmap,
+        }
+    }
+
+    pub(crate) fn update_progress(&mut self, progress: f32) {
+        self.progress = progress;
+        self.last_offset = self.image_size.map(|x| (x as f64 * progress as f64) as u64);
+    }
+
+    /// SHA256 of remote images. Used to find the image in cache.
+    pub(crate) fn sha256(&self) -> Option<[u8; 32]> {
+        const_hex::decode_to_array(self.image_sha256.as_ref()?).ok()
+    }
+
+    /// Markers of all interrupted flashings, oldest first.
+    pub(crate) fn load() -> Vec<Self> {
+        let Some(entries) = Self::marker_dir().and_then(|x| std::fs::read_dir(x).ok()) else {
+            return Vec::new();
+        };
+
+        let mut markers: Vec<Self> = entries
+            .filter_map(Result::ok)
+            .map(|x| x.path())
+            .filter(|x| x.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|p| {
+                let data = std::fs::read(&p).ok()?;
+                serde_json::from_slice(&data)
+                    .inspect_err(|e| {
+                        tracing::warn!("Ignoring invalid flashing marker {}: {e}", p.display())
+                    })
+                    .ok()
+            })
+            .collect();
+        markers.sort_by_key(|x| x.job_id);
+
+        markers
+    }
+
+    /// Write the marker. Skipped once the marker has been cleared, or if newer progress has
+    /// already been written.
+    pub(crate) async fn save(&self) -> std::io::Result<()> {
+        let Some(marker_p) = Self::marker_path(self.job_id) else {
+            return Ok(());
+        };
+
+        let mut writes = MARKER_WRITES.lock().await;
+        match writes.get(&self.job_id) {
+            Some(None) => return Ok(()),
+            Some(Some(x)) if *x > self.progress => return Ok(()),
+            _ => {}
+        }
+
+        let data = serde_json::to_string_pretty(self).unwrap();
+        tokio::fs::create_dir_all(marker_p.parent().unwrap()).await?;
+        tokio::fs::write(marker_p, data).await?;
+
+        writes.insert(self.job_id, Some(self.progress));
+        Ok(())
+    }
+
+    /// Remove the marker of `job_id`. Any pending writes of the marker are dropped.
+    pub(crate) async fn clear(job_id: u64) -> std::io::Result<()> {
+        let Some(marker_p) = Self::marker_path(job_id) else {
+            return Ok(());
+        };
+
+        let mut writes = MARKER_WRITES.lock().await;
+        writes.insert(job_id, None);
+
+        match tokio::fs::remove_file(marker_p).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Directory with one marker per job, so concurrent jobs do not overwrite each other.
+    fn marker_dir() -> Option<PathBuf> {
+        let dirs = crate::helpers::project_dirs()?;
+        let dir = dirs.state_dir().unwrap_or(dirs.data_local_dir());
+        Some(dir.join("flashing"))
+    }
+
+    fn marker_path(job_id: u64) -> Option<PathBuf> {
+        Some(Self::marker_dir()?.join(format!("{job_id}.json")))
+    }
+}
+
+/// Registry of successful flashes. Shared with bb-imager-cli.
+pub(crate) fn flash_history() -> Option<bb_helper::history::History> {
+    let dirs = crate::helpers::project_dirs()?;
+    Some(bb_helper::history::History::new(
+        dirs.data_local_dir().join("history.jsonl"),
+    ))
+}
+
+/// Checksums of local images keyed by file metadata. Shared with bb-imager-cli.
+pub(crate) fn checksum_cache() -> Option<bb_helper::history::ChecksumCache> {
+    let dirs = crate::helpers::project_dirs()?;
+    let dir = dirs.state_dir().unwrap_or(dirs.data_local_dir());
+    Some(bb_helper::history::ChecksumCache::new(
+        dir.join("checksums.json"),
+    ))
+}
+
+/// Record a successful flash. Checksum of local images is computed here since it is not known
+/// beforehand. Unchanged images reuse the cached checksum.
+pub(crate) async fn record_flash(
+    mut entry: bb_helper::history::HistoryEntry,
+    local_img: Option<PathBuf>,
+) {
+    let Some(history) = flash_history() else {
+        return;
+    };
+
+    if entry.image_sha256.is_none()
+        && let Some(p) = local_img
+    {
+        let cache = checksum_cache();
+        match tokio::task::spawn_blocking(move || match cache {
+            Some(c) => c.file_sha256(&p),
+            None => bb_helper::history::file_sha256(&p),
+        })
+        .await
+        .unwrap()
+        {
+            Ok(x) => entry = entry.image_sha256(x),
+            Err(e) => tracing::warn!("Failed to compute image checksum: {e}"),
+        }
+    }
+
+    if let Err(e) = tokio::task::spawn_blocking(move || history.append(&entry))
+        .await
+        .unwrap()
+    {
+        tracing::warn!("Failed to record flash in history: {e}");
+    }
+}
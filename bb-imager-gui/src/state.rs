@@ -1,25 +1,39 @@
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     time::{Duration, Instant},
 };
 
 use bb_config::config;
-use iced::{Task, widget};
+use iced::{Task, futures::SinkExt, widget};
+use tokio_stream::StreamExt as _;
+use tracing::Instrument;
 
 use crate::{
-    BBImager, constants,
+    BBImager, constants, failure,
     helpers::{self, DestinationItem, OsImageId, OsImageItem},
     message::BBImagerMessage,
-    persistance, updater,
+    persistance, telemetry, updater,
 };
 
+/// Remote catalog which could not be fetched. Retried automatically with backoff.
+#[derive(Debug)]
+pub(crate) struct CatalogFailure {
+    pub(crate) error: String,
+    attempts: u32,
+    /// A retry is running.
+    pub(crate) fetching: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct BBImagerCommon {
     pub(crate) app_config: persistance::GuiConfiguration,
+    pub(crate) window_size: iced::Size,
     pub(crate) boards: helpers::Boards,
     pub(crate) downloader: bb_downloader::Downloader,
-    pub(crate) timezones: widget::combo_box::State<String>,
-    pub(crate) keymaps: widget::combo_box::State<String>,
+    /// Search in the timezone picker.
+    pub(crate) timezone_search: String,
+    /// Search in the keymap picker.
+    pub(crate) keymap_search: String,
 
     // Constant image handles
     pub(crate) board_svg_handle: widget::svg::Handle,
@@ -31,15 +45,165 @@ pub(crate) struct BBImagerCommon {
     pub(crate) usb_svg_handle: widget::svg::Handle,
     pub(crate) file_save_icon: widget::svg::Handle,
     pub(crate) info_svg_handle: widget::svg::Handle,
+    pub(crate) settings_svg_handle: widget::svg::Handle,
     pub(crate) copy_svg_handle: widget::svg::Handle,
+    pub(crate) star_svg_handle: widget::svg::Handle,
+    pub(crate) expand_more_svg_handle: widget::svg::Handle,
+    pub(crate) star_border_svg_handle: widget::svg::Handle,
     pub(crate) window_icon_handle: widget::image::Handle,
 
     pub(crate) img_handle_cache: helpers::ImageHandleCache,
 
     pub(crate) scroll_id: widget::Id,
+
+    /// Flashing jobs by id. Jobs keep running when the user navigates away from the flashing page.
+    pub(crate) jobs: BTreeMap<bb_flasher::JobId, FlashJob>,
+
+    /// Errors from resolving remote OS sub lists, by position in OS list.
+    pub(crate) remote_subitem_errors: HashMap<Vec<usize>, String>,
+
+    /// Expanded Os image groups by board. Only kept for the current session.
+    pub(crate) expanded_groups: HashMap<usize, HashSet<Vec<usize>>>,
+
+    /// Progress of running jobs last shown on the taskbar.
+    pub(crate) taskbar_progress: Option<f32>,
+
+    /// Locked configuration has been unlocked with the admin PIN for this session.
+    pub(crate) unlocked: bool,
+
+    /// Local image opened through a file association. Preselected once a board supporting it is
+    /// chosen.
+    pub(crate) launch_image: Option<std::path::PathBuf>,
+
+    /// Search in the command palette. [`None`] while the palette is closed.
+    pub(crate) palette: Option<String>,
+    pub(crate) palette_id: widget::Id,
+
+    /// Remote catalogs which could not be fetched, by url. Only bundled boards are available until
+    /// they are fetched.
+    pub(crate) catalog_failures: HashMap<url::Url, CatalogFailure>,
+
+    /// Number of catalogs and icons being downloaded in background.
+    pub(crate) background_tasks: usize,
+
+    /// Messages shown inside the application, one at a time, by id. The first one is visible.
+    pub(crate) toasts: VecDeque<(u64, String)>,
+    pub(crate) next_toast_id: u64,
+
+    /// Versions of connected microcontrollers by destination identifier. [`None`] while probing.
+    #[cfg(feature = "bcf_cc1352p7")]
+    pub(crate) device_versions:
+        HashMap<String, Option<Result<bb_flasher::bcf::DeviceVersion, String>>>,
+
+    /// Simulate flashing instead of using real hardware.
+    #[cfg(feature = "demo")]
+    pub(crate) mock_flasher: helpers::MockFlasherConfig,
 }
 
 impl BBImagerCommon {
+    /// Record failure to fetch a remote catalog, and retry it after a delay.
+    pub(crate) fn catalog_failed(&mut self, url: url::Url, error: String) -> Task<BBImagerMessage> {
+        let failure = self
+            .catalog_failures
+            .entry(url.clone())
+            .or_insert(CatalogFailure {
+                error: String::new(),
+                attempts: 0,
+                fetching: false,
+            });
+        failure.error = error;
+        failure.fetching = false;
+
+        let delay = constants::CATALOG_RETRY_DELAY * (1 << failure.attempts.min(6));
+        failure.attempts += 1;
+
+        Task::future(async move {
+            tokio::time::sleep(delay).await;
+            BBImagerMessage::RetryCatalog(url)
+        })
+    }
+
+    /// Fetch a remote catalog which failed earlier, unless it is already being fetched.
+    pub(crate) fn retry_catalog(&mut self, url: url::Url) -> Task<BBImagerMessage> {
+        match self.catalog_failures.get_mut(&url) {
+            Some(x) if !x.fetching => {
+                x.fetching = true;
+                let trusted = self.app_config.catalog_url() == Some(&url);
+                helpers::fetch_config_task(self.downloader.clone(), url, trusted)
+            }
+            _ => Task::none(),
+        }
+    }
+
+    /// Fetch all failed remote catalogs now. Backoff starts over for further failures.
+    pub(crate) fn refresh_catalogs(&mut self) -> Task<BBImagerMessage> {
+        let urls: Vec<_> = self
+            .catalog_failures
+            .iter_mut()
+            .map(|(k, v)| {
+                v.attempts = 0;
+                k.clone()
+            })
+            .collect();
+
+        Task::batch(urls.into_iter().map(|x| self.retry_catalog(x)))
+    }
+
+    /// Queue a message to show inside the application. Messages already in the queue are
+    /// ignored.
+    pub(crate) fn push_toast(&mut self, msg: String) -> Task<BBImagerMessage> {
+        if self.toasts.iter().any(|(_, x)| *x == msg) {
+            return Task::none();
+        }
+
+        let id = self.next_toast_id;
+        self.next_toast_id += 1;
+        self.toasts.push_back((id, msg));
+
+        if self.toasts.len() == 1 {
+            toast_timer(id)
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Remove a message, and start the timer for the next one if it was visible.
+    pub(crate) fn dismiss_toast(&mut self, id: u64) -> Task<BBImagerMessage> {
+        let visible = self.toasts.front().is_some_and(|(x, _)| *x == id);
+        self.toasts.retain(|(x, _)| *x != id);
+
+        match self.toasts.front() {
+            Some((x, _)) if visible => toast_timer(*x),
+            _ => Task::none(),
+        }
+    }
+
+    /// Probe version of a microcontroller destination. Only done when requested by the user, since
+    /// probing resets the device into bootloader.
+    #[cfg(feature = "bcf_cc1352p7")]
+    pub(crate) fn probe_destination(
+        &mut self,
+        dst: helpers::Destination,
+        serial: Option<&config::SerialConfig>,
+    ) -> Task<BBImagerMessage> {
+        if !dst.supports_probe()
+            || matches!(self.device_versions.get(&dst.identifier()), Some(None))
+        {
+            return Task::none();
+        }
+
+        let id = dst.identifier();
+        self.device_versions.insert(id.clone(), None);
+
+        Task::perform(dst.probe(helpers::bcf_port_config(serial)), move |x| {
+            BBImagerMessage::DestinationProbed(id.clone(), x)
+        })
+    }
+
+    pub(crate) fn save_app_config(&self) -> Task<BBImagerMessage> {
+        save_app_config(self.app_config.clone())
+    }
+
     pub(crate) fn updater_task(&self) -> Task<BBImagerMessage> {
         if cfg!(feature = "updater") {
             let downloader = self.downloader.clone();
@@ -62,26 +226,185 @@ impl BBImagerCommon {
         }
     }
 
+    /// Load icons which are not loaded yet. Icons downloaded in previous sessions are used from
+    /// cache, while icons which failed to download earlier are tried again.
     pub(crate) fn fetch_images(
         &self,
         iter: impl IntoIterator<Item = url::Url>,
     ) -> Task<BBImagerMessage> {
-        let tasks = iter.into_iter().map(|icon| {
-            let downloader = self.downloader.clone();
-            let icon_clone = icon.clone();
-            let icon_clone2 = icon.clone();
-            Task::perform(
-                async move { downloader.download_no_cache(icon_clone, None).await },
-                move |p| match p {
-                    Ok(p) => BBImagerMessage::ResolveImage(icon_clone2, p),
-                    Err(_) => {
-                        tracing::warn!("Failed to fetch image {}", icon);
-                        BBImagerMessage::Null
-                    }
+        let tasks = iter
+            .into_iter()
+            .filter(|icon| self.img_handle_cache.get(icon).is_none())
+            .map(
+                |icon| match self.downloader.check_cache_from_url(icon.clone()) {
+                    Some(p) => Task::done(BBImagerMessage::ResolveImage(icon, p)),
+                    None => self.download_image(icon),
                 },
+            );
+        Task::batch(tasks)
+    }
+
+    /// Download all loaded icons again, replacing the cached copies.
+    pub(crate) fn refresh_images(&self) -> Task<BBImagerMessage> {
+        let icons: HashSet<url::Url> = self
+            .img_handle_cache
+            .urls()
+            .cloned()
+            .chain(
+                self.boards
+                    .devices()
+                    .filter_map(|(_, dev)| dev.icon.clone()),
             )
+            .collect();
+
+        Task::batch(icons.into_iter().map(|x| self.download_image(x)))
+    }
+
+    fn download_image(&self, icon: url::Url) -> Task<BBImagerMessage> {
+        let downloader = self.downloader.clone();
+        let icon_clone = icon.clone();
+        let icon_clone2 = icon.clone();
+        helpers::background_task(Task::perform(
+            async move { downloader.download_no_cache(icon_clone, None).await },
+            move |p| match p {
+                Ok(p) => BBImagerMessage::ResolveImage(icon_clone2, p),
+                Err(_) => {
+                    tracing::warn!("Failed to fetch image {}", icon);
+                    BBImagerMessage::Toast("Failed to download some icons".to_string())
+                }
+            },
+        ))
+    }
+
+    /// Start flashing in background. Progress and result are reported using messages tagged with
+    /// the returned job id.
+    pub(crate) fn spawn_flash_job(
+        &mut self,
+        job_info: (String, String, String),
+        img: helpers::BoardImage,
+        customization: helpers::FlashingCustomization,
+        dst: helpers::Destination,
+        marker: Option<persistance::FlashingMarker>,
+        history: Option<(bb_helper::history::HistoryEntry, Option<std::path::PathBuf>)>,
+    ) -> (bb_flasher::JobId, Task<BBImagerMessage>) {
+        let job_id = bb_flasher::JobId::new();
+
+        let history = history
+            .filter(|_| self.app_config.history())
+            .map(|(entry, local_img)| match &dst {
+                helpers::Destination::SdCard(t) => match bb_flasher::sd::card_serial(t.path()) {
+                    Some(x) => (entry.destination_serial(x), local_img),
+                    None => (entry, local_img),
+                },
+                _ => (entry, local_img),
+            });
+
+        let is_download = dst.is_download_action();
+        let size = img.image_size().map(bb_flasher::ByteSize::new);
+
+        let (customization, hostname) = customization.for_destination(&dst);
+        let credentials = customization.credentials();
+        let login = customization.login();
+
+        tracing::info!("Selected Image: {:#?}", img);
+        tracing::info!("Selected Destination: {:#?}", dst);
+        tracing::info!("Selected Customization: {:#?}", customization);
+        if let Some(x) = &hostname {
+            tracing::info!("Generated hostname: {x}");
+        }
+
+        let span = tracing::info_span!(
+            "flash_job",
+            job = %job_id,
+            board = %job_info.0,
+            image = %job_info.1,
+            destination = %job_info.2,
+        );
+        let board = job_info.0.clone();
+
+        let cancel = tokio_util::sync::CancellationToken::new();
+        let mut options = helpers::FlashOptions::new(&self.app_config);
+        let catalog_timeouts = self
+            .boards
+            .devices()
+            .find(|(_, x)| x.name == job_info.0)
+            .and_then(|(_, x)| x.timeouts.as_ref());
+        options.timeouts = helpers::flash_timeouts(&dst, catalog_timeouts);
+        #[cfg(feature = "demo")]
+        let mock_flasher = self.mock_flasher;
+
+        let s = iced::stream::channel(20, async move |mut chan| {
+            let (tx, mut rx) = iced::futures::channel::mpsc::channel(19);
+
+            let cancel_child = cancel.child_token();
+            let start = Instant::now();
+            let flash_task = tokio::spawn(
+                async move {
+                    #[cfg(feature = "demo")]
+                    if mock_flasher.enabled {
+                        tracing::info!("Using mock flasher");
+                        return mock_flasher.flash(job_id, tx, cancel_child).await;
+                    }
+
+                    helpers::flash(img, customization, dst, options, job_id, tx, cancel_child).await
+                }
+                .instrument(span.clone()),
+            );
+            let mut chan_clone = chan.clone();
+            let progress_task = tokio::spawn(async move {
+                while let Some(progress) = rx.next().await {
+                    let _ = chan_clone.try_send(BBImagerMessage::FlashProgress(progress));
+                }
+            });
+            let _guard = cancel.drop_guard();
+
+            let res = flash_task
+                .await
+                .expect("Tokio runtime failed to spawn task");
+
+            let success = res.is_ok();
+            telemetry::record_flash(&board, success, start.elapsed());
+            let res = span.in_scope(|| match res {
+                Ok(_) => {
+                    tracing::info!("Flashing Successfull");
+                    BBImagerMessage::FlashSuccess(job_id)
+                }
+                Err(e) => {
+                    tracing::error!("Flashing failed with error: {:#?}", e);
+                    BBImagerMessage::FlashFail(job_id, failure::Failure::new(&e))
+                }
+            });
+
+            let _ = chan.send(res).await;
+            progress_task.abort();
+
+            if success && let Some((entry, local_img)) = history {
+                persistance::record_flash(entry, local_img).await;
+            }
         });
-        Task::batch(tasks)
+
+        let (t, h) = Task::stream(s).abortable();
+
+        self.jobs.insert(
+            job_id,
+            FlashJob {
+                board: job_info.0,
+                image: job_info.1,
+                destination: job_info.2,
+                is_download,
+                size,
+                cancel: h,
+                progress: bb_flasher::DownloadFlashingStatus::Preparing,
+                start_timestamp: None,
+                marker,
+                status: JobStatus::Running,
+                hostname,
+                credentials,
+                login,
+            },
+        );
+
+        (job_id, t)
     }
 
     pub(crate) fn fetch_board_images(&self) -> Task<BBImagerMessage> {
@@ -114,25 +437,7 @@ impl BBImagerCommon {
         let Some(os_images) = self.boards.images(board, target) else {
             // Maybe resolving was missed
             if let config::OsListItem::RemoteSubList(item) = self.boards.image(target) {
-                let url = item.subitems_url.clone();
-                tracing::debug!("Downloading subitems from {:?}", url);
-
-                let target_clone: Vec<usize> = target.to_vec();
-                let downloader = self.downloader.clone();
-
-                return Task::perform(
-                    async move { downloader.download_json_no_cache(url).await },
-                    move |x| match x {
-                        Ok(item) => BBImagerMessage::ResolveRemoteSubitemItem {
-                            item,
-                            target: target_clone.clone(),
-                        },
-                        Err(e) => {
-                            tracing::warn!("Failed to download subitems with error {e}");
-                            BBImagerMessage::Null
-                        }
-                    },
-                );
+                return self.fetch_remote_subitem(target.to_vec(), item.subitems_url.clone());
             } else {
                 return Task::none();
             }
@@ -141,7 +446,6 @@ impl BBImagerCommon {
         let remote_image_jobs = os_images
             .filter_map(|(idx, x)| {
                 if let config::OsListItem::RemoteSubList(item) = x {
-                    tracing::debug!("Fetch: {:?} at {}", item.subitems_url, idx);
                     Some((idx, item.subitems_url.clone()))
                 } else {
                     None
@@ -151,40 +455,86 @@ impl BBImagerCommon {
                 let mut new_target: Vec<usize> = target.to_vec();
                 new_target.push(idx);
 
-                let downloader = self.downloader.clone();
-                let url_clone = url.clone();
-                Task::perform(
-                    async move {
-                        downloader
-                            .download_json_no_cache::<Vec<config::OsListItem>, url::Url>(url_clone)
-                            .await
-                    },
-                    move |x| match x {
-                        Ok(item) => BBImagerMessage::ResolveRemoteSubitemItem {
-                            item,
-                            target: new_target.clone(),
-                        },
-                        Err(e) => {
-                            tracing::warn!("Failed to download subitems {:?} with error {e}", url);
-                            BBImagerMessage::Null
-                        }
-                    },
-                )
+                self.fetch_remote_subitem(new_target, url)
             });
 
         Task::batch(remote_image_jobs)
     }
+
+    /// Download subitems of a remote sub list. Cached lists are used when offline.
+    fn fetch_remote_subitem(&self, target: Vec<usize>, url: url::Url) -> Task<BBImagerMessage> {
+        tracing::debug!("Fetch: {:?} at {:?}", url, target);
+
+        let downloader = self.downloader.clone();
+        Task::perform(
+            async move {
+                downloader
+                    .download_json_with_ttl::<Vec<config::OsListItem>, url::Url>(
+                        url.clone(),
+                        constants::REMOTE_SUBITEMS_TTL,
+                    )
+                    .await
+                    .map_err(|e| {
+                        tracing::warn!("Failed to download subitems {:?} with error {e}", url);
+                        e.to_string()
+                    })
+            },
+            move |x| match x {
+                Ok(item) => BBImagerMessage::ResolveRemoteSubitemItem {
+                    item,
+                    target: target.clone(),
+                },
+                Err(error) => BBImagerMessage::RemoteSubitemFailed {
+                    target: target.clone(),
+                    error,
+                },
+            },
+        )
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct ChooseBoardState {
     pub(crate) common: BBImagerCommon,
     pub(crate) selected_board: Option<usize>,
+    /// Search query for boards and Os images of all boards.
+    pub(crate) search: String,
 }
 
 impl ChooseBoardState {
+    /// Boards with name matching the search query. Pinned boards are listed first.
     pub(crate) fn devices(&self) -> impl Iterator<Item = (usize, &config::Device)> {
-        self.common.boards.devices()
+        let query = self.search.trim().to_lowercase();
+        let mut devs: Vec<_> = self
+            .common
+            .boards
+            .devices()
+            .filter(move |(_, x)| x.name.to_lowercase().contains(&query))
+            .collect();
+        devs.sort_by_key(|(_, x)| !self.is_board_pinned(&x.name));
+
+        devs.into_iter()
+    }
+
+    pub(crate) fn is_board_pinned(&self, name: &str) -> bool {
+        self.common.app_config.is_board_pinned(name)
+    }
+
+    pub(crate) fn star_svg(&self, pinned: bool) -> &widget::svg::Handle {
+        if pinned {
+            &self.common.star_svg_handle
+        } else {
+            &self.common.star_border_svg_handle
+        }
+    }
+
+    /// Os images of all boards matching the search query.
+    pub(crate) fn search_results(&self) -> Vec<helpers::SearchResult<'_>> {
+        self.common.boards.search(&self.search)
+    }
+
+    pub(crate) fn board(&self, idx: usize) -> &config::Device {
+        self.common.boards.device(idx)
     }
 
     pub(crate) fn board_svg(&self) -> &widget::svg::Handle {
@@ -199,6 +549,13 @@ impl ChooseBoardState {
         Some(self.common.boards.device(self.selected_board?))
     }
 
+    /// Selected board along with the name of its newest stable Os image.
+    pub(crate) fn recommended(&self) -> Option<(usize, &str)> {
+        let board = self.selected_board?;
+        let (_, name) = self.common.boards.recommended(board)?;
+        Some((board, name))
+    }
+
     pub(crate) fn image_handle_cache(&self) -> &helpers::ImageHandleCache {
         &self.common.img_handle_cache
     }
@@ -209,6 +566,7 @@ impl From<ChooseOsState> for ChooseBoardState {
         Self {
             common: value.common,
             selected_board: Some(value.selected_board),
+            search: String::new(),
         }
     }
 }
@@ -219,9 +577,51 @@ pub(crate) struct ChooseOsState {
     pub(crate) selected_board: usize,
     pub(crate) pos: Vec<usize>,
     pub(crate) selected_image: Option<(OsImageId, helpers::BoardImage)>,
+    /// SHA256 of the selected local image, if requested
+    pub(crate) checksum: Option<LocalChecksum>,
+}
+
+/// SHA256 computation of a local image.
+#[derive(Debug, Clone)]
+pub(crate) enum LocalChecksum {
+    Computing(f32),
+    Done(Result<String, String>),
 }
 
 impl ChooseOsState {
+    /// Compute SHA256 of the selected local image in background.
+    pub(crate) fn compute_checksum(&mut self) -> Task<BBImagerMessage> {
+        let Some(path) = self
+            .selected_image
+            .as_ref()
+            .and_then(|(_, x)| x.local_path())
+        else {
+            return Task::none();
+        };
+
+        self.checksum = Some(LocalChecksum::Computing(0.0));
+
+        let s = iced::stream::channel(20, async move |mut chan| {
+            let mut progress = chan.clone();
+            let p = path.clone();
+            let res = tokio::task::spawn_blocking(move || {
+                bb_helper::history::file_sha256_with_progress(&p, |pos, size| {
+                    let _ = progress.try_send(BBImagerMessage::ChecksumProgress(
+                        pos as f32 / size.max(1) as f32,
+                    ));
+                })
+            })
+            .await
+            .expect("Tokio runtime failed to spawn task")
+            .map(|x| x.iter().map(|b| format!("{b:02x}")).collect())
+            .map_err(|e| e.to_string());
+
+            let _ = chan.send(BBImagerMessage::ChecksumResult(path, res)).await;
+        });
+
+        Task::stream(s)
+    }
+
     pub(crate) fn selected_image(&self) -> Option<(&OsImageId, &helpers::BoardImage)> {
         match &self.selected_image {
             Some((x, y)) => Some((x, y)),
@@ -233,35 +633,141 @@ impl ChooseOsState {
         self.common.boards.device(self.selected_board)
     }
 
+    /// Items at the current position in Os list. Sub lists are shown as collapsible groups, with
+    /// items of expanded groups following the group. Pinned images are listed first in each group.
     pub(crate) fn images(&self) -> Option<impl Iterator<Item = OsImageItem<'_>>> {
-        let iter = self
-            .common
+        // Check that the current position has been resolved
+        self.common
             .boards
-            .images(self.selected_board, self.pos.as_slice())?
-            .map(|(id, x)| {
-                let mut idx = self.pos.clone();
-                idx.push(id);
-
-                OsImageItem::remote(
-                    idx,
-                    x.icon(),
-                    x.name(),
-                    matches!(
-                        x,
-                        config::OsListItem::SubList(_) | config::OsListItem::RemoteSubList(_)
-                    ),
-                )
-            });
+            .images(self.selected_board, self.pos.as_slice())?;
+
+        let mut items = Vec::new();
+        self.group_items(&self.pos, 0, &mut items);
 
         let extra = match self.flasher() {
             config::Flasher::SdCard => vec![
                 OsImageItem::format(self.pos.clone(), "Format SD Card"),
+                OsImageItem::customize(self.pos.clone()),
                 OsImageItem::local(self.pos.clone()),
             ],
             _ => vec![OsImageItem::local(self.pos.clone())],
         };
 
-        Some(iter.chain(extra))
+        Some(items.into_iter().chain(extra))
+    }
+
+    fn group_items<'a>(&'a self, pos: &[usize], depth: usize, res: &mut Vec<OsImageItem<'a>>) {
+        let Some(items) = self.common.boards.images(self.selected_board, pos) else {
+            return;
+        };
+        let mut items: Vec<_> = items.collect();
+        items.sort_by_key(|(_, x)| {
+            !(matches!(
+                x,
+                config::OsListItem::Image(_) | config::OsListItem::Bundle(_)
+            ) && self.common.app_config.is_image_pinned(x.name()))
+        });
+
+        for (id, x) in items {
+            let mut idx = pos.to_vec();
+            idx.push(id);
+
+            match x {
+                config::OsListItem::SubList(_) => {
+                    let group = helpers::ImageGroup {
+                        count: self.common.boards.image_count(self.selected_board, &idx),
+                        expanded: self.is_group_expanded(&idx),
+                    };
+
+                    res.push(OsImageItem::group(
+                        idx.clone(),
+                        x.icon(),
+                        x.name(),
+                        depth,
+                        group,
+                    ));
+
+                    if group.expanded {
+                        self.group_items(&idx, depth + 1, res);
+                    }
+                }
+                config::OsListItem::RemoteSubList(_) => {
+                    res.push(OsImageItem::remote(idx, x.icon(), x.name(), true, depth))
+                }
+                config::OsListItem::Image(img) => res.push(
+                    OsImageItem::remote(idx, x.icon(), x.name(), false, depth)
+                        .with_badge(self.image_badge(img)),
+                ),
+                config::OsListItem::Bundle(_) => {
+                    res.push(OsImageItem::remote(idx, x.icon(), x.name(), false, depth))
+                }
+            }
+        }
+    }
+
+    /// Cache status or download size of an image.
+    fn image_badge(&self, img: &config::OsImage) -> Option<String> {
+        if self
+            .common
+            .downloader
+            .check_cache(img.image_download_sha256)
+            .is_some()
+        {
+            Some("Cached".to_string())
+        } else {
+            img.image_download_size
+                .map(|x| format!("{} download", bb_flasher::ByteSize::new(x)))
+        }
+    }
+
+    pub(crate) fn is_group_expanded(&self, target: &[usize]) -> bool {
+        self.common
+            .expanded_groups
+            .get(&self.selected_board)
+            .is_some_and(|x| x.contains(target))
+    }
+
+    /// Expand or collapse a group. Returns `true` if the group was expanded.
+    pub(crate) fn toggle_group(&mut self, target: Vec<usize>) -> bool {
+        let groups = self
+            .common
+            .expanded_groups
+            .entry(self.selected_board)
+            .or_default();
+
+        if groups.remove(&target) {
+            false
+        } else {
+            groups.insert(target);
+            true
+        }
+    }
+
+    pub(crate) fn expand_more_svg(&self) -> &widget::svg::Handle {
+        &self.common.expand_more_svg_handle
+    }
+
+    /// Only images from the Os list can be pinned. Sub lists and local images cannot.
+    pub(crate) fn is_image_pinned(&self, item: &OsImageItem) -> bool {
+        matches!(item.id, OsImageId::Remote(_))
+            && !item.is_sublist
+            && self.common.app_config.is_image_pinned(item.label)
+    }
+
+    /// Name of the selected image if it can be pinned.
+    pub(crate) fn selected_image_name(&self) -> Option<&str> {
+        match &self.selected_image.as_ref()?.0 {
+            OsImageId::Remote(x) => Some(self.image(x).name()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn star_svg(&self, pinned: bool) -> &widget::svg::Handle {
+        if pinned {
+            &self.common.star_svg_handle
+        } else {
+            &self.common.star_border_svg_handle
+        }
     }
 
     pub(crate) fn image(&self, idx: &[usize]) -> &config::OsListItem {
@@ -272,12 +778,22 @@ impl ChooseOsState {
         &self.common.img_handle_cache
     }
 
+    /// Error from resolving the currently open remote sub list, if any.
+    pub(crate) fn subitems_error(&self) -> Option<&str> {
+        self.common
+            .remote_subitem_errors
+            .get(&self.pos)
+            .map(String::as_str)
+    }
+
     pub(crate) fn flasher(&self) -> config::Flasher {
         if self.pos.is_empty() {
             self.selected_board().flasher
         } else {
             match self.image(&self.pos) {
-                config::OsListItem::Image(_) => panic!("Expected list"),
+                config::OsListItem::Image(_) | config::OsListItem::Bundle(_) => {
+                    panic!("Expected list")
+                }
                 config::OsListItem::SubList(x) => x.flasher,
                 config::OsListItem::RemoteSubList(x) => x.flasher,
             }
@@ -300,6 +816,10 @@ impl ChooseOsState {
         &self.common.file_add_svg_handle
     }
 
+    pub(crate) fn settings_svg(&self) -> &widget::svg::Handle {
+        &self.common.settings_svg_handle
+    }
+
     pub(crate) fn arrow_back_svg(&self) -> &widget::svg::Handle {
         &self.common.arrow_back_svg_handle
     }
@@ -331,6 +851,7 @@ impl From<CustomizeState> for ChooseOsState {
             selected_board: value.selected_board,
             pos: Vec::new(),
             selected_image: Some(value.selected_image),
+            checksum: None,
         }
     }
 }
@@ -342,6 +863,7 @@ impl From<ChooseDestState> for ChooseOsState {
             selected_board: value.selected_board,
             pos: Vec::new(),
             selected_image: Some(value.selected_image),
+            checksum: None,
         }
     }
 }
@@ -361,6 +883,13 @@ impl ChooseDestState {
         let iter = self.destinations.iter().map(DestinationItem::Destination);
 
         let temp = match self.selected_image.1.file_name() {
+            Some(x) if self.selected_image.1.flasher() == config::Flasher::SdCard => {
+                let export = x.strip_suffix(".xz").unwrap_or(&x).to_string();
+                vec![
+                    DestinationItem::SaveToFile(x),
+                    DestinationItem::ExportImage(export),
+                ]
+            }
             Some(x) => vec![DestinationItem::SaveToFile(x)],
             None => vec![],
         };
@@ -386,6 +915,44 @@ impl ChooseDestState {
             None => self.selected_board().instructions.as_deref(),
         }
     }
+
+    /// Short version summary to show in destination list.
+    #[cfg(feature = "bcf_cc1352p7")]
+    pub(crate) fn version_summary(&self, dst: &helpers::Destination) -> Option<String> {
+        match self.common.device_versions.get(&dst.identifier())? {
+            None => Some("Probing...".to_string()),
+            Some(Ok(v)) => Some(format!(
+                "Firmware: {}",
+                v.firmware.as_deref().unwrap_or("Unknown")
+            )),
+            Some(Err(_)) => None,
+        }
+    }
+
+    /// Bootloader and firmware version of microcontroller destinations.
+    #[cfg(feature = "bcf_cc1352p7")]
+    pub(crate) fn version_details(
+        &self,
+        dst: &helpers::Destination,
+    ) -> Vec<(&'static str, String)> {
+        match self.common.device_versions.get(&dst.identifier()) {
+            Some(None) => vec![("Version", "Probing...".to_string())],
+            Some(Some(Ok(v))) => vec![
+                (
+                    "Bootloader",
+                    v.bootloader
+                        .clone()
+                        .unwrap_or_else(|| "Unknown".to_string()),
+                ),
+                (
+                    "Firmware",
+                    v.firmware.clone().unwrap_or_else(|| "Unknown".to_string()),
+                ),
+            ],
+            Some(Some(Err(e))) => vec![("Version", format!("Failed to probe: {e}"))],
+            None => Vec::new(),
+        }
+    }
 }
 
 impl From<CustomizeState> for ChooseDestState {
@@ -408,15 +975,15 @@ pub(crate) struct CustomizeState {
     pub(crate) selected_image: (OsImageId, helpers::BoardImage),
     pub(crate) selected_dest: helpers::Destination,
     pub(crate) customization: helpers::FlashingCustomization,
+    /// Result of inspecting the selected image, if requested.
+    pub(crate) image_info: Option<Result<bb_flasher::sd::ImageInfo, String>>,
 }
 
 impl CustomizeState {
-    pub(crate) fn timezones(&self) -> &widget::combo_box::State<String> {
-        &self.common.timezones
-    }
-
-    pub(crate) fn keymaps(&self) -> &widget::combo_box::State<String> {
-        &self.common.keymaps
+    /// Only SD Card images contain partitions which can be inspected.
+    pub(crate) fn can_inspect(&self) -> bool {
+        matches!(self.selected_image.1, helpers::BoardImage::Image { .. })
+            && self.selected_image.1.flasher() == config::Flasher::SdCard
     }
 
     pub(crate) fn app_config(&self) -> &persistance::GuiConfiguration {
@@ -424,13 +991,7 @@ impl CustomizeState {
     }
 
     pub(crate) fn save_app_config(&self) -> Task<BBImagerMessage> {
-        let config = self.app_config().clone();
-        Task::future(async move {
-            if let Err(e) = config.save().await {
-                tracing::error!("Failed to save config: {e}");
-            }
-            BBImagerMessage::Null
-        })
+        save_app_config(self.app_config().clone())
     }
 
     pub(crate) fn selected_board(&self) -> &str {
@@ -441,9 +1002,13 @@ impl CustomizeState {
         self.selected_image.1.to_string()
     }
 
+    pub(crate) fn image_warning(&self) -> Option<&str> {
+        self.selected_image.1.warning()
+    }
+
     pub(crate) fn selected_destination(&self) -> String {
         match self.selected_dest.size() {
-            Some(x) => format!("{} ({})", self.selected_dest, helpers::pretty_bytes(x)),
+            Some(x) => format!("{} ({x})", self.selected_dest),
             None => self.selected_dest.to_string(),
         }
     }
@@ -485,6 +1050,10 @@ impl CustomizeState {
                     ans.push("• USB DHCP enabled");
                 }
 
+                if x.volume_label.is_some() || x.rootfs_label.is_some() {
+                    ans.push("• Volume labels configured");
+                }
+
                 ans
             }
             helpers::FlashingCustomization::Bcf(x) => {
@@ -499,19 +1068,38 @@ impl CustomizeState {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum JobStatus {
+    Running,
+    Success,
+    Failed(String),
+    Cancelled,
+}
+
 #[derive(Debug)]
-pub(crate) struct FlashingState {
-    pub(crate) common: BBImagerCommon,
-    pub(crate) selected_board: usize,
-    pub(crate) cancel_flashing: iced::task::Handle,
+pub(crate) struct FlashJob {
+    pub(crate) board: String,
+    pub(crate) image: String,
+    pub(crate) destination: String,
+    pub(crate) is_download: bool,
+    /// Size of the image being written. Only known for remote images.
+    pub(crate) size: Option<bb_flasher::ByteSize>,
+    pub(crate) cancel: iced::task::Handle,
     pub(crate) progress: bb_flasher::DownloadFlashingStatus,
     pub(crate) start_timestamp: Option<Instant>,
-    pub(crate) is_download: bool,
+    pub(crate) marker: Option<persistance::FlashingMarker>,
+    pub(crate) status: JobStatus,
+    /// Hostname generated from a template for this destination.
+    pub(crate) hostname: Option<String>,
+    /// Hostname, user and Wi-Fi settings shown as QR Code after flashing.
+    pub(crate) credentials: Option<String>,
+    /// Hostname and user to find and log in to the board after flashing.
+    pub(crate) login: Option<(String, Option<String>)>,
 }
 
-impl FlashingState {
-    pub(crate) fn selected_board(&self) -> &config::Device {
-        self.common.boards.device(self.selected_board)
+impl FlashJob {
+    pub(crate) fn is_running(&self) -> bool {
+        self.status == JobStatus::Running
     }
 
     pub(crate) fn time_remaining(&self) -> Option<Duration> {
@@ -529,11 +1117,29 @@ impl FlashingState {
                     Some(t.mul_f32(scale))
                 }
             }
-            bb_flasher::DownloadFlashingStatus::Customizing => Some(Duration::from_secs(1)),
+            bb_flasher::DownloadFlashingStatus::Customizing(_) => Some(Duration::from_secs(1)),
             _ => None,
         }
     }
 
+    /// Average write speed. Only known while flashing images of known size.
+    pub(crate) fn rate(&self) -> Option<bb_flasher::TransferRate> {
+        let bb_flasher::DownloadFlashingStatus::FlashingProgress(_) = self.progress else {
+            return None;
+        };
+        let written = self.progress.transferred(self.size?)?;
+
+        Some(bb_flasher::TransferRate::new(
+            written,
+            self.start_timestamp?.elapsed(),
+        ))
+    }
+
+    /// Progress of the current step, between 0 and 1.
+    pub(crate) fn progress_fraction(&self) -> f32 {
+        self.progress.progress()
+    }
+
     pub(crate) fn progress_update(&mut self, u: bb_flasher::DownloadFlashingStatus) {
         // Required for better time estimate.
         match u {
@@ -548,6 +1154,350 @@ impl FlashingState {
 
         self.progress = u;
     }
+
+    /// Persist flashing progress so that an interrupted flash can be detected on next launch.
+    pub(crate) fn save_marker(&mut self) -> Task<BBImagerMessage> {
+        const STEP: f32 = 0.05;
+
+        let bb_flasher::DownloadFlashingStatus::FlashingProgress(progress) = self.progress else {
+            return Task::none();
+        };
+        let Some(marker) = self.marker.as_mut() else {
+            return Task::none();
+        };
+
+        if progress - marker.progress < STEP {
+            return Task::none();
+        }
+
+        marker.update_progress(progress);
+        let marker = marker.clone();
+
+        Task::future(async move {
+            if let Err(e) = marker.save().await {
+                tracing::warn!("Failed to save flashing marker: {e}");
+            }
+            BBImagerMessage::Null
+        })
+    }
+
+    /// Mark the job as finished. Returns the notification message.
+    pub(crate) fn finish(&mut self, status: JobStatus) -> &'static str {
+        let msg = match (&status, self.is_download) {
+            (JobStatus::Success, false) => "Flashing finished successfully",
+            (JobStatus::Success, true) => "Download finished successfully",
+            (JobStatus::Failed(_), false) => "Flashing failed",
+            (JobStatus::Failed(_), true) => "Download failed",
+            (JobStatus::Cancelled, false) => "Flashing cancelled by user",
+            (JobStatus::Cancelled, true) => "Download cancelled by user",
+            (JobStatus::Running, _) => unreachable!(),
+        };
+
+        if status == JobStatus::Cancelled {
+            self.cancel.abort();
+        }
+        self.status = status;
+
+        msg
+    }
+}
+
+impl BBImagerCommon {
+    /// Job with `id`. Finished jobs are removed once cleared from the jobs list, so late messages
+    /// might refer to jobs which no longer exist.
+    pub(crate) fn job(&self, id: bb_flasher::JobId) -> Option<&FlashJob> {
+        self.jobs.get(&id)
+    }
+
+    pub(crate) fn job_mut(&mut self, id: bb_flasher::JobId) -> Option<&mut FlashJob> {
+        self.jobs.get_mut(&id)
+    }
+
+    /// Warning shown while using a custom catalog, or catalogs which could not be verified.
+    pub(crate) fn catalog_warning(&self) -> Option<String> {
+        if let Some(x) = self.app_config.catalog_url() {
+            return Some(format!("Using custom catalog {x}"));
+        }
+
+        if bb_config::signature::pinned_keys().is_empty() {
+            return None;
+        }
+
+        match self.boards.unsigned_catalogs().count() {
+            0 => None,
+            1 => Some("1 catalog source is not signed".to_string()),
+            n => Some(format!("{n} catalog sources are not signed")),
+        }
+    }
+
+    /// Whether settings and customization can currently be changed.
+    pub(crate) const fn is_locked(&self) -> bool {
+        self.app_config.locked() && !self.unlocked
+    }
+
+    /// Check if a running job is using the destination.
+    pub(crate) fn destination_busy(&self, dest: &str) -> bool {
+        self.jobs
+            .values()
+            .any(|x| x.is_running() && !x.is_download && x.destination == dest)
+    }
+
+    /// Show average progress of running jobs on the taskbar. Hidden once all jobs finish.
+    pub(crate) fn update_taskbar(&mut self) -> Task<BBImagerMessage> {
+        const STEP: f32 = 0.01;
+
+        let running: Vec<f32> = self
+            .jobs
+            .values()
+            .filter(|x| x.is_running())
+            .map(FlashJob::progress_fraction)
+            .collect();
+        let progress =
+            (!running.is_empty()).then(|| running.iter().sum::<f32>() / running.len() as f32);
+
+        match (self.taskbar_progress, progress) {
+            (None, None) => return Task::none(),
+            (Some(old), Some(new)) if (new - old).abs() < STEP => return Task::none(),
+            _ => {}
+        }
+
+        self.taskbar_progress = progress;
+        crate::taskbar::set_progress(progress)
+    }
+
+    /// Remove flashing marker of job `id` once it finishes.
+    pub(crate) fn clear_flashing_marker(&self, id: bb_flasher::JobId) -> Task<BBImagerMessage> {
+        let Some(job_id) = self
+            .job(id)
+            .and_then(|x| x.marker.as_ref())
+            .map(|x| x.job_id)
+        else {
+            return Task::none();
+        };
+
+        Task::future(async move {
+            if let Err(e) = persistance::FlashingMarker::clear(job_id).await {
+                tracing::warn!("Failed to clear flashing marker: {e}");
+            }
+            BBImagerMessage::Null
+        })
+    }
+}
+
+/// Guided flashing of [`helpers::BoardImage::Bundle`], one step at a time.
+#[derive(Debug)]
+pub(crate) struct BundleState {
+    pub(crate) common: BBImagerCommon,
+    pub(crate) selected_board: usize,
+    pub(crate) selected_image: (OsImageId, helpers::BoardImage),
+    /// Index of the current step. Equal to the number of steps once all steps are done.
+    pub(crate) step: usize,
+    pub(crate) destinations: Vec<helpers::Destination>,
+    pub(crate) selected_dest: Option<helpers::Destination>,
+    /// Job flashing the current step
+    pub(crate) job: Option<bb_flasher::JobId>,
+    /// Error from the last attempt of the current step
+    pub(crate) error: Option<String>,
+}
+
+impl BundleState {
+    pub(crate) fn new(
+        common: BBImagerCommon,
+        selected_board: usize,
+        selected_image: (OsImageId, helpers::BoardImage),
+    ) -> Self {
+        Self {
+            common,
+            selected_board,
+            selected_image,
+            step: 0,
+            destinations: Vec::new(),
+            selected_dest: None,
+            job: None,
+            error: None,
+        }
+    }
+
+    pub(crate) fn selected_board(&self) -> &config::Device {
+        self.common.boards.device(self.selected_board)
+    }
+
+    pub(crate) fn steps(&self) -> &[helpers::BundleStep] {
+        match &self.selected_image.1 {
+            helpers::BoardImage::Bundle { steps, .. } => steps,
+            _ => panic!("Expected bundle"),
+        }
+    }
+
+    pub(crate) fn current_step(&self) -> Option<&helpers::BundleStep> {
+        self.steps().get(self.step)
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.step >= self.steps().len()
+    }
+
+    pub(crate) fn job(&self) -> Option<&FlashJob> {
+        self.job.and_then(|x| self.common.job(x))
+    }
+
+    pub(crate) fn is_flashing(&self) -> bool {
+        self.job().is_some_and(FlashJob::is_running)
+    }
+
+    /// Destination for the current step. Steps with a static destination do not need to be
+    /// selected by the user.
+    pub(crate) fn destination(&self) -> Option<helpers::Destination> {
+        let step = self.current_step()?;
+        helpers::static_destination(step.img.flasher()).or_else(|| self.selected_dest.clone())
+    }
+
+    /// Move to next step once the current step is flashed successfully.
+    pub(crate) fn advance(&mut self) {
+        self.step += 1;
+        self.job = None;
+        self.error = None;
+        self.selected_dest = None;
+        self.destinations.clear();
+    }
+}
+
+impl From<BundleState> for ChooseOsState {
+    fn from(value: BundleState) -> Self {
+        Self {
+            common: value.common,
+            selected_board: value.selected_board,
+            pos: Vec::new(),
+            selected_image: Some(value.selected_image),
+            checksum: None,
+        }
+    }
+}
+
+/// Locked down flashing of a single preconfigured image, for flashing stations.
+#[derive(Debug)]
+pub(crate) struct KioskState {
+    pub(crate) common: BBImagerCommon,
+    pub(crate) job: helpers::KioskJob,
+    /// Board and image of the job, once found in the catalog.
+    pub(crate) selected: Option<(usize, helpers::BoardImage)>,
+    pub(crate) destinations: Vec<helpers::Destination>,
+    pub(crate) selected_dest: Option<helpers::Destination>,
+    /// Job flashing the selected destination
+    pub(crate) flash_job: Option<bb_flasher::JobId>,
+    /// Outcome of the last flash. Shown until the next flash starts.
+    pub(crate) last_result: Option<Result<String, String>>,
+    /// Number of successful flashes in this session
+    pub(crate) flashed: usize,
+}
+
+impl KioskState {
+    pub(crate) fn new(common: BBImagerCommon, job: helpers::KioskJob) -> Self {
+        let mut res = Self {
+            common,
+            job,
+            selected: None,
+            destinations: Vec::new(),
+            selected_dest: None,
+            flash_job: None,
+            last_result: None,
+            flashed: 0,
+        };
+        res.resolve();
+        res
+    }
+
+    /// Find board and image of the job in the catalog. Called again whenever the catalog is
+    /// updated, since the image might be in a catalog which is still being fetched.
+    pub(crate) fn resolve(&mut self) {
+        if self.selected.is_some() {
+            return;
+        }
+
+        let Some((board, target)) = self
+            .common
+            .boards
+            .find(&self.job.board, self.job.image.as_deref())
+        else {
+            return;
+        };
+
+        let img = match (&self.job.image_path, target) {
+            (Some(path), _) => {
+                helpers::BoardImage::local(path.clone(), self.common.boards.device(board).flasher)
+            }
+            (None, Some(target)) => match self.common.boards.image(&target) {
+                config::OsListItem::Image(x) => helpers::BoardImage::remote(
+                    x.clone(),
+                    self.common.boards.flasher(board, &target),
+                    self.common.downloader.clone(),
+                ),
+                _ => {
+                    tracing::error!("Only single images are supported in kiosk mode");
+                    return;
+                }
+            },
+            (None, None) => return,
+        };
+
+        tracing::info!("Kiosk image: {img}");
+        self.selected = Some((board, img));
+    }
+
+    pub(crate) fn selected_board(&self) -> Option<&config::Device> {
+        self.selected
+            .as_ref()
+            .map(|(x, _)| self.common.boards.device(*x))
+    }
+
+    pub(crate) fn job(&self) -> Option<&FlashJob> {
+        self.flash_job.and_then(|x| self.common.job(x))
+    }
+
+    pub(crate) fn is_flashing(&self) -> bool {
+        self.job().is_some_and(FlashJob::is_running)
+    }
+
+    pub(crate) fn destination(&self) -> Option<helpers::Destination> {
+        let (_, img) = self.selected.as_ref()?;
+        helpers::static_destination(img.flasher()).or_else(|| self.selected_dest.clone())
+    }
+
+    /// Reset for the next destination once a flash finishes.
+    pub(crate) fn finish(&mut self, res: Result<String, String>) {
+        if res.is_ok() {
+            self.flashed += 1;
+        }
+        self.last_result = Some(res);
+        self.flash_job = None;
+        self.selected_dest = None;
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct FlashingState {
+    pub(crate) common: BBImagerCommon,
+    pub(crate) selected_board: usize,
+    pub(crate) job: bb_flasher::JobId,
+    /// Selections used to start the job. Allows retrying failed jobs.
+    pub(crate) selection: JobSelection,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct JobSelection {
+    pub(crate) selected_image: (OsImageId, helpers::BoardImage),
+    pub(crate) selected_dest: helpers::Destination,
+    pub(crate) customization: helpers::FlashingCustomization,
+}
+
+impl FlashingState {
+    pub(crate) fn selected_board(&self) -> &config::Device {
+        self.common.boards.device(self.selected_board)
+    }
+
+    pub(crate) fn job(&self) -> Option<&FlashJob> {
+        self.common.job(self.job)
+    }
 }
 
 #[derive(Debug)]
@@ -555,6 +1505,21 @@ pub(crate) struct FlashingFinishState {
     pub(crate) common: BBImagerCommon,
     pub(crate) selected_board: usize,
     pub(crate) is_download: bool,
+    pub(crate) hostname: Option<String>,
+    /// QR Code with device credentials
+    pub(crate) qr_code: Option<widget::svg::Handle>,
+    pub(crate) login: Option<(String, Option<String>)>,
+    pub(crate) discovery: Discovery,
+}
+
+/// Search for the flashed board on the local network.
+#[derive(Debug, Default)]
+pub(crate) enum Discovery {
+    #[default]
+    Idle,
+    Searching,
+    Found(Vec<std::net::IpAddr>),
+    Failed(String),
 }
 
 impl FlashingFinishState {
@@ -565,20 +1530,208 @@ impl FlashingFinishState {
 
 impl From<FlashingState> for FlashingFinishState {
     fn from(value: FlashingState) -> Self {
+        let job = value.job();
+
         Self {
+            is_download: job.is_some_and(|x| x.is_download),
+            hostname: job.and_then(|x| x.hostname.clone()),
+            qr_code: job
+                .and_then(|x| x.credentials.as_deref())
+                .and_then(|x| bb_helper::qr::QrCode::encode(x.as_bytes()).ok())
+                .map(|x| widget::svg::Handle::from_memory(x.to_svg(4).into_bytes())),
+            login: job.and_then(|x| x.login.clone()),
+            discovery: Discovery::Idle,
             common: value.common,
             selected_board: value.selected_board,
-            is_download: value.is_download,
         }
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct RecoveryState {
+    pub(crate) common: BBImagerCommon,
+    pub(crate) marker: persistance::FlashingMarker,
+    /// Other interrupted flashings, shown once this one is dismissed.
+    pub(crate) pending: Vec<persistance::FlashingMarker>,
+    /// Destination is being compared with the image.
+    pub(crate) verifying: bool,
+    /// Result of the last comparison of the destination with the image.
+    pub(crate) verified: Option<Result<(), String>>,
+}
+
+impl RecoveryState {
+    pub(crate) const fn new(
+        common: BBImagerCommon,
+        marker: persistance::FlashingMarker,
+        pending: Vec<persistance::FlashingMarker>,
+    ) -> Self {
+        Self {
+            common,
+            marker,
+            pending,
+            verifying: false,
+            verified: None,
+        }
+    }
+
+    /// Index of the board used in the interrupted flashing, if it is still present.
+    pub(crate) fn board(&self) -> Option<usize> {
+        self.common
+            .boards
+            .devices()
+            .find(|(_, x)| x.name == self.marker.board)
+            .map(|(id, _)| id)
+    }
+
+    /// Board and image used in the interrupted flashing, if they are still present.
+    pub(crate) fn selection(&self) -> Option<(usize, (OsImageId, helpers::BoardImage))> {
+        let (board, target) = self
+            .common
+            .boards
+            .find(&self.marker.board, Some(self.marker.image.as_str()))?;
+        let flasher = self.common.boards.device(board).flasher;
+
+        let img = match (&self.marker.image_path, target) {
+            (Some(path), _) if path.exists() => (
+                OsImageId::Local(Vec::new()),
+                helpers::BoardImage::local(path.clone(), flasher),
+            ),
+            (None, Some(target)) => match self.common.boards.image(&target) {
+                config::OsListItem::Image(x) => {
+                    let img = helpers::BoardImage::remote(
+                        x.clone(),
+                        self.common.boards.flasher(board, &target),
+                        self.common.downloader.clone(),
+                    );
+                    (OsImageId::Remote(target), img)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some((board, img))
+    }
+
+    /// Destination used in the interrupted flashing, if it is still connected. Only SD Cards are
+    /// recorded.
+    pub(crate) fn destination(&self) -> Option<helpers::Destination> {
+        let path = self.marker.destination_path.clone()?;
+        bb_flasher::sd::Target::try_from(path)
+            .ok()
+            .map(helpers::Destination::SdCard)
+    }
+
+    /// Destination can be compared with the image. Remote images need to be in cache.
+    pub(crate) fn can_verify(&self) -> bool {
+        self.marker.destination_path.is_some()
+            && (self.marker.image_path.is_some() || self.marker.sha256().is_some())
+    }
+
+    /// Compare the destination with the image, to check if the interrupted flashing had already
+    /// written the whole image.
+    pub(crate) fn verify(&mut self) -> Task<BBImagerMessage> {
+        let downloader = self.common.downloader.clone();
+        let img = self.marker.image_path.clone();
+        let sha256 = self.marker.sha256();
+        let dst = self.marker.destination_path.clone();
+        let bmap = match self.marker.bmap.clone() {
+            Some(persistance::MarkerBmap::Local(p)) => Some(Ok(helpers::Bmap::Local(p))),
+            Some(persistance::MarkerBmap::Remote(url)) => {
+                Some(url.parse().map(|url| helpers::Bmap::Remote {
+                    url: Box::new(url),
+                    downloader: downloader.clone(),
+                }))
+            }
+            None => None,
+        };
+
+        self.verifying = true;
+        self.verified = None;
+
+        Task::future(async move {
+            let img = match (img, sha256) {
+                (Some(x), _) => Some(x),
+                (None, Some(x)) => downloader.check_cache_from_sha(x).await,
+                (None, None) => None,
+            };
+            let Some(img) = img else {
+                return BBImagerMessage::RecoveryVerified(Err(
+                    "Image is no longer available".to_string()
+                ));
+            };
+            let Some(dst) = dst else {
+                return BBImagerMessage::RecoveryVerified(Err(
+                    "Destination can not be verified".to_string()
+                ));
+            };
+
+            let res: anyhow::Result<()> = async move {
+                let dst = bb_flasher::sd::Target::try_from(dst)?;
+                let bmap = bmap.transpose()?;
+                bb_flasher::sd::compare(bb_flasher::LocalImage::new(img.into()), bmap, dst, None)
+                    .await
+            }
+            .await
+            .map_err(|e| format!("{e}"));
+
+            BBImagerMessage::RecoveryVerified(res)
+        })
+    }
+
+    pub(crate) fn progress(&self) -> String {
+        let progress = format!("{:.0}%", self.marker.progress.clamp(0.0, 1.0) * 100.0);
+
+        match self.marker.last_offset {
+            Some(x) => format!("{progress} ({})", bb_flasher::ByteSize::new(x)),
+            None => progress,
+        }
+    }
+}
+
+pub(crate) struct OnboardingState {
+    pub(crate) common: BBImagerCommon,
+    pub(crate) step: usize,
+    pub(crate) dont_show: bool,
+}
+
+impl OnboardingState {
+    pub(crate) const fn new(common: BBImagerCommon) -> Self {
+        Self {
+            common,
+            step: 0,
+            dont_show: false,
+        }
+    }
+
+    pub(crate) fn is_last_step(&self) -> bool {
+        self.step + 1 == constants::ONBOARDING_STEPS.len()
+    }
+}
+
 pub(crate) struct FlashingFailState {
     pub(crate) common: BBImagerCommon,
-    pub(crate) err: String,
+    pub(crate) selected_board: usize,
+    pub(crate) selection: JobSelection,
+    pub(crate) failure: failure::Failure,
+    /// Report copied to clipboard by the user.
+    pub(crate) diagnostics: String,
     pub(crate) logs: widget::text_editor::Content,
 }
 
+impl From<FlashingFailState> for CustomizeState {
+    fn from(value: FlashingFailState) -> Self {
+        Self {
+            common: value.common,
+            selected_board: value.selected_board,
+            selected_image: value.selection.selected_image,
+            selected_dest: value.selection.selected_dest,
+            customization: value.selection.customization,
+            image_info: None,
+        }
+    }
+}
+
 // State for Pages that can be opened from any of the normal pages but are not part of normal flow.
 // Eg: Application info
 pub(crate) enum OverlayData {
@@ -591,6 +1744,7 @@ pub(crate) enum OverlayData {
     FlashingCancel(FlashingFinishState),
     FlashingFail(FlashingFailState),
     FlashingSuccess(FlashingFinishState),
+    Bundle(BundleState),
 }
 
 impl OverlayData {
@@ -605,6 +1759,7 @@ impl OverlayData {
             Self::FlashingCancel(x) => &mut x.common,
             Self::FlashingFail(x) => &mut x.common,
             Self::FlashingSuccess(x) => &mut x.common,
+            Self::Bundle(x) => &mut x.common,
         }
     }
 
@@ -619,6 +1774,7 @@ impl OverlayData {
             Self::FlashingCancel(x) => &x.common,
             Self::FlashingFail(x) => &x.common,
             Self::FlashingSuccess(x) => &x.common,
+            Self::Bundle(x) => &x.common,
         }
     }
 }
@@ -637,7 +1793,16 @@ impl TryFrom<BBImager> for OverlayData {
             BBImager::FlashingCancel(x) => Ok(Self::FlashingCancel(x)),
             BBImager::FlashingFail(x) => Ok(Self::FlashingFail(x)),
             BBImager::FlashingSuccess(x) => Ok(Self::FlashingSuccess(x)),
-            BBImager::Dummy | BBImager::AppInfo(_) => Err(()),
+            BBImager::Bundle(x) => Ok(Self::Bundle(x)),
+            BBImager::Dummy
+            | BBImager::AppInfo(_)
+            | BBImager::Settings(_)
+            | BBImager::Jobs(_)
+            | BBImager::History(_)
+            | BBImager::UsbNetwork(_)
+            | BBImager::Onboarding(_)
+            | BBImager::Recovery(_)
+            | BBImager::Kiosk(_) => Err(()),
         }
     }
 }
@@ -654,6 +1819,7 @@ impl From<OverlayData> for BBImager {
             OverlayData::FlashingCancel(x) => Self::FlashingCancel(x),
             OverlayData::FlashingFail(x) => Self::FlashingFail(x),
             OverlayData::FlashingSuccess(x) => Self::FlashingSuccess(x),
+            OverlayData::Bundle(x) => Self::Bundle(x),
         }
     }
 }
@@ -663,23 +1829,43 @@ pub(crate) struct OverlayState {
     pub(crate) log_path: String,
     pub(crate) license: widget::text_editor::Content,
     pub(crate) cache_dir: String,
+    pub(crate) catalog: String,
 }
 
 impl OverlayState {
     pub(crate) fn new(page: OverlayData) -> Self {
         let log_path = helpers::log_file_path().to_string_lossy().to_string();
         let license = widget::text_editor::Content::with_text(constants::APP_LINCESE);
-        let cache_dir = helpers::project_dirs()
-            .unwrap()
+        let cache_dir = page
+            .common()
+            .downloader
             .cache_dir()
             .to_string_lossy()
             .to_string();
+        let boards = &page.common().boards;
+        let signature = if bb_config::signature::pinned_keys().is_empty() {
+            "signature verification not available".to_string()
+        } else {
+            match boards.unsigned_catalogs().count() {
+                0 => "signed".to_string(),
+                n => format!("{n} unsigned sources"),
+            }
+        };
+        let catalog = format!(
+            "{} boards, updated {}, {signature}",
+            boards.devices().count(),
+            boards
+                .catalog_date()
+                .map(|x| x.to_string())
+                .unwrap_or_else(|| "N/A".to_string())
+        );
 
         Self {
             page,
             log_path,
             license,
             cache_dir,
+            catalog,
         }
     }
 
@@ -691,3 +1877,150 @@ impl OverlayState {
         self.page.common_mut()
     }
 }
+
+pub(crate) struct JobsState {
+    pub(crate) page: OverlayData,
+}
+
+pub(crate) struct HistoryState {
+    pub(crate) page: OverlayData,
+    /// Recorded flashes, oldest first.
+    pub(crate) entries: Vec<bb_helper::history::HistoryEntry>,
+}
+
+impl HistoryState {
+    pub(crate) fn new(page: OverlayData) -> Self {
+        let entries = persistance::flash_history()
+            .and_then(|x| x.entries().ok())
+            .unwrap_or_default();
+
+        Self { page, entries }
+    }
+}
+
+pub(crate) struct UsbNetworkState {
+    pub(crate) page: OverlayData,
+    /// Connected USB network gadget and services on the board. [`None`] until the board is
+    /// connected.
+    pub(crate) status: Option<(
+        bb_helper::usb_network::Link,
+        bb_helper::usb_network::Services,
+    )>,
+}
+
+impl UsbNetworkState {
+    /// User configured while flashing, for the SSH command.
+    pub(crate) fn user(&self) -> Option<&str> {
+        match &self.page {
+            OverlayData::FlashingSuccess(x) => x.login.as_ref()?.1.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) struct SettingsState {
+    pub(crate) page: OverlayData,
+    pub(crate) cache_dir: String,
+    pub(crate) proxy: String,
+    pub(crate) ca_certs: String,
+    pub(crate) insecure_tls: bool,
+    pub(crate) catalog_url: String,
+    /// Problems found in the last imported catalog file
+    pub(crate) catalog_diagnostics: Vec<bb_config::validate::Diagnostic>,
+    pub(crate) download_limit: String,
+    pub(crate) write_limit: String,
+    pub(crate) scratch_dir: String,
+    /// Admin PIN entered to unlock settings
+    pub(crate) pin: String,
+    pub(crate) pin_error: bool,
+}
+
+impl SettingsState {
+    pub(crate) fn new(page: OverlayData) -> Self {
+        let common = page.common();
+        let cache_dir = common.downloader.cache_dir().to_string_lossy().to_string();
+        let proxy = common.app_config.proxy().unwrap_or_default().to_string();
+        let ca_certs = common
+            .app_config
+            .ca_certs()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let insecure_tls = common.app_config.insecure_tls();
+        let catalog_url = common
+            .app_config
+            .catalog_url()
+            .map(|x| x.to_string())
+            .unwrap_or_default();
+        let download_limit = common
+            .app_config
+            .download_limit()
+            .map(|x| x.to_string())
+            .unwrap_or_default();
+        let write_limit = common
+            .app_config
+            .write_limit()
+            .map(|x| x.to_string())
+            .unwrap_or_default();
+        let scratch_dir = Self::scratch_dir_label(&common.app_config);
+
+        Self {
+            page,
+            cache_dir,
+            proxy,
+            ca_certs,
+            insecure_tls,
+            catalog_url,
+            catalog_diagnostics: Vec::new(),
+            download_limit,
+            write_limit,
+            scratch_dir,
+            pin: String::new(),
+            pin_error: false,
+        }
+    }
+
+    pub(crate) fn scratch_dir_label(config: &persistance::GuiConfiguration) -> String {
+        config
+            .scratch_dir()
+            .map(std::path::Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    pub(crate) fn common(&self) -> &BBImagerCommon {
+        self.page.common()
+    }
+
+    pub(crate) fn common_mut(&mut self) -> &mut BBImagerCommon {
+        self.page.common_mut()
+    }
+
+    pub(crate) fn app_config(&self) -> &persistance::GuiConfiguration {
+        &self.common().app_config
+    }
+
+    pub(crate) fn save_app_config(&self) -> Task<BBImagerMessage> {
+        save_app_config(self.app_config().clone())
+    }
+}
+
+fn save_app_config(config: persistance::GuiConfiguration) -> Task<BBImagerMessage> {
+    Task::future(async move {
+        match config.save().await {
+            Ok(()) => BBImagerMessage::Null,
+            Err(e) => {
+                tracing::error!("Failed to save config: {e}");
+                BBImagerMessage::Toast(format!("Failed to save settings: {e}"))
+            }
+        }
+    })
+}
+
+/// Dismiss a message after it has been visible for some time.
+fn toast_timer(id: u64) -> Task<BBImagerMessage> {
+    Task::future(async move {
+        tokio::time::sleep(constants::TOAST_DURATION).await;
+        BBImagerMessage::DismissToast(id)
+    })
+}
@@ -0,0 +1,97 @@
+//! Show progress of running jobs on the taskbar (Windows) or launcher (Linux) icon, so that it
+//! can be followed while the window is minimized.
+
+use iced::Task;
+
+use crate::message::BBImagerMessage;
+
+/// Set progress shown on the taskbar icon. [`None`] hides the progress.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_progress(progress: Option<f32>) -> Task<BBImagerMessage> {
+    Task::future(async move {
+        if let Err(e) = launcher_entry_update(progress).await {
+            tracing::debug!("Failed to update launcher progress: {e}");
+        }
+        BBImagerMessage::Null
+    })
+}
+
+/// Set progress shown on the taskbar icon. [`None`] hides the progress.
+#[cfg(windows)]
+pub(crate) fn set_progress(progress: Option<f32>) -> Task<BBImagerMessage> {
+    iced::window::latest()
+        .and_then(move |id| {
+            iced::window::run(id, move |window| {
+                if let Err(e) = taskbar_list_update(window, progress) {
+                    tracing::debug!("Failed to update taskbar progress: {e}");
+                }
+            })
+        })
+        .map(|_| BBImagerMessage::Null)
+}
+
+/// Set progress shown on the taskbar icon. [`None`] hides the progress.
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) fn set_progress(_: Option<f32>) -> Task<BBImagerMessage> {
+    Task::none()
+}
+
+/// Unity LauncherEntry API. Supported by Ubuntu Dock, Dash to Dock and KDE Plasma task manager.
+#[cfg(target_os = "linux")]
+async fn launcher_entry_update(progress: Option<f32>) -> zbus::Result<()> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    static CONNECTION: tokio::sync::OnceCell<zbus::Connection> = tokio::sync::OnceCell::const_new();
+
+    let conn = CONNECTION
+        .get_or_try_init(zbus::Connection::session)
+        .await?;
+
+    let props = HashMap::from([
+        ("progress", Value::from(f64::from(progress.unwrap_or(0.0)))),
+        ("progress-visible", Value::from(progress.is_some())),
+    ]);
+
+    conn.emit_signal(
+        None::<zbus::names::BusName<'_>>,
+        "/org/beagleboard/imagingutility",
+        "com.canonical.Unity.LauncherEntry",
+        "Update",
+        &(crate::constants::DESKTOP_ENTRY_URI, props),
+    )
+    .await
+}
+
+#[cfg(windows)]
+fn taskbar_list_update(
+    window: &dyn iced::window::Window,
+    progress: Option<f32>,
+) -> windows::core::Result<()> {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use windows::Win32::{
+        Foundation::HWND,
+        System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance},
+        UI::Shell::{ITaskbarList3, TBPF_NOPROGRESS, TBPF_NORMAL, TaskbarList},
+    };
+
+    const STEPS: u64 = 1000;
+
+    let Ok(RawWindowHandle::Win32(handle)) = window.window_handle().map(|x| x.as_raw()) else {
+        return Ok(());
+    };
+    let hwnd = HWND(handle.hwnd.get() as *mut _);
+
+    unsafe {
+        let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)?;
+        taskbar.HrInit()?;
+
+        match progress {
+            Some(x) => {
+                taskbar.SetProgressState(hwnd, TBPF_NORMAL)?;
+                taskbar.SetProgressValue(hwnd, (x.clamp(0.0, 1.0) * STEPS as f32) as u64, STEPS)
+            }
+            None => taskbar.SetProgressState(hwnd, TBPF_NOPROGRESS),
+        }
+    }
+}
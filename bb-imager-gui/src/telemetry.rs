@@ -0,0 +1,152 @@
+//! Export traces and metrics to an OpenTelemetry collector, so that fleets of flashing stations
+//! can be monitored centrally.
+//!
+//! Requires the `otlp` feature and is only enabled when `OTEL_EXPORTER_OTLP_ENDPOINT` is set. The
+//! exporter can be further configured using the standard `OTEL_*` environment variables.
+
+use std::time::Duration;
+
+use tracing_subscriber::{Layer, Registry};
+
+/// Flushes pending traces and metrics on drop.
+pub(crate) struct Guard {
+    #[cfg(feature = "otlp")]
+    providers: Option<(
+        opentelemetry_sdk::trace::SdkTracerProvider,
+        opentelemetry_sdk::metrics::SdkMeterProvider,
+    )>,
+}
+
+/// Setup exporters. The returned layer should be added to the tracing subscriber, and the guard
+/// kept alive till the application exits.
+#[cfg(feature = "otlp")]
+pub(crate) fn init() -> (Guard, Option<impl Layer<Registry>>) {
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+        return (Guard { providers: None }, None);
+    }
+
+    match otlp::providers() {
+        Ok((tracer, meter)) => {
+            let layer = otlp::layer(&tracer);
+            let guard = Guard {
+                providers: Some((tracer, meter)),
+            };
+            (guard, Some(layer))
+        }
+        Err(e) => {
+            // Subscriber is not registered yet
+            eprintln!("Failed to setup OpenTelemetry exporter: {e}");
+            (Guard { providers: None }, None)
+        }
+    }
+}
+
+/// Setup exporters. The returned layer should be added to the tracing subscriber, and the guard
+/// kept alive till the application exits.
+#[cfg(not(feature = "otlp"))]
+pub(crate) fn init() -> (Guard, Option<impl Layer<Registry>>) {
+    (Guard {}, None::<tracing_subscriber::layer::Identity>)
+}
+
+/// Record a finished flashing job.
+#[cfg(feature = "otlp")]
+pub(crate) fn record_flash(board: &str, success: bool, duration: Duration) {
+    let attrs = [
+        opentelemetry::KeyValue::new("board", board.to_string()),
+        opentelemetry::KeyValue::new("result", if success { "success" } else { "failure" }),
+    ];
+
+    otlp::METRICS.jobs.add(1, &attrs);
+    otlp::METRICS
+        .duration
+        .record(duration.as_secs_f64(), &attrs);
+}
+
+/// Record a finished flashing job.
+#[cfg(not(feature = "otlp"))]
+pub(crate) fn record_flash(_: &str, _: bool, _: Duration) {}
+
+#[cfg(feature = "otlp")]
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Some((tracer, meter)) = self.providers.take() {
+            if let Err(e) = tracer.shutdown() {
+                tracing::warn!("Failed to flush traces: {e}");
+            }
+            if let Err(e) = meter.shutdown() {
+                tracing::warn!("Failed to flush metrics: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use std::sync::LazyLock;
+
+    use opentelemetry::{
+        KeyValue, global,
+        metrics::{Counter, Histogram},
+        trace::TracerProvider,
+    };
+    use opentelemetry_sdk::{Resource, metrics::SdkMeterProvider, trace::SdkTracerProvider};
+    use tracing_subscriber::{Layer, Registry};
+
+    const SCOPE: &str = "bb-imager-gui";
+
+    pub(super) struct Metrics {
+        pub(super) jobs: Counter<u64>,
+        pub(super) duration: Histogram<f64>,
+    }
+
+    pub(super) static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+        let meter = global::meter(SCOPE);
+        Metrics {
+            jobs: meter
+                .u64_counter("bb_imager.flash.jobs")
+                .with_description("Number of finished flashing jobs")
+                .build(),
+            duration: meter
+                .f64_histogram("bb_imager.flash.duration")
+                .with_description("Time taken by flashing jobs")
+                .with_unit("s")
+                .build(),
+        }
+    });
+
+    pub(super) fn providers()
+    -> Result<(SdkTracerProvider, SdkMeterProvider), opentelemetry_otlp::ExporterBuildError> {
+        let resource = Resource::builder()
+            .with_service_name(SCOPE)
+            .with_attributes([
+                KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+                KeyValue::new("host.name", whoami::hostname().unwrap_or_default()),
+            ])
+            .build();
+
+        let tracer = SdkTracerProvider::builder()
+            .with_resource(resource.clone())
+            .with_batch_exporter(
+                opentelemetry_otlp::SpanExporter::builder()
+                    .with_http()
+                    .build()?,
+            )
+            .build();
+
+        let meter = SdkMeterProvider::builder()
+            .with_resource(resource)
+            .with_periodic_exporter(
+                opentelemetry_otlp::MetricExporter::builder()
+                    .with_http()
+                    .build()?,
+            )
+            .build();
+        global::set_meter_provider(meter.clone());
+
+        Ok((tracer, meter))
+    }
+
+    pub(super) fn layer(tracer: &SdkTracerProvider) -> impl Layer<Registry> {
+        tracing_opentelemetry::layer().with_tracer(tracer.tracer(SCOPE))
+    }
+}
@@ -11,9 +11,14 @@ const INP_BOX_WIDTH: u32 = 420;
 pub(crate) fn view<'a>(state: &'a OverlayState) -> Element<'a, BBImagerMessage> {
     page_type3(
         review_view(state),
-        [widget::button("BACK")
-            .on_press(BBImagerMessage::Back)
-            .style(widget::button::secondary)],
+        [
+            widget::button("FLASH HISTORY")
+                .on_press(BBImagerMessage::History)
+                .style(widget::button::secondary),
+            widget::button("BACK")
+                .on_press(BBImagerMessage::Back)
+                .style(widget::button::secondary),
+        ],
     )
 }
 
@@ -24,6 +29,14 @@ fn review_view<'a>(state: &'a OverlayState) -> Element<'a, BBImagerMessage> {
         crate::constants::APP_RELEASE,
         crate::constants::APP_DESC,
         widget::rule::horizontal(2),
+        widget::text(crate::constants::BEAGLE_BOARD_ABOUT).align_x(iced::Center),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "Git Commit",
+            widget::text(crate::constants::APP_COMMIT).into()
+        ),
+        element_with_label("Catalog", widget::text(&state.catalog).into()),
+        widget::rule::horizontal(2),
         element_with_label(
             "Cache Directory",
             widget::text_input(&state.cache_dir, &state.cache_dir)
@@ -40,7 +53,10 @@ fn review_view<'a>(state: &'a OverlayState) -> Element<'a, BBImagerMessage> {
                 .into()
         ),
         widget::rule::horizontal(2),
-        widget::container(selectable_text(&state.license)).padding(iced::Padding::ZERO.right(16))
+        widget::container(selectable_text(&state.license)).padding(iced::Padding::ZERO.right(16)),
+        widget::rule::horizontal(2),
+        widget::text("Third-Party Software").font(crate::constants::FONT_BOLD),
+        widget::text(crate::constants::THIRD_PARTY_ATTRIBUTIONS).size(12),
     ]
     .spacing(8)
     .padding(VIEW_COL_PADDING)
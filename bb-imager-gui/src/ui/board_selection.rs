@@ -20,8 +20,30 @@ pub(crate) fn view<'a>(state: &'a ChooseBoardState) -> Element<'a, BBImagerMessa
         &state.common,
         board_list_pane(state),
         board_view_pane(state),
-        [widget::button("NEXT")
-            .on_press_maybe(state.selected_board.map(|_| BBImagerMessage::Next))],
+        (!state.common.jobs.is_empty())
+            .then(|| {
+                widget::button(text(format!("JOBS ({})", state.common.jobs.len())))
+                    .on_press(BBImagerMessage::Jobs)
+                    .style(widget::button::secondary)
+            })
+            .into_iter()
+            .chain(state.selected_board().map(|x| {
+                let pinned = state.is_board_pinned(&x.name);
+                helpers::pin_btn(
+                    state.star_svg(pinned).clone(),
+                    BBImagerMessage::TogglePinBoard(x.name.clone()),
+                )
+            }))
+            .chain([helpers::settings_btn(
+                state.common.settings_svg_handle.clone(),
+            )])
+            .chain(state.recommended().map(|(board, name)| {
+                widget::button(text(format!("RECOMMENDED: {name}")))
+                    .on_press(BBImagerMessage::SelectRecommended(board))
+                    .style(widget::button::success)
+            }))
+            .chain([widget::button("NEXT")
+                .on_press_maybe(state.selected_board.map(|_| BBImagerMessage::Next))]),
     )
 }
 
@@ -43,9 +65,20 @@ fn board_list_pane<'a>(state: &'a ChooseBoardState) -> Element<'a, BBImagerMessa
                     .style(svg_icon_style)
                     .into(),
             };
+            let row = row![img, text(&dev.name).size(18).width(iced::Length::Fill)];
+            let row = if state.is_board_pinned(&dev.name) {
+                row.push(
+                    widget::svg(state.star_svg(true).clone())
+                        .height(20)
+                        .width(iced::Shrink)
+                        .style(svg_icon_style),
+                )
+            } else {
+                row
+            };
+
             button(
-                row![img, text(&dev.name).size(18).width(iced::Length::Fill)]
-                    .spacing(12)
+                row.spacing(12)
                     .padding(8)
                     .align_y(iced::alignment::Vertical::Center),
             )
@@ -54,20 +87,94 @@ fn board_list_pane<'a>(state: &'a ChooseBoardState) -> Element<'a, BBImagerMessa
         })
         .map(Into::into);
 
-    widget::scrollable(column(items).padding(LIST_COL_PADDING))
-        .id(state.common.scroll_id.clone())
+    let results = state.search_results();
+    let result_items = results.into_iter().map(|x| {
+        let img: Element<BBImagerMessage> = match state.image_handle_cache().get(x.item.icon()) {
+            Some(handle) => handle.view(ICON_WIDTH, iced::Shrink),
+            _ => widget::svg(state.downloading_svg().clone())
+                .width(ICON_WIDTH)
+                .style(svg_icon_style)
+                .into(),
+        };
+        let board = &state.board(x.board).name;
+
+        button(
+            row![
+                img,
+                column![
+                    text(x.item.name()).size(18),
+                    text(board).size(14).style(widget::text::secondary)
+                ]
+                .spacing(4)
+                .width(iced::Length::Fill)
+            ]
+            .spacing(12)
+            .padding(8)
+            .align_y(iced::alignment::Vertical::Center),
+        )
+        .on_press(BBImagerMessage::SelectSearchResult {
+            board: x.board,
+            target: x.target,
+        })
+        .style(move |theme, status| card_btn_style(theme, status, false))
         .into()
+    });
+
+    let search = widget::text_input("Search boards and images", &state.search)
+        .on_input(BBImagerMessage::BoardSearch)
+        .padding(8);
+
+    column![
+        widget::container(search).padding(LIST_COL_PADDING),
+        widget::scrollable(column(items.chain(result_items)).padding(LIST_COL_PADDING))
+            .id(state.common.scroll_id.clone())
+    ]
+    .into()
 }
 
 fn board_view_pane<'a>(state: &'a ChooseBoardState) -> Element<'a, BBImagerMessage> {
     match state.selected_board() {
         Some(dev) => helpers::board_view_pane(dev, &state.common),
-        None => widget::center(
-            text("Please Select a Board")
-                .font(constants::FONT_BOLD)
-                .size(28),
-        )
-        .padding(VIEW_COL_PADDING)
-        .into(),
+        None => {
+            let mut col = column![
+                text("Please Select a Board")
+                    .font(constants::FONT_BOLD)
+                    .size(28)
+            ]
+            .align_x(iced::Alignment::Center)
+            .spacing(8);
+
+            if let Some(x) = &state.common.launch_image {
+                col = col.push(text(format!(
+                    "to flash {}",
+                    x.file_name().unwrap_or_default().to_string_lossy()
+                )));
+            }
+
+            if let Some(x) = state.common.catalog_warning() {
+                col = col.push(text(x).style(widget::text::danger));
+            }
+
+            if let Some(x) = state.common.catalog_failures.values().next() {
+                col = col.push(
+                    row![
+                        text(format!(
+                            "Catalog offline, showing bundled boards: {}",
+                            x.error
+                        ))
+                        .style(widget::text::danger),
+                        button("RETRY")
+                            .on_press_maybe(
+                                (!x.fetching).then_some(BBImagerMessage::RefreshCatalogs)
+                            )
+                            .style(widget::button::secondary),
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Center),
+                );
+            }
+
+            widget::center(col).padding(VIEW_COL_PADDING).into()
+        }
     }
 }
@@ -0,0 +1,132 @@
+use iced::{
+    Element,
+    widget::{self, button, text},
+};
+
+use crate::{
+    BBImagerMessage, constants,
+    state::BundleState,
+    ui::helpers::{VIEW_COL_PADDING, detail_entry, page_type1},
+};
+
+pub(crate) fn view(state: &BundleState) -> Element<'_, BBImagerMessage> {
+    let btns = if state.is_finished() {
+        vec![button("FINISH").on_press(BBImagerMessage::Restart)]
+    } else if let Some(job) = state.job.filter(|_| state.is_flashing()) {
+        vec![
+            button("Cancel")
+                .style(widget::button::danger)
+                .on_press(BBImagerMessage::FlashCancel(job)),
+        ]
+    } else {
+        let label = if state.error.is_some() {
+            "RETRY"
+        } else {
+            "FLASH"
+        };
+
+        vec![
+            button("BACK")
+                .on_press(BBImagerMessage::Back)
+                .style(widget::button::secondary),
+            button(label).on_press_maybe(
+                state
+                    .destination()
+                    .map(|_| BBImagerMessage::BundleFlashStep),
+            ),
+        ]
+    };
+
+    page_type1(&state.common, steps_pane(state), step_pane(state), btns)
+}
+
+fn steps_pane(state: &BundleState) -> Element<'_, BBImagerMessage> {
+    let steps = state.steps().iter().enumerate().map(|(i, x)| {
+        let status = if i < state.step {
+            "Done"
+        } else if i > state.step {
+            "Pending"
+        } else if state.is_flashing() {
+            "Flashing"
+        } else if state.error.is_some() {
+            "Failed"
+        } else {
+            "Next"
+        };
+
+        let label = text(format!("{}. {}", i + 1, x.img)).width(iced::Length::Fill);
+        let label = if i == state.step {
+            label.font(constants::FONT_BOLD)
+        } else {
+            label
+        };
+
+        widget::column![
+            label,
+            text(format!("{} - {status}", x.img.flasher())).size(14)
+        ]
+        .spacing(4)
+        .into()
+    });
+
+    let col = widget::column![
+        text(state.selected_image.1.to_string())
+            .size(24)
+            .font(constants::FONT_BOLD),
+        widget::rule::horizontal(2),
+    ]
+    .extend(steps);
+
+    widget::scrollable(col.spacing(16).padding(VIEW_COL_PADDING))
+        .id(state.common.scroll_id.clone())
+        .into()
+}
+
+fn step_pane(state: &BundleState) -> Element<'_, BBImagerMessage> {
+    let Some(step) = state.current_step() else {
+        return widget::center(
+            text("All steps flashed successfully")
+                .size(20)
+                .align_x(iced::Center),
+        )
+        .into();
+    };
+
+    if let Some(job) = state.job().filter(|x| x.is_running()) {
+        return crate::ui::flash::job_progress_view(job);
+    }
+
+    let mut col = widget::column![
+        text(format!(
+            "Step {} of {}",
+            state.step + 1,
+            state.steps().len()
+        ))
+        .size(20)
+        .font(constants::FONT_BOLD),
+        detail_entry("Image", step.img.to_string()),
+    ];
+
+    if let Some(x) = &step.instructions {
+        col = col.push(text(x));
+    }
+
+    col = match crate::helpers::static_destination(step.img.flasher()) {
+        Some(x) => col.push(detail_entry("Destination", x.to_string())),
+        None => col.push(
+            widget::pick_list(
+                state.destinations.as_slice(),
+                state.selected_dest.as_ref(),
+                BBImagerMessage::SelectDest,
+            )
+            .placeholder("Select Destination")
+            .width(iced::Length::Fill),
+        ),
+    };
+
+    if let Some(e) = &state.error {
+        col = col.push(text(e).style(widget::text::danger));
+    }
+
+    widget::scrollable(col.spacing(16).padding(VIEW_COL_PADDING)).into()
+}
@@ -6,13 +6,39 @@ use iced::{
 use crate::{
     BBImagerMessage,
     helpers::{self, FlashingCustomization},
-    persistance,
+    locale, persistance,
     ui::helpers::{VIEW_COL_PADDING, element_with_element, element_with_label, page_type2},
 };
 
 const INPUT_WIDTH: u32 = 200;
 
+/// Commonly tweaked kernel parameters, offered as starting points in the command line editor.
+const CMDLINE_PRESETS: &[(&str, &str)] = &[
+    ("Serial Console", "console=ttyS0,115200n8"),
+    ("Isolate CPU", "isolcpus=1"),
+    ("Video Mode", "video=HDMI-A-1:1920x1080@60"),
+    ("Quiet Boot", "quiet"),
+];
+
 pub(crate) fn view<'a>(state: &'a crate::state::CustomizeState) -> Element<'a, BBImagerMessage> {
+    if state.common.is_locked() {
+        return page_type2(
+            &state.common,
+            widget::center(
+                text("Customization is locked by the administrator. Saved settings will be used.")
+                    .align_x(iced::Center),
+            )
+            .padding(VIEW_COL_PADDING)
+            .into(),
+            [
+                widget::button("BACK")
+                    .on_press(BBImagerMessage::Back)
+                    .style(widget::button::secondary),
+                widget::button("NEXT").on_press(BBImagerMessage::Next),
+            ],
+        );
+    }
+
     page_type2(
         &state.common,
         customization_pane(state),
@@ -173,36 +199,33 @@ fn linux_sd_card<'a>(
     let toggle = widget::toggler(config.timezone.is_some())
         .label("Set Timezone")
         .on_toggle(|t| {
-            let tz = if t { helpers::system_timezone() } else { None };
+            let tz = t.then(|| helpers::system_timezone().to_string());
             BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
-                config.clone().update_timezone(tz.cloned()),
+                config.clone().update_timezone(tz),
             ))
         });
-    col = match config.timezone.as_ref() {
-        Some(tz) => {
-            let xc = config.clone();
-            col.push(element_with_element(
-                toggle.into(),
-                widget::combo_box(
-                    state.timezones(),
-                    "Timezone",
-                    Some(&tz.to_owned()),
-                    move |t| {
-                        BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
-                            xc.clone().update_timezone(Some(t)),
-                        ))
-                    },
-                )
-                .width(INPUT_WIDTH)
-                .into(),
-            ))
-        }
+    col = match config.timezone.as_deref() {
+        Some(tz) => col.extend([
+            element_with_element(toggle.into(), text(tz).into()).into(),
+            picker(
+                locale::timezones(),
+                &state.common.timezone_search,
+                tz,
+                BBImagerMessage::TimezoneSearch,
+                |t| {
+                    BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_timezone(Some(t.to_string())),
+                    ))
+                },
+            ),
+        ]),
         None => col.push(toggle),
     };
 
     col = col.push(widget::rule::horizontal(2));
 
     // Hostname
+    let invalid = !config.validate_hostname();
     let toggle = widget::toggler(config.hostname.is_some())
         .label("Set Hostname")
         .on_toggle(|t| {
@@ -214,17 +237,31 @@ fn linux_sd_card<'a>(
     col = match config.hostname.as_ref() {
         Some(hostname) => col.push(element_with_element(
             toggle.into(),
-            widget::text_input("beagle", hostname)
+            widget::text_input("beagle-{rand4}", hostname)
                 .on_input(|inp| {
                     BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
                         config.clone().update_hostname(Some(inp)),
                     ))
                 })
+                .style(move |theme, status| {
+                    let mut t = widget::text_input::default(theme, status);
+                    if invalid {
+                        t.border = t.border.color(theme.palette().danger);
+                    }
+                    t
+                })
                 .width(INPUT_WIDTH)
                 .into(),
         )),
         None => col.push(toggle),
     };
+    if config.hostname.is_some() {
+        col = col.push(
+            text("Use {rand4} or {serial4} for unique hostnames when flashing many SD Cards")
+                .size(12)
+                .style(widget::text::secondary),
+        );
+    }
 
     col = col.push(widget::rule::horizontal(2));
 
@@ -241,26 +278,21 @@ fn linux_sd_card<'a>(
                 config.clone().update_keymap(keymap),
             ))
         });
-    col = match config.keymap.as_ref() {
-        Some(keymap) => {
-            let xc = config.clone();
-
-            col.push(element_with_element(
-                toggle.into(),
-                widget::combo_box(
-                    state.keymaps(),
-                    "Keymap",
-                    Some(&keymap.to_owned()),
-                    move |t| {
-                        BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
-                            xc.clone().update_keymap(Some(t)),
-                        ))
-                    },
-                )
-                .width(INPUT_WIDTH)
-                .into(),
-            ))
-        }
+    col = match config.keymap.as_deref() {
+        Some(keymap) => col.extend([
+            element_with_element(toggle.into(), text(keymap).into()).into(),
+            picker(
+                locale::keymaps(),
+                &state.common.keymap_search,
+                keymap,
+                BBImagerMessage::KeymapSearch,
+                |t| {
+                    BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_keymap(Some(t.to_string())),
+                    ))
+                },
+            ),
+        ]),
         None => col.push(toggle),
     };
 
@@ -297,11 +329,335 @@ fn linux_sd_card<'a>(
             }),
     );
 
+    col = col.push(widget::rule::horizontal(2));
+
+    // Volume Labels
+    col = col.push(
+        widget::toggler(config.volume_label.is_some())
+            .label("Set Volume Labels")
+            .on_toggle(|t| {
+                let (volume, rootfs) = if t {
+                    (Some("BEAGLE".to_string()), config.rootfs_label.clone())
+                } else {
+                    (None, None)
+                };
+                BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                    config
+                        .clone()
+                        .update_volume_label(volume)
+                        .update_rootfs_label(rootfs),
+                ))
+            }),
+    );
+    if let Some(volume) = config.volume_label.as_ref() {
+        col = col.extend([
+            input_with_label(
+                "Boot Partition",
+                "BEAGLE",
+                volume,
+                |inp| {
+                    FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_volume_label(Some(inp)),
+                    )
+                },
+                !config.validate_volume_label(),
+            )
+            .into(),
+            input_with_label(
+                "Root Partition (optional)",
+                "rootfs",
+                config.rootfs_label.as_deref().unwrap_or(""),
+                |inp| {
+                    FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_rootfs_label(if inp.is_empty() {
+                            None
+                        } else {
+                            Some(inp)
+                        }),
+                    )
+                },
+                !config.validate_rootfs_label(),
+            )
+            .into(),
+        ]);
+    }
+
+    col = col.push(widget::rule::horizontal(2));
+
+    // Serial Console
+    col = col.push(
+        widget::toggler(config.serial_console.is_some())
+            .label("Configure Serial Console")
+            .on_toggle(|t| {
+                BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                    config.clone().update_serial_console(t.then_some(true)),
+                ))
+            }),
+    );
+    if let Some(enabled) = config.serial_console {
+        col = col.push(element_with_label(
+            "Serial Console (115200 baud)",
+            widget::toggler(enabled)
+                .on_toggle(|t| {
+                    BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_serial_console(Some(t)),
+                    ))
+                })
+                .into(),
+        ));
+    }
+
+    col = col.push(widget::rule::horizontal(2));
+
+    // Kernel command line
+    col = col.push(
+        widget::toggler(config.cmdline_append.is_some())
+            .label("Append to Kernel Command Line")
+            .on_toggle(|t| {
+                BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                    config.clone().update_cmdline_append(t.then(String::new)),
+                ))
+            }),
+    );
+    if let Some(args) = config.cmdline_append.as_ref() {
+        let invalid = !config.validate_cmdline_append();
+        let presets = CMDLINE_PRESETS.iter().map(|(label, param)| {
+            let updated = if args.trim().is_empty() {
+                param.to_string()
+            } else {
+                format!("{} {param}", args.trim_end())
+            };
+            widget::button(text(*label))
+                .style(widget::button::secondary)
+                .on_press(BBImagerMessage::UpdateFlashConfig(
+                    FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_cmdline_append(Some(updated)),
+                    ),
+                ))
+                .into()
+        });
+
+        col = col.extend([
+            widget::text_input("console=ttyS0,115200n8", args)
+                .on_input(|x| {
+                    BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_cmdline_append(Some(x)),
+                    ))
+                })
+                .style(move |theme, status| {
+                    let mut t = widget::text_input::default(theme, status);
+                    if invalid {
+                        t.border = t.border.color(theme.palette().danger);
+                    }
+                    t
+                })
+                .into(),
+            widget::row(presets).spacing(8).wrap().into(),
+        ]);
+        if invalid && let Err(e) = bb_flasher::sd::validate_cmdline(args) {
+            col = col.push(text(e.to_string()).style(widget::text::danger));
+        }
+    }
+
+    col = col.push(widget::rule::horizontal(2));
+
+    // Proxy and apt mirror
+    let network =
+        config.http_proxy.is_some() || config.https_proxy.is_some() || config.apt_mirror.is_some();
+    col = col.push(
+        widget::toggler(network)
+            .label("Configure Proxy and apt Mirror")
+            .on_toggle(|t| {
+                let c = config.clone();
+                let c = if t {
+                    c.update_http_proxy(Some(String::new()))
+                } else {
+                    c.update_http_proxy(None)
+                        .update_https_proxy(None)
+                        .update_apt_mirror(None)
+                };
+                BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(c))
+            }),
+    );
+    if network {
+        let invalid =
+            |x: &Option<String>| x.as_deref().is_some_and(|x| !bb_flasher::sd::valid_url(x));
+        let optional = |x: String| if x.is_empty() { None } else { Some(x) };
+
+        col = col.extend([
+            input_with_label(
+                "HTTP Proxy",
+                "http://10.0.0.1:3142",
+                config.http_proxy.as_deref().unwrap_or(""),
+                move |inp| {
+                    FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_http_proxy(Some(inp)),
+                    )
+                },
+                invalid(&config.http_proxy),
+            )
+            .into(),
+            input_with_label(
+                "HTTPS Proxy (optional)",
+                "http://10.0.0.1:3142",
+                config.https_proxy.as_deref().unwrap_or(""),
+                move |inp| {
+                    FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_https_proxy(optional(inp)),
+                    )
+                },
+                invalid(&config.https_proxy),
+            )
+            .into(),
+            input_with_label(
+                "apt Mirror (optional)",
+                "http://deb.debian.org/debian",
+                config.apt_mirror.as_deref().unwrap_or(""),
+                move |inp| {
+                    FlashingCustomization::LinuxSdSysconfig(
+                        config.clone().update_apt_mirror(optional(inp)),
+                    )
+                },
+                invalid(&config.apt_mirror),
+            )
+            .into(),
+        ]);
+    }
+
+    col = col.push(widget::rule::horizontal(2));
+
+    // Packages installed on first boot
+    col = col.extend([
+        text("Install packages on first boot (requires network)").into(),
+        widget::text_input(
+            "git, python3-numpy",
+            config.preinstall.as_deref().unwrap_or(""),
+        )
+        .on_input(|x| {
+            BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                config
+                    .clone()
+                    .update_preinstall(if x.is_empty() { None } else { Some(x) }),
+            ))
+        })
+        .style(move |theme, status| {
+            let mut t = widget::text_input::default(theme, status);
+            if !config.validate_preinstall() {
+                t.border = t.border.color(theme.palette().danger);
+            }
+            t
+        })
+        .into(),
+    ]);
+
+    col = col.push(widget::rule::horizontal(2));
+
+    // Files copied to the boot partition
+    col = col.push(text("Copy files to boot partition"));
+    for (i, file) in config.files.iter().enumerate() {
+        let update = move |files: Vec<persistance::SdBootFile>| {
+            BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdSysconfig(
+                config.clone().update_files(files),
+            ))
+        };
+        let mut removed = config.files.clone();
+        removed.remove(i);
+        let invalid = !file.validate();
+
+        col = col.push(
+            widget::row![
+                text(file.src.to_string_lossy()).width(iced::Fill),
+                text("to"),
+                widget::text_input("path in boot partition", &file.dest)
+                    .on_input(move |inp| {
+                        let mut files = config.files.clone();
+                        files[i] = file.clone().update_dest(inp);
+                        update(files)
+                    })
+                    .style(move |theme, status| {
+                        let mut t = widget::text_input::default(theme, status);
+                        if invalid {
+                            t.border = t.border.color(theme.palette().danger);
+                        }
+                        t
+                    })
+                    .width(INPUT_WIDTH),
+                widget::button("REMOVE")
+                    .style(widget::button::danger)
+                    .on_press(update(removed)),
+            ]
+            .spacing(8)
+            .align_y(iced::Center),
+        );
+    }
+    col = col.push(
+        widget::row![
+            widget::button("ADD FILE")
+                .style(widget::button::secondary)
+                .on_press(BBImagerMessage::AddBootFile(false)),
+            widget::button("ADD FOLDER")
+                .style(widget::button::secondary)
+                .on_press(BBImagerMessage::AddBootFile(true)),
+        ]
+        .spacing(8),
+    );
+
     widget::scrollable(col.spacing(16).padding(VIEW_COL_PADDING))
         .id(state.common.scroll_id.clone())
         .into()
 }
 
+/// Searchable list grouped by region. Shows all groups until a search query is entered, then
+/// matches ordered by relevance.
+fn picker<'a>(
+    groups: &'a [locale::Group],
+    query: &'a str,
+    selected: &'a str,
+    on_search: fn(String) -> BBImagerMessage,
+    on_select: impl Fn(&'static str) -> BBImagerMessage + 'a,
+) -> Element<'a, BBImagerMessage> {
+    let entry = |e: &'a locale::Entry, detail: Option<&'a str>| -> Element<'a, BBImagerMessage> {
+        let label = match detail {
+            Some(x) => format!("{} ({x})", e.label),
+            None => e.label.clone(),
+        };
+        widget::button(text(label))
+            .width(iced::Fill)
+            .style(if e.id == selected {
+                widget::button::primary
+            } else {
+                widget::button::text
+            })
+            .on_press(on_select(e.id))
+            .into()
+    };
+
+    let entry = &entry;
+    let list: Vec<Element<'a, BBImagerMessage>> = if query.trim().is_empty() {
+        groups
+            .iter()
+            .flat_map(move |g| {
+                let header: Element<'a, BBImagerMessage> =
+                    text(g.name).font(crate::constants::FONT_BOLD).into();
+                std::iter::once(header).chain(g.entries.iter().map(move |e| entry(e, None)))
+            })
+            .collect()
+    } else {
+        locale::search(groups, query)
+            .into_iter()
+            .map(|(g, e)| entry(e, Some(g.name)))
+            .collect()
+    };
+
+    widget::column![
+        widget::text_input("Search", query).on_input(on_search),
+        widget::scrollable(widget::column(list).spacing(2)).height(200),
+    ]
+    .spacing(8)
+    .padding(iced::Padding::ZERO.horizontal(16))
+    .into()
+}
+
 fn input_with_label<'a, F>(
     label: &'static str,
     placeholder: &'static str,
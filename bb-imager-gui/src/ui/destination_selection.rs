@@ -30,38 +30,91 @@ pub(crate) fn view<'a>(state: &'a ChooseDestState) -> Element<'a, BBImagerMessag
     )
 }
 
+/// Destinations are sorted by USB port, so identical card readers on the same hub are listed
+/// together.
+fn group_label(loc: &bb_flasher::sd::UsbLocation) -> String {
+    match loc.hub() {
+        Some(hub) => format!("USB Hub {hub}"),
+        None => format!("USB Bus {}", loc.bus),
+    }
+}
+
 fn dest_list_pane<'a>(state: &'a ChooseDestState) -> Element<'a, BBImagerMessage> {
-    let items = state
-        .destinations()
-        .map(|dest| {
-            let is_selected = state
-                .selected_dest
-                .as_ref()
-                .map(|x| dest.is_selected(x))
-                .unwrap_or(false);
-
-            let icon: Element<BBImagerMessage> = match dest {
-                DestinationItem::SaveToFile(_) => widget::svg(state.file_save_icon().clone()),
-                DestinationItem::Destination(_) => widget::svg(state.usb_svg().clone()),
+    let mut last_group = None;
+    let items = state.destinations().flat_map(move |dest| {
+        let group = dest.location().map(group_label);
+        let header: Option<Element<'a, BBImagerMessage>> = match &group {
+            Some(x) if last_group.as_ref() != Some(x) => Some(
+                widget::container(text(x.clone()).size(14).font(constants::FONT_BOLD))
+                    .padding(iced::Padding::ZERO.top(8).left(8))
+                    .into(),
+            ),
+            _ => None,
+        };
+        last_group = group;
+
+        let is_selected = state
+            .selected_dest
+            .as_ref()
+            .map(|x| dest.is_selected(x))
+            .unwrap_or(false);
+
+        let icon: Element<BBImagerMessage> = match dest {
+            DestinationItem::SaveToFile(_) | DestinationItem::ExportImage(_) => {
+                widget::svg(state.file_save_icon().clone())
             }
-            .height(ICON_WIDTH)
-            .width(ICON_WIDTH)
-            .style(svg_icon_style)
-            .into();
+            DestinationItem::Destination(_) => widget::svg(state.usb_svg().clone()),
+        }
+        .height(ICON_WIDTH)
+        .width(ICON_WIDTH)
+        .style(svg_icon_style)
+        .into();
 
-            let row = widget::row![
-                icon,
-                text(dest.to_string()).size(18).width(iced::Length::Fill)
-            ];
-            button(
-                row.spacing(12)
+        let label = widget::column![text(dest.to_string()).size(18)];
+        let label = match (dest.location(), dest.reader()) {
+            (Some(loc), Some(reader)) => {
+                label.push(text(format!("Port {loc} · {reader}")).size(14))
+            }
+            (Some(loc), None) => label.push(text(format!("Port {loc}")).size(14)),
+            _ => label,
+        };
+        #[cfg(feature = "bcf_cc1352p7")]
+        let label = match &dest {
+            DestinationItem::Destination(d) => {
+                label.extend(state.version_summary(d).map(|x| text(x).size(14).into()))
+            }
+            DestinationItem::SaveToFile(_) | DestinationItem::ExportImage(_) => label,
+        };
+
+        let disabled = dest.disabled_reason();
+        let label = match disabled {
+            Some(_) => label.push(text("Write Protected").size(14)),
+            None => label,
+        };
+
+        let row = widget::row![icon, label.width(iced::Length::Fill)];
+        let btn = button(
+            row.spacing(12)
+                .padding(8)
+                .align_y(iced::alignment::Vertical::Center),
+        )
+        .on_press_maybe(disabled.is_none().then(|| dest.msg()))
+        .style(move |theme, status| card_btn_style(theme, status, is_selected));
+
+        let item: Element<'a, BBImagerMessage> = match disabled {
+            Some(reason) => widget::tooltip(
+                btn,
+                widget::container(text(reason))
                     .padding(8)
-                    .align_y(iced::alignment::Vertical::Center),
+                    .style(widget::container::rounded_box),
+                widget::tooltip::Position::Bottom,
             )
-            .on_press(dest.msg())
-            .style(move |theme, status| card_btn_style(theme, status, is_selected))
-        })
-        .map(Into::into);
+            .into(),
+            None => btn.into(),
+        };
+
+        header.into_iter().chain([item])
+    });
 
     widget::scrollable(
         widget::column(
@@ -107,6 +160,45 @@ fn dest_view_pane<'a>(state: &'a crate::state::ChooseDestState) -> Element<'a, B
                     .map(|(k, v)| detail_entry(k, v))
                     .map(Into::into),
             );
+            #[cfg(feature = "bcf_cc1352p7")]
+            let col = col.extend(
+                state
+                    .version_details(dest)
+                    .into_iter()
+                    .map(|(k, v)| detail_entry(k, v))
+                    .map(Into::into),
+            );
+
+            #[cfg(feature = "bcf_cc1352p7")]
+            let col = if dest.supports_probe() {
+                col.push(
+                    widget::container(widget::tooltip(
+                        widget::button("READ VERSION")
+                            .on_press(BBImagerMessage::ProbeDest(dest.clone()))
+                            .style(widget::button::secondary),
+                        widget::container(text("Resets the device into bootloader"))
+                            .padding(8)
+                            .style(widget::container::rounded_box),
+                        widget::tooltip::Position::Bottom,
+                    ))
+                    .center_x(iced::Fill),
+                )
+            } else {
+                col
+            };
+
+            let col = if dest.supports_identify() {
+                col.push(
+                    widget::container(
+                        widget::button("IDENTIFY")
+                            .on_press(BBImagerMessage::IdentifyDest(dest.clone()))
+                            .style(widget::button::secondary),
+                    )
+                    .center_x(iced::Fill),
+                )
+            } else {
+                col
+            };
 
             widget::scrollable(col.spacing(16).padding(VIEW_COL_PADDING))
                 .id(state.common.scroll_id.clone())
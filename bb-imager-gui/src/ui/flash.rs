@@ -5,7 +5,7 @@ use iced::{
 
 use crate::{
     BBImagerMessage, constants,
-    state::FlashingState,
+    state::{FlashJob, FlashingState},
     ui::helpers::{self, ProgressCircle, VIEW_COL_PADDING, detail_entry, page_type1},
 };
 
@@ -14,30 +14,54 @@ pub(crate) fn view(state: &FlashingState) -> Element<'_, BBImagerMessage> {
         &state.common,
         info_view(state),
         progress_view(state),
-        [button("Cancel")
-            .style(widget::button::danger)
-            .on_press(BBImagerMessage::FlashCancel)],
+        [
+            button("FLASH ANOTHER")
+                .style(widget::button::secondary)
+                .on_press(BBImagerMessage::Restart),
+            button("Cancel")
+                .style(widget::button::danger)
+                .on_press(BBImagerMessage::FlashCancel(state.job)),
+        ],
     )
 }
 
 pub(crate) fn progress_view(state: &FlashingState) -> Element<'_, BBImagerMessage> {
-    let (prog, label) = match state.progress {
+    match state.job() {
+        Some(job) => job_progress_view(job),
+        None => widget::space::horizontal().into(),
+    }
+}
+
+/// Progress circle for a flashing job.
+pub(crate) fn job_progress_view(job: &FlashJob) -> Element<'_, BBImagerMessage> {
+    let (prog, label) = match job.progress {
         bb_flasher::DownloadFlashingStatus::Preparing => (0.0, "Preparing ..."),
+        bb_flasher::DownloadFlashingStatus::CheckingImage => (0.0, "Checking Image ..."),
         bb_flasher::DownloadFlashingStatus::DownloadingProgress(x) => (x, "Downloading ..."),
+        bb_flasher::DownloadFlashingStatus::DecompressingProgress(x) => (x, "Decompressing ..."),
         bb_flasher::DownloadFlashingStatus::FlashingProgress(x) => (x, "Flashing Image ..."),
         bb_flasher::DownloadFlashingStatus::Verifying => (0.99, "Verifying ..."),
-        bb_flasher::DownloadFlashingStatus::Customizing => (0.99, "Customizing ..."),
+        bb_flasher::DownloadFlashingStatus::Customizing(_) => (0.99, "Customizing ..."),
+        bb_flasher::DownloadFlashingStatus::BootTesting => (0.99, "Waiting for Boot ..."),
+        bb_flasher::DownloadFlashingStatus::BootVerified => (1.0, "Boot Verified"),
+        bb_flasher::DownloadFlashingStatus::BootFailed => (1.0, "Boot Failed"),
     };
 
     let progress = ProgressCircle::new(prog, 10.0, constants::TONGUE_ORANGE);
 
     let mut col = widget::column![progress, widget::text(label)];
-    if let Some(x) = state.time_remaining() {
+    if let bb_flasher::DownloadFlashingStatus::Customizing(x) = job.progress {
+        col = col.push(widget::text(x.to_string()));
+    }
+    if let Some(x) = job.time_remaining() {
         col = col.push(detail_entry(
             "Time Remaining",
             crate::helpers::pretty_duration(x),
         ));
     }
+    if let Some(x) = job.rate() {
+        col = col.push(detail_entry("Speed", x.to_string()));
+    }
 
     col.align_x(iced::Center).padding(VIEW_COL_PADDING).into()
 }
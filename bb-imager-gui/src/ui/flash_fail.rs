@@ -14,20 +14,41 @@ pub(crate) fn view(state: &FlashingFailState) -> Element<'_, BBImagerMessage> {
         &state.common,
         info_view(state),
         progress_view(state),
-        [button("Restart")
-            .style(widget::button::danger)
-            .on_press(BBImagerMessage::Restart)],
+        [
+            button("Copy Diagnostics")
+                .style(widget::button::secondary)
+                .on_press(BBImagerMessage::CopyToClipboard(state.diagnostics.clone())),
+            button("Retry").on_press(BBImagerMessage::FlashRetry),
+            button("Restart")
+                .style(widget::button::danger)
+                .on_press(BBImagerMessage::Restart),
+        ],
     )
 }
 
 pub(crate) fn progress_view(state: &FlashingFailState) -> Element<'_, BBImagerMessage> {
-    widget::column![
+    let steps = state
+        .failure
+        .remediation()
+        .iter()
+        .enumerate()
+        .map(|(i, x)| widget::text(format!("{}. {x}", i + 1)).into());
+
+    let col = widget::column![
         CircleBar::new("Failed", 10.0, constants::DANGER),
-        widget::text(&state.err)
+        widget::text(state.failure.title())
+            .size(20)
+            .font(constants::FONT_BOLD),
+        widget::text(&state.failure.message),
+        widget::rule::horizontal(2),
+        widget::text("How to fix").font(constants::FONT_BOLD),
+        widget::column(steps).spacing(4),
     ]
+    .spacing(8)
     .align_x(iced::Center)
-    .padding(VIEW_COL_PADDING)
-    .into()
+    .padding(VIEW_COL_PADDING);
+
+    widget::scrollable(col).into()
 }
 
 pub(crate) fn info_view(state: &FlashingFailState) -> Element<'_, BBImagerMessage> {
@@ -5,8 +5,8 @@ use iced::{
 
 use crate::{
     BBImagerMessage, constants,
-    state::FlashingFinishState,
-    ui::helpers::{CircleBar, VIEW_COL_PADDING, board_view_pane, page_type1},
+    state::{Discovery, FlashingFinishState},
+    ui::helpers::{CircleBar, VIEW_COL_PADDING, board_view_pane, copy_btn, page_type1},
 };
 
 pub(crate) fn view(state: &FlashingFinishState) -> Element<'_, BBImagerMessage> {
@@ -14,9 +14,16 @@ pub(crate) fn view(state: &FlashingFinishState) -> Element<'_, BBImagerMessage>
         &state.common,
         info_view(state),
         progress_view(state),
-        [button("Restart")
-            .style(widget::button::primary)
-            .on_press(BBImagerMessage::Restart)],
+        (!state.is_download)
+            .then(|| {
+                button("Connect over USB")
+                    .style(widget::button::secondary)
+                    .on_press(BBImagerMessage::UsbNetwork)
+            })
+            .into_iter()
+            .chain([button("Restart")
+                .style(widget::button::primary)
+                .on_press(BBImagerMessage::Restart)]),
     )
 }
 
@@ -27,13 +34,70 @@ pub(crate) fn progress_view(state: &FlashingFinishState) -> Element<'static, BBI
         "Successfully Flashed Image"
     };
 
-    widget::column![
+    let mut col = widget::column![
         CircleBar::new("100%", 10.0, constants::CHECK_MARK_GREEN),
         msg
-    ]
-    .align_x(iced::Center)
-    .padding(VIEW_COL_PADDING)
-    .into()
+    ];
+    if let Some(x) = &state.hostname {
+        col = col.push(widget::text(format!("Hostname: {x}")));
+    }
+    if let Some(x) = &state.qr_code {
+        col = col
+            .push(widget::svg(x.clone()).width(200).height(200))
+            .push(widget::text("Scan to get hostname, user and Wi-Fi settings").size(14));
+    }
+    if !state.is_download
+        && let Some(x) = discovery_view(state)
+    {
+        col = col.push(x);
+    }
+
+    col.align_x(iced::Center).padding(VIEW_COL_PADDING).into()
+}
+
+/// Find the board on the network once it boots, and show how to log in.
+fn discovery_view(state: &FlashingFinishState) -> Option<Element<'static, BBImagerMessage>> {
+    let (hostname, user) = state.login.as_ref()?;
+    let name = bb_helper::mdns::fqdn(hostname);
+
+    let res = match &state.discovery {
+        Discovery::Idle => button("Find My Board")
+            .style(widget::button::secondary)
+            .on_press(BBImagerMessage::FindBoard)
+            .into(),
+        Discovery::Searching => widget::text(format!("Waiting for {name} to come online ..."))
+            .size(14)
+            .into(),
+        Discovery::Found(addrs) => {
+            let addrs: Vec<String> = addrs.iter().map(ToString::to_string).collect();
+            let ssh = bb_helper::mdns::ssh_command(hostname, user.as_deref());
+
+            widget::column![
+                widget::text(format!("{name} is online at {}", addrs.join(", "))).size(14),
+                widget::row![
+                    widget::text(ssh.clone()).font(constants::FONT_BOLD),
+                    copy_btn(state.common.copy_svg_handle.clone())
+                        .on_press(BBImagerMessage::CopyToClipboard(ssh)),
+                ]
+                .spacing(8)
+                .align_y(iced::Center),
+            ]
+            .spacing(4)
+            .align_x(iced::Center)
+            .into()
+        }
+        Discovery::Failed(e) => widget::column![
+            widget::text(e.clone()).size(14),
+            button("Retry")
+                .style(widget::button::secondary)
+                .on_press(BBImagerMessage::FindBoard),
+        ]
+        .spacing(4)
+        .align_x(iced::Center)
+        .into(),
+    };
+
+    Some(res)
 }
 
 pub(crate) fn info_view(state: &FlashingFinishState) -> Element<'_, BBImagerMessage> {
@@ -52,23 +52,39 @@ pub(crate) fn page_type1<'a>(
     col2: Element<'a, BBImagerMessage>,
     btns: impl IntoIterator<Item = widget::Button<'a, BBImagerMessage>>,
 ) -> Element<'a, BBImagerMessage> {
-    let row2 = widget::row(
-        [
-            info_btn(common.info_svg_handle.clone()).into(),
-            widget::space::horizontal().into(),
-        ]
-        .into_iter()
-        .chain(btns.into_iter().map(Into::into)),
-    )
-    .align_y(iced::Center)
-    .width(iced::Length::Fill)
-    .spacing(24);
+    let row2: Element<'a, BBImagerMessage> =
+        if common.window_size.width < crate::constants::NARROW_WIDTH {
+            // Stack buttons vertically when there is not enough space
+            widget::row![
+                info_btn(common.info_svg_handle.clone()),
+                widget::space::horizontal(),
+                widget::column(btns.into_iter().map(Into::into))
+                    .spacing(8)
+                    .align_x(iced::alignment::Horizontal::Right),
+            ]
+            .align_y(iced::alignment::Vertical::Bottom)
+            .width(iced::Length::Fill)
+            .into()
+        } else {
+            widget::row(
+                [
+                    info_btn(common.info_svg_handle.clone()).into(),
+                    widget::space::horizontal().into(),
+                ]
+                .into_iter()
+                .chain(btns.into_iter().map(Into::into)),
+            )
+            .align_y(iced::Center)
+            .width(iced::Length::Fill)
+            .spacing(24)
+            .into()
+        };
 
     let col2 = widget::column![
         card_box(col2)
             .height(iced::Length::Fill)
             .width(iced::Length::Fill),
-        row2.width(iced::Length::Fill)
+        row2
     ]
     .spacing(24)
     .width(iced::FillPortion(1));
@@ -414,6 +430,27 @@ fn info_btn(handle: widget::svg::Handle) -> widget::Button<'static, BBImagerMess
         .height(iced::Shrink)
 }
 
+pub(crate) fn settings_btn(
+    handle: widget::svg::Handle,
+) -> widget::Button<'static, BBImagerMessage> {
+    widget::button(widget::svg(handle))
+        .on_press(BBImagerMessage::Settings)
+        .width(iced::Shrink)
+        .height(iced::Shrink)
+        .style(widget::button::secondary)
+}
+
+pub(crate) fn pin_btn<'a>(
+    handle: widget::svg::Handle,
+    msg: BBImagerMessage,
+) -> widget::Button<'a, BBImagerMessage> {
+    widget::button(widget::svg(handle))
+        .on_press(msg)
+        .width(iced::Shrink)
+        .height(iced::Shrink)
+        .style(widget::button::secondary)
+}
+
 pub(crate) fn copy_btn<'a>(handle: widget::svg::Handle) -> widget::Button<'a, BBImagerMessage> {
     widget::button(widget::svg(handle))
         .width(iced::Shrink)
@@ -0,0 +1,77 @@
+use iced::{
+    Element,
+    widget::{self, text},
+};
+
+use crate::{
+    constants,
+    message::BBImagerMessage,
+    state::HistoryState,
+    ui::helpers::{VIEW_COL_PADDING, page_type3},
+};
+
+const HEADING_SIZE: u32 = 26;
+
+pub(crate) fn view<'a>(state: &'a HistoryState) -> Element<'a, BBImagerMessage> {
+    page_type3(
+        history_view(state),
+        [widget::button("BACK")
+            .on_press(BBImagerMessage::Back)
+            .style(widget::button::secondary)],
+    )
+}
+
+fn history_view<'a>(state: &'a HistoryState) -> Element<'a, BBImagerMessage> {
+    let common = state.page.common();
+
+    let entries = state.entries.iter().rev().map(entry_view);
+
+    let col = widget::column![
+        text("Flash History")
+            .font(constants::FONT_BOLD)
+            .size(HEADING_SIZE),
+        widget::rule::horizontal(2),
+    ]
+    .push(if state.entries.is_empty() {
+        Element::from(text("No previous flashes recorded"))
+    } else {
+        widget::column(entries).spacing(16).into()
+    })
+    .spacing(16)
+    .padding(VIEW_COL_PADDING);
+
+    widget::scrollable(col).id(common.scroll_id.clone()).into()
+}
+
+fn entry_view(entry: &bb_helper::history::HistoryEntry) -> Element<'_, BBImagerMessage> {
+    let destination = match &entry.destination_name {
+        Some(name) => format!("{} ({})", name.trim(), entry.destination),
+        None => entry.destination.clone(),
+    };
+    let destination = match &entry.destination_serial {
+        Some(serial) => format!("{destination}, Serial: {serial}"),
+        None => destination,
+    };
+
+    widget::column![
+        text(entry.timestamp_utc()).font(constants::FONT_BOLD),
+        text(format!(
+            "{} | {}",
+            entry.board.as_deref().unwrap_or("Unknown Device"),
+            entry.image
+        )),
+        text(destination),
+        text(format!(
+            "Image SHA256: {}",
+            entry.image_sha256.as_deref().unwrap_or("N/A")
+        ))
+        .size(12),
+        text(format!(
+            "Customization SHA256: {}",
+            entry.customization_sha256.as_deref().unwrap_or("N/A")
+        ))
+        .size(12),
+    ]
+    .spacing(2)
+    .into()
+}
@@ -13,19 +13,31 @@ use crate::{
 };
 
 const ICON_WIDTH: u32 = 60;
+/// Indentation of items inside expanded groups
+const GROUP_INDENT: f32 = 24.0;
 
 pub(crate) fn view<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBImagerMessage> {
     page_type1(
         &state.common,
         os_list_pane(state),
         os_view_pane(state),
-        [
-            widget::button("BACK")
-                .on_press(BBImagerMessage::Back)
-                .style(widget::button::secondary),
-            widget::button("NEXT")
-                .on_press_maybe(state.selected_image().map(|_| BBImagerMessage::Next)),
-        ],
+        state
+            .selected_image_name()
+            .map(|x| {
+                let pinned = state.common.app_config.is_image_pinned(x);
+                helpers::pin_btn(
+                    state.star_svg(pinned).clone(),
+                    BBImagerMessage::TogglePinImage(x.to_string()),
+                )
+            })
+            .into_iter()
+            .chain([
+                widget::button("BACK")
+                    .on_press(BBImagerMessage::Back)
+                    .style(widget::button::secondary),
+                widget::button("NEXT")
+                    .on_press_maybe(state.selected_image().map(|_| BBImagerMessage::Next)),
+            ]),
     )
 }
 
@@ -48,6 +60,13 @@ fn os_list_pane<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBIma
                                 .style(svg_icon_style)
                                 .into()
                         }
+                        crate::helpers::OsImageId::Customize(_) => {
+                            widget::svg(state.settings_svg().clone())
+                                .height(ICON_WIDTH)
+                                .width(ICON_WIDTH)
+                                .style(svg_icon_style)
+                                .into()
+                        }
                         crate::helpers::OsImageId::Local(_) => {
                             widget::svg(state.file_add_svg().clone())
                                 .height(ICON_WIDTH)
@@ -70,11 +89,20 @@ fn os_list_pane<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBIma
                         }
                     };
 
-                    let row =
-                        widget::row![icon, text(img.label).size(18).width(iced::Length::Fill)];
-                    let row = if img.is_sublist {
+                    let label = match img.group {
+                        Some(g) => format!("{} ({})", img.label, g.count),
+                        None => img.label.to_string(),
+                    };
+                    let row = widget::row![icon, text(label).size(18).width(iced::Length::Fill)];
+                    let row = match &img.badge {
+                        Some(x) => {
+                            row.push(text(x.clone()).size(14).style(widget::text::secondary))
+                        }
+                        None => row,
+                    };
+                    let row = if state.is_image_pinned(&img) {
                         row.push(
-                            widget::svg(state.arrow_forward_svg().clone())
+                            widget::svg(state.star_svg(true).clone())
                                 .height(20)
                                 .width(iced::Shrink)
                                 .style(svg_icon_style),
@@ -82,14 +110,37 @@ fn os_list_pane<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBIma
                     } else {
                         row
                     };
+                    let row = match (img.is_sublist, img.group) {
+                        (_, Some(g)) => row.push(
+                            widget::svg(if g.expanded {
+                                state.expand_more_svg().clone()
+                            } else {
+                                state.arrow_forward_svg().clone()
+                            })
+                            .height(20)
+                            .width(iced::Shrink)
+                            .style(svg_icon_style),
+                        ),
+                        (true, None) => row.push(
+                            widget::svg(state.arrow_forward_svg().clone())
+                                .height(20)
+                                .width(iced::Shrink)
+                                .style(svg_icon_style),
+                        ),
+                        (false, None) => row,
+                    };
 
-                    button(
-                        row.spacing(12)
-                            .padding(8)
-                            .align_y(iced::alignment::Vertical::Center),
+                    let depth = img.depth as f32;
+                    widget::container(
+                        button(
+                            row.spacing(12)
+                                .padding(8)
+                                .align_y(iced::alignment::Vertical::Center),
+                        )
+                        .on_press(BBImagerMessage::SelectOs(img.id))
+                        .style(move |theme, status| card_btn_style(theme, status, is_selected)),
                     )
-                    .on_press(BBImagerMessage::SelectOs(img.id))
-                    .style(move |theme, status| card_btn_style(theme, status, is_selected))
+                    .padding(iced::Padding::ZERO.left(depth * GROUP_INDENT))
                 })
                 .map(Into::into);
 
@@ -118,13 +169,33 @@ fn os_list_pane<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBIma
                 .id(state.common.scroll_id.clone())
                 .into()
         }
-        None => widget::center(
-            iced_aw::Spinner::new()
-                .width(50)
-                .height(50)
-                .circle_radius(3.0),
-        )
-        .into(),
+        None => match state.subitems_error() {
+            Some(e) => widget::center(
+                widget::column![
+                    text("Failed to load images").size(18),
+                    text(e).style(widget::text::danger),
+                    widget::row![
+                        widget::button("BACK")
+                            .on_press(BBImagerMessage::GotoOsListParent)
+                            .style(widget::button::secondary),
+                        widget::button("RETRY")
+                            .on_press(BBImagerMessage::RetryRemoteSubitem(state.pos.clone())),
+                    ]
+                    .spacing(8),
+                ]
+                .spacing(16)
+                .padding(VIEW_COL_PADDING)
+                .align_x(iced::Center),
+            )
+            .into(),
+            None => widget::center(
+                iced_aw::Spinner::new()
+                    .width(50)
+                    .height(50)
+                    .circle_radius(3.0),
+            )
+            .into(),
+        },
     }
 }
 
@@ -148,6 +219,12 @@ fn os_view_pane<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBIma
                     .height(100)
                     .width(iced::Length::Fill)
                     .into(),
+                crate::helpers::BoardImageIcon::Customize => {
+                    widget::svg(state.settings_svg().clone())
+                        .height(100)
+                        .width(iced::Length::Fill)
+                        .into()
+                }
             };
 
             let mut col = widget::column![icon];
@@ -179,6 +256,16 @@ fn os_view_pane<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBIma
                 None => col,
             };
 
+            let col = match img.warning() {
+                Some(x) => col.push(
+                    text(x)
+                        .style(widget::text::danger)
+                        .align_x(iced::alignment::Alignment::Center)
+                        .width(iced::Length::Fill),
+                ),
+                None => col,
+            };
+
             let col = col.extend(
                 img.details()
                     .iter()
@@ -186,6 +273,33 @@ fn os_view_pane<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBIma
                     .map(Into::into),
             );
 
+            let col = match img.release_notes() {
+                Some(x) => col.push(
+                    widget::column![
+                        text("Release Notes").font(constants::FONT_BOLD).size(18),
+                        text(x)
+                    ]
+                    .spacing(8),
+                ),
+                None => col,
+            };
+
+            let col = match img.release_notes_url() {
+                Some(x) => col.push(widget::center(
+                    widget::button("RELEASE NOTES")
+                        .on_press(BBImagerMessage::OpenUrl(x.clone()))
+                        .style(widget::button::secondary),
+                )),
+                None => col,
+            };
+
+            // Checksum can be computed for local images to compare with published checksums
+            let col = if img.local_path().is_some() {
+                col.push(checksum_view(state))
+            } else {
+                col
+            };
+
             widget::scrollable(col.spacing(16).padding(VIEW_COL_PADDING))
                 .id(state.common.scroll_id.clone())
                 .into()
@@ -203,3 +317,40 @@ fn os_view_pane<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBIma
         }
     }
 }
+
+fn checksum_view<'a>(state: &'a crate::state::ChooseOsState) -> Element<'a, BBImagerMessage> {
+    use crate::state::LocalChecksum;
+
+    match &state.checksum {
+        None => widget::center(
+            widget::button("COMPUTE SHA256")
+                .on_press(BBImagerMessage::ComputeChecksum)
+                .style(widget::button::secondary),
+        )
+        .into(),
+        Some(LocalChecksum::Computing(x)) => widget::column![
+            text("Computing SHA256"),
+            widget::progress_bar(0.0..=1.0, *x).girth(8)
+        ]
+        .spacing(8)
+        .into(),
+        Some(LocalChecksum::Done(Ok(x))) => widget::row![
+            widget::container(detail_entry("SHA256", x)).width(iced::Length::Fill),
+            helpers::copy_btn(state.copy_svg().clone())
+                .on_press(BBImagerMessage::CopyToClipboard(x.clone())),
+        ]
+        .spacing(8)
+        .align_y(iced::alignment::Vertical::Center)
+        .into(),
+        Some(LocalChecksum::Done(Err(e))) => widget::column![
+            text(format!("Failed to compute SHA256: {e}")).style(widget::text::danger),
+            widget::center(
+                widget::button("RETRY")
+                    .on_press(BBImagerMessage::ComputeChecksum)
+                    .style(widget::button::secondary),
+            )
+        ]
+        .spacing(8)
+        .into(),
+    }
+}
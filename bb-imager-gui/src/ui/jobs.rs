@@ -0,0 +1,107 @@
+use iced::{
+    Element,
+    widget::{self, text},
+};
+
+use crate::{
+    constants,
+    message::BBImagerMessage,
+    state::{FlashJob, JobStatus, JobsState},
+    ui::helpers::{VIEW_COL_PADDING, page_type3},
+};
+
+const HEADING_SIZE: u32 = 26;
+
+pub(crate) fn view<'a>(state: &'a JobsState) -> Element<'a, BBImagerMessage> {
+    let has_finished = state.page.common().jobs.values().any(|x| !x.is_running());
+
+    page_type3(
+        jobs_view(state),
+        [
+            widget::button("CLEAR FINISHED")
+                .on_press_maybe(has_finished.then_some(BBImagerMessage::JobsClear))
+                .style(widget::button::secondary),
+            widget::button("BACK")
+                .on_press(BBImagerMessage::Back)
+                .style(widget::button::secondary),
+        ],
+    )
+}
+
+fn jobs_view<'a>(state: &'a JobsState) -> Element<'a, BBImagerMessage> {
+    let common = state.page.common();
+
+    let jobs = common.jobs.iter().map(|(id, job)| job_view(*id, job));
+
+    let col = widget::column![
+        text("Jobs").font(constants::FONT_BOLD).size(HEADING_SIZE),
+        widget::rule::horizontal(2),
+    ]
+    .push(if common.jobs.is_empty() {
+        Element::from(text("No jobs"))
+    } else {
+        widget::column(jobs).spacing(16).into()
+    })
+    .spacing(16)
+    .padding(VIEW_COL_PADDING);
+
+    widget::scrollable(col).id(common.scroll_id.clone()).into()
+}
+
+fn job_view(id: bb_flasher::JobId, job: &FlashJob) -> Element<'_, BBImagerMessage> {
+    let (prog, label) = match &job.status {
+        JobStatus::Running => match job.progress {
+            bb_flasher::DownloadFlashingStatus::Preparing => (0.0, "Preparing".to_string()),
+            bb_flasher::DownloadFlashingStatus::CheckingImage => {
+                (0.0, "Checking Image".to_string())
+            }
+            bb_flasher::DownloadFlashingStatus::DownloadingProgress(x) => {
+                (x, format!("Downloading {:.0}%", x * 100.0))
+            }
+            bb_flasher::DownloadFlashingStatus::DecompressingProgress(x) => {
+                (x, format!("Decompressing {:.0}%", x * 100.0))
+            }
+            bb_flasher::DownloadFlashingStatus::FlashingProgress(x) => {
+                (x, format!("Flashing {:.0}%", x * 100.0))
+            }
+            bb_flasher::DownloadFlashingStatus::Verifying => (0.99, "Verifying".to_string()),
+            bb_flasher::DownloadFlashingStatus::Customizing(x) => {
+                (0.99, format!("Customizing: {x}"))
+            }
+            bb_flasher::DownloadFlashingStatus::BootTesting => {
+                (0.99, "Waiting for Boot".to_string())
+            }
+            bb_flasher::DownloadFlashingStatus::BootVerified => (1.0, "Boot Verified".to_string()),
+            bb_flasher::DownloadFlashingStatus::BootFailed => (1.0, "Boot Failed".to_string()),
+        },
+        JobStatus::Success => (1.0, "Finished".to_string()),
+        JobStatus::Failed(e) => (1.0, format!("Failed: {e}")),
+        JobStatus::Cancelled => (0.0, "Cancelled".to_string()),
+    };
+
+    let mut row = widget::row![
+        widget::column![
+            text(format!("{} | {}", job.board, job.image)).font(constants::FONT_BOLD),
+            text(match &job.hostname {
+                Some(x) => format!("{} | Hostname: {x}", job.destination),
+                None => job.destination.clone(),
+            }),
+            widget::progress_bar(0.0..=1.0, prog).girth(8),
+            text(label).size(12),
+        ]
+        .spacing(4)
+        .width(iced::Fill)
+    ]
+    .spacing(16)
+    .align_y(iced::Center);
+
+    if job.is_running() {
+        row = row.push(
+            widget::button("CANCEL")
+                .on_press(BBImagerMessage::FlashCancel(id))
+                .style(widget::button::danger),
+        );
+    }
+
+    row.into()
+}
@@ -0,0 +1,117 @@
+use iced::{
+    Element,
+    widget::{self, button, text},
+};
+
+use crate::{
+    constants,
+    message::BBImagerMessage,
+    state::KioskState,
+    ui::helpers::{VIEW_COL_PADDING, page_type3},
+};
+
+const HEADING_SIZE: u32 = 32;
+const START_BTN_SIZE: u32 = 32;
+
+pub(crate) fn view(state: &KioskState) -> Element<'_, BBImagerMessage> {
+    let btns = match state.flash_job.filter(|_| state.is_flashing()) {
+        Some(job) => vec![
+            button(text("CANCEL").size(START_BTN_SIZE))
+                .padding([16, 48])
+                .style(widget::button::danger)
+                .on_press(BBImagerMessage::FlashCancel(job)),
+        ],
+        None => vec![
+            button(text("START").size(START_BTN_SIZE))
+                .padding([16, 48])
+                .on_press_maybe(state.destination().map(|_| BBImagerMessage::KioskFlash)),
+        ],
+    };
+
+    page_type3(kiosk_view(state), btns)
+}
+
+fn kiosk_view(state: &KioskState) -> Element<'_, BBImagerMessage> {
+    let Some((_, img)) = &state.selected else {
+        let image = state
+            .job
+            .image_path
+            .as_ref()
+            .map(|x| x.to_string_lossy())
+            .or(state.job.image.as_deref().map(Into::into))
+            .unwrap_or_default();
+
+        return widget::center(
+            text(format!(
+                "Waiting for {} | {image} in the catalog",
+                state.job.board
+            ))
+            .size(20)
+            .align_x(iced::Center),
+        )
+        .into();
+    };
+
+    let mut col = widget::column![
+        text(img.to_string())
+            .font(constants::FONT_BOLD)
+            .size(HEADING_SIZE),
+        text(
+            state
+                .selected_board()
+                .map(|x| x.name.as_str())
+                .unwrap_or_default()
+        )
+        .size(20),
+        text(format!("Flashed: {}", state.flashed)),
+        widget::rule::horizontal(2),
+    ]
+    .spacing(16)
+    .align_x(iced::Center);
+
+    if let Some(job) = state.job().filter(|x| x.is_running()) {
+        return col
+            .push(crate::ui::flash::job_progress_view(job))
+            .padding(VIEW_COL_PADDING)
+            .into();
+    }
+
+    col = match crate::helpers::static_destination(img.flasher()) {
+        Some(x) => col.push(text(x.to_string()).size(20)),
+        None => col.push(
+            widget::pick_list(
+                state.destinations.as_slice(),
+                state.selected_dest.as_ref(),
+                BBImagerMessage::SelectDest,
+            )
+            .placeholder("Insert Destination")
+            .text_size(20)
+            .padding(12)
+            .width(iced::Length::Fill),
+        ),
+    };
+
+    if let Some(x) = state
+        .destination()
+        .and_then(|x| state.job.destination_override(&x))
+        .and_then(|x| x.hostname.as_deref())
+    {
+        col = col.push(text(format!("Hostname: {x}")).size(20));
+    }
+
+    col = match &state.last_result {
+        Some(Ok(x)) => col.push(
+            text(format!("{x} flashed successfully. Insert the next one."))
+                .size(20)
+                .style(widget::text::success),
+        ),
+        Some(Err(e)) => col.push(
+            text(format!("Flashing failed: {e}"))
+                .size(20)
+                .style(widget::text::danger),
+        ),
+        None => col,
+    };
+
+    widget::scrollable(col.padding(VIEW_COL_PADDING)).into()
+}
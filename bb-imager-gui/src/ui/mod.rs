@@ -1,7 +1,10 @@
-use crate::{BBImager, message::BBImagerMessage};
+use iced::widget;
+
+use crate::{BBImager, constants, message::BBImagerMessage};
 
 mod app_info;
 mod board_selection;
+mod bundle;
 mod configuration;
 mod destination_selection;
 mod flash;
@@ -9,10 +12,94 @@ mod flash_cancel;
 mod flash_fail;
 mod flash_success;
 mod helpers;
+mod history;
 mod image_selection;
+mod jobs;
+mod kiosk;
+mod onboarding;
+mod palette;
+mod recovery;
 mod review;
+mod settings;
+mod usb_network;
 
 pub(crate) fn view(state: &BBImager) -> iced::Element<'_, BBImagerMessage> {
+    let page = match state.common().background_tasks {
+        0 => page(state),
+        n => background_activity(page(state), n),
+    };
+
+    let page = match state.common().toasts.front() {
+        Some((id, msg)) => toast(page, *id, msg, state.common().toasts.len()),
+        None => page,
+    };
+
+    match &state.common().palette {
+        Some(query) => palette::view(state, page, query),
+        None => page,
+    }
+}
+
+/// Spinner with the number of running background downloads, shown at the bottom left of `page`.
+fn background_activity(
+    page: iced::Element<'_, BBImagerMessage>,
+    count: usize,
+) -> iced::Element<'_, BBImagerMessage> {
+    let label = match count {
+        1 => "Downloading 1 catalog or icon".to_string(),
+        n => format!("Downloading {n} catalogs and icons"),
+    };
+
+    let indicator = widget::row![
+        iced_aw::Spinner::new()
+            .width(16)
+            .height(16)
+            .circle_radius(2.0),
+        widget::text(label).size(12),
+    ]
+    .spacing(8)
+    .align_y(iced::Center);
+
+    let overlay = widget::container(indicator)
+        .align_left(iced::Fill)
+        .align_bottom(iced::Fill)
+        .padding(8);
+
+    widget::stack![page, overlay].into()
+}
+
+/// Message shown at the bottom of `page`, along with the number of queued messages. Dismissed on
+/// click.
+fn toast<'a>(
+    page: iced::Element<'a, BBImagerMessage>,
+    id: u64,
+    msg: &'a str,
+    queued: usize,
+) -> iced::Element<'a, BBImagerMessage> {
+    let mut content = widget::row![widget::text(msg)].spacing(16);
+    if queued > 1 {
+        content = content.push(widget::text(format!("1/{queued}")));
+    }
+
+    let content = widget::button(content)
+        .padding(12)
+        .on_press(BBImagerMessage::DismissToast(id))
+        .style(|_, _| widget::button::Style {
+            background: Some(constants::CARD.into()),
+            text_color: iced::Color::WHITE,
+            border: iced::border::rounded(8),
+            ..Default::default()
+        });
+
+    let overlay = widget::container(content)
+        .center_x(iced::Fill)
+        .align_bottom(iced::Fill)
+        .padding(24);
+
+    widget::stack![page, overlay].into()
+}
+
+fn page(state: &BBImager) -> iced::Element<'_, BBImagerMessage> {
     match state {
         BBImager::ChooseBoard(inner) => board_selection::view(inner),
         BBImager::ChooseOs(inner) => image_selection::view(inner),
@@ -24,6 +111,14 @@ pub(crate) fn view(state: &BBImager) -> iced::Element<'_, BBImagerMessage> {
         BBImager::FlashingFail(inner) => flash_fail::view(inner),
         BBImager::FlashingSuccess(inner) => flash_success::view(inner),
         BBImager::AppInfo(inner) => app_info::view(inner),
+        BBImager::Settings(inner) => settings::view(inner),
+        BBImager::Jobs(inner) => jobs::view(inner),
+        BBImager::History(inner) => history::view(inner),
+        BBImager::UsbNetwork(inner) => usb_network::view(inner),
+        BBImager::Onboarding(inner) => onboarding::view(inner),
+        BBImager::Recovery(inner) => recovery::view(inner),
+        BBImager::Bundle(inner) => bundle::view(inner),
+        BBImager::Kiosk(inner) => kiosk::view(inner),
         _ => panic!("Unexpected message"),
     }
 }
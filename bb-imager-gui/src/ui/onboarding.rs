@@ -0,0 +1,70 @@
+use iced::{
+    Element,
+    widget::{self, text},
+};
+
+use crate::{
+    constants,
+    message::BBImagerMessage,
+    state::OnboardingState,
+    ui::helpers::{VIEW_COL_PADDING, page_type3},
+};
+
+const HEADING_SIZE: u32 = 26;
+
+pub(crate) fn view<'a>(state: &'a OnboardingState) -> Element<'a, BBImagerMessage> {
+    let next = if state.is_last_step() {
+        widget::button("GET STARTED").on_press(BBImagerMessage::OnboardingFinish)
+    } else {
+        widget::button("NEXT").on_press(BBImagerMessage::Next)
+    };
+
+    page_type3(
+        onboarding_view(state),
+        [
+            widget::button("SKIP")
+                .on_press(BBImagerMessage::OnboardingFinish)
+                .style(widget::button::secondary),
+            widget::button("BACK")
+                .on_press_maybe((state.step > 0).then_some(BBImagerMessage::Back))
+                .style(widget::button::secondary),
+            next,
+        ],
+    )
+}
+
+fn onboarding_view<'a>(state: &'a OnboardingState) -> Element<'a, BBImagerMessage> {
+    let (title, desc) = constants::ONBOARDING_STEPS[state.step];
+
+    let mut col = widget::column![
+        text(format!(
+            "Step {} of {}",
+            state.step + 1,
+            constants::ONBOARDING_STEPS.len()
+        ))
+        .style(widget::text::primary),
+        text(title).font(constants::FONT_BOLD).size(HEADING_SIZE),
+        widget::rule::horizontal(2),
+        text(desc),
+    ];
+
+    if state.is_last_step() {
+        col = col.push(
+            widget::button("OPEN DOCUMENTATION")
+                .on_press_with(|| {
+                    BBImagerMessage::OpenUrl(url::Url::parse(constants::DOCS_URL).unwrap())
+                })
+                .style(widget::button::secondary),
+        );
+    }
+
+    col = col.push(widget::space::vertical()).push(
+        widget::toggler(state.dont_show)
+            .label("Don't show this again")
+            .on_toggle(BBImagerMessage::OnboardingDontShow),
+    );
+
+    widget::container(col.spacing(16).padding(VIEW_COL_PADDING))
+        .height(iced::Fill)
+        .into()
+}
@@ -0,0 +1,55 @@
+use iced::{Element, widget};
+
+use crate::{BBImager, constants, message::BBImagerMessage, palette};
+
+/// Command palette shown on top of `page`, with a dimmed background which closes it on click.
+pub(crate) fn view<'a>(
+    state: &'a BBImager,
+    page: Element<'a, BBImagerMessage>,
+    query: &'a str,
+) -> Element<'a, BBImagerMessage> {
+    let actions = palette::search(state, query);
+
+    let search = widget::text_input("Type a command", query)
+        .id(state.common().palette_id.clone())
+        .on_input(BBImagerMessage::PaletteSearch)
+        .on_submit_maybe(actions.first().map(|x| BBImagerMessage::PaletteRun(*x)))
+        .padding(8);
+
+    let items = actions.into_iter().map(|x| {
+        widget::button(widget::text(x.label()))
+            .width(iced::Fill)
+            .style(widget::button::text)
+            .on_press(BBImagerMessage::PaletteRun(x))
+            .into()
+    });
+
+    let content = widget::container(
+        widget::column![search, widget::column(items).spacing(2)]
+            .spacing(8)
+            .padding(16),
+    )
+    .width(480)
+    .style(|_| {
+        widget::container::Style::default()
+            .background(constants::CARD)
+            .border(iced::border::rounded(8))
+    });
+
+    let overlay = widget::container(widget::opaque(content))
+        .center_x(iced::Fill)
+        .padding(iced::Padding::ZERO.top(96))
+        .height(iced::Fill)
+        .style(|_| {
+            widget::container::Style::default().background(iced::Color {
+                a: 0.6,
+                ..iced::Color::BLACK
+            })
+        });
+
+    widget::stack![
+        page,
+        widget::opaque(widget::mouse_area(overlay).on_press(BBImagerMessage::ClosePalette))
+    ]
+    .into()
+}
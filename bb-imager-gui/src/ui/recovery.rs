@@ -0,0 +1,82 @@
+use iced::{
+    Element,
+    widget::{self, text},
+};
+
+use crate::{
+    constants,
+    message::BBImagerMessage,
+    state::RecoveryState,
+    ui::helpers::{VIEW_COL_PADDING, page_type3},
+};
+
+const HEADING_SIZE: u32 = 26;
+
+pub(crate) fn view<'a>(state: &'a RecoveryState) -> Element<'a, BBImagerMessage> {
+    page_type3(
+        recovery_view(state),
+        [
+            widget::button("DISMISS")
+                .on_press(BBImagerMessage::RecoveryDismiss)
+                .style(widget::button::secondary),
+            widget::button("RE-VERIFY")
+                .on_press_maybe(
+                    (state.can_verify() && !state.verifying)
+                        .then_some(BBImagerMessage::RecoveryVerify),
+                )
+                .style(widget::button::secondary),
+            widget::button("FLASH AGAIN").on_press(BBImagerMessage::RecoveryRestart),
+        ],
+    )
+}
+
+fn recovery_view<'a>(state: &'a RecoveryState) -> Element<'a, BBImagerMessage> {
+    let mut grid = widget::grid![
+        text("Device"),
+        text(&state.marker.board),
+        text("Operating System"),
+        text(&state.marker.image),
+        text("Storage"),
+        text(&state.marker.destination),
+        text("Progress"),
+        text(state.progress()),
+    ];
+
+    if let Some(x) = &state.marker.image_sha256 {
+        grid = grid.push(text("Image SHA256")).push(text(x));
+    }
+
+    let mut col = widget::column![
+        text("Interrupted Flashing")
+            .font(constants::FONT_BOLD)
+            .size(HEADING_SIZE),
+        text(
+            "The previous flashing did not finish. The contents of the destination are \
+             incomplete and it should be flashed again before use."
+        )
+        .style(widget::text::primary),
+        widget::rule::horizontal(2),
+        grid.height(iced::Length::Shrink).spacing(8).columns(2),
+    ];
+
+    if !state.pending.is_empty() {
+        col = col.push(text(format!(
+            "{} more interrupted flashing(s) will be shown once this one is dismissed.",
+            state.pending.len()
+        )));
+    }
+
+    col = match (&state.verified, state.verifying) {
+        (_, true) => col.push(text("Comparing storage with the image...")),
+        (Some(Ok(())), false) => col.push(
+            text("Storage matches the image. The image was written completely.")
+                .style(widget::text::success),
+        ),
+        (Some(Err(e)), false) => col.push(text(e).style(widget::text::danger)),
+        (None, false) => col,
+    };
+
+    widget::scrollable(col.spacing(16).padding(VIEW_COL_PADDING))
+        .id(state.common.scroll_id.clone())
+        .into()
+}
@@ -54,6 +54,10 @@ fn review_view<'a>(state: &'a CustomizeState) -> Element<'a, BBImagerMessage> {
         .columns(2),
     ];
 
+    if let Some(x) = state.image_warning() {
+        col = col.push(text(x).style(widget::text::danger));
+    }
+
     let modifications = state.modifications();
     if !modifications.is_empty() {
         col = col.extend([
@@ -66,9 +70,73 @@ fn review_view<'a>(state: &'a CustomizeState) -> Element<'a, BBImagerMessage> {
                 .spacing(8)
                 .into(),
         ]);
+
+        if crate::helpers::skip_customization(&state.customization, &state.common.app_config) {
+            col = col.push(
+                text("Saved customization applied. Go BACK to change it for this flash.")
+                    .style(widget::text::secondary),
+            );
+        }
+    }
+
+    if state.can_inspect() {
+        col = col.extend([
+            widget::rule::horizontal(2).into(),
+            text("Image Details")
+                .font(constants::FONT_BOLD)
+                .size(HEADING_SIZE)
+                .into(),
+            image_info_view(state.image_info.as_ref()),
+        ]);
     }
 
     widget::scrollable(col.spacing(16).padding(VIEW_COL_PADDING))
         .id(state.common.scroll_id.clone())
         .into()
 }
+
+fn image_info_view<'a>(
+    info: Option<&'a Result<bb_flasher::sd::ImageInfo, String>>,
+) -> Element<'a, BBImagerMessage> {
+    match info {
+        None => widget::button("INSPECT")
+            .on_press(BBImagerMessage::InspectImage)
+            .style(widget::button::secondary)
+            .into(),
+        Some(Err(e)) => text(e).style(widget::text::danger).into(),
+        Some(Ok(info)) => {
+            let summary = widget::grid![
+                text("Partition Table"),
+                text(info.partition_table.to_string()),
+                text("Distro"),
+                text(info.distro.as_deref().unwrap_or("Unknown")),
+            ]
+            .height(iced::Length::Shrink)
+            .spacing(8)
+            .columns(2);
+
+            let header: [Element<'a, BBImagerMessage>; 5] =
+                ["#", "Type", "Size", "Filesystem", "Label"]
+                    .map(|x| text(x).font(constants::FONT_BOLD).into());
+            let partitions = info.partitions.iter().flat_map(|p| {
+                [
+                    text(p.number).into(),
+                    text(&p.kind).into(),
+                    text(bb_flasher::ByteSize::new(p.size).to_string()).into(),
+                    text(p.filesystem.map(|x| x.to_string()).unwrap_or_default()).into(),
+                    text(p.label.as_deref().unwrap_or_default()).into(),
+                ]
+            });
+
+            widget::column![
+                summary,
+                widget::Grid::with_children(header.into_iter().chain(partitions))
+                    .height(iced::Length::Shrink)
+                    .spacing(8)
+                    .columns(5)
+            ]
+            .spacing(16)
+            .into()
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use iced::{Element, widget};
+
+use crate::{
+    message::BBImagerMessage,
+    persistance::{AppTheme, SdVerify},
+    state::SettingsState,
+    ui::helpers::{VIEW_COL_PADDING, element_with_label, page_type3},
+};
+
+const INP_BOX_WIDTH: u32 = 420;
+const HEADING_SIZE: u32 = 26;
+
+pub(crate) fn view<'a>(state: &'a SettingsState) -> Element<'a, BBImagerMessage> {
+    let back = widget::button("BACK")
+        .on_press(BBImagerMessage::Back)
+        .style(widget::button::secondary);
+
+    if state.common().is_locked() {
+        return page_type3(
+            locked_view(state),
+            [
+                back,
+                widget::button("UNLOCK").on_press_maybe(
+                    (state.app_config().has_admin_pin() && !state.pin.is_empty())
+                        .then_some(BBImagerMessage::SettingsUnlock),
+                ),
+            ],
+        );
+    }
+
+    page_type3(
+        settings_view(state),
+        [
+            back,
+            widget::button("SAVE").on_press(BBImagerMessage::SettingsSave),
+        ],
+    )
+}
+
+fn locked_view<'a>(state: &'a SettingsState) -> Element<'a, BBImagerMessage> {
+    let mut col = widget::column![
+        widget::text("Settings Locked")
+            .font(crate::constants::FONT_BOLD)
+            .size(HEADING_SIZE),
+        widget::rule::horizontal(2),
+        widget::text("Settings and customization have been locked by the administrator."),
+    ];
+
+    col = if state.app_config().has_admin_pin() {
+        let mut col = col.push(element_with_label(
+            "Admin PIN",
+            widget::text_input("PIN", &state.pin)
+                .secure(true)
+                .width(INP_BOX_WIDTH)
+                .on_input(BBImagerMessage::SettingsPin)
+                .on_submit(BBImagerMessage::SettingsUnlock)
+                .into(),
+        ));
+        if state.pin_error {
+            col = col.push(widget::text("Incorrect PIN").style(widget::text::danger));
+        }
+        col
+    } else {
+        col.push(widget::text(
+            "Remove the lock from the configuration file to change settings.",
+        ))
+    };
+
+    widget::scrollable(col.spacing(16).padding(VIEW_COL_PADDING)).into()
+}
+
+fn settings_view<'a>(state: &'a SettingsState) -> Element<'a, BBImagerMessage> {
+    let config = state.app_config();
+
+    let col = widget::column![
+        widget::text("Settings")
+            .font(crate::constants::FONT_BOLD)
+            .size(HEADING_SIZE),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "Theme",
+            widget::pick_list(
+                AppTheme::ALL,
+                Some(config.theme()),
+                BBImagerMessage::SettingsTheme
+            )
+            .into()
+        ),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "Cache Directory",
+            widget::row![
+                widget::text_input(&state.cache_dir, &state.cache_dir)
+                    .width(INP_BOX_WIDTH)
+                    .on_input(|_| BBImagerMessage::Null),
+                widget::button("CHANGE")
+                    .on_press(BBImagerMessage::SelectCacheDir)
+                    .style(widget::button::secondary)
+            ]
+            .spacing(8)
+            .into()
+        ),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "Scratch Directory",
+            widget::row![
+                widget::text_input(&state.scratch_dir, &state.scratch_dir)
+                    .width(INP_BOX_WIDTH)
+                    .on_input(|_| BBImagerMessage::Null),
+                widget::button("CHANGE")
+                    .on_press(BBImagerMessage::SelectScratchDir)
+                    .style(widget::button::secondary),
+                widget::button("RESET")
+                    .on_press_maybe(
+                        config
+                            .scratch_dir()
+                            .map(|_| BBImagerMessage::UpdateScratchDir(None))
+                    )
+                    .style(widget::button::secondary)
+            ]
+            .spacing(8)
+            .into()
+        ),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "Image Checksum Cache",
+            widget::button("CLEAR")
+                .on_press(BBImagerMessage::ClearChecksumCache)
+                .style(widget::button::secondary)
+                .into()
+        ),
+        element_with_label(
+            "Board and Image Icons",
+            widget::button("REFRESH")
+                .on_press(BBImagerMessage::RefreshIcons)
+                .style(widget::button::secondary)
+                .into()
+        ),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "Proxy",
+            widget::text_input("http://proxy.example.com:8080", &state.proxy)
+                .width(INP_BOX_WIDTH)
+                .on_input(BBImagerMessage::SettingsProxy)
+                .on_submit(BBImagerMessage::SettingsSave)
+                .into()
+        ),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "CA Certificates",
+            widget::text_input("/path/to/ca-bundle.pem", &state.ca_certs)
+                .width(INP_BOX_WIDTH)
+                .on_input(BBImagerMessage::SettingsCaCerts)
+                .on_submit(BBImagerMessage::SettingsSave)
+                .into()
+        ),
+        widget::container(
+            widget::toggler(state.insecure_tls)
+                .label("Skip TLS certificate verification (insecure, closed networks only)")
+                .on_toggle(BBImagerMessage::SettingsInsecureTls)
+        )
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "Additional Catalog URL",
+            widget::text_input("https://example.com/config.json", &state.catalog_url)
+                .width(INP_BOX_WIDTH)
+                .on_input(BBImagerMessage::SettingsCatalogUrl)
+                .on_submit(BBImagerMessage::SettingsSave)
+                .into()
+        ),
+        element_with_label(
+            "Import Board Folder",
+            widget::button("IMPORT")
+                .on_press(BBImagerMessage::ImportBoard)
+                .style(widget::button::secondary)
+                .into()
+        ),
+        element_with_label(
+            "Import Catalog File",
+            widget::button("IMPORT")
+                .on_press(BBImagerMessage::ImportCatalog)
+                .style(widget::button::secondary)
+                .into()
+        ),
+        widget::column(state.catalog_diagnostics.iter().map(|x| {
+            let style = match x.severity {
+                bb_config::validate::Severity::Error => widget::text::danger,
+                bb_config::validate::Severity::Warning => widget::text::secondary,
+            };
+            widget::text(x.to_string()).size(14).style(style).into()
+        }))
+        .spacing(4)
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "Download Limit (KiB/s)",
+            widget::text_input("Unlimited", &state.download_limit)
+                .width(INP_BOX_WIDTH)
+                .on_input(BBImagerMessage::SettingsDownloadLimit)
+                .on_submit(BBImagerMessage::SettingsSave)
+                .into()
+        ),
+        widget::rule::horizontal(2),
+        element_with_label(
+            "SD Card Write Limit (MiB/s)",
+            widget::text_input("Unlimited", &state.write_limit)
+                .width(INP_BOX_WIDTH)
+                .on_input(BBImagerMessage::SettingsWriteLimit)
+                .on_submit(BBImagerMessage::SettingsSave)
+                .into()
+        ),
+        element_with_label(
+            "SD Card Verification",
+            widget::pick_list(
+                SdVerify::ALL,
+                Some(config.sd_verify()),
+                BBImagerMessage::SettingsSdVerify
+            )
+            .into()
+        ),
+        widget::rule::horizontal(2),
+        widget::container(
+            widget::toggler(config.cache_before_flash())
+                .label("Download images completely before flashing")
+                .on_toggle(BBImagerMessage::SettingsCacheBeforeFlash)
+        )
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+        widget::container(
+            widget::toggler(config.apply_saved_customization())
+                .label("Always apply saved SD Card customization")
+                .on_toggle(BBImagerMessage::SettingsApplySavedCustomization)
+        )
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+        widget::container(
+            widget::toggler(config.unit_system() == bb_flasher::UnitSystem::Decimal)
+                .label("Show sizes in decimal units (GB instead of GiB)")
+                .on_toggle(BBImagerMessage::SettingsDecimalUnits)
+        )
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+        widget::container(
+            widget::toggler(config.telemetry())
+                .label("Send anonymous usage statistics")
+                .on_toggle(BBImagerMessage::SettingsTelemetry)
+        )
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+        widget::container(
+            widget::toggler(config.notifications())
+                .label("Use system notifications")
+                .on_toggle(BBImagerMessage::SettingsNotifications)
+        )
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+        widget::container(
+            widget::toggler(config.history())
+                .label("Record flashes in history")
+                .on_toggle(BBImagerMessage::SettingsHistory)
+        )
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+    ]
+    .spacing(8)
+    .padding(VIEW_COL_PADDING)
+    .width(iced::Fill)
+    .align_x(iced::Center);
+
+    #[cfg(feature = "demo")]
+    let col = col.push(developer_view(state));
+
+    widget::scrollable(col)
+        .id(state.common().scroll_id.clone())
+        .into()
+}
+
+/// Hidden developer menu. Only present in builds with `demo` feature.
+#[cfg(feature = "demo")]
+fn developer_view<'a>(state: &'a SettingsState) -> Element<'a, BBImagerMessage> {
+    let mock = state.common().mock_flasher;
+
+    widget::column![
+        widget::rule::horizontal(2),
+        widget::text("Developer")
+            .font(crate::constants::FONT_BOLD)
+            .size(HEADING_SIZE),
+        widget::container(
+            widget::toggler(mock.enabled)
+                .label("Use mock flasher")
+                .on_toggle(move |enabled| BBImagerMessage::MockFlasher(
+                    crate::helpers::MockFlasherConfig { enabled, ..mock }
+                ))
+        )
+        .padding(iced::Padding::ZERO.horizontal(16))
+        .width(iced::Fill),
+        element_with_label(
+            "Stage Duration (s)",
+            widget::row![
+                widget::slider(1..=60, mock.stage_secs, move |stage_secs| {
+                    BBImagerMessage::MockFlasher(crate::helpers::MockFlasherConfig {
+                        stage_secs,
+                        ..mock
+                    })
+                })
+                .width(INP_BOX_WIDTH - 40),
+                widget::text(mock.stage_secs)
+            ]
+            .spacing(8)
+            .into()
+        ),
+        element_with_label(
+            "Fail At",
+            widget::row![
+                widget::pick_list(bb_flasher::mock::Stage::ALL, mock.fail_at, move |x| {
+                    BBImagerMessage::MockFlasher(crate::helpers::MockFlasherConfig {
+                        fail_at: Some(x),
+                        ..mock
+                    })
+                })
+                .placeholder("Never"),
+                widget::button("CLEAR")
+                    .on_press(BBImagerMessage::MockFlasher(
+                        crate::helpers::MockFlasherConfig {
+                            fail_at: None,
+                            ..mock
+                        }
+                    ))
+                    .style(widget::button::secondary)
+            ]
+            .spacing(8)
+            .into()
+        ),
+    ]
+    .spacing(8)
+    .width(iced::Fill)
+    .align_x(iced::Center)
+    .into()
+}
@@ -0,0 +1,93 @@
+use iced::{
+    Element,
+    widget::{self, text},
+};
+
+use crate::{
+    constants,
+    message::BBImagerMessage,
+    state::UsbNetworkState,
+    ui::helpers::{VIEW_COL_PADDING, copy_btn, page_type3},
+};
+
+const HEADING_SIZE: u32 = 26;
+
+pub(crate) fn view(state: &UsbNetworkState) -> Element<'_, BBImagerMessage> {
+    let mut btns = Vec::new();
+
+    if let Some((link, services)) = state.status {
+        if services.ide {
+            btns.push(
+                widget::button("OPEN WEB IDE")
+                    .on_press(open(link.url(bb_helper::usb_network::IDE_PORT)))
+                    .style(widget::button::primary),
+            );
+        }
+        if services.http {
+            btns.push(
+                widget::button("OPEN WEB PAGE")
+                    .on_press(open(link.url(bb_helper::usb_network::HTTP_PORT)))
+                    .style(widget::button::secondary),
+            );
+        }
+    }
+
+    btns.push(
+        widget::button("BACK")
+            .on_press(BBImagerMessage::Back)
+            .style(widget::button::secondary),
+    );
+
+    page_type3(status_view(state), btns)
+}
+
+fn open(url: String) -> BBImagerMessage {
+    BBImagerMessage::OpenUrl(url::Url::parse(&url).expect("Invalid board URL"))
+}
+
+fn status_view(state: &UsbNetworkState) -> Element<'_, BBImagerMessage> {
+    let common = state.page.common();
+
+    let mut col = widget::column![
+        text("Connect over USB")
+            .font(constants::FONT_BOLD)
+            .size(HEADING_SIZE),
+        widget::rule::horizontal(2),
+    ]
+    .spacing(16)
+    .padding(VIEW_COL_PADDING);
+
+    col = match state.status {
+        None => col.push(text("Waiting for the board ...")).push(text(
+            "Connect the board to this computer using a USB cable. Once booted, the board \
+            provides a network connection over USB and can be reached at 192.168.7.2 \
+            (192.168.6.2 on macOS). First boot can take a few minutes.",
+        )),
+        Some((link, services)) if !services.any() => col
+            .push(text(format!("Board connected at {}", link.board)))
+            .push(text("Waiting for services on the board to start ...")),
+        Some((link, services)) => {
+            col = col.push(text(format!("Board connected at {}", link.board)));
+
+            if services.ssh {
+                let ssh = match state.user() {
+                    Some(u) => format!("ssh {u}@{}", link.board),
+                    None => format!("ssh {}", link.board),
+                };
+                col = col.push(
+                    widget::row![
+                        text(ssh.clone()).font(constants::FONT_BOLD),
+                        copy_btn(common.copy_svg_handle.clone())
+                            .on_press(BBImagerMessage::CopyToClipboard(ssh)),
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Center),
+                );
+            }
+
+            col
+        }
+    };
+
+    widget::scrollable(col).id(common.scroll_id.clone()).into()
+}
@@ -22,7 +22,7 @@ async fn latest_version(downloader: bb_downloader::Downloader) -> io::Result<Ver
     semver::Version::parse(ver).map_err(|e| io::Error::other(e.to_string()))
 }
 
-fn current_version() -> Version {
+pub(crate) fn current_version() -> Version {
     semver::Version::parse(env!("CARGO_PKG_VERSION")).unwrap()
 }
 